@@ -0,0 +1,110 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Typed events the plugins publish for the other plugins, in Rust, without going through the webview.
+//!
+//! The channels are the types of the events: the subscribers of `NetworkStatus` receive every `NetworkStatus`
+//! published by the network-status plugin. The plugins document the types they publish.
+
+use tauri::{Manager, Runtime, State};
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+type Handler<T> = Arc<dyn Fn(&T) + Send + Sync>;
+
+/// Returned by [`Bus::subscribe`], to unsubscribe.
+pub struct Subscription<T> {
+    id: u64,
+    event: PhantomData<fn(&T)>,
+}
+
+struct Channel<T> {
+    handlers: Vec<(u64, Handler<T>)>,
+}
+
+#[derive(Default)]
+pub struct Bus {
+    /// The `Channel<T>` of each type of event.
+    channels: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    next_id: AtomicU64,
+}
+
+impl Bus {
+    /// Calls the subscribers of the type of the event, on the current thread.
+    pub fn publish<T: Send + Sync + 'static>(&self, event: T) {
+        let handlers: Vec<Handler<T>> = match self.channels.lock().unwrap().get(&TypeId::of::<T>())
+        {
+            Some(channel) => channel
+                .downcast_ref::<Channel<T>>()
+                .unwrap()
+                .handlers
+                .iter()
+                .map(|(_, handler)| handler.clone())
+                .collect(),
+            None => return,
+        };
+        // not holding the lock, the handlers may publish or subscribe themselves
+        for handler in handlers {
+            handler(&event);
+        }
+    }
+
+    /// Calls `handler` with every `T` published from now on, on the thread publishing it.
+    pub fn subscribe<T: Send + Sync + 'static, F: Fn(&T) + Send + Sync + 'static>(
+        &self,
+        handler: F,
+    ) -> Subscription<T> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.channels
+            .lock()
+            .unwrap()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                Box::new(Channel::<T> {
+                    handlers: Vec::new(),
+                })
+            })
+            .downcast_mut::<Channel<T>>()
+            .unwrap()
+            .handlers
+            .push((id, Arc::new(handler)));
+        Subscription {
+            id,
+            event: PhantomData,
+        }
+    }
+
+    pub fn unsubscribe<T: Send + Sync + 'static>(&self, subscription: Subscription<T>) {
+        if let Some(channel) = self.channels.lock().unwrap().get_mut(&TypeId::of::<T>()) {
+            channel
+                .downcast_mut::<Channel<T>>()
+                .unwrap()
+                .handlers
+                .retain(|(id, _)| *id != subscription.id);
+        }
+    }
+}
+
+pub trait BusExt<R: Runtime> {
+    /// The bus of the app, created on first use so the plugins don't depend on their registration order.
+    fn bus(&self) -> State<'_, Bus>;
+}
+
+impl<R: Runtime, T: Manager<R>> BusExt<R> for T {
+    fn bus(&self) -> State<'_, Bus> {
+        if self.try_state::<Bus>().is_none() {
+            // a concurrent first use managed it already otherwise
+            self.manage(Bus::default());
+        }
+        self.state::<Bus>()
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Reads the `plugins > <name>` sections of `tauri.conf.json` into typed structs, with errors pointing at the invalid value.
+
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+use tauri::{AppHandle, Runtime};
+
+/// The config of a plugin, its fields are the schema of the section.
+///
+/// Add `#[serde(deny_unknown_fields)]` to catch the misspelled keys.
+pub trait PluginConfig: DeserializeOwned + Default {
+    /// Checks what the types can't express, e.g. ranges. Errors with the path of the value in the section,
+    /// e.g. `preload[0]`, and the reason.
+    fn validate(&self) -> Result<(), (String, String)> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid `plugins > {plugin}{}` in tauri.conf.json: {message}", display_path(.path))]
+pub struct ConfigError {
+    pub plugin: String,
+    /// The path of the invalid value in the section, e.g. `preload[0]`, empty for the section itself.
+    pub path: String,
+    pub message: String,
+}
+
+fn display_path(path: &str) -> String {
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!(" > {}", path)
+    }
+}
+
+/// Parses the section of the plugin, a missing section is the default config.
+pub fn parse<C: PluginConfig>(plugin: &str, config: JsonValue) -> Result<C, ConfigError> {
+    let error = |path: String, message: String| ConfigError {
+        plugin: plugin.into(),
+        path,
+        message,
+    };
+
+    if config.is_null() {
+        return Ok(C::default());
+    }
+    let config: C = serde_path_to_error::deserialize(config).map_err(|e| {
+        let path = e.path().to_string();
+        // the root is `.`
+        let path = if path == "." { String::new() } else { path };
+        error(path, e.into_inner().to_string())
+    })?;
+    config
+        .validate()
+        .map_err(|(path, message)| error(path, message))?;
+    Ok(config)
+}
+
+/// Parses the section of the plugin in the config of the app, for the plugins built with `tauri::plugin::Builder`
+/// which don't receive it.
+pub fn load<C: PluginConfig, R: Runtime>(
+    app: &AppHandle<R>,
+    plugin: &str,
+) -> Result<C, ConfigError> {
+    let config = app
+        .config()
+        .plugins
+        .0
+        .get(plugin)
+        .cloned()
+        .unwrap_or_default();
+    parse(plugin, config)
+}
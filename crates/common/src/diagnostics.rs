@@ -0,0 +1,108 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The counters and the health the plugins report, collected on demand by the diagnostics plugin.
+
+use serde::Serialize;
+use tauri::{Manager, Runtime, State};
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+type Reporter = Arc<dyn Fn() -> Report + Send + Sync>;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Health {
+    #[default]
+    Ok,
+    /// Working, with failures, e.g. a connection retrying.
+    Degraded,
+    Failing,
+}
+
+/// What a plugin reports about itself.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub health: Health,
+    /// Counters and gauges, e.g. `connections`.
+    pub metrics: BTreeMap<String, f64>,
+    /// Why the plugin isn't healthy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl Report {
+    pub fn new(health: Health) -> Self {
+        Self {
+            health,
+            ..Default::default()
+        }
+    }
+
+    pub fn metric(mut self, name: impl Into<String>, value: impl Into<f64>) -> Self {
+        self.metrics.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message.replace(message.into());
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct Diagnostics {
+    reporters: Mutex<BTreeMap<String, Reporter>>,
+}
+
+impl Diagnostics {
+    /// Registers the reporter of the plugin, replacing the previous one. It's called on every report, keep it cheap.
+    pub fn register<F: Fn() -> Report + Send + Sync + 'static>(
+        &self,
+        plugin: impl Into<String>,
+        reporter: F,
+    ) {
+        self.reporters
+            .lock()
+            .unwrap()
+            .insert(plugin.into(), Arc::new(reporter));
+    }
+
+    pub fn unregister(&self, plugin: &str) {
+        self.reporters.lock().unwrap().remove(plugin);
+    }
+
+    /// The reports of the plugins, by name.
+    pub fn report(&self) -> BTreeMap<String, Report> {
+        let reporters: Vec<(String, Reporter)> = self
+            .reporters
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(plugin, reporter)| (plugin.clone(), reporter.clone()))
+            .collect();
+        reporters
+            .into_iter()
+            .map(|(plugin, reporter)| (plugin, reporter()))
+            .collect()
+    }
+}
+
+pub trait DiagnosticsExt<R: Runtime> {
+    /// The registry of the app, created on first use so the plugins don't depend on their registration order.
+    fn diagnostics(&self) -> State<'_, Diagnostics>;
+}
+
+impl<R: Runtime, T: Manager<R>> DiagnosticsExt<R> for T {
+    fn diagnostics(&self) -> State<'_, Diagnostics> {
+        if self.try_state::<Diagnostics>().is_none() {
+            self.manage(Diagnostics::default());
+        }
+        self.state::<Diagnostics>()
+    }
+}
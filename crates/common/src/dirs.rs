@@ -0,0 +1,173 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The directories the plugins keep their state in, resolved once for the app from the `plugins > dirs`
+//! section of `tauri.conf.json` instead of each plugin asking the `PathResolver`.
+//!
+//! ```json
+//! { "plugins": { "dirs": { "portable": true, "profile": "work" } } }
+//! ```
+
+use serde::Deserialize;
+use tauri::{api::path, utils::platform::current_exe, Config, Manager, Runtime, State};
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::{config, PluginConfig};
+
+/// Enables the portable mode when it's next to the executable, e.g. for the builds run from a USB stick.
+pub const PORTABLE_MARKER: &str = ".portable";
+
+/// The `plugins > dirs` section of `tauri.conf.json`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DirsConfig {
+    /// Keeps the state in the `data`, `config` and `logs` directories next to the executable.
+    /// Also enabled by a [`PORTABLE_MARKER`] file there.
+    #[serde(default)]
+    pub portable: bool,
+    /// The subdirectory of each directory, e.g. to keep the state of several accounts apart.
+    pub profile: Option<String>,
+}
+
+impl PluginConfig for DirsConfig {
+    fn validate(&self) -> Result<(), (String, String)> {
+        match &self.profile {
+            Some(profile) if !is_single_component(profile) => Err((
+                "profile".into(),
+                "expected a directory name, without separators".into(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn is_single_component(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    )
+}
+
+/// The resolved directories, `None` when the platform has no such directory.
+#[derive(Debug, Clone, Default)]
+pub struct StateDirs {
+    data: Option<PathBuf>,
+    config: Option<PathBuf>,
+    log: Option<PathBuf>,
+}
+
+impl StateDirs {
+    /// Resolves the directories of the app. Manage the result before the plugins use it to configure them
+    /// from Rust: `tauri::Builder::default().manage(StateDirs::resolve(context.config(), &dirs))`.
+    ///
+    /// Outside of the portable mode `XDG_DATA_HOME`, `XDG_CONFIG_HOME` and `XDG_STATE_HOME` override the
+    /// directories of the platform on Linux and the BSDs, the logs go to the state directory.
+    pub fn resolve(app_config: &Config, config: &DirsConfig) -> Self {
+        let identifier = &app_config.tauri.bundle.identifier;
+        let portable = portable_dir(config);
+
+        let resolve = |portable_name: &str, xdg_var: &str, xdg_name: Option<&str>, default| {
+            let dir = match &portable {
+                Some(dir) => Some(dir.join(portable_name)),
+                None => xdg(xdg_var)
+                    .map(|dir| match xdg_name {
+                        Some(name) => dir.join(identifier).join(name),
+                        None => dir.join(identifier),
+                    })
+                    .or(default),
+            };
+            match &config.profile {
+                Some(profile) => dir.map(|dir| dir.join(profile)),
+                None => dir,
+            }
+        };
+
+        Self {
+            data: resolve(
+                "data",
+                "XDG_DATA_HOME",
+                None,
+                path::app_data_dir(app_config),
+            ),
+            config: resolve(
+                "config",
+                "XDG_CONFIG_HOME",
+                None,
+                path::app_config_dir(app_config),
+            ),
+            log: resolve(
+                "logs",
+                "XDG_STATE_HOME",
+                Some("logs"),
+                path::app_log_dir(app_config),
+            ),
+        }
+    }
+
+    /// Replaces `PathResolver::app_data_dir`.
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        self.data.clone()
+    }
+
+    /// Replaces `PathResolver::app_config_dir`.
+    pub fn config_dir(&self) -> Option<PathBuf> {
+        self.config.clone()
+    }
+
+    /// Replaces `PathResolver::app_log_dir`.
+    pub fn log_dir(&self) -> Option<PathBuf> {
+        self.log.clone()
+    }
+}
+
+/// The directory of the executable in the portable mode.
+fn portable_dir(config: &DirsConfig) -> Option<PathBuf> {
+    let dir = current_exe().ok()?.parent()?.to_path_buf();
+    if config.portable || dir.join(PORTABLE_MARKER).exists() {
+        Some(dir)
+    } else {
+        None
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn xdg(var: &str) -> Option<PathBuf> {
+    // the relative paths are invalid per the specification
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+}
+
+#[cfg(any(not(unix), target_os = "macos"))]
+fn xdg(_var: &str) -> Option<PathBuf> {
+    None
+}
+
+pub trait StateDirsExt<R: Runtime> {
+    /// The directories of the app, resolved on first use so the plugins don't depend on their registration order.
+    fn state_dirs(&self) -> State<'_, StateDirs>;
+}
+
+impl<R: Runtime, T: Manager<R>> StateDirsExt<R> for T {
+    fn state_dirs(&self) -> State<'_, StateDirs> {
+        if self.try_state::<StateDirs>().is_none() {
+            let app_config = self.config();
+            let section = app_config
+                .plugins
+                .0
+                .get("dirs")
+                .cloned()
+                .unwrap_or_default();
+            let config = config::parse("dirs", section).unwrap_or_else(|e| {
+                log::error!("{}, the default directories are used", e);
+                DirsConfig::default()
+            });
+            // a concurrent first use managed it already otherwise
+            self.manage(StateDirs::resolve(&app_config, &config));
+        }
+        self.state::<StateDirs>()
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The payload the commands of every plugin reject with, so the frontends match on codes instead of messages.
+
+use serde::{Deserialize, Serialize, Serializer};
+
+/// What the frontend receives when a command fails.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS))]
+pub struct ErrorPayload {
+    /// Identifies the failure within the plugin, e.g. `not_found`. The codes are stable, the messages aren't.
+    pub code: String,
+    /// The human readable description of the failure.
+    pub message: String,
+    /// The name of the plugin, e.g. `store`.
+    pub plugin: String,
+    /// Whether retrying the same call may succeed, e.g. after a network failure.
+    pub retryable: bool,
+    /// What the OS reported, e.g. `os error 13`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, ts(optional))]
+    pub detail: Option<String>,
+}
+
+/// The errors of the plugins, serialized with [`serialize`].
+pub trait PluginError: std::error::Error {
+    /// The name of the plugin.
+    const PLUGIN: &'static str;
+
+    /// The snake case code of the failure.
+    fn code(&self) -> &'static str;
+
+    fn retryable(&self) -> bool {
+        false
+    }
+
+    fn detail(&self) -> Option<String> {
+        None
+    }
+
+    fn payload(&self) -> ErrorPayload {
+        ErrorPayload {
+            code: self.code().into(),
+            message: self.to_string(),
+            plugin: Self::PLUGIN.into(),
+            retryable: self.retryable(),
+            detail: self.detail(),
+        }
+    }
+}
+
+/// For the `Serialize` implementation of the errors.
+pub fn serialize<E: PluginError, S: Serializer>(
+    error: &E,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    error.payload().serialize(serializer)
+}
+
+/// The detail of an IO error, the code the OS returned.
+pub fn io_detail(error: &std::io::Error) -> Option<String> {
+    error
+        .raw_os_error()
+        .map(|code| format!("os error {}", code))
+}
+
+/// Whether the IO operation may succeed when retried.
+pub fn io_retryable(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        error.kind(),
+        ErrorKind::Interrupted | ErrorKind::TimedOut | ErrorKind::WouldBlock
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use ts_rs::TS;
+
+    /// Every plugin rejects with the payload, so it's exported to the bindings of each of them.
+    #[test]
+    fn export_bindings_error_payload() {
+        let plugins = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../plugins");
+        for plugin in std::fs::read_dir(plugins).unwrap() {
+            let guest_js = plugin.unwrap().path().join("guest-js");
+            if guest_js.is_dir() {
+                ErrorPayload::export_to(guest_js.join("types/ErrorPayload.ts")).unwrap();
+            }
+        }
+    }
+}
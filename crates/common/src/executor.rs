@@ -0,0 +1,249 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! A bounded pool of threads the plugins submit their heavy work to, by priority, instead of spawning
+//! a thread or a blocking task per call.
+
+use tauri::{
+    async_runtime::{channel, Receiver},
+    Manager, Runtime, State,
+};
+
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::BinaryHeap,
+    future::Future,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread::{available_parallelism, Builder as ThreadBuilder},
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The queued tasks of a higher priority start first, the tasks of the same priority in the order they were submitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// E.g. the work prefetching what may be used later.
+    Low,
+    #[default]
+    Normal,
+    /// E.g. the work the user is waiting for.
+    High,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum TaskError {
+    #[error("the task was cancelled")]
+    Cancelled,
+    #[error("the task panicked")]
+    Panicked,
+}
+
+/// Cancels a task, the queued tasks don't start and the running ones check [`CancellationToken::is_cancelled`].
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// A submitted task, dropping it doesn't cancel it.
+pub struct Task<T> {
+    token: CancellationToken,
+    rx: Receiver<Result<T, TaskError>>,
+}
+
+impl<T> Task<T> {
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// The token of the task, e.g. to cancel it from a command.
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub async fn join(mut self) -> Result<T, TaskError> {
+        // the job is dropped without sending when it's cancelled before it starts
+        self.rx.recv().await.unwrap_or(Err(TaskError::Cancelled))
+    }
+
+    /// [`Task::join`] for the threads outside of the async runtime.
+    pub fn join_blocking(mut self) -> Result<T, TaskError> {
+        self.rx.blocking_recv().unwrap_or(Err(TaskError::Cancelled))
+    }
+}
+
+struct Queued {
+    priority: Priority,
+    sequence: u64,
+    token: CancellationToken,
+    job: Job,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    // the heap pops the greatest, the highest priority and then the lowest sequence
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: BinaryHeap<Queued>,
+    /// The workers started, at most `threads`.
+    workers: usize,
+    /// The workers waiting for a job.
+    idle: usize,
+}
+
+struct Inner {
+    threads: usize,
+    queue: Mutex<Queue>,
+    available: Condvar,
+    sequence: AtomicU64,
+}
+
+/// The pool, shared by the plugins through [`ExecutorExt::executor`]. The threads are started on demand.
+#[derive(Clone)]
+pub struct Executor(Arc<Inner>);
+
+impl Default for Executor {
+    /// As many threads as the CPU runs in parallel, at least 2.
+    fn default() -> Self {
+        Self::new(available_parallelism().map_or(2, |threads| threads.get().max(2)))
+    }
+}
+
+impl Executor {
+    /// Manage it before the plugins use it to change the number of threads:
+    /// `tauri::Builder::default().manage(Executor::new(8))`.
+    pub fn new(threads: usize) -> Self {
+        Self(Arc::new(Inner {
+            threads: threads.max(1),
+            queue: Default::default(),
+            available: Condvar::new(),
+            sequence: AtomicU64::new(0),
+        }))
+    }
+
+    /// Queues blocking work, given the token of the task to stop early when it's cancelled.
+    pub fn spawn<T, F>(&self, priority: Priority, f: F) -> Task<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&CancellationToken) -> T + Send + 'static,
+    {
+        let token = CancellationToken::default();
+        let (tx, rx) = channel(1);
+        let token_ = token.clone();
+        let job = Box::new(move || {
+            let result = catch_unwind(AssertUnwindSafe(|| f(&token_))).map_err(|_| {
+                log::error!("a task of the executor panicked");
+                TaskError::Panicked
+            });
+            let _ = tx.blocking_send(result);
+        });
+        self.push(Queued {
+            priority,
+            sequence: self.0.sequence.fetch_add(1, Ordering::Relaxed),
+            token: token.clone(),
+            job,
+        });
+        Task { token, rx }
+    }
+
+    /// Queues async work, e.g. a transfer, it holds a thread of the pool until it completes.
+    pub fn spawn_async<T, F, Fut>(&self, priority: Priority, f: F) -> Task<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = T>,
+    {
+        self.spawn(priority, move |token| {
+            tauri::async_runtime::block_on(f(token.clone()))
+        })
+    }
+
+    /// The tasks waiting for a thread.
+    pub fn queued(&self) -> usize {
+        self.0.queue.lock().unwrap().jobs.len()
+    }
+
+    fn push(&self, queued: Queued) {
+        let mut queue = self.0.queue.lock().unwrap();
+        queue.jobs.push(queued);
+        // the idle workers may not have woken up for the jobs queued before yet
+        if queue.jobs.len() > queue.idle && queue.workers < self.0.threads {
+            let inner = self.0.clone();
+            let spawned = ThreadBuilder::new()
+                .name("tauri-plugin-executor".into())
+                .spawn(move || work(&inner));
+            match spawned {
+                Ok(_) => queue.workers += 1,
+                Err(e) => log::error!("failed to start a thread of the executor: {}", e),
+            }
+        }
+        self.0.available.notify_one();
+    }
+}
+
+fn work(inner: &Inner) {
+    loop {
+        let queued = {
+            let mut queue = inner.queue.lock().unwrap();
+            loop {
+                if let Some(queued) = queue.jobs.pop() {
+                    break queued;
+                }
+                queue.idle += 1;
+                queue = inner.available.wait(queue).unwrap();
+                queue.idle -= 1;
+            }
+        };
+        // dropping the job closes the channel of the task, which resolves as cancelled
+        if !queued.token.is_cancelled() {
+            (queued.job)();
+        }
+    }
+}
+
+pub trait ExecutorExt<R: Runtime> {
+    /// The executor of the app, created on first use so the plugins don't depend on their registration order.
+    fn executor(&self) -> State<'_, Executor>;
+}
+
+impl<R: Runtime, T: Manager<R>> ExecutorExt<R> for T {
+    fn executor(&self) -> State<'_, Executor> {
+        if self.try_state::<Executor>().is_none() {
+            // a concurrent first use managed it already otherwise
+            self.manage(Executor::default());
+        }
+        self.state::<Executor>()
+    }
+}
@@ -0,0 +1,23 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The pieces shared by the plugins of the workspace.
+
+pub mod bus;
+pub mod config;
+pub mod diagnostics;
+pub mod dirs;
+pub mod error;
+pub mod executor;
+pub mod network;
+pub mod shutdown;
+
+pub use bus::{Bus, BusExt};
+pub use config::PluginConfig;
+pub use diagnostics::{DiagnosticsExt, Health, Report};
+pub use dirs::{StateDirs, StateDirsExt};
+pub use error::{ErrorPayload, PluginError};
+pub use executor::{Executor, ExecutorExt};
+pub use network::{NetworkScope, NetworkScopeExt};
+pub use shutdown::{Shutdown, ShutdownExt};
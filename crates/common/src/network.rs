@@ -0,0 +1,199 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The hosts the plugins connecting to the network may reach, configured in the `scope` of their section of
+//! `tauri.conf.json` and adjustable from Rust with [`NetworkScopeExt::network_scope`].
+
+use glob::{MatchOptions, Pattern};
+use serde::Deserialize;
+use tauri::{Manager, Runtime, State};
+use url::Url;
+
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt,
+    sync::{Arc, Mutex, RwLock},
+};
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScopeError {
+    #[error("invalid network scope rule `{0}`: {1}")]
+    InvalidRule(String, String),
+    #[error("invalid URL `{0}`: {1}")]
+    InvalidUrl(String, url::ParseError),
+    #[error("`{0}` isn't allowed by the network scope")]
+    NotAllowed(String),
+}
+
+/// `scheme://host:port`, each part a glob, e.g. `wss://*.example.com` or `http://localhost:*`.
+///
+/// Without a port only the default port of the scheme matches.
+#[derive(Clone)]
+pub struct Rule {
+    rule: String,
+    scheme: Pattern,
+    host: Pattern,
+    port: Option<Pattern>,
+}
+
+impl Rule {
+    pub fn matches(&self, url: &Url) -> bool {
+        let port = match (&self.port, url.port()) {
+            (None, None) => true,
+            (Some(pattern), port) => port
+                .or_else(|| url.port_or_known_default())
+                .is_some_and(|port| pattern.matches(&port.to_string())),
+            (None, Some(_)) => false,
+        };
+        port && self.scheme.matches_with(url.scheme(), MATCH_OPTIONS)
+            && url
+                .host_str()
+                .is_some_and(|host| self.host.matches_with(host, MATCH_OPTIONS))
+    }
+}
+
+impl fmt::Debug for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Rule").field(&self.rule).finish()
+    }
+}
+
+impl TryFrom<String> for Rule {
+    type Error = ScopeError;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        let error = |message: &str| ScopeError::InvalidRule(rule.clone(), message.into());
+        let pattern = |part: &str| Pattern::new(part).map_err(|e| error(e.msg));
+
+        let (scheme, authority) = rule
+            .split_once("://")
+            .ok_or_else(|| error("expected `scheme://host`"))?;
+        if authority.contains('/') {
+            return Err(error("paths aren't supported, only `scheme://host:port`"));
+        }
+        // the brackets of the IPv6 addresses are part of the host
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.ends_with(']') => (host, Some(port)),
+            _ => (authority, None),
+        };
+        if scheme.is_empty() || host.is_empty() || port.is_some_and(str::is_empty) {
+            return Err(error("expected `scheme://host:port`"));
+        }
+
+        // `[` starts a character class in the globs
+        let host = if host.starts_with('[') {
+            pattern(&Pattern::escape(host))?
+        } else {
+            pattern(host)?
+        };
+
+        Ok(Self {
+            scheme: pattern(scheme)?,
+            host,
+            port: port.map(pattern).transpose()?,
+            rule,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::try_from(String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The `scope` of the section of a plugin.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ScopeConfig {
+    /// Everything is allowed when it's missing, nothing when it's empty.
+    pub allow: Option<Vec<Rule>>,
+    /// Overrides `allow`.
+    #[serde(default)]
+    pub deny: Vec<Rule>,
+}
+
+/// The hosts a plugin may reach.
+#[derive(Debug, Default)]
+pub struct NetworkScope {
+    config: RwLock<ScopeConfig>,
+}
+
+impl NetworkScope {
+    /// Replaces the rules, the plugins call it with their config when they're initialized.
+    pub fn set_config(&self, config: ScopeConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Allows the URLs matching the rule, only them when nothing was allowed explicitly before.
+    pub fn allow(&self, rule: impl Into<String>) -> Result<(), ScopeError> {
+        let rule = Rule::try_from(rule.into())?;
+        self.config
+            .write()
+            .unwrap()
+            .allow
+            .get_or_insert_with(Vec::new)
+            .push(rule);
+        Ok(())
+    }
+
+    pub fn deny(&self, rule: impl Into<String>) -> Result<(), ScopeError> {
+        let rule = Rule::try_from(rule.into())?;
+        self.config.write().unwrap().deny.push(rule);
+        Ok(())
+    }
+
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let config = self.config.read().unwrap();
+        let allowed = match &config.allow {
+            Some(allow) => allow.iter().any(|rule| rule.matches(url)),
+            None => true,
+        };
+        allowed && !config.deny.iter().any(|rule| rule.matches(url))
+    }
+
+    /// Parses the URL and errors when it isn't allowed.
+    pub fn check(&self, url: &str) -> Result<Url, ScopeError> {
+        let parsed = Url::parse(url).map_err(|e| ScopeError::InvalidUrl(url.into(), e))?;
+        if self.is_allowed(&parsed) {
+            Ok(parsed)
+        } else {
+            Err(ScopeError::NotAllowed(url.into()))
+        }
+    }
+}
+
+/// The scopes of the plugins, by plugin name.
+#[derive(Default)]
+struct NetworkScopes(Mutex<HashMap<String, Arc<NetworkScope>>>);
+
+pub trait NetworkScopeExt<R: Runtime> {
+    /// The scope of the plugin, e.g. `websocket`, everything is allowed until it's configured.
+    fn network_scope(&self, plugin: &str) -> Arc<NetworkScope>;
+}
+
+impl<R: Runtime, T: Manager<R>> NetworkScopeExt<R> for T {
+    fn network_scope(&self, plugin: &str) -> Arc<NetworkScope> {
+        if self.try_state::<NetworkScopes>().is_none() {
+            // a concurrent first use managed it already otherwise
+            self.manage(NetworkScopes::default());
+        }
+        let scopes: State<'_, NetworkScopes> = self.state();
+        let scope = scopes
+            .0
+            .lock()
+            .unwrap()
+            .entry(plugin.into())
+            .or_default()
+            .clone();
+        scope
+    }
+}
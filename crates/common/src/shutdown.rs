@@ -0,0 +1,174 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Runs the teardown of the plugins when the app exits, stage by stage and within a timeout, so quitting
+//! quickly doesn't lose data.
+//!
+//! The plugins register their hooks with [`Shutdown::register`] and pass their events to [`on_event`]:
+//! the `Save` hooks run when the exit is requested, the app may still prevent it, and all the hooks run once on the exit.
+
+use tauri::{AppHandle, Manager, RunEvent, Runtime, State};
+
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc, Mutex,
+    },
+    thread::spawn,
+    time::Duration,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Hook = dyn Fn() + Send + Sync;
+
+/// The hooks run stage by stage, then in the order they were registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// Closes the connections, so nothing changes the data while it's saved, e.g. the websockets.
+    Disconnect,
+    /// Saves the data, e.g. the stores. Also run when the exit is requested, the hooks must be repeatable.
+    Save,
+    /// Releases what the saves may still use, e.g. the database pools.
+    Release,
+}
+
+pub struct Shutdown {
+    hooks: Mutex<Vec<(Stage, String, Arc<Hook>)>>,
+    timeout: Mutex<Duration>,
+    done: AtomicBool,
+    /// The plugin whose events run the hooks, the first one passing its events to [`on_event`].
+    driver: Mutex<Option<String>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            hooks: Default::default(),
+            timeout: Mutex::new(DEFAULT_TIMEOUT),
+            done: Default::default(),
+            driver: Default::default(),
+        }
+    }
+}
+
+impl Shutdown {
+    /// Registers a hook of the plugin. It runs on another thread while the main thread waits, it must not
+    /// wait for the main thread, e.g. by querying the windows.
+    pub fn register<F: Fn() + Send + Sync + 'static>(&self, plugin: &str, stage: Stage, hook: F) {
+        self.hooks
+            .lock()
+            .unwrap()
+            .push((stage, plugin.into(), Arc::new(hook)));
+    }
+
+    /// How long the exit waits for the hooks, 5 seconds by default. What's still running is abandoned.
+    pub fn set_timeout(&self, timeout: Duration) {
+        *self.timeout.lock().unwrap() = timeout;
+    }
+
+    /// Runs the `Save` hooks, returns whether they finished in time.
+    pub fn save(&self) -> bool {
+        if self.done.load(Ordering::SeqCst) {
+            return true;
+        }
+        let hooks = self
+            .hooks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(stage, _, _)| *stage == Stage::Save)
+            .map(|(_, plugin, hook)| (plugin.clone(), hook.clone()))
+            .collect();
+        self.run_hooks(hooks)
+    }
+
+    /// Runs all the hooks once, returns whether they finished in time.
+    ///
+    /// Call it before `AppHandle::exit`, which exits without the events.
+    pub fn run(&self) -> bool {
+        if self.done.swap(true, Ordering::SeqCst) {
+            return true;
+        }
+        let mut hooks = std::mem::take(&mut *self.hooks.lock().unwrap());
+        // stable, the registration order is kept within the stages
+        hooks.sort_by_key(|(stage, _, _)| *stage);
+        self.run_hooks(
+            hooks
+                .into_iter()
+                .map(|(_, plugin, hook)| (plugin, hook))
+                .collect(),
+        )
+    }
+
+    fn run_hooks(&self, hooks: Vec<(String, Arc<Hook>)>) -> bool {
+        if hooks.is_empty() {
+            return true;
+        }
+        let timeout = *self.timeout.lock().unwrap();
+        let current = Arc::new(Mutex::new(String::new()));
+        let current_ = current.clone();
+        let (tx, rx) = channel();
+        spawn(move || {
+            for (plugin, hook) in hooks {
+                *current_.lock().unwrap() = plugin.clone();
+                // the other plugins still get to save
+                if catch_unwind(AssertUnwindSafe(|| hook())).is_err() {
+                    log::error!("the shutdown hook of `{}` panicked", plugin);
+                }
+            }
+            let _ = tx.send(());
+        });
+
+        let finished = rx.recv_timeout(timeout).is_ok();
+        if !finished {
+            log::warn!(
+                "the shutdown timed out after {:?} in the hook of `{}`",
+                timeout,
+                current.lock().unwrap()
+            );
+        }
+        finished
+    }
+}
+
+/// For the `on_event` of the plugins registering hooks, only the events of the first plugin calling it run them.
+pub fn on_event<R: Runtime>(app: &AppHandle<R>, plugin: &str, event: &RunEvent) {
+    let shutdown = app.shutdown();
+    if shutdown
+        .driver
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| plugin.into())
+        != plugin
+    {
+        return;
+    }
+    match event {
+        RunEvent::ExitRequested { .. } => {
+            shutdown.save();
+        }
+        RunEvent::Exit => {
+            shutdown.run();
+        }
+        _ => (),
+    }
+}
+
+pub trait ShutdownExt<R: Runtime> {
+    /// The hooks of the app, created on first use so the plugins don't depend on their registration order.
+    fn shutdown(&self) -> State<'_, Shutdown>;
+}
+
+impl<R: Runtime, T: Manager<R>> ShutdownExt<R> for T {
+    fn shutdown(&self) -> State<'_, Shutdown> {
+        if self.try_state::<Shutdown>().is_none() {
+            // a concurrent first use managed it already otherwise
+            self.manage(Shutdown::default());
+        }
+        self.state::<Shutdown>()
+    }
+}
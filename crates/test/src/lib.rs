@@ -0,0 +1,293 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Runs the plugins on a mock runtime, without windows or a webview, to test them in CI.
+//!
+//! [`MockApp`] builds the app, invokes the commands like the webview would and captures the events emitted to the windows.
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use tauri::{
+    api::ipc::CallbackFn,
+    utils::{
+        assets::{AssetKey, Assets, CspHash},
+        config::{Config, WindowConfig},
+    },
+    App, Builder, Context, InvokePayload, Manager, PackageInfo, Pattern, Window,
+};
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Sender},
+        Arc, Mutex,
+    },
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+mod mock_runtime;
+pub use mock_runtime::*;
+/// The events of the runtime, for [`MockApp::window_event`].
+pub use tauri_runtime::window::WindowEvent;
+
+/// The label of the window of [`mock_config`].
+pub const MAIN_WINDOW: &str = "main";
+/// How long [`MockApp::invoke`] waits for the response of a command.
+const INVOKE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Starts the object passed to the event listeners of the windows, see `Window::emit_internal` in tauri.
+const EMIT_MARKER: &str = "fn && fn({event: ";
+
+type Response = Result<JsonValue, JsonValue>;
+
+/// The assets of the mock apps, there are none.
+pub struct NoopAssets;
+
+impl Assets for NoopAssets {
+    fn get(&self, _key: &AssetKey) -> Option<Cow<'_, [u8]>> {
+        None
+    }
+
+    fn csp_hashes(&self, _html_path: &AssetKey) -> Box<dyn Iterator<Item = CspHash<'_>> + '_> {
+        Box::new(std::iter::empty())
+    }
+}
+
+/// The config of the mock apps: a single `main` window and the APIs of the allowlist disabled.
+pub fn mock_config() -> Config {
+    let mut config = Config::default();
+    config.tauri.windows = vec![WindowConfig::default()];
+    config
+}
+
+pub fn mock_context(config: Config) -> Context<NoopAssets> {
+    Context::new(
+        config,
+        Arc::new(NoopAssets),
+        None,
+        None,
+        None,
+        PackageInfo {
+            name: "test".into(),
+            version: "0.1.0".parse().unwrap(),
+            authors: "Tauri",
+            description: "Tauri test",
+        },
+        (),
+        Pattern::Brownfield(std::marker::PhantomData),
+        #[cfg(feature = "shell-scope")]
+        tauri::scope::ShellScopeConfig {
+            open: None,
+            scopes: HashMap::new(),
+        },
+    )
+}
+
+/// An event emitted to the JavaScript listeners of a window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmittedEvent {
+    /// The label of the window it was delivered to.
+    pub window: String,
+    pub event: String,
+    /// The label of the window that emitted it, `None` when it was emitted by the app.
+    pub source: Option<String>,
+    pub payload: JsonValue,
+}
+
+/// The commands waiting for their response, by the id of their success callback.
+#[derive(Default)]
+struct Ipc {
+    next_callback: AtomicUsize,
+    pending: Mutex<HashMap<usize, Sender<Response>>>,
+}
+
+/// An app running on the [`MockRuntime`], it derefs to the [`App`] so the plugins' `ManagerExt` traits work on it.
+pub struct MockApp {
+    app: App<MockRuntime>,
+    context: RuntimeContext,
+    ipc: Arc<Ipc>,
+    events: Mutex<Vec<EmittedEvent>>,
+}
+
+impl MockApp {
+    /// Builds the app with [`mock_config`], the plugins are set up before it returns.
+    pub fn build(builder: Builder<MockRuntime>) -> tauri::Result<Self> {
+        Self::build_with_config(builder, mock_config())
+    }
+
+    /// Builds the app with the given config, e.g. for the `plugins` section the plugins read.
+    pub fn build_with_config(builder: Builder<MockRuntime>, config: Config) -> tauri::Result<Self> {
+        let ipc = Arc::<Ipc>::default();
+        let responses = ipc.clone();
+        let context = RuntimeContext::default();
+        context.prepare();
+
+        let app = builder
+            .invoke_system(String::new(), move |_window, response, callback, _error| {
+                if let Some(tx) = responses.pending.lock().unwrap().remove(&callback.0) {
+                    let _ = tx.send(response.into_result());
+                }
+            })
+            .build(mock_context(config))?;
+        Ok(Self {
+            app,
+            context,
+            ipc,
+            events: Default::default(),
+        })
+    }
+
+    /// The window of [`mock_config`].
+    pub fn window(&self) -> Window<MockRuntime> {
+        self.app
+            .get_window(MAIN_WINDOW)
+            .expect("the config of the app has no `main` window")
+    }
+
+    /// Invokes the command from the main window, e.g. `plugin:store|get`, and waits for its response.
+    ///
+    /// Panics when it doesn't respond within 30 seconds.
+    pub fn invoke<A: Serialize>(&self, cmd: &str, args: A) -> Response {
+        self.invoke_from(&self.window(), cmd, args)
+    }
+
+    /// Invokes the command from the window, e.g. to test the commands scoped to the window.
+    pub fn invoke_from<A: Serialize>(
+        &self,
+        window: &Window<MockRuntime>,
+        cmd: &str,
+        args: A,
+    ) -> Response {
+        let args = serde_json::to_value(args).expect("the arguments must serialize to JSON");
+        self.call(window, cmd, None, args)
+    }
+
+    /// Registers a JavaScript listener for the event on the main window, as `listen` of `@tauri-apps/api/event` does.
+    ///
+    /// The events sent with `emit_all` and `emit_to` are always captured, `Window::emit` only
+    /// delivers them to the windows listening to them.
+    pub fn listen(&self, event: &str) {
+        let window = self.window();
+        let handler = self.ipc.next_callback.fetch_add(1, Ordering::Relaxed);
+        let args = serde_json::json!({
+            "cmd": "listen",
+            "event": event,
+            "windowLabel": null,
+            "handler": handler,
+        });
+        if let Err(e) = self.call(&window, "tauri", Some("Event"), args) {
+            panic!("failed to listen to `{}`: {}", event, e);
+        }
+    }
+
+    /// Takes the events emitted since the last call.
+    pub fn take_events(&self) -> Vec<EmittedEvent> {
+        self.collect_events();
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+
+    /// Waits for the event, for the events emitted from a background thread. The other events are kept.
+    pub fn wait_for_event(&self, event: &str, timeout: Duration) -> Option<EmittedEvent> {
+        let start = Instant::now();
+        loop {
+            self.collect_events();
+            {
+                let mut events = self.events.lock().unwrap();
+                if let Some(index) = events.iter().position(|emitted| emitted.event == event) {
+                    return Some(events.remove(index));
+                }
+            }
+            if start.elapsed() >= timeout {
+                return None;
+            }
+            sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Tells the plugins the window loaded the page, as the webview does on navigation.
+    pub fn load(&self, window: &Window<MockRuntime>, url: &str) -> tauri::Result<()> {
+        window.clone().on_message(InvokePayload {
+            cmd: "__initialized".into(),
+            tauri_module: None,
+            callback: CallbackFn(0),
+            error: CallbackFn(0),
+            inner: serde_json::json!({ "url": url }),
+        })
+    }
+
+    /// Dispatches the event to the handlers of the window, e.g. `WindowEvent::Moved` for the plugins saving the window state.
+    pub fn window_event(&self, window: &Window<MockRuntime>, event: WindowEvent) {
+        self.context.window_event(window.label(), &event);
+    }
+
+    fn call(
+        &self,
+        window: &Window<MockRuntime>,
+        cmd: &str,
+        module: Option<&str>,
+        args: JsonValue,
+    ) -> Response {
+        let callback = self.ipc.next_callback.fetch_add(2, Ordering::Relaxed);
+        let (tx, rx) = channel();
+        self.ipc.pending.lock().unwrap().insert(callback, tx);
+
+        window
+            .clone()
+            .on_message(InvokePayload {
+                cmd: cmd.into(),
+                tauri_module: module.map(Into::into),
+                callback: CallbackFn(callback),
+                error: CallbackFn(callback + 1),
+                inner: args,
+            })
+            .expect("failed to invoke the command");
+        rx.recv_timeout(INVOKE_TIMEOUT).unwrap_or_else(|_| {
+            panic!(
+                "`{}` didn't respond within {} seconds",
+                cmd,
+                INVOKE_TIMEOUT.as_secs()
+            )
+        })
+    }
+
+    fn collect_events(&self) {
+        let scripts = self.context.take_scripts();
+        self.events.lock().unwrap().extend(
+            scripts
+                .into_iter()
+                .filter_map(|(window, script)| parse_event(window, &script)),
+        );
+    }
+}
+
+impl Deref for MockApp {
+    type Target = App<MockRuntime>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.app
+    }
+}
+
+/// Parses the script evaluated by tauri to deliver an event, the other scripts are ignored.
+fn parse_event(window: String, script: &str) -> Option<EmittedEvent> {
+    let rest = &script[script.find(EMIT_MARKER)? + EMIT_MARKER.len()..];
+    let (event, rest) = next_value(rest)?;
+    let (source, rest) = next_value(rest.strip_prefix(", windowLabel: ")?)?;
+    let (payload, _) = next_value(rest.strip_prefix(", payload: ")?)?;
+    Some(EmittedEvent {
+        window,
+        event: serde_json::from_value(event).ok()?,
+        source: serde_json::from_value(source).ok()?,
+        payload,
+    })
+}
+
+fn next_value(s: &str) -> Option<(JsonValue, &str)> {
+    let mut values = serde_json::Deserializer::from_str(s).into_iter::<JsonValue>();
+    let value = values.next()?.ok()?;
+    Some((value, &s[values.byte_offset()..]))
+}
@@ -0,0 +1,766 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! A port of the mock runtime of the tauri tests, which is private to the tauri crate.
+//!
+//! The tasks for the main thread run right away on the calling thread, the scripts evaluated on the windows
+//! are recorded and the window events are dispatched by [`crate::MockApp::window_event`].
+
+#![allow(dead_code, unused_variables)]
+
+#[cfg(target_os = "macos")]
+use tauri::utils::TitleBarStyle;
+use tauri::utils::{config::WindowConfig, Theme};
+use tauri_runtime::{
+    menu::{Menu, MenuUpdate},
+    monitor::Monitor,
+    webview::{WindowBuilder, WindowBuilderBase},
+    window::{
+        dpi::{PhysicalPosition, PhysicalSize, Position, Size},
+        CursorIcon, DetachedWindow, MenuEvent, PendingWindow, WindowEvent,
+    },
+    Dispatch, EventLoopProxy, Icon, Result, RunEvent, Runtime, RuntimeHandle, UserAttentionType,
+    UserEvent,
+};
+use tauri_runtime::{
+    menu::{SystemTrayMenu, TrayHandle},
+    SystemTray, SystemTrayEvent, TrayId,
+};
+use uuid::Uuid;
+
+#[cfg(windows)]
+use windows::Win32::Foundation::HWND;
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+type ShortcutMap = HashMap<String, Box<dyn Fn() + Send + 'static>>;
+type WindowEventHandler = Arc<dyn Fn(&WindowEvent) + Send + Sync + 'static>;
+
+thread_local! {
+  /// The context of the next runtime created on the thread, tauri creates the runtime itself.
+  static NEXT_CONTEXT: RefCell<Option<RuntimeContext>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Default)]
+pub struct RuntimeContext {
+    shortcuts: Arc<Mutex<ShortcutMap>>,
+    clipboard: Arc<Mutex<Option<String>>>,
+    /// The label of the window and the script, in the order they were evaluated.
+    scripts: Arc<Mutex<Vec<(String, String)>>>,
+    window_event_handlers: Arc<Mutex<HashMap<String, Vec<WindowEventHandler>>>>,
+}
+
+impl RuntimeContext {
+    /// Makes it the context of the next runtime created on this thread.
+    pub(crate) fn prepare(&self) {
+        NEXT_CONTEXT.with(|context| context.borrow_mut().replace(self.clone()));
+    }
+
+    /// Takes the scripts evaluated since the last call.
+    pub(crate) fn take_scripts(&self) -> Vec<(String, String)> {
+        std::mem::take(&mut *self.scripts.lock().unwrap())
+    }
+
+    /// Runs the window event handlers of the window, outside of the lock so they can register others.
+    pub(crate) fn window_event(&self, label: &str, event: &WindowEvent) {
+        let handlers = self
+            .window_event_handlers
+            .lock()
+            .unwrap()
+            .get(label)
+            .cloned()
+            .unwrap_or_default();
+        for handler in handlers {
+            handler(event);
+        }
+    }
+
+    fn detached_window<T: UserEvent>(
+        &self,
+        pending: PendingWindow<T, MockRuntime>,
+    ) -> DetachedWindow<T, MockRuntime> {
+        DetachedWindow {
+            dispatcher: MockDispatcher {
+                label: pending.label.clone(),
+                context: self.clone(),
+            },
+            label: pending.label,
+            menu_ids: Default::default(),
+            js_event_listeners: Default::default(),
+        }
+    }
+}
+
+impl fmt::Debug for RuntimeContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RuntimeContext")
+            .field("clipboard", &self.clipboard)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockRuntimeHandle {
+    context: RuntimeContext,
+}
+
+impl<T: UserEvent> RuntimeHandle<T> for MockRuntimeHandle {
+    type Runtime = MockRuntime;
+
+    fn create_proxy(&self) -> EventProxy {
+        EventProxy {}
+    }
+
+    /// Create a new webview window.
+    fn create_window(
+        &self,
+        pending: PendingWindow<T, Self::Runtime>,
+    ) -> Result<DetachedWindow<T, Self::Runtime>> {
+        Ok(self.context.detached_window(pending))
+    }
+
+    /// Run a task on the main thread.
+    fn run_on_main_thread<F: FnOnce() + Send + 'static>(&self, f: F) -> Result<()> {
+        f();
+        Ok(())
+    }
+
+    fn system_tray(
+        &self,
+        system_tray: SystemTray,
+    ) -> Result<<Self::Runtime as Runtime<T>>::TrayHandler> {
+        Ok(MockTrayHandler {
+            context: self.context.clone(),
+        })
+    }
+
+    /// An empty handle, there's no display to connect to.
+    fn raw_display_handle(&self) -> raw_window_handle::RawDisplayHandle {
+        #[cfg(windows)]
+        return raw_window_handle::RawDisplayHandle::Windows(
+            raw_window_handle::WindowsDisplayHandle::empty(),
+        );
+        #[cfg(target_os = "macos")]
+        return raw_window_handle::RawDisplayHandle::AppKit(
+            raw_window_handle::AppKitDisplayHandle::empty(),
+        );
+        #[cfg(not(any(windows, target_os = "macos")))]
+        return raw_window_handle::RawDisplayHandle::Xlib(
+            raw_window_handle::XlibDisplayHandle::empty(),
+        );
+    }
+
+    /// Shows the application, but does not automatically focus it.
+    #[cfg(target_os = "macos")]
+    fn show(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Hides the application.
+    #[cfg(target_os = "macos")]
+    fn hide(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockDispatcher {
+    label: String,
+    context: RuntimeContext,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockGlobalShortcutManager {
+    context: RuntimeContext,
+}
+
+impl tauri_runtime::GlobalShortcutManager for MockGlobalShortcutManager {
+    fn is_registered(&self, accelerator: &str) -> Result<bool> {
+        Ok(self
+            .context
+            .shortcuts
+            .lock()
+            .unwrap()
+            .contains_key(accelerator))
+    }
+
+    fn register<F: Fn() + Send + 'static>(&mut self, accelerator: &str, handler: F) -> Result<()> {
+        self.context
+            .shortcuts
+            .lock()
+            .unwrap()
+            .insert(accelerator.into(), Box::new(handler));
+        Ok(())
+    }
+
+    fn unregister_all(&mut self) -> Result<()> {
+        *self.context.shortcuts.lock().unwrap() = Default::default();
+        Ok(())
+    }
+
+    fn unregister(&mut self, accelerator: &str) -> Result<()> {
+        self.context.shortcuts.lock().unwrap().remove(accelerator);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockClipboardManager {
+    context: RuntimeContext,
+}
+
+impl tauri_runtime::ClipboardManager for MockClipboardManager {
+    fn write_text<T: Into<String>>(&mut self, text: T) -> Result<()> {
+        self.context.clipboard.lock().unwrap().replace(text.into());
+        Ok(())
+    }
+
+    fn read_text(&self) -> Result<Option<String>> {
+        Ok(self.context.clipboard.lock().unwrap().clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockWindowBuilder {}
+
+impl WindowBuilderBase for MockWindowBuilder {}
+
+impl WindowBuilder for MockWindowBuilder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn with_config(config: WindowConfig) -> Self {
+        Self {}
+    }
+
+    fn menu(self, menu: Menu) -> Self {
+        self
+    }
+
+    fn center(self) -> Self {
+        self
+    }
+
+    fn position(self, x: f64, y: f64) -> Self {
+        self
+    }
+
+    fn inner_size(self, min_width: f64, min_height: f64) -> Self {
+        self
+    }
+
+    fn min_inner_size(self, min_width: f64, min_height: f64) -> Self {
+        self
+    }
+
+    fn max_inner_size(self, max_width: f64, max_height: f64) -> Self {
+        self
+    }
+
+    fn resizable(self, resizable: bool) -> Self {
+        self
+    }
+
+    fn title<S: Into<String>>(self, title: S) -> Self {
+        self
+    }
+
+    fn fullscreen(self, fullscreen: bool) -> Self {
+        self
+    }
+
+    fn focused(self, focused: bool) -> Self {
+        self
+    }
+
+    fn maximized(self, maximized: bool) -> Self {
+        self
+    }
+
+    fn visible(self, visible: bool) -> Self {
+        self
+    }
+
+    fn transparent(self, transparent: bool) -> Self {
+        self
+    }
+
+    fn decorations(self, decorations: bool) -> Self {
+        self
+    }
+
+    fn always_on_top(self, always_on_top: bool) -> Self {
+        self
+    }
+
+    fn icon(self, icon: Icon) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn skip_taskbar(self, skip: bool) -> Self {
+        self
+    }
+
+    #[cfg(windows)]
+    fn parent_window(self, parent: HWND) -> Self {
+        self
+    }
+
+    #[cfg(target_os = "macos")]
+    fn parent_window(self, parent: *mut std::ffi::c_void) -> Self {
+        self
+    }
+
+    #[cfg(windows)]
+    fn owner_window(self, owner: HWND) -> Self {
+        self
+    }
+
+    #[cfg(target_os = "macos")]
+    fn title_bar_style(self, style: TitleBarStyle) -> Self {
+        self
+    }
+
+    #[cfg(target_os = "macos")]
+    fn hidden_title(self, transparent: bool) -> Self {
+        self
+    }
+
+    #[cfg(target_os = "macos")]
+    fn tabbing_identifier(self, identifier: &str) -> Self {
+        self
+    }
+
+    fn theme(self, theme: Option<Theme>) -> Self {
+        self
+    }
+
+    fn has_icon(&self) -> bool {
+        false
+    }
+
+    fn get_menu(&self) -> Option<&Menu> {
+        None
+    }
+}
+
+impl<T: UserEvent> Dispatch<T> for MockDispatcher {
+    type Runtime = MockRuntime;
+
+    type WindowBuilder = MockWindowBuilder;
+
+    fn run_on_main_thread<F: FnOnce() + Send + 'static>(&self, f: F) -> Result<()> {
+        f();
+        Ok(())
+    }
+
+    fn on_window_event<F: Fn(&WindowEvent) + Send + 'static>(&self, f: F) -> Uuid {
+        // the handlers are called from the thread dispatching the event, the mutex makes them `Sync`
+        let f = Mutex::new(f);
+        self.context
+            .window_event_handlers
+            .lock()
+            .unwrap()
+            .entry(self.label.clone())
+            .or_default()
+            .push(Arc::new(move |event| (f.lock().unwrap())(event)));
+        Uuid::new_v4()
+    }
+
+    fn on_menu_event<F: Fn(&MenuEvent) + Send + 'static>(&self, f: F) -> Uuid {
+        Uuid::new_v4()
+    }
+
+    fn open_devtools(&self) {}
+
+    fn close_devtools(&self) {}
+
+    fn is_devtools_open(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn scale_factor(&self) -> Result<f64> {
+        Ok(1.0)
+    }
+
+    fn inner_position(&self) -> Result<PhysicalPosition<i32>> {
+        Ok(PhysicalPosition { x: 0, y: 0 })
+    }
+
+    fn outer_position(&self) -> Result<PhysicalPosition<i32>> {
+        Ok(PhysicalPosition { x: 0, y: 0 })
+    }
+
+    fn inner_size(&self) -> Result<PhysicalSize<u32>> {
+        Ok(PhysicalSize {
+            width: 0,
+            height: 0,
+        })
+    }
+
+    fn outer_size(&self) -> Result<PhysicalSize<u32>> {
+        Ok(PhysicalSize {
+            width: 0,
+            height: 0,
+        })
+    }
+
+    fn is_fullscreen(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn is_maximized(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn is_decorated(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn is_resizable(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn is_visible(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn is_menu_visible(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn current_monitor(&self) -> Result<Option<Monitor>> {
+        Ok(None)
+    }
+
+    fn primary_monitor(&self) -> Result<Option<Monitor>> {
+        Ok(None)
+    }
+
+    fn available_monitors(&self) -> Result<Vec<Monitor>> {
+        Ok(Vec::new())
+    }
+
+    fn theme(&self) -> Result<Theme> {
+        Ok(Theme::Light)
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    /// Fails, the mock windows have no native window.
+    fn gtk_window(&self) -> Result<gtk::ApplicationWindow> {
+        Err(tauri_runtime::Error::FailedToReceiveMessage)
+    }
+
+    /// An empty handle, as `Window` unwraps it to implement `HasRawWindowHandle`.
+    fn raw_window_handle(&self) -> Result<raw_window_handle::RawWindowHandle> {
+        #[cfg(windows)]
+        return Ok(raw_window_handle::RawWindowHandle::Win32(
+            raw_window_handle::Win32WindowHandle::empty(),
+        ));
+        #[cfg(target_os = "macos")]
+        return Ok(raw_window_handle::RawWindowHandle::AppKit(
+            raw_window_handle::AppKitWindowHandle::empty(),
+        ));
+        #[cfg(not(any(windows, target_os = "macos")))]
+        return Ok(raw_window_handle::RawWindowHandle::Xlib(
+            raw_window_handle::XlibWindowHandle::empty(),
+        ));
+    }
+
+    fn center(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn print(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn request_user_attention(&self, request_type: Option<UserAttentionType>) -> Result<()> {
+        Ok(())
+    }
+
+    fn create_window(
+        &mut self,
+        pending: PendingWindow<T, Self::Runtime>,
+    ) -> Result<DetachedWindow<T, Self::Runtime>> {
+        Ok(self.context.detached_window(pending))
+    }
+
+    fn set_resizable(&self, resizable: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_title<S: Into<String>>(&self, title: S) -> Result<()> {
+        Ok(())
+    }
+
+    fn maximize(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn unmaximize(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn minimize(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn unminimize(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn show_menu(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn hide_menu(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn show(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn hide(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn close(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_decorations(&self, decorations: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_always_on_top(&self, always_on_top: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_size(&self, size: Size) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_min_size(&self, size: Option<Size>) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_max_size(&self, size: Option<Size>) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_position(&self, position: Position) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_fullscreen(&self, fullscreen: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_focus(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_icon(&self, icon: Icon) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_skip_taskbar(&self, skip: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_cursor_grab(&self, grab: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_cursor_visible(&self, visible: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_cursor_icon(&self, icon: CursorIcon) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_cursor_position<Pos: Into<Position>>(&self, position: Pos) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_ignore_cursor_events(&self, ignore: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn start_dragging(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn eval_script<S: Into<String>>(&self, script: S) -> Result<()> {
+        self.context
+            .scripts
+            .lock()
+            .unwrap()
+            .push((self.label.clone(), script.into()));
+        Ok(())
+    }
+
+    fn update_menu_item(&self, id: u16, update: MenuUpdate) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockTrayHandler {
+    context: RuntimeContext,
+}
+
+impl TrayHandle for MockTrayHandler {
+    fn set_icon(&self, icon: Icon) -> Result<()> {
+        Ok(())
+    }
+    fn set_menu(&self, menu: SystemTrayMenu) -> Result<()> {
+        Ok(())
+    }
+    fn update_item(&self, id: u16, update: MenuUpdate) -> Result<()> {
+        Ok(())
+    }
+    #[cfg(target_os = "macos")]
+    fn set_icon_as_template(&self, is_template: bool) -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    fn set_title(&self, title: &str) -> tauri_runtime::Result<()> {
+        Ok(())
+    }
+
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventProxy {}
+
+impl<T: UserEvent> EventLoopProxy<T> for EventProxy {
+    fn send_event(&self, event: T) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct MockRuntime {
+    pub context: RuntimeContext,
+    global_shortcut_manager: MockGlobalShortcutManager,
+    clipboard_manager: MockClipboardManager,
+    tray_handler: MockTrayHandler,
+}
+
+impl MockRuntime {
+    fn init() -> Self {
+        let context = NEXT_CONTEXT
+            .with(|context| context.borrow_mut().take())
+            .unwrap_or_default();
+        Self {
+            global_shortcut_manager: MockGlobalShortcutManager {
+                context: context.clone(),
+            },
+            clipboard_manager: MockClipboardManager {
+                context: context.clone(),
+            },
+            tray_handler: MockTrayHandler {
+                context: context.clone(),
+            },
+            context,
+        }
+    }
+}
+
+impl<T: UserEvent> Runtime<T> for MockRuntime {
+    type Dispatcher = MockDispatcher;
+    type Handle = MockRuntimeHandle;
+    type GlobalShortcutManager = MockGlobalShortcutManager;
+    type ClipboardManager = MockClipboardManager;
+    type TrayHandler = MockTrayHandler;
+    type EventLoopProxy = EventProxy;
+
+    fn new() -> Result<Self> {
+        Ok(Self::init())
+    }
+
+    #[cfg(any(windows, target_os = "linux"))]
+    fn new_any_thread() -> Result<Self> {
+        Ok(Self::init())
+    }
+
+    fn create_proxy(&self) -> EventProxy {
+        EventProxy {}
+    }
+
+    fn handle(&self) -> Self::Handle {
+        MockRuntimeHandle {
+            context: self.context.clone(),
+        }
+    }
+
+    fn global_shortcut_manager(&self) -> Self::GlobalShortcutManager {
+        self.global_shortcut_manager.clone()
+    }
+
+    fn clipboard_manager(&self) -> Self::ClipboardManager {
+        self.clipboard_manager.clone()
+    }
+
+    fn create_window(&self, pending: PendingWindow<T, Self>) -> Result<DetachedWindow<T, Self>> {
+        Ok(self.context.detached_window(pending))
+    }
+
+    fn system_tray(&self, system_tray: SystemTray) -> Result<Self::TrayHandler> {
+        Ok(self.tray_handler.clone())
+    }
+
+    fn on_system_tray_event<F: Fn(TrayId, &SystemTrayEvent) + Send + 'static>(&mut self, f: F) {}
+
+    #[cfg(target_os = "macos")]
+    fn set_activation_policy(&mut self, activation_policy: tauri_runtime::ActivationPolicy) {}
+
+    #[cfg(target_os = "macos")]
+    fn show(&self) {}
+
+    #[cfg(target_os = "macos")]
+    fn hide(&self) {}
+
+    #[cfg(any(
+        target_os = "macos",
+        windows,
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    fn run_iteration<F: Fn(RunEvent<T>) + 'static>(
+        &mut self,
+        callback: F,
+    ) -> tauri_runtime::RunIteration {
+        Default::default()
+    }
+
+    fn run<F: FnMut(RunEvent<T>) + 'static>(self, callback: F) {
+        loop {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+    }
+}
@@ -0,0 +1,51 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+/** `noPreference` when the desktop leaves the choice to the apps, only reported on Linux. */
+export type ColorScheme = "light" | "dark" | "noPreference";
+
+export interface Color {
+  r: number;
+  g: number;
+  b: number;
+}
+
+/** The window backgrounds available on Windows. */
+export interface Effects {
+  /** Windows 11. */
+  mica: boolean;
+  /** Windows 10 version 1809 and later. */
+  acrylic: boolean;
+  /** Whether the transparency effects are enabled, the effects are drawn with a solid color otherwise. */
+  transparency: boolean;
+}
+
+export interface Settings {
+  colorScheme: ColorScheme;
+  /** `null` when the desktop doesn't have one. */
+  accentColor: Color | null;
+  highContrast: boolean;
+  /** The user asked to reduce or disable the animations. */
+  reducedMotion: boolean;
+  /** Only reported on Windows. */
+  effects: Effects | null;
+}
+
+async function settings(): Promise<Settings> {
+  return await invoke("plugin:appearance|settings");
+}
+
+/**
+ * Calls the handler every time the appearance settings of the system change.
+ */
+async function onChange(
+  handler: (settings: Settings) => void
+): Promise<UnlistenFn> {
+  return await listen<Settings>("appearance://changed", ({ payload }) =>
+    handler(payload)
+  );
+}
+
+export { settings, onChange };
@@ -0,0 +1,186 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use serde::Serialize;
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, RunEvent, Runtime, State, WindowEvent,
+};
+
+use std::{
+    sync::Mutex,
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+type ChangeHandler = dyn Fn(&Settings) + Send + Sync;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+    /// The desktop leaves the choice to the apps, only reported on Linux.
+    NoPreference,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// The window backgrounds available on Windows, see the `window-vibrancy` crate to apply them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Effects {
+    /// Windows 11.
+    pub mica: bool,
+    /// Windows 10 version 1809 and later.
+    pub acrylic: bool,
+    /// Whether the transparency effects are enabled, the effects are drawn with a solid color otherwise.
+    pub transparency: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    pub color_scheme: ColorScheme,
+    /// `None` when the desktop doesn't have one.
+    pub accent_color: Option<Color>,
+    pub high_contrast: bool,
+    /// The user asked to reduce or disable the animations.
+    pub reduced_motion: bool,
+    /// Only reported on Windows.
+    pub effects: Option<Effects>,
+}
+
+pub struct Appearance<R: Runtime> {
+    app: AppHandle<R>,
+    settings: Mutex<Settings>,
+    handlers: Mutex<Vec<Box<ChangeHandler>>>,
+}
+
+impl<R: Runtime> Appearance<R> {
+    pub fn settings(&self) -> Settings {
+        *self.settings.lock().unwrap()
+    }
+
+    /// Calls `handler` every time the settings change, before they are emitted to the windows.
+    pub fn on_change<F: Fn(&Settings) + Send + Sync + 'static>(&self, handler: F) {
+        self.handlers.lock().unwrap().push(Box::new(handler));
+    }
+
+    fn refresh(&self) {
+        let settings = platform::settings();
+        if std::mem::replace(&mut *self.settings.lock().unwrap(), settings) == settings {
+            return;
+        }
+        for handler in self.handlers.lock().unwrap().iter() {
+            handler(&settings);
+        }
+        let _ = self.app.emit_all("appearance://changed", settings);
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn appearance(&self) -> State<'_, Appearance<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn appearance(&self) -> State<'_, Appearance<R>> {
+        self.state::<Appearance<R>>()
+    }
+}
+
+#[command]
+async fn settings<R: Runtime>(app: AppHandle<R>) -> Settings {
+    app.appearance().settings()
+}
+
+pub struct Builder {
+    poll_interval: Duration,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How often the settings are checked, defaults to 2 seconds.
+    ///
+    /// They are also checked as soon as a window is notified of a theme change,
+    /// and when the desktop portal reports a change on Linux.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let poll_interval = self.poll_interval;
+
+        PluginBuilder::new("appearance")
+            .invoke_handler(tauri::generate_handler![settings])
+            .setup(move |app| {
+                app.manage(Appearance {
+                    app: app.clone(),
+                    settings: Mutex::new(platform::settings()),
+                    handlers: Default::default(),
+                });
+
+                #[cfg(target_os = "linux")]
+                {
+                    let app = app.clone();
+                    platform::listen(move || app.appearance().refresh());
+                }
+
+                let app = app.clone();
+                spawn(move || loop {
+                    sleep(poll_interval);
+                    app.appearance().refresh();
+                });
+
+                Ok(())
+            })
+            .on_event(|app, event| {
+                if let RunEvent::WindowEvent {
+                    event: WindowEvent::ThemeChanged(_),
+                    ..
+                } = event
+                {
+                    app.appearance().refresh();
+                }
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
@@ -0,0 +1,100 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Reads the settings from the desktop portal, which is implemented by GNOME, KDE and most other desktops.
+
+use log::warn;
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::{OwnedValue, Value},
+};
+
+use std::{sync::OnceLock, thread::spawn};
+
+use crate::{Color, ColorScheme, Settings};
+
+const APPEARANCE: &str = "org.freedesktop.appearance";
+/// Some portals also expose the GNOME settings, which have the animations switch.
+const GNOME_INTERFACE: &str = "org.gnome.desktop.interface";
+
+fn proxy(connection: &Connection) -> zbus::Result<Proxy<'_>> {
+    Proxy::new(
+        connection,
+        "org.freedesktop.portal.Desktop",
+        "/org/freedesktop/portal/desktop",
+        "org.freedesktop.portal.Settings",
+    )
+}
+
+fn connection() -> Option<&'static Connection> {
+    static CONNECTION: OnceLock<Option<Connection>> = OnceLock::new();
+    CONNECTION
+        .get_or_init(|| match Connection::session() {
+            Ok(connection) => Some(connection),
+            Err(e) => {
+                warn!("failed to connect to the session bus: {}", e);
+                None
+            }
+        })
+        .as_ref()
+}
+
+/// `Read` wraps the value in a second variant.
+fn unwrap(value: Value<'_>) -> Value<'_> {
+    match value {
+        Value::Value(value) => unwrap(*value),
+        value => value,
+    }
+}
+
+fn read<T: TryFrom<OwnedValue>>(proxy: &Proxy<'_>, namespace: &str, key: &str) -> Option<T> {
+    let value: OwnedValue = proxy.call("Read", &(namespace, key)).ok()?;
+    T::try_from(unwrap(value.into()).into()).ok()
+}
+
+/// The accent color is in sRGB between `0` and `1`, out of the range when not set.
+fn color((r, g, b): (f64, f64, f64)) -> Option<Color> {
+    let channel = |c: f64| (0.0..=1.0).contains(&c).then(|| (c * 255.0).round() as u8);
+    Some(Color {
+        r: channel(r)?,
+        g: channel(g)?,
+        b: channel(b)?,
+    })
+}
+
+pub(crate) fn settings() -> Settings {
+    let Some(proxy) = connection().and_then(|connection| proxy(connection).ok()) else {
+        return Settings::default();
+    };
+    let color_scheme = match read::<u32>(&proxy, APPEARANCE, "color-scheme") {
+        Some(1) => ColorScheme::Dark,
+        Some(2) => ColorScheme::Light,
+        _ => ColorScheme::NoPreference,
+    };
+    Settings {
+        color_scheme,
+        accent_color: read::<(f64, f64, f64)>(&proxy, APPEARANCE, "accent-color").and_then(color),
+        high_contrast: read::<u32>(&proxy, APPEARANCE, "contrast") == Some(1),
+        reduced_motion: read::<bool>(&proxy, GNOME_INTERFACE, "enable-animations") == Some(false),
+        effects: None,
+    }
+}
+
+/// Calls `on_change` every time the portal reports a setting change.
+pub(crate) fn listen<F: Fn() + Send + 'static>(on_change: F) {
+    spawn(move || {
+        let result = connection()
+            .ok_or(zbus::Error::Unsupported)
+            .and_then(proxy)
+            .and_then(|proxy| {
+                for _ in proxy.receive_signal("SettingChanged")? {
+                    on_change();
+                }
+                Ok(())
+            });
+        if let Err(e) = result {
+            warn!("failed to listen to the appearance settings: {}", e);
+        }
+    });
+}
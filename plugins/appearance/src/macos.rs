@@ -0,0 +1,51 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::process::Command;
+
+use crate::{Color, ColorScheme, Settings};
+
+/// Reads a key of the user defaults, `None` when it's not set.
+fn read(domain: &str, key: &str) -> Option<String> {
+    let output = Command::new("defaults")
+        .args(["read", domain, key])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// `AppleAccentColor` is an index in the accent colors of the system settings, blue isn't stored.
+fn accent_color(index: Option<i32>) -> Color {
+    let (r, g, b) = match index {
+        Some(-1) => (142, 142, 147),
+        Some(0) => (255, 59, 48),
+        Some(1) => (255, 149, 0),
+        Some(2) => (255, 204, 0),
+        Some(3) => (40, 205, 65),
+        Some(5) => (175, 82, 222),
+        Some(6) => (255, 45, 85),
+        _ => (0, 122, 255),
+    };
+    Color { r, g, b }
+}
+
+pub(crate) fn settings() -> Settings {
+    // the key is removed in light mode
+    let color_scheme = match read("-g", "AppleInterfaceStyle").as_deref() {
+        Some("Dark") => ColorScheme::Dark,
+        _ => ColorScheme::Light,
+    };
+    let accent = read("-g", "AppleAccentColor").and_then(|index| index.parse().ok());
+    Settings {
+        color_scheme,
+        accent_color: Some(accent_color(accent)),
+        high_contrast: read("com.apple.universalaccess", "increaseContrast").as_deref()
+            == Some("1"),
+        reduced_motion: read("com.apple.universalaccess", "reduceMotion").as_deref() == Some("1"),
+        effects: None,
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::{BOOL, ERROR_SUCCESS},
+        System::Registry::{
+            RegGetValueW, HKEY, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, RRF_RT_REG_DWORD,
+            RRF_RT_REG_SZ,
+        },
+        UI::{
+            Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW},
+            WindowsAndMessaging::{
+                SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SPI_GETHIGHCONTRAST,
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+            },
+        },
+    },
+};
+
+use std::{ffi::c_void, iter::once, mem::size_of};
+
+use crate::{Color, ColorScheme, Effects, Settings};
+
+const PERSONALIZE: &str = r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize";
+const DWM: &str = r"Software\Microsoft\Windows\DWM";
+const CURRENT_VERSION: &str = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion";
+
+/// The first builds of Windows 10 version 1809 and Windows 11.
+const ACRYLIC_BUILD: u32 = 17763;
+const MICA_BUILD: u32 = 22000;
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(once(0)).collect()
+}
+
+fn read_dword(key: HKEY, subkey: &str, value: &str) -> Option<u32> {
+    let (subkey, value) = (wide(subkey), wide(value));
+    let mut data = 0u32;
+    let mut size = size_of::<u32>() as u32;
+    let result = unsafe {
+        RegGetValueW(
+            key,
+            PCWSTR(subkey.as_ptr()),
+            PCWSTR(value.as_ptr()),
+            RRF_RT_REG_DWORD,
+            std::ptr::null_mut(),
+            &mut data as *mut u32 as *mut c_void,
+            &mut size,
+        )
+    };
+    (result == ERROR_SUCCESS).then_some(data)
+}
+
+fn read_string(key: HKEY, subkey: &str, value: &str) -> Option<String> {
+    let (subkey, value) = (wide(subkey), wide(value));
+    let mut buffer = [0u16; 64];
+    let mut size = (buffer.len() * size_of::<u16>()) as u32;
+    let result = unsafe {
+        RegGetValueW(
+            key,
+            PCWSTR(subkey.as_ptr()),
+            PCWSTR(value.as_ptr()),
+            RRF_RT_REG_SZ,
+            std::ptr::null_mut(),
+            buffer.as_mut_ptr() as *mut c_void,
+            &mut size,
+        )
+    };
+    (result == ERROR_SUCCESS).then(|| {
+        let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+        String::from_utf16_lossy(&buffer[..len])
+    })
+}
+
+fn high_contrast() -> bool {
+    let mut info = HIGHCONTRASTW {
+        cbSize: size_of::<HIGHCONTRASTW>() as u32,
+        ..Default::default()
+    };
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETHIGHCONTRAST,
+            info.cbSize,
+            &mut info as *mut HIGHCONTRASTW as *mut c_void,
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    }
+    .as_bool()
+        && info.dwFlags.0 & HCF_HIGHCONTRASTON.0 != 0
+}
+
+fn animations() -> bool {
+    let mut enabled = BOOL(1);
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            &mut enabled as *mut BOOL as *mut c_void,
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    };
+    enabled.as_bool()
+}
+
+pub(crate) fn settings() -> Settings {
+    let color_scheme = match read_dword(HKEY_CURRENT_USER, PERSONALIZE, "AppsUseLightTheme") {
+        Some(0) => ColorScheme::Dark,
+        _ => ColorScheme::Light,
+    };
+    // stored as 0xAABBGGRR
+    let accent_color = read_dword(HKEY_CURRENT_USER, DWM, "AccentColor").map(|color| Color {
+        r: color as u8,
+        g: (color >> 8) as u8,
+        b: (color >> 16) as u8,
+    });
+    let build = read_string(HKEY_LOCAL_MACHINE, CURRENT_VERSION, "CurrentBuildNumber")
+        .and_then(|build| build.parse::<u32>().ok())
+        .unwrap_or_default();
+    Settings {
+        color_scheme,
+        accent_color,
+        high_contrast: high_contrast(),
+        reduced_motion: !animations(),
+        effects: Some(Effects {
+            mica: build >= MICA_BUILD,
+            acrylic: build >= ACRYLIC_BUILD,
+            transparency: read_dword(HKEY_CURRENT_USER, PERSONALIZE, "EnableTransparency")
+                != Some(0),
+        }),
+    }
+}
@@ -0,0 +1,118 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { appWindow } from "@tauri-apps/api/window";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export type Format = "zip" | "tar" | "tarGz" | "7z";
+
+export interface CreateOptions {
+  /** Guessed from the extension of the archive when omitted. */
+  format?: Format;
+  /** Encrypts the entries, with AES-256 for 7z archives and ZipCrypto for zip archives. */
+  password?: string;
+}
+
+export interface ExtractOptions {
+  /** Guessed from the extension of the archive when omitted. */
+  format?: Format;
+  password?: string;
+}
+
+export interface Entry {
+  /** The path of the entry inside the archive, always separated by `/`. */
+  name: string;
+  isDir: boolean;
+  /** The uncompressed size. */
+  size: number;
+}
+
+interface ProgressPayload {
+  id: number;
+  processed: number;
+  total: number;
+}
+
+type ProgressHandler = (processed: number, total: number) => void;
+const handlers: Map<number, ProgressHandler> = new Map();
+let listening = false;
+
+async function listenToProgressEventIfNeeded(): Promise<void> {
+  if (listening) {
+    return await Promise.resolve();
+  }
+  return await appWindow
+    .listen<ProgressPayload>("archive://progress", ({ payload }) => {
+      const handler = handlers.get(payload.id);
+      if (handler != null) {
+        handler(payload.processed, payload.total);
+      }
+    })
+    .then(() => {
+      listening = true;
+    });
+}
+
+async function withProgress(
+  command: string,
+  args: Record<string, unknown>,
+  progressHandler?: ProgressHandler
+): Promise<void> {
+  const ids = new Uint32Array(1);
+  window.crypto.getRandomValues(ids);
+  const id = ids[0];
+
+  if (progressHandler != null) {
+    handlers.set(id, progressHandler);
+    await listenToProgressEventIfNeeded();
+  }
+
+  try {
+    await invoke(`plugin:archive|${command}`, { id, ...args });
+  } finally {
+    handlers.delete(id);
+  }
+}
+
+/**
+ * Creates an archive containing the given files and directories, directories are added recursively.
+ */
+async function create(
+  sources: string[],
+  archive: string,
+  options?: CreateOptions,
+  progressHandler?: ProgressHandler
+): Promise<void> {
+  return await withProgress(
+    "create_archive",
+    { sources, archive, options },
+    progressHandler
+  );
+}
+
+/**
+ * Extracts the archive into the destination directory.
+ */
+async function extract(
+  archive: string,
+  destination: string,
+  options?: ExtractOptions,
+  progressHandler?: ProgressHandler
+): Promise<void> {
+  return await withProgress(
+    "extract_archive",
+    { archive, destination, options },
+    progressHandler
+  );
+}
+
+/**
+ * Lists the entries of the archive without extracting it.
+ */
+async function list(
+  archive: string,
+  options?: ExtractOptions
+): Promise<Entry[]> {
+  return await invoke("plugin:archive|list_archive", { archive, options });
+}
+
+export { create, extract, list };
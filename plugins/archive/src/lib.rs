@@ -0,0 +1,499 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    Manager, Runtime, Window,
+};
+use tauri_plugin_common::{error, executor::Priority, ExecutorExt, PluginError};
+
+use std::{
+    fs::{create_dir_all, File},
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+};
+
+mod sevenz;
+mod tarball;
+mod zipfile;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Zip(zip::result::ZipError),
+    #[error(transparent)]
+    SevenZ(sevenz_rust::Error),
+    #[error("unknown archive format of {0}")]
+    UnknownFormat(PathBuf),
+    #[error("path not allowed on the configured scope: {0}")]
+    PathForbidden(PathBuf),
+    #[error("entry `{0}` would be extracted outside of the destination")]
+    UnsafeEntry(String),
+    #[error("the archive is encrypted, a password is required")]
+    PasswordRequired,
+    #[error("invalid password")]
+    InvalidPassword,
+    #[error("tar archives can't be encrypted")]
+    EncryptionUnsupported,
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(error: zip::result::ZipError) -> Self {
+        use zip::result::ZipError;
+        match error {
+            ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => Self::PasswordRequired,
+            ZipError::InvalidPassword => Self::InvalidPassword,
+            ZipError::Io(e) => Self::Io(e),
+            error => Self::Zip(error),
+        }
+    }
+}
+
+impl From<sevenz_rust::Error> for Error {
+    fn from(error: sevenz_rust::Error) -> Self {
+        match error {
+            sevenz_rust::Error::PasswordRequired => Self::PasswordRequired,
+            sevenz_rust::Error::Io(e, _) | sevenz_rust::Error::FileOpen(e, _) => Self::Io(e),
+            error => Self::SevenZ(error),
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "archive";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Zip(..) => "zip",
+            Self::SevenZ(..) => "seven_z",
+            Self::UnknownFormat(..) => "unknown_format",
+            Self::PathForbidden(..) => "path_forbidden",
+            Self::UnsafeEntry(..) => "unsafe_entry",
+            Self::PasswordRequired => "password_required",
+            Self::InvalidPassword => "invalid_password",
+            Self::EncryptionUnsupported => "encryption_unsupported",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Format {
+    Zip,
+    Tar,
+    TarGz,
+    #[serde(rename = "7z")]
+    SevenZ,
+}
+
+impl Format {
+    /// Guesses the format from the extension of the archive.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".7z") {
+            Some(Self::SevenZ)
+        } else {
+            None
+        }
+    }
+
+    fn resolve(format: Option<Self>, path: &Path) -> Result<Self> {
+        format
+            .or_else(|| Self::from_path(path))
+            .ok_or_else(|| Error::UnknownFormat(path.to_path_buf()))
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateOptions {
+    /// Guessed from the extension of the archive when `None`.
+    pub format: Option<Format>,
+    /// Encrypts the entries, with AES-256 for 7z archives and ZipCrypto for zip archives.
+    ///
+    /// ZipCrypto is weak but can be opened by every unzip tool, use 7z to protect sensitive files.
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractOptions {
+    /// Guessed from the extension of the archive when `None`.
+    pub format: Option<Format>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    /// The path of the entry inside the archive, always separated by `/`.
+    pub name: String,
+    pub is_dir: bool,
+    /// The uncompressed size.
+    pub size: u64,
+}
+
+/// How much of an operation is done, in bytes.
+///
+/// Creating counts the bytes read from the sources and extracting the uncompressed bytes written,
+/// except for tar archives that have no index and count the bytes read from the archive instead.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub processed: u64,
+    pub total: u64,
+}
+
+struct Tracker<'a> {
+    progress: Progress,
+    on_progress: &'a mut dyn FnMut(Progress),
+}
+
+impl<'a> Tracker<'a> {
+    fn new(total: u64, on_progress: &'a mut dyn FnMut(Progress)) -> Self {
+        Self {
+            progress: Progress {
+                processed: 0,
+                total,
+            },
+            on_progress,
+        }
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        self.progress.processed += bytes;
+        (self.on_progress)(self.progress);
+    }
+
+    fn finish(&mut self) {
+        self.advance(self.progress.total.saturating_sub(self.progress.processed));
+    }
+}
+
+/// Reports the bytes read through it to the tracker.
+struct ProgressReader<'a, 'b, R> {
+    inner: R,
+    tracker: &'a mut Tracker<'b>,
+}
+
+impl<'a, 'b, R> ProgressReader<'a, 'b, R> {
+    fn new(inner: R, tracker: &'a mut Tracker<'b>) -> Self {
+        Self { inner, tracker }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read > 0 {
+            self.tracker.advance(read as u64);
+        }
+        Ok(read)
+    }
+}
+
+/// A file or directory to add to an archive.
+struct Source {
+    path: PathBuf,
+    /// The path inside the archive.
+    name: String,
+    is_dir: bool,
+    size: u64,
+}
+
+/// Walks the sources, which are added at the root of the archive under their file name.
+///
+/// The symlinks inside of the directories are skipped, their targets may be out of the fs scope the sources were
+/// checked against, like the links of the extracted archives.
+fn collect_sources<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<Source>> {
+    fn walk(path: &Path, name: String, sources: &mut Vec<Source>) -> Result<()> {
+        let metadata = path.metadata()?;
+        if metadata.is_dir() {
+            sources.push(Source {
+                path: path.to_path_buf(),
+                name: name.clone(),
+                is_dir: true,
+                size: 0,
+            });
+            for entry in path.read_dir()? {
+                let entry = entry?;
+                if entry.file_type()?.is_symlink() {
+                    continue;
+                }
+                let child = format!("{}/{}", name, entry.file_name().to_string_lossy());
+                walk(&entry.path(), child, sources)?;
+            }
+        } else {
+            sources.push(Source {
+                path: path.to_path_buf(),
+                name,
+                is_dir: false,
+                size: metadata.len(),
+            });
+        }
+        Ok(())
+    }
+
+    let mut sources = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "source has no name"))?;
+        walk(path, name, &mut sources)?;
+    }
+    Ok(sources)
+}
+
+/// Where an archive is extracted, every entry must resolve inside of it.
+struct Destination<'a> {
+    root: PathBuf,
+    allowed: &'a dyn Fn(&Path) -> bool,
+}
+
+impl Destination<'_> {
+    /// Resolves the path of an entry, rejecting absolute paths and `..` components.
+    fn resolve(&self, name: &str) -> Result<PathBuf> {
+        let name = name.replace('\\', "/");
+        let mut path = self.root.clone();
+        for component in Path::new(&name).components() {
+            match component {
+                Component::Normal(c) => path.push(c),
+                Component::CurDir => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    return Err(Error::UnsafeEntry(name))
+                }
+            }
+        }
+        if (self.allowed)(&path) {
+            Ok(path)
+        } else {
+            Err(Error::PathForbidden(path))
+        }
+    }
+
+    fn create_dir(&self, name: &str) -> Result<()> {
+        create_dir_all(self.resolve(name)?).map_err(Into::into)
+    }
+
+    fn write_file(&self, name: &str, reader: &mut dyn Read) -> Result<()> {
+        let path = self.resolve(name)?;
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        io::copy(reader, &mut File::create(path)?)?;
+        Ok(())
+    }
+}
+
+/// Creates an archive of the files and directories.
+pub fn create<P: AsRef<Path>, F: FnMut(Progress)>(
+    sources: &[P],
+    archive: &Path,
+    options: &CreateOptions,
+    mut on_progress: F,
+) -> Result<()> {
+    let format = Format::resolve(options.format, archive)?;
+    let sources = collect_sources(sources)?;
+    let total = sources.iter().map(|s| s.size).sum();
+    let mut tracker = Tracker::new(total, &mut on_progress);
+    let password = options.password.as_deref();
+
+    match format {
+        Format::Zip => zipfile::create(&sources, archive, password, &mut tracker),
+        Format::Tar | Format::TarGz if password.is_some() => Err(Error::EncryptionUnsupported),
+        Format::Tar => tarball::create(&sources, archive, false, &mut tracker),
+        Format::TarGz => tarball::create(&sources, archive, true, &mut tracker),
+        Format::SevenZ => sevenz::create(&sources, archive, password, &mut tracker),
+    }
+}
+
+/// Extracts the archive into the destination directory.
+///
+/// Entries that would end up outside of the destination fail the extraction, links are skipped.
+pub fn extract<F: FnMut(Progress)>(
+    archive: &Path,
+    destination: &Path,
+    options: &ExtractOptions,
+    on_progress: F,
+) -> Result<()> {
+    extract_with(archive, destination, options, &|_| true, on_progress)
+}
+
+fn extract_with<F: FnMut(Progress)>(
+    archive: &Path,
+    destination: &Path,
+    options: &ExtractOptions,
+    allowed: &dyn Fn(&Path) -> bool,
+    mut on_progress: F,
+) -> Result<()> {
+    let format = Format::resolve(options.format, archive)?;
+    create_dir_all(destination)?;
+    let destination = Destination {
+        root: destination.to_path_buf(),
+        allowed,
+    };
+    let password = options.password.as_deref();
+
+    match format {
+        Format::Zip => zipfile::extract(archive, &destination, password, &mut on_progress),
+        Format::Tar => tarball::extract(archive, &destination, false, &mut on_progress),
+        Format::TarGz => tarball::extract(archive, &destination, true, &mut on_progress),
+        Format::SevenZ => sevenz::extract(archive, &destination, password, &mut on_progress),
+    }
+}
+
+/// Lists the entries without extracting them.
+pub fn list(archive: &Path, options: &ExtractOptions) -> Result<Vec<Entry>> {
+    match Format::resolve(options.format, archive)? {
+        Format::Zip => zipfile::list(archive),
+        Format::Tar => tarball::list(archive, false),
+        Format::TarGz => tarball::list(archive, true),
+        Format::SevenZ => sevenz::list(archive, options.password.as_deref()),
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ProgressPayload {
+    id: u32,
+    #[serde(flatten)]
+    progress: Progress,
+}
+
+fn check_scope<R: Runtime>(window: &Window<R>, path: &Path) -> Result<()> {
+    if window.fs_scope().is_allowed(path) {
+        Ok(())
+    } else {
+        Err(Error::PathForbidden(path.to_path_buf()))
+    }
+}
+
+fn emitter<R: Runtime>(window: Window<R>, id: u32) -> impl FnMut(Progress) {
+    move |progress| {
+        let _ = window.emit("archive://progress", ProgressPayload { id, progress });
+    }
+}
+
+#[command]
+async fn create_archive<R: Runtime>(
+    window: Window<R>,
+    id: u32,
+    sources: Vec<PathBuf>,
+    archive: PathBuf,
+    options: Option<CreateOptions>,
+) -> Result<()> {
+    for source in &sources {
+        check_scope(&window, source)?;
+    }
+    check_scope(&window, &archive)?;
+
+    let executor = window.executor().inner().clone();
+    let on_progress = emitter(window, id);
+    executor
+        .spawn(Priority::Normal, move |_| {
+            create(
+                &sources,
+                &archive,
+                &options.unwrap_or_default(),
+                on_progress,
+            )
+        })
+        .join()
+        .await
+        .map_err(io::Error::other)?
+}
+
+#[command]
+async fn extract_archive<R: Runtime>(
+    window: Window<R>,
+    id: u32,
+    archive: PathBuf,
+    destination: PathBuf,
+    options: Option<ExtractOptions>,
+) -> Result<()> {
+    check_scope(&window, &archive)?;
+    check_scope(&window, &destination)?;
+
+    let scope = window.fs_scope();
+    let executor = window.executor().inner().clone();
+    let on_progress = emitter(window, id);
+    executor
+        .spawn(Priority::Normal, move |_| {
+            extract_with(
+                &archive,
+                &destination,
+                &options.unwrap_or_default(),
+                &|path| scope.is_allowed(path),
+                on_progress,
+            )
+        })
+        .join()
+        .await
+        .map_err(io::Error::other)?
+}
+
+#[command]
+async fn list_archive<R: Runtime>(
+    window: Window<R>,
+    archive: PathBuf,
+    options: Option<ExtractOptions>,
+) -> Result<Vec<Entry>> {
+    check_scope(&window, &archive)?;
+
+    window
+        .executor()
+        .spawn(Priority::High, move |_| {
+            list(&archive, &options.unwrap_or_default())
+        })
+        .join()
+        .await
+        .map_err(io::Error::other)?
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    PluginBuilder::new("archive")
+        .invoke_handler(tauri::generate_handler![
+            create_archive,
+            extract_archive,
+            list_archive
+        ])
+        .build()
+}
@@ -0,0 +1,108 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use sevenz_rust::{
+    lzma::LZMA2Options, AesEncoderOptions, Password, SevenZArchiveEntry, SevenZReader, SevenZWriter,
+};
+
+use std::{fs::File, io::ErrorKind, path::Path};
+
+use crate::{Destination, Entry, Error, Progress, ProgressReader, Result, Source, Tracker};
+
+fn password_of(password: Option<&str>) -> Password {
+    password.map(Password::from).unwrap_or_else(Password::empty)
+}
+
+fn is_corrupted(error: &Error) -> bool {
+    match error {
+        Error::SevenZ(sevenz_rust::Error::ChecksumVerificationFailed) => true,
+        Error::Io(e) => matches!(e.kind(), ErrorKind::InvalidData | ErrorKind::InvalidInput),
+        _ => false,
+    }
+}
+
+pub(crate) fn create(
+    sources: &[Source],
+    archive: &Path,
+    password: Option<&str>,
+    tracker: &mut Tracker,
+) -> Result<()> {
+    let mut writer = SevenZWriter::create(archive)?;
+    if let Some(password) = password {
+        writer.set_content_methods(vec![
+            AesEncoderOptions::new(password.into()).into(),
+            LZMA2Options::default().into(),
+        ]);
+    }
+
+    for source in sources {
+        let entry = SevenZArchiveEntry::from_path(&source.path, source.name.clone());
+        if source.is_dir {
+            writer.push_archive_entry::<File>(entry, None)?;
+        } else {
+            let reader = ProgressReader::new(File::open(&source.path)?, tracker);
+            writer.push_archive_entry(entry, Some(reader))?;
+        }
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+pub(crate) fn extract(
+    archive: &Path,
+    destination: &Destination,
+    password: Option<&str>,
+    on_progress: &mut dyn FnMut(Progress),
+) -> Result<()> {
+    let mut reader = SevenZReader::open(archive, password_of(password))?;
+    let total = reader
+        .archive()
+        .files
+        .iter()
+        .map(|entry| entry.size())
+        .sum();
+    let mut tracker = Tracker::new(total, on_progress);
+
+    // errors of the destination abort the extraction, but can't go through the callback
+    let mut failure = None;
+    let result = reader.for_each_entries(|entry, data| {
+        let result = if entry.is_directory() {
+            destination.create_dir(entry.name())
+        } else {
+            destination.write_file(entry.name(), &mut ProgressReader::new(data, &mut tracker))
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                failure.replace(e);
+                Err(sevenz_rust::Error::other("extraction aborted"))
+            }
+        }
+    });
+
+    let result = match (failure, result) {
+        (Some(e), _) => Err(e),
+        (None, result) => result.map_err(Into::into),
+    };
+    // a wrong key decrypts to garbage that the decoder rejects
+    match result {
+        Err(e) if password.is_some() && is_corrupted(&e) => Err(Error::InvalidPassword),
+        result => result,
+    }
+}
+
+pub(crate) fn list(archive: &Path, password: Option<&str>) -> Result<Vec<Entry>> {
+    let reader = SevenZReader::open(archive, password_of(password))?;
+    Ok(reader
+        .archive()
+        .files
+        .iter()
+        .map(|entry| Entry {
+            name: entry.name().to_string(),
+            is_dir: entry.is_directory(),
+            size: entry.size(),
+        })
+        .collect())
+}
@@ -0,0 +1,115 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use tar::{Archive, Builder, EntryType, Header};
+
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+use crate::{Destination, Entry, Progress, ProgressReader, Result, Source, Tracker};
+
+fn append<W: Write>(
+    builder: &mut Builder<W>,
+    sources: &[Source],
+    tracker: &mut Tracker,
+) -> Result<()> {
+    for source in sources {
+        if source.is_dir {
+            builder.append_dir(&source.name, &source.path)?;
+        } else {
+            let file = File::open(&source.path)?;
+            let mut header = Header::new_gnu();
+            header.set_metadata(&file.metadata()?);
+            builder.append_data(
+                &mut header,
+                &source.name,
+                ProgressReader::new(file, tracker),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn create(
+    sources: &[Source],
+    archive: &Path,
+    gzip: bool,
+    tracker: &mut Tracker,
+) -> Result<()> {
+    let file = File::create(archive)?;
+    if gzip {
+        let mut builder = Builder::new(GzEncoder::new(file, Compression::default()));
+        append(&mut builder, sources, tracker)?;
+        builder.into_inner()?.finish()?;
+    } else {
+        let mut builder = Builder::new(file);
+        append(&mut builder, sources, tracker)?;
+        builder.into_inner()?;
+    }
+    Ok(())
+}
+
+fn open<'a>(reader: impl Read + 'a, gzip: bool) -> Archive<Box<dyn Read + 'a>> {
+    if gzip {
+        Archive::new(Box::new(GzDecoder::new(reader)))
+    } else {
+        Archive::new(Box::new(reader))
+    }
+}
+
+pub(crate) fn extract(
+    archive: &Path,
+    destination: &Destination,
+    gzip: bool,
+    on_progress: &mut dyn FnMut(Progress),
+) -> Result<()> {
+    let file = File::open(archive)?;
+    let mut tracker = Tracker::new(file.metadata()?.len(), on_progress);
+    let mut tar = open(ProgressReader::new(file, &mut tracker), gzip);
+
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        match entry.header().entry_type() {
+            EntryType::Directory => destination.create_dir(&name)?,
+            EntryType::Regular | EntryType::Continuous => {
+                destination.write_file(&name, &mut entry)?
+            }
+            _ => log::warn!(
+                "skipping `{}`, only files and directories are extracted",
+                name
+            ),
+        }
+    }
+
+    // the end of the archive is padding that is never read
+    drop(tar);
+    tracker.finish();
+    Ok(())
+}
+
+pub(crate) fn list(archive: &Path, gzip: bool) -> Result<Vec<Entry>> {
+    let mut tar = open(File::open(archive)?, gzip);
+    let mut entries = Vec::new();
+    for entry in tar.entries()? {
+        let entry = entry?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() || entry_type.is_file() || entry_type == EntryType::Continuous {
+            entries.push(Entry {
+                name: entry
+                    .path()?
+                    .to_string_lossy()
+                    .trim_end_matches('/')
+                    .to_string(),
+                is_dir: entry_type.is_dir(),
+                size: entry.size(),
+            });
+        }
+    }
+    Ok(entries)
+}
@@ -0,0 +1,112 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use time::OffsetDateTime;
+use zip::{
+    unstable::write::FileOptionsExt, write::SimpleFileOptions, CompressionMethod, DateTime,
+    ZipArchive, ZipWriter,
+};
+
+use std::{fs::File, io, path::Path};
+
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+use crate::{Destination, Entry, Progress, ProgressReader, Result, Source, Tracker};
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+pub(crate) fn create(
+    sources: &[Source],
+    archive: &Path,
+    password: Option<&str>,
+    tracker: &mut Tracker,
+) -> Result<()> {
+    let mut writer = ZipWriter::new(File::create(archive)?);
+
+    for source in sources {
+        let metadata = source.path.metadata()?;
+        let mut options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(source.size > u32::MAX as u64);
+        if let Some(modified) = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| DateTime::try_from(OffsetDateTime::from(modified)).ok())
+        {
+            options = options.last_modified_time(modified);
+        }
+        #[cfg(unix)]
+        {
+            options = options.unix_permissions(metadata.permissions().mode());
+        }
+
+        if source.is_dir {
+            writer.add_directory(source.name.as_str(), options)?;
+        } else {
+            if let Some(password) = password {
+                options = options.with_deprecated_encryption(password.as_bytes());
+            }
+            writer.start_file(source.name.as_str(), options)?;
+            io::copy(
+                &mut ProgressReader::new(File::open(&source.path)?, tracker),
+                &mut writer,
+            )?;
+        }
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+pub(crate) fn extract(
+    archive: &Path,
+    destination: &Destination,
+    password: Option<&str>,
+    on_progress: &mut dyn FnMut(Progress),
+) -> Result<()> {
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+
+    let mut total = 0;
+    for i in 0..zip.len() {
+        total += zip.by_index_raw(i)?.size();
+    }
+    let mut tracker = Tracker::new(total, on_progress);
+
+    for i in 0..zip.len() {
+        let mut file = match password {
+            Some(password) => zip.by_index_decrypt(i, password.as_bytes())?,
+            None => zip.by_index(i)?,
+        };
+        let name = file.name().to_string();
+
+        if file
+            .unix_mode()
+            .is_some_and(|mode| mode & S_IFMT == S_IFLNK)
+        {
+            log::warn!("skipping symlink `{}`", name);
+        } else if file.is_dir() {
+            destination.create_dir(&name)?;
+        } else {
+            destination.write_file(&name, &mut ProgressReader::new(&mut file, &mut tracker))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn list(archive: &Path) -> Result<Vec<Entry>> {
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    let mut entries = Vec::with_capacity(zip.len());
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i)?;
+        entries.push(Entry {
+            name: file.name().trim_end_matches('/').to_string(),
+            is_dir: file.is_dir(),
+            size: file.size(),
+        });
+    }
+    Ok(entries)
+}
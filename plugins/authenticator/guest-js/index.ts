@@ -1,5 +1,7 @@
 import { invoke } from "@tauri-apps/api/tauri";
 
+export type { ErrorPayload } from "./types/ErrorPayload";
+
 export class Authenticator {
   async init(): Promise<void> {
     return await invoke("plugin:authenticator|init");
@@ -1,4 +1,5 @@
 use serde::{Serialize, Serializer};
+use tauri_plugin_common::{error, PluginError};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -17,6 +18,19 @@ impl Serialize for Error {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "authenticator";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Base64Decode(..) => "base64_decode",
+            Self::JSON(..) => "json",
+            Self::U2F(..) => "u2f",
+            Self::Auth(..) => "auth",
+        }
     }
 }
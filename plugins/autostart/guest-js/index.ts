@@ -4,6 +4,8 @@
 
 import { invoke } from "@tauri-apps/api/tauri";
 
+export type { ErrorPayload } from "./types/ErrorPayload";
+
 export async function isEnabled(): Promise<boolean> {
   return await invoke("plugin:autostart|is_enabled");
 }
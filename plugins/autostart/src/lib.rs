@@ -9,6 +9,7 @@ use tauri::{
     plugin::{Builder, TauriPlugin},
     Manager, Runtime, State,
 };
+use tauri_plugin_common::{error, PluginError};
 
 use std::env::current_exe;
 
@@ -33,7 +34,32 @@ impl Serialize for Error {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "autostart";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Anyhow(..) => "anyhow",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
     }
 }
 
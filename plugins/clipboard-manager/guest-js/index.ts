@@ -0,0 +1,125 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export type ClipFormat = "text" | "html" | "rtf" | "image" | "files";
+
+export type ClipContent =
+  | { type: "text"; payload: string }
+  | { type: "html"; payload: { html: string; altText: string | null } }
+  | { type: "rtf"; payload: string }
+  /** PNG encoded image. */
+  | { type: "image"; payload: number[] }
+  /** File paths in the platform's native representation (`file://` URIs on Linux). */
+  | { type: "files"; payload: string[] };
+
+export interface HistoryEntry {
+  /** Milliseconds since the Unix epoch at which the change was observed. */
+  timestamp: number;
+  content: ClipContent;
+}
+
+export async function write(content: ClipContent): Promise<void> {
+  await invoke("plugin:clipboard-manager|write", { content });
+}
+
+export async function read(format: ClipFormat): Promise<ClipContent | null> {
+  return await invoke("plugin:clipboard-manager|read", { format });
+}
+
+export async function availableFormats(): Promise<ClipFormat[]> {
+  return await invoke("plugin:clipboard-manager|available_formats");
+}
+
+export async function clear(): Promise<void> {
+  await invoke("plugin:clipboard-manager|clear");
+}
+
+export async function writeText(text: string): Promise<void> {
+  await write({ type: "text", payload: text });
+}
+
+export async function readText(): Promise<string | null> {
+  const content = await read("text");
+  return content?.type === "text" ? content.payload : null;
+}
+
+export async function writeHtml(html: string, altText?: string): Promise<void> {
+  await write({ type: "html", payload: { html, altText: altText ?? null } });
+}
+
+export async function readHtml(): Promise<string | null> {
+  const content = await read("html");
+  return content?.type === "html" ? content.payload.html : null;
+}
+
+export async function writeRtf(rtf: string): Promise<void> {
+  await write({ type: "rtf", payload: rtf });
+}
+
+export async function readRtf(): Promise<string | null> {
+  const content = await read("rtf");
+  return content?.type === "rtf" ? content.payload : null;
+}
+
+/**
+ * Writes an image to the clipboard.
+ *
+ * @param image The encoded image, any format supported by the `image` crate is accepted.
+ */
+export async function writeImage(
+  image: Uint8Array | ArrayBuffer | number[]
+): Promise<void> {
+  const payload = Array.from(
+    image instanceof ArrayBuffer ? new Uint8Array(image) : image
+  );
+  await write({ type: "image", payload });
+}
+
+/** Reads the clipboard image as PNG bytes. */
+export async function readImage(): Promise<Uint8Array | null> {
+  const content = await read("image");
+  return content?.type === "image" ? Uint8Array.from(content.payload) : null;
+}
+
+export async function writeFiles(files: string[]): Promise<void> {
+  await write({ type: "files", payload: files });
+}
+
+export async function readFiles(): Promise<string[] | null> {
+  const content = await read("files");
+  return content?.type === "files" ? content.payload : null;
+}
+
+/**
+ * Returns the clipboard history, most recent first.
+ *
+ * The history is only recorded when it's enabled with `Builder::history_size` on the Rust side.
+ */
+export async function history(): Promise<HistoryEntry[]> {
+  return await invoke("plugin:clipboard-manager|history");
+}
+
+export async function clearHistory(): Promise<void> {
+  await invoke("plugin:clipboard-manager|clear_history");
+}
+
+/**
+ * Listens to clipboard changes.
+ *
+ * Starts monitoring the clipboard if it isn't already,
+ * the monitoring keeps running until `stopMonitoring` is called.
+ */
+export async function onClipboardChange(
+  cb: (entry: HistoryEntry) => void
+): Promise<UnlistenFn> {
+  await invoke("plugin:clipboard-manager|start_monitoring");
+  return await listen<HistoryEntry>("clipboard://changed", (event) => {
+    cb(event.payload);
+  });
+}
+
+export async function stopMonitoring(): Promise<void> {
+  await invoke("plugin:clipboard-manager|stop_monitoring");
+}
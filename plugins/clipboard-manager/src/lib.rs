@@ -0,0 +1,334 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use clipboard_rs::{
+    common::RustImage, Clipboard, ClipboardContent, ClipboardContext, ClipboardHandler,
+    ClipboardWatcher, ClipboardWatcherContext, ContentFormat, RustImageData, WatcherShutdown,
+};
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, RunEvent, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::VecDeque,
+    sync::{mpsc::channel, Arc, Mutex},
+    thread::spawn,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{0}")]
+    Clipboard(String),
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for Error {
+    fn from(error: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        Self::Clipboard(error.to_string())
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "clipboard-manager";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Clipboard(..) => "clipboard",
+        }
+    }
+}
+
+/// A clipboard format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClipFormat {
+    Text,
+    Html,
+    Rtf,
+    Image,
+    Files,
+}
+
+impl ClipFormat {
+    const ALL: [Self; 5] = [Self::Text, Self::Html, Self::Rtf, Self::Image, Self::Files];
+}
+
+impl From<ClipFormat> for ContentFormat {
+    fn from(format: ClipFormat) -> Self {
+        match format {
+            ClipFormat::Text => Self::Text,
+            ClipFormat::Html => Self::Html,
+            ClipFormat::Rtf => Self::Rtf,
+            ClipFormat::Image => Self::Image,
+            ClipFormat::Files => Self::Files,
+        }
+    }
+}
+
+/// Content read from or written to the clipboard.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "camelCase")]
+pub enum ClipContent {
+    Text(String),
+    Html {
+        html: String,
+        /// Plain text written alongside the HTML for applications that can't paste markup.
+        #[serde(rename = "altText")]
+        alt_text: Option<String>,
+    },
+    Rtf(String),
+    /// A PNG encoded image.
+    Image(Vec<u8>),
+    /// File paths, in the platform's native representation (`file://` URIs on Linux).
+    Files(Vec<String>),
+}
+
+impl ClipContent {
+    fn into_contents(self) -> Result<Vec<ClipboardContent>> {
+        let contents = match self {
+            Self::Text(text) => vec![ClipboardContent::Text(text)],
+            Self::Html { html, alt_text } => {
+                let mut contents = vec![ClipboardContent::Html(html)];
+                if let Some(text) = alt_text {
+                    contents.push(ClipboardContent::Text(text));
+                }
+                contents
+            }
+            Self::Rtf(rtf) => vec![ClipboardContent::Rtf(rtf)],
+            Self::Image(bytes) => vec![ClipboardContent::Image(RustImageData::from_bytes(&bytes)?)],
+            Self::Files(files) => vec![ClipboardContent::Files(files)],
+        };
+        Ok(contents)
+    }
+}
+
+fn read_content(ctx: &ClipboardContext, format: ClipFormat) -> Result<Option<ClipContent>> {
+    if !ctx.has(format.into()) {
+        return Ok(None);
+    }
+    let content = match format {
+        ClipFormat::Text => ClipContent::Text(ctx.get_text()?),
+        ClipFormat::Html => ClipContent::Html {
+            html: ctx.get_html()?,
+            alt_text: ctx.get_text().ok(),
+        },
+        ClipFormat::Rtf => ClipContent::Rtf(ctx.get_rich_text()?),
+        ClipFormat::Image => ClipContent::Image(ctx.get_image()?.to_png()?.get_bytes().to_vec()),
+        ClipFormat::Files => ClipContent::Files(ctx.get_files()?),
+    };
+    Ok(Some(content))
+}
+
+/// The richest content currently on the clipboard, used for the history and change events.
+fn read_preferred(ctx: &ClipboardContext) -> Option<ClipContent> {
+    [
+        ClipFormat::Files,
+        ClipFormat::Image,
+        ClipFormat::Html,
+        ClipFormat::Rtf,
+        ClipFormat::Text,
+    ]
+    .into_iter()
+    .find_map(|format| read_content(ctx, format).ok().flatten())
+}
+
+/// An entry of the clipboard history.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    /// Milliseconds since the Unix epoch at which the change was observed.
+    pub timestamp: u64,
+    pub content: ClipContent,
+}
+
+struct ClipboardState {
+    ctx: Mutex<ClipboardContext>,
+    history: Arc<Mutex<VecDeque<HistoryEntry>>>,
+    history_size: usize,
+    monitor: Mutex<Option<WatcherShutdown>>,
+}
+
+struct ChangeHandler<R: Runtime> {
+    app: AppHandle<R>,
+    ctx: ClipboardContext,
+    history: Arc<Mutex<VecDeque<HistoryEntry>>>,
+    history_size: usize,
+}
+
+impl<R: Runtime> ClipboardHandler for ChangeHandler<R> {
+    fn on_clipboard_change(&mut self) {
+        let Some(content) = read_preferred(&self.ctx) else {
+            return;
+        };
+        let entry = HistoryEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            content,
+        };
+
+        if self.history_size > 0 {
+            let mut history = self.history.lock().unwrap();
+            // copying the same content twice shouldn't push it down the history
+            if history.front().map(|e| &e.content) != Some(&entry.content) {
+                history.push_front(entry.clone());
+                history.truncate(self.history_size);
+            }
+        }
+
+        let _ = self.app.emit_all("clipboard://changed", entry);
+    }
+}
+
+fn start_monitor<R: Runtime>(app: &AppHandle<R>, state: &ClipboardState) -> Result<()> {
+    let mut monitor = state.monitor.lock().unwrap();
+    if monitor.is_some() {
+        return Ok(());
+    }
+
+    let handler = ChangeHandler {
+        app: app.clone(),
+        ctx: ClipboardContext::new()?,
+        history: state.history.clone(),
+        history_size: state.history_size,
+    };
+
+    let (tx, rx) = channel();
+    spawn(move || match ClipboardWatcherContext::new() {
+        Ok(mut watcher) => {
+            let _ = tx.send(Ok(watcher.get_shutdown_channel()));
+            watcher.add_handler(handler).start_watch();
+        }
+        Err(e) => {
+            let _ = tx.send(Err(e));
+        }
+    });
+
+    let shutdown = rx.recv().map_err(|e| Error::Clipboard(e.to_string()))??;
+    monitor.replace(shutdown);
+    Ok(())
+}
+
+#[command]
+async fn write(state: State<'_, ClipboardState>, content: ClipContent) -> Result<()> {
+    let contents = content.into_contents()?;
+    state.ctx.lock().unwrap().set(contents).map_err(Into::into)
+}
+
+#[command]
+async fn read(state: State<'_, ClipboardState>, format: ClipFormat) -> Result<Option<ClipContent>> {
+    read_content(&state.ctx.lock().unwrap(), format)
+}
+
+#[command]
+async fn available_formats(state: State<'_, ClipboardState>) -> Result<Vec<ClipFormat>> {
+    let ctx = state.ctx.lock().unwrap();
+    Ok(ClipFormat::ALL
+        .into_iter()
+        .filter(|format| ctx.has((*format).into()))
+        .collect())
+}
+
+#[command]
+async fn clear(state: State<'_, ClipboardState>) -> Result<()> {
+    state.ctx.lock().unwrap().clear().map_err(Into::into)
+}
+
+#[command]
+async fn history(state: State<'_, ClipboardState>) -> Result<Vec<HistoryEntry>> {
+    Ok(state.history.lock().unwrap().iter().cloned().collect())
+}
+
+#[command]
+async fn clear_history(state: State<'_, ClipboardState>) -> Result<()> {
+    state.history.lock().unwrap().clear();
+    Ok(())
+}
+
+#[command]
+async fn start_monitoring<R: Runtime>(
+    app: AppHandle<R>,
+    state: State<'_, ClipboardState>,
+) -> Result<()> {
+    start_monitor(&app, &state)
+}
+
+#[command]
+async fn stop_monitoring(state: State<'_, ClipboardState>) -> Result<()> {
+    if let Some(shutdown) = state.monitor.lock().unwrap().take() {
+        shutdown.stop();
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct Builder {
+    history_size: usize,
+}
+
+impl Builder {
+    /// Keeps the last `size` distinct clipboard contents in memory.
+    ///
+    /// Recording the history requires monitoring the clipboard, which is started with the app.
+    /// A size of `0`, the default, disables the history.
+    pub fn history_size(mut self, size: usize) -> Self {
+        self.history_size = size;
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let history_size = self.history_size;
+
+        PluginBuilder::new("clipboard-manager")
+            .invoke_handler(tauri::generate_handler![
+                write,
+                read,
+                available_formats,
+                clear,
+                history,
+                clear_history,
+                start_monitoring,
+                stop_monitoring
+            ])
+            .setup(move |app| {
+                let state = ClipboardState {
+                    ctx: Mutex::new(ClipboardContext::new().map_err(Error::from)?),
+                    history: Default::default(),
+                    history_size,
+                    monitor: Default::default(),
+                };
+                if history_size > 0 {
+                    start_monitor(app, &state)?;
+                }
+                app.manage(state);
+                Ok(())
+            })
+            .on_event(|app, event| {
+                if let RunEvent::Exit = event {
+                    if let Some(shutdown) =
+                        app.state::<ClipboardState>().monitor.lock().unwrap().take()
+                    {
+                        shutdown.stop();
+                    }
+                }
+            })
+            .build()
+    }
+}
@@ -0,0 +1,22 @@
+import { invoke } from "@tauri-apps/api/tauri";
+
+import type { ErrorPayload } from "./types/ErrorPayload";
+import type { Grant } from "./types/Grant";
+
+export type { ErrorPayload, Grant };
+
+/**
+ * The answers the user gave to the prompts of the pages of this origin.
+ */
+async function grants(): Promise<Grant[]> {
+  return await invoke("plugin:consent|grants");
+}
+
+/**
+ * Forgets the answer for the permission for this origin, the user is asked again the next time.
+ */
+async function revoke(permission: string): Promise<void> {
+  return await invoke("plugin:consent|revoke", { permission });
+}
+
+export { grants, revoke };
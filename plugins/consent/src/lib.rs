@@ -0,0 +1,409 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Asks the user before the privileged operations of the plugins and remembers the answers per origin.
+//!
+//! The plugins call [`check`] before such an operation, it allows everything when this plugin isn't registered.
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    api::dialog::blocking::ask,
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    Manager, Runtime, State, Window,
+};
+use tauri_plugin_common::{error, PluginError};
+use url::Url;
+
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, read_to_string, write},
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+type Result<T> = std::result::Result<T, Error>;
+type PromptHandler<R> = dyn Fn(&Window<R>, &Permission) -> bool + Send + Sync;
+
+const GRANTS_FILE: &str = "consent.json";
+/// Matches every permission in the policies.
+pub const ANY_PERMISSION: &str = "*";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "consent";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Json(..) => "json",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+/// A privileged operation, declared by the plugin gating it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permission {
+    /// Namespaced by the plugin, e.g. `fs-watch:watch`.
+    pub id: &'static str,
+    /// The title of the prompt.
+    pub title: &'static str,
+    /// Completes "Allow the app to …?", e.g. `watch files for changes`.
+    pub description: &'static str,
+}
+
+impl Permission {
+    pub const fn new(id: &'static str, title: &'static str, description: &'static str) -> Self {
+        Self {
+            id,
+            title,
+            description,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Policy {
+    /// Asks the first time and remembers the answer for the origin.
+    Ask,
+    /// Asks every time, nothing is remembered.
+    AskEveryTime,
+    Allow,
+    Deny,
+}
+
+/// A remembered answer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct Grant {
+    pub permission: String,
+    pub origin: String,
+    pub allowed: bool,
+    /// When the user answered, in seconds since the Unix epoch.
+    #[cfg_attr(test, ts(type = "number"))]
+    pub timestamp: u64,
+}
+
+fn default_prompt<R: Runtime>(window: &Window<R>, permission: &Permission) -> bool {
+    ask(
+        Some(window),
+        permission.title,
+        format!("Allow the app to {}?", permission.description),
+    )
+}
+
+/// The policies are looked up by window, then by origin, then by permission, the first one found applies.
+#[derive(Default)]
+struct Policies {
+    windows: HashMap<(String, String), Policy>,
+    origins: HashMap<(String, String), Policy>,
+    permissions: HashMap<String, Policy>,
+    default: Option<Policy>,
+}
+
+impl Policies {
+    fn get(&self, label: &str, origin: &str, permission: &str) -> Policy {
+        let keyed = |map: &HashMap<(String, String), Policy>, key: &str| {
+            map.get(&(key.to_string(), permission.to_string()))
+                .or_else(|| map.get(&(key.to_string(), ANY_PERMISSION.to_string())))
+                .copied()
+        };
+        keyed(&self.windows, label)
+            .or_else(|| keyed(&self.origins, origin))
+            .or_else(|| self.permissions.get(permission).copied())
+            .or(self.default)
+            .unwrap_or(Policy::Ask)
+    }
+}
+
+pub struct Consent<R: Runtime> {
+    path: Option<PathBuf>,
+    policies: Policies,
+    on_prompt: Box<PromptHandler<R>>,
+    /// The origin of the page loaded by each window.
+    origins: Mutex<HashMap<String, String>>,
+    grants: Mutex<Vec<Grant>>,
+    /// Held while a prompt is shown, the concurrent requests are answered by it.
+    prompt: Mutex<()>,
+}
+
+impl<R: Runtime> Consent<R> {
+    /// Whether the window may perform the operation, asking the user when the policy says so.
+    ///
+    /// Blocks while the prompt is shown, it must not be called from the main thread.
+    pub fn request(&self, window: &Window<R>, permission: &Permission) -> bool {
+        let origin = self.origin(window);
+        let policy = self.policies.get(window.label(), &origin, permission.id);
+        match policy {
+            Policy::Allow => return true,
+            Policy::Deny => return false,
+            Policy::AskEveryTime => return (self.on_prompt)(window, permission),
+            Policy::Ask => (),
+        }
+
+        let _prompt = self.prompt.lock().unwrap();
+        if let Some(allowed) = self.granted(permission.id, &origin) {
+            return allowed;
+        }
+        let allowed = (self.on_prompt)(window, permission);
+        self.grants.lock().unwrap().push(Grant {
+            permission: permission.id.into(),
+            origin,
+            allowed,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or_default(),
+        });
+        if let Err(e) = self.save() {
+            log::warn!("failed to save the permissions: {}", e);
+        }
+        allowed
+    }
+
+    /// The remembered answer for the permission and the origin.
+    pub fn granted(&self, permission: &str, origin: &str) -> Option<bool> {
+        self.grants
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|grant| grant.permission == permission && grant.origin == origin)
+            .map(|grant| grant.allowed)
+    }
+
+    pub fn grants(&self) -> Vec<Grant> {
+        self.grants.lock().unwrap().clone()
+    }
+
+    /// Forgets the answers for the permission, for every origin when `origin` is `None`, the user is asked again.
+    pub fn revoke(&self, permission: &str, origin: Option<&str>) -> Result<()> {
+        self.grants.lock().unwrap().retain(|grant| {
+            grant.permission != permission || origin.is_some_and(|origin| grant.origin != origin)
+        });
+        self.save()
+    }
+
+    /// The origin of the page loaded by the window, e.g. `https://tauri.localhost` for the app.
+    pub fn origin(&self, window: &Window<R>) -> String {
+        self.origins
+            .lock()
+            .unwrap()
+            .get(window.label())
+            .cloned()
+            .unwrap_or_else(|| "null".into())
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent)?;
+            }
+            write(path, serde_json::to_vec(&*self.grants.lock().unwrap())?)?;
+        }
+        Ok(())
+    }
+}
+
+/// For the plugins gating an operation: asks through the consent plugin when it's registered, allows otherwise.
+///
+/// Blocks while the prompt is shown, it must not be called from the main thread.
+pub fn check<R: Runtime>(window: &Window<R>, permission: &Permission) -> bool {
+    match window.try_state::<Consent<R>>() {
+        Some(consent) => consent.request(window, permission),
+        None => true,
+    }
+}
+
+/// [`check`] on the blocking thread pool, for the async commands.
+pub async fn check_async<R: Runtime>(window: Window<R>, permission: Permission) -> bool {
+    tauri::async_runtime::spawn_blocking(move || check(&window, &permission))
+        .await
+        .unwrap_or(false)
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn consent(&self) -> State<'_, Consent<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn consent(&self) -> State<'_, Consent<R>> {
+        self.state::<Consent<R>>()
+    }
+}
+
+/// The remembered answers for the origin of the window, the pages can't see the other origins.
+#[command]
+fn grants<R: Runtime>(window: Window<R>) -> Vec<Grant> {
+    let consent = window.consent();
+    let origin = consent.origin(&window);
+    consent
+        .grants()
+        .into_iter()
+        .filter(|grant| grant.origin == origin)
+        .collect()
+}
+
+#[command]
+fn revoke<R: Runtime>(window: Window<R>, permission: String) -> Result<()> {
+    let consent = window.consent();
+    let origin = consent.origin(&window);
+    consent.revoke(&permission, Some(&origin))
+}
+
+pub struct Builder<R: Runtime> {
+    policies: Policies,
+    on_prompt: Box<PromptHandler<R>>,
+    persist: bool,
+}
+
+impl<R: Runtime> Default for Builder<R> {
+    fn default() -> Self {
+        Self {
+            policies: Default::default(),
+            on_prompt: Box::new(default_prompt),
+            persist: true,
+        }
+    }
+}
+
+impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The policy of the permissions without one, defaults to [`Policy::Ask`].
+    pub fn default_policy(mut self, policy: Policy) -> Self {
+        self.policies.default.replace(policy);
+        self
+    }
+
+    pub fn policy(mut self, permission: impl Into<String>, policy: Policy) -> Self {
+        self.policies.permissions.insert(permission.into(), policy);
+        self
+    }
+
+    /// The policy for the pages of an origin, e.g. `https://example.com`.
+    /// [`ANY_PERMISSION`] applies it to all the permissions.
+    pub fn origin_policy(
+        mut self,
+        origin: impl Into<String>,
+        permission: impl Into<String>,
+        policy: Policy,
+    ) -> Self {
+        self.policies
+            .origins
+            .insert((origin.into(), permission.into()), policy);
+        self
+    }
+
+    /// The policy for a window, whatever page it loaded. [`ANY_PERMISSION`] applies it to all the permissions.
+    pub fn window_policy(
+        mut self,
+        label: impl Into<String>,
+        permission: impl Into<String>,
+        policy: Policy,
+    ) -> Self {
+        self.policies
+            .windows
+            .insert((label.into(), permission.into()), policy);
+        self
+    }
+
+    /// Replaces the dialog asking the user, e.g. to show the prompt in the webview.
+    pub fn on_prompt<F: Fn(&Window<R>, &Permission) -> bool + Send + Sync + 'static>(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.on_prompt = Box::new(handler);
+        self
+    }
+
+    /// Keeps the answers in memory only, the user is asked again when the app restarts.
+    pub fn in_memory(mut self) -> Self {
+        self.persist = false;
+        self
+    }
+
+    pub fn build(self) -> TauriPlugin<R> {
+        let Self {
+            policies,
+            on_prompt,
+            persist,
+        } = self;
+
+        PluginBuilder::new("consent")
+            .invoke_handler(tauri::generate_handler![grants, revoke])
+            .setup(move |app| {
+                let path = if persist {
+                    app.path_resolver()
+                        .app_data_dir()
+                        .map(|dir| dir.join(GRANTS_FILE))
+                } else {
+                    None
+                };
+                let grants = match path.as_ref().map(read_to_string) {
+                    Some(Ok(grants)) => serde_json::from_str(&grants)?,
+                    _ => Vec::new(),
+                };
+                app.manage(Consent {
+                    path,
+                    policies,
+                    on_prompt,
+                    origins: Default::default(),
+                    grants: Mutex::new(grants),
+                    prompt: Default::default(),
+                });
+                Ok(())
+            })
+            .on_page_load(|window, payload| {
+                let origin = Url::parse(payload.url())
+                    .map(|url| url.origin().ascii_serialization())
+                    .unwrap_or_else(|_| "null".into());
+                window
+                    .consent()
+                    .origins
+                    .lock()
+                    .unwrap()
+                    .insert(window.label().into(), origin);
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
@@ -0,0 +1,42 @@
+import { invoke } from "@tauri-apps/api/tauri";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface Report {
+  id: string;
+  /** When the app crashed, RFC 3339 in UTC. */
+  timestamp: string;
+  /** The version of the app that crashed. */
+  version: string;
+  os: string;
+  arch: string;
+  minidump: string;
+  /** The end of the log when the app crashed. */
+  log?: string;
+  /** The size of the minidump and the log, in bytes. */
+  size: number;
+}
+
+/** The reports of the past crashes, the oldest first. */
+export async function reports(): Promise<Report[]> {
+  return await invoke("plugin:crash-reporter|reports");
+}
+
+export async function deleteReport(id: string): Promise<void> {
+  return await invoke("plugin:crash-reporter|delete", { id });
+}
+
+/** Whether the reports are sent, `null` until the user was asked. */
+export async function consent(): Promise<boolean | null> {
+  return await invoke("plugin:crash-reporter|consent");
+}
+
+/** Remembers the answer of the user across the launches. */
+export async function setConsent(consent: boolean): Promise<void> {
+  return await invoke("plugin:crash-reporter|set_consent", { consent });
+}
+
+/** Sends the report to the upload URL and deletes it, once the user consented. */
+export async function upload(id: string): Promise<void> {
+  return await invoke("plugin:crash-reporter|upload", { id });
+}
@@ -0,0 +1,450 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Writes minidumps of the native crashes with the end of the log, to upload them on a later launch
+//! once the user consented.
+
+use crash_handler::{make_crash_event, CrashContext, CrashEventResult, CrashHandler};
+use minidumper::Client;
+use reqwest::multipart::{Form, Part};
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    fs::{create_dir_all, read, read_dir, read_to_string, remove_file, write},
+    io,
+    path::PathBuf,
+    process::{Child, Command},
+    sync::Mutex,
+    thread::sleep,
+    time::Duration,
+};
+
+mod monitor;
+
+pub use monitor::monitor;
+use monitor::{Context, CONTEXT_MESSAGE, DIRECTORY_VAR, SOCKET_VAR};
+
+type Result<T> = std::result::Result<T, Error>;
+
+const DIRECTORY: &str = "crashes";
+const SETTINGS_FILE: &str = "crash-reporter.json";
+/// The monitor gets about 2 seconds to start.
+const CONNECT_ATTEMPTS: u32 = 40;
+const CONNECT_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("the app data directory could not be resolved")]
+    NoDirectory,
+    #[error("crash report not found: {0}")]
+    ReportNotFound(String),
+    #[error("the user did not consent to send the crash reports")]
+    NoConsent,
+    #[error("no upload URL was configured")]
+    NoUploadUrl,
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "crash-reporter";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Json(..) => "json",
+            Self::Request(..) => "request",
+            Self::NoDirectory => "no_directory",
+            Self::ReportNotFound(..) => "report_not_found",
+            Self::NoConsent => "no_consent",
+            Self::NoUploadUrl => "no_upload_url",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            Self::Request(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+/// Written by the monitor next to the minidump.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReportInfo {
+    pub(crate) id: String,
+    /// RFC 3339, in UTC.
+    pub(crate) timestamp: String,
+    pub(crate) version: String,
+    pub(crate) os: String,
+    pub(crate) arch: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub id: String,
+    /// When the app crashed, RFC 3339 in UTC.
+    pub timestamp: String,
+    /// The version of the app that crashed.
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub minidump: PathBuf,
+    /// The end of the log when the app crashed.
+    pub log: Option<PathBuf>,
+    /// The size of the minidump and the log, in bytes.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Settings {
+    consent: Option<bool>,
+}
+
+pub struct CrashReporter {
+    directory: Option<PathBuf>,
+    settings_path: Option<PathBuf>,
+    settings: Mutex<Settings>,
+    upload_url: Option<String>,
+    client: reqwest::Client,
+    /// Detaches the crash handler when dropped.
+    _monitor: Option<(CrashHandler, Child)>,
+}
+
+impl CrashReporter {
+    fn directory(&self) -> Result<&PathBuf> {
+        self.directory.as_ref().ok_or(Error::NoDirectory)
+    }
+
+    /// Whether the reports are sent, unknown until the user was asked.
+    pub fn consent(&self) -> Option<bool> {
+        self.settings.lock().unwrap().consent
+    }
+
+    /// Remembers the answer of the user across the launches.
+    pub fn set_consent(&self, consent: bool) -> Result<()> {
+        let mut settings = self.settings.lock().unwrap();
+        settings.consent.replace(consent);
+        let path = self.settings_path.as_ref().ok_or(Error::NoDirectory)?;
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+        write(path, serde_json::to_vec(&*settings)?)?;
+        Ok(())
+    }
+
+    fn report(&self, id: &str) -> Result<Report> {
+        // the ids are file names
+        if uuid::Uuid::parse_str(id).is_err() {
+            return Err(Error::ReportNotFound(id.into()));
+        }
+        let directory = self.directory()?;
+        let info: ReportInfo = match read_to_string(directory.join(id).with_extension("json")) {
+            Ok(info) => serde_json::from_str(&info)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::ReportNotFound(id.into()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let minidump = directory.join(&info.id).with_extension("dmp");
+        let log = Some(directory.join(&info.id).with_extension("log")).filter(|log| log.is_file());
+        let size = std::iter::once(&minidump)
+            .chain(&log)
+            .filter_map(|path| path.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        Ok(Report {
+            id: info.id,
+            timestamp: info.timestamp,
+            version: info.version,
+            os: info.os,
+            arch: info.arch,
+            minidump,
+            log,
+            size,
+        })
+    }
+
+    /// The reports of the past crashes, the oldest first.
+    pub fn reports(&self) -> Result<Vec<Report>> {
+        let directory = self.directory()?;
+        let entries = match read_dir(directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut reports = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == "json"))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .filter_map(|id| self.report(&id).ok())
+            .collect::<Vec<_>>();
+        reports.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        Ok(reports)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        let report = self.report(id)?;
+        for path in std::iter::once(&report.minidump).chain(&report.log) {
+            let _ = remove_file(path);
+        }
+        remove_file(self.directory()?.join(id).with_extension("json"))?;
+        Ok(())
+    }
+
+    /// Sends the report to the upload URL and deletes it, once the user consented.
+    ///
+    /// The minidump is the `upload_file_minidump` part of a multipart form, like Breakpad and Crashpad send them,
+    /// the log is the `log` part and the other fields are text parts.
+    pub async fn upload(&self, id: &str) -> Result<()> {
+        if self.consent() != Some(true) {
+            return Err(Error::NoConsent);
+        }
+        let url = self.upload_url.as_ref().ok_or(Error::NoUploadUrl)?;
+        let report = self.report(id)?;
+
+        let mut form = Form::new()
+            .text("guid", report.id.clone())
+            .text("timestamp", report.timestamp)
+            .text("version", report.version)
+            .text("os", report.os)
+            .text("arch", report.arch)
+            .part(
+                "upload_file_minidump",
+                Part::bytes(read(&report.minidump)?).file_name(format!("{}.dmp", report.id)),
+            );
+        if let Some(log) = &report.log {
+            form = form.part(
+                "log",
+                Part::bytes(read(log)?).file_name(format!("{}.log", report.id)),
+            );
+        }
+        self.client
+            .post(url)
+            .multipart(form)
+            .send()
+            .await?
+            .error_for_status()?;
+        self.delete(id)
+    }
+}
+
+/// Starts the monitor and sends it the crashes.
+fn attach<R: Runtime>(
+    app: &AppHandle<R>,
+    directory: &PathBuf,
+    log_file: Option<PathBuf>,
+) -> Result<(CrashHandler, Child)> {
+    let socket = std::env::temp_dir().join(format!("tauri-crash-{}.sock", std::process::id()));
+    let mut monitor = Command::new(std::env::current_exe()?)
+        .env(SOCKET_VAR, &socket)
+        .env(DIRECTORY_VAR, directory)
+        .spawn()?;
+
+    let mut attempts = 0;
+    let client = loop {
+        match Client::with_name(socket.as_path()) {
+            Ok(client) => break client,
+            Err(e) => {
+                attempts += 1;
+                if attempts == CONNECT_ATTEMPTS {
+                    let _ = monitor.kill();
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, e.to_string()).into());
+                }
+                sleep(CONNECT_INTERVAL);
+            }
+        }
+    };
+    let context = Context {
+        version: app.package_info().version.to_string(),
+        log_file,
+    };
+    let _ = client.send_message(CONTEXT_MESSAGE, serde_json::to_vec(&context)?);
+
+    // SAFETY: the event only asks the monitor to write the minidump, without allocating
+    let handler = CrashHandler::attach(unsafe {
+        make_crash_event(move |context: &CrashContext| {
+            CrashEventResult::Handled(client.request_dump(context).is_ok())
+        })
+    })
+    .map_err(|e| io::Error::other(e.to_string()))?;
+    // the monitor reads the memory of the app, which Yama restricts to the parents otherwise
+    #[cfg(target_os = "linux")]
+    handler.set_ptracer(Some(monitor.id()));
+
+    Ok((handler, monitor))
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn crash_reporter(&self) -> State<'_, CrashReporter>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn crash_reporter(&self) -> State<'_, CrashReporter> {
+        self.state::<CrashReporter>()
+    }
+}
+
+#[command]
+fn reports<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Report>> {
+    app.crash_reporter().reports()
+}
+
+#[command]
+fn delete<R: Runtime>(app: AppHandle<R>, id: String) -> Result<()> {
+    app.crash_reporter().delete(&id)
+}
+
+#[command]
+fn consent<R: Runtime>(app: AppHandle<R>) -> Option<bool> {
+    app.crash_reporter().consent()
+}
+
+#[command]
+fn set_consent<R: Runtime>(app: AppHandle<R>, consent: bool) -> Result<()> {
+    app.crash_reporter().set_consent(consent)
+}
+
+#[command]
+async fn upload<R: Runtime>(app: AppHandle<R>, id: String) -> Result<()> {
+    app.crash_reporter().upload(&id).await
+}
+
+#[derive(Default)]
+pub struct Builder {
+    upload_url: Option<String>,
+    log_file: Option<PathBuf>,
+    auto_upload: bool,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where `upload` sends the reports, e.g. the minidump endpoint of Sentry or BugSplat.
+    pub fn upload_url<U: Into<String>>(mut self, url: U) -> Self {
+        self.upload_url.replace(url.into());
+        self
+    }
+
+    /// The log whose end is kept with the minidumps,
+    /// defaults to the file the log plugin writes in the app log directory.
+    pub fn log_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.log_file.replace(path.into());
+        self
+    }
+
+    /// Uploads the reports of the past crashes when the app starts, once the user consented.
+    pub fn auto_upload(mut self, auto_upload: bool) -> Self {
+        self.auto_upload = auto_upload;
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let Self {
+            upload_url,
+            log_file,
+            auto_upload,
+        } = self;
+
+        PluginBuilder::new("crash-reporter")
+            .invoke_handler(tauri::generate_handler![
+                reports,
+                delete,
+                consent,
+                set_consent,
+                upload
+            ])
+            .setup(move |app| {
+                // `monitor` wasn't called in main, this process mustn't start the app
+                monitor();
+
+                let data_dir = app.path_resolver().app_data_dir();
+                let directory = data_dir.as_ref().map(|dir| dir.join(DIRECTORY));
+                let settings_path = data_dir.as_ref().map(|dir| dir.join(SETTINGS_FILE));
+                let settings = match settings_path.as_ref().map(read_to_string) {
+                    Some(Ok(settings)) => serde_json::from_str(&settings)?,
+                    _ => Settings::default(),
+                };
+                let log_file = log_file.or_else(|| {
+                    app.path_resolver()
+                        .app_log_dir()
+                        .map(|dir| dir.join(format!("{}.log", app.package_info().name)))
+                });
+
+                let monitor = match &directory {
+                    Some(directory) => match attach(app, directory, log_file) {
+                        Ok(monitor) => Some(monitor),
+                        Err(e) => {
+                            log::error!("could not start the crash monitor: {}", e);
+                            None
+                        }
+                    },
+                    None => None,
+                };
+
+                app.manage(CrashReporter {
+                    directory,
+                    settings_path,
+                    settings: Mutex::new(settings),
+                    upload_url,
+                    client: reqwest::Client::new(),
+                    _monitor: monitor,
+                });
+
+                if auto_upload && app.crash_reporter().consent() == Some(true) {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let reporter = app.crash_reporter();
+                        for report in reporter.reports().unwrap_or_default() {
+                            if let Err(e) = reporter.upload(&report.id).await {
+                                log::warn!(
+                                    "could not upload the crash report {}: {}",
+                                    report.id,
+                                    e
+                                );
+                            }
+                        }
+                    });
+                }
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
@@ -0,0 +1,156 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The app runs again as a monitor process, which writes the minidumps when the app crashes:
+//! a crashed process can't be trusted to write them itself.
+
+use minidumper::{LoopAction, MinidumpBinary, Server, ServerHandler};
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use std::{
+    fs::{create_dir_all, write, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, Mutex},
+};
+
+use crate::ReportInfo;
+
+/// The name of the socket, set in the environment of the monitor.
+pub(crate) const SOCKET_VAR: &str = "TAURI_CRASH_REPORTER_SOCKET";
+/// The directory of the reports.
+pub(crate) const DIRECTORY_VAR: &str = "TAURI_CRASH_REPORTER_DIRECTORY";
+/// The kind of the message with the [`Context`].
+pub(crate) const CONTEXT_MESSAGE: u32 = 1;
+/// The end of the log copied next to the minidump.
+const LOG_EXCERPT_LENGTH: u64 = 64 * 1024;
+
+/// Sent by the app once connected, the monitor doesn't run Tauri.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Context {
+    pub(crate) version: String,
+    pub(crate) log_file: Option<PathBuf>,
+}
+
+struct Handler {
+    directory: PathBuf,
+    context: Mutex<Context>,
+    id: Mutex<Option<String>>,
+}
+
+fn log_excerpt(log_file: &Path) -> io::Result<Vec<u8>> {
+    let mut file = File::open(log_file)?;
+    let length = file.metadata()?.len();
+    file.seek(SeekFrom::Start(length.saturating_sub(LOG_EXCERPT_LENGTH)))?;
+    let mut excerpt = Vec::new();
+    file.read_to_end(&mut excerpt)?;
+    // starts at a line
+    if length > LOG_EXCERPT_LENGTH {
+        if let Some(start) = excerpt.iter().position(|b| *b == b'\n') {
+            excerpt.drain(..=start);
+        }
+    }
+    Ok(excerpt)
+}
+
+impl Handler {
+    fn write_report(&self, id: &str) -> io::Result<()> {
+        let context = self.context.lock().unwrap().clone();
+        if let Some(log_file) = &context.log_file {
+            match log_excerpt(log_file) {
+                Ok(excerpt) => write(self.directory.join(id).with_extension("log"), excerpt)?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let info = ReportInfo {
+            id: id.into(),
+            timestamp: OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .unwrap_or_default(),
+            version: context.version,
+            os: std::env::consts::OS.into(),
+            arch: std::env::consts::ARCH.into(),
+        };
+        write(
+            self.directory.join(id).with_extension("json"),
+            serde_json::to_vec(&info)?,
+        )
+    }
+}
+
+impl ServerHandler for Handler {
+    fn create_minidump_file(&self) -> io::Result<(File, PathBuf)> {
+        let id = uuid::Uuid::new_v4().to_string();
+        create_dir_all(&self.directory)?;
+        let path = self.directory.join(&id).with_extension("dmp");
+        self.id.lock().unwrap().replace(id);
+        Ok((File::create(&path)?, path))
+    }
+
+    fn on_minidump_created(&self, result: Result<MinidumpBinary, minidumper::Error>) -> LoopAction {
+        let id = self.id.lock().unwrap().take();
+        match (result, id) {
+            (Ok(mut minidump), Some(id)) => {
+                use std::io::Write;
+                let _ = minidump.file.flush();
+                if let Err(e) = self.write_report(&id) {
+                    eprintln!("could not write the crash report {}: {}", id, e);
+                }
+            }
+            (Err(e), _) => eprintln!("could not write the minidump: {}", e),
+            _ => {}
+        }
+        // the app is gone
+        LoopAction::Exit
+    }
+
+    fn on_message(&self, kind: u32, buffer: Vec<u8>) {
+        if kind == CONTEXT_MESSAGE {
+            if let Ok(context) = serde_json::from_slice(&buffer) {
+                *self.context.lock().unwrap() = context;
+            }
+        }
+    }
+
+    fn on_client_disconnected(&self, clients: usize) -> LoopAction {
+        if clients == 0 {
+            LoopAction::Exit
+        } else {
+            LoopAction::Continue
+        }
+    }
+}
+
+/// Runs the monitor when the process was started as one, until the app exits.
+///
+/// Call it first in `main`, before Tauri starts. The plugin runs it otherwise, only later.
+pub fn monitor() {
+    let (Some(socket), Some(directory)) = (
+        std::env::var_os(SOCKET_VAR),
+        std::env::var_os(DIRECTORY_VAR),
+    ) else {
+        return;
+    };
+
+    let socket = PathBuf::from(socket);
+    let result = Server::with_name(socket.as_path()).and_then(|mut server| {
+        server.run(
+            Box::new(Handler {
+                directory: directory.into(),
+                context: Default::default(),
+                id: Default::default(),
+            }),
+            &AtomicBool::new(false),
+            None,
+        )
+    });
+    if let Err(e) = result {
+        eprintln!("the crash monitor failed: {}", e);
+    }
+    std::process::exit(0);
+}
@@ -0,0 +1,35 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+/**
+ * Registers the app as the handler of `scheme` for the current user.
+ *
+ * Not supported on macOS, where schemes are declared with `CFBundleURLTypes` in the Info.plist.
+ */
+export async function register(scheme: string): Promise<void> {
+  await invoke("plugin:deep-link|register", { scheme });
+}
+
+export async function unregister(scheme: string): Promise<void> {
+  await invoke("plugin:deep-link|unregister", { scheme });
+}
+
+export async function isRegistered(scheme: string): Promise<boolean> {
+  return await invoke("plugin:deep-link|is_registered", { scheme });
+}
+
+/** The URLs the app was last opened with. */
+export async function getCurrent(): Promise<URL[]> {
+  const urls = await invoke<string[]>("plugin:deep-link|get_current");
+  return urls.map((url) => new URL(url));
+}
+
+export async function onOpenUrl(
+  handler: (urls: URL[]) => void
+): Promise<UnlistenFn> {
+  return await listen<string[]>("deep-link://new-url", (event) => {
+    handler(event.payload.map((url) => new URL(url)));
+  });
+}
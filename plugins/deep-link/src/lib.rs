@@ -0,0 +1,268 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use serde::{ser::Serializer, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+use url::Url;
+
+use std::{collections::HashSet, sync::Mutex};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use windows as platform;
+
+type Result<T> = std::result::Result<T, Error>;
+type OpenUrlHandler<R> = dyn Fn(&AppHandle<R>, &[Url]) + Send + Sync;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid URL scheme `{0}`")]
+    InvalidScheme(String),
+    #[error("URL schemes must be declared in the bundle's Info.plist on macOS")]
+    UnsupportedPlatform,
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "deep-link";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::InvalidScheme(..) => "invalid_scheme",
+            Self::UnsupportedPlatform => "unsupported_platform",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+/// Valid schemes start with a letter followed by letters, digits, `+`, `-` or `.`, see RFC 3986.
+fn validate_scheme(scheme: &str) -> Result<()> {
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidScheme(scheme.into()))
+    }
+}
+
+pub struct DeepLink<R: Runtime> {
+    app: AppHandle<R>,
+    schemes: Mutex<HashSet<String>>,
+    current: Mutex<Vec<Url>>,
+    on_open_url: Option<Box<OpenUrlHandler<R>>>,
+}
+
+impl<R: Runtime> DeepLink<R> {
+    /// Registers the app as the handler of `scheme` for the current user.
+    ///
+    /// Not supported on macOS, where schemes are declared with `CFBundleURLTypes` in the Info.plist.
+    pub fn register(&self, scheme: &str) -> Result<()> {
+        validate_scheme(scheme)?;
+        platform::register(&self.app, scheme)?;
+        self.schemes.lock().unwrap().insert(scheme.to_lowercase());
+        Ok(())
+    }
+
+    /// Removes the handler registered with [`Self::register`].
+    pub fn unregister(&self, scheme: &str) -> Result<()> {
+        validate_scheme(scheme)?;
+        platform::unregister(&self.app, scheme)?;
+        self.schemes.lock().unwrap().remove(&scheme.to_lowercase());
+        Ok(())
+    }
+
+    /// Whether the app is the current handler of `scheme`.
+    pub fn is_registered(&self, scheme: &str) -> Result<bool> {
+        validate_scheme(scheme)?;
+        platform::is_registered(&self.app, scheme)
+    }
+
+    /// The URLs the app was last opened with.
+    pub fn get_current(&self) -> Vec<Url> {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Handles the command line arguments of a launch.
+    ///
+    /// On Windows and Linux the URL is passed as an argument to a new process,
+    /// call this from your single instance handler with the arguments forwarded by the second instance.
+    pub fn handle_cli_args<I: IntoIterator<Item = S>, S: AsRef<str>>(&self, args: I) {
+        let schemes = self.schemes.lock().unwrap().clone();
+        let urls = args
+            .into_iter()
+            .filter_map(|arg| Url::parse(arg.as_ref()).ok())
+            .filter(|url| schemes.contains(url.scheme()))
+            .collect::<Vec<_>>();
+        self.handle_urls(urls);
+    }
+
+    fn handle_urls(&self, urls: Vec<Url>) {
+        if urls.is_empty() {
+            return;
+        }
+        if let Some(on_open_url) = &self.on_open_url {
+            on_open_url(&self.app, &urls);
+        }
+        let _ = self.app.emit_all("deep-link://new-url", &urls);
+        *self.current.lock().unwrap() = urls;
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn deep_link(&self) -> State<'_, DeepLink<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn deep_link(&self) -> State<'_, DeepLink<R>> {
+        self.state::<DeepLink<R>>()
+    }
+}
+
+#[command]
+async fn register<R: Runtime>(app: AppHandle<R>, scheme: String) -> Result<()> {
+    app.deep_link().register(&scheme)
+}
+
+#[command]
+async fn unregister<R: Runtime>(app: AppHandle<R>, scheme: String) -> Result<()> {
+    app.deep_link().unregister(&scheme)
+}
+
+#[command]
+async fn is_registered<R: Runtime>(app: AppHandle<R>, scheme: String) -> Result<bool> {
+    app.deep_link().is_registered(&scheme)
+}
+
+#[command]
+async fn get_current<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Url>> {
+    Ok(app.deep_link().get_current())
+}
+
+pub struct Builder<R: Runtime> {
+    schemes: Vec<String>,
+    on_open_url: Option<Box<OpenUrlHandler<R>>>,
+}
+
+impl<R: Runtime> Default for Builder<R> {
+    fn default() -> Self {
+        Self {
+            schemes: Vec::new(),
+            on_open_url: None,
+        }
+    }
+}
+
+impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles the given scheme.
+    ///
+    /// On Windows and Linux the scheme is registered for the current user when the app starts.
+    /// On macOS it must also be declared in the Info.plist for the launches to reach the app.
+    pub fn scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.schemes.push(scheme.into());
+        self
+    }
+
+    /// Called with the parsed URLs whenever the app is opened through one of its schemes.
+    pub fn on_open_url<F: Fn(&AppHandle<R>, &[Url]) + Send + Sync + 'static>(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.on_open_url.replace(Box::new(handler));
+        self
+    }
+
+    pub fn build(self) -> TauriPlugin<R> {
+        let Self {
+            schemes,
+            on_open_url,
+        } = self;
+
+        PluginBuilder::new("deep-link")
+            .invoke_handler(tauri::generate_handler![
+                register,
+                unregister,
+                is_registered,
+                get_current
+            ])
+            .setup(move |app| {
+                let deep_link = DeepLink {
+                    app: app.clone(),
+                    schemes: Default::default(),
+                    current: Default::default(),
+                    on_open_url,
+                };
+
+                for scheme in schemes {
+                    validate_scheme(&scheme)?;
+                    #[cfg(not(target_os = "macos"))]
+                    platform::register(app, &scheme)?;
+                    deep_link
+                        .schemes
+                        .lock()
+                        .unwrap()
+                        .insert(scheme.to_lowercase());
+                }
+
+                deep_link.handle_cli_args(std::env::args().skip(1));
+                app.manage(deep_link);
+
+                #[cfg(target_os = "macos")]
+                {
+                    let app = app.clone();
+                    macos::listen(move |url| {
+                        if let Ok(url) = Url::parse(&url) {
+                            app.deep_link().handle_urls(vec![url]);
+                        }
+                    });
+                }
+
+                Ok(())
+            })
+            .build()
+    }
+}
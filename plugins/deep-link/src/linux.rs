@@ -0,0 +1,81 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use std::{
+    env::current_exe,
+    fs::{create_dir_all, remove_file, write},
+    io::{Error as IoError, ErrorKind},
+    path::PathBuf,
+    process::Command,
+};
+
+use crate::Result;
+
+fn applications_dir() -> Result<PathBuf> {
+    tauri::api::path::data_dir()
+        .map(|dir| dir.join("applications"))
+        .ok_or_else(|| {
+            IoError::new(ErrorKind::NotFound, "could not resolve the data directory").into()
+        })
+}
+
+fn desktop_file_name<R: Runtime>(app: &AppHandle<R>, scheme: &str) -> String {
+    format!(
+        "{}-handler-{}.desktop",
+        app.config().tauri.bundle.identifier,
+        scheme.to_lowercase()
+    )
+}
+
+fn mime_type(scheme: &str) -> String {
+    format!("x-scheme-handler/{}", scheme.to_lowercase())
+}
+
+pub fn register<R: Runtime>(app: &AppHandle<R>, scheme: &str) -> Result<()> {
+    let exe = match app.env().appimage {
+        Some(appimage) => appimage.into(),
+        None => current_exe()?,
+    };
+
+    let dir = applications_dir()?;
+    create_dir_all(&dir)?;
+
+    let file_name = desktop_file_name(app, scheme);
+    write(
+        dir.join(&file_name),
+        format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec=\"{}\" %u\nTerminal=false\nNoDisplay=true\nMimeType={};\n",
+            app.package_info().name,
+            exe.display(),
+            mime_type(scheme)
+        ),
+    )?;
+
+    // the database isn't required by every desktop environment, failing to update it is fine
+    let _ = Command::new("update-desktop-database").arg(&dir).status();
+
+    let status = Command::new("xdg-mime")
+        .args(["default", &file_name, &mime_type(scheme)])
+        .status()?;
+    if !status.success() {
+        return Err(IoError::other(format!("xdg-mime exited with {status}")).into());
+    }
+    Ok(())
+}
+
+pub fn unregister<R: Runtime>(app: &AppHandle<R>, scheme: &str) -> Result<()> {
+    match remove_file(applications_dir()?.join(desktop_file_name(app, scheme))) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+pub fn is_registered<R: Runtime>(app: &AppHandle<R>, scheme: &str) -> Result<bool> {
+    let output = Command::new("xdg-mime")
+        .args(["query", "default", &mime_type(scheme)])
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim() == desktop_file_name(app, scheme))
+}
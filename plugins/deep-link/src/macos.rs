@@ -0,0 +1,86 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use objc::{
+    class,
+    declare::ClassDecl,
+    msg_send,
+    runtime::{Object, Sel},
+    sel, sel_impl,
+};
+use tauri::{AppHandle, Runtime};
+
+use std::{ffi::CStr, os::raw::c_char, sync::OnceLock};
+
+use crate::{Error, Result};
+
+// four character codes from the Apple Events headers
+const K_INTERNET_EVENT_CLASS: u32 = u32::from_be_bytes(*b"GURL");
+const K_AE_GET_URL: u32 = u32::from_be_bytes(*b"GURL");
+const KEY_DIRECT_OBJECT: u32 = u32::from_be_bytes(*b"----");
+
+type UrlHandler = Box<dyn Fn(String) + Send + Sync>;
+
+static HANDLER: OnceLock<UrlHandler> = OnceLock::new();
+
+pub fn register<R: Runtime>(_app: &AppHandle<R>, _scheme: &str) -> Result<()> {
+    Err(Error::UnsupportedPlatform)
+}
+
+pub fn unregister<R: Runtime>(_app: &AppHandle<R>, _scheme: &str) -> Result<()> {
+    Err(Error::UnsupportedPlatform)
+}
+
+pub fn is_registered<R: Runtime>(_app: &AppHandle<R>, _scheme: &str) -> Result<bool> {
+    Err(Error::UnsupportedPlatform)
+}
+
+extern "C" fn handle_get_url(_this: &Object, _cmd: Sel, event: *mut Object, _reply: *mut Object) {
+    unsafe {
+        let descriptor: *mut Object =
+            msg_send![event, paramDescriptorForKeyword: KEY_DIRECT_OBJECT];
+        if descriptor.is_null() {
+            return;
+        }
+        let string: *mut Object = msg_send![descriptor, stringValue];
+        if string.is_null() {
+            return;
+        }
+        let utf8: *const c_char = msg_send![string, UTF8String];
+        if utf8.is_null() {
+            return;
+        }
+        if let Some(handler) = HANDLER.get() {
+            handler(CStr::from_ptr(utf8).to_string_lossy().into_owned());
+        }
+    }
+}
+
+/// Installs the `kAEGetURL` Apple Event handler, URLs are delivered through events on macOS
+/// instead of command line arguments.
+pub fn listen<F: Fn(String) + Send + Sync + 'static>(handler: F) {
+    if HANDLER.set(Box::new(handler)).is_err() {
+        return;
+    }
+
+    unsafe {
+        let mut decl = match ClassDecl::new("TauriPluginDeepLinkHandler", class!(NSObject)) {
+            Some(decl) => decl,
+            None => return,
+        };
+        decl.add_method(
+            sel!(handleGetUrl:withReplyEvent:),
+            handle_get_url as extern "C" fn(&Object, Sel, *mut Object, *mut Object),
+        );
+        let class = decl.register();
+
+        let delegate: *mut Object = msg_send![class, new];
+        let manager: *mut Object = msg_send![class!(NSAppleEventManager), sharedAppleEventManager];
+        let _: () = msg_send![manager,
+            setEventHandler: delegate
+            andSelector: sel!(handleGetUrl:withReplyEvent:)
+            forEventClass: K_INTERNET_EVENT_CLASS
+            andEventID: K_AE_GET_URL];
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use tauri::{AppHandle, Runtime};
+use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+
+use std::{env::current_exe, io::ErrorKind};
+
+use crate::Result;
+
+fn key_path(scheme: &str) -> String {
+    format!("Software\\Classes\\{}", scheme.to_lowercase())
+}
+
+fn command() -> Result<String> {
+    Ok(format!("\"{}\" \"%1\"", current_exe()?.display()))
+}
+
+pub fn register<R: Runtime>(_app: &AppHandle<R>, scheme: &str) -> Result<()> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let exe = current_exe()?;
+
+    let (key, _) = hkcu.create_subkey(key_path(scheme))?;
+    key.set_value("", &format!("URL:{} protocol", scheme))?;
+    key.set_value("URL Protocol", &"")?;
+
+    let (icon, _) = key.create_subkey("DefaultIcon")?;
+    icon.set_value("", &format!("\"{}\",0", exe.display()))?;
+
+    let (command_key, _) = key.create_subkey("shell\\open\\command")?;
+    command_key.set_value("", &command()?)?;
+
+    Ok(())
+}
+
+pub fn unregister<R: Runtime>(_app: &AppHandle<R>, scheme: &str) -> Result<()> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    match hkcu.delete_subkey_all(key_path(scheme)) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+pub fn is_registered<R: Runtime>(_app: &AppHandle<R>, scheme: &str) -> Result<bool> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let registered = hkcu
+        .open_subkey(format!("{}\\shell\\open\\command", key_path(scheme)))
+        .and_then(|key| key.get_value::<String, _>(""))
+        .map(|value| value == command().unwrap_or_default())
+        .unwrap_or(false);
+    Ok(registered)
+}
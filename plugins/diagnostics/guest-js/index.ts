@@ -0,0 +1,35 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export type Health = "ok" | "degraded" | "failing";
+
+export interface Report {
+  health: Health;
+  /** Counters and gauges, e.g. `connections`. */
+  metrics: Record<string, number>;
+  /** Why the plugin isn't healthy. */
+  message?: string;
+}
+
+/**
+ * The reports of the plugins, by name.
+ */
+async function report(): Promise<Record<string, Report>> {
+  return await invoke("plugin:diagnostics|report");
+}
+
+/**
+ * Calls `handler` with the reports emitted every `Builder::interval`.
+ */
+async function onReport(
+  handler: (reports: Record<string, Report>) => void
+): Promise<UnlistenFn> {
+  return await listen<Record<string, Report>>(
+    "diagnostics://report",
+    ({ payload }) => handler(payload)
+  );
+}
+
+export { report, onReport };
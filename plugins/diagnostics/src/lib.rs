@@ -0,0 +1,65 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Exposes the counters and the health the plugins report to `tauri_plugin_common::diagnostics`, for status pages.
+
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime,
+};
+use tauri_plugin_common::{DiagnosticsExt, Report};
+
+use std::{
+    collections::BTreeMap,
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+const REPORT_EVENT: &str = "diagnostics://report";
+
+/// The reports of the plugins, by name.
+#[command]
+fn report<R: Runtime>(app: AppHandle<R>) -> BTreeMap<String, Report> {
+    app.diagnostics().report()
+}
+
+#[derive(Default)]
+pub struct Builder {
+    interval: Option<Duration>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emits the reports to all the windows every `interval`, as the `diagnostics://report` event.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval.replace(interval);
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let interval = self.interval;
+
+        PluginBuilder::new("diagnostics")
+            .invoke_handler(tauri::generate_handler![report])
+            .setup(move |app| {
+                if let Some(interval) = interval {
+                    let app = app.clone();
+                    spawn(move || loop {
+                        sleep(interval);
+                        let _ = app.emit_all(REPORT_EVENT, app.diagnostics().report());
+                    });
+                }
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
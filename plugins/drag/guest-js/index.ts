@@ -0,0 +1,30 @@
+import { invoke } from "@tauri-apps/api/tauri";
+
+import type { DragResult } from "./types/DragResult";
+import type { ErrorPayload } from "./types/ErrorPayload";
+
+export type { DragResult, ErrorPayload };
+
+/**
+ * A file written by the app once it's dropped, through the handler given to `Builder::on_promise`.
+ */
+export interface FilePromise {
+  /** The name of the file, without directories. */
+  name: string;
+  /** Given back to the promise handler, e.g. the id of the document to export. */
+  data?: unknown;
+}
+
+/** An absolute path to an existing file, or a promised file. */
+export type DragItem = string | FilePromise;
+
+/**
+ * Starts a native drag from the current window, call it while the primary mouse button is held, e.g. on `mousedown`.
+ *
+ * Resolves once the items were dropped or the drag was cancelled.
+ */
+async function startDrag(items: DragItem[]): Promise<DragResult> {
+  return await invoke("plugin:drag|start_drag", { items });
+}
+
+export { startDrag };
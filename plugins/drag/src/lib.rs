@@ -0,0 +1,261 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Native drags out of the webview, carrying files to the file manager or other apps.
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    Manager, Runtime, Window,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{mpsc::channel, Arc},
+};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+type Result<T> = std::result::Result<T, Error>;
+type PromiseHandler<R> =
+    dyn Fn(&Window<R>, &FilePromise, &Path) -> std::io::Result<()> + Send + Sync;
+/// Writes a promised file to the given path.
+type Writer = Arc<dyn Fn(&FilePromise, &Path) -> std::io::Result<()> + Send + Sync>;
+type CompletionHandler = Box<dyn FnOnce(DragResult) + Send>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error("nothing to drag")]
+    Empty,
+    #[error("the file doesn't exist: {0}")]
+    FileNotFound(PathBuf),
+    #[error("invalid file name `{0}`")]
+    InvalidFileName(String),
+    #[error("no handler writes the promised files, see `Builder::on_promise`")]
+    NoPromiseHandler,
+    #[error("failed to start the drag: {0}")]
+    Drag(String),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "drag";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Tauri(..) => "tauri",
+            Self::Empty => "empty",
+            Self::FileNotFound(..) => "file_not_found",
+            Self::InvalidFileName(..) => "invalid_file_name",
+            Self::NoPromiseHandler => "no_promise_handler",
+            Self::Drag(..) => "drag",
+        }
+    }
+}
+
+/// A file written by the app once it's dropped.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FilePromise {
+    /// The name of the file, without directories.
+    pub name: String,
+    /// Given back to the promise handler, e.g. the id of the document to export.
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum DragItem {
+    /// An existing file, given as an absolute path.
+    File(PathBuf),
+    Promise(FilePromise),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub enum DragResult {
+    Dropped,
+    Cancelled,
+}
+
+fn validate(items: &[DragItem]) -> Result<()> {
+    if items.is_empty() {
+        return Err(Error::Empty);
+    }
+    for item in items {
+        match item {
+            DragItem::File(path) if !path.exists() => {
+                return Err(Error::FileNotFound(path.clone()))
+            }
+            DragItem::Promise(promise)
+                if Path::new(&promise.name).file_name() != Some(promise.name.as_ref()) =>
+            {
+                return Err(Error::InvalidFileName(promise.name.clone()))
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Writes the promised files to a new temporary directory, the drop targets of Windows and Linux only take paths.
+#[cfg(not(target_os = "macos"))]
+fn materialize(items: &[DragItem], write: &Writer) -> std::io::Result<Vec<PathBuf>> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let mut dir = None;
+    let mut paths = Vec::with_capacity(items.len());
+    for item in items {
+        match item {
+            DragItem::File(path) => paths.push(path.clone()),
+            DragItem::Promise(promise) => {
+                let dir = match &mut dir {
+                    Some(dir) => dir,
+                    None => dir.insert({
+                        let dir = std::env::temp_dir().join(format!(
+                            "tauri-drag-{}-{}",
+                            std::process::id(),
+                            COUNTER.fetch_add(1, Ordering::Relaxed)
+                        ));
+                        std::fs::create_dir_all(&dir)?;
+                        dir
+                    }),
+                };
+                let path = dir.join(&promise.name);
+                write(promise, &path)?;
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+pub trait WindowExt {
+    /// Starts a drag from the window, it should be called while the primary mouse button is held.
+    ///
+    /// The handler is called once the items were dropped or the drag was cancelled.
+    fn start_drag<F: FnOnce(DragResult) + Send + 'static>(
+        &self,
+        items: Vec<DragItem>,
+        on_end: F,
+    ) -> Result<()>;
+}
+
+impl<R: Runtime> WindowExt for Window<R> {
+    fn start_drag<F: FnOnce(DragResult) + Send + 'static>(
+        &self,
+        items: Vec<DragItem>,
+        on_end: F,
+    ) -> Result<()> {
+        validate(&items)?;
+
+        let on_promise = self
+            .try_state::<Drag<R>>()
+            .and_then(|drag| drag.on_promise.clone());
+        let write: Writer = match on_promise {
+            Some(on_promise) => {
+                let window = self.clone();
+                Arc::new(move |promise, path| on_promise(&window, promise, path))
+            }
+            None if items
+                .iter()
+                .any(|item| matches!(item, DragItem::Promise(_))) =>
+            {
+                return Err(Error::NoPromiseHandler)
+            }
+            None => Arc::new(|_, _| Ok(())),
+        };
+
+        platform::start_drag(self, items, write, Box::new(on_end))
+    }
+}
+
+struct Drag<R: Runtime> {
+    on_promise: Option<Arc<PromiseHandler<R>>>,
+}
+
+#[command]
+async fn start_drag<R: Runtime>(window: Window<R>, items: Vec<DragItem>) -> Result<DragResult> {
+    // waits for the main thread, which runs the whole drag on Windows
+    tauri::async_runtime::spawn_blocking(move || {
+        let (tx, rx) = channel();
+        WindowExt::start_drag(&window, items, move |result| {
+            let _ = tx.send(result);
+        })?;
+        Ok(rx.recv().unwrap_or(DragResult::Cancelled))
+    })
+    .await
+    .map_err(|e| Error::Drag(e.to_string()))?
+}
+
+pub struct Builder<R: Runtime> {
+    on_promise: Option<Arc<PromiseHandler<R>>>,
+}
+
+impl<R: Runtime> Default for Builder<R> {
+    fn default() -> Self {
+        Self { on_promise: None }
+    }
+}
+
+impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the promised files, called with the path of the file to create when the drop target asks for it.
+    ///
+    /// On macOS the path is in the directory the files were dropped in, elsewhere it's in a temporary
+    /// directory whose files are then given to the drop target, which may ask for them before the drop.
+    pub fn on_promise<
+        F: Fn(&Window<R>, &FilePromise, &Path) -> std::io::Result<()> + Send + Sync + 'static,
+    >(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.on_promise.replace(Arc::new(handler));
+        self
+    }
+
+    pub fn build(self) -> TauriPlugin<R> {
+        let on_promise = self.on_promise;
+
+        PluginBuilder::new("drag")
+            .invoke_handler(tauri::generate_handler![start_drag])
+            .setup(move |app| {
+                app.manage(Drag { on_promise });
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
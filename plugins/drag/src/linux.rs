@@ -0,0 +1,118 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use gtk::{gdk::DragAction, glib, prelude::*, TargetList};
+use tauri::{Runtime, Window};
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::mpsc::channel,
+};
+
+use crate::{materialize, CompletionHandler, DragItem, DragResult, Error, Result, Writer};
+
+const PRIMARY_BUTTON: i32 = 1;
+
+fn uris(items: &[DragItem], write: &Writer) -> std::io::Result<Vec<String>> {
+    materialize(items, write)?
+        .iter()
+        .map(|path| {
+            glib::filename_to_uri(path, None)
+                .map(Into::into)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))
+        })
+        .collect()
+}
+
+fn begin(
+    widget: &gtk::ApplicationWindow,
+    items: Vec<DragItem>,
+    write: Writer,
+    on_end: CompletionHandler,
+) -> Result<()> {
+    let targets = TargetList::new(&[]);
+    targets.add_uri_targets(0);
+
+    // the promised files are written when the target first asks for the data, usually on drop
+    let data: Rc<RefCell<Option<Vec<String>>>> = Default::default();
+    let data_get = widget.connect_drag_data_get(move |_, _, selection, _, _| {
+        let mut data = data.borrow_mut();
+        if data.is_none() {
+            match uris(&items, &write) {
+                Ok(uris) => {
+                    data.replace(uris);
+                }
+                Err(e) => log::warn!("failed to write the promised files: {}", e),
+            }
+        }
+        if let Some(uris) = &*data {
+            selection.set_uris(&uris.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+    });
+
+    // `drag-failed` is emitted before `drag-end` when the drag is cancelled or refused
+    let failed = Rc::new(Cell::new(false));
+    let failed_ = failed.clone();
+    let drag_failed = widget.connect_drag_failed(move |_, _, _| {
+        failed_.set(true);
+        glib::signal::Inhibit(false)
+    });
+
+    let handlers = Rc::new(RefCell::new(Some((data_get, drag_failed))));
+    let handlers_ = handlers.clone();
+    let on_end = RefCell::new(Some(on_end));
+    let drag_end = widget.connect_drag_end(move |widget, _| {
+        if let Some((data_get, drag_failed)) = handlers_.borrow_mut().take() {
+            widget.disconnect(data_get);
+            widget.disconnect(drag_failed);
+        }
+        if let Some(on_end) = on_end.borrow_mut().take() {
+            on_end(if failed.get() {
+                DragResult::Cancelled
+            } else {
+                DragResult::Dropped
+            });
+        }
+    });
+
+    let event = gtk::current_event();
+    let context = widget.drag_begin_with_coordinates(
+        &targets,
+        DragAction::COPY,
+        PRIMARY_BUTTON,
+        event.as_ref(),
+        -1,
+        -1,
+    );
+    if context.is_none() {
+        if let Some((data_get, drag_failed)) = handlers.borrow_mut().take() {
+            widget.disconnect(data_get);
+            widget.disconnect(drag_failed);
+        }
+        widget.disconnect(drag_end);
+        return Err(Error::Drag("GTK refused to start the drag".into()));
+    }
+    Ok(())
+}
+
+pub(crate) fn start_drag<R: Runtime>(
+    window: &Window<R>,
+    items: Vec<DragItem>,
+    write: Writer,
+    on_end: CompletionHandler,
+) -> Result<()> {
+    let (tx, rx) = channel();
+    let window_ = window.clone();
+    // GTK must be called from the main thread
+    window.run_on_main_thread(move || {
+        let result = window_
+            .gtk_window()
+            .map_err(Into::into)
+            .and_then(|widget| begin(&widget, items, write, on_end));
+        let _ = tx.send(result);
+    })?;
+    rx.recv()
+        .map_err(|_| Error::Drag("the event loop is closed".into()))?
+}
@@ -0,0 +1,293 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use block::Block;
+use objc::{
+    class,
+    declare::ClassDecl,
+    msg_send,
+    runtime::{Class, Object, Sel, BOOL, NO},
+    sel, sel_impl, Encode, Encoding,
+};
+use tauri::{Runtime, Window};
+
+use std::{
+    ffi::c_void,
+    os::raw::c_char,
+    path::PathBuf,
+    sync::{mpsc::channel, Mutex},
+};
+
+use crate::{CompletionHandler, DragItem, DragResult, Error, Result, Writer};
+
+type Id = *mut Object;
+
+const SOURCE_CLASS: &str = "TauriPluginDragSource";
+const NS_UTF8_STRING_ENCODING: usize = 4;
+const NS_DRAG_OPERATION_NONE: usize = 0;
+const NS_DRAG_OPERATION_COPY: usize = 1;
+const ICON_SIZE: f64 = 32.0;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct NSPoint {
+    x: f64,
+    y: f64,
+}
+
+unsafe impl Encode for NSPoint {
+    fn encode() -> Encoding {
+        unsafe { Encoding::from_str("{CGPoint=dd}") }
+    }
+}
+
+#[repr(C)]
+struct NSRect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+unsafe impl Encode for NSRect {
+    fn encode() -> Encoding {
+        unsafe { Encoding::from_str("{CGRect={CGPoint=dd}{CGSize=dd}}") }
+    }
+}
+
+/// Owned by the source object, freed when it's deallocated.
+struct State {
+    items: Vec<DragItem>,
+    write: Writer,
+    on_end: Mutex<Option<CompletionHandler>>,
+}
+
+unsafe fn ns_string(s: &str) -> Id {
+    let string: Id = msg_send![class!(NSString), alloc];
+    let string: Id = msg_send![string,
+        initWithBytes: s.as_ptr() as *const c_void
+        length: s.len()
+        encoding: NS_UTF8_STRING_ENCODING];
+    msg_send![string, autorelease]
+}
+
+unsafe fn string(string: Id) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+    let utf8: *const c_char = msg_send![string, UTF8String];
+    (!utf8.is_null()).then(|| {
+        std::ffi::CStr::from_ptr(utf8)
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+unsafe fn state(this: &Object) -> &State {
+    &*(*this.get_ivar::<usize>("state") as *const State)
+}
+
+/// The providers keep `[source, index]` as their user info, which keeps the source alive until the files are written.
+unsafe fn promise_index(provider: Id) -> usize {
+    let info: Id = msg_send![provider, userInfo];
+    let index: Id = msg_send![info, objectAtIndex: 1usize];
+    msg_send![index, unsignedIntegerValue]
+}
+
+extern "C" fn operation_mask(_this: &Object, _cmd: Sel, _session: Id, _context: isize) -> usize {
+    NS_DRAG_OPERATION_COPY
+}
+
+extern "C" fn ended(this: &Object, _cmd: Sel, _session: Id, _point: NSPoint, operation: usize) {
+    unsafe {
+        if let Some(on_end) = state(this).on_end.lock().unwrap().take() {
+            on_end(if operation == NS_DRAG_OPERATION_NONE {
+                DragResult::Cancelled
+            } else {
+                DragResult::Dropped
+            });
+        }
+        // balances `new`
+        let _: () = msg_send![this, release];
+    }
+}
+
+extern "C" fn file_name(this: &Object, _cmd: Sel, provider: Id, _file_type: Id) -> Id {
+    unsafe {
+        match state(this).items.get(promise_index(provider)) {
+            Some(DragItem::Promise(promise)) => ns_string(&promise.name),
+            _ => ns_string("untitled"),
+        }
+    }
+}
+
+extern "C" fn write_promise(this: &Object, _cmd: Sel, provider: Id, url: Id, handler: Id) {
+    unsafe {
+        let state = state(this);
+        let path: Id = msg_send![url, path];
+        let result = match (state.items.get(promise_index(provider)), string(path)) {
+            (Some(DragItem::Promise(promise)), Some(path)) => {
+                (state.write)(promise, &PathBuf::from(path)).map_err(|e| e.to_string())
+            }
+            _ => Err("unknown file promise".to_string()),
+        };
+
+        let error: Id = match result {
+            Ok(()) => std::ptr::null_mut(),
+            Err(e) => {
+                log::warn!("failed to write the promised file: {}", e);
+                let info: Id = msg_send![class!(NSDictionary),
+                    dictionaryWithObject: ns_string(&e)
+                    forKey: ns_string("NSLocalizedDescription")];
+                msg_send![class!(NSError),
+                    errorWithDomain: ns_string("tauri-plugin-drag")
+                    code: 1isize
+                    userInfo: info]
+            }
+        };
+        let handler = &*(handler as *const Block<(Id,), ()>);
+        handler.call((error,));
+    }
+}
+
+extern "C" fn dealloc(this: &Object, _cmd: Sel) {
+    unsafe {
+        drop(Box::from_raw(*this.get_ivar::<usize>("state") as *mut State));
+        let _: () = msg_send![super(this, class!(NSObject)), dealloc];
+    }
+}
+
+fn source_class() -> &'static Class {
+    if let Some(class) = Class::get(SOURCE_CLASS) {
+        return class;
+    }
+
+    let mut decl = ClassDecl::new(SOURCE_CLASS, class!(NSObject)).unwrap();
+    decl.add_ivar::<usize>("state");
+    unsafe {
+        decl.add_method(
+            sel!(draggingSession:sourceOperationMaskForDraggingContext:),
+            operation_mask as extern "C" fn(&Object, Sel, Id, isize) -> usize,
+        );
+        decl.add_method(
+            sel!(draggingSession:endedAtPoint:operation:),
+            ended as extern "C" fn(&Object, Sel, Id, NSPoint, usize),
+        );
+        decl.add_method(
+            sel!(filePromiseProvider:fileNameForType:),
+            file_name as extern "C" fn(&Object, Sel, Id, Id) -> Id,
+        );
+        decl.add_method(
+            sel!(filePromiseProvider:writePromiseToURL:completionHandler:),
+            write_promise as extern "C" fn(&Object, Sel, Id, Id, Id),
+        );
+        decl.add_method(sel!(dealloc), dealloc as extern "C" fn(&Object, Sel));
+    }
+    decl.register()
+}
+
+/// A file URL for the existing files, a file promise provider for the others.
+unsafe fn pasteboard_writer(source: Id, index: usize, item: &DragItem) -> (Id, Id) {
+    let workspace: Id = msg_send![class!(NSWorkspace), sharedWorkspace];
+    match item {
+        DragItem::File(path) => {
+            let path = ns_string(&path.to_string_lossy());
+            let url: Id = msg_send![class!(NSURL), fileURLWithPath: path];
+            let icon: Id = msg_send![workspace, iconForFile: path];
+            (url, icon)
+        }
+        DragItem::Promise(promise) => {
+            let info: Id = msg_send![class!(NSMutableArray), array];
+            let _: () = msg_send![info, addObject: source];
+            let number: Id = msg_send![class!(NSNumber), numberWithUnsignedInteger: index];
+            let _: () = msg_send![info, addObject: number];
+
+            let provider: Id = msg_send![class!(NSFilePromiseProvider), alloc];
+            let provider: Id = msg_send![provider,
+                initWithFileType: ns_string("public.data")
+                delegate: source];
+            let _: () = msg_send![provider, setUserInfo: info];
+            let provider: Id = msg_send![provider, autorelease];
+
+            let extension = std::path::Path::new(&promise.name)
+                .extension()
+                .map(|extension| extension.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let icon: Id = msg_send![workspace, iconForFileType: ns_string(&extension)];
+            (provider, icon)
+        }
+    }
+}
+
+unsafe fn begin(
+    ns_window: Id,
+    items: Vec<DragItem>,
+    write: Writer,
+    on_end: CompletionHandler,
+) -> Result<()> {
+    let app: Id = msg_send![class!(NSApplication), sharedApplication];
+    let event: Id = msg_send![app, currentEvent];
+    if event.is_null() {
+        return Err(Error::Drag("no mouse event to start the drag from".into()));
+    }
+    let view: Id = msg_send![ns_window, contentView];
+    let location: NSPoint = msg_send![event, locationInWindow];
+    let location: NSPoint =
+        msg_send![view, convertPoint: location fromView: std::ptr::null_mut::<Object>()];
+
+    let count = items.len();
+    let boxed = Box::new(State {
+        items,
+        write,
+        on_end: Mutex::new(Some(on_end)),
+    });
+    let source: Id = msg_send![source_class(), new];
+    (*source).set_ivar::<usize>("state", Box::into_raw(boxed) as usize);
+
+    let dragging_items: Id = msg_send![class!(NSMutableArray), array];
+    for index in 0..count {
+        let item = &state(&*source).items[index];
+        let (writer, icon) = pasteboard_writer(source, index, item);
+        let dragging_item: Id = msg_send![class!(NSDraggingItem), alloc];
+        let dragging_item: Id = msg_send![dragging_item, initWithPasteboardWriter: writer];
+        // stacked under the pointer
+        let offset = index as f64 * 4.0;
+        let frame = NSRect {
+            x: location.x - ICON_SIZE / 2.0 + offset,
+            y: location.y - ICON_SIZE / 2.0 - offset,
+            width: ICON_SIZE,
+            height: ICON_SIZE,
+        };
+        let _: () = msg_send![dragging_item, setDraggingFrame: frame contents: icon];
+        let _: () = msg_send![dragging_items, addObject: dragging_item];
+        let _: () = msg_send![dragging_item, release];
+    }
+
+    let _: Id = msg_send![view,
+        beginDraggingSessionWithItems: dragging_items
+        event: event
+        source: source];
+    Ok(())
+}
+
+pub(crate) fn start_drag<R: Runtime>(
+    window: &Window<R>,
+    items: Vec<DragItem>,
+    write: Writer,
+    on_end: CompletionHandler,
+) -> Result<()> {
+    let ns_window = window.ns_window()? as usize;
+    // AppKit must be called from the main thread
+    let is_main_thread: BOOL = unsafe { msg_send![class!(NSThread), isMainThread] };
+    if is_main_thread != NO {
+        return unsafe { begin(ns_window as Id, items, write, on_end) };
+    }
+
+    let (tx, rx) = channel();
+    window.run_on_main_thread(move || {
+        let _ = tx.send(unsafe { begin(ns_window as Id, items, write, on_end) });
+    })?;
+    rx.recv()
+        .map_err(|_| Error::Drag("the event loop is closed".into()))?
+}
@@ -0,0 +1,249 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use tauri::{Runtime, Window};
+use windows::{
+    core::{implement, Result as WinResult, HRESULT},
+    Win32::{
+        Foundation::{
+            BOOL, DRAGDROP_S_CANCEL, DRAGDROP_S_DROP, DRAGDROP_S_USEDEFAULTCURSORS, DV_E_FORMATETC,
+            E_FAIL, E_NOTIMPL, E_OUTOFMEMORY, OLE_E_ADVISENOTSUPPORTED, POINT,
+        },
+        System::{
+            Com::{
+                IAdviseSink, IDataObject, IDataObject_Impl, IEnumFORMATETC, IEnumSTATDATA,
+                DATADIR_GET, DVASPECT_CONTENT, FORMATETC, STGMEDIUM, STGMEDIUM_0, TYMED_HGLOBAL,
+            },
+            Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GHND},
+            Ole::{
+                DoDragDrop, IDropSource, IDropSource_Impl, OleInitialize, DROPEFFECT,
+                DROPEFFECT_COPY, DROPEFFECT_NONE,
+            },
+            SystemServices::CF_HDROP,
+        },
+        UI::{
+            Shell::{SHCreateStdEnumFmtEtc, DROPFILES},
+            WindowsAndMessaging::MK_LBUTTON,
+        },
+    },
+};
+
+use std::{
+    os::windows::ffi::OsStrExt,
+    path::PathBuf,
+    sync::{mpsc::channel, Mutex},
+};
+
+use crate::{materialize, CompletionHandler, DragItem, DragResult, Error, Result, Writer};
+
+fn hdrop_format() -> FORMATETC {
+    FORMATETC {
+        cfFormat: CF_HDROP.0 as u16,
+        ptd: std::ptr::null_mut(),
+        dwAspect: DVASPECT_CONTENT.0 as u32,
+        lindex: -1,
+        tymed: TYMED_HGLOBAL.0 as u32,
+    }
+}
+
+/// A `DROPFILES` structure followed by the paths, each terminated by a nul and the list by another one.
+fn hdrop(paths: &[PathBuf]) -> WinResult<isize> {
+    let mut files: Vec<u16> = Vec::new();
+    for path in paths {
+        files.extend(path.as_os_str().encode_wide());
+        files.push(0);
+    }
+    files.push(0);
+
+    let header = std::mem::size_of::<DROPFILES>();
+    let size = header + files.len() * std::mem::size_of::<u16>();
+    unsafe {
+        let global = GlobalAlloc(GHND, size);
+        if global == 0 {
+            return Err(E_OUTOFMEMORY.into());
+        }
+        let ptr = GlobalLock(global) as *mut u8;
+        if ptr.is_null() {
+            GlobalFree(global);
+            return Err(E_OUTOFMEMORY.into());
+        }
+        ptr.cast::<DROPFILES>().write_unaligned(DROPFILES {
+            pFiles: header as u32,
+            pt: POINT::default(),
+            fNC: BOOL(0),
+            fWide: BOOL(1),
+        });
+        std::ptr::copy_nonoverlapping(files.as_ptr(), ptr.add(header).cast(), files.len());
+        GlobalUnlock(global);
+        Ok(global)
+    }
+}
+
+/// Only offers `CF_HDROP`, rendered when a target first asks for it so the promised files are written lazily.
+#[implement(IDataObject)]
+struct DataObject {
+    items: Vec<DragItem>,
+    write: Writer,
+    paths: Mutex<Option<Vec<PathBuf>>>,
+}
+
+impl DataObject {
+    fn accepts(format: &FORMATETC) -> bool {
+        format.cfFormat == CF_HDROP.0 as u16
+            && format.tymed & TYMED_HGLOBAL.0 as u32 != 0
+            && format.dwAspect == DVASPECT_CONTENT.0 as u32
+    }
+}
+
+impl IDataObject_Impl for DataObject {
+    fn GetData(&self, format: *const FORMATETC) -> WinResult<STGMEDIUM> {
+        if !unsafe { format.as_ref() }.is_some_and(Self::accepts) {
+            return Err(DV_E_FORMATETC.into());
+        }
+        let mut paths = self.paths.lock().unwrap();
+        if paths.is_none() {
+            match materialize(&self.items, &self.write) {
+                Ok(written) => {
+                    paths.replace(written);
+                }
+                Err(e) => {
+                    log::warn!("failed to write the promised files: {}", e);
+                    return Err(E_FAIL.into());
+                }
+            }
+        }
+        Ok(STGMEDIUM {
+            tymed: TYMED_HGLOBAL.0 as u32,
+            Anonymous: STGMEDIUM_0 {
+                hGlobal: hdrop(paths.as_deref().unwrap_or_default())?,
+            },
+            pUnkForRelease: None,
+        })
+    }
+
+    fn GetDataHere(&self, _format: *const FORMATETC, _medium: *mut STGMEDIUM) -> WinResult<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn QueryGetData(&self, format: *const FORMATETC) -> WinResult<()> {
+        if unsafe { format.as_ref() }.is_some_and(Self::accepts) {
+            Ok(())
+        } else {
+            Err(DV_E_FORMATETC.into())
+        }
+    }
+
+    fn GetCanonicalFormatEtc(&self, _format: *const FORMATETC, out: *mut FORMATETC) -> HRESULT {
+        if let Some(out) = unsafe { out.as_mut() } {
+            out.ptd = std::ptr::null_mut();
+        }
+        E_NOTIMPL
+    }
+
+    fn SetData(
+        &self,
+        _format: *const FORMATETC,
+        _medium: *const STGMEDIUM,
+        _release: BOOL,
+    ) -> WinResult<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn EnumFormatEtc(&self, direction: u32) -> WinResult<IEnumFORMATETC> {
+        if direction == DATADIR_GET.0 as u32 {
+            unsafe { SHCreateStdEnumFmtEtc(&[hdrop_format()]) }
+        } else {
+            Err(E_NOTIMPL.into())
+        }
+    }
+
+    fn DAdvise(
+        &self,
+        _format: *const FORMATETC,
+        _advf: u32,
+        _sink: &Option<IAdviseSink>,
+    ) -> WinResult<u32> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+
+    fn DUnadvise(&self, _connection: u32) -> WinResult<()> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+
+    fn EnumDAdvise(&self) -> WinResult<IEnumSTATDATA> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+}
+
+#[implement(IDropSource)]
+struct DropSource;
+
+impl IDropSource_Impl for DropSource {
+    // the success codes other than `S_OK` go through the error of the bindings
+    fn QueryContinueDrag(&self, escape_pressed: BOOL, key_state: u32) -> WinResult<()> {
+        if escape_pressed.as_bool() {
+            Err(DRAGDROP_S_CANCEL.into())
+        } else if key_state & MK_LBUTTON == 0 {
+            Err(DRAGDROP_S_DROP.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn GiveFeedback(&self, _effect: u32) -> WinResult<()> {
+        Err(DRAGDROP_S_USEDEFAULTCURSORS.into())
+    }
+}
+
+/// Runs the modal loop of the drag, until the items are dropped or the drag is cancelled.
+fn run(items: Vec<DragItem>, write: Writer) -> Result<DragResult> {
+    // already initialized on the thread of the windows accepting file drops, it's reference counted
+    let _ = unsafe { OleInitialize(std::ptr::null_mut()) };
+
+    let data: IDataObject = DataObject {
+        items,
+        write,
+        paths: Default::default(),
+    }
+    .into();
+    let source: IDropSource = DropSource.into();
+    let mut effect = DROPEFFECT::default();
+    let result = unsafe { DoDragDrop(&data, &source, DROPEFFECT_COPY, &mut effect) };
+    if result == DRAGDROP_S_DROP {
+        Ok(if effect == DROPEFFECT_NONE {
+            DragResult::Cancelled
+        } else {
+            DragResult::Dropped
+        })
+    } else if result == DRAGDROP_S_CANCEL {
+        Ok(DragResult::Cancelled)
+    } else {
+        Err(Error::Drag(
+            windows::core::Error::from(result)
+                .message()
+                .to_string_lossy(),
+        ))
+    }
+}
+
+pub(crate) fn start_drag<R: Runtime>(
+    window: &Window<R>,
+    items: Vec<DragItem>,
+    write: Writer,
+    on_end: CompletionHandler,
+) -> Result<()> {
+    // the drag loop must run on the thread of the windows, the result is only known once it ended
+    let (tx, rx) = channel();
+    window.run_on_main_thread(move || match run(items, write) {
+        Ok(result) => {
+            let _ = tx.send(Ok(()));
+            on_end(result);
+        }
+        Err(e) => {
+            let _ = tx.send(Err(e));
+        }
+    })?;
+    rx.recv()
+        .map_err(|_| Error::Drag("the event loop is closed".into()))?
+}
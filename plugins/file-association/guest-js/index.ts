@@ -0,0 +1,53 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface Association {
+  /** The file extension, e.g. `md`. */
+  extension: string;
+  /**
+   * The MIME type of the files, used on Linux.
+   * Defaults to `application/x-<extension>`.
+   */
+  mimeType?: string;
+  /** The name of the file type shown by the file manager. */
+  description?: string;
+}
+
+/**
+ * Registers the app as a handler of the file type for the current user.
+ * Not supported on macOS, where file types are declared in the Info.plist.
+ */
+export async function register(association: Association): Promise<void> {
+  await invoke("plugin:file-association|register", { association });
+}
+
+export async function unregister(extension: string): Promise<void> {
+  await invoke("plugin:file-association|unregister", { extension });
+}
+
+export async function isDefault(extension: string): Promise<boolean> {
+  return await invoke("plugin:file-association|is_default", { extension });
+}
+
+/**
+ * Makes the app the default handler of the file type.
+ * On Windows this doesn't override the choice of the user.
+ */
+export async function setDefault(extension: string): Promise<void> {
+  await invoke("plugin:file-association|set_default", { extension });
+}
+
+/** The files the app was last opened with. */
+export async function getCurrent(): Promise<string[]> {
+  return await invoke("plugin:file-association|get_current");
+}
+
+export async function onOpenFiles(
+  handler: (paths: string[]) => void
+): Promise<UnlistenFn> {
+  return await listen<string[]>("file-association://opened", (event) =>
+    handler(event.payload)
+  );
+}
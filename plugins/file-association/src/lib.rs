@@ -0,0 +1,341 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+type Result<T> = std::result::Result<T, Error>;
+type OpenFilesHandler<R> = dyn Fn(&AppHandle<R>, &[PathBuf]) + Send + Sync;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid file extension `{0}`")]
+    InvalidExtension(String),
+    #[error("file types must be declared with `CFBundleDocumentTypes` in the bundle's Info.plist on macOS")]
+    UnsupportedPlatform,
+    #[error("failed to change the default handler of `{0}`")]
+    SetDefaultFailed(String),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "file-association";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::InvalidExtension(..) => "invalid_extension",
+            Self::UnsupportedPlatform => "unsupported_platform",
+            Self::SetDefaultFailed(..) => "set_default_failed",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+/// Extensions are given without the leading dot and may only contain letters, digits, `-` and `_`.
+fn normalize_extension(extension: &str) -> Result<String> {
+    let extension = extension.strip_prefix('.').unwrap_or(extension);
+    let valid = !extension.is_empty()
+        && extension
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if valid {
+        Ok(extension.to_lowercase())
+    } else {
+        Err(Error::InvalidExtension(extension.into()))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Association {
+    /// The file extension, e.g. `md`.
+    pub extension: String,
+    /// The MIME type of the files, used on Linux. Defaults to `application/x-<extension>`,
+    /// which is then declared to the shared MIME database.
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    /// The name of the file type shown by the file manager, e.g. `Markdown document`.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl Association {
+    pub fn new(extension: impl Into<String>) -> Self {
+        Self {
+            extension: extension.into(),
+            mime_type: None,
+            description: None,
+        }
+    }
+
+    pub fn mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type.replace(mime_type.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description.replace(description.into());
+        self
+    }
+
+    fn normalized(mut self) -> Result<Self> {
+        self.extension = normalize_extension(&self.extension)?;
+        Ok(self)
+    }
+}
+
+pub struct FileAssociations<R: Runtime> {
+    app: AppHandle<R>,
+    associations: Mutex<HashMap<String, Association>>,
+    current: Mutex<Vec<PathBuf>>,
+    on_open_files: Option<Box<OpenFilesHandler<R>>>,
+}
+
+impl<R: Runtime> FileAssociations<R> {
+    /// Registers the app as a handler of the file type for the current user, it then shows up in the
+    /// "Open with" menus of the file manager.
+    ///
+    /// Not supported on macOS, where file types are declared with `CFBundleDocumentTypes` in the Info.plist.
+    pub fn register(&self, association: Association) -> Result<()> {
+        let association = association.normalized()?;
+        platform::register(&self.app, &association)?;
+        self.associations
+            .lock()
+            .unwrap()
+            .insert(association.extension.clone(), association);
+        Ok(())
+    }
+
+    /// Removes the handler registered with [`Self::register`].
+    pub fn unregister(&self, extension: &str) -> Result<()> {
+        let extension = normalize_extension(extension)?;
+        platform::unregister(&self.app, &extension)?;
+        self.associations.lock().unwrap().remove(&extension);
+        Ok(())
+    }
+
+    /// Whether the app is the default handler of the file type.
+    pub fn is_default(&self, extension: &str) -> Result<bool> {
+        platform::is_default(&self.app, &normalize_extension(extension)?)
+    }
+
+    /// Makes the app the default handler of a registered or declared file type.
+    ///
+    /// Windows doesn't let apps change the user's choice, the app only becomes the default when
+    /// the user didn't pick another one.
+    pub fn set_default(&self, extension: &str) -> Result<()> {
+        platform::set_default(&self.app, &normalize_extension(extension)?)
+    }
+
+    /// The files the app was last opened with.
+    pub fn get_current(&self) -> Vec<PathBuf> {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Handles the command line arguments of a launch.
+    ///
+    /// On Windows and Linux the files are passed as arguments to a new process,
+    /// call this from your single instance handler with the arguments forwarded by the second instance.
+    /// `cwd` resolves relative paths, pass the working directory of the second instance.
+    pub fn handle_cli_args<I: IntoIterator<Item = S>, S: AsRef<str>>(&self, args: I, cwd: &Path) {
+        let associations = self.associations.lock().unwrap();
+        let files = args
+            .into_iter()
+            .map(|arg| cwd.join(arg.as_ref()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|e| associations.contains_key(&e.to_lowercase()))
+            })
+            .collect::<Vec<_>>();
+        drop(associations);
+        self.handle_files(files);
+    }
+
+    fn handle_files(&self, files: Vec<PathBuf>) {
+        if files.is_empty() {
+            return;
+        }
+        if let Some(on_open_files) = &self.on_open_files {
+            on_open_files(&self.app, &files);
+        }
+        let _ = self.app.emit_all("file-association://opened", &files);
+        *self.current.lock().unwrap() = files;
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn file_associations(&self) -> State<'_, FileAssociations<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn file_associations(&self) -> State<'_, FileAssociations<R>> {
+        self.state::<FileAssociations<R>>()
+    }
+}
+
+#[command]
+async fn register<R: Runtime>(app: AppHandle<R>, association: Association) -> Result<()> {
+    app.file_associations().register(association)
+}
+
+#[command]
+async fn unregister<R: Runtime>(app: AppHandle<R>, extension: String) -> Result<()> {
+    app.file_associations().unregister(&extension)
+}
+
+#[command]
+async fn is_default<R: Runtime>(app: AppHandle<R>, extension: String) -> Result<bool> {
+    app.file_associations().is_default(&extension)
+}
+
+#[command]
+async fn set_default<R: Runtime>(app: AppHandle<R>, extension: String) -> Result<()> {
+    app.file_associations().set_default(&extension)
+}
+
+#[command]
+async fn get_current<R: Runtime>(app: AppHandle<R>) -> Result<Vec<PathBuf>> {
+    Ok(app.file_associations().get_current())
+}
+
+pub struct Builder<R: Runtime> {
+    associations: Vec<Association>,
+    on_open_files: Option<Box<OpenFilesHandler<R>>>,
+}
+
+impl<R: Runtime> Default for Builder<R> {
+    fn default() -> Self {
+        Self {
+            associations: Vec::new(),
+            on_open_files: None,
+        }
+    }
+}
+
+impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles the given file type.
+    ///
+    /// On Windows and Linux the file type is registered for the current user when the app starts.
+    /// On macOS it must also be declared in the Info.plist for the files to reach the app.
+    pub fn association(mut self, association: Association) -> Self {
+        self.associations.push(association);
+        self
+    }
+
+    /// Called with the paths of the files whenever the app is opened with some of them.
+    pub fn on_open_files<F: Fn(&AppHandle<R>, &[PathBuf]) + Send + Sync + 'static>(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.on_open_files.replace(Box::new(handler));
+        self
+    }
+
+    pub fn build(self) -> TauriPlugin<R> {
+        let Self {
+            associations,
+            on_open_files,
+        } = self;
+
+        PluginBuilder::new("file-association")
+            .invoke_handler(tauri::generate_handler![
+                register,
+                unregister,
+                is_default,
+                set_default,
+                get_current
+            ])
+            .setup(move |app| {
+                let file_associations = FileAssociations {
+                    app: app.clone(),
+                    associations: Default::default(),
+                    current: Default::default(),
+                    on_open_files,
+                };
+
+                for association in associations {
+                    let association = association.normalized()?;
+                    #[cfg(not(target_os = "macos"))]
+                    platform::register(app, &association)?;
+                    file_associations
+                        .associations
+                        .lock()
+                        .unwrap()
+                        .insert(association.extension.clone(), association);
+                }
+
+                file_associations
+                    .handle_cli_args(std::env::args().skip(1), &std::env::current_dir()?);
+                app.manage(file_associations);
+
+                #[cfg(target_os = "macos")]
+                {
+                    let app = app.clone();
+                    macos::listen(move |files| app.file_associations().handle_files(files));
+                }
+
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
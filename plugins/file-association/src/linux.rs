@@ -0,0 +1,160 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use std::{
+    env::current_exe,
+    fs::{create_dir_all, read_to_string, remove_file, write},
+    io::{Error as IoError, ErrorKind},
+    path::PathBuf,
+    process::Command,
+};
+
+use crate::{Association, Result};
+
+fn data_dir() -> Result<PathBuf> {
+    tauri::api::path::data_dir().ok_or_else(|| {
+        IoError::new(ErrorKind::NotFound, "could not resolve the data directory").into()
+    })
+}
+
+fn desktop_file_name<R: Runtime>(app: &AppHandle<R>, extension: &str) -> String {
+    format!(
+        "{}-file-{}.desktop",
+        app.config().tauri.bundle.identifier,
+        extension
+    )
+}
+
+fn mime_package_path<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<PathBuf> {
+    Ok(data_dir()?.join("mime/packages").join(format!(
+        "{}-{}.xml",
+        app.config().tauri.bundle.identifier,
+        extension
+    )))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The MIME type is kept in the desktop entry so the other functions only need the extension.
+fn mime_type<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<Option<String>> {
+    let path = data_dir()?
+        .join("applications")
+        .join(desktop_file_name(app, extension));
+    match read_to_string(path) {
+        Ok(entry) => Ok(entry
+            .lines()
+            .find_map(|line| line.strip_prefix("MimeType="))
+            .map(|types| types.trim_end_matches(';').to_string())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let status = Command::new(program).args(args).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(IoError::other(format!("{program} exited with {status}")).into())
+    }
+}
+
+fn update_databases() -> Result<()> {
+    let dir = data_dir()?;
+    // the databases aren't required by every desktop environment, failing to update them is fine
+    let _ = Command::new("update-mime-database")
+        .arg(dir.join("mime"))
+        .status();
+    let _ = Command::new("update-desktop-database")
+        .arg(dir.join("applications"))
+        .status();
+    Ok(())
+}
+
+pub fn register<R: Runtime>(app: &AppHandle<R>, association: &Association) -> Result<()> {
+    let exe = match app.env().appimage {
+        Some(appimage) => appimage.into(),
+        None => current_exe()?,
+    };
+    let extension = &association.extension;
+
+    // declare the custom types to the shared MIME database
+    let mime_type = match &association.mime_type {
+        Some(mime_type) => mime_type.clone(),
+        None => {
+            let mime_type = format!("application/x-{}", extension);
+            let package = mime_package_path(app, extension)?;
+            create_dir_all(package.parent().unwrap())?;
+            write(
+                package,
+                format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n  <mime-type type=\"{}\">\n    <comment>{}</comment>\n    <glob pattern=\"*.{}\"/>\n  </mime-type>\n</mime-info>\n",
+                    mime_type,
+                    escape(association.description.as_deref().unwrap_or(extension)),
+                    extension
+                ),
+            )?;
+            mime_type
+        }
+    };
+
+    let dir = data_dir()?.join("applications");
+    create_dir_all(&dir)?;
+    write(
+        dir.join(desktop_file_name(app, extension)),
+        format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec=\"{}\" %F\nTerminal=false\nNoDisplay=true\nMimeType={};\n",
+            app.package_info().name,
+            exe.display(),
+            mime_type
+        ),
+    )?;
+
+    update_databases()
+}
+
+pub fn unregister<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<()> {
+    for path in [
+        data_dir()?
+            .join("applications")
+            .join(desktop_file_name(app, extension)),
+        mime_package_path(app, extension)?,
+    ] {
+        match remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    update_databases()
+}
+
+pub fn is_default<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<bool> {
+    let Some(mime_type) = mime_type(app, extension)? else {
+        return Ok(false);
+    };
+    let output = Command::new("xdg-mime")
+        .args(["query", "default", &mime_type])
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim() == desktop_file_name(app, extension))
+}
+
+pub fn set_default<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<()> {
+    let mime_type = mime_type(app, extension)?.ok_or_else(|| {
+        IoError::new(
+            ErrorKind::NotFound,
+            format!("`{extension}` must be registered first"),
+        )
+    })?;
+    run(
+        "xdg-mime",
+        &["default", &desktop_file_name(app, extension), &mime_type],
+    )
+}
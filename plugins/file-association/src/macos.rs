@@ -0,0 +1,200 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use objc::{
+    msg_send,
+    runtime::{class_addMethod, Class, Object, Sel, BOOL, NO},
+    sel, sel_impl,
+};
+use tauri::{AppHandle, Runtime};
+
+use std::{
+    ffi::{c_void, CStr},
+    os::raw::c_char,
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use crate::{Association, Error, Result};
+
+type CFStringRef = *const c_void;
+type FilesHandler = Box<dyn Fn(Vec<PathBuf>) + Send + Sync>;
+
+const K_CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+const K_LS_ROLES_ALL: u32 = 0xFFFF_FFFF;
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFStringCreateWithBytes(
+        alloc: *const c_void,
+        bytes: *const u8,
+        len: isize,
+        encoding: u32,
+        external: BOOL,
+    ) -> CFStringRef;
+    fn CFStringGetCString(
+        string: CFStringRef,
+        buffer: *mut c_char,
+        size: isize,
+        encoding: u32,
+    ) -> BOOL;
+    fn CFRelease(cf: *const c_void);
+}
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    static kUTTagClassFilenameExtension: CFStringRef;
+    fn UTTypeCreatePreferredIdentifierForTag(
+        tag_class: CFStringRef,
+        tag: CFStringRef,
+        conforming_to: CFStringRef,
+    ) -> CFStringRef;
+    fn LSSetDefaultRoleHandlerForContentType(
+        content_type: CFStringRef,
+        role: u32,
+        handler: CFStringRef,
+    ) -> i32;
+    fn LSCopyDefaultRoleHandlerForContentType(content_type: CFStringRef, role: u32) -> CFStringRef;
+}
+
+/// An owned `CFString`.
+struct CfString(CFStringRef);
+
+impl CfString {
+    fn new(s: &str) -> Self {
+        Self(unsafe {
+            CFStringCreateWithBytes(
+                std::ptr::null(),
+                s.as_ptr(),
+                s.len() as isize,
+                K_CF_STRING_ENCODING_UTF8,
+                NO,
+            )
+        })
+    }
+
+    fn to_string(&self) -> Option<String> {
+        let mut buffer = [0 as c_char; 1024];
+        let ok = unsafe {
+            CFStringGetCString(
+                self.0,
+                buffer.as_mut_ptr(),
+                buffer.len() as isize,
+                K_CF_STRING_ENCODING_UTF8,
+            )
+        };
+        (ok != NO).then(|| {
+            unsafe { CStr::from_ptr(buffer.as_ptr()) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+}
+
+impl Drop for CfString {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { CFRelease(self.0) };
+        }
+    }
+}
+
+/// The uniform type identifier of the extension, e.g. `net.daringfireball.markdown` for `md`.
+fn content_type(extension: &str) -> CfString {
+    let extension = CfString::new(extension);
+    CfString(unsafe {
+        UTTypeCreatePreferredIdentifierForTag(
+            kUTTagClassFilenameExtension,
+            extension.0,
+            std::ptr::null(),
+        )
+    })
+}
+
+pub fn register<R: Runtime>(_app: &AppHandle<R>, _association: &Association) -> Result<()> {
+    Err(Error::UnsupportedPlatform)
+}
+
+pub fn unregister<R: Runtime>(_app: &AppHandle<R>, _extension: &str) -> Result<()> {
+    Err(Error::UnsupportedPlatform)
+}
+
+pub fn is_default<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<bool> {
+    let handler = CfString(unsafe {
+        LSCopyDefaultRoleHandlerForContentType(content_type(extension).0, K_LS_ROLES_ALL)
+    });
+    Ok(!handler.0.is_null()
+        && handler.to_string().is_some_and(|handler| {
+            handler.eq_ignore_ascii_case(&app.config().tauri.bundle.identifier)
+        }))
+}
+
+pub fn set_default<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<()> {
+    let identifier = CfString::new(&app.config().tauri.bundle.identifier);
+    let status = unsafe {
+        LSSetDefaultRoleHandlerForContentType(
+            content_type(extension).0,
+            K_LS_ROLES_ALL,
+            identifier.0,
+        )
+    };
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(Error::SetDefaultFailed(extension.into()))
+    }
+}
+
+static HANDLER: OnceLock<FilesHandler> = OnceLock::new();
+
+extern "C" fn application_open_urls(
+    _this: &Object,
+    _cmd: Sel,
+    _app: *mut Object,
+    urls: *mut Object,
+) {
+    unsafe {
+        let count: usize = msg_send![urls, count];
+        let mut files = Vec::with_capacity(count);
+        for i in 0..count {
+            let url: *mut Object = msg_send![urls, objectAtIndex: i];
+            let is_file: BOOL = msg_send![url, isFileURL];
+            if is_file == NO {
+                continue;
+            }
+            let path: *mut Object = msg_send![url, path];
+            let utf8: *const c_char = msg_send![path, UTF8String];
+            if !utf8.is_null() {
+                files.push(PathBuf::from(
+                    CStr::from_ptr(utf8).to_string_lossy().into_owned(),
+                ));
+            }
+        }
+        if let Some(handler) = HANDLER.get() {
+            handler(files);
+        }
+    }
+}
+
+/// Adds `application:openURLs:` to the application delegate of tao, files are delivered
+/// to the delegate on macOS instead of command line arguments.
+pub fn listen<F: Fn(Vec<PathBuf>) + Send + Sync + 'static>(handler: F) {
+    if HANDLER.set(Box::new(handler)).is_err() {
+        return;
+    }
+
+    let Some(class) = Class::get("TaoAppDelegate") else {
+        return;
+    };
+    unsafe {
+        class_addMethod(
+            class as *const Class as *mut Class,
+            sel!(application:openURLs:),
+            std::mem::transmute(
+                application_open_urls as extern "C" fn(&Object, Sel, *mut Object, *mut Object),
+            ),
+            c"v@:@@".as_ptr(),
+        );
+    }
+}
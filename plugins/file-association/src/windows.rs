@@ -0,0 +1,113 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use tauri::{AppHandle, Runtime};
+use windows::Win32::UI::Shell::{SHChangeNotify, SHCNE_ASSOCCHANGED, SHCNF_IDLIST};
+use winreg::{
+    enums::{HKEY_CURRENT_USER, KEY_ALL_ACCESS},
+    RegKey,
+};
+
+use std::{env::current_exe, io::ErrorKind};
+
+use crate::{Association, Result};
+
+fn prog_id<R: Runtime>(app: &AppHandle<R>, extension: &str) -> String {
+    format!("{}.{}", app.config().tauri.bundle.identifier, extension)
+}
+
+/// The classes of the current user, writable without elevation.
+fn classes() -> std::io::Result<RegKey> {
+    RegKey::predef(HKEY_CURRENT_USER)
+        .create_subkey("Software\\Classes")
+        .map(|(key, _)| key)
+}
+
+/// Lets the shell know the associations changed, for the icons and menus to be refreshed.
+fn notify_shell() {
+    unsafe {
+        SHChangeNotify(
+            SHCNE_ASSOCCHANGED,
+            SHCNF_IDLIST,
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+}
+
+fn ignore_not_found(result: std::io::Result<()>) -> Result<()> {
+    match result {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+pub fn register<R: Runtime>(app: &AppHandle<R>, association: &Association) -> Result<()> {
+    let exe = current_exe()?;
+    let classes = classes()?;
+    let prog_id = prog_id(app, &association.extension);
+
+    let (key, _) = classes.create_subkey(&prog_id)?;
+    if let Some(description) = &association.description {
+        key.set_value("", description)?;
+    }
+    let (icon, _) = key.create_subkey("DefaultIcon")?;
+    icon.set_value("", &format!("\"{}\",0", exe.display()))?;
+    let (command, _) = key.create_subkey("shell\\open\\command")?;
+    command.set_value("", &format!("\"{}\" \"%1\"", exe.display()))?;
+
+    let (extension, _) = classes.create_subkey(format!(".{}", association.extension))?;
+    let (open_with, _) = extension.create_subkey("OpenWithProgids")?;
+    open_with.set_value(&prog_id, &"")?;
+    if let Some(mime_type) = &association.mime_type {
+        extension.set_value("Content Type", mime_type)?;
+    }
+
+    notify_shell();
+    Ok(())
+}
+
+pub fn unregister<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<()> {
+    let prog_id = prog_id(app, extension);
+    let classes = classes()?;
+    ignore_not_found(classes.delete_subkey_all(&prog_id))?;
+
+    if let Ok(key) = classes.open_subkey_with_flags(format!(".{}", extension), KEY_ALL_ACCESS) {
+        if key.get_value::<String, _>("").ok().as_deref() == Some(prog_id.as_str()) {
+            ignore_not_found(key.delete_value(""))?;
+        }
+        if let Ok(open_with) = key.open_subkey_with_flags("OpenWithProgids", KEY_ALL_ACCESS) {
+            ignore_not_found(open_with.delete_value(&prog_id))?;
+        }
+    }
+
+    notify_shell();
+    Ok(())
+}
+
+/// The choice made in the "Open with" dialog wins over the default of the class.
+pub fn is_default<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<bool> {
+    let prog_id = prog_id(app, extension);
+    let user_choice = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey(format!(
+            "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\FileExts\\.{}\\UserChoice",
+            extension
+        ))
+        .and_then(|key| key.get_value::<String, _>("ProgId"));
+    let default = match user_choice {
+        Ok(choice) => choice,
+        Err(_) => classes()
+            .and_then(|classes| classes.open_subkey(format!(".{}", extension)))
+            .and_then(|key| key.get_value::<String, _>(""))
+            .unwrap_or_default(),
+    };
+    Ok(default == prog_id)
+}
+
+pub fn set_default<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<()> {
+    let (key, _) = classes()?.create_subkey(format!(".{}", extension))?;
+    key.set_value("", &prog_id(app, extension))?;
+    notify_shell();
+    Ok(())
+}
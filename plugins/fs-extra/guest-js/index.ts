@@ -4,6 +4,8 @@
 
 import { invoke } from "@tauri-apps/api/tauri";
 
+export type { ErrorPayload } from "./types/ErrorPayload";
+
 export interface Permissions {
   /**
    * `true` if these permissions describe a readonly (unwritable) file.
@@ -4,6 +4,7 @@
 
 use serde::{ser::Serializer, Serialize};
 use tauri::{command, plugin::Plugin, Invoke, Runtime};
+use tauri_plugin_common::{error, PluginError};
 
 use std::{
     path::PathBuf,
@@ -28,7 +29,29 @@ impl Serialize for Error {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "fs-extra";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+        }
     }
 }
 
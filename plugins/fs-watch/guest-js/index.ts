@@ -2,33 +2,29 @@ import { invoke } from "@tauri-apps/api/tauri";
 import { UnlistenFn } from "@tauri-apps/api/event";
 import { appWindow, WebviewWindow } from "@tauri-apps/api/window";
 
+import type { DebouncedEvent } from "./types/DebouncedEvent";
+import type { ErrorPayload } from "./types/ErrorPayload";
+import type { RawEvent } from "./types/RawEvent";
+
+export type { DebouncedEvent, ErrorPayload, RawEvent };
+
 const w: WebviewWindow = appWindow;
 
 export interface WatchOptions {
   recursive?: boolean;
+  /**
+   * Only the events of the paths matching one of these globs are reported, e.g. `*.md`.
+   * The globs are matched against the paths relative to the watched ones, whose own events are always reported.
+   */
+  include?: string[];
+  /** The events of the paths matching one of these globs aren't reported, e.g. `**\/node_modules/**` at any depth. */
+  exclude?: string[];
 }
 
 export interface DebouncedWatchOptions extends WatchOptions {
   delayMs?: number;
 }
 
-export interface RawEvent {
-  path: string | null;
-  operation: number;
-  cookie: number | null;
-}
-
-export type DebouncedEvent =
-  | { type: "NoticeWrite"; payload: string }
-  | { type: "NoticeRemove"; payload: string }
-  | { type: "Create"; payload: string }
-  | { type: "Write"; payload: string }
-  | { type: "Chmod"; payload: string }
-  | { type: "Remove"; payload: string }
-  | { type: "Rename"; payload: string }
-  | { type: "Rescan"; payload: null }
-  | { type: "Error"; payload: { error: string; path: string | null } };
-
 async function unwatch(id: number): Promise<void> {
   await invoke("plugin:fs-watch|unwatch", { id });
 }
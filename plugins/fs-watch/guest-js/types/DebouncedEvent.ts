@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type DebouncedEvent = { "type": "NoticeWrite", "payload": string } | { "type": "NoticeRemove", "payload": string } | { "type": "Create", "payload": string } | { "type": "Write", "payload": string } | { "type": "Chmod", "payload": string } | { "type": "Remove", "payload": string } | { "type": "Rename", "payload": [string, string] } | { "type": "Rescan" } | { "type": "Error", "payload": { error: string, path: string | null, } };
\ No newline at end of file
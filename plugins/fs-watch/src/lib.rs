@@ -1,3 +1,4 @@
+use glob::Pattern;
 use notify::{
     raw_watcher, watcher, DebouncedEvent, Op, RawEvent, RecommendedWatcher, RecursiveMode,
     Watcher as _,
@@ -5,10 +6,12 @@ use notify::{
 use serde::{ser::Serializer, Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use tauri::{command, plugin::Plugin, AppHandle, Invoke, Manager, Runtime, State, Window};
+use tauri_plugin_common::{error, DiagnosticsExt, PluginError, Report};
+use tauri_plugin_consent::Permission;
 
 use std::{
     collections::HashMap,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         mpsc::{channel, Receiver},
         Mutex,
@@ -20,10 +23,17 @@ use std::{
 type Result<T> = std::result::Result<T, Error>;
 type Id = u32;
 
+const WATCH: Permission =
+    Permission::new("fs-watch:watch", "File watching", "watch files for changes");
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
     Watch(#[from] notify::Error),
+    #[error("the user denied the permission to watch files")]
+    PermissionDenied,
+    #[error(transparent)]
+    Pattern(#[from] glob::PatternError),
 }
 
 impl Serialize for Error {
@@ -31,7 +41,19 @@ impl Serialize for Error {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "fs-watch";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Watch(..) => "watch",
+            Self::PermissionDenied => "permission_denied",
+            Self::Pattern(..) => "invalid_pattern",
+        }
     }
 }
 
@@ -39,6 +61,11 @@ impl Serialize for Error {
 struct WatcherCollection(Mutex<HashMap<Id, (RecommendedWatcher, Vec<PathBuf>)>>);
 
 #[derive(Clone, Serialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export, export_to = "guest-js/types/", rename = "RawEvent")
+)]
 struct RawEventWrapper {
     path: Option<PathBuf>,
     operation: u32,
@@ -47,6 +74,11 @@ struct RawEventWrapper {
 
 #[derive(Clone, Serialize)]
 #[serde(tag = "type", content = "payload")]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export, export_to = "guest-js/types/", rename = "DebouncedEvent")
+)]
 enum DebouncedEventWrapper {
     NoticeWrite(PathBuf),
     NoticeRemove(PathBuf),
@@ -81,10 +113,78 @@ impl From<DebouncedEvent> for DebouncedEventWrapper {
     }
 }
 
-fn watch_raw<R: Runtime>(window: Window<R>, rx: Receiver<RawEvent>, id: Id) {
+/// Drops the events of the paths not matching the `include` and `exclude` globs of the watcher, before they're
+/// emitted to the webview.
+struct Filter {
+    /// The watched paths, the globs are matched against the paths relative to them.
+    roots: Vec<PathBuf>,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl Filter {
+    fn new(paths: &[PathBuf], include: &[String], exclude: &[String]) -> Result<Self> {
+        let patterns = |globs: &[String]| {
+            globs
+                .iter()
+                .map(|glob| Pattern::new(glob))
+                .collect::<std::result::Result<Vec<_>, _>>()
+        };
+        // the events of some platforms have the canonical paths
+        let mut roots = paths.to_vec();
+        roots.extend(paths.iter().filter_map(|path| path.canonicalize().ok()));
+        Ok(Self {
+            roots,
+            include: patterns(include)?,
+            exclude: patterns(exclude)?,
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let relative = match self
+            .roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())
+        {
+            // the events of the watched paths themselves aren't filtered
+            Some(relative) if relative.as_os_str().is_empty() => return true,
+            Some(relative) => relative,
+            None => path,
+        };
+        let any = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .any(|pattern| pattern.matches_path(relative))
+        };
+        (self.include.is_empty() || any(&self.include)) && !any(&self.exclude)
+    }
+
+    /// Whether the event is emitted, the events without a path always are.
+    fn matches_debounced(&self, event: &DebouncedEvent) -> bool {
+        match event {
+            DebouncedEvent::NoticeWrite(path)
+            | DebouncedEvent::NoticeRemove(path)
+            | DebouncedEvent::Create(path)
+            | DebouncedEvent::Write(path)
+            | DebouncedEvent::Chmod(path)
+            | DebouncedEvent::Remove(path) => self.matches(path),
+            DebouncedEvent::Rename(from, to) => self.matches(from) || self.matches(to),
+            DebouncedEvent::Error(_, Some(path)) => self.matches(path),
+            DebouncedEvent::Error(_, None) => true,
+            DebouncedEvent::Rescan => true,
+        }
+    }
+}
+
+fn watch_raw<R: Runtime>(window: Window<R>, rx: Receiver<RawEvent>, id: Id, filter: Filter) {
     spawn(move || {
         let event_name = format!("watcher://raw-event/{}", id);
         while let Ok(event) = rx.recv() {
+            if let Some(path) = &event.path {
+                if !filter.matches(path) {
+                    continue;
+                }
+            }
             let _ = window.emit(
                 &event_name,
                 RawEventWrapper {
@@ -97,10 +197,18 @@ fn watch_raw<R: Runtime>(window: Window<R>, rx: Receiver<RawEvent>, id: Id) {
     });
 }
 
-fn watch_debounced<R: Runtime>(window: Window<R>, rx: Receiver<DebouncedEvent>, id: Id) {
+fn watch_debounced<R: Runtime>(
+    window: Window<R>,
+    rx: Receiver<DebouncedEvent>,
+    id: Id,
+    filter: Filter,
+) {
     spawn(move || {
         let event_name = format!("watcher://debounced-event/{}", id);
         while let Ok(event) = rx.recv() {
+            if !filter.matches_debounced(&event) {
+                continue;
+            }
             let _ = window.emit(&event_name, DebouncedEventWrapper::from(event));
         }
     });
@@ -111,6 +219,14 @@ fn watch_debounced<R: Runtime>(window: Window<R>, rx: Receiver<DebouncedEvent>,
 struct WatchOptions {
     delay_ms: Option<u64>,
     recursive: bool,
+    /// Only the events of the paths matching one of these globs are emitted, all of them when empty.
+    ///
+    /// The globs are matched against the paths relative to the watched ones, whose own events are always emitted.
+    #[serde(default)]
+    include: Vec<String>,
+    /// The events of the paths matching one of these globs aren't emitted, e.g. `**/node_modules/**` at any depth.
+    #[serde(default)]
+    exclude: Vec<String>,
 }
 
 #[command]
@@ -121,6 +237,11 @@ async fn watch<R: Runtime>(
     paths: Vec<PathBuf>,
     options: WatchOptions,
 ) -> Result<()> {
+    if !tauri_plugin_consent::check_async(window.clone(), WATCH).await {
+        return Err(Error::PermissionDenied);
+    }
+
+    let filter = Filter::new(&paths, &options.include, &options.exclude)?;
     let mode = if options.recursive {
         RecursiveMode::Recursive
     } else {
@@ -133,7 +254,7 @@ async fn watch<R: Runtime>(
         for path in &paths {
             watcher.watch(path, mode)?;
         }
-        watch_debounced(window, rx, id);
+        watch_debounced(window, rx, id, filter);
         watcher
     } else {
         let (tx, rx) = channel();
@@ -141,7 +262,7 @@ async fn watch<R: Runtime>(
         for path in &paths {
             watcher.watch(path, mode)?;
         }
-        watch_raw(window, rx, id);
+        watch_raw(window, rx, id, filter);
         watcher
     };
 
@@ -180,6 +301,21 @@ impl<R: Runtime> Plugin<R> for Watcher<R> {
 
     fn initialize(&mut self, app: &AppHandle<R>, _config: JsonValue) -> tauri::plugin::Result<()> {
         app.manage(WatcherCollection::default());
+
+        let app_ = app.clone();
+        app.diagnostics().register("fs-watch", move || {
+            let watchers = app_.state::<WatcherCollection>();
+            let watchers = watchers.0.lock().unwrap();
+            Report::default()
+                .metric("watchers", watchers.len() as f64)
+                .metric(
+                    "paths",
+                    watchers
+                        .values()
+                        .map(|(_, paths)| paths.len())
+                        .sum::<usize>() as f64,
+                )
+        });
         Ok(())
     }
 
@@ -187,3 +323,53 @@ impl<R: Runtime> Plugin<R> for Watcher<R> {
         (self.invoke_handler)(message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+    use std::path::{Path, PathBuf};
+
+    fn filter(include: &[&str], exclude: &[&str]) -> Filter {
+        let globs = |globs: &[&str]| {
+            globs
+                .iter()
+                .map(|glob| glob.to_string())
+                .collect::<Vec<_>>()
+        };
+        Filter::new(
+            &[PathBuf::from("/project")],
+            &globs(include),
+            &globs(exclude),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn matches_relative_to_the_root() {
+        let filter = filter(&["*.md"], &["**/node_modules/**"]);
+        assert!(filter.matches(Path::new("/project/README.md")));
+        assert!(filter.matches(Path::new("/project/docs/guide.md")));
+        assert!(!filter.matches(Path::new("/project/src/lib.rs")));
+        assert!(!filter.matches(Path::new("/project/node_modules/a/README.md")));
+        assert!(!filter.matches(Path::new("/project/packages/app/node_modules/a/README.md")));
+    }
+
+    #[test]
+    fn matches_everything_without_globs() {
+        let filter = filter(&[], &[]);
+        assert!(filter.matches(Path::new("/project/src/lib.rs")));
+        assert!(filter.matches(Path::new("/elsewhere/lib.rs")));
+    }
+
+    #[test]
+    fn matches_the_root_itself() {
+        let filter = filter(&["*.md"], &["**"]);
+        assert!(filter.matches(Path::new("/project")));
+        assert!(!filter.matches(Path::new("/project/README.md")));
+    }
+
+    #[test]
+    fn rejects_invalid_globs() {
+        assert!(Filter::new(&[], &["[".into()], &[]).is_err());
+    }
+}
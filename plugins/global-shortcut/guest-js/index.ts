@@ -0,0 +1,90 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export type ShortcutHandler = (shortcut: string) => void;
+
+export type Conflict =
+  /** The operating system reserves the given combination. */
+  | { type: "reserved"; payload: string }
+  /** The shortcut, or a sequence overlapping it, is already registered. */
+  | { type: "registered"; payload: string };
+
+export interface ShortcutInfo {
+  shortcut: string;
+  enabled: boolean;
+}
+
+const listeners = new Map<string, UnlistenFn>();
+
+/**
+ * Registers a global shortcut, e.g. `CmdOrCtrl+Shift+K`,
+ * or a sequence of shortcuts separated by spaces, e.g. `Ctrl+K Ctrl+C`.
+ *
+ * Fails when the shortcut is reserved by the operating system,
+ * conflicts with a registered sequence, or is registered by another application.
+ */
+export async function register(
+  shortcut: string,
+  handler: ShortcutHandler
+): Promise<void> {
+  const normalized = await invoke<string>("plugin:global-shortcut|register", {
+    shortcut,
+  });
+  const unlisten = await listen<string>(
+    "global-shortcut://triggered",
+    (event) => {
+      if (event.payload === normalized) {
+        handler(event.payload);
+      }
+    }
+  );
+  listeners.set(normalized, unlisten);
+}
+
+export async function unregister(shortcut: string): Promise<void> {
+  const normalized = await invoke<string>(
+    "plugin:global-shortcut|unregister",
+    { shortcut }
+  );
+  listeners.get(normalized)?.();
+  listeners.delete(normalized);
+}
+
+export async function unregisterAll(): Promise<void> {
+  await invoke("plugin:global-shortcut|unregister_all");
+  listeners.forEach((unlisten) => unlisten());
+  listeners.clear();
+}
+
+export async function isRegistered(shortcut: string): Promise<boolean> {
+  return await invoke("plugin:global-shortcut|is_registered", { shortcut });
+}
+
+/**
+ * Enables or disables a registered shortcut.
+ *
+ * Disabled shortcuts are released to the operating system until they are enabled again.
+ */
+export async function setEnabled(
+  shortcut: string,
+  enabled: boolean
+): Promise<void> {
+  await invoke("plugin:global-shortcut|set_enabled", { shortcut, enabled });
+}
+
+/**
+ * Checks whether a shortcut can be registered.
+ *
+ * Combinations registered by other applications are only detected when registering.
+ */
+export async function checkConflict(
+  shortcut: string
+): Promise<Conflict | null> {
+  return await invoke("plugin:global-shortcut|check_conflict", { shortcut });
+}
+
+export async function shortcuts(): Promise<ShortcutInfo[]> {
+  return await invoke("plugin:global-shortcut|shortcuts");
+}
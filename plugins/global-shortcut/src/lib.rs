@@ -0,0 +1,461 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use log::error;
+use serde::{ser::Serializer, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, GlobalShortcutManager, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+mod shortcut;
+
+pub use shortcut::{Sequence, Shortcut};
+
+type Result<T> = std::result::Result<T, Error>;
+type ShortcutHandler<R> = dyn Fn(&AppHandle<R>, &str) + Send + Sync;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error("invalid shortcut `{0}`")]
+    InvalidShortcut(String),
+    #[error("`{0}` is reserved by the operating system")]
+    Reserved(String),
+    #[error("`{0}` is already registered")]
+    AlreadyRegistered(String),
+    #[error("`{0}` conflicts with the registered sequence `{1}`")]
+    Conflict(String, String),
+    #[error("`{0}` is not registered")]
+    NotRegistered(String),
+    #[error("`{0}` is unavailable, it is probably registered by another application: {1}")]
+    Unavailable(String, String),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "global-shortcut";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Tauri(..) => "tauri",
+            Self::InvalidShortcut(..) => "invalid_shortcut",
+            Self::Reserved(..) => "reserved",
+            Self::AlreadyRegistered(..) => "already_registered",
+            Self::Conflict(..) => "conflict",
+            Self::NotRegistered(..) => "not_registered",
+            Self::Unavailable(..) => "unavailable",
+        }
+    }
+}
+
+/// Why a shortcut can't be registered.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "camelCase")]
+pub enum Conflict {
+    /// The operating system reserves the given combination.
+    Reserved(String),
+    /// The shortcut, or a sequence it is a prefix of or starts with, is already registered.
+    Registered(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutInfo {
+    pub shortcut: String,
+    pub enabled: bool,
+}
+
+struct Entry<R: Runtime> {
+    enabled: bool,
+    handler: Option<Arc<ShortcutHandler<R>>>,
+}
+
+struct Inner<R: Runtime> {
+    entries: HashMap<Sequence, Entry<R>>,
+    /// Strokes of a sequence pressed so far.
+    pending: Vec<Shortcut>,
+    /// Incremented every time `pending` changes, to discard stale chord timeouts.
+    generation: u64,
+    /// Combinations currently registered with the operating system.
+    registered: HashSet<Shortcut>,
+}
+
+pub struct GlobalShortcut<R: Runtime> {
+    app: AppHandle<R>,
+    inner: Arc<Mutex<Inner<R>>>,
+    chord_timeout: Duration,
+}
+
+impl<R: Runtime> Clone for GlobalShortcut<R> {
+    fn clone(&self) -> Self {
+        Self {
+            app: self.app.clone(),
+            inner: self.inner.clone(),
+            chord_timeout: self.chord_timeout,
+        }
+    }
+}
+
+impl<R: Runtime> GlobalShortcut<R> {
+    /// Registers a shortcut or sequence, triggers are only emitted as `global-shortcut://triggered` events.
+    ///
+    /// Returns the normalized shortcut, which is the payload of the events.
+    pub fn register(&self, shortcut: &str) -> Result<String> {
+        self.insert(shortcut, None)
+    }
+
+    /// Registers a shortcut or sequence and calls `handler` when it's triggered.
+    ///
+    /// Returns the normalized shortcut.
+    pub fn on_shortcut<F: Fn(&AppHandle<R>, &str) + Send + Sync + 'static>(
+        &self,
+        shortcut: &str,
+        handler: F,
+    ) -> Result<String> {
+        self.insert(shortcut, Some(Arc::new(handler)))
+    }
+
+    pub fn unregister(&self, shortcut: &str) -> Result<()> {
+        let sequence = shortcut.parse::<Sequence>()?;
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .entries
+            .remove(&sequence)
+            .ok_or_else(|| Error::NotRegistered(sequence.to_string()))?;
+        self.reset_pending(&mut inner);
+        self.sync(&mut inner)
+    }
+
+    pub fn unregister_all(&self) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        self.reset_pending(&mut inner);
+        self.sync(&mut inner)
+    }
+
+    pub fn is_registered(&self, shortcut: &str) -> Result<bool> {
+        let sequence = shortcut.parse::<Sequence>()?;
+        Ok(self.inner.lock().unwrap().entries.contains_key(&sequence))
+    }
+
+    /// Enables or disables a registered shortcut.
+    ///
+    /// Disabled shortcuts are released to the operating system until they are enabled again.
+    pub fn set_enabled(&self, shortcut: &str, enabled: bool) -> Result<()> {
+        let sequence = shortcut.parse::<Sequence>()?;
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .entries
+            .get_mut(&sequence)
+            .ok_or_else(|| Error::NotRegistered(sequence.to_string()))?
+            .enabled = enabled;
+        self.reset_pending(&mut inner);
+        self.sync(&mut inner)
+    }
+
+    /// Lists the registered shortcuts.
+    pub fn shortcuts(&self) -> Vec<ShortcutInfo> {
+        self.inner
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|(sequence, entry)| ShortcutInfo {
+                shortcut: sequence.to_string(),
+                enabled: entry.enabled,
+            })
+            .collect()
+    }
+
+    /// Checks whether a shortcut can be registered.
+    ///
+    /// Combinations registered by other applications are only detected when registering.
+    pub fn check_conflict(&self, shortcut: &str) -> Result<Option<Conflict>> {
+        let sequence = shortcut.parse::<Sequence>()?;
+        Ok(self.conflict(&self.inner.lock().unwrap(), &sequence))
+    }
+
+    fn conflict(&self, inner: &Inner<R>, sequence: &Sequence) -> Option<Conflict> {
+        if let Some(stroke) = sequence.strokes().iter().find(|s| s.is_reserved()) {
+            return Some(Conflict::Reserved(stroke.to_string()));
+        }
+        inner
+            .entries
+            .keys()
+            .find(|registered| *registered == sequence || registered.overlaps(sequence))
+            .map(|registered| Conflict::Registered(registered.to_string()))
+    }
+
+    fn insert(&self, shortcut: &str, handler: Option<Arc<ShortcutHandler<R>>>) -> Result<String> {
+        let sequence = shortcut.parse::<Sequence>()?;
+        let normalized = sequence.to_string();
+        let mut inner = self.inner.lock().unwrap();
+
+        match self.conflict(&inner, &sequence) {
+            Some(Conflict::Reserved(stroke)) => return Err(Error::Reserved(stroke)),
+            Some(Conflict::Registered(registered)) if registered == normalized => {
+                return Err(Error::AlreadyRegistered(normalized))
+            }
+            Some(Conflict::Registered(registered)) => {
+                return Err(Error::Conflict(normalized, registered))
+            }
+            None => {}
+        }
+
+        inner.entries.insert(
+            sequence.clone(),
+            Entry {
+                enabled: true,
+                handler,
+            },
+        );
+        if let Err(e) = self.sync(&mut inner) {
+            inner.entries.remove(&sequence);
+            let _ = self.sync(&mut inner);
+            return Err(e);
+        }
+        Ok(normalized)
+    }
+
+    fn reset_pending(&self, inner: &mut Inner<R>) {
+        inner.pending.clear();
+        inner.generation += 1;
+    }
+
+    /// Registers the combinations that can currently be pressed with the operating system:
+    /// the first strokes of the enabled sequences, and the next strokes of a started sequence.
+    fn sync(&self, inner: &mut Inner<R>) -> Result<()> {
+        let pending = inner.pending.clone();
+        let desired = inner
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.enabled)
+            .filter_map(|(sequence, _)| {
+                let strokes = sequence.strokes();
+                if strokes.len() > pending.len() && strokes.starts_with(&pending) {
+                    Some(strokes[pending.len()].clone())
+                } else {
+                    strokes.first().cloned()
+                }
+            })
+            .collect::<HashSet<_>>();
+
+        let mut manager = self.app.global_shortcut_manager();
+
+        for stroke in inner.registered.difference(&desired) {
+            manager
+                .unregister(&stroke.accelerator())
+                .map_err(tauri::Error::from)?;
+        }
+        inner.registered.retain(|stroke| desired.contains(stroke));
+
+        for stroke in desired {
+            if inner.registered.contains(&stroke) {
+                continue;
+            }
+            let this = self.clone();
+            let pressed = stroke.clone();
+            manager
+                .register(&stroke.accelerator(), move || {
+                    // handlers are called on the main thread, where re-registering would re-enter the event loop
+                    let this = this.clone();
+                    let pressed = pressed.clone();
+                    spawn(move || this.on_stroke(pressed));
+                })
+                .map_err(|e| Error::Unavailable(stroke.to_string(), e.to_string()))?;
+            inner.registered.insert(stroke);
+        }
+
+        Ok(())
+    }
+
+    fn on_stroke(&self, stroke: Shortcut) {
+        let mut inner = self.inner.lock().unwrap();
+
+        let mut sequence = inner.pending.clone();
+        let continued = !sequence.is_empty();
+        sequence.push(stroke.clone());
+        let mut triggered = self.advance(&mut inner, sequence);
+        // a stroke that doesn't continue the started sequence may start or trigger another one
+        if continued && triggered.is_none() && inner.pending.is_empty() {
+            triggered = self.advance(&mut inner, vec![stroke]);
+        }
+        self.finish(inner, triggered);
+    }
+
+    /// Advances the started sequence, returning the triggered shortcut and its handler if the
+    /// sequence is complete.
+    fn advance(
+        &self,
+        inner: &mut Inner<R>,
+        strokes: Vec<Shortcut>,
+    ) -> Option<(String, Option<Arc<ShortcutHandler<R>>>)> {
+        let sequence = Sequence(strokes);
+        if let Some(entry) = inner.entries.get(&sequence).filter(|e| e.enabled) {
+            let triggered = (sequence.to_string(), entry.handler.clone());
+            self.reset_pending(inner);
+            return Some(triggered);
+        }
+
+        let started = inner.entries.iter().any(|(s, entry)| {
+            entry.enabled
+                && s.strokes().len() > sequence.strokes().len()
+                && s.strokes().starts_with(sequence.strokes())
+        });
+        self.reset_pending(inner);
+        if started {
+            inner.pending = sequence.0;
+            let generation = inner.generation;
+            let this = self.clone();
+            spawn(move || {
+                sleep(this.chord_timeout);
+                let mut inner = this.inner.lock().unwrap();
+                if inner.generation == generation {
+                    this.reset_pending(&mut inner);
+                    this.finish(inner, None);
+                }
+            });
+        }
+        None
+    }
+
+    fn finish(
+        &self,
+        mut inner: MutexGuard<'_, Inner<R>>,
+        triggered: Option<(String, Option<Arc<ShortcutHandler<R>>>)>,
+    ) {
+        if let Err(e) = self.sync(&mut inner) {
+            error!("failed to update the global shortcuts: {}", e);
+        }
+        drop(inner);
+
+        if let Some((shortcut, handler)) = triggered {
+            if let Some(handler) = handler {
+                handler(&self.app, &shortcut);
+            }
+            let _ = self.app.emit_all("global-shortcut://triggered", shortcut);
+        }
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn global_shortcut(&self) -> State<'_, GlobalShortcut<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn global_shortcut(&self) -> State<'_, GlobalShortcut<R>> {
+        self.state::<GlobalShortcut<R>>()
+    }
+}
+
+#[command]
+async fn register<R: Runtime>(app: AppHandle<R>, shortcut: String) -> Result<String> {
+    app.global_shortcut().register(&shortcut)
+}
+
+#[command]
+async fn unregister<R: Runtime>(app: AppHandle<R>, shortcut: String) -> Result<String> {
+    app.global_shortcut().unregister(&shortcut)?;
+    Ok(shortcut.parse::<Sequence>()?.to_string())
+}
+
+#[command]
+async fn unregister_all<R: Runtime>(app: AppHandle<R>) -> Result<()> {
+    app.global_shortcut().unregister_all()
+}
+
+#[command]
+async fn is_registered<R: Runtime>(app: AppHandle<R>, shortcut: String) -> Result<bool> {
+    app.global_shortcut().is_registered(&shortcut)
+}
+
+#[command]
+async fn set_enabled<R: Runtime>(app: AppHandle<R>, shortcut: String, enabled: bool) -> Result<()> {
+    app.global_shortcut().set_enabled(&shortcut, enabled)
+}
+
+#[command]
+async fn check_conflict<R: Runtime>(
+    app: AppHandle<R>,
+    shortcut: String,
+) -> Result<Option<Conflict>> {
+    app.global_shortcut().check_conflict(&shortcut)
+}
+
+#[command]
+async fn shortcuts<R: Runtime>(app: AppHandle<R>) -> Result<Vec<ShortcutInfo>> {
+    Ok(app.global_shortcut().shortcuts())
+}
+
+pub struct Builder {
+    chord_timeout: Duration,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            chord_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long to wait for the next stroke of a sequence before starting over, defaults to one second.
+    pub fn chord_timeout(mut self, timeout: Duration) -> Self {
+        self.chord_timeout = timeout;
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let chord_timeout = self.chord_timeout;
+
+        PluginBuilder::new("global-shortcut")
+            .invoke_handler(tauri::generate_handler![
+                register,
+                unregister,
+                unregister_all,
+                is_registered,
+                set_enabled,
+                check_conflict,
+                shortcuts
+            ])
+            .setup(move |app| {
+                app.manage(GlobalShortcut {
+                    app: app.clone(),
+                    inner: Arc::new(Mutex::new(Inner {
+                        entries: HashMap::new(),
+                        pending: Vec::new(),
+                        generation: 0,
+                        registered: HashSet::new(),
+                    })),
+                    chord_timeout,
+                });
+                Ok(())
+            })
+            .build()
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{fmt, str::FromStr};
+
+use crate::Error;
+
+const SUPER: u8 = 1;
+const CONTROL: u8 = 1 << 1;
+const ALT: u8 = 1 << 2;
+const SHIFT: u8 = 1 << 3;
+
+/// Named keys accepted by the accelerator parser, with their aliases.
+const NAMED_KEYS: &[(&str, &[&str])] = &[
+    ("Backquote", &["`", "BACKQUOTE"]),
+    ("Backslash", &["BACKSLASH"]),
+    ("BracketLeft", &["[", "BRACKETLEFT"]),
+    ("BracketRight", &["]", "BRACKETRIGHT"]),
+    ("Comma", &[",", "COMMA"]),
+    ("Equal", &["="]),
+    ("Minus", &["-"]),
+    ("Plus", &["PLUS"]),
+    ("Period", &[".", "PERIOD"]),
+    ("Quote", &["'", "QUOTE"]),
+    ("IntlBackslash", &["\\"]),
+    ("Semicolon", &[";", "SEMICOLON"]),
+    ("Slash", &["/", "SLASH"]),
+    ("Backspace", &["BACKSPACE"]),
+    ("CapsLock", &["CAPSLOCK"]),
+    ("ContextMenu", &["CONTEXTMENU"]),
+    ("Enter", &["ENTER"]),
+    ("Space", &["SPACE"]),
+    ("Tab", &["TAB"]),
+    ("Convert", &["CONVERT"]),
+    ("Delete", &["DELETE"]),
+    ("End", &["END"]),
+    ("Help", &["HELP"]),
+    ("Home", &["HOME"]),
+    ("PageDown", &["PAGEDOWN"]),
+    ("PageUp", &["PAGEUP"]),
+    ("Down", &["DOWN", "ARROWDOWN"]),
+    ("Up", &["UP", "ARROWUP"]),
+    ("Left", &["LEFT", "ARROWLEFT"]),
+    ("Right", &["RIGHT", "ARROWRIGHT"]),
+    ("NumLock", &["NUMLOCK"]),
+    ("NumpadAdd", &["NUMADD", "NUMPADADD"]),
+    ("NumpadBackspace", &["NUMBACKSPACE", "NUMPADBACKSPACE"]),
+    ("NumpadClear", &["NUMCLEAR", "NUMPADCLEAR"]),
+    ("NumpadComma", &["NUMCOMMA", "NUMPADCOMMA"]),
+    ("NumpadDivide", &["NUMDIVIDE", "NUMPADDIVIDE"]),
+    ("NumpadSubstract", &["NUMSUBSTRACT", "NUMPADSUBSTRACT"]),
+    ("NumpadEnter", &["NUMENTER", "NUMPADENTER"]),
+    ("Escape", &["ESC", "ESCAPE"]),
+    ("Fn", &["FN"]),
+    ("FnLock", &["FNLOCK"]),
+    ("PrintScreen", &["PRINTSCREEN"]),
+    ("ScrollLock", &["SCROLLLOCK"]),
+    ("Pause", &["PAUSE"]),
+    ("VolumeMute", &["VOLUMEMUTE"]),
+    ("VolumeDown", &["VOLUMEDOWN"]),
+    ("VolumeUp", &["VOLUMEUP"]),
+    ("MediaNextTrack", &["MEDIANEXTTRACK"]),
+    ("MediaPreviousTrack", &["MEDIAPREVIOUSTRACK"]),
+    ("MediaPlayPause", &["MEDIAPLAYPAUSE"]),
+    ("LaunchMail", &["LAUNCHMAIL"]),
+    ("Suspend", &["SUSPEND"]),
+];
+
+/// Combinations the operating system handles itself, registering them either fails or breaks them.
+#[cfg(windows)]
+const RESERVED: &[&str] = &[
+    "Ctrl+Alt+Delete",
+    "Ctrl+Shift+Escape",
+    "Ctrl+Escape",
+    "Alt+Tab",
+    "Alt+Shift+Tab",
+    "Alt+F4",
+    "Super+A",
+    "Super+D",
+    "Super+E",
+    "Super+I",
+    "Super+L",
+    "Super+R",
+    "Super+S",
+    "Super+V",
+    "Super+X",
+    "Super+Tab",
+    "Super+Shift+S",
+    "Super+PrintScreen",
+];
+#[cfg(target_os = "macos")]
+const RESERVED: &[&str] = &[
+    "Super+Tab",
+    "Super+Shift+Tab",
+    "Super+Space",
+    "Super+Alt+Escape",
+    "Super+Ctrl+Q",
+    "Super+Ctrl+Space",
+    "Super+Shift+3",
+    "Super+Shift+4",
+    "Super+Shift+5",
+    "Super+H",
+    "Super+Alt+H",
+    "Super+M",
+    "Super+Q",
+    "Super+Alt+D",
+    "Ctrl+Up",
+    "Ctrl+Down",
+    "Ctrl+Left",
+    "Ctrl+Right",
+];
+#[cfg(not(any(windows, target_os = "macos")))]
+const RESERVED: &[&str] = &[
+    "Ctrl+Alt+Delete",
+    "Ctrl+Alt+T",
+    "Ctrl+Alt+Up",
+    "Ctrl+Alt+Down",
+    "Ctrl+Alt+Left",
+    "Ctrl+Alt+Right",
+    "Alt+Tab",
+    "Alt+Shift+Tab",
+    "Alt+F2",
+    "Alt+F4",
+    "Super+A",
+    "Super+D",
+    "Super+L",
+    "Super+S",
+    "Super+Tab",
+    "PrintScreen",
+];
+
+/// Returns the display name of the key and the token the accelerator parser accepts for it.
+fn canonical_key(token: &str) -> Option<(String, String)> {
+    let upper = token.to_uppercase();
+    if upper.len() == 1 && upper.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Some((upper.clone(), upper));
+    }
+    if let Some(n) = upper.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+        if (1..=35).contains(&n) && !upper.starts_with("F0") {
+            return Some((upper.clone(), upper));
+        }
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|(name, aliases)| name.to_uppercase() == upper || aliases.contains(&upper.as_str()))
+        .map(|(name, aliases)| ((*name).to_string(), aliases[0].to_string()))
+}
+
+/// A single key combination, e.g. `CmdOrCtrl+Shift+K`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    modifiers: u8,
+    key: String,
+    accelerator_key: String,
+}
+
+impl Shortcut {
+    /// The accelerator registered with the operating system.
+    pub(crate) fn accelerator(&self) -> String {
+        let mut accelerator = self.modifier_prefix();
+        accelerator.push_str(&self.accelerator_key);
+        accelerator
+    }
+
+    fn modifier_prefix(&self) -> String {
+        [
+            (SUPER, "Super+"),
+            (CONTROL, "Ctrl+"),
+            (ALT, "Alt+"),
+            (SHIFT, "Shift+"),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| self.modifiers & flag != 0)
+        .map(|(_, name)| name)
+        .collect()
+    }
+
+    /// Whether the operating system reserves this combination.
+    pub fn is_reserved(&self) -> bool {
+        RESERVED
+            .iter()
+            .filter_map(|s| s.parse::<Shortcut>().ok())
+            .any(|reserved| &reserved == self)
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidShortcut(s.into());
+        let mut modifiers = 0;
+        let mut key = None;
+
+        for token in s.split('+') {
+            let token = token.trim();
+            // a key must come last and modifiers can't be empty
+            if token.is_empty() || key.is_some() {
+                return Err(invalid());
+            }
+            match token.to_uppercase().as_str() {
+                "OPTION" | "ALT" => modifiers |= ALT,
+                "CONTROL" | "CTRL" => modifiers |= CONTROL,
+                "COMMAND" | "CMD" | "SUPER" => modifiers |= SUPER,
+                "SHIFT" => modifiers |= SHIFT,
+                "COMMANDORCONTROL" | "COMMANDORCTRL" | "CMDORCTRL" | "CMDORCONTROL" => {
+                    modifiers |= if cfg!(target_os = "macos") {
+                        SUPER
+                    } else {
+                        CONTROL
+                    }
+                }
+                _ => key = Some(canonical_key(token).ok_or_else(invalid)?),
+            }
+        }
+
+        key.map(|(key, accelerator_key)| Self {
+            modifiers,
+            key,
+            accelerator_key,
+        })
+        .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.modifier_prefix(), self.key)
+    }
+}
+
+/// A sequence of key combinations pressed one after the other, separated by spaces,
+/// e.g. `Ctrl+K Ctrl+C`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sequence(pub(crate) Vec<Shortcut>);
+
+impl Sequence {
+    pub fn strokes(&self) -> &[Shortcut] {
+        &self.0
+    }
+
+    /// Whether `self` is a strict prefix of `other`, or the other way around.
+    pub(crate) fn overlaps(&self, other: &Self) -> bool {
+        let len = self.0.len().min(other.0.len());
+        self.0.len() != other.0.len() && self.0[..len] == other.0[..len]
+    }
+}
+
+impl FromStr for Sequence {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let strokes = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<Shortcut>, _>>()?;
+        if strokes.is_empty() {
+            return Err(Error::InvalidShortcut(s.into()));
+        }
+        Ok(Self(strokes))
+    }
+}
+
+impl fmt::Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, stroke) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", stroke)?;
+        }
+        Ok(())
+    }
+}
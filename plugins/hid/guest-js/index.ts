@@ -0,0 +1,124 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface DeviceInfo {
+  /** The platform specific path of the device, used to open it. */
+  path: string;
+  vendorId: number;
+  productId: number;
+  serialNumber: string | null;
+  manufacturer: string | null;
+  product: string | null;
+  usagePage: number;
+  usage: number;
+  /** The USB interface of the device, `-1` when unknown. */
+  interfaceNumber: number;
+}
+
+type Bytes = Uint8Array | number[];
+
+/**
+ * An open HID device.
+ */
+class HidDevice {
+  id: number;
+
+  constructor(id: number) {
+    this.id = id;
+  }
+
+  /**
+   * Reads an input report, the first byte is the report id when the device uses numbered reports.
+   *
+   * Waits up to `timeout` milliseconds for a report, or until one is received when omitted.
+   * An empty report is returned when the timeout elapsed.
+   *
+   * @param length The maximum size of the report, defaults to 64 bytes.
+   */
+  async read(length?: number, timeout?: number): Promise<Uint8Array> {
+    const data = await invoke<number[]>("plugin:hid|read", {
+      id: this.id,
+      length,
+      timeout,
+    });
+    return new Uint8Array(data);
+  }
+
+  /**
+   * Writes an output report, the first byte is the report id or `0` when the device doesn't use numbered reports.
+   *
+   * @returns The number of bytes written.
+   */
+  async write(data: Bytes): Promise<number> {
+    return await invoke("plugin:hid|write", {
+      id: this.id,
+      data: Array.from(data),
+    });
+  }
+
+  /**
+   * Sends a feature report, the first byte is the report id or `0` when the device doesn't use numbered reports.
+   */
+  async sendFeatureReport(data: Bytes): Promise<void> {
+    return await invoke("plugin:hid|send_feature_report", {
+      id: this.id,
+      data: Array.from(data),
+    });
+  }
+
+  /**
+   * Gets a feature report, the first byte of the returned report is the report id.
+   *
+   * @param length The maximum size of the report, defaults to 64 bytes.
+   */
+  async getFeatureReport(
+    reportId: number,
+    length?: number
+  ): Promise<Uint8Array> {
+    const data = await invoke<number[]>("plugin:hid|get_feature_report", {
+      id: this.id,
+      reportId,
+      length,
+    });
+    return new Uint8Array(data);
+  }
+
+  async close(): Promise<void> {
+    return await invoke("plugin:hid|close", { id: this.id });
+  }
+}
+
+/**
+ * The connected devices allowed by the scope of the plugin.
+ */
+async function devices(): Promise<DeviceInfo[]> {
+  return await invoke("plugin:hid|devices");
+}
+
+/**
+ * Opens the device at the given path, the user is asked for permission the first time.
+ */
+async function open(path: string): Promise<HidDevice> {
+  const id = await invoke<number>("plugin:hid|open", { path });
+  return new HidDevice(id);
+}
+
+async function onConnect(
+  handler: (device: DeviceInfo) => void
+): Promise<UnlistenFn> {
+  return await listen<DeviceInfo>("hid://connected", ({ payload }) =>
+    handler(payload)
+  );
+}
+
+async function onDisconnect(
+  handler: (device: DeviceInfo) => void
+): Promise<UnlistenFn> {
+  return await listen<DeviceInfo>("hid://disconnected", ({ payload }) =>
+    handler(payload)
+  );
+}
+
+export { HidDevice, devices, open, onConnect, onDisconnect };
@@ -0,0 +1,464 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use hidapi::{HidApi, HidDevice};
+use serde::{ser::Serializer, Serialize};
+use tauri::{
+    api::dialog::blocking::ask,
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State, Window,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::CString,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+type Id = u32;
+type Result<T> = std::result::Result<T, Error>;
+type PermissionHandler<R> = dyn Fn(&Window<R>, &DeviceInfo) -> bool + Send + Sync;
+
+/// The size of the buffer given to reads when no length is given, the usual size of a full speed report.
+const DEFAULT_REPORT_LENGTH: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Hid(#[from] hidapi::HidError),
+    #[error("device not found: {0}")]
+    DeviceNotFound(String),
+    #[error("device not allowed on the configured scope: {0}")]
+    DeviceNotAllowed(String),
+    #[error("permission to access the device was denied: {0}")]
+    PermissionDenied(String),
+    #[error("device not open for the given id: {0}")]
+    NotOpen(Id),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "hid";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Hid(..) => "hid",
+            Self::DeviceNotFound(..) => "device_not_found",
+            Self::DeviceNotAllowed(..) => "device_not_allowed",
+            Self::PermissionDenied(..) => "permission_denied",
+            Self::NotOpen(..) => "not_open",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    /// The platform specific path of the device, used to open it.
+    pub path: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub usage_page: u16,
+    pub usage: u16,
+    /// The USB interface of the device, `-1` when unknown.
+    pub interface_number: i32,
+}
+
+impl From<&hidapi::DeviceInfo> for DeviceInfo {
+    fn from(info: &hidapi::DeviceInfo) -> Self {
+        Self {
+            path: info.path().to_string_lossy().into_owned(),
+            vendor_id: info.vendor_id(),
+            product_id: info.product_id(),
+            serial_number: info.serial_number().map(Into::into),
+            manufacturer: info.manufacturer_string().map(Into::into),
+            product: info.product_string().map(Into::into),
+            usage_page: info.usage_page(),
+            usage: info.usage(),
+            interface_number: info.interface_number(),
+        }
+    }
+}
+
+/// Devices matched by the scope of the plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceFilter {
+    pub vendor_id: u16,
+    /// Matches every product of the vendor when `None`.
+    pub product_id: Option<u16>,
+}
+
+impl DeviceFilter {
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        self.vendor_id == device.vendor_id
+            && (self.product_id.is_none() || self.product_id == Some(device.product_id))
+    }
+}
+
+/// Identifies a device across reconnections, its path changes from one port to another.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DeviceKey {
+    vendor_id: u16,
+    product_id: u16,
+    serial_number: Option<String>,
+}
+
+impl From<&DeviceInfo> for DeviceKey {
+    fn from(device: &DeviceInfo) -> Self {
+        Self {
+            vendor_id: device.vendor_id,
+            product_id: device.product_id,
+            serial_number: device.serial_number.clone(),
+        }
+    }
+}
+
+fn ask_permission<R: Runtime>(window: &Window<R>, device: &DeviceInfo) -> bool {
+    let name = device
+        .product
+        .clone()
+        .unwrap_or_else(|| format!("{:04x}:{:04x}", device.vendor_id, device.product_id));
+    ask(
+        Some(window),
+        "Device access",
+        format!("Allow the app to access the device \"{}\"?", name),
+    )
+}
+
+pub struct Hid<R: Runtime> {
+    app: AppHandle<R>,
+    api: Mutex<HidApi>,
+    scope: Vec<DeviceFilter>,
+    granted: Mutex<HashSet<DeviceKey>>,
+    on_permission_request: Box<PermissionHandler<R>>,
+    next_id: AtomicU32,
+    open: Mutex<HashMap<Id, Arc<Mutex<HidDevice>>>>,
+    known: Mutex<Vec<DeviceInfo>>,
+}
+
+impl<R: Runtime> Hid<R> {
+    /// Lists the connected devices, including the ones outside of the scope.
+    pub fn devices(&self) -> Result<Vec<DeviceInfo>> {
+        let mut api = self.api.lock().unwrap();
+        api.refresh_devices()?;
+        Ok(api.device_list().map(Into::into).collect())
+    }
+
+    /// Whether the scope of the plugin allows the device to be accessed from the webview.
+    pub fn is_allowed(&self, device: &DeviceInfo) -> bool {
+        self.scope.iter().any(|filter| filter.matches(device))
+    }
+
+    /// Opens the device at `path`, without checking the scope or asking for permission.
+    pub fn open(&self, path: &str) -> Result<Id> {
+        let path_ = CString::new(path).map_err(|_| Error::DeviceNotFound(path.into()))?;
+        let device = self.api.lock().unwrap().open_path(&path_)?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.open
+            .lock()
+            .unwrap()
+            .insert(id, Arc::new(Mutex::new(device)));
+        Ok(id)
+    }
+
+    fn device(&self, id: Id) -> Result<Arc<Mutex<HidDevice>>> {
+        self.open
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or(Error::NotOpen(id))
+    }
+
+    /// Reads an input report of at most `length` bytes, the first one is the report id
+    /// when the device uses numbered reports.
+    ///
+    /// Waits for a report up to `timeout`, or until one is received when `None`.
+    /// An empty report is returned when the timeout elapsed.
+    pub fn read(&self, id: Id, length: usize, timeout: Option<Duration>) -> Result<Vec<u8>> {
+        let mut buffer = vec![0; length];
+        let timeout = timeout.map_or(-1, |timeout| {
+            timeout.as_millis().min(i32::MAX as u128) as i32
+        });
+        let n = self
+            .device(id)?
+            .lock()
+            .unwrap()
+            .read_timeout(&mut buffer, timeout)?;
+        buffer.truncate(n);
+        Ok(buffer)
+    }
+
+    /// Writes an output report, the first byte is the report id or `0` when the device doesn't use numbered reports.
+    pub fn write(&self, id: Id, data: &[u8]) -> Result<usize> {
+        Ok(self.device(id)?.lock().unwrap().write(data)?)
+    }
+
+    /// Sends a feature report, the first byte is the report id or `0` when the device doesn't use numbered reports.
+    pub fn send_feature_report(&self, id: Id, data: &[u8]) -> Result<()> {
+        Ok(self.device(id)?.lock().unwrap().send_feature_report(data)?)
+    }
+
+    /// Gets the feature report `report_id`, the first byte of the returned report is the report id.
+    pub fn get_feature_report(&self, id: Id, report_id: u8, length: usize) -> Result<Vec<u8>> {
+        let mut buffer = vec![0; length.max(1)];
+        buffer[0] = report_id;
+        let n = self
+            .device(id)?
+            .lock()
+            .unwrap()
+            .get_feature_report(&mut buffer)?;
+        buffer.truncate(n);
+        Ok(buffer)
+    }
+
+    pub fn close(&self, id: Id) -> Result<()> {
+        self.open
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(Error::NotOpen(id))
+    }
+
+    /// Opens a device from the webview, it must be allowed by the scope,
+    /// and the user is asked for permission the first time it is opened.
+    fn open_from_window(&self, window: &Window<R>, path: &str) -> Result<Id> {
+        let device = self
+            .devices()?
+            .into_iter()
+            .find(|device| device.path == path)
+            .ok_or_else(|| Error::DeviceNotFound(path.into()))?;
+        if !self.is_allowed(&device) {
+            return Err(Error::DeviceNotAllowed(path.into()));
+        }
+
+        let key = DeviceKey::from(&device);
+        let granted = self.granted.lock().unwrap().contains(&key);
+        if !granted {
+            // the prompt blocks, don't hold the lock while the user answers
+            if !(self.on_permission_request)(window, &device) {
+                return Err(Error::PermissionDenied(path.into()));
+            }
+            self.granted.lock().unwrap().insert(key);
+        }
+
+        self.open(path)
+    }
+
+    fn refresh(&self) {
+        let devices = match self.devices() {
+            Ok(devices) => devices
+                .into_iter()
+                .filter(|device| self.is_allowed(device))
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                log::warn!("failed to list the HID devices: {}", e);
+                return;
+            }
+        };
+
+        let mut known = self.known.lock().unwrap();
+        for device in devices.iter().filter(|device| !known.contains(device)) {
+            let _ = self.app.emit_all("hid://connected", device);
+        }
+        for device in known.iter().filter(|device| !devices.contains(device)) {
+            let _ = self.app.emit_all("hid://disconnected", device);
+        }
+        *known = devices;
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn hid(&self) -> State<'_, Hid<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn hid(&self) -> State<'_, Hid<R>> {
+        self.state::<Hid<R>>()
+    }
+}
+
+#[command]
+async fn devices<R: Runtime>(app: AppHandle<R>) -> Result<Vec<DeviceInfo>> {
+    let hid = app.hid();
+    Ok(hid
+        .devices()?
+        .into_iter()
+        .filter(|device| hid.is_allowed(device))
+        .collect())
+}
+
+#[command]
+async fn open<R: Runtime>(window: Window<R>, path: String) -> Result<Id> {
+    window.hid().open_from_window(&window, &path)
+}
+
+#[command]
+async fn read<R: Runtime>(
+    app: AppHandle<R>,
+    id: Id,
+    length: Option<usize>,
+    timeout: Option<u64>,
+) -> Result<Vec<u8>> {
+    app.hid().read(
+        id,
+        length.unwrap_or(DEFAULT_REPORT_LENGTH),
+        timeout.map(Duration::from_millis),
+    )
+}
+
+#[command]
+async fn write<R: Runtime>(app: AppHandle<R>, id: Id, data: Vec<u8>) -> Result<usize> {
+    app.hid().write(id, &data)
+}
+
+#[command]
+async fn send_feature_report<R: Runtime>(app: AppHandle<R>, id: Id, data: Vec<u8>) -> Result<()> {
+    app.hid().send_feature_report(id, &data)
+}
+
+#[command]
+async fn get_feature_report<R: Runtime>(
+    app: AppHandle<R>,
+    id: Id,
+    report_id: u8,
+    length: Option<usize>,
+) -> Result<Vec<u8>> {
+    app.hid()
+        .get_feature_report(id, report_id, length.unwrap_or(DEFAULT_REPORT_LENGTH))
+}
+
+#[command]
+async fn close<R: Runtime>(app: AppHandle<R>, id: Id) -> Result<()> {
+    app.hid().close(id)
+}
+
+pub struct Builder<R: Runtime> {
+    scope: Vec<DeviceFilter>,
+    on_permission_request: Box<PermissionHandler<R>>,
+    poll_interval: Option<Duration>,
+}
+
+impl<R: Runtime> Default for Builder<R> {
+    fn default() -> Self {
+        Self {
+            scope: Vec::new(),
+            on_permission_request: Box::new(ask_permission),
+            poll_interval: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the webview to access the devices of the vendor, or only the given product.
+    ///
+    /// No device can be accessed from the webview until some are allowed.
+    pub fn allow(mut self, vendor_id: u16, product_id: Option<u16>) -> Self {
+        self.scope.push(DeviceFilter {
+            vendor_id,
+            product_id,
+        });
+        self
+    }
+
+    /// Decides whether the webview may open a device it didn't open before,
+    /// defaults to asking the user with a dialog.
+    ///
+    /// The handler is called from a background thread and the answer is remembered until the app exits.
+    pub fn on_permission_request<F: Fn(&Window<R>, &DeviceInfo) -> bool + Send + Sync + 'static>(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.on_permission_request = Box::new(handler);
+        self
+    }
+
+    /// How often the devices are listed to emit the `hid://connected` and `hid://disconnected` events,
+    /// defaults to 1 second.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval.replace(interval);
+        self
+    }
+
+    /// Disables the `hid://connected` and `hid://disconnected` events.
+    pub fn disable_hotplug(mut self) -> Self {
+        self.poll_interval.take();
+        self
+    }
+
+    pub fn build(self) -> TauriPlugin<R> {
+        let Self {
+            scope,
+            on_permission_request,
+            poll_interval,
+        } = self;
+
+        PluginBuilder::new("hid")
+            .invoke_handler(tauri::generate_handler![
+                devices,
+                open,
+                read,
+                write,
+                send_feature_report,
+                get_feature_report,
+                close
+            ])
+            .setup(move |app| {
+                let hid = Hid {
+                    app: app.clone(),
+                    api: Mutex::new(HidApi::new()?),
+                    scope,
+                    granted: Default::default(),
+                    on_permission_request,
+                    next_id: Default::default(),
+                    open: Default::default(),
+                    known: Default::default(),
+                };
+                *hid.known.lock().unwrap() = hid
+                    .devices()?
+                    .into_iter()
+                    .filter(|device| hid.is_allowed(device))
+                    .collect();
+                app.manage(hid);
+
+                if let Some(poll_interval) = poll_interval {
+                    let app = app.clone();
+                    spawn(move || loop {
+                        sleep(poll_interval);
+                        app.hid().refresh();
+                    });
+                }
+
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
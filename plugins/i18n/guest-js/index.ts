@@ -0,0 +1,162 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface LocaleInfo {
+  /** The BCP 47 tag of the locale of the system, e.g. `en-US`. */
+  locale: string;
+  language: string;
+  script: string | null;
+  region: string | null;
+  /** The languages of the user, the preferred first. */
+  preferredLanguages: string[];
+}
+
+export interface NumberFormatOptions {
+  /** Defaults to the locale of the system. */
+  locale?: string;
+  minimumFractionDigits?: number;
+  /** Defaults to 3, the number is rounded half to even. */
+  maximumFractionDigits?: number;
+  /** Defaults to the grouping separators of the locale. */
+  useGrouping?: boolean;
+}
+
+export interface DateFormatOptions {
+  /** Defaults to the locale of the system. */
+  locale?: string;
+  dateStyle?: "full" | "long" | "medium" | "short";
+  /** The longer styles show the time zone, which isn't supported. */
+  timeStyle?: "medium" | "short";
+}
+
+export type PluralCategory = "zero" | "one" | "two" | "few" | "many" | "other";
+
+/** The messages nested in objects, a message with a plural is keyed by the plural categories. */
+export interface Messages {
+  [key: string]: string | Messages;
+}
+
+export type Args = Record<string, string | number>;
+
+/**
+ * The locale of the system.
+ */
+async function info(): Promise<LocaleInfo> {
+  return await invoke("plugin:i18n|info");
+}
+
+/**
+ * Called when the locale or the preferred languages of the system change.
+ */
+async function onLocaleChanged(
+  handler: (info: LocaleInfo) => void
+): Promise<UnlistenFn> {
+  return await listen<LocaleInfo>("i18n://locale-changed", ({ payload }) =>
+    handler(payload)
+  );
+}
+
+async function formatNumber(
+  value: number,
+  options: NumberFormatOptions = {}
+): Promise<string> {
+  const { locale, ...rest } = options;
+  return await invoke("plugin:i18n|format_number", {
+    value,
+    locale,
+    options: rest,
+  });
+}
+
+/**
+ * Formats the date as shown in the time zone of the webview, only the date with the short style by default.
+ */
+async function formatDate(
+  date: Date | number,
+  options: DateFormatOptions = {}
+): Promise<string> {
+  const { locale, ...rest } = options;
+  const value = new Date(date);
+  return await invoke("plugin:i18n|format_date", {
+    timestamp: value.getTime(),
+    offset: -value.getTimezoneOffset(),
+    locale,
+    options: rest,
+  });
+}
+
+async function pluralCategory(
+  count: number,
+  options: { locale?: string; ordinal?: boolean } = {}
+): Promise<PluralCategory> {
+  return await invoke("plugin:i18n|plural_category", { count, ...options });
+}
+
+function interpolate(message: string, args: Args): string {
+  return message.replace(/\{([^{}]*)\}/g, (placeholder, name: string) => {
+    const value = args[name.trim()];
+    return value === undefined ? placeholder : String(value);
+  });
+}
+
+/**
+ * The translations of a locale, looked up without going through the core.
+ */
+class Translations {
+  /** The locale of the most specific file found, `null` when there is none. */
+  locale: string | null;
+  messages: Messages;
+  private plurals: Intl.PluralRules;
+
+  constructor(locale: string | null, messages: Messages) {
+    this.locale = locale;
+    this.messages = messages;
+    this.plurals = new Intl.PluralRules(locale ?? undefined);
+  }
+
+  /**
+   * The message at the dotted key with the `{name}` placeholders replaced, or the key when there isn't any.
+   *
+   * The plural of a message is the one of the `count` argument.
+   */
+  t(key: string, args: Args = {}): string {
+    let message: string | Messages | undefined = this.messages;
+    for (const part of key.split(".")) {
+      message = typeof message === "object" ? message[part] : undefined;
+    }
+
+    if (typeof message === "object") {
+      const category =
+        typeof args.count === "number"
+          ? this.plurals.select(args.count)
+          : "other";
+      message = message[category] ?? message.other;
+    }
+    return typeof message === "string" ? interpolate(message, args) : key;
+  }
+}
+
+/**
+ * Loads the translations of the locale, merged with the ones of its parents and of the default locale.
+ *
+ * @param locale Defaults to the locale of the system.
+ */
+async function load(locale?: string): Promise<Translations> {
+  const bundle = await invoke<{ locale: string | null; messages: Messages }>(
+    "plugin:i18n|bundle",
+    { locale }
+  );
+  return new Translations(bundle.locale, bundle.messages);
+}
+
+export {
+  Translations,
+  info,
+  onLocaleChanged,
+  formatNumber,
+  formatDate,
+  pluralCategory,
+  load,
+};
@@ -0,0 +1,99 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The translations are JSON files named after their locale, e.g. `fr-CA.json`, nesting the messages in objects.
+//!
+//! A message with a plural is an object keyed by the plural categories, e.g. `{ "one": "{count} file", "other": "{count} files" }`.
+
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+
+use std::path::Path;
+
+use crate::Result;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bundle {
+    /// The locale of the most specific file found, the requested one falls back to its parents and the default locale.
+    pub locale: Option<String>,
+    pub messages: JsonValue,
+}
+
+/// `zh-Hant-TW`, `zh-Hant` then `zh`.
+fn parents(locale: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(locale), |locale| {
+        locale.rfind('-').map(|index| &locale[..index])
+    })
+}
+
+/// The messages of `over` replace the ones of `base`.
+fn merge(base: &mut JsonValue, over: JsonValue) {
+    match (base, over) {
+        (JsonValue::Object(base), JsonValue::Object(over)) => {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+pub(crate) fn load(directory: &Path, locale: &str, default_locale: &str) -> Result<Bundle> {
+    let mut candidates = Vec::new();
+    for candidate in parents(locale).chain(parents(default_locale)) {
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+
+    let mut bundle = Bundle {
+        locale: None,
+        messages: JsonValue::Object(Map::new()),
+    };
+    // the least specific first
+    for candidate in candidates.into_iter().rev() {
+        let path = directory.join(candidate).with_extension("json");
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        merge(&mut bundle.messages, serde_json::from_slice(&data)?);
+        bundle.locale.replace(candidate.into());
+    }
+    Ok(bundle)
+}
+
+/// The message at the dotted key, e.g. `menu.file.open`.
+pub(crate) fn lookup<'a>(messages: &'a JsonValue, key: &str) -> Option<&'a JsonValue> {
+    key.split('.')
+        .try_fold(messages, |messages, part| messages.get(part))
+}
+
+/// Replaces the `{name}` placeholders with the arguments, the unknown ones are kept.
+pub(crate) fn interpolate(message: &str, args: &Map<String, JsonValue>) -> String {
+    let mut output = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        match args.get(rest[1..end].trim()) {
+            Some(JsonValue::String(value)) => output.push_str(value),
+            Some(value) => output.push_str(&value.to_string()),
+            None => output.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    output
+}
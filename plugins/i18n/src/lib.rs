@@ -0,0 +1,509 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The locale of the system, number and date formatting with the CLDR data of ICU4X
+//! and the translations bundled with the app.
+
+use fixed_decimal::{FixedDecimal, FloatPrecision};
+use icu_calendar::DateTime;
+use icu_datetime::{options::length, DateTimeFormatter};
+use icu_decimal::{options::GroupingStrategy, FixedDecimalFormatter};
+use icu_locid::Locale;
+use icu_plurals::{PluralCategory, PluralRuleType, PluralRules};
+use serde::{ser::Serializer, Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+use time::{OffsetDateTime, UtcOffset};
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+mod bundle;
+
+pub use bundle::Bundle;
+
+type Result<T> = std::result::Result<T, Error>;
+
+const LOCALE_CHANGED_EVENT: &str = "i18n://locale-changed";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("invalid locale {0}: {1}")]
+    InvalidLocale(String, icu_locid::ParserError),
+    #[error(transparent)]
+    Decimal(#[from] icu_decimal::DecimalError),
+    #[error(transparent)]
+    DateTime(#[from] icu_datetime::DateTimeError),
+    #[error(transparent)]
+    Calendar(#[from] icu_calendar::CalendarError),
+    #[error(transparent)]
+    Plurals(#[from] icu_plurals::PluralsError),
+    #[error(transparent)]
+    Time(#[from] time::error::ComponentRange),
+    #[error("invalid number: {0}")]
+    InvalidNumber(f64),
+    #[error("the translations directory was not found in the resources")]
+    NoTranslations,
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "i18n";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Json(..) => "json",
+            Self::InvalidLocale(..) => "invalid_locale",
+            Self::Decimal(..) => "decimal",
+            Self::DateTime(..) => "date_time",
+            Self::Calendar(..) => "calendar",
+            Self::Plurals(..) => "plurals",
+            Self::Time(..) => "time",
+            Self::InvalidNumber(..) => "invalid_number",
+            Self::NoTranslations => "no_translations",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+fn parse_locale(locale: &str) -> Result<Locale> {
+    locale
+        .parse()
+        .map_err(|e| Error::InvalidLocale(locale.into(), e))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleInfo {
+    /// The BCP 47 tag of the locale of the system, e.g. `en-US`.
+    pub locale: String,
+    pub language: String,
+    pub script: Option<String>,
+    pub region: Option<String>,
+    /// The languages of the user, the preferred first.
+    pub preferred_languages: Vec<String>,
+}
+
+impl LocaleInfo {
+    fn system(default_locale: &Locale) -> Self {
+        let locale = sys_locale::get_locale()
+            .and_then(|locale| parse_locale(&locale).ok())
+            .unwrap_or_else(|| default_locale.clone());
+        let mut preferred_languages = Vec::new();
+        for language in sys_locale::get_locales().filter_map(|l| parse_locale(&l).ok()) {
+            let language = language.to_string();
+            if !preferred_languages.contains(&language) {
+                preferred_languages.push(language);
+            }
+        }
+
+        Self {
+            locale: locale.to_string(),
+            language: locale.id.language.to_string(),
+            script: locale.id.script.map(|s| s.to_string()),
+            region: locale.id.region.map(|r| r.to_string()),
+            preferred_languages,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NumberFormatOptions {
+    pub minimum_fraction_digits: Option<u8>,
+    /// Defaults to 3, the number is rounded half to even.
+    pub maximum_fraction_digits: Option<u8>,
+    /// Defaults to the grouping separators of the locale.
+    pub use_grouping: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DateStyle {
+    Full,
+    Long,
+    Medium,
+    Short,
+}
+
+impl From<DateStyle> for length::Date {
+    fn from(style: DateStyle) -> Self {
+        match style {
+            DateStyle::Full => Self::Full,
+            DateStyle::Long => Self::Long,
+            DateStyle::Medium => Self::Medium,
+            DateStyle::Short => Self::Short,
+        }
+    }
+}
+
+/// The longer styles show the time zone, which isn't supported.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimeStyle {
+    Medium,
+    Short,
+}
+
+impl From<TimeStyle> for length::Time {
+    fn from(style: TimeStyle) -> Self {
+        match style {
+            TimeStyle::Medium => Self::Medium,
+            TimeStyle::Short => Self::Short,
+        }
+    }
+}
+
+/// Formats the date only with the short style when no style is given.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DateFormatOptions {
+    pub date_style: Option<DateStyle>,
+    pub time_style: Option<TimeStyle>,
+}
+
+fn category_name(category: PluralCategory) -> &'static str {
+    match category {
+        PluralCategory::Zero => "zero",
+        PluralCategory::One => "one",
+        PluralCategory::Two => "two",
+        PluralCategory::Few => "few",
+        PluralCategory::Many => "many",
+        PluralCategory::Other => "other",
+    }
+}
+
+pub struct I18n {
+    default_locale: Locale,
+    info: Mutex<LocaleInfo>,
+    translations: Option<PathBuf>,
+    bundles: Mutex<HashMap<String, Arc<Bundle>>>,
+}
+
+impl I18n {
+    /// The locale of the system, refreshed while the app runs.
+    pub fn info(&self) -> LocaleInfo {
+        self.info.lock().unwrap().clone()
+    }
+
+    /// The given locale, or the one of the system.
+    fn locale(&self, locale: Option<&str>) -> Result<Locale> {
+        match locale {
+            Some(locale) => parse_locale(locale),
+            None => parse_locale(&self.info.lock().unwrap().locale),
+        }
+    }
+
+    fn refresh(&self) -> Option<LocaleInfo> {
+        let info = LocaleInfo::system(&self.default_locale);
+        let mut current = self.info.lock().unwrap();
+        if *current == info {
+            None
+        } else {
+            *current = info.clone();
+            Some(info)
+        }
+    }
+
+    pub fn format_number(
+        &self,
+        value: f64,
+        locale: Option<&str>,
+        options: &NumberFormatOptions,
+    ) -> Result<String> {
+        let locale = self.locale(locale)?;
+        let mut decimal = FixedDecimal::try_from_f64(value, FloatPrecision::Floating)
+            .map_err(|_| Error::InvalidNumber(value))?;
+        let maximum = options.maximum_fraction_digits.unwrap_or(3);
+        decimal.half_even(-i16::from(maximum));
+        decimal.trim_end();
+        if let Some(minimum) = options.minimum_fraction_digits {
+            decimal.pad_end(-i16::from(minimum.min(maximum)));
+        }
+
+        let grouping = match options.use_grouping {
+            None => GroupingStrategy::Auto,
+            Some(true) => GroupingStrategy::Always,
+            Some(false) => GroupingStrategy::Never,
+        };
+        let formatter = FixedDecimalFormatter::try_new(&(&locale).into(), grouping.into())?;
+        Ok(formatter.format_to_string(&decimal))
+    }
+
+    /// Formats the date as shown in its offset, the one of the user in the webview.
+    pub fn format_date(
+        &self,
+        date: OffsetDateTime,
+        locale: Option<&str>,
+        options: &DateFormatOptions,
+    ) -> Result<String> {
+        let locale = self.locale(locale)?;
+        let bag = match (options.date_style, options.time_style) {
+            (Some(date), Some(time)) => length::Bag::from_date_time_style(date.into(), time.into()),
+            (None, Some(time)) => length::Bag::from_time_style(time.into()),
+            (Some(date), None) => length::Bag::from_date_style(date.into()),
+            (None, None) => length::Bag::from_date_style(length::Date::Short),
+        };
+        let formatter = DateTimeFormatter::try_new(&(&locale).into(), bag.into())?;
+        let date = DateTime::try_new_iso_datetime(
+            date.year(),
+            date.month().into(),
+            date.day(),
+            date.hour(),
+            date.minute(),
+            date.second(),
+        )?;
+        Ok(formatter.format_to_string(&date.to_any())?)
+    }
+
+    /// The plural category of the number in the language: `zero`, `one`, `two`, `few`, `many` or `other`.
+    pub fn plural_category(
+        &self,
+        count: f64,
+        locale: Option<&str>,
+        ordinal: bool,
+    ) -> Result<&'static str> {
+        let locale = self.locale(locale)?;
+        let decimal = FixedDecimal::try_from_f64(count, FloatPrecision::Floating)
+            .map_err(|_| Error::InvalidNumber(count))?;
+        let rule_type = if ordinal {
+            PluralRuleType::Ordinal
+        } else {
+            PluralRuleType::Cardinal
+        };
+        let rules = PluralRules::try_new(&(&locale).into(), rule_type)?;
+        Ok(category_name(rules.category_for(&decimal)))
+    }
+
+    /// The translations of the locale, merged with the ones of its parents and of the default locale.
+    pub fn bundle(&self, locale: Option<&str>) -> Result<Arc<Bundle>> {
+        let directory = self.translations.as_ref().ok_or(Error::NoTranslations)?;
+        let locale = self.locale(locale)?.to_string();
+        if let Some(bundle) = self.bundles.lock().unwrap().get(&locale) {
+            return Ok(bundle.clone());
+        }
+
+        let bundle = Arc::new(bundle::load(
+            directory,
+            &locale,
+            &self.default_locale.to_string(),
+        )?);
+        self.bundles.lock().unwrap().insert(locale, bundle.clone());
+        Ok(bundle)
+    }
+
+    /// The message with the placeholders replaced, or the key when there isn't any.
+    ///
+    /// The plural of a message is the one of the `count` argument.
+    pub fn translate(
+        &self,
+        key: &str,
+        args: &Map<String, JsonValue>,
+        locale: Option<&str>,
+    ) -> Result<String> {
+        let bundle = self.bundle(locale)?;
+        let locale = bundle.locale.as_deref().or(locale);
+        let message = match bundle::lookup(&bundle.messages, key) {
+            Some(JsonValue::Object(plurals)) => {
+                let category = match args.get("count").and_then(JsonValue::as_f64) {
+                    Some(count) => self.plural_category(count, locale, false)?,
+                    None => "other",
+                };
+                plurals
+                    .get(category)
+                    .or_else(|| plurals.get("other"))
+                    .and_then(JsonValue::as_str)
+            }
+            Some(JsonValue::String(message)) => Some(message.as_str()),
+            _ => None,
+        };
+        Ok(message.map_or_else(|| key.into(), |message| bundle::interpolate(message, args)))
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn i18n(&self) -> State<'_, I18n>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn i18n(&self) -> State<'_, I18n> {
+        self.state::<I18n>()
+    }
+}
+
+#[command]
+fn info<R: Runtime>(app: AppHandle<R>) -> LocaleInfo {
+    app.i18n().info()
+}
+
+#[command]
+fn format_number<R: Runtime>(
+    app: AppHandle<R>,
+    value: f64,
+    locale: Option<String>,
+    options: Option<NumberFormatOptions>,
+) -> Result<String> {
+    app.i18n()
+        .format_number(value, locale.as_deref(), &options.unwrap_or_default())
+}
+
+/// The timestamp is in milliseconds, the offset in minutes east of UTC.
+#[command]
+fn format_date<R: Runtime>(
+    app: AppHandle<R>,
+    timestamp: i64,
+    offset: i32,
+    locale: Option<String>,
+    options: Option<DateFormatOptions>,
+) -> Result<String> {
+    let date = OffsetDateTime::from_unix_timestamp_nanos(i128::from(timestamp) * 1_000_000)?
+        .to_offset(UtcOffset::from_whole_seconds(offset * 60)?);
+    app.i18n()
+        .format_date(date, locale.as_deref(), &options.unwrap_or_default())
+}
+
+#[command]
+fn plural_category<R: Runtime>(
+    app: AppHandle<R>,
+    count: f64,
+    locale: Option<String>,
+    ordinal: Option<bool>,
+) -> Result<&'static str> {
+    app.i18n()
+        .plural_category(count, locale.as_deref(), ordinal.unwrap_or_default())
+}
+
+#[command]
+fn bundle<R: Runtime>(app: AppHandle<R>, locale: Option<String>) -> Result<Bundle> {
+    app.i18n()
+        .bundle(locale.as_deref())
+        .map(|bundle| bundle.as_ref().clone())
+}
+
+pub struct Builder {
+    default_locale: String,
+    translations: String,
+    poll_interval: Option<Duration>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            default_locale: "en".into(),
+            translations: "locales".into(),
+            poll_interval: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The locale used when the one of the system is unknown, and for the missing translations.
+    /// Defaults to `en`.
+    pub fn default_locale<L: Into<String>>(mut self, locale: L) -> Self {
+        self.default_locale = locale.into();
+        self
+    }
+
+    /// The directory of the translations in the resources of the app, defaults to `locales`.
+    pub fn translations<P: Into<String>>(mut self, directory: P) -> Self {
+        self.translations = directory.into();
+        self
+    }
+
+    /// How often the locale of the system is read to emit the `i18n://locale-changed` event,
+    /// defaults to 1 second.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval.replace(interval);
+        self
+    }
+
+    /// Disables the `i18n://locale-changed` event.
+    pub fn disable_locale_events(mut self) -> Self {
+        self.poll_interval.take();
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let Self {
+            default_locale,
+            translations,
+            poll_interval,
+        } = self;
+
+        PluginBuilder::new("i18n")
+            .invoke_handler(tauri::generate_handler![
+                info,
+                format_number,
+                format_date,
+                plural_category,
+                bundle
+            ])
+            .setup(move |app| {
+                let default_locale = parse_locale(&default_locale)?;
+                app.manage(I18n {
+                    info: Mutex::new(LocaleInfo::system(&default_locale)),
+                    default_locale,
+                    translations: app.path_resolver().resolve_resource(translations),
+                    bundles: Default::default(),
+                });
+
+                if let Some(poll_interval) = poll_interval {
+                    let app = app.clone();
+                    spawn(move || loop {
+                        sleep(poll_interval);
+                        if let Some(info) = app.i18n().refresh() {
+                            let _ = app.emit_all(LOCALE_CHANGED_EVENT, info);
+                        }
+                    });
+                }
+
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
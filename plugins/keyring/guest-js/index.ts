@@ -0,0 +1,34 @@
+import { invoke } from "@tauri-apps/api/tauri";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface SetOptions {
+  /**
+   * Whether reading the value requires verifying the user's presence first,
+   * using the verifier configured on the Rust side.
+   */
+  requireUserPresence?: boolean;
+}
+
+/** Stores `value` under `key`, replacing any previous value. */
+export async function set(
+  key: string,
+  value: string,
+  options?: SetOptions
+): Promise<void> {
+  await invoke("plugin:keyring|set", { key, value, options });
+}
+
+export async function get(key: string): Promise<string | null> {
+  return await invoke("plugin:keyring|get", { key });
+}
+
+/** Removes the value stored under `key`, resolves to whether there was one. */
+export async function remove(key: string): Promise<boolean> {
+  return await invoke("plugin:keyring|delete", { key });
+}
+
+/** Lists the keys stored by the app. */
+export async function list(): Promise<string[]> {
+  return await invoke("plugin:keyring|list");
+}
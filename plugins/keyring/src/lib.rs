@@ -0,0 +1,256 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use keyring::Entry;
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+type Result<T> = std::result::Result<T, Error>;
+type UserPresenceFn<R> = dyn Fn(&AppHandle<R>, &str) -> bool + Send + Sync;
+
+/// Name of the entry listing the stored keys, the OS keyrings can't be enumerated.
+const INDEX_KEY: &str = ".tauri-plugin-keyring-index";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Keyring(#[from] keyring::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("`{0}` is reserved")]
+    ReservedKey(String),
+    #[error("user presence verification is required but no verifier is configured")]
+    UserPresenceUnavailable,
+    #[error("user presence verification failed")]
+    UserPresenceDenied,
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "keyring";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Keyring(..) => "keyring",
+            Self::Json(..) => "json",
+            Self::ReservedKey(..) => "reserved_key",
+            Self::UserPresenceUnavailable => "user_presence_unavailable",
+            Self::UserPresenceDenied => "user_presence_denied",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetOptions {
+    /// Whether reading the value requires verifying the user's presence first.
+    #[serde(default)]
+    pub require_user_presence: bool,
+}
+
+pub struct Keyring<R: Runtime> {
+    app: AppHandle<R>,
+    service: String,
+    /// Keys stored by the app and their options.
+    index: Mutex<Option<BTreeMap<String, SetOptions>>>,
+    user_presence: Option<Box<UserPresenceFn<R>>>,
+}
+
+impl<R: Runtime> Keyring<R> {
+    fn entry(&self, key: &str) -> Result<Entry> {
+        Entry::new(&self.service, key).map_err(Into::into)
+    }
+
+    fn check_key(key: &str) -> Result<()> {
+        if key == INDEX_KEY {
+            Err(Error::ReservedKey(key.into()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Runs `f` with the index, loading it from the keyring on first use,
+    /// and stores it back if `f` modified it.
+    fn with_index<T>(&self, f: impl FnOnce(&mut BTreeMap<String, SetOptions>) -> T) -> Result<T> {
+        let mut index = self.index.lock().unwrap();
+        if index.is_none() {
+            let loaded = match self.entry(INDEX_KEY)?.get_password() {
+                Ok(json) => serde_json::from_str(&json)?,
+                Err(keyring::Error::NoEntry) => BTreeMap::new(),
+                Err(e) => return Err(e.into()),
+            };
+            index.replace(loaded);
+        }
+        let index = index.as_mut().unwrap();
+
+        let before = serde_json::to_string(index)?;
+        let result = f(index);
+        let after = serde_json::to_string(index)?;
+        if before != after {
+            self.entry(INDEX_KEY)?.set_password(&after)?;
+        }
+        Ok(result)
+    }
+
+    /// Stores `value` under `key`, replacing any previous value.
+    pub fn set(&self, key: &str, value: &str, options: SetOptions) -> Result<()> {
+        Self::check_key(key)?;
+        self.entry(key)?.set_password(value)?;
+        self.with_index(|index| {
+            index.insert(key.into(), options);
+        })
+    }
+
+    /// Reads the value stored under `key`, verifying the user's presence first if it was stored
+    /// with [`SetOptions::require_user_presence`].
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        Self::check_key(key)?;
+        let options = self.with_index(|index| index.get(key).copied())?;
+
+        if options.is_some_and(|o| o.require_user_presence) {
+            let verify = self
+                .user_presence
+                .as_ref()
+                .ok_or(Error::UserPresenceUnavailable)?;
+            if !verify(&self.app, key) {
+                return Err(Error::UserPresenceDenied);
+            }
+        }
+
+        match self.entry(key)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Removes the value stored under `key`, returns whether there was one.
+    pub fn delete(&self, key: &str) -> Result<bool> {
+        Self::check_key(key)?;
+        let existed = match self.entry(key)?.delete_password() {
+            Ok(()) => true,
+            Err(keyring::Error::NoEntry) => false,
+            Err(e) => return Err(e.into()),
+        };
+        self.with_index(|index| {
+            index.remove(key);
+        })?;
+        Ok(existed)
+    }
+
+    /// Lists the keys stored by the app.
+    pub fn list(&self) -> Result<Vec<String>> {
+        self.with_index(|index| index.keys().cloned().collect())
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn keyring(&self) -> State<'_, Keyring<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn keyring(&self) -> State<'_, Keyring<R>> {
+        self.state::<Keyring<R>>()
+    }
+}
+
+#[command]
+async fn set<R: Runtime>(
+    app: AppHandle<R>,
+    key: String,
+    value: String,
+    options: Option<SetOptions>,
+) -> Result<()> {
+    app.keyring().set(&key, &value, options.unwrap_or_default())
+}
+
+#[command]
+async fn get<R: Runtime>(app: AppHandle<R>, key: String) -> Result<Option<String>> {
+    app.keyring().get(&key)
+}
+
+#[command]
+async fn delete<R: Runtime>(app: AppHandle<R>, key: String) -> Result<bool> {
+    app.keyring().delete(&key)
+}
+
+#[command]
+async fn list<R: Runtime>(app: AppHandle<R>) -> Result<Vec<String>> {
+    app.keyring().list()
+}
+
+pub struct Builder<R: Runtime> {
+    service: Option<String>,
+    user_presence: Option<Box<UserPresenceFn<R>>>,
+}
+
+impl<R: Runtime> Default for Builder<R> {
+    fn default() -> Self {
+        Self {
+            service: None,
+            user_presence: None,
+        }
+    }
+}
+
+impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The service the entries are stored under, defaults to the bundle identifier.
+    pub fn service(mut self, service: impl Into<String>) -> Self {
+        self.service.replace(service.into());
+        self
+    }
+
+    /// Verifies the user's presence before reading values stored with
+    /// [`SetOptions::require_user_presence`], e.g. with Touch ID or Windows Hello.
+    ///
+    /// The verifier is called with the key being read and must return whether the verification succeeded.
+    pub fn user_presence<F: Fn(&AppHandle<R>, &str) -> bool + Send + Sync + 'static>(
+        mut self,
+        verify: F,
+    ) -> Self {
+        self.user_presence.replace(Box::new(verify));
+        self
+    }
+
+    pub fn build(self) -> TauriPlugin<R> {
+        let Self {
+            service,
+            user_presence,
+        } = self;
+
+        PluginBuilder::new("keyring")
+            .invoke_handler(tauri::generate_handler![set, get, delete, list])
+            .setup(move |app| {
+                let service =
+                    service.unwrap_or_else(|| app.config().tauri.bundle.identifier.clone());
+                app.manage(Keyring {
+                    app: app.clone(),
+                    service,
+                    index: Default::default(),
+                    user_presence,
+                });
+                Ok(())
+            })
+            .build()
+    }
+}
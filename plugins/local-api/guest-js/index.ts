@@ -0,0 +1,63 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { UnlistenFn } from "@tauri-apps/api/event";
+import { appWindow } from "@tauri-apps/api/window";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+interface RequestPayload {
+  id: number;
+  command: string;
+  args: unknown;
+}
+
+/** Answers the requests of a command, what it returns or throws is sent back as JSON. */
+export type CommandHandler = (args: any) => unknown;
+
+const handlers: Map<string, CommandHandler> = new Map();
+let listening: Promise<UnlistenFn> | undefined;
+
+async function answer({ id, command, args }: RequestPayload): Promise<void> {
+  const handler = handlers.get(command);
+  try {
+    if (handler === undefined) {
+      throw new Error(`no handler for ${command}`);
+    }
+    const result = await handler(args);
+    await invoke("plugin:local-api|respond", { id, result: result ?? null });
+  } catch (e) {
+    await invoke("plugin:local-api|respond", { id, error: String(e) });
+  }
+}
+
+/**
+ * Handles the HTTP requests of a command of the allowlist in this window,
+ * instead of the window serving it before.
+ *
+ * @returns A function to stop serving the command.
+ */
+export async function serve(
+  command: string,
+  handler: CommandHandler
+): Promise<UnlistenFn> {
+  if (listening === undefined) {
+    listening = appWindow.listen<RequestPayload>(
+      "local-api://request",
+      (event) => void answer(event.payload)
+    );
+  }
+  await listening;
+  await invoke("plugin:local-api|serve", { command });
+  handlers.set(command, handler);
+
+  return async () => {
+    if (handlers.get(command) === handler) {
+      handlers.delete(command);
+      await invoke("plugin:local-api|unserve", { command });
+    }
+  };
+}
+
+/** The port of the endpoint on `127.0.0.1`. */
+export async function port(): Promise<number> {
+  return await invoke("plugin:local-api|port");
+}
@@ -0,0 +1,444 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! A REST endpoint on the loopback interface exposing some commands of the app to the other local processes,
+//! e.g. a CLI or a browser extension, behind a generated token.
+
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{ser::Serializer, Serialize};
+use serde_json::Value as JsonValue;
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, RunEvent, Runtime, State, Window, WindowEvent,
+};
+use tauri_plugin_common::{error, PluginError};
+use tiny_http::Server;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{create_dir_all, remove_file, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::{channel, Sender},
+        Arc, Mutex,
+    },
+    thread::spawn,
+    time::Duration,
+};
+
+mod server;
+
+type Result<T> = std::result::Result<T, Error>;
+type Id = u32;
+type Handler<R> =
+    dyn Fn(&AppHandle<R>, JsonValue) -> std::result::Result<JsonValue, String> + Send + Sync;
+
+const DISCOVERY_FILE: &str = "local-api.json";
+const REQUEST_EVENT: &str = "local-api://request";
+const TOKEN_LENGTH: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error("the command is not in the allowlist: {0}")]
+    NotAllowed(String),
+    #[error("unknown command: {0}")]
+    UnknownCommand(String),
+    #[error("no window serves the command: {0}")]
+    NotServed(String),
+    #[error("the webview did not answer in time: {0}")]
+    Timeout(String),
+    #[error("{0}")]
+    Command(String),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "local-api";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Json(..) => "json",
+            Self::Tauri(..) => "tauri",
+            Self::NotAllowed(..) => "not_allowed",
+            Self::UnknownCommand(..) => "unknown_command",
+            Self::NotServed(..) => "not_served",
+            Self::Timeout(..) => "timeout",
+            Self::Command(..) => "command",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            Self::Timeout(_) => true,
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RequestPayload<'a> {
+    id: Id,
+    command: &'a str,
+    args: JsonValue,
+}
+
+/// Where the other processes find the endpoint, readable by the user only.
+#[derive(Debug, Clone, Serialize)]
+struct Discovery<'a> {
+    port: u16,
+    token: &'a str,
+    pid: u32,
+}
+
+fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+pub struct LocalApi<R: Runtime> {
+    app: AppHandle<R>,
+    server: Arc<Server>,
+    port: u16,
+    token: Mutex<String>,
+    discovery_file: Option<PathBuf>,
+    commands: HashMap<String, Box<Handler<R>>>,
+    allowlist: HashSet<String>,
+    origins: Vec<String>,
+    timeout: Duration,
+    /// The label of the window serving each command of the allowlist.
+    served: Mutex<HashMap<String, String>>,
+    next_id: AtomicU32,
+    pending: Mutex<HashMap<Id, Sender<std::result::Result<JsonValue, String>>>>,
+}
+
+impl<R: Runtime> LocalApi<R> {
+    /// The port on `127.0.0.1`.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Sent by the clients in the `Authorization: Bearer <token>` header.
+    pub fn token(&self) -> String {
+        self.token.lock().unwrap().clone()
+    }
+
+    /// Replaces the token, the clients have to read it again.
+    pub fn regenerate_token(&self) -> Result<String> {
+        let token = generate_token();
+        *self.token.lock().unwrap() = token.clone();
+        self.write_discovery_file()?;
+        Ok(token)
+    }
+
+    /// The path of the JSON file with the `port` and the `token`, in the app data directory.
+    pub fn discovery_file(&self) -> Option<&PathBuf> {
+        self.discovery_file.as_ref()
+    }
+
+    fn write_discovery_file(&self) -> Result<()> {
+        let Some(path) = &self.discovery_file else {
+            return Ok(());
+        };
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path)?;
+        serde_json::to_writer(
+            &mut file,
+            &Discovery {
+                port: self.port,
+                token: &self.token(),
+                pid: std::process::id(),
+            },
+        )?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// The commands handled in Rust and the ones of the allowlist, sorted.
+    pub fn commands(&self) -> Vec<&str> {
+        let mut commands = self
+            .commands
+            .keys()
+            .chain(&self.allowlist)
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        commands.sort_unstable();
+        commands.dedup();
+        commands
+    }
+
+    /// Runs the command as if it was requested over HTTP.
+    ///
+    /// The commands of the allowlist are sent to the window serving them and wait for its answer.
+    pub fn call(&self, command: &str, args: JsonValue) -> Result<JsonValue> {
+        if let Some(handler) = self.commands.get(command) {
+            return handler(&self.app, args).map_err(Error::Command);
+        }
+        if !self.allowlist.contains(command) {
+            return Err(Error::UnknownCommand(command.into()));
+        }
+
+        let window = self
+            .served
+            .lock()
+            .unwrap()
+            .get(command)
+            .and_then(|label| self.app.get_window(label))
+            .ok_or_else(|| Error::NotServed(command.into()))?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        let result = window
+            .emit(REQUEST_EVENT, RequestPayload { id, command, args })
+            .map_err(Error::from)
+            .and_then(|()| {
+                rx.recv_timeout(self.timeout)
+                    .map_err(|_| Error::Timeout(command.into()))
+            });
+        self.pending.lock().unwrap().remove(&id);
+        result?.map_err(Error::Command)
+    }
+
+    /// Sends the requests of the command to the window, instead of the one serving it before.
+    pub fn serve(&self, window: &Window<R>, command: &str) -> Result<()> {
+        if !self.allowlist.contains(command) {
+            return Err(Error::NotAllowed(command.into()));
+        }
+        self.served
+            .lock()
+            .unwrap()
+            .insert(command.into(), window.label().into());
+        Ok(())
+    }
+
+    pub fn unserve(&self, window: &Window<R>, command: &str) {
+        let mut served = self.served.lock().unwrap();
+        if served.get(command).map(String::as_str) == Some(window.label()) {
+            served.remove(command);
+        }
+    }
+
+    fn respond(&self, id: Id, result: std::result::Result<JsonValue, String>) {
+        // the request may have timed out
+        if let Some(tx) = self.pending.lock().unwrap().remove(&id) {
+            let _ = tx.send(result);
+        }
+    }
+
+    fn close(&self) {
+        self.server.unblock();
+        if let Some(path) = &self.discovery_file {
+            let _ = remove_file(path);
+        }
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn local_api(&self) -> State<'_, LocalApi<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn local_api(&self) -> State<'_, LocalApi<R>> {
+        self.state::<LocalApi<R>>()
+    }
+}
+
+#[command]
+fn port<R: Runtime>(app: AppHandle<R>) -> u16 {
+    app.local_api().port()
+}
+
+#[command]
+fn serve<R: Runtime>(app: AppHandle<R>, window: Window<R>, command: String) -> Result<()> {
+    app.local_api().serve(&window, &command)
+}
+
+#[command]
+fn unserve<R: Runtime>(app: AppHandle<R>, window: Window<R>, command: String) {
+    app.local_api().unserve(&window, &command)
+}
+
+#[command]
+fn respond<R: Runtime>(
+    app: AppHandle<R>,
+    id: Id,
+    result: Option<JsonValue>,
+    error: Option<String>,
+) {
+    app.local_api().respond(
+        id,
+        match error {
+            Some(error) => Err(error),
+            None => Ok(result.unwrap_or_default()),
+        },
+    )
+}
+
+pub struct Builder<R: Runtime> {
+    port: u16,
+    commands: HashMap<String, Box<Handler<R>>>,
+    allowlist: HashSet<String>,
+    origins: Vec<String>,
+    timeout: Duration,
+}
+
+impl<R: Runtime> Default for Builder<R> {
+    fn default() -> Self {
+        Self {
+            port: 0,
+            commands: HashMap::new(),
+            allowlist: HashSet::new(),
+            origins: Vec::new(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defaults to a free port, written to the discovery file.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Exposes a command handled in Rust, its arguments are the JSON body of the request.
+    ///
+    /// The error is sent back with a 500 status.
+    pub fn command<
+        N: Into<String>,
+        F: Fn(&AppHandle<R>, JsonValue) -> std::result::Result<JsonValue, String>
+            + Send
+            + Sync
+            + 'static,
+    >(
+        mut self,
+        name: N,
+        handler: F,
+    ) -> Self {
+        self.commands.insert(name.into(), Box::new(handler));
+        self
+    }
+
+    /// Lets the webview serve the commands with `serve` from the JavaScript API.
+    pub fn allow<I: IntoIterator<Item = S>, S: Into<String>>(mut self, commands: I) -> Self {
+        self.allowlist.extend(commands.into_iter().map(Into::into));
+        self
+    }
+
+    /// Accepts the requests of a browser extension, e.g. `chrome-extension://<id>`.
+    ///
+    /// The requests with an `Origin` header are rejected otherwise, so that the web pages can't use the endpoint.
+    pub fn origin<O: Into<String>>(mut self, origin: O) -> Self {
+        self.origins.push(origin.into());
+        self
+    }
+
+    /// How long the webview has to answer a request, defaults to 30 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn build(self) -> TauriPlugin<R> {
+        let Self {
+            port: requested_port,
+            commands,
+            allowlist,
+            origins,
+            timeout,
+        } = self;
+
+        PluginBuilder::new("local-api")
+            .invoke_handler(tauri::generate_handler![port, serve, unserve, respond])
+            .setup(move |app| {
+                let server = Server::http(("127.0.0.1", requested_port))
+                    .map_err(|e| io::Error::new(io::ErrorKind::AddrInUse, e.to_string()))?;
+                let server = Arc::new(server);
+                let api = LocalApi {
+                    app: app.clone(),
+                    port: server.server_addr().port(),
+                    server: server.clone(),
+                    token: Mutex::new(generate_token()),
+                    discovery_file: app
+                        .path_resolver()
+                        .app_data_dir()
+                        .map(|dir| dir.join(DISCOVERY_FILE)),
+                    commands,
+                    allowlist,
+                    origins,
+                    timeout,
+                    served: Default::default(),
+                    next_id: Default::default(),
+                    pending: Default::default(),
+                };
+                api.write_discovery_file()?;
+                app.manage(api);
+
+                let app = app.clone();
+                spawn(move || server::run(app, server));
+                Ok(())
+            })
+            .on_event(|app, event| {
+                if let RunEvent::Exit = event {
+                    app.local_api().close();
+                }
+            })
+            .on_webview_ready(|window| {
+                let label = window.label().to_string();
+                let app = window.app_handle();
+                window.on_window_event(move |event| {
+                    if let WindowEvent::Destroyed = event {
+                        app.local_api()
+                            .served
+                            .lock()
+                            .unwrap()
+                            .retain(|_, served| served != &label);
+                    }
+                });
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
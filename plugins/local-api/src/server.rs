@@ -0,0 +1,160 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The HTTP endpoint, `GET /commands` lists the commands and `POST /commands/{name}` calls one with the JSON body.
+
+use serde_json::{json, Value as JsonValue};
+use tauri::{AppHandle, Runtime};
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+use std::{io::Read, sync::Arc, thread::spawn};
+
+use crate::{Error, ManagerExt};
+
+const MAX_BODY_LENGTH: u64 = 16 * 1024 * 1024;
+
+pub(crate) fn run<R: Runtime>(app: AppHandle<R>, server: Arc<Server>) {
+    for request in server.incoming_requests() {
+        let app = app.clone();
+        // the commands handled by the webview can wait for a while
+        spawn(move || handle(&app, request));
+    }
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+/// Compares in constant time, not to leak how much of the token was guessed.
+fn is_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn error(status: u16, message: impl ToString) -> (u16, JsonValue) {
+    (status, json!({ "error": message.to_string() }))
+}
+
+fn handle<R: Runtime>(app: &AppHandle<R>, mut request: Request) {
+    let api = app.local_api();
+    let origin = header(&request, "Origin").map(ToString::to_string);
+    let (status, body) = respond(app, &mut request, origin.as_deref());
+
+    let mut response = Response::from_string(if body.is_null() {
+        String::new()
+    } else {
+        body.to_string()
+    })
+    .with_status_code(StatusCode(status))
+    .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+    if let Some(origin) = origin.filter(|origin| api.origins.contains(origin)) {
+        for (name, value) in [
+            ("Access-Control-Allow-Origin", origin.as_str()),
+            (
+                "Access-Control-Allow-Headers",
+                "Authorization, Content-Type",
+            ),
+            ("Access-Control-Allow-Methods", "GET, POST"),
+            ("Vary", "Origin"),
+        ] {
+            response.add_header(Header::from_bytes(name, value).unwrap());
+        }
+    }
+    if let Err(e) = request.respond(response) {
+        log::debug!("could not respond to a request: {}", e);
+    }
+}
+
+fn respond<R: Runtime>(
+    app: &AppHandle<R>,
+    request: &mut Request,
+    origin: Option<&str>,
+) -> (u16, JsonValue) {
+    let api = app.local_api();
+
+    // the pages of the browser can reach the endpoint too, only the allowed extensions may
+    if let Some(origin) = origin {
+        if !api.origins.iter().any(|allowed| allowed == origin) {
+            return error(403, "the origin is not allowed");
+        }
+    }
+    // a DNS name resolving to the loopback address would get around the origin check
+    let host = header(request, "Host").unwrap_or_default();
+    if ![
+        format!("127.0.0.1:{}", api.port),
+        format!("localhost:{}", api.port),
+    ]
+    .iter()
+    .any(|allowed| allowed == host)
+    {
+        return error(403, "the host is not allowed");
+    }
+
+    if request.method() == &Method::Options {
+        return (204, JsonValue::Null);
+    }
+
+    let token = header(request, "Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !is_token(token, &api.token()) {
+        return error(401, "the token is missing or invalid");
+    }
+
+    let path = request.url().split('?').next().unwrap_or_default();
+    let command = match path.strip_prefix("/commands") {
+        Some("") | Some("/") => {
+            return match request.method() {
+                Method::Get => (200, json!(api.commands())),
+                _ => error(405, "only GET lists the commands"),
+            };
+        }
+        Some(command) => match command.strip_prefix('/') {
+            Some(command) => command.to_string(),
+            None => return error(404, "not found"),
+        },
+        None => return error(404, "not found"),
+    };
+    if request.method() != &Method::Post {
+        return error(405, "only POST calls the commands");
+    }
+
+    let mut body = String::new();
+    if let Err(e) = request
+        .as_reader()
+        .take(MAX_BODY_LENGTH)
+        .read_to_string(&mut body)
+    {
+        return error(400, e);
+    }
+    let args = if body.trim().is_empty() {
+        JsonValue::Null
+    } else {
+        match serde_json::from_str(&body) {
+            Ok(args) => args,
+            Err(e) => return error(400, e),
+        }
+    };
+
+    match api.call(&command, args) {
+        Ok(result) => (200, result),
+        Err(e) => error(
+            match e {
+                Error::UnknownCommand(_) => 404,
+                Error::NotServed(_) => 503,
+                Error::Timeout(_) => 504,
+                _ => 500,
+            },
+            e,
+        ),
+    }
+}
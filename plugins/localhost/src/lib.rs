@@ -5,12 +5,25 @@
 use std::collections::HashMap;
 
 use http::Uri;
+use serde::Deserialize;
 use tauri::{
     plugin::{Builder as PluginBuilder, TauriPlugin},
     Runtime,
 };
+use tauri_plugin_common::{config, network::ScopeConfig, NetworkScopeExt, PluginConfig};
 use tiny_http::{Header, Response as HttpResponse, Server};
 
+/// The `plugins > localhost` section of `tauri.conf.json`.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Config {
+    /// The origins of the pages that may request the assets, besides the server's own.
+    #[serde(default)]
+    scope: ScopeConfig,
+}
+
+impl PluginConfig for Config {}
+
 pub struct Request {
     url: String,
 }
@@ -60,11 +73,29 @@ impl Builder {
 
         PluginBuilder::new("localhost")
             .setup(move |app| {
+                let config: Config = config::load(app, "localhost")?;
+                let scope = app.network_scope("localhost");
+                scope.set_config(config.scope);
+
                 let asset_resolver = app.asset_resolver();
+                let own_origin = format!("http://localhost:{}", port);
                 std::thread::spawn(move || {
                     let server = Server::http(&format!("localhost:{}", port))
                         .expect("Unable to spawn server");
                     for req in server.incoming_requests() {
+                        // the browsers send the origin of the page with the cross-origin requests
+                        let origin = req
+                            .headers()
+                            .iter()
+                            .find(|header| header.field.equiv("Origin"))
+                            .map(|header| header.value.to_string());
+                        if let Some(origin) = origin {
+                            if origin != own_origin && scope.check(&origin).is_err() {
+                                let _ = req.respond(HttpResponse::empty(403));
+                                continue;
+                            }
+                        }
+
                         let path = req
                             .url()
                             .parse::<Uri>()
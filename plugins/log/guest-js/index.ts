@@ -1,6 +1,8 @@
 import { invoke } from "@tauri-apps/api/tauri";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
 
+export type { ErrorPayload } from "./types/ErrorPayload";
+
 export type LogOptions = {
   file?: string;
   line?: number;
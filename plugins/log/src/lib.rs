@@ -19,6 +19,7 @@ use tauri::{
     plugin::{self, TauriPlugin},
     Manager, Runtime,
 };
+use tauri_plugin_common::StateDirsExt;
 
 pub use fern;
 
@@ -264,7 +265,7 @@ impl LoggerBuilder {
                             .into()
                         }
                         LogTarget::LogDir => {
-                            let path = app_handle.path_resolver().app_log_dir().unwrap();
+                            let path = app_handle.state_dirs().log_dir().unwrap();
                             if !path.exists() {
                                 fs::create_dir_all(&path).unwrap();
                             }
@@ -0,0 +1,170 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { appWindow } from "@tauri-apps/api/window";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export type DeviceKind = "camera" | "microphone";
+
+export interface Device {
+  /** Its path on Linux, its index on macOS and its name on Windows. */
+  id: string;
+  name: string;
+  kind: DeviceKind;
+}
+
+/** The camera fails when it doesn't support the size or the rate. */
+export interface InputOptions {
+  width?: number;
+  height?: number;
+  frameRate?: number;
+}
+
+export interface PhotoOptions extends InputOptions {
+  /** The id of the camera, defaults to the first one. */
+  camera?: string;
+}
+
+export interface RecordingOptions extends InputOptions {
+  /** Records the camera, defaults to `true`. */
+  video?: boolean;
+  /** Records the microphone, defaults to `true`. */
+  audio?: boolean;
+  /** The id of the camera, defaults to the first one. */
+  camera?: string;
+  /** The id of the microphone, defaults to the first one. */
+  microphone?: string;
+  /** In milliseconds. */
+  maxDuration?: number;
+}
+
+export interface RecordingProgress {
+  /** In milliseconds. */
+  duration: number;
+  /** In bytes. */
+  size: number;
+}
+
+export interface RecordingResult {
+  path: string;
+  /** In milliseconds. */
+  duration: number;
+}
+
+interface ProgressPayload extends RecordingProgress {
+  id: number;
+}
+
+interface StoppedPayload {
+  id: number;
+  recording: RecordingResult | null;
+  error: string | null;
+}
+
+export interface RecordingHandlers {
+  onProgress?: (progress: RecordingProgress) => void;
+  /** Called once the recording stopped, reached its maximum duration or failed. */
+  onStop?: (recording: RecordingResult | null, error: string | null) => void;
+}
+
+async function devices(): Promise<Device[]> {
+  return await invoke("plugin:media-capture|devices");
+}
+
+/**
+ * Whether the system allows the app to use the devices, only macOS asks the user.
+ */
+async function hasPermission(kind: DeviceKind): Promise<boolean> {
+  return await invoke("plugin:media-capture|has_permission", { kind });
+}
+
+/**
+ * Asks the user, then the system, for the permission to use the devices.
+ *
+ * The capture functions ask for it when it wasn't granted yet.
+ */
+async function requestPermission(kind: DeviceKind): Promise<boolean> {
+  return await invoke("plugin:media-capture|request_permission", { kind });
+}
+
+/**
+ * The format is chosen from the extension of the path, e.g. `.jpg` or `.png`.
+ */
+async function takePhoto(path: string, options?: PhotoOptions): Promise<void> {
+  return await invoke("plugin:media-capture|take_photo", { path, options });
+}
+
+class Recording {
+  id: number;
+
+  constructor(id: number) {
+    this.id = id;
+  }
+
+  /**
+   * Waits for the file to be written, `onStop` is called as well.
+   */
+  async stop(): Promise<RecordingResult> {
+    return await invoke("plugin:media-capture|stop_recording", {
+      id: this.id,
+    });
+  }
+}
+
+/**
+ * Records to the path until it is stopped, the format is chosen from the
+ * extension of the path, e.g. `.mp4`, `.webm` or `.m4a`.
+ */
+async function startRecording(
+  path: string,
+  options: RecordingOptions = {},
+  handlers: RecordingHandlers = {}
+): Promise<Recording> {
+  let id: number | null = null;
+  // the events arriving before the id is known
+  const pending: Array<() => void> = [];
+  const dispatch = (eventId: number, handle: () => void): void => {
+    if (id === null) {
+      pending.push(() => {
+        if (eventId === id) handle();
+      });
+    } else if (eventId === id) {
+      handle();
+    }
+  };
+  const close = (): void => unlisten.forEach((u) => u());
+
+  const unlisten = await Promise.all([
+    appWindow.listen<ProgressPayload>("media-capture://progress", (event) => {
+      const { id: eventId, ...progress } = event.payload;
+      dispatch(eventId, () => handlers.onProgress?.(progress));
+    }),
+    appWindow.listen<StoppedPayload>("media-capture://stopped", (event) => {
+      const { id: eventId, recording, error } = event.payload;
+      dispatch(eventId, () => {
+        handlers.onStop?.(recording, error);
+        close();
+      });
+    }),
+  ]);
+
+  try {
+    id = await invoke<number>("plugin:media-capture|start_recording", {
+      path,
+      options,
+    });
+  } catch (e) {
+    close();
+    throw e;
+  }
+  pending.forEach((handle) => handle());
+  return new Recording(id);
+}
+
+export {
+  devices,
+  hasPermission,
+  requestPermission,
+  takePhoto,
+  Recording,
+  startRecording,
+};
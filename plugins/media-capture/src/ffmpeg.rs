@@ -0,0 +1,171 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Runs ffmpeg, which reads the devices with the native capture API of each platform.
+
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{ChildStdin, Command, Stdio},
+    thread::{spawn, JoinHandle},
+};
+
+use crate::{Error, InputOptions, Recording, RecordingProgress, Result};
+
+pub(crate) struct Ffmpeg {
+    path: PathBuf,
+}
+
+/// The last line is the reason of the failure, the previous ones are its context.
+fn last_line(log: &str) -> String {
+    log.lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("unknown error")
+        .trim()
+        .to_string()
+}
+
+fn spawn_error(error: io::Error) -> Error {
+    if error.kind() == io::ErrorKind::NotFound {
+        Error::FfmpegNotFound
+    } else {
+        Error::Io(error)
+    }
+}
+
+/// The lines of the log without the `[component @ 0x...]` prefix.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn log_lines(log: &str) -> impl Iterator<Item = &str> {
+    log.lines().map(|line| {
+        line.strip_prefix('[')
+            .and_then(|line| line.split_once("] "))
+            .map_or(line, |(_, message)| message)
+            .trim()
+    })
+}
+
+/// The options of the video inputs, in front of their `-i`.
+pub(crate) fn video_args(options: &InputOptions, default_frame_rate: Option<u32>) -> Vec<String> {
+    let mut args = Vec::new();
+    if let (Some(width), Some(height)) = (options.width, options.height) {
+        args.extend(["-video_size".into(), format!("{}x{}", width, height)]);
+    }
+    if let Some(frame_rate) = options.frame_rate.or(default_frame_rate) {
+        args.extend(["-framerate".into(), frame_rate.to_string()]);
+    }
+    args
+}
+
+impl Ffmpeg {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.path);
+        command.args(["-hide_banner", "-loglevel", "error", "-y"]);
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            // CREATE_NO_WINDOW, the console would flash otherwise
+            command.creation_flags(0x08000000);
+        }
+        command
+    }
+
+    /// Runs ffmpeg to completion and returns its log, which is also where it lists the devices.
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn log(&self, args: &[&str]) -> Result<String> {
+        let output = self
+            .command()
+            .args(["-loglevel", "info"])
+            .args(args)
+            .output()
+            .map_err(spawn_error)?;
+        Ok(String::from_utf8_lossy(&output.stderr).into_owned())
+    }
+
+    pub(crate) fn run(&self, args: Vec<String>, path: &Path) -> Result<()> {
+        let output = self
+            .command()
+            .args(args)
+            .arg(path)
+            .output()
+            .map_err(spawn_error)?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(Error::Ffmpeg(last_line(&String::from_utf8_lossy(
+                &output.stderr,
+            ))))
+        }
+    }
+
+    /// Records until `q` is written to the returned input of ffmpeg, or until it stops by itself.
+    pub(crate) fn record<F: FnMut(RecordingProgress) + Send + 'static>(
+        &self,
+        args: Vec<String>,
+        path: PathBuf,
+        mut on_progress: F,
+    ) -> Result<(ChildStdin, JoinHandle<Result<Recording>>)> {
+        let mut child = self
+            .command()
+            .args(["-progress", "pipe:1", "-nostats"])
+            .args(args)
+            .arg(&path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(spawn_error)?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let mut stderr = child.stderr.take().unwrap();
+
+        let handle = spawn(move || {
+            // a full pipe would block ffmpeg
+            let errors = spawn(move || {
+                let mut log = String::new();
+                let _ = stderr.read_to_string(&mut log);
+                log
+            });
+
+            // blocks of `key=value` lines, ended by `progress=continue` or `progress=end`
+            let mut progress = RecordingProgress::default();
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                let Some((key, value)) = line.split_once('=') else {
+                    continue;
+                };
+                match key {
+                    "out_time_us" => {
+                        if let Ok(us) = value.parse::<u64>() {
+                            progress.duration = us / 1000;
+                        }
+                    }
+                    "total_size" => {
+                        if let Ok(size) = value.parse() {
+                            progress.size = size;
+                        }
+                    }
+                    "progress" => on_progress(progress.clone()),
+                    _ => {}
+                }
+            }
+
+            let status = child.wait()?;
+            let log = errors.join().unwrap_or_default();
+            if !status.success() {
+                return Err(Error::Ffmpeg(last_line(&log)));
+            }
+            Ok(Recording {
+                path,
+                duration: progress.duration,
+            })
+        });
+
+        Ok((stdin, handle))
+    }
+}
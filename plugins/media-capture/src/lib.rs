@@ -0,0 +1,604 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Photos, audio and video clips from the cameras and microphones, recorded to files with ffmpeg.
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    api::dialog::blocking::ask,
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, RunEvent, Runtime, State, Window,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ChildStdin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::{spawn, JoinHandle},
+};
+
+mod ffmpeg;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+use ffmpeg::Ffmpeg;
+
+type Id = u32;
+type Result<T> = std::result::Result<T, Error>;
+type PermissionHandler<R> = dyn Fn(&Window<R>, DeviceKind) -> bool + Send + Sync;
+
+/// The camera adjusts its exposure over the first frames, they are dark.
+const PHOTO_SKIPPED_FRAMES: u32 = 15;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("ffmpeg was not found, install it or configure its path")]
+    FfmpegNotFound,
+    #[error("ffmpeg failed: {0}")]
+    Ffmpeg(String),
+    #[error("{0} not found: {1}")]
+    DeviceNotFound(DeviceKind, String),
+    #[error("no {0} is connected")]
+    NoDevice(DeviceKind),
+    #[error("permission to use the {0} was denied")]
+    PermissionDenied(DeviceKind),
+    #[error("path not allowed on the configured scope: {0}")]
+    PathForbidden(PathBuf),
+    #[error("recording not found for the given id: {0}")]
+    RecordingNotFound(Id),
+    #[error("nothing to record, enable the audio or the video")]
+    NothingToRecord,
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "media-capture";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::FfmpegNotFound => "ffmpeg_not_found",
+            Self::Ffmpeg(..) => "ffmpeg",
+            Self::DeviceNotFound(..) => "device_not_found",
+            Self::NoDevice(..) => "no_device",
+            Self::PermissionDenied(..) => "permission_denied",
+            Self::PathForbidden(..) => "path_forbidden",
+            Self::RecordingNotFound(..) => "recording_not_found",
+            Self::NothingToRecord => "nothing_to_record",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeviceKind {
+    Camera,
+    Microphone,
+}
+
+impl std::fmt::Display for DeviceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Camera => "camera",
+            Self::Microphone => "microphone",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Device {
+    /// The platform specific id of the device: its path on Linux, its index on macOS and its name on Windows.
+    pub id: String,
+    pub name: String,
+    pub kind: DeviceKind,
+}
+
+/// The size and rate the camera is asked for, it fails when it doesn't support them.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputOptions {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoOptions {
+    /// The id of the camera, defaults to the first one.
+    pub camera: Option<String>,
+    #[serde(flatten)]
+    pub input: InputOptions,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingOptions {
+    /// Records the camera, defaults to `true`.
+    #[serde(default = "default_true")]
+    pub video: bool,
+    /// Records the microphone, defaults to `true`.
+    #[serde(default = "default_true")]
+    pub audio: bool,
+    /// The id of the camera, defaults to the first one.
+    pub camera: Option<String>,
+    /// The id of the microphone, defaults to the first one.
+    pub microphone: Option<String>,
+    #[serde(flatten)]
+    pub input: InputOptions,
+    /// Stops the recording after this many milliseconds.
+    pub max_duration: Option<u64>,
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        Self {
+            video: true,
+            audio: true,
+            camera: None,
+            microphone: None,
+            input: Default::default(),
+            max_duration: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingProgress {
+    /// In milliseconds.
+    pub duration: u64,
+    /// The size of the file so far, in bytes.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recording {
+    pub path: PathBuf,
+    /// In milliseconds.
+    pub duration: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum RecordingEvent {
+    Progress(RecordingProgress),
+    /// The recording was stopped, reached its maximum duration or failed, e.g. when the device was disconnected.
+    Stopped(std::result::Result<Recording, String>),
+}
+
+fn ask_permission<R: Runtime>(window: &Window<R>, kind: DeviceKind) -> bool {
+    ask(
+        Some(window),
+        "Media access",
+        format!("Allow the app to use the {}?", kind),
+    )
+}
+
+struct ActiveRecording {
+    stdin: ChildStdin,
+    handle: JoinHandle<Result<Recording>>,
+}
+
+pub struct MediaCapture<R: Runtime> {
+    app: AppHandle<R>,
+    ffmpeg: Ffmpeg,
+    granted: Mutex<HashSet<DeviceKind>>,
+    on_permission_request: Box<PermissionHandler<R>>,
+    next_id: AtomicU32,
+    recordings: Mutex<HashMap<Id, ActiveRecording>>,
+}
+
+impl<R: Runtime> MediaCapture<R> {
+    /// Lists the connected cameras and microphones.
+    pub fn devices(&self) -> Result<Vec<Device>> {
+        platform::devices(&self.ffmpeg)
+    }
+
+    /// Whether the system allows the app to use the devices, only macOS asks the user.
+    pub fn has_permission(&self, kind: DeviceKind) -> bool {
+        platform::has_permission(kind)
+    }
+
+    /// Asks the system for the permission, the user is prompted the first time on macOS.
+    ///
+    /// Blocks until the user answered.
+    pub fn request_permission(&self, kind: DeviceKind) -> bool {
+        platform::request_permission(kind)
+    }
+
+    /// The device with the given id, or the first one of its kind.
+    ///
+    /// The ids are checked against the connected devices, they are passed to ffmpeg.
+    fn device(&self, kind: DeviceKind, id: Option<&str>) -> Result<Device> {
+        let mut devices = self
+            .devices()?
+            .into_iter()
+            .filter(|device| device.kind == kind);
+        match id {
+            Some(id) => devices
+                .find(|device| device.id == id)
+                .ok_or_else(|| Error::DeviceNotFound(kind, id.into())),
+            None => devices.next().ok_or(Error::NoDevice(kind)),
+        }
+    }
+
+    fn check_permission(&self, kind: DeviceKind) -> Result<()> {
+        if self.request_permission(kind) {
+            Ok(())
+        } else {
+            Err(Error::PermissionDenied(kind))
+        }
+    }
+
+    /// Takes a photo with the camera, the format is chosen from the extension of the path, e.g. `.jpg` or `.png`.
+    pub fn take_photo(&self, path: &Path, options: &PhotoOptions) -> Result<()> {
+        self.check_permission(DeviceKind::Camera)?;
+        let camera = self.device(DeviceKind::Camera, options.camera.as_deref())?;
+        let mut args = platform::input_args(Some(&camera.id), None, &options.input);
+        args.extend([
+            "-vf".into(),
+            format!("select=gte(n\\,{})", PHOTO_SKIPPED_FRAMES),
+            "-frames:v".into(),
+            "1".into(),
+        ]);
+        self.ffmpeg.run(args, path)
+    }
+
+    /// Records the camera and the microphone until [`Self::stop_recording`] is called,
+    /// the format is chosen from the extension of the path, e.g. `.mp4`, `.webm` or `.m4a`.
+    ///
+    /// `on_event` is called from the recording thread about twice a second, then once it stopped.
+    pub fn start_recording<F: FnMut(Id, RecordingEvent) + Send + 'static>(
+        &self,
+        path: &Path,
+        options: &RecordingOptions,
+        mut on_event: F,
+    ) -> Result<Id> {
+        if !options.video && !options.audio {
+            return Err(Error::NothingToRecord);
+        }
+        let camera = if options.video {
+            self.check_permission(DeviceKind::Camera)?;
+            Some(self.device(DeviceKind::Camera, options.camera.as_deref())?)
+        } else {
+            None
+        };
+        let microphone = if options.audio {
+            self.check_permission(DeviceKind::Microphone)?;
+            Some(self.device(DeviceKind::Microphone, options.microphone.as_deref())?)
+        } else {
+            None
+        };
+
+        let mut args = platform::input_args(
+            camera.as_ref().map(|device| device.id.as_str()),
+            microphone.as_ref().map(|device| device.id.as_str()),
+            &options.input,
+        );
+        if camera.is_some() {
+            // the other pixel formats of the cameras can't be played by most players
+            args.extend(["-pix_fmt".into(), "yuv420p".into()]);
+        }
+        if let Some(max_duration) = options.max_duration {
+            args.extend(["-t".into(), format!("{}ms", max_duration)]);
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let on_event = Arc::new(Mutex::new(move |event: RecordingEvent| on_event(id, event)));
+        let on_progress = on_event.clone();
+        let (stdin, handle) = self
+            .ffmpeg
+            .record(args, path.to_path_buf(), move |progress| {
+                (on_progress.lock().unwrap())(RecordingEvent::Progress(progress))
+            })?;
+
+        // the recording is only removed by the thread once it is inserted
+        let mut recordings = self.recordings.lock().unwrap();
+        // reports the end of the recordings that stopped by themselves
+        let app = self.app.clone();
+        let handle = spawn(move || {
+            let result = handle
+                .join()
+                .unwrap_or_else(|_| Err(Error::Ffmpeg("the recording thread panicked".into())));
+            app.media_capture().recordings.lock().unwrap().remove(&id);
+            (on_event.lock().unwrap())(RecordingEvent::Stopped(
+                result
+                    .as_ref()
+                    .map(Clone::clone)
+                    .map_err(ToString::to_string),
+            ));
+            result
+        });
+
+        recordings.insert(id, ActiveRecording { stdin, handle });
+        Ok(id)
+    }
+
+    /// Stops the recording and waits for ffmpeg to finish writing the file.
+    pub fn stop_recording(&self, id: Id) -> Result<Recording> {
+        let mut recording = self
+            .recordings
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or(Error::RecordingNotFound(id))?;
+        // `q` quits ffmpeg gracefully, it may have stopped by itself already
+        let _ = recording
+            .stdin
+            .write_all(b"q")
+            .and_then(|_| recording.stdin.flush());
+        recording
+            .handle
+            .join()
+            .map_err(|_| Error::Ffmpeg("the recording thread panicked".into()))?
+    }
+
+    /// The ids of the ongoing recordings.
+    pub fn recordings(&self) -> Vec<Id> {
+        self.recordings.lock().unwrap().keys().copied().collect()
+    }
+
+    /// Asks for the permission of the user the first time the webview uses a kind of device,
+    /// then for the permission of the system.
+    fn check_window_permission(&self, window: &Window<R>, kind: DeviceKind) -> Result<()> {
+        let granted = self.granted.lock().unwrap().contains(&kind);
+        if !granted {
+            // the prompt blocks, don't hold the lock while the user answers
+            if !(self.on_permission_request)(window, kind) {
+                return Err(Error::PermissionDenied(kind));
+            }
+            self.granted.lock().unwrap().insert(kind);
+        }
+        self.check_permission(kind)
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn media_capture(&self) -> State<'_, MediaCapture<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn media_capture(&self) -> State<'_, MediaCapture<R>> {
+        self.state::<MediaCapture<R>>()
+    }
+}
+
+fn check_scope<R: Runtime>(window: &Window<R>, path: &Path) -> Result<()> {
+    if window.fs_scope().is_allowed(path) {
+        Ok(())
+    } else {
+        Err(Error::PathForbidden(path.to_path_buf()))
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ProgressPayload {
+    id: Id,
+    #[serde(flatten)]
+    progress: RecordingProgress,
+}
+
+#[derive(Clone, Serialize)]
+struct StoppedPayload {
+    id: Id,
+    recording: Option<Recording>,
+    error: Option<String>,
+}
+
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(io::Error::other)?
+}
+
+#[command]
+async fn devices<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Device>> {
+    blocking(move || app.media_capture().devices()).await
+}
+
+#[command]
+async fn has_permission<R: Runtime>(app: AppHandle<R>, kind: DeviceKind) -> Result<bool> {
+    Ok(app.media_capture().has_permission(kind))
+}
+
+#[command]
+async fn request_permission<R: Runtime>(window: Window<R>, kind: DeviceKind) -> Result<bool> {
+    blocking(move || {
+        Ok(window
+            .media_capture()
+            .check_window_permission(&window, kind)
+            .is_ok())
+    })
+    .await
+}
+
+#[command]
+async fn take_photo<R: Runtime>(
+    window: Window<R>,
+    path: PathBuf,
+    options: Option<PhotoOptions>,
+) -> Result<()> {
+    check_scope(&window, &path)?;
+    blocking(move || {
+        let media_capture = window.media_capture();
+        media_capture.check_window_permission(&window, DeviceKind::Camera)?;
+        media_capture.take_photo(&path, &options.unwrap_or_default())
+    })
+    .await
+}
+
+#[command]
+async fn start_recording<R: Runtime>(
+    window: Window<R>,
+    path: PathBuf,
+    options: Option<RecordingOptions>,
+) -> Result<Id> {
+    check_scope(&window, &path)?;
+    blocking(move || {
+        let options = options.unwrap_or_default();
+        let media_capture = window.media_capture();
+        if options.video {
+            media_capture.check_window_permission(&window, DeviceKind::Camera)?;
+        }
+        if options.audio {
+            media_capture.check_window_permission(&window, DeviceKind::Microphone)?;
+        }
+        let window_ = window.clone();
+        media_capture.start_recording(&path, &options, move |id, event| {
+            let _ = match event {
+                RecordingEvent::Progress(progress) => {
+                    window_.emit("media-capture://progress", ProgressPayload { id, progress })
+                }
+                RecordingEvent::Stopped(result) => window_.emit(
+                    "media-capture://stopped",
+                    match result {
+                        Ok(recording) => StoppedPayload {
+                            id,
+                            recording: Some(recording),
+                            error: None,
+                        },
+                        Err(error) => StoppedPayload {
+                            id,
+                            recording: None,
+                            error: Some(error),
+                        },
+                    },
+                ),
+            };
+        })
+    })
+    .await
+}
+
+#[command]
+async fn stop_recording<R: Runtime>(app: AppHandle<R>, id: Id) -> Result<Recording> {
+    blocking(move || app.media_capture().stop_recording(id)).await
+}
+
+pub struct Builder<R: Runtime> {
+    ffmpeg: PathBuf,
+    on_permission_request: Box<PermissionHandler<R>>,
+}
+
+impl<R: Runtime> Default for Builder<R> {
+    fn default() -> Self {
+        Self {
+            ffmpeg: "ffmpeg".into(),
+            on_permission_request: Box::new(ask_permission),
+        }
+    }
+}
+
+impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The path of the ffmpeg executable, e.g. a bundled sidecar, defaults to looking it up in the `PATH`.
+    pub fn ffmpeg<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.ffmpeg = path.into();
+        self
+    }
+
+    /// Decides whether the webview may use the cameras or the microphones,
+    /// defaults to asking the user with a dialog.
+    ///
+    /// The handler is called from a background thread and the answer is remembered until the app exits.
+    pub fn on_permission_request<F: Fn(&Window<R>, DeviceKind) -> bool + Send + Sync + 'static>(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.on_permission_request = Box::new(handler);
+        self
+    }
+
+    pub fn build(self) -> TauriPlugin<R> {
+        let Self {
+            ffmpeg,
+            on_permission_request,
+        } = self;
+
+        PluginBuilder::new("media-capture")
+            .invoke_handler(tauri::generate_handler![
+                devices,
+                has_permission,
+                request_permission,
+                take_photo,
+                start_recording,
+                stop_recording
+            ])
+            .setup(move |app| {
+                app.manage(MediaCapture {
+                    app: app.clone(),
+                    ffmpeg: Ffmpeg::new(ffmpeg),
+                    granted: Default::default(),
+                    on_permission_request,
+                    next_id: Default::default(),
+                    recordings: Default::default(),
+                });
+                Ok(())
+            })
+            .on_event(|app, event| {
+                // ffmpeg would keep recording after the app exits, and the files would be left unfinished
+                if let RunEvent::Exit = event {
+                    let media_capture = app.media_capture();
+                    for id in media_capture.recordings() {
+                        let _ = media_capture.stop_recording(id);
+                    }
+                }
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
@@ -0,0 +1,99 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Video4Linux cameras and ALSA microphones, listed from sysfs and procfs.
+
+use std::fs;
+
+use crate::{
+    ffmpeg::{video_args, Ffmpeg},
+    Device, DeviceKind, InputOptions, Result,
+};
+
+/// A single camera has several nodes, only the first one captures the video, the others are its metadata.
+fn cameras() -> Vec<Device> {
+    let Ok(entries) = fs::read_dir("/sys/class/video4linux") else {
+        return Vec::new();
+    };
+    let mut nodes = entries
+        .flatten()
+        .filter_map(|entry| {
+            let node = entry.file_name().to_string_lossy().into_owned();
+            let number = node.strip_prefix("video")?.parse::<u32>().ok()?;
+            let index = fs::read_to_string(entry.path().join("index")).unwrap_or_default();
+            if index.trim() != "0" {
+                return None;
+            }
+            let name =
+                fs::read_to_string(entry.path().join("name")).unwrap_or_else(|_| node.clone());
+            Some((
+                number,
+                Device {
+                    id: format!("/dev/{}", node),
+                    name: name.trim().to_string(),
+                    kind: DeviceKind::Camera,
+                },
+            ))
+        })
+        .collect::<Vec<_>>();
+    nodes.sort_by_key(|(number, _)| *number);
+    nodes.into_iter().map(|(_, device)| device).collect()
+}
+
+/// The lines look like `00-00: ALC3246 Analog : ALC3246 Analog : playback 1 : capture 1`.
+fn microphones() -> Vec<Device> {
+    let mut devices = vec![Device {
+        id: "default".into(),
+        name: "Default".into(),
+        kind: DeviceKind::Microphone,
+    }];
+    let pcm = fs::read_to_string("/proc/asound/pcm").unwrap_or_default();
+    devices.extend(pcm.lines().filter_map(|line| {
+        let mut fields = line.split(" : ");
+        let (numbers, name) = fields.next()?.split_once(':')?;
+        if !fields.any(|field| field.trim().starts_with("capture")) {
+            return None;
+        }
+        let (card, device) = numbers.split_once('-')?;
+        let (card, device) = (card.parse::<u32>().ok()?, device.parse::<u32>().ok()?);
+        Some(Device {
+            id: format!("hw:{},{}", card, device),
+            name: name.trim().to_string(),
+            kind: DeviceKind::Microphone,
+        })
+    }));
+    devices
+}
+
+pub(crate) fn devices(_ffmpeg: &Ffmpeg) -> Result<Vec<Device>> {
+    let mut devices = cameras();
+    devices.extend(microphones());
+    Ok(devices)
+}
+
+pub(crate) fn input_args(
+    camera: Option<&str>,
+    microphone: Option<&str>,
+    options: &InputOptions,
+) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(camera) = camera {
+        args.extend(["-f".into(), "v4l2".into()]);
+        args.extend(video_args(options, None));
+        args.extend(["-i".into(), camera.into()]);
+    }
+    if let Some(microphone) = microphone {
+        args.extend(["-f".into(), "alsa".into(), "-i".into(), microphone.into()]);
+    }
+    args
+}
+
+/// The devices are opened with the permissions of the user.
+pub(crate) fn has_permission(_kind: DeviceKind) -> bool {
+    true
+}
+
+pub(crate) fn request_permission(_kind: DeviceKind) -> bool {
+    true
+}
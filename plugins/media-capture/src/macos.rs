@@ -0,0 +1,127 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! AVFoundation devices, and the Camera and Microphone permissions.
+
+use block::ConcreteBlock;
+use objc::{
+    class, msg_send,
+    runtime::{Object, BOOL, NO},
+    sel, sel_impl,
+};
+
+use std::sync::mpsc::channel;
+
+use crate::{
+    ffmpeg::{log_lines, video_args, Ffmpeg},
+    Device, DeviceKind, InputOptions, Result,
+};
+
+type Id = *mut Object;
+
+/// `AVAuthorizationStatusAuthorized`.
+const AUTHORIZED: isize = 3;
+/// AVFoundation only captures at the frame rates of the camera, ffmpeg defaults to 29.97.
+const DEFAULT_FRAME_RATE: u32 = 30;
+
+#[link(name = "AVFoundation", kind = "framework")]
+extern "C" {
+    static AVMediaTypeVideo: Id;
+    static AVMediaTypeAudio: Id;
+}
+
+fn media_type(kind: DeviceKind) -> Id {
+    unsafe {
+        match kind {
+            DeviceKind::Camera => AVMediaTypeVideo,
+            DeviceKind::Microphone => AVMediaTypeAudio,
+        }
+    }
+}
+
+/// The log looks like:
+///
+/// ```text
+/// [AVFoundation indev @ 0x7f8] AVFoundation video devices:
+/// [AVFoundation indev @ 0x7f8] [0] FaceTime HD Camera
+/// [AVFoundation indev @ 0x7f8] [1] Capture screen 0
+/// [AVFoundation indev @ 0x7f8] AVFoundation audio devices:
+/// [AVFoundation indev @ 0x7f8] [0] MacBook Pro Microphone
+/// ```
+pub(crate) fn devices(ffmpeg: &Ffmpeg) -> Result<Vec<Device>> {
+    let log = ffmpeg.log(&["-f", "avfoundation", "-list_devices", "true", "-i", ""])?;
+    let mut kind = None;
+    let mut devices = Vec::new();
+    for line in log_lines(&log) {
+        if line.ends_with("video devices:") {
+            kind.replace(DeviceKind::Camera);
+        } else if line.ends_with("audio devices:") {
+            kind.replace(DeviceKind::Microphone);
+        } else if let (Some(kind), Some((index, name))) = (
+            kind,
+            line.strip_prefix('[')
+                .and_then(|line| line.split_once("] ")),
+        ) {
+            // the screens are listed with the cameras
+            if !name.starts_with("Capture screen") {
+                devices.push(Device {
+                    id: index.into(),
+                    name: name.trim().into(),
+                    kind,
+                });
+            }
+        }
+    }
+    Ok(devices)
+}
+
+pub(crate) fn input_args(
+    camera: Option<&str>,
+    microphone: Option<&str>,
+    options: &InputOptions,
+) -> Vec<String> {
+    let mut args = vec!["-f".into(), "avfoundation".into()];
+    if camera.is_some() {
+        args.extend(video_args(options, Some(DEFAULT_FRAME_RATE)));
+    }
+    args.extend([
+        "-i".into(),
+        format!(
+            "{}:{}",
+            camera.unwrap_or("none"),
+            microphone.unwrap_or("none")
+        ),
+    ]);
+    args
+}
+
+pub(crate) fn has_permission(kind: DeviceKind) -> bool {
+    let status: isize = unsafe {
+        msg_send![class!(AVCaptureDevice), authorizationStatusForMediaType: media_type(kind)]
+    };
+    status == AUTHORIZED
+}
+
+/// Prompts the user the first time, the answer is remembered by the system.
+///
+/// The app must describe its use with `NSCameraUsageDescription` and `NSMicrophoneUsageDescription` in its `Info.plist`,
+/// it is killed otherwise.
+pub(crate) fn request_permission(kind: DeviceKind) -> bool {
+    if has_permission(kind) {
+        return true;
+    }
+    let (tx, rx) = channel();
+    let handler = ConcreteBlock::new(move |granted: BOOL| {
+        let _ = tx.send(granted != NO);
+    })
+    .copy();
+    unsafe {
+        let _: () = msg_send![
+            class!(AVCaptureDevice),
+            requestAccessForMediaType: media_type(kind)
+            completionHandler: &*handler
+        ];
+    }
+    rx.recv().unwrap_or(false)
+}
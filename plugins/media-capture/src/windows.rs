@@ -0,0 +1,74 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! DirectShow devices.
+
+use crate::{
+    ffmpeg::{log_lines, video_args, Ffmpeg},
+    Device, DeviceKind, InputOptions, Result,
+};
+
+/// Recent versions of ffmpeg log `"HD Webcam" (video)`, older ones list the devices under
+/// `DirectShow video devices` and `DirectShow audio devices` headers.
+/// Both follow every device with its `Alternative name`.
+pub(crate) fn devices(ffmpeg: &Ffmpeg) -> Result<Vec<Device>> {
+    let log = ffmpeg.log(&["-list_devices", "true", "-f", "dshow", "-i", "dummy"])?;
+    let mut section = None;
+    let mut devices = Vec::new();
+    for line in log_lines(&log) {
+        if line.contains("DirectShow video devices") {
+            section.replace(DeviceKind::Camera);
+        } else if line.contains("DirectShow audio devices") {
+            section.replace(DeviceKind::Microphone);
+        } else if let Some((name, rest)) =
+            line.strip_prefix('"').and_then(|line| line.split_once('"'))
+        {
+            let kind = if rest.contains("video") {
+                Some(DeviceKind::Camera)
+            } else if rest.contains("audio") {
+                Some(DeviceKind::Microphone)
+            } else if rest.contains("none") {
+                None
+            } else {
+                section
+            };
+            if let Some(kind) = kind {
+                devices.push(Device {
+                    id: name.into(),
+                    name: name.into(),
+                    kind,
+                });
+            }
+        }
+    }
+    Ok(devices)
+}
+
+pub(crate) fn input_args(
+    camera: Option<&str>,
+    microphone: Option<&str>,
+    options: &InputOptions,
+) -> Vec<String> {
+    let mut args = vec!["-f".into(), "dshow".into()];
+    if camera.is_some() {
+        args.extend(video_args(options, None));
+    }
+    let input = camera
+        .map(|camera| format!("video={}", camera))
+        .into_iter()
+        .chain(microphone.map(|microphone| format!("audio={}", microphone)))
+        .collect::<Vec<_>>()
+        .join(":");
+    args.extend(["-i".into(), input]);
+    args
+}
+
+/// Denying the access in the privacy settings makes the capture fail, it can't be requested.
+pub(crate) fn has_permission(_kind: DeviceKind) -> bool {
+    true
+}
+
+pub(crate) fn request_permission(_kind: DeviceKind) -> bool {
+    true
+}
@@ -0,0 +1,148 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+import { appWindow } from "@tauri-apps/api/window";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface PortInfo {
+  /** Identifies the port, the backends don't give a stable id. */
+  name: string;
+  kind: "input" | "output";
+}
+
+export interface MidiMessage {
+  /** In microseconds, from the clock of the backend: only the difference between two messages is meaningful. */
+  timestamp: number;
+  data: Uint8Array;
+}
+
+interface MessagePayload {
+  id: number;
+  timestamp: number;
+  data: number[];
+}
+
+/**
+ * An open input, its messages are given to the handler until it's closed.
+ */
+class MidiInput {
+  id: number;
+  private unlisten: UnlistenFn;
+
+  constructor(id: number, unlisten: UnlistenFn) {
+    this.id = id;
+    this.unlisten = unlisten;
+  }
+
+  async close(): Promise<void> {
+    this.unlisten();
+    return await invoke("plugin:midi|close", { id: this.id });
+  }
+}
+
+/**
+ * An open output.
+ */
+class MidiOutput {
+  id: number;
+
+  constructor(id: number) {
+    this.id = id;
+  }
+
+  /**
+   * Sends a complete message, e.g. `[0x90, 60, 127]` for a note on.
+   */
+  async send(message: Uint8Array | number[]): Promise<void> {
+    return await invoke("plugin:midi|send", {
+      id: this.id,
+      message: Array.from(message),
+    });
+  }
+
+  async close(): Promise<void> {
+    return await invoke("plugin:midi|close", { id: this.id });
+  }
+}
+
+/**
+ * Lists the inputs then the outputs.
+ */
+async function ports(): Promise<PortInfo[]> {
+  return await invoke("plugin:midi|ports");
+}
+
+/**
+ * Opens the input, the handler is attached before so the first messages aren't missed.
+ *
+ * The system exclusive messages are ignored unless `sysex` is set.
+ */
+async function openInput(
+  name: string,
+  handler: (message: MidiMessage) => void,
+  options: { sysex?: boolean } = {}
+): Promise<MidiInput> {
+  let id: number | null = null;
+  // the messages arriving before the id is known
+  const pending: MessagePayload[] = [];
+  const dispatch = (payload: MessagePayload): void => {
+    handler({
+      timestamp: payload.timestamp,
+      data: Uint8Array.from(payload.data),
+    });
+  };
+
+  const unlisten = await appWindow.listen<MessagePayload>(
+    "midi://message",
+    ({ payload }) => {
+      if (id === null) {
+        pending.push(payload);
+      } else if (payload.id === id) {
+        dispatch(payload);
+      }
+    }
+  );
+
+  try {
+    id = await invoke<number>("plugin:midi|open_input", {
+      name,
+      sysex: options.sysex,
+    });
+  } catch (e) {
+    unlisten();
+    throw e;
+  }
+  pending.filter((payload) => payload.id === id).forEach(dispatch);
+  return new MidiInput(id, unlisten);
+}
+
+async function openOutput(name: string): Promise<MidiOutput> {
+  const id = await invoke<number>("plugin:midi|open_output", { name });
+  return new MidiOutput(id);
+}
+
+async function onConnect(
+  handler: (port: PortInfo) => void
+): Promise<UnlistenFn> {
+  return await listen<PortInfo>("midi://connected", ({ payload }) =>
+    handler(payload)
+  );
+}
+
+async function onDisconnect(
+  handler: (port: PortInfo) => void
+): Promise<UnlistenFn> {
+  return await listen<PortInfo>("midi://disconnected", ({ payload }) =>
+    handler(payload)
+  );
+}
+
+export {
+  MidiInput,
+  MidiOutput,
+  ports,
+  openInput,
+  openOutput,
+  onConnect,
+  onDisconnect,
+};
@@ -0,0 +1,408 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use midir::{
+    ConnectErrorKind, Ignore, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection,
+};
+use serde::{ser::Serializer, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State, Window, WindowEvent,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+type Id = u32;
+type Result<T> = std::result::Result<T, Error>;
+
+const MESSAGE_EVENT: &str = "midi://message";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Init(#[from] midir::InitError),
+    #[error(transparent)]
+    PortInfo(#[from] midir::PortInfoError),
+    #[error("failed to connect to the port: {0}")]
+    Connect(ConnectErrorKind),
+    #[error(transparent)]
+    Send(#[from] midir::SendError),
+    #[error("port not found: {0}")]
+    PortNotFound(String),
+    #[error("port not open for the given id: {0}")]
+    NotOpen(Id),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "midi";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Init(..) => "init",
+            Self::PortInfo(..) => "port_info",
+            Self::Connect(..) => "connect",
+            Self::Send(..) => "send",
+            Self::PortNotFound(..) => "port_not_found",
+            Self::NotOpen(..) => "not_open",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PortKind {
+    Input,
+    Output,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortInfo {
+    /// Identifies the port, the backends don't give a stable id.
+    pub name: String,
+    pub kind: PortKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MessagePayload<'a> {
+    id: Id,
+    /// In microseconds, from the clock of the backend: only the difference between two messages is meaningful.
+    timestamp: u64,
+    data: &'a [u8],
+}
+
+/// A connection and the label of the window which opened it, closed with the window.
+struct Connection<T> {
+    window: Option<String>,
+    connection: T,
+}
+
+pub struct Midi<R: Runtime> {
+    app: AppHandle<R>,
+    client_name: String,
+    next_id: AtomicU32,
+    inputs: Mutex<HashMap<Id, Connection<MidiInputConnection<()>>>>,
+    outputs: Mutex<HashMap<Id, Connection<MidiOutputConnection>>>,
+    known: Mutex<Vec<PortInfo>>,
+}
+
+impl<R: Runtime> Midi<R> {
+    /// Lists the inputs then the outputs.
+    pub fn ports(&self) -> Result<Vec<PortInfo>> {
+        let input = MidiInput::new(&self.client_name)?;
+        let output = MidiOutput::new(&self.client_name)?;
+        let mut ports = Vec::new();
+        for port in input.ports() {
+            ports.push(PortInfo {
+                name: input.port_name(&port)?,
+                kind: PortKind::Input,
+            });
+        }
+        for port in output.ports() {
+            ports.push(PortInfo {
+                name: output.port_name(&port)?,
+                kind: PortKind::Output,
+            });
+        }
+        Ok(ports)
+    }
+
+    /// Opens the input, the handler is called from a background thread with the timestamp
+    /// of the message in microseconds and its bytes.
+    ///
+    /// The system exclusive messages are ignored unless `sysex` is set.
+    pub fn open_input<F: FnMut(u64, &[u8]) + Send + 'static>(
+        &self,
+        name: &str,
+        sysex: bool,
+        handler: F,
+    ) -> Result<Id> {
+        self.connect_input(None, name, sysex, |_| handler)
+    }
+
+    /// The handler is created with the id of the input.
+    fn connect_input<F: FnMut(u64, &[u8]) + Send + 'static>(
+        &self,
+        window: Option<String>,
+        name: &str,
+        sysex: bool,
+        handler: impl FnOnce(Id) -> F,
+    ) -> Result<Id> {
+        let mut input = MidiInput::new(&self.client_name)?;
+        input.ignore(if sysex { Ignore::None } else { Ignore::Sysex });
+        let port = input
+            .ports()
+            .into_iter()
+            .find(|port| input.port_name(port).is_ok_and(|n| n == name))
+            .ok_or_else(|| Error::PortNotFound(name.into()))?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut handler = handler(id);
+        let connection = input
+            .connect(
+                &port,
+                &self.client_name,
+                move |timestamp, data, _| handler(timestamp, data),
+                (),
+            )
+            .map_err(|e| Error::Connect(e.kind()))?;
+
+        self.inputs
+            .lock()
+            .unwrap()
+            .insert(id, Connection { window, connection });
+        Ok(id)
+    }
+
+    pub fn open_output(&self, name: &str) -> Result<Id> {
+        self.connect_output(None, name)
+    }
+
+    fn connect_output(&self, window: Option<String>, name: &str) -> Result<Id> {
+        let output = MidiOutput::new(&self.client_name)?;
+        let port = output
+            .ports()
+            .into_iter()
+            .find(|port| output.port_name(port).is_ok_and(|n| n == name))
+            .ok_or_else(|| Error::PortNotFound(name.into()))?;
+        let connection = output
+            .connect(&port, &self.client_name)
+            .map_err(|e| Error::Connect(e.kind()))?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.outputs
+            .lock()
+            .unwrap()
+            .insert(id, Connection { window, connection });
+        Ok(id)
+    }
+
+    /// Sends a complete message on the output, e.g. `[0x90, 60, 127]` for a note on.
+    pub fn send(&self, id: Id, message: &[u8]) -> Result<()> {
+        self.outputs
+            .lock()
+            .unwrap()
+            .get_mut(&id)
+            .ok_or(Error::NotOpen(id))?
+            .connection
+            .send(message)?;
+        Ok(())
+    }
+
+    /// Closes the input or output.
+    pub fn close(&self, id: Id) -> Result<()> {
+        // the input may be in its callback, don't wait for it with the lock held
+        let input = self.inputs.lock().unwrap().remove(&id);
+        if let Some(input) = input {
+            input.connection.close();
+            return Ok(());
+        }
+        self.outputs
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .map(|output| {
+                output.connection.close();
+            })
+            .ok_or(Error::NotOpen(id))
+    }
+
+    fn close_window(&self, label: &str) {
+        let inputs = {
+            let mut inputs = self.inputs.lock().unwrap();
+            let ids = inputs
+                .iter()
+                .filter(|(_, input)| input.window.as_deref() == Some(label))
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+            ids.into_iter()
+                .filter_map(|id| inputs.remove(&id))
+                .collect::<Vec<_>>()
+        };
+        for input in inputs {
+            input.connection.close();
+        }
+        self.outputs
+            .lock()
+            .unwrap()
+            .retain(|_, output| output.window.as_deref() != Some(label));
+    }
+
+    fn refresh(&self) {
+        let ports = match self.ports() {
+            Ok(ports) => ports,
+            Err(e) => {
+                log::warn!("failed to list the MIDI ports: {}", e);
+                return;
+            }
+        };
+
+        let mut known = self.known.lock().unwrap();
+        for port in ports.iter().filter(|port| !known.contains(port)) {
+            let _ = self.app.emit_all("midi://connected", port);
+        }
+        for port in known.iter().filter(|port| !ports.contains(port)) {
+            let _ = self.app.emit_all("midi://disconnected", port);
+        }
+        *known = ports;
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn midi(&self) -> State<'_, Midi<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn midi(&self) -> State<'_, Midi<R>> {
+        self.state::<Midi<R>>()
+    }
+}
+
+#[command]
+async fn ports<R: Runtime>(app: AppHandle<R>) -> Result<Vec<PortInfo>> {
+    app.midi().ports()
+}
+
+/// The messages are emitted to the window as `midi://message` events.
+#[command]
+async fn open_input<R: Runtime>(
+    window: Window<R>,
+    name: String,
+    sysex: Option<bool>,
+) -> Result<Id> {
+    let emitter = window.clone();
+    window.midi().connect_input(
+        Some(window.label().into()),
+        &name,
+        sysex.unwrap_or_default(),
+        |id| {
+            move |timestamp, data: &[u8]| {
+                let _ = emitter.emit(
+                    MESSAGE_EVENT,
+                    MessagePayload {
+                        id,
+                        timestamp,
+                        data,
+                    },
+                );
+            }
+        },
+    )
+}
+
+#[command]
+async fn open_output<R: Runtime>(window: Window<R>, name: String) -> Result<Id> {
+    window
+        .midi()
+        .connect_output(Some(window.label().into()), &name)
+}
+
+#[command]
+async fn send<R: Runtime>(app: AppHandle<R>, id: Id, message: Vec<u8>) -> Result<()> {
+    app.midi().send(id, &message)
+}
+
+#[command]
+async fn close<R: Runtime>(app: AppHandle<R>, id: Id) -> Result<()> {
+    app.midi().close(id)
+}
+
+pub struct Builder {
+    poll_interval: Option<Duration>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            poll_interval: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How often the ports are listed to emit the `midi://connected` and `midi://disconnected` events,
+    /// defaults to 1 second.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval.replace(interval);
+        self
+    }
+
+    /// Disables the `midi://connected` and `midi://disconnected` events.
+    pub fn disable_hotplug(mut self) -> Self {
+        self.poll_interval.take();
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let poll_interval = self.poll_interval;
+
+        PluginBuilder::new("midi")
+            .invoke_handler(tauri::generate_handler![
+                ports,
+                open_input,
+                open_output,
+                send,
+                close
+            ])
+            .setup(move |app| {
+                let midi = Midi {
+                    app: app.clone(),
+                    client_name: app.package_info().name.clone(),
+                    next_id: Default::default(),
+                    inputs: Default::default(),
+                    outputs: Default::default(),
+                    known: Default::default(),
+                };
+                *midi.known.lock().unwrap() = midi.ports().unwrap_or_default();
+                app.manage(midi);
+
+                if let Some(poll_interval) = poll_interval {
+                    let app = app.clone();
+                    spawn(move || loop {
+                        sleep(poll_interval);
+                        app.midi().refresh();
+                    });
+                }
+
+                Ok(())
+            })
+            .on_webview_ready(|window| {
+                let label = window.label().to_string();
+                let app = window.app_handle();
+                window.on_window_event(move |event| {
+                    if let WindowEvent::Destroyed = event {
+                        app.midi().close_window(&label);
+                    }
+                });
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
@@ -0,0 +1,72 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export type ConnectionType = "none" | "wifi" | "ethernet" | "cellular" | "other";
+
+export interface NetworkStatus {
+  /** Whether the internet is reachable, `false` behind a captive portal. */
+  online: boolean;
+  connectionType: ConnectionType;
+  /** Whether the connection is billed by usage or has a data cap, e.g. cellular or tethering. */
+  metered: boolean;
+  /** Whether requests are intercepted by a login page, e.g. on hotel or airport networks. */
+  captivePortal: boolean;
+}
+
+export interface WaitOptions {
+  /** Whether a metered connection is good enough. Defaults to `false`. */
+  allowMetered?: boolean;
+}
+
+/** Checks the connection now. */
+export async function status(): Promise<NetworkStatus> {
+  return await invoke("plugin:network-status|status");
+}
+
+export async function onChange(
+  handler: (status: NetworkStatus) => void
+): Promise<UnlistenFn> {
+  return await listen<NetworkStatus>("network-status://changed", (event) =>
+    handler(event.payload)
+  );
+}
+
+/**
+ * Resolves once the app is online, e.g. to hold uploads or sync until the connection is back.
+ *
+ * ```ts
+ * await waitForConnection();
+ * await upload(url, path);
+ * ```
+ */
+export async function waitForConnection(
+  options: WaitOptions = {}
+): Promise<NetworkStatus> {
+  const usable = (s: NetworkStatus): boolean =>
+    s.online && (options.allowMetered === true || !s.metered);
+
+  return await new Promise((resolve, reject) => {
+    let unlisten: UnlistenFn | undefined;
+    let done = false;
+    const finish = (s: NetworkStatus): void => {
+      if (!done && usable(s)) {
+        done = true;
+        unlisten?.();
+        resolve(s);
+      }
+    };
+
+    onChange(finish)
+      .then(async (fn) => {
+        unlisten = fn;
+        if (done) {
+          fn();
+          return;
+        }
+        finish(await status());
+      })
+      .catch(reject);
+  });
+}
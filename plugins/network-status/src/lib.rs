@@ -0,0 +1,290 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use reqwest::{redirect::Policy, Client, StatusCode};
+use serde::{ser::Serializer, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, BusExt, PluginError};
+
+use std::{
+    sync::Mutex,
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+type Result<T> = std::result::Result<T, Error>;
+type ChangeHandler = dyn Fn(&NetworkStatus) + Send + Sync;
+
+/// Answers `204 No Content` to every request, anything else means the request was intercepted.
+pub const DEFAULT_PROBE_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "network-status";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Request(..) => "request",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Request(e) => e.is_timeout() || e.is_connect(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionType {
+    #[default]
+    None,
+    Wifi,
+    Ethernet,
+    Cellular,
+    /// Connections through virtual interfaces, e.g. VPN tunnels.
+    Other,
+}
+
+/// What the operating system reports about the default route.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Connection {
+    up: bool,
+    connection_type: ConnectionType,
+    metered: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Probe {
+    Reachable,
+    CaptivePortal,
+    Unreachable,
+}
+
+/// Published on the [`tauri_plugin_common::Bus`] of the app when it changes, for the other plugins.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+    /// Whether the internet is reachable, `false` behind a captive portal.
+    pub online: bool,
+    pub connection_type: ConnectionType,
+    /// Whether the connection is billed by usage or has a data cap, e.g. cellular or tethering.
+    pub metered: bool,
+    /// Whether requests are intercepted by a login page, e.g. on hotel or airport networks.
+    pub captive_portal: bool,
+}
+
+impl NetworkStatus {
+    fn new(connection: Connection, probe: Option<Probe>) -> Self {
+        match (connection.up, probe) {
+            (false, _) => Self::default(),
+            (true, probe) => Self {
+                online: matches!(probe, None | Some(Probe::Reachable)),
+                connection_type: connection.connection_type,
+                metered: connection.metered,
+                captive_portal: probe == Some(Probe::CaptivePortal),
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+struct Cache {
+    connection: Connection,
+    probe: Option<Probe>,
+    status: NetworkStatus,
+}
+
+pub struct Network<R: Runtime> {
+    app: AppHandle<R>,
+    client: Client,
+    probe_url: Option<String>,
+    cache: Mutex<Cache>,
+    handlers: Mutex<Vec<Box<ChangeHandler>>>,
+}
+
+impl<R: Runtime> Network<R> {
+    /// The status found by the last check.
+    pub fn status(&self) -> NetworkStatus {
+        self.cache.lock().unwrap().status.clone()
+    }
+
+    /// Checks the connection now, probing for a captive portal even if the connection didn't change.
+    pub async fn refresh(&self) -> NetworkStatus {
+        self.update(true).await
+    }
+
+    /// Calls `handler` every time the status changes.
+    pub fn on_change<F: Fn(&NetworkStatus) + Send + Sync + 'static>(&self, handler: F) {
+        self.handlers.lock().unwrap().push(Box::new(handler));
+    }
+
+    async fn probe(&self) -> Option<Probe> {
+        let url = self.probe_url.as_ref()?;
+        Some(match self.client.get(url).send().await {
+            Ok(response) if response.status() == StatusCode::NO_CONTENT => Probe::Reachable,
+            Ok(_) => Probe::CaptivePortal,
+            Err(_) => Probe::Unreachable,
+        })
+    }
+
+    async fn update(&self, force: bool) -> NetworkStatus {
+        let connection = platform::connection();
+        let (cached, cached_probe) = {
+            let cache = self.cache.lock().unwrap();
+            (cache.connection, cache.probe)
+        };
+
+        // probing is only needed when the connection changed, or until the internet is reachable
+        let probe = if !connection.up {
+            None
+        } else if force || connection != cached || cached_probe != Some(Probe::Reachable) {
+            self.probe().await
+        } else {
+            cached_probe
+        };
+
+        let status = NetworkStatus::new(connection, probe);
+        let changed = {
+            let mut cache = self.cache.lock().unwrap();
+            cache.connection = connection;
+            cache.probe = probe;
+            std::mem::replace(&mut cache.status, status.clone()) != status
+        };
+
+        if changed {
+            for handler in self.handlers.lock().unwrap().iter() {
+                handler(&status);
+            }
+            self.app.bus().publish(status.clone());
+            let _ = self.app.emit_all("network-status://changed", &status);
+        }
+        status
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn network(&self) -> State<'_, Network<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn network(&self) -> State<'_, Network<R>> {
+        self.state::<Network<R>>()
+    }
+}
+
+#[command]
+async fn status<R: Runtime>(app: AppHandle<R>) -> Result<NetworkStatus> {
+    Ok(app.network().refresh().await)
+}
+
+pub struct Builder {
+    poll_interval: Duration,
+    probe_url: Option<String>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            probe_url: Some(DEFAULT_PROBE_URL.into()),
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How often the connection is checked, defaults to 5 seconds.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// The URL requested to detect captive portals, it must answer `204 No Content`.
+    ///
+    /// Defaults to [`DEFAULT_PROBE_URL`].
+    pub fn probe_url(mut self, url: impl Into<String>) -> Self {
+        self.probe_url.replace(url.into());
+        self
+    }
+
+    /// Trusts the operating system, the app is considered online whenever there is a default route.
+    pub fn disable_probe(mut self) -> Self {
+        self.probe_url.take();
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let Self {
+            poll_interval,
+            probe_url,
+        } = self;
+
+        PluginBuilder::new("network-status")
+            .invoke_handler(tauri::generate_handler![status])
+            .setup(move |app| {
+                let client = Client::builder()
+                    .redirect(Policy::none())
+                    .timeout(PROBE_TIMEOUT)
+                    .build()?;
+                app.manage(Network {
+                    app: app.clone(),
+                    client,
+                    probe_url,
+                    cache: Default::default(),
+                    handlers: Default::default(),
+                });
+
+                let app = app.clone();
+                spawn(move || loop {
+                    tauri::async_runtime::block_on(app.network().update(false));
+                    sleep(poll_interval);
+                });
+
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
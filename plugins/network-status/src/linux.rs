@@ -0,0 +1,70 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{fs::read_to_string, path::Path, process::Command};
+
+use crate::{Connection, ConnectionType};
+
+/// `ARPHRD_PPP` in `linux/if_arp.h`.
+const ARPHRD_PPP: &str = "512";
+/// `RTF_UP` in `linux/route.h`.
+const RTF_UP: u32 = 0x1;
+
+/// The interface of the default route with the lowest metric.
+fn default_interface() -> Option<String> {
+    let routes = read_to_string("/proc/net/route").ok()?;
+    routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+            let metric = fields.get(6)?.parse::<u32>().ok()?;
+            (fields.get(1) == Some(&"00000000") && flags & RTF_UP != 0)
+                .then(|| (metric, fields[0].to_string()))
+        })
+        .min()
+        .map(|(_, interface)| interface)
+}
+
+fn connection_type(interface: &str) -> ConnectionType {
+    let dir = Path::new("/sys/class/net").join(interface);
+    if dir.join("wireless").exists() || dir.join("phy80211").exists() {
+        ConnectionType::Wifi
+    } else if interface.starts_with("wwan")
+        || read_to_string(dir.join("type")).is_ok_and(|t| t.trim() == ARPHRD_PPP)
+    {
+        ConnectionType::Cellular
+    } else if dir.join("device").exists() {
+        ConnectionType::Ethernet
+    } else {
+        // virtual interfaces, e.g. VPN tunnels or bridges
+        ConnectionType::Other
+    }
+}
+
+/// Asks NetworkManager, which either knows or guesses from the device whether the connection is metered.
+fn metered(interface: &str) -> Option<bool> {
+    let output = Command::new("nmcli")
+        .args(["-t", "-g", "GENERAL.METERED", "device", "show", interface])
+        .output()
+        .ok()?;
+    output.status.success().then(|| {
+        String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .starts_with("yes")
+    })
+}
+
+pub(crate) fn connection() -> Connection {
+    let Some(interface) = default_interface() else {
+        return Connection::default();
+    };
+    let connection_type = connection_type(&interface);
+    Connection {
+        up: true,
+        metered: metered(&interface).unwrap_or(connection_type == ConnectionType::Cellular),
+        connection_type,
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::process::Command;
+
+use crate::{Connection, ConnectionType};
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn default_interface() -> Option<String> {
+    run("route", &["-n", "get", "default"])?
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("interface:"))
+        .map(|interface| interface.trim().to_string())
+}
+
+/// The name of the hardware port of `interface`, e.g. `Wi-Fi` or `Thunderbolt Ethernet`.
+fn hardware_port(interface: &str) -> Option<String> {
+    let ports = run("networksetup", &["-listallhardwareports"])?;
+    let mut port = None;
+    for line in ports.lines() {
+        if let Some(name) = line.strip_prefix("Hardware Port:") {
+            port = Some(name.trim());
+        } else if line.strip_prefix("Device:").map(str::trim) == Some(interface) {
+            return port.map(Into::into);
+        }
+    }
+    None
+}
+
+pub(crate) fn connection() -> Connection {
+    let Some(interface) = default_interface() else {
+        return Connection::default();
+    };
+    let connection_type = match hardware_port(&interface).as_deref() {
+        Some("Wi-Fi" | "AirPort") => ConnectionType::Wifi,
+        // tethering through a phone
+        Some(port) if port.starts_with("iPhone") || port.starts_with("iPad") => {
+            ConnectionType::Cellular
+        }
+        Some(port) if port.contains("Ethernet") || port.contains("LAN") => ConnectionType::Ethernet,
+        _ => ConnectionType::Other,
+    };
+    Connection {
+        up: true,
+        metered: connection_type == ConnectionType::Cellular,
+        connection_type,
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use windows::Networking::Connectivity::{
+    ConnectionProfile, NetworkConnectivityLevel, NetworkCostType, NetworkInformation,
+};
+
+use crate::{Connection, ConnectionType};
+
+fn metered(profile: &ConnectionProfile) -> windows::core::Result<bool> {
+    let cost = profile.GetConnectionCost()?;
+    Ok(matches!(
+        cost.NetworkCostType()?,
+        NetworkCostType::Fixed | NetworkCostType::Variable
+    ) || cost.Roaming()?
+        || cost.OverDataLimit()?)
+}
+
+pub(crate) fn connection() -> Connection {
+    let Ok(profile) = NetworkInformation::GetInternetConnectionProfile() else {
+        return Connection::default();
+    };
+    let level = profile
+        .GetNetworkConnectivityLevel()
+        .unwrap_or(NetworkConnectivityLevel::None);
+    // `ConstrainedInternetAccess` usually means a captive portal, which the probe reports
+    if level != NetworkConnectivityLevel::InternetAccess
+        && level != NetworkConnectivityLevel::ConstrainedInternetAccess
+    {
+        return Connection::default();
+    }
+
+    let connection_type = if profile.IsWlanConnectionProfile().unwrap_or(false) {
+        ConnectionType::Wifi
+    } else if profile.IsWwanConnectionProfile().unwrap_or(false) {
+        ConnectionType::Cellular
+    } else {
+        ConnectionType::Ethernet
+    };
+    Connection {
+        up: true,
+        metered: metered(&profile).unwrap_or(false),
+        connection_type,
+    }
+}
@@ -0,0 +1,99 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+/** The action reported when the body of the notification is clicked. */
+export const DEFAULT_ACTION = "default";
+
+export interface Action {
+  id: string;
+  title: string;
+  /**
+   * Makes this an inline reply action with the given placeholder, on Windows, macOS and KDE.
+   * A notification supports a single reply action, which replaces the other actions on macOS.
+   */
+  input?: string;
+}
+
+export interface Schedule {
+  /** When to show the notification. */
+  at: Date | number;
+  /** Shows the notification again every `every` seconds. */
+  every?: number;
+}
+
+export interface Notification {
+  /** Generated when not set. Showing a notification with the same id replaces the previous one. */
+  id?: string;
+  title: string;
+  body?: string;
+  actions?: Action[];
+  /** Shows the notification later instead of immediately, the schedule is kept across restarts. */
+  schedule?: Schedule;
+}
+
+export interface ActionEvent {
+  /** The id of the notification. */
+  id: string;
+  /** The id of the action, `DEFAULT_ACTION` when the notification itself was clicked. */
+  action: string;
+  /** The text entered in a reply action. */
+  reply: string | null;
+}
+
+export interface DismissEvent {
+  id: string;
+  reason: "user" | "expired" | "app" | "unknown";
+}
+
+/** Shows the notification, or schedules it if it has a schedule. Resolves to its id. */
+export async function show(notification: Notification): Promise<string> {
+  const schedule =
+    notification.schedule === undefined
+      ? undefined
+      : {
+          ...notification.schedule,
+          at: new Date(notification.schedule.at).getTime(),
+        };
+  return await invoke("plugin:notifications|show", {
+    notification: { ...notification, schedule },
+  });
+}
+
+/** Cancels a scheduled notification and closes it if it is shown. */
+export async function cancel(id: string): Promise<void> {
+  await invoke("plugin:notifications|cancel", { id });
+}
+
+export async function scheduled(): Promise<Notification[]> {
+  return await invoke("plugin:notifications|scheduled");
+}
+
+/** The ids of the notifications shown and not acted upon or dismissed yet. */
+export async function delivered(): Promise<string[]> {
+  return await invoke("plugin:notifications|delivered");
+}
+
+/**
+ * Calls `handler` when the action with the given id is triggered on any notification.
+ * Use `DEFAULT_ACTION` to handle clicks on the notifications themselves.
+ */
+export async function onAction(
+  action: string,
+  handler: (event: ActionEvent) => void
+): Promise<UnlistenFn> {
+  return await listen<ActionEvent>("notifications://action", (event) => {
+    if (event.payload.action === action) {
+      handler(event.payload);
+    }
+  });
+}
+
+export async function onDismiss(
+  handler: (event: DismissEvent) => void
+): Promise<UnlistenFn> {
+  return await listen<DismissEvent>("notifications://dismissed", (event) =>
+    handler(event.payload)
+  );
+}
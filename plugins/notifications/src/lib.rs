@@ -0,0 +1,499 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use log::error;
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{create_dir_all, read_to_string, rename, write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::{sleep, spawn},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+type Result<T> = std::result::Result<T, Error>;
+type ActionHandler<R> = dyn Fn(&AppHandle<R>, &ActionEvent) + Send + Sync;
+type DismissHandler<R> = dyn Fn(&AppHandle<R>, &DismissEvent) + Send + Sync;
+type Feedback = Arc<dyn Fn(PlatformEvent) + Send + Sync>;
+
+pub const SCHEDULE_FILENAME: &str = ".notifications";
+
+/// The action reported when the body of the notification is clicked.
+pub const DEFAULT_ACTION: &str = "default";
+
+const TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[cfg(target_os = "linux")]
+    #[error(transparent)]
+    DBus(#[from] zbus::Error),
+    #[cfg(target_os = "macos")]
+    #[error(transparent)]
+    MacOs(#[from] mac_notification_sys::error::Error),
+    #[cfg(windows)]
+    #[error(transparent)]
+    Windows(#[from] ::windows::core::Error),
+    #[error("`{0}` is reserved for clicks on the notification")]
+    ReservedAction(String),
+    #[error("notification `{0}` not found")]
+    NotFound(String),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "notifications";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Json(..) => "json",
+            #[cfg(target_os = "linux")]
+            Self::DBus(..) => "dbus",
+            #[cfg(target_os = "macos")]
+            Self::MacOs(..) => "macos",
+            #[cfg(windows)]
+            Self::Windows(..) => "windows",
+            Self::ReservedAction(..) => "reserved_action",
+            Self::NotFound(..) => "not_found",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Action {
+    pub id: String,
+    pub title: String,
+    /// Makes this an inline reply action with the given placeholder, on Windows, macOS and KDE.
+    ///
+    /// A notification supports a single reply action, which replaces the other actions on macOS.
+    #[serde(default)]
+    pub input: Option<String>,
+}
+
+impl Action {
+    pub fn new(id: impl Into<String>, title: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            input: None,
+        }
+    }
+
+    pub fn reply(id: impl Into<String>, title: impl Into<String>, placeholder: &str) -> Self {
+        Self {
+            input: Some(placeholder.into()),
+            ..Self::new(id, title)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    /// When to show the notification, in milliseconds since the Unix epoch.
+    pub at: u64,
+    /// Shows the notification again every `every` seconds.
+    #[serde(default)]
+    pub every: Option<u64>,
+}
+
+impl Schedule {
+    /// The next occurrence after `now`, `None` once a notification that doesn't repeat is shown.
+    fn next_after(&self, now: u64) -> Option<u64> {
+        let every = self.every.filter(|every| *every > 0)? * 1000;
+        let missed = now.saturating_sub(self.at) / every + 1;
+        Some(self.at + missed * every)
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    /// Generated when not set. Showing a notification with the same id replaces the previous one.
+    #[serde(default)]
+    pub id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub actions: Vec<Action>,
+    /// Shows the notification later instead of immediately, the schedule is kept across restarts.
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+}
+
+impl Notification {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id.replace(id.into());
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.body.replace(body.into());
+        self
+    }
+
+    pub fn action(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule.replace(schedule);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionEvent {
+    /// The id of the notification.
+    pub id: String,
+    /// The id of the action, [`DEFAULT_ACTION`] when the notification itself was clicked.
+    pub action: String,
+    /// The text entered in a reply action.
+    pub reply: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DismissReason {
+    User,
+    Expired,
+    /// The app closed the notification.
+    App,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DismissEvent {
+    pub id: String,
+    pub reason: DismissReason,
+}
+
+/// What the platform backends report.
+pub(crate) enum PlatformEvent {
+    Action(ActionEvent),
+    Dismissed(DismissEvent),
+}
+
+pub struct Notifications<R: Runtime> {
+    app: AppHandle<R>,
+    backend: platform::Backend,
+    path: Option<PathBuf>,
+    next_id: AtomicU32,
+    scheduled: Mutex<HashMap<String, Notification>>,
+    /// The notifications shown and not acted upon or dismissed yet.
+    delivered: Mutex<HashSet<String>>,
+    action_handlers: Mutex<HashMap<String, Vec<Box<ActionHandler<R>>>>>,
+    dismiss_handlers: Mutex<Vec<Box<DismissHandler<R>>>>,
+}
+
+impl<R: Runtime> Notifications<R> {
+    /// Shows the notification, or schedules it if it has a [`Schedule`]. Returns its id.
+    pub fn show(&self, mut notification: Notification) -> Result<String> {
+        if let Some(action) = notification
+            .actions
+            .iter()
+            .find(|action| action.id == DEFAULT_ACTION)
+        {
+            return Err(Error::ReservedAction(action.id.clone()));
+        }
+
+        let id = notification.id.get_or_insert_with(|| {
+            format!(
+                "{:x}-{:x}",
+                now(),
+                self.next_id.fetch_add(1, Ordering::Relaxed)
+            )
+        });
+        let id = id.clone();
+
+        if notification.schedule.is_some() {
+            self.scheduled
+                .lock()
+                .unwrap()
+                .insert(id.clone(), notification);
+            self.save()?;
+        } else {
+            self.deliver(&id, &notification)?;
+        }
+        Ok(id)
+    }
+
+    /// Cancels a scheduled notification and closes it if it is shown.
+    pub fn cancel(&self, id: &str) -> Result<()> {
+        let scheduled = self.scheduled.lock().unwrap().remove(id).is_some();
+        if scheduled {
+            self.save()?;
+        }
+        let delivered = self.delivered.lock().unwrap().contains(id);
+        if delivered {
+            self.backend.close(id)?;
+        }
+
+        if scheduled || delivered {
+            Ok(())
+        } else {
+            Err(Error::NotFound(id.into()))
+        }
+    }
+
+    pub fn scheduled(&self) -> Vec<Notification> {
+        self.scheduled.lock().unwrap().values().cloned().collect()
+    }
+
+    /// The ids of the notifications shown and not acted upon or dismissed yet.
+    pub fn delivered(&self) -> Vec<String> {
+        self.delivered.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Calls `handler` when the action with the given id is triggered on any notification.
+    ///
+    /// Use [`DEFAULT_ACTION`] to handle clicks on the notifications themselves.
+    pub fn on_action<F: Fn(&AppHandle<R>, &ActionEvent) + Send + Sync + 'static>(
+        &self,
+        action: impl Into<String>,
+        handler: F,
+    ) {
+        self.action_handlers
+            .lock()
+            .unwrap()
+            .entry(action.into())
+            .or_default()
+            .push(Box::new(handler));
+    }
+
+    pub fn on_dismiss<F: Fn(&AppHandle<R>, &DismissEvent) + Send + Sync + 'static>(
+        &self,
+        handler: F,
+    ) {
+        self.dismiss_handlers
+            .lock()
+            .unwrap()
+            .push(Box::new(handler));
+    }
+
+    fn deliver(&self, id: &str, notification: &Notification) -> Result<()> {
+        self.backend.show(id, notification)?;
+        self.delivered.lock().unwrap().insert(id.into());
+        Ok(())
+    }
+
+    fn handle(&self, event: PlatformEvent) {
+        match event {
+            PlatformEvent::Action(event) => {
+                self.delivered.lock().unwrap().remove(&event.id);
+                if let Some(handlers) = self.action_handlers.lock().unwrap().get(&event.action) {
+                    for handler in handlers {
+                        handler(&self.app, &event);
+                    }
+                }
+                let _ = self.app.emit_all("notifications://action", event);
+            }
+            PlatformEvent::Dismissed(event) => {
+                // some platforms also report closing the notification after an action
+                if !self.delivered.lock().unwrap().remove(&event.id) {
+                    return;
+                }
+                for handler in self.dismiss_handlers.lock().unwrap().iter() {
+                    handler(&self.app, &event);
+                }
+                let _ = self.app.emit_all("notifications://dismissed", event);
+            }
+        }
+    }
+
+    fn tick(&self) {
+        let now = now();
+        let due = {
+            let mut scheduled = self.scheduled.lock().unwrap();
+            let due = scheduled
+                .iter()
+                .filter(|(_, n)| n.schedule.is_some_and(|s| s.at <= now))
+                .map(|(id, n)| (id.clone(), n.clone()))
+                .collect::<Vec<_>>();
+            for (id, notification) in &due {
+                match notification.schedule.and_then(|s| s.next_after(now)) {
+                    Some(next) => {
+                        if let Some(schedule) =
+                            scheduled.get_mut(id).and_then(|n| n.schedule.as_mut())
+                        {
+                            schedule.at = next;
+                        }
+                    }
+                    None => {
+                        scheduled.remove(id);
+                    }
+                }
+            }
+            due
+        };
+        if due.is_empty() {
+            return;
+        }
+
+        for (id, notification) in due {
+            if let Err(e) = self.deliver(&id, &notification) {
+                error!("failed to show notification `{}`: {}", id, e);
+            }
+        }
+        if let Err(e) = self.save() {
+            error!("failed to save the scheduled notifications: {}", e);
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let scheduled = serde_json::to_vec(&*self.scheduled.lock().unwrap())?;
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("tmp");
+        write(&tmp, scheduled)?;
+        rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn notifications(&self) -> State<'_, Notifications<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn notifications(&self) -> State<'_, Notifications<R>> {
+        self.state::<Notifications<R>>()
+    }
+}
+
+#[command]
+async fn show<R: Runtime>(app: AppHandle<R>, notification: Notification) -> Result<String> {
+    app.notifications().show(notification)
+}
+
+#[command]
+async fn cancel<R: Runtime>(app: AppHandle<R>, id: String) -> Result<()> {
+    app.notifications().cancel(&id)
+}
+
+#[command]
+async fn scheduled<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Notification>> {
+    Ok(app.notifications().scheduled())
+}
+
+#[command]
+async fn delivered<R: Runtime>(app: AppHandle<R>) -> Result<Vec<String>> {
+    Ok(app.notifications().delivered())
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    PluginBuilder::new("notifications")
+        .invoke_handler(tauri::generate_handler![show, cancel, scheduled, delivered])
+        .setup(|app| {
+            let path = app
+                .path_resolver()
+                .app_data_dir()
+                .map(|dir| dir.join(SCHEDULE_FILENAME));
+            let scheduled = match &path {
+                Some(path) if path.exists() => serde_json::from_str(&read_to_string(path)?)?,
+                _ => HashMap::new(),
+            };
+
+            let handle = app.clone();
+            let backend = platform::Backend::new(
+                &app.config().tauri.bundle.identifier,
+                &app.package_info().name,
+                Arc::new(move |event| handle.notifications().handle(event)),
+            )?;
+
+            app.manage(Notifications {
+                app: app.clone(),
+                backend,
+                path,
+                next_id: Default::default(),
+                scheduled: Mutex::new(scheduled),
+                delivered: Default::default(),
+                action_handlers: Default::default(),
+                dismiss_handlers: Default::default(),
+            });
+
+            let app = app.clone();
+            spawn(move || loop {
+                sleep(TICK);
+                app.notifications().tick();
+            });
+
+            Ok(())
+        })
+        .build()
+}
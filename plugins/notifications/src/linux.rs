@@ -0,0 +1,176 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use log::warn;
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::Value,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread::spawn,
+};
+
+use crate::{
+    ActionEvent, DismissEvent, DismissReason, Feedback, Notification, PlatformEvent, Result,
+    DEFAULT_ACTION,
+};
+
+const DESTINATION: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+/// The action key of the KDE inline replies.
+const INLINE_REPLY: &str = "inline-reply";
+
+struct Shown {
+    id: String,
+    reply_action: Option<String>,
+}
+
+/// Talks to the notification server of the desktop, see the
+/// [Desktop Notifications Specification](https://specifications.freedesktop.org/notification-spec/latest/).
+pub(crate) struct Backend {
+    proxy: Proxy<'static>,
+    app_name: String,
+    /// The notifications shown by the server, by the id it assigned.
+    shown: Arc<Mutex<HashMap<u32, Shown>>>,
+}
+
+fn listen(
+    proxy: Proxy<'static>,
+    shown: Arc<Mutex<HashMap<u32, Shown>>>,
+    feedback: Feedback,
+) -> zbus::Result<()> {
+    for signal in proxy.receive_all_signals()? {
+        let event = match signal.member().as_ref().map(|m| m.as_str()) {
+            Some("ActionInvoked") => {
+                let (server_id, key): (u32, String) = signal.body()?;
+                let shown = shown.lock().unwrap();
+                let Some(notification) = shown.get(&server_id) else {
+                    continue;
+                };
+                PlatformEvent::Action(ActionEvent {
+                    id: notification.id.clone(),
+                    action: key,
+                    reply: None,
+                })
+            }
+            Some("NotificationReplied") => {
+                let (server_id, text): (u32, String) = signal.body()?;
+                let shown = shown.lock().unwrap();
+                let Some(notification) = shown.get(&server_id) else {
+                    continue;
+                };
+                PlatformEvent::Action(ActionEvent {
+                    id: notification.id.clone(),
+                    action: notification.reply_action.clone().unwrap_or_default(),
+                    reply: Some(text),
+                })
+            }
+            Some("NotificationClosed") => {
+                let (server_id, reason): (u32, u32) = signal.body()?;
+                let Some(notification) = shown.lock().unwrap().remove(&server_id) else {
+                    continue;
+                };
+                PlatformEvent::Dismissed(DismissEvent {
+                    id: notification.id,
+                    reason: match reason {
+                        1 => DismissReason::Expired,
+                        2 => DismissReason::User,
+                        3 => DismissReason::App,
+                        _ => DismissReason::Unknown,
+                    },
+                })
+            }
+            _ => continue,
+        };
+        feedback(event);
+    }
+    Ok(())
+}
+
+impl Backend {
+    pub(crate) fn new(_identifier: &str, app_name: &str, feedback: Feedback) -> Result<Self> {
+        let connection = Connection::session()?;
+        let proxy = Proxy::new(&connection, DESTINATION, PATH, DESTINATION)?;
+        let shown = Arc::new(Mutex::new(HashMap::new()));
+
+        let listener = proxy.clone();
+        let shown_ = shown.clone();
+        spawn(move || {
+            if let Err(e) = listen(listener, shown_, feedback) {
+                warn!("failed to listen to notification events: {}", e);
+            }
+        });
+
+        Ok(Self {
+            proxy,
+            app_name: app_name.into(),
+            shown,
+        })
+    }
+
+    pub(crate) fn show(&self, id: &str, notification: &Notification) -> Result<()> {
+        let mut actions = vec![DEFAULT_ACTION, ""];
+        let mut hints = HashMap::<&str, Value<'_>>::new();
+        let mut reply_action = None;
+        for action in &notification.actions {
+            match &action.input {
+                Some(placeholder) if reply_action.is_none() => {
+                    actions.extend([INLINE_REPLY, &action.title]);
+                    hints.insert("x-kde-reply-placeholder-text", placeholder.as_str().into());
+                    reply_action = Some(action.id.clone());
+                }
+                _ => actions.extend([action.id.as_str(), action.title.as_str()]),
+            }
+        }
+
+        // showing a notification again replaces it
+        let replaces = self
+            .shown
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, shown)| shown.id == id)
+            .map_or(0, |(server_id, _)| *server_id);
+
+        let server_id: u32 = self.proxy.call(
+            "Notify",
+            &(
+                &self.app_name,
+                replaces,
+                "",
+                &notification.title,
+                notification.body.as_deref().unwrap_or_default(),
+                actions,
+                hints,
+                -1i32,
+            ),
+        )?;
+        self.shown.lock().unwrap().insert(
+            server_id,
+            Shown {
+                id: id.into(),
+                reply_action,
+            },
+        );
+        Ok(())
+    }
+
+    pub(crate) fn close(&self, id: &str) -> Result<()> {
+        let server_id = self
+            .shown
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, shown)| shown.id == id)
+            .map(|(server_id, _)| *server_id);
+        if let Some(server_id) = server_id {
+            self.proxy
+                .call::<_, _, ()>("CloseNotification", &server_id)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use log::{error, warn};
+use mac_notification_sys::{MainButton, NotificationResponse};
+
+use std::thread::spawn;
+
+use crate::{
+    ActionEvent, DismissEvent, DismissReason, Feedback, Notification, PlatformEvent, Result,
+    DEFAULT_ACTION,
+};
+
+const CLOSE_BUTTON: &str = "Close";
+const ACTIONS_BUTTON: &str = "Actions";
+
+/// Each notification blocks a thread until the user responds, macOS doesn't report which notification
+/// a response belongs to otherwise.
+pub(crate) struct Backend {
+    feedback: Feedback,
+}
+
+impl Backend {
+    pub(crate) fn new(identifier: &str, _app_name: &str, feedback: Feedback) -> Result<Self> {
+        // fails in development, the notifications are then sent on behalf of the terminal
+        if let Err(e) = mac_notification_sys::set_application(identifier) {
+            warn!("failed to send notifications as `{}`: {}", identifier, e);
+        }
+        Ok(Self { feedback })
+    }
+
+    pub(crate) fn show(&self, id: &str, notification: &Notification) -> Result<()> {
+        let id = id.to_string();
+        let notification = notification.clone();
+        let feedback = self.feedback.clone();
+
+        spawn(move || {
+            let titles = notification
+                .actions
+                .iter()
+                .map(|action| action.title.as_str())
+                .collect::<Vec<_>>();
+            let reply = notification
+                .actions
+                .iter()
+                .find(|action| action.input.is_some());
+
+            let mut builder = mac_notification_sys::Notification::new();
+            builder
+                .title(&notification.title)
+                .message(notification.body.as_deref().unwrap_or_default())
+                .close_button(CLOSE_BUTTON)
+                .wait_for_click(true);
+            match (reply, titles.as_slice()) {
+                (Some(reply), _) => {
+                    builder.main_button(MainButton::Response(
+                        reply.input.as_deref().unwrap_or_default(),
+                    ));
+                }
+                (None, []) => {}
+                (None, [title]) => {
+                    builder.main_button(MainButton::SingleAction(title));
+                }
+                (None, titles) => {
+                    builder.main_button(MainButton::DropdownActions(ACTIONS_BUTTON, titles));
+                }
+            }
+
+            let (action, reply_text) = match builder.send() {
+                Ok(NotificationResponse::Click) => (Some(DEFAULT_ACTION.to_string()), None),
+                Ok(NotificationResponse::ActionButton(title)) => (
+                    notification
+                        .actions
+                        .iter()
+                        .find(|action| action.title == title)
+                        .map(|action| action.id.clone()),
+                    None,
+                ),
+                Ok(NotificationResponse::Reply(text)) => {
+                    (reply.map(|action| action.id.clone()), Some(text))
+                }
+                Ok(NotificationResponse::CloseButton(_)) => {
+                    feedback(PlatformEvent::Dismissed(DismissEvent {
+                        id,
+                        reason: DismissReason::User,
+                    }));
+                    return;
+                }
+                Ok(NotificationResponse::None) => {
+                    feedback(PlatformEvent::Dismissed(DismissEvent {
+                        id,
+                        reason: DismissReason::Unknown,
+                    }));
+                    return;
+                }
+                Err(e) => {
+                    error!("failed to show notification `{}`: {}", id, e);
+                    return;
+                }
+            };
+
+            if let Some(action) = action {
+                feedback(PlatformEvent::Action(ActionEvent {
+                    id,
+                    action,
+                    reply: reply_text,
+                }));
+            }
+        });
+        Ok(())
+    }
+
+    /// Notifications can't be withdrawn while waiting for their response.
+    pub(crate) fn close(&self, _id: &str) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use windows::{
+    core::{IInspectable, Interface, HSTRING},
+    Data::Xml::Dom::XmlDocument,
+    Foundation::TypedEventHandler,
+    UI::Notifications::{
+        ToastActivatedEventArgs, ToastDismissalReason, ToastDismissedEventArgs, ToastNotification,
+        ToastNotificationManager, ToastNotifier,
+    },
+};
+
+use crate::{
+    ActionEvent, DismissEvent, DismissReason, Feedback, Notification, PlatformEvent, Result,
+    DEFAULT_ACTION,
+};
+
+const GROUP: &str = "tauri-plugin-notifications";
+const REPLY_INPUT: &str = "reply";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Builds the [toast content](https://learn.microsoft.com/en-us/windows/apps/design/shell/tiles-and-notifications/adaptive-interactive-toasts).
+fn toast_xml(notification: &Notification) -> String {
+    let mut text = format!("<text>{}</text>", escape(&notification.title));
+    if let Some(body) = &notification.body {
+        text.push_str(&format!("<text>{}</text>", escape(body)));
+    }
+
+    // the inputs must come before the actions
+    let mut inputs = String::new();
+    let mut actions = String::new();
+    for action in &notification.actions {
+        match &action.input {
+            Some(placeholder) if inputs.is_empty() => {
+                inputs = format!(
+                    r#"<input id="{}" type="text" placeHolderContent="{}"/>"#,
+                    REPLY_INPUT,
+                    escape(placeholder)
+                );
+                actions.push_str(&format!(
+                    r#"<action content="{}" arguments="{}" hint-inputId="{}"/>"#,
+                    escape(&action.title),
+                    escape(&action.id),
+                    REPLY_INPUT
+                ));
+            }
+            _ => actions.push_str(&format!(
+                r#"<action content="{}" arguments="{}"/>"#,
+                escape(&action.title),
+                escape(&action.id)
+            )),
+        }
+    }
+
+    format!(
+        r#"<toast launch="{}"><visual><binding template="ToastGeneric">{}</binding></visual><actions>{}{}</actions></toast>"#,
+        DEFAULT_ACTION, text, inputs, actions
+    )
+}
+
+/// Shows toasts as the app's AppUserModelID, which is the bundle identifier for installed apps.
+///
+/// Actions are only routed while the app is running.
+pub(crate) struct Backend {
+    notifier: ToastNotifier,
+    app_id: HSTRING,
+    feedback: Feedback,
+}
+
+impl Backend {
+    pub(crate) fn new(identifier: &str, _app_name: &str, feedback: Feedback) -> Result<Self> {
+        let app_id = HSTRING::from(identifier);
+        Ok(Self {
+            notifier: ToastNotificationManager::CreateToastNotifierWithId(&app_id)?,
+            app_id,
+            feedback,
+        })
+    }
+
+    pub(crate) fn show(&self, id: &str, notification: &Notification) -> Result<()> {
+        let document = XmlDocument::new()?;
+        document.LoadXml(&HSTRING::from(toast_xml(notification)))?;
+        let toast = ToastNotification::CreateToastNotification(&document)?;
+        toast.SetTag(&HSTRING::from(id))?;
+        toast.SetGroup(&HSTRING::from(GROUP))?;
+
+        let feedback = self.feedback.clone();
+        let toast_id = id.to_string();
+        toast.Activated(&TypedEventHandler::new(
+            move |_: &Option<ToastNotification>, args: &Option<IInspectable>| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let args = args.cast::<ToastActivatedEventArgs>()?;
+                let reply = args
+                    .UserInput()
+                    .and_then(|input| input.Lookup(&HSTRING::from(REPLY_INPUT)))
+                    .and_then(|value| HSTRING::try_from(&value))
+                    .ok()
+                    .map(|value| value.to_string());
+                feedback(PlatformEvent::Action(ActionEvent {
+                    id: toast_id.clone(),
+                    action: args.Arguments()?.to_string(),
+                    reply,
+                }));
+                Ok(())
+            },
+        ))?;
+
+        let feedback = self.feedback.clone();
+        let toast_id = id.to_string();
+        toast.Dismissed(&TypedEventHandler::new(
+            move |_: &Option<ToastNotification>, args: &Option<ToastDismissedEventArgs>| {
+                let reason = match args.as_ref().map(|args| args.Reason()).transpose()? {
+                    Some(ToastDismissalReason::UserCanceled) => DismissReason::User,
+                    Some(ToastDismissalReason::TimedOut) => DismissReason::Expired,
+                    Some(ToastDismissalReason::ApplicationHidden) => DismissReason::App,
+                    _ => DismissReason::Unknown,
+                };
+                feedback(PlatformEvent::Dismissed(DismissEvent {
+                    id: toast_id.clone(),
+                    reason,
+                }));
+                Ok(())
+            },
+        ))?;
+
+        self.notifier.Show(&toast)?;
+        Ok(())
+    }
+
+    pub(crate) fn close(&self, id: &str) -> Result<()> {
+        ToastNotificationManager::History()?.RemoveGroupedTagWithId(
+            &HSTRING::from(id),
+            &HSTRING::from(GROUP),
+            &self.app_id,
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+import { invoke } from "@tauri-apps/api/tauri";
+
+import type { Change } from "./types/Change";
+import type { ChangedPayload } from "./types/ChangedPayload";
+import type { Entry } from "./types/Entry";
+import type { ErrorPayload } from "./types/ErrorPayload";
+import type { List } from "./types/List";
+import type { RestoreReport } from "./types/RestoreReport";
+import type { RestoreStatus } from "./types/RestoreStatus";
+import type { Restored } from "./types/Restored";
+import type { RestoredEntry } from "./types/RestoredEntry";
+import type { Scope } from "./types/Scope";
+import type { TargetType } from "./types/TargetType";
+
+export type {
+  Change,
+  ChangedPayload,
+  Entry,
+  ErrorPayload,
+  List,
+  RestoreReport,
+  RestoreStatus,
+  Restored,
+  RestoredEntry,
+  Scope,
+  TargetType,
+};
+
+export interface PersistedScope {
+  /** In the order they were granted. */
+  allowed: Entry[];
+  forbidden: Entry[];
+}
+
+export interface ScopeDump {
+  /** The version of the scope file of the plugin that exported it. */
+  version: number;
+  fs: PersistedScope;
+  assetProtocol: PersistedScope;
+}
+
+/**
+ * The paths granted and forbidden at runtime in the scope, `fs` by default, restored on the next launches.
+ */
+async function list(scope?: Scope): Promise<PersistedScope> {
+  return await invoke("plugin:persisted-scope|list", { scope });
+}
+
+/**
+ * Stops persisting the granted path and denies it until the app restarts, in both scopes.
+ *
+ * Resolves to whether the path was persisted.
+ */
+async function removeAllowed(path: string): Promise<boolean> {
+  return await invoke("plugin:persisted-scope|remove_allowed", { path });
+}
+
+/**
+ * How each entry of the scope file was restored on startup.
+ */
+async function restoreReport(): Promise<RestoreReport> {
+  return await invoke("plugin:persisted-scope|restore_report");
+}
+
+/**
+ * The persisted entries of both scopes, e.g. to back up the grants or sync them to another machine.
+ */
+async function exportScope(): Promise<ScopeDump> {
+  return await invoke("plugin:persisted-scope|export_scope");
+}
+
+/**
+ * Grants and forbids the entries of the dump and persists them with the current ones.
+ *
+ * Rejects unless the app enabled it with `Builder::js_import`.
+ */
+async function importScope(dump: ScopeDump): Promise<void> {
+  await invoke("plugin:persisted-scope|import_scope", { dump });
+}
+
+/**
+ * Calls the handler every time an entry of the persisted scope changes.
+ */
+async function onChanged(
+  handler: (payload: ChangedPayload) => void
+): Promise<UnlistenFn> {
+  return await listen<ChangedPayload>(
+    "persisted-scope://changed",
+    ({ payload }) => handler(payload)
+  );
+}
+
+/**
+ * Calls the handler with what the startup restored, once the page of the window is loaded.
+ */
+async function onRestored(
+  handler: (restored: Restored) => void
+): Promise<UnlistenFn> {
+  return await listen<Restored>("persisted-scope://restored", ({ payload }) =>
+    handler(payload)
+  );
+}
+
+export {
+  list,
+  removeAllowed,
+  restoreReport,
+  exportScope,
+  importScope,
+  onChanged,
+  onRestored,
+};
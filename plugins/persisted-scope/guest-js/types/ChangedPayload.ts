@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Change } from "./Change";
+import type { List } from "./List";
+import type { Scope } from "./Scope";
+import type { TargetType } from "./TargetType";
+
+export interface ChangedPayload { scope: Scope, list: List, change: Change, path: string, targetType: TargetType, expiresAt?: number, lastUsed?: number, }
\ No newline at end of file
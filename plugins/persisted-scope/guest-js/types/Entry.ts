@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TargetType } from "./TargetType";
+
+export interface Entry { path: string, targetType: TargetType, expiresAt?: number, lastUsed?: number, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RestoredEntry } from "./RestoredEntry";
+
+export interface RestoreReport { dryRun: boolean, entries: Array<RestoredEntry>, }
\ No newline at end of file
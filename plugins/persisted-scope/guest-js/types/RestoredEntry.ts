@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { List } from "./List";
+import type { RestoreStatus } from "./RestoreStatus";
+import type { Scope } from "./Scope";
+import type { TargetType } from "./TargetType";
+
+export interface RestoredEntry { scope: Scope, list: List, path: string, targetType: TargetType, expiresAt?: number, lastUsed?: number, status: RestoreStatus, error?: string, }
\ No newline at end of file
@@ -0,0 +1,77 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Encrypts the scope file with XChaCha20-Poly1305 when the app sets a key, with the `encryption` feature.
+
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    Key, XChaCha20Poly1305, XNonce,
+};
+
+use crate::{Error, Result};
+
+/// Starts the encrypted scope files, followed by the nonce and the encrypted JSON.
+const MAGIC: &[u8] = b"tauri-persisted-scope:xchacha20poly1305:";
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 24;
+
+/// Encodes and decodes the scope file, which is plain JSON without a key.
+#[derive(Clone, Default)]
+pub(crate) struct Codec {
+    #[cfg(feature = "encryption")]
+    key: Option<[u8; 32]>,
+}
+
+impl Codec {
+    #[cfg(feature = "encryption")]
+    pub fn new(key: Option<[u8; 32]>) -> Self {
+        Self { key }
+    }
+
+    pub fn encode(&self, json: Vec<u8>) -> Result<Vec<u8>> {
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.key {
+            let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+            let nonce: [u8; NONCE_LEN] = rand::random();
+            let encrypted = cipher
+                .encrypt(XNonce::from_slice(&nonce), json.as_slice())
+                .map_err(|_| Error::Encrypt)?;
+            return Ok([MAGIC, &nonce, &encrypted].concat());
+        }
+        Ok(json)
+    }
+
+    /// Returns the JSON and whether the file must be rewritten, e.g. a plain file once a key is set.
+    pub fn decode(&self, bytes: Vec<u8>) -> Result<(Vec<u8>, bool)> {
+        if !bytes.starts_with(MAGIC) {
+            return Ok((bytes, self.encrypts()));
+        }
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.key {
+            let encrypted = &bytes[MAGIC.len()..];
+            if encrypted.len() < NONCE_LEN {
+                return Err(Error::Decrypt);
+            }
+            let (nonce, encrypted) = encrypted.split_at(NONCE_LEN);
+            let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+            let json = cipher
+                .decrypt(XNonce::from_slice(nonce), encrypted)
+                .map_err(|_| Error::Decrypt)?;
+            return Ok((json, false));
+        }
+        Err(Error::Encrypted)
+    }
+
+    fn encrypts(&self) -> bool {
+        #[cfg(feature = "encryption")]
+        {
+            self.key.is_some()
+        }
+        #[cfg(not(feature = "encryption"))]
+        {
+            false
+        }
+    }
+}
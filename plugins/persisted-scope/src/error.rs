@@ -0,0 +1,77 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use serde::{Serialize, Serializer};
+use tauri_plugin_common::{error, PluginError};
+
+use crate::scope_file::SCOPE_FILE_VERSION;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error(transparent)]
+    Bincode(#[from] Box<bincode::ErrorKind>),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("the scope file has no `version`")]
+    MissingVersion,
+    #[error("the scope file has the version {0}, this version of the plugin reads up to {SCOPE_FILE_VERSION}")]
+    UnsupportedVersion(u64),
+    #[error("the scope file is encrypted, its key must be set with `Builder::encryption_key`")]
+    Encrypted,
+    #[error("failed to encrypt the scope file")]
+    Encrypt,
+    #[error("failed to decrypt the scope file, the key is wrong or the file was modified")]
+    Decrypt,
+    #[error(
+        "importing the scope from JavaScript is disabled, it's enabled by `Builder::js_import`"
+    )]
+    ImportDenied,
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "persisted-scope";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Tauri(..) => "tauri",
+            Self::Bincode(..) => "bincode",
+            Self::Json(..) => "json",
+            Self::MissingVersion => "missing_version",
+            Self::UnsupportedVersion(..) => "unsupported_version",
+            Self::Encrypted => "encrypted",
+            Self::Encrypt => "encrypt",
+            Self::Decrypt => "decrypt",
+            Self::ImportDenied => "import_denied",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
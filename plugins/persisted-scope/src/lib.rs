@@ -4,98 +4,975 @@
 
 use serde::{Deserialize, Serialize};
 use tauri::{
-    plugin::{Builder, TauriPlugin},
-    FsScopeEvent, Manager, Runtime,
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, FsScope, Manager, Runtime, State,
+};
+use tauri_plugin_common::{
+    shutdown::{self, Stage},
+    ShutdownExt, StateDirsExt,
 };
 
 use std::{
-    fs::{create_dir_all, File},
-    io::Write,
+    collections::HashSet,
+    fs::canonicalize,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Mutex,
+    },
+    thread::spawn,
+    time::{Duration, Instant, SystemTime},
 };
 
+mod encryption;
+mod error;
+mod persistable;
+mod scope_file;
+
+pub use error::Error;
+pub use persistable::PersistableScope;
+pub use scope_file::{Entry, Scope, ScopeState, TargetType};
+
+use encryption::Codec;
+use scope_file::{unix_seconds, Scopes, SCOPE_FILE_VERSION};
+
+type Result<T> = std::result::Result<T, Error>;
+
 const SCOPE_STATE_FILENAME: &str = ".persisted-scope";
+/// How long the scope waits for more changes before it's saved by default, e.g. while a recursive allow fires its events.
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(200);
+/// The longest a steady stream of changes delays the save.
+const SAVE_MAX_DELAY: Duration = Duration::from_secs(2);
+const CHANGED_EVENT: &str = "persisted-scope://changed";
+const RESTORED_EVENT: &str = "persisted-scope://restored";
 
-#[derive(Debug, thiserror::Error)]
-enum Error {
-    #[error(transparent)]
-    Io(#[from] std::io::Error),
-    #[error(transparent)]
-    Tauri(#[from] tauri::Error),
-    #[error(transparent)]
-    TauriApi(#[from] tauri::api::Error),
-    #[error(transparent)]
-    Bincode(#[from] Box<bincode::ErrorKind>),
+/// The list of the scope file an entry is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub enum List {
+    Allowed,
+    Forbidden,
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
-struct Scope {
-    allowed_paths: Vec<String>,
-    forbidden_patterns: Vec<String>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub enum Change {
+    /// The path was granted or forbidden, or an entry of the path was broadened.
+    Added,
+    Removed,
+    /// The expiry of the entry changed.
+    Updated,
 }
 
-pub fn init<R: Runtime>() -> TauriPlugin<R> {
-    Builder::new("persisted-scope")
-        .setup(|app| {
-            let fs_scope = app.fs_scope();
+/// The payload of the `persisted-scope://changed` event, emitted when an entry of the scope file changes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct ChangedPayload {
+    pub scope: Scope,
+    pub list: List,
+    pub change: Change,
+    #[serde(flatten)]
+    pub entry: Entry,
+}
+
+/// The payload of the `persisted-scope://restored` event, what the startup restored from the scope file, in both scopes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct Restored {
+    pub allowed: usize,
+    pub forbidden: usize,
+    /// The entries dropped as they expired.
+    pub expired: usize,
+    /// The granted paths dropped as they no longer exist, with [`Builder::prune_missing`].
+    pub pruned: usize,
+    /// The least recently granted paths dropped above [`Builder::max_entries`].
+    pub evicted: usize,
+}
+
+/// How an entry of the scope file was restored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub enum RestoreStatus {
+    Restored,
+    /// Not restored, [`Builder::dry_run`] is enabled.
+    #[cfg_attr(test, ts(rename = "dryRun"))]
+    DryRun,
+    /// The scope can't apply the entry, a pattern the config no longer has or an entry of the asset protocol scope
+    /// without the `protocol-asset` feature.
+    Unsupported,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct RestoredEntry {
+    pub scope: Scope,
+    pub list: List,
+    #[serde(flatten)]
+    pub entry: Entry,
+    pub status: RestoreStatus,
+    /// Why the entry failed to be restored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, ts(optional))]
+    pub error: Option<String>,
+}
+
+/// How each entry of the scope file was restored on startup.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct RestoreReport {
+    pub dry_run: bool,
+    pub entries: Vec<RestoredEntry>,
+}
+
+/// The persisted entries of both scopes, e.g. to back up the grants of the user or sync them to another machine.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeDump {
+    /// The version of the scope file of the plugin that exported it.
+    pub version: u64,
+    #[serde(default)]
+    pub fs: ScopeState,
+    #[serde(default)]
+    pub asset_protocol: ScopeState,
+}
+
+/// The paths granted and forbidden at runtime, restored into the scopes on startup.
+pub struct PersistedScope<R: Runtime> {
+    app: AppHandle<R>,
+    /// `None` when the app data directory can't be resolved, nothing is saved then.
+    path: Option<PathBuf>,
+    fs_scope: FsScope,
+    #[cfg(feature = "protocol-asset")]
+    asset_protocol_scope: FsScope,
+    state: Mutex<Scopes>,
+    /// The paths forbidden by [`PersistedScope::remove_allowed`], which aren't persisted.
+    revoked: Mutex<HashSet<(Scope, PathBuf)>>,
+    save_strategy: SaveStrategy,
+    codec: Codec,
+    /// The time the new grants are persisted for.
+    default_ttl: Option<Duration>,
+    /// The most granted paths persisted per scope.
+    max_entries: Option<usize>,
+    path_policy: PathPolicy,
+    /// Wakes the thread saving the debounced changes.
+    changed: Mutex<Sender<()>>,
+    /// Keeps the saves from writing the file at the same time.
+    writing: Mutex<()>,
+    restored: Restored,
+    report: RestoreReport,
+    js_import: bool,
+}
+
+impl<R: Runtime> PersistedScope<R> {
+    /// Removes the granted path from the scope file and denies it until the app restarts, even where the
+    /// allowlist of the config grants it, as the `FsScope` can't remove what it allowed.
+    ///
+    /// The path is revoked in both scopes. Returns whether the path was persisted.
+    pub fn remove_allowed<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let path = self.lookup_path(path.as_ref());
+        let removed = {
+            let mut state = self.state.lock().unwrap();
+            Scope::ALL
+                .into_iter()
+                .filter_map(|scope| {
+                    scope_file::remove(&mut state.get_mut(scope).allowed, &path)
+                        .map(|entry| (scope, entry))
+                })
+                .collect::<Vec<_>>()
+        };
+        if removed.is_empty() {
+            return Ok(false);
+        }
+        self.save()?;
+
+        for (scope, entry) in removed {
+            self.emit_changed(scope, List::Allowed, Change::Removed, entry.clone());
+            self.revoked.lock().unwrap().insert((scope, path.clone()));
+            if let Some(fs_scope) = self.scope(scope) {
+                fs_scope.forbid(&entry)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Removes the forbidden path from the scope file. It stays forbidden until the app restarts, as the
+    /// `FsScope` can't lift a forbid.
+    ///
+    /// The path is removed from both scopes. Returns whether the path was persisted.
+    pub fn remove_forbidden<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let path = self.lookup_path(path.as_ref());
+        let removed = {
+            let mut state = self.state.lock().unwrap();
+            Scope::ALL
+                .into_iter()
+                .filter_map(|scope| {
+                    scope_file::remove(&mut state.get_mut(scope).forbidden, &path)
+                        .map(|entry| (scope, entry))
+                })
+                .collect::<Vec<_>>()
+        };
+        if removed.is_empty() {
+            return Ok(false);
+        }
+        self.save()?;
+        for (scope, entry) in removed {
+            self.emit_changed(scope, List::Forbidden, Change::Removed, entry);
+        }
+        Ok(true)
+    }
+
+    /// Sets when the granted path expires in both scopes, `None` to keep it. Returns whether the path was persisted.
+    ///
+    /// The expired paths are dropped on the next launch, the scope keeps them until then.
+    pub fn set_expiry<P: AsRef<Path>>(
+        &self,
+        path: P,
+        expires_at: Option<SystemTime>,
+    ) -> Result<bool> {
+        let path = self.lookup_path(path.as_ref());
+        let updated = {
+            let mut state = self.state.lock().unwrap();
+            Scope::ALL
+                .into_iter()
+                .filter_map(|scope| {
+                    let allowed = &mut state.get_mut(scope).allowed;
+                    allowed.iter_mut().find(|e| e.path == path).map(|entry| {
+                        entry.expires_at = expires_at.map(unix_seconds);
+                        (scope, entry.clone())
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        if updated.is_empty() {
+            return Ok(false);
+        }
+        self.save()?;
+        for (scope, entry) in updated {
+            self.emit_changed(scope, List::Allowed, Change::Updated, entry);
+        }
+        Ok(true)
+    }
+
+    /// The paths granted to the scope, in the order they were granted.
+    pub fn allowed(&self, scope: Scope) -> Vec<Entry> {
+        self.state.lock().unwrap().get(scope).allowed.clone()
+    }
+
+    /// The paths forbidden in the scope, in the order they were forbidden.
+    pub fn forbidden(&self, scope: Scope) -> Vec<Entry> {
+        self.state.lock().unwrap().get(scope).forbidden.clone()
+    }
+
+    /// What the startup restored from the scope file.
+    pub fn restored(&self) -> Restored {
+        self.restored
+    }
+
+    /// How each entry of the scope file was restored on startup.
+    pub fn restore_report(&self) -> RestoreReport {
+        self.report.clone()
+    }
+
+    /// The persisted entries, as plain JSON once serialized.
+    pub fn export_scope(&self) -> ScopeDump {
+        let state = self.state.lock().unwrap();
+        ScopeDump {
+            version: SCOPE_FILE_VERSION,
+            fs: state.fs.clone(),
+            asset_protocol: state.asset_protocol.clone(),
+        }
+    }
+
+    /// Grants and forbids the entries of the dump, keeping their expiry, and persists them with the current ones.
+    /// The expired entries are skipped.
+    pub fn import_scope(&self, dump: ScopeDump) -> Result<()> {
+        if dump.version > SCOPE_FILE_VERSION {
+            return Err(Error::UnsupportedVersion(dump.version));
+        }
+        let now = SystemTime::now();
+        let imported = [
+            (Scope::Fs, dump.fs),
+            (Scope::AssetProtocol, dump.asset_protocol),
+        ];
+        for (scope, state) in imported {
+            let entries = state.allowed.into_iter().map(|e| (List::Allowed, e));
+            let entries = entries.chain(state.forbidden.into_iter().map(|e| (List::Forbidden, e)));
+            for (list, mut entry) in entries {
+                if entry.is_expired(now) {
+                    continue;
+                }
+                if entry.target_type != TargetType::Pattern {
+                    entry.path = match stored_path(self.path_policy, &entry.path, list) {
+                        Some(path) => path,
+                        None => continue,
+                    };
+                }
+                // the scope events persist the entry with the default expiry, it's replaced by the imported one
+                if let Some(fs_scope) = self.scope(scope) {
+                    match list {
+                        List::Allowed => fs_scope.allow(&entry)?,
+                        List::Forbidden => fs_scope.forbid(&entry)?,
+                    };
+                }
+                let mut state = self.state.lock().unwrap();
+                let state = state.get_mut(scope);
+                match list {
+                    List::Allowed => scope_file::insert(&mut state.allowed, entry),
+                    List::Forbidden => scope_file::insert(&mut state.forbidden, entry),
+                };
+            }
+            if let Some(max) = self.max_entries {
+                let evicted =
+                    scope_file::evict(&mut self.state.lock().unwrap().get_mut(scope).allowed, max);
+                for entry in evicted {
+                    self.emit_changed(scope, List::Allowed, Change::Removed, entry);
+                }
+            }
+        }
+        self.save()
+    }
+
+    /// The path an entry of the path is persisted with.
+    fn lookup_path(&self, path: &Path) -> PathBuf {
+        stored_path(self.path_policy, path, List::Forbidden).unwrap_or_else(|| normalize(path))
+    }
+
+    /// The `FsScope` the entries are restored into, `None` for the asset protocol without the `protocol-asset` feature.
+    fn scope(&self, scope: Scope) -> Option<&FsScope> {
+        match scope {
+            Scope::Fs => Some(&self.fs_scope),
             #[cfg(feature = "protocol-asset")]
-            let asset_protocol_scope = app.asset_protocol_scope();
-            let app = app.clone();
-            let app_dir = app.path_resolver().app_data_dir();
+            Scope::AssetProtocol => Some(&self.asset_protocol_scope),
+            #[cfg(not(feature = "protocol-asset"))]
+            Scope::AssetProtocol => None,
+        }
+    }
 
-            if let Some(app_dir) = app_dir {
-                let scope_state_path = app_dir.join(SCOPE_STATE_FILENAME);
+    fn emit_changed(&self, scope: Scope, list: List, change: Change, entry: Entry) {
+        let payload = ChangedPayload {
+            scope,
+            list,
+            change,
+            entry,
+        };
+        let _ = self.app.emit_all(CHANGED_EVENT, payload);
+    }
 
-                let _ = fs_scope.forbid_file(&scope_state_path);
-                #[cfg(feature = "protocol-asset")]
-                let _ = asset_protocol_scope.forbid_file(&scope_state_path);
-
-                if scope_state_path.exists() {
-                    let scope: Scope = tauri::api::file::read_binary(&scope_state_path)
-                        .map_err(Error::from)
-                        .and_then(|scope| bincode::deserialize(&scope).map_err(Into::into))
-                        .unwrap_or_default();
-                    for allowed in scope.allowed_paths {
-                        // allows the path as is
-                        let _ = fs_scope.allow_file(&allowed);
-                        #[cfg(feature = "protocol-asset")]
-                        let _ = asset_protocol_scope.allow_file(allowed);
+    fn save(&self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let _writing = self.writing.lock().unwrap();
+        let state = {
+            let mut state = self.state.lock().unwrap();
+            state.dedup();
+            state.clone()
+        };
+        scope_file::save(path, &state, &self.codec)
+    }
+
+    fn log_save(&self) {
+        if let Err(e) = self.save() {
+            log::error!("failed to save the scope file: {}", e);
+        }
+    }
+
+    fn on_change<S: PersistableScope>(
+        &self,
+        scope: Scope,
+        persistable: &S,
+        list: List,
+        path: &Path,
+    ) {
+        let target_type = persistable.target_type(list, path);
+        let mut evicted = Vec::new();
+        let entry = {
+            let mut state = self.state.lock().unwrap();
+            let state = state.get_mut(scope);
+            match list {
+                List::Allowed => {
+                    let path = match stored_path(self.path_policy, path, List::Allowed) {
+                        Some(path) => path,
+                        None => {
+                            log::warn!(
+                                "the granted path {} can't be canonicalized, it isn't persisted",
+                                path.display()
+                            );
+                            return;
+                        }
+                    };
+                    let now = SystemTime::now();
+                    let entry = Entry {
+                        path,
+                        target_type,
+                        expires_at: self.default_ttl.map(|ttl| unix_seconds(now + ttl)),
+                        last_used: Some(unix_seconds(now)),
+                    };
+                    let entry = scope_file::insert(&mut state.allowed, entry);
+                    if let Some(max) = self.max_entries {
+                        evicted = scope_file::evict(&mut state.allowed, max);
                     }
-                    for forbidden in scope.forbidden_patterns {
-                        // forbid the path as is
-                        let _ = fs_scope.forbid_file(&forbidden);
-                        #[cfg(feature = "protocol-asset")]
-                        let _ = asset_protocol_scope.forbid_file(forbidden);
+                    entry
+                }
+                List::Forbidden => {
+                    let path = self.lookup_path(path);
+                    if self
+                        .revoked
+                        .lock()
+                        .unwrap()
+                        .contains(&(scope, path.clone()))
+                    {
+                        return;
+                    }
+                    let entry = Entry {
+                        path,
+                        target_type,
+                        expires_at: None,
+                        last_used: None,
+                    };
+                    scope_file::insert(&mut state.forbidden, entry)
+                }
+            }
+        };
+        match self.save_strategy {
+            SaveStrategy::OnEvent => self.log_save(),
+            // the saver only stops when it panicked
+            SaveStrategy::Debounced(_) => {
+                if self.changed.lock().unwrap().send(()).is_err() {
+                    self.log_save();
+                }
+            }
+            SaveStrategy::OnExit => (),
+        }
+        self.emit_changed(scope, list, Change::Added, entry);
+        for entry in evicted {
+            self.emit_changed(scope, List::Allowed, Change::Removed, entry);
+        }
+    }
+}
+
+/// Saves the changes once they stop coming in.
+fn spawn_saver<R: Runtime>(app: AppHandle<R>, changes: Receiver<()>, debounce: Duration) {
+    let max_delay = SAVE_MAX_DELAY.max(debounce);
+    spawn(move || {
+        while changes.recv().is_ok() {
+            let start = Instant::now();
+            while start.elapsed() < max_delay && changes.recv_timeout(debounce).is_ok() {}
+            app.persisted_scope().log_save();
+        }
+    });
+}
+
+/// The path as the `FsScope` stores it.
+fn normalize(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+/// The path as it's persisted with the policy, `None` when the granted path isn't persisted.
+/// The forbidden paths are always persisted, as they were forbidden when they can't be canonicalized.
+fn stored_path(policy: PathPolicy, path: &Path, list: List) -> Option<PathBuf> {
+    let path = normalize(path);
+    if policy == PathPolicy::AsGranted {
+        return Some(path);
+    }
+    match canonicalize(&path) {
+        Ok(canonical) => Some(normalize(&canonical)),
+        Err(_) if policy == PathPolicy::Canonicalize || list == List::Forbidden => Some(path),
+        Err(_) => None,
+    }
+}
+
+/// Restores the entries into the scope, `None` when it isn't available, and reports how each one was restored.
+fn restore<S: PersistableScope>(
+    scope: Scope,
+    persistable: Option<&S>,
+    state: &ScopeState,
+    dry_run: bool,
+    report: &mut Vec<RestoredEntry>,
+) {
+    let allowed = state.allowed.iter().map(|e| (List::Allowed, e));
+    let entries = allowed.chain(state.forbidden.iter().map(|e| (List::Forbidden, e)));
+    for (list, entry) in entries {
+        let (status, error) = match persistable {
+            None => (RestoreStatus::Unsupported, None),
+            Some(_) if dry_run => (RestoreStatus::DryRun, None),
+            Some(persistable) => {
+                let applied = match list {
+                    List::Allowed => persistable.allow(entry),
+                    List::Forbidden => persistable.forbid(entry),
+                };
+                match applied {
+                    Ok(true) => (RestoreStatus::Restored, None),
+                    Ok(false) => (RestoreStatus::Unsupported, None),
+                    Err(e) => (RestoreStatus::Failed, Some(e.to_string())),
+                }
+            }
+        };
+        report.push(RestoredEntry {
+            scope,
+            list,
+            entry: entry.clone(),
+            status,
+            error,
+        });
+    }
+}
+
+fn log_report(report: &RestoreReport, audit: bool) {
+    for restored in &report.entries {
+        let path = restored.entry.path.display();
+        match restored.status {
+            RestoreStatus::Failed => log::warn!(
+                "failed to restore `{}` in the {:?} scope: {}",
+                path,
+                restored.scope,
+                restored.error.as_deref().unwrap_or_default()
+            ),
+            RestoreStatus::Unsupported if restored.entry.target_type == TargetType::Pattern => {
+                log::warn!(
+                    "the persisted pattern `{}` can't be applied, the scope only takes it from the config",
+                    path
+                )
+            }
+            // the asset protocol scope without the `protocol-asset` feature, its entries are kept for when it's enabled
+            RestoreStatus::Unsupported if !audit => (),
+            status if audit => log::info!(
+                "{:?} `{}` ({:?}) in the {:?} scope: {:?}",
+                restored.list,
+                path,
+                restored.entry.target_type,
+                restored.scope,
+                status
+            ),
+            _ => (),
+        }
+    }
+}
+
+/// Persists the changes of the scope.
+fn persist<R: Runtime, S: PersistableScope>(app: &AppHandle<R>, scope: Scope, persistable: &S) {
+    let app = app.clone();
+    let persistable_ = persistable.clone();
+    persistable.on_change(move |list, path| {
+        app.persisted_scope()
+            .on_change(scope, &persistable_, list, path)
+    });
+}
+
+pub trait PersistedScopeExt<R: Runtime> {
+    fn persisted_scope(&self) -> State<'_, PersistedScope<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> PersistedScopeExt<R> for T {
+    fn persisted_scope(&self) -> State<'_, PersistedScope<R>> {
+        self.state::<PersistedScope<R>>()
+    }
+}
+
+#[command]
+fn list<R: Runtime>(app: AppHandle<R>, scope: Option<Scope>) -> ScopeState {
+    let persisted_scope = app.persisted_scope();
+    let scope = scope.unwrap_or(Scope::Fs);
+    ScopeState {
+        allowed: persisted_scope.allowed(scope),
+        forbidden: persisted_scope.forbidden(scope),
+    }
+}
+
+#[command]
+fn remove_allowed<R: Runtime>(app: AppHandle<R>, path: PathBuf) -> Result<bool> {
+    app.persisted_scope().remove_allowed(path)
+}
+
+#[command]
+fn restore_report<R: Runtime>(app: AppHandle<R>) -> RestoreReport {
+    app.persisted_scope().restore_report()
+}
+
+#[command]
+fn export_scope<R: Runtime>(app: AppHandle<R>) -> ScopeDump {
+    app.persisted_scope().export_scope()
+}
+
+#[command]
+fn import_scope<R: Runtime>(app: AppHandle<R>, dump: ScopeDump) -> Result<()> {
+    let persisted_scope = app.persisted_scope();
+    if !persisted_scope.js_import {
+        return Err(Error::ImportDenied);
+    }
+    persisted_scope.import_scope(dump)
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
+
+/// The directory of the scope file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Directory {
+    /// `StateDirs::data_dir`, the app data directory unless `plugins > dirs` relocates it.
+    #[default]
+    Data,
+    /// `StateDirs::config_dir`.
+    Config,
+}
+
+/// How the paths are persisted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathPolicy {
+    /// As they were granted, a folder reached through a symlink or with another case on Windows is persisted again.
+    #[default]
+    AsGranted,
+    /// Canonicalized, resolving the symlinks and, on Windows, the case and the `\\?\` prefix the scope matches
+    /// the accessed paths with. A path that can't be canonicalized, e.g. one that doesn't exist yet, is persisted as granted.
+    Canonicalize,
+    /// As [`PathPolicy::Canonicalize`], but a granted path that can't be canonicalized isn't persisted.
+    /// The forbidden paths are still persisted as they were forbidden.
+    CanonicalizeOrSkip,
+}
+
+/// When the changes of the scope are saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveStrategy {
+    /// On every change.
+    OnEvent,
+    /// Once the changes stop coming in for the duration, at most 2 seconds after the first one unless the duration is longer.
+    Debounced(Duration),
+    /// Only when the app exits, a crash loses the changes.
+    OnExit,
+}
+
+impl Default for SaveStrategy {
+    fn default() -> Self {
+        Self::Debounced(SAVE_DEBOUNCE)
+    }
+}
+
+#[derive(Default)]
+pub struct Builder {
+    prune_missing: bool,
+    directory: Directory,
+    path: Option<PathBuf>,
+    save_strategy: SaveStrategy,
+    default_ttl: Option<Duration>,
+    max_entries: Option<usize>,
+    path_policy: PathPolicy,
+    js_import: bool,
+    audit: bool,
+    dry_run: bool,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<[u8; 32]>,
+}
+
+impl Builder {
+    /// Drops the granted paths that no longer exist when they're restored, e.g. the deleted or renamed folders,
+    /// and rewrites the scope file. The forbidden paths are kept, they may guard a path created later.
+    pub fn prune_missing(mut self, prune_missing: bool) -> Self {
+        self.prune_missing = prune_missing;
+        self
+    }
+
+    /// Sets the directory of the scope file, [`Directory::Data`] by default.
+    pub fn directory(mut self, directory: Directory) -> Self {
+        self.directory = directory;
+        self
+    }
+
+    /// Sets the path of the scope file, `.persisted-scope` by default. A relative path is relative to the directory.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Sets when the changes are saved, [`SaveStrategy::Debounced`] by 200 milliseconds by default.
+    pub fn save_strategy(mut self, save_strategy: SaveStrategy) -> Self {
+        self.save_strategy = save_strategy;
+        self
+    }
+
+    /// Persists the new grants for the duration, e.g. to remember the folders for 30 days. They're kept until then by default.
+    ///
+    /// [`PersistedScope::set_expiry`] changes the expiry of a grant.
+    pub fn default_ttl(mut self, ttl: Duration) -> Self {
+        self.default_ttl = Some(ttl);
+        self
+    }
+
+    /// Persists at most the number of granted paths per scope, e.g. 500 for the recent files of an editor. The least
+    /// recently granted paths are evicted from the scope file, the scope keeps them until the app restarts.
+    ///
+    /// The forbidden paths aren't counted, dropping them would widen the access.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Sets how the paths are persisted, [`PathPolicy::AsGranted`] by default. The entries of the scope file are
+    /// canonicalized on startup once a canonicalizing policy is set.
+    pub fn path_policy(mut self, path_policy: PathPolicy) -> Self {
+        self.path_policy = path_policy;
+        self
+    }
+
+    /// Logs how each entry of the scope file is restored, the failures are logged either way.
+    /// [`PersistedScope::restore_report`] returns them.
+    pub fn audit(mut self, audit: bool) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Reports what would be restored without changing the scopes, e.g. to inspect the scope file of a user.
+    /// The scope file is left as it is until a grant changes.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Lets the webview import a dump with the `import_scope` command. It grants any path, so it's disabled by default
+    /// and [`PersistedScope::import_scope`] is only available from Rust.
+    pub fn js_import(mut self, js_import: bool) -> Self {
+        self.js_import = js_import;
+        self
+    }
+
+    /// Encrypts the scope file with the key, so the granted paths can't be read by the other programs of the user,
+    /// e.g. with a key kept in the keychain. The plain files are encrypted on the first launch.
+    #[cfg(feature = "encryption")]
+    pub fn encryption_key(mut self, key: [u8; 32]) -> Self {
+        self.encryption_key = Some(key);
+        self
+    }
+
+    fn codec(&self) -> Codec {
+        #[cfg(feature = "encryption")]
+        {
+            Codec::new(self.encryption_key)
+        }
+        #[cfg(not(feature = "encryption"))]
+        {
+            Codec::default()
+        }
+    }
+
+    fn resolve_path<R: Runtime>(&self, app: &AppHandle<R>) -> Option<PathBuf> {
+        let file = self
+            .path
+            .clone()
+            .unwrap_or_else(|| SCOPE_STATE_FILENAME.into());
+        if file.is_absolute() {
+            return Some(file);
+        }
+        let dirs = app.state_dirs();
+        let dir = match self.directory {
+            Directory::Data => dirs.data_dir(),
+            Directory::Config => dirs.config_dir(),
+        };
+        dir.map(|dir| dir.join(file))
+    }
+
+    /// Reads the scope file, migrating and pruning it.
+    fn load(&self, path: &Path, codec: &Codec) -> (Scopes, Restored) {
+        if !path.exists() {
+            return Default::default();
+        }
+        let (mut state, outdated) = match scope_file::load(path, codec) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                let backup = scope_file::back_up(path);
+                log::warn!(
+                    "failed to read the scope file, it was moved to {}: {}",
+                    backup.display(),
+                    e
+                );
+                return Default::default();
+            }
+        };
+
+        let mut canonicalized = false;
+        if self.path_policy != PathPolicy::AsGranted {
+            for scope in Scope::ALL {
+                let state = state.get_mut(scope);
+                for (list, entries) in [
+                    (List::Allowed, &mut state.allowed),
+                    (List::Forbidden, &mut state.forbidden),
+                ] {
+                    let persisted = std::mem::take(entries);
+                    for mut entry in persisted.iter().cloned() {
+                        if entry.target_type != TargetType::Pattern {
+                            entry.path = match stored_path(self.path_policy, &entry.path, list) {
+                                Some(path) => path,
+                                None => continue,
+                            };
+                        }
+                        scope_file::insert(entries, entry);
                     }
+                    canonicalized |= *entries != persisted;
                 }
+            }
+        }
 
-                fs_scope.listen(move |event| {
-                    let fs_scope = app.fs_scope();
-                    if let FsScopeEvent::PathAllowed(_) = event {
-                        let scope = Scope {
-                            allowed_paths: fs_scope
-                                .allowed_patterns()
-                                .into_iter()
-                                .map(|p| p.to_string())
-                                .collect(),
-                            forbidden_patterns: fs_scope
-                                .forbidden_patterns()
-                                .into_iter()
-                                .map(|p| p.to_string())
-                                .collect(),
-                        };
-                        let scope_state_path = scope_state_path.clone();
-
-                        let _ = create_dir_all(&app_dir)
-                            .and_then(|_| File::create(scope_state_path))
-                            .map_err(Error::Io)
-                            .and_then(|mut f| {
-                                f.write_all(&bincode::serialize(&scope).map_err(Error::from)?)
-                                    .map_err(Into::into)
-                            });
+        let deduped = state.dedup();
+        if deduped > 0 {
+            log::info!(
+                "dropped {} entries covered by broader ones from the scope file",
+                deduped
+            );
+        }
+        let now = SystemTime::now();
+        let mut restored = Restored::default();
+        for scope in Scope::ALL {
+            let state = state.get_mut(scope);
+            // the state file itself was persisted by the previous versions
+            scope_file::remove(&mut state.forbidden, &normalize(path));
+            let persisted = state.allowed.len() + state.forbidden.len();
+            state.allowed.retain(|entry| !entry.is_expired(now));
+            state.forbidden.retain(|entry| !entry.is_expired(now));
+            restored.expired += persisted - state.allowed.len() - state.forbidden.len();
+
+            let granted = state.allowed.len();
+            if self.prune_missing {
+                state.allowed.retain(|entry| entry.path.exists());
+            }
+            restored.pruned += granted - state.allowed.len();
+            if let Some(max) = self.max_entries {
+                restored.evicted += scope_file::evict(&mut state.allowed, max).len();
+            }
+            restored.allowed += state.allowed.len();
+            restored.forbidden += state.forbidden.len();
+        }
+        if restored.expired > 0 {
+            log::info!(
+                "dropped {} expired entries from the scope file",
+                restored.expired
+            );
+        }
+        if restored.pruned > 0 {
+            log::info!(
+                "pruned {} missing paths from the scope file",
+                restored.pruned
+            );
+        }
+        if restored.evicted > 0 {
+            log::info!(
+                "evicted {} least recently granted paths from the scope file",
+                restored.evicted
+            );
+        }
+        let rewrite = outdated
+            || canonicalized
+            || deduped > 0
+            || restored.expired > 0
+            || restored.pruned > 0
+            || restored.evicted > 0;
+        if rewrite && !self.dry_run {
+            if let Err(e) = scope_file::save(path, &state, codec) {
+                log::error!("failed to rewrite the scope file: {}", e);
+            }
+        }
+        (state, restored)
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        PluginBuilder::new("persisted-scope")
+            // lifting a forbid widens the access, it's only available from Rust
+            .invoke_handler(tauri::generate_handler![
+                list,
+                remove_allowed,
+                restore_report,
+                export_scope,
+                import_scope
+            ])
+            .setup(move |app| {
+                let fs_scope = app.fs_scope();
+                #[cfg(feature = "protocol-asset")]
+                let asset_protocol_scope = app.asset_protocol_scope();
+                let path = self.resolve_path(app);
+                let codec = self.codec();
+
+                let (mut state, mut restored) = Default::default();
+                if let Some(path) = &path {
+                    for file in [path.clone(), scope_file::temp_path(path)] {
+                        let _ = fs_scope.forbid_file(&file);
+                        #[cfg(feature = "protocol-asset")]
+                        let _ = asset_protocol_scope.forbid_file(&file);
                     }
+                    (state, restored) = self.load(path, &codec);
+                }
+
+                let mut report = RestoreReport {
+                    dry_run: self.dry_run,
+                    entries: Vec::new(),
+                };
+                restore(
+                    Scope::Fs,
+                    Some(&fs_scope),
+                    &state.fs,
+                    self.dry_run,
+                    &mut report.entries,
+                );
+                #[cfg(feature = "protocol-asset")]
+                let asset_protocol = Some(&asset_protocol_scope);
+                #[cfg(not(feature = "protocol-asset"))]
+                let asset_protocol = None::<&FsScope>;
+                restore(
+                    Scope::AssetProtocol,
+                    asset_protocol,
+                    &state.asset_protocol,
+                    self.dry_run,
+                    &mut report.entries,
+                );
+                log_report(&report, self.audit);
+
+                let (changed, changes) = channel();
+                app.manage(PersistedScope {
+                    app: app.clone(),
+                    path,
+                    fs_scope: fs_scope.clone(),
+                    #[cfg(feature = "protocol-asset")]
+                    asset_protocol_scope: asset_protocol_scope.clone(),
+                    state: Mutex::new(state),
+                    revoked: Default::default(),
+                    save_strategy: self.save_strategy,
+                    codec,
+                    default_ttl: self.default_ttl,
+                    max_entries: self.max_entries,
+                    path_policy: self.path_policy,
+                    changed: Mutex::new(changed),
+                    writing: Default::default(),
+                    restored,
+                    report,
+                    js_import: self.js_import,
                 });
-            }
-            Ok(())
-        })
-        .build()
+                if let SaveStrategy::Debounced(debounce) = self.save_strategy {
+                    spawn_saver(app.clone(), changes, debounce);
+                }
+
+                let app_ = app.clone();
+                app.shutdown()
+                    .register("persisted-scope", Stage::Save, move || {
+                        app_.persisted_scope().log_save()
+                    });
+
+                persist(app, Scope::Fs, &fs_scope);
+                #[cfg(feature = "protocol-asset")]
+                persist(app, Scope::AssetProtocol, &asset_protocol_scope);
+                Ok(())
+            })
+            // the windows are created after the setup, they're told what was restored once their page is loaded
+            .on_page_load(|window, _| {
+                let _ = window.emit(RESTORED_EVENT, window.persisted_scope().restored());
+            })
+            .on_event(|app, event| shutdown::on_event(app, "persisted-scope", event))
+            .build()
+    }
 }
@@ -0,0 +1,89 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The operations the plugin persists and restores a scope with.
+
+use tauri::{scope::GlobPattern, FsScope, FsScopeEvent};
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+};
+
+use crate::{Entry, List, TargetType};
+
+/// A scope the grants of which are persisted and restored by the plugin.
+///
+/// It's implemented for the `FsScope` of the fs and asset protocol scopes. The shell and HTTP scopes of Tauri 1
+/// are only read from the allowlist of the config, they have no method granting at runtime nor events, so there's
+/// nothing for an adapter of them to persist.
+pub trait PersistableScope: Clone + Send + Sync + 'static {
+    /// Grants the entry, returns whether the scope could apply it.
+    fn allow(&self, entry: &Entry) -> tauri::Result<bool>;
+
+    /// Forbids the entry, returns whether the scope could apply it.
+    fn forbid(&self, entry: &Entry) -> tauri::Result<bool>;
+
+    /// The target type of the path granted or forbidden, from what the scope added for it.
+    fn target_type(&self, list: List, path: &Path) -> TargetType;
+
+    /// Calls the handler with the paths granted and forbidden at runtime.
+    fn on_change<F: Fn(List, &Path) + Send + 'static>(&self, handler: F);
+}
+
+impl PersistableScope for FsScope {
+    fn allow(&self, entry: &Entry) -> tauri::Result<bool> {
+        match entry.target_type {
+            TargetType::File => self.allow_file(&entry.path)?,
+            TargetType::Directory => self.allow_directory(&entry.path, false)?,
+            TargetType::RecursiveDirectory => self.allow_directory(&entry.path, true)?,
+            TargetType::Pattern => return Ok(has_pattern(&self.allowed_patterns(), entry)),
+        }
+        Ok(true)
+    }
+
+    fn forbid(&self, entry: &Entry) -> tauri::Result<bool> {
+        match entry.target_type {
+            TargetType::File => self.forbid_file(&entry.path)?,
+            TargetType::Directory => self.forbid_directory(&entry.path, false)?,
+            TargetType::RecursiveDirectory => self.forbid_directory(&entry.path, true)?,
+            TargetType::Pattern => return Ok(has_pattern(&self.forbidden_patterns(), entry)),
+        }
+        Ok(true)
+    }
+
+    fn target_type(&self, list: List, path: &Path) -> TargetType {
+        let patterns = match list {
+            List::Allowed => self.allowed_patterns(),
+            List::Forbidden => self.forbidden_patterns(),
+        };
+        // the patterns of the path as the `FsScope` stores it
+        let path: PathBuf = path.components().collect();
+        let escaped = GlobPattern::escape(&path.to_string_lossy());
+        let has = |suffix: &str| {
+            let pattern = format!("{}{}{}", escaped, MAIN_SEPARATOR, suffix);
+            patterns.iter().any(|p| p.as_str() == pattern)
+        };
+        if has("**") {
+            TargetType::RecursiveDirectory
+        } else if has("*") {
+            TargetType::Directory
+        } else {
+            TargetType::File
+        }
+    }
+
+    fn on_change<F: Fn(List, &Path) + Send + 'static>(&self, handler: F) {
+        self.listen(move |event| match event {
+            FsScopeEvent::PathAllowed(path) => handler(List::Allowed, path),
+            FsScopeEvent::PathForbidden(path) => handler(List::Forbidden, path),
+        });
+    }
+}
+
+/// The `FsScope` has no method taking a glob, the patterns only apply while the config has them.
+fn has_pattern(patterns: &HashSet<GlobPattern>, entry: &Entry) -> bool {
+    let pattern = entry.path.to_string_lossy();
+    patterns.iter().any(|p| p.as_str() == pattern)
+}
@@ -0,0 +1,391 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The scope file and the migrations from its previous versions.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use std::{
+    ffi::OsString,
+    fs::{create_dir_all, read, rename, File},
+    io::Write,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{encryption::Codec, Error, Result};
+
+/// The version of the scope file, bump it and migrate the previous one in [`migrate`] when [`ScopeFile`] changes.
+pub(crate) const SCOPE_FILE_VERSION: u64 = 3;
+
+/// The scope an entry is persisted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub enum Scope {
+    Fs,
+    /// Only restored with the `protocol-asset` feature, the entries are kept in the scope file without it.
+    // ts-rs lowercases the variants it converts to camel case
+    #[cfg_attr(test, ts(rename = "assetProtocol"))]
+    AssetProtocol,
+}
+
+impl Scope {
+    pub(crate) const ALL: [Self; 2] = [Self::Fs, Self::AssetProtocol];
+}
+
+/// What a persisted path grants or forbids, as the `allow_*` and `forbid_*` methods of the `FsScope`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub enum TargetType {
+    File,
+    /// The directory and its direct children.
+    Directory,
+    /// The directory and everything in it.
+    #[cfg_attr(test, ts(rename = "recursiveDirectory"))]
+    RecursiveDirectory,
+    /// A glob, the `path` is the pattern.
+    ///
+    /// The `FsScope` of Tauri 1 only allows the escaped paths, the patterns are kept in the scope file
+    /// but only restored when the allowlist of the config still has them.
+    Pattern,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct Entry {
+    pub path: PathBuf,
+    pub target_type: TargetType,
+    /// When the entry expires, in seconds since the Unix epoch. It's dropped on the first launch after it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, ts(optional, type = "number"))]
+    pub expires_at: Option<u64>,
+    /// When the path was last granted, in seconds since the Unix epoch. The least recently granted paths are evicted
+    /// first by `Builder::max_entries`, the entries of the previous versions have none and go before them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, ts(optional, type = "number"))]
+    pub last_used: Option<u64>,
+}
+
+impl Entry {
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| unix_seconds(now) >= expires_at)
+    }
+}
+
+pub(crate) fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// The persisted entries of a scope, by path.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ScopeState {
+    #[serde(default)]
+    pub allowed: Vec<Entry>,
+    #[serde(default)]
+    pub forbidden: Vec<Entry>,
+}
+
+impl ScopeState {
+    fn is_empty(&self) -> bool {
+        self.allowed.is_empty() && self.forbidden.is_empty()
+    }
+
+    /// Drops the entries of the paths covered by a broader entry, e.g. the files granted in a directory granted
+    /// recursively later. Returns how many were dropped.
+    pub(crate) fn dedup(&mut self) -> usize {
+        dedup(&mut self.allowed) + dedup(&mut self.forbidden)
+    }
+}
+
+/// The entries of the scopes, persisted independently as a path may only be granted to one of them.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Scopes {
+    #[serde(flatten)]
+    pub fs: ScopeState,
+    #[serde(default, skip_serializing_if = "ScopeState::is_empty")]
+    pub asset_protocol: ScopeState,
+}
+
+impl Scopes {
+    /// The versions before 3 restored the same entries in both scopes.
+    fn mirrored(state: ScopeState) -> Self {
+        Self {
+            fs: state.clone(),
+            asset_protocol: state,
+        }
+    }
+
+    pub fn get(&self, scope: Scope) -> &ScopeState {
+        match scope {
+            Scope::Fs => &self.fs,
+            Scope::AssetProtocol => &self.asset_protocol,
+        }
+    }
+
+    pub fn get_mut(&mut self, scope: Scope) -> &mut ScopeState {
+        match scope {
+            Scope::Fs => &mut self.fs,
+            Scope::AssetProtocol => &mut self.asset_protocol,
+        }
+    }
+
+    pub fn dedup(&mut self) -> usize {
+        self.fs.dedup() + self.asset_protocol.dedup()
+    }
+}
+
+/// Adds the entry, a path already persisted keeps the broadest target type and takes the expiry and the last use of
+/// the new entry.
+///
+/// Returns the persisted entry.
+pub(crate) fn insert(entries: &mut Vec<Entry>, entry: Entry) -> Entry {
+    match entries.iter_mut().find(|e| e.path == entry.path) {
+        Some(existing) => {
+            existing.target_type = existing.target_type.max(entry.target_type);
+            existing.expires_at = entry.expires_at;
+            existing.last_used = entry.last_used.or(existing.last_used);
+            existing.clone()
+        }
+        None => {
+            entries.push(entry.clone());
+            entry
+        }
+    }
+}
+
+pub(crate) fn remove(entries: &mut Vec<Entry>, path: &Path) -> Option<Entry> {
+    let index = entries.iter().position(|e| e.path == path)?;
+    Some(entries.remove(index))
+}
+
+/// Whether the broader entry covers the path of the entry for as long as it's persisted.
+fn subsumes(broader: &Entry, entry: &Entry) -> bool {
+    if broader.path == entry.path {
+        return false;
+    }
+    let covers = match (broader.target_type, entry.target_type) {
+        // the globs can't be compared
+        (TargetType::Pattern, _) | (_, TargetType::Pattern) => false,
+        (TargetType::RecursiveDirectory, _) => entry.path.starts_with(&broader.path),
+        // the directory only covers its direct children, not what a child directory grants
+        (TargetType::Directory, TargetType::File) => {
+            entry.path.parent() == Some(broader.path.as_path())
+        }
+        _ => false,
+    };
+    let outlives = match (broader.expires_at, entry.expires_at) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(broader), Some(entry)) => broader >= entry,
+    };
+    covers && outlives
+}
+
+/// Drops the subsumed entries, the broader entry takes their last use. Returns how many were dropped.
+fn dedup(entries: &mut Vec<Entry>) -> usize {
+    let len = entries.len();
+    let mut i = 0;
+    while i < entries.len() {
+        match entries
+            .iter()
+            .position(|broader| subsumes(broader, &entries[i]))
+        {
+            Some(broader) => {
+                let entry = entries.remove(i);
+                let broader = &mut entries[if broader > i { broader - 1 } else { broader }];
+                broader.last_used = broader.last_used.max(entry.last_used);
+            }
+            None => i += 1,
+        }
+    }
+    len - entries.len()
+}
+
+/// Removes the least recently used entries above the maximum, returns them.
+pub(crate) fn evict(entries: &mut Vec<Entry>, max: usize) -> Vec<Entry> {
+    let mut evicted = Vec::new();
+    while entries.len() > max {
+        // the first of the least recently used, the entries are in the order they were added
+        let index = entries
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, e)| e.last_used)
+            .map(|(index, _)| index)
+            .unwrap_or_default();
+        evicted.push(entries.remove(index));
+    }
+    evicted
+}
+
+#[derive(Deserialize, Serialize)]
+struct ScopeFile {
+    version: u64,
+    #[serde(flatten)]
+    scopes: Scopes,
+}
+
+/// The version 2, the entries of both scopes.
+#[derive(Deserialize)]
+struct ScopeFileV2 {
+    #[serde(flatten)]
+    state: ScopeState,
+}
+
+/// The version 1, the patterns of the `FsScope` as they were returned by `allowed_patterns`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScopeFileV1 {
+    allowed_paths: Vec<String>,
+    forbidden_patterns: Vec<String>,
+}
+
+/// The bincode scope file written before the versions, the same fields as the version 1.
+#[derive(Deserialize)]
+struct LegacyScope {
+    allowed_paths: Vec<String>,
+    forbidden_patterns: Vec<String>,
+}
+
+impl From<ScopeFileV1> for ScopeState {
+    fn from(v1: ScopeFileV1) -> Self {
+        let entries = |patterns: Vec<String>| {
+            let mut entries = Vec::new();
+            for pattern in patterns {
+                insert(&mut entries, entry_from_pattern(&pattern));
+            }
+            entries
+        };
+        Self {
+            allowed: entries(v1.allowed_paths),
+            forbidden: entries(v1.forbidden_patterns),
+        }
+    }
+}
+
+/// The entry of a pattern built by the `FsScope`: the escaped path, followed by `*` or `**` for the directories.
+/// The globs of the allowlist of the config are kept as they are.
+fn entry_from_pattern(pattern: &str) -> Entry {
+    let recursive = format!("{}**", MAIN_SEPARATOR);
+    let children = format!("{}*", MAIN_SEPARATOR);
+    let (path, target_type) = if let Some(path) = pattern.strip_suffix(&recursive) {
+        (path, TargetType::RecursiveDirectory)
+    } else if let Some(path) = pattern.strip_suffix(&children) {
+        (path, TargetType::Directory)
+    } else {
+        (pattern, TargetType::File)
+    };
+    if unescape(path).is_none() {
+        return Entry {
+            path: pattern.into(),
+            target_type: TargetType::Pattern,
+            expires_at: None,
+            last_used: None,
+        };
+    }
+    Entry {
+        path: unescape(path).unwrap_or_default().into(),
+        target_type,
+        expires_at: None,
+        last_used: None,
+    }
+}
+
+/// Reverts `glob::Pattern::escape`, which wraps `?`, `*`, `[` and `]` in brackets. `None` for a glob.
+fn unescape(pattern: &str) -> Option<String> {
+    let mut path = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        let bytes = rest.as_bytes();
+        if c == '[' && bytes.len() >= 3 && bytes[2] == b']' && b"?*[]".contains(&bytes[1]) {
+            path.push(bytes[1] as char);
+            rest = &rest[3..];
+        } else if "?*[]".contains(c) {
+            return None;
+        } else {
+            path.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Some(path)
+}
+
+/// Reads the scope file, returns whether it must be rewritten in the current version.
+pub(crate) fn load(path: &Path, codec: &Codec) -> Result<(Scopes, bool)> {
+    let (bytes, reencode) = codec.decode(read(path)?)?;
+    match serde_json::from_slice::<JsonValue>(&bytes) {
+        Ok(value) => migrate(value).map(|(state, outdated)| (state, outdated || reencode)),
+        // bincode isn't valid JSON, it starts with the length of the paths
+        Err(_) => {
+            let legacy: LegacyScope = bincode::deserialize(&bytes)?;
+            let v1 = ScopeFileV1 {
+                allowed_paths: legacy.allowed_paths,
+                forbidden_patterns: legacy.forbidden_patterns,
+            };
+            Ok((Scopes::mirrored(v1.into()), true))
+        }
+    }
+}
+
+fn migrate(value: JsonValue) -> Result<(Scopes, bool)> {
+    let version = value
+        .get("version")
+        .and_then(JsonValue::as_u64)
+        .ok_or(Error::MissingVersion)?;
+    match version {
+        1 => {
+            let v1 = serde_json::from_value::<ScopeFileV1>(value)?;
+            Ok((Scopes::mirrored(v1.into()), true))
+        }
+        2 => {
+            let v2 = serde_json::from_value::<ScopeFileV2>(value)?;
+            Ok((Scopes::mirrored(v2.state), true))
+        }
+        SCOPE_FILE_VERSION => Ok((serde_json::from_value::<ScopeFile>(value)?.scopes, false)),
+        version => Err(Error::UnsupportedVersion(version)),
+    }
+}
+
+pub(crate) fn save(path: &Path, scopes: &Scopes, codec: &Codec) -> Result<()> {
+    let file = ScopeFile {
+        version: SCOPE_FILE_VERSION,
+        scopes: scopes.clone(),
+    };
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    // a crash while writing leaves the previous file intact
+    let temp = temp_path(path);
+    let mut f = File::create(&temp)?;
+    f.write_all(&codec.encode(serde_json::to_vec_pretty(&file)?)?)?;
+    f.sync_all()?;
+    rename(&temp, path)?;
+    Ok(())
+}
+
+/// The file written by [`save`] before it replaces the scope file.
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".tmp")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Moves the unreadable scope file aside, so the next save doesn't overwrite the grants it may still hold.
+pub(crate) fn back_up(path: &Path) -> PathBuf {
+    let backup = with_suffix(path, ".bak");
+    if let Err(e) = rename(path, &backup) {
+        log::error!("failed to back up the scope file: {}", e);
+    }
+    backup
+}
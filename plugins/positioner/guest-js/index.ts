@@ -3,6 +3,8 @@
 
 import { invoke } from "@tauri-apps/api/tauri";
 
+export type { ErrorPayload } from "./types/ErrorPayload";
+
 /**
  * Well known window positions.
  */
@@ -12,10 +12,12 @@
 mod ext;
 
 pub use ext::*;
+use serde::{ser::Serializer, Serialize};
 use tauri::{
     plugin::{self, TauriPlugin},
-    Result, Runtime,
+    Runtime,
 };
+use tauri_plugin_common::{error, PluginError};
 
 #[cfg(feature = "system-tray")]
 use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, SystemTrayEvent};
@@ -51,9 +53,37 @@ pub fn on_tray_event<R: Runtime>(app: &AppHandle<R>, event: &SystemTrayEvent) {
     }
 }
 
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "positioner";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Tauri(..) => "tauri",
+        }
+    }
+}
+
 #[tauri::command]
 async fn move_window<R: Runtime>(window: tauri::Window<R>, position: Position) -> Result<()> {
-    window.move_window(position)
+    window.move_window(position)?;
+    Ok(())
 }
 
 /// The Tauri plugin that exposes [`WindowExt::move_window`] to the webview.
@@ -0,0 +1,80 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export type PowerSource = "ac" | "battery" | "unknown";
+
+export interface Battery {
+  /** The charge between `0` and `1`, averaged over all batteries. */
+  level: number;
+  charging: boolean;
+}
+
+export interface PowerStatus {
+  source: PowerSource;
+  /** `null` on machines without a battery. */
+  battery: Battery | null;
+}
+
+export type PowerEvent =
+  | { type: "statusChanged"; payload: PowerStatus }
+  | { type: "sourceChanged"; payload: PowerSource }
+  /** Not reported on macOS. */
+  | { type: "suspend" }
+  | { type: "resume" }
+  | { type: "lock" }
+  | { type: "unlock" };
+
+/**
+ * What an inhibitor prevents:
+ * - `sleep` keeps the system awake, the display may still turn off.
+ * - `display` keeps the system awake and the display on.
+ */
+export type InhibitKind = "sleep" | "display";
+
+export async function status(): Promise<PowerStatus> {
+  return await invoke("plugin:power|status");
+}
+
+export async function onEvent(
+  handler: (event: PowerEvent) => void
+): Promise<UnlistenFn> {
+  return await listen<PowerEvent>("power://event", (event) =>
+    handler(event.payload)
+  );
+}
+
+export class Inhibitor {
+  id: number;
+
+  constructor(id: number) {
+    this.id = id;
+  }
+
+  async release(): Promise<void> {
+    await invoke("plugin:power|release", { id: this.id });
+  }
+}
+
+/**
+ * Prevents the system from sleeping until the inhibitor is released.
+ * The reason is shown to the user by the operating system.
+ *
+ * ```ts
+ * const inhibitor = await inhibit("Exporting video");
+ * try {
+ *   await exportVideo();
+ * } finally {
+ *   await inhibitor.release();
+ * }
+ * ```
+ */
+export async function inhibit(
+  reason: string,
+  kind: InhibitKind = "sleep"
+): Promise<Inhibitor> {
+  return await invoke<number>("plugin:power|inhibit", { reason, kind }).then(
+    (id) => new Inhibitor(id)
+  );
+}
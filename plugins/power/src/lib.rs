@@ -0,0 +1,282 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+type Result<T> = std::result::Result<T, Error>;
+type EventHandler = dyn Fn(&PowerEvent) + Send + Sync;
+type Emitter = Arc<dyn Fn(PowerEvent) + Send + Sync>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[cfg(target_os = "linux")]
+    #[error(transparent)]
+    DBus(#[from] zbus::Error),
+    #[cfg(windows)]
+    #[error(transparent)]
+    Windows(#[from] ::windows::core::Error),
+    #[error("inhibitor {0} not found")]
+    InhibitorNotFound(u32),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "power";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            #[cfg(target_os = "linux")]
+            Self::DBus(..) => "dbus",
+            #[cfg(windows)]
+            Self::Windows(..) => "windows",
+            Self::InhibitorNotFound(..) => "inhibitor_not_found",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    /// Desktops usually don't report their power supply.
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Battery {
+    /// The charge between `0` and `1`, averaged over all batteries.
+    pub level: f64,
+    pub charging: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerStatus {
+    pub source: PowerSource,
+    /// `None` on machines without a battery.
+    pub battery: Option<Battery>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "camelCase")]
+pub enum PowerEvent {
+    StatusChanged(PowerStatus),
+    /// The machine was plugged in or unplugged.
+    SourceChanged(PowerSource),
+    /// The system is about to sleep, not reported on macOS.
+    Suspend,
+    Resume,
+    /// The screen was locked.
+    Lock,
+    Unlock,
+}
+
+/// What an inhibitor prevents.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InhibitKind {
+    /// Keeps the system awake, the display may still turn off.
+    #[default]
+    Sleep,
+    /// Keeps the system awake and the display on, e.g. while presenting.
+    Display,
+}
+
+pub struct Power<R: Runtime> {
+    app: AppHandle<R>,
+    status: Mutex<PowerStatus>,
+    next_id: AtomicU32,
+    inhibitors: Mutex<HashMap<u32, platform::Inhibitor>>,
+    handlers: Mutex<Vec<Box<EventHandler>>>,
+}
+
+impl<R: Runtime> Power<R> {
+    pub fn status(&self) -> PowerStatus {
+        platform::status()
+    }
+
+    /// Prevents the system from sleeping until [`Self::release`] is called with the returned id.
+    ///
+    /// The reason is shown to the user by the operating system, e.g. `Exporting video`.
+    pub fn inhibit(&self, kind: InhibitKind, reason: &str) -> Result<u32> {
+        let inhibitor = platform::inhibit(&self.app.package_info().name, reason, kind)?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.inhibitors.lock().unwrap().insert(id, inhibitor);
+        Ok(id)
+    }
+
+    pub fn release(&self, id: u32) -> Result<()> {
+        self.inhibitors
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .map(drop)
+            .ok_or(Error::InhibitorNotFound(id))
+    }
+
+    /// Calls `handler` for every power event, before it is emitted to the windows.
+    pub fn on_event<F: Fn(&PowerEvent) + Send + Sync + 'static>(&self, handler: F) {
+        self.handlers.lock().unwrap().push(Box::new(handler));
+    }
+
+    fn emit(&self, event: PowerEvent) {
+        for handler in self.handlers.lock().unwrap().iter() {
+            handler(&event);
+        }
+        let _ = self.app.emit_all("power://event", event);
+    }
+
+    fn poll(&self) {
+        let status = platform::status();
+        let previous = std::mem::replace(&mut *self.status.lock().unwrap(), status);
+        if previous.source != status.source {
+            self.emit(PowerEvent::SourceChanged(status.source));
+        }
+        if previous != status {
+            self.emit(PowerEvent::StatusChanged(status));
+        }
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn power(&self) -> State<'_, Power<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn power(&self) -> State<'_, Power<R>> {
+        self.state::<Power<R>>()
+    }
+}
+
+#[command]
+async fn status<R: Runtime>(app: AppHandle<R>) -> Result<PowerStatus> {
+    Ok(app.power().status())
+}
+
+#[command]
+async fn inhibit<R: Runtime>(
+    app: AppHandle<R>,
+    kind: Option<InhibitKind>,
+    reason: String,
+) -> Result<u32> {
+    app.power().inhibit(kind.unwrap_or_default(), &reason)
+}
+
+#[command]
+async fn release<R: Runtime>(app: AppHandle<R>, id: u32) -> Result<()> {
+    app.power().release(id)
+}
+
+pub struct Builder {
+    poll_interval: Duration,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How often the battery and power source are checked, defaults to 10 seconds.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let poll_interval = self.poll_interval;
+
+        PluginBuilder::new("power")
+            .invoke_handler(tauri::generate_handler![status, inhibit, release])
+            .setup(move |app| {
+                app.manage(Power {
+                    app: app.clone(),
+                    status: Mutex::new(platform::status()),
+                    next_id: Default::default(),
+                    inhibitors: Default::default(),
+                    handlers: Default::default(),
+                });
+
+                let handle = app.clone();
+                platform::listen(Arc::new(move |event| handle.power().emit(event)));
+
+                let app = app.clone();
+                spawn(move || loop {
+                    sleep(poll_interval);
+                    app.power().poll();
+                });
+
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
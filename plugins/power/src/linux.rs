@@ -0,0 +1,164 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use log::warn;
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::{OwnedFd, OwnedObjectPath},
+};
+
+use std::{
+    fs::{read_dir, read_to_string},
+    thread::spawn,
+};
+
+use crate::{Battery, Emitter, InhibitKind, PowerEvent, PowerSource, PowerStatus, Result};
+
+const LOGIND: &str = "org.freedesktop.login1";
+const SCREENSAVER: &str = "org.freedesktop.ScreenSaver";
+
+fn manager(connection: &Connection) -> zbus::Result<Proxy<'_>> {
+    Proxy::new(
+        connection,
+        LOGIND,
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )
+}
+
+pub(crate) fn status() -> PowerStatus {
+    let mut levels = Vec::new();
+    let mut charging = false;
+    let mut ac = None;
+
+    for supply in read_dir("/sys/class/power_supply")
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let path = supply.path();
+        let read = |name: &str| {
+            read_to_string(path.join(name))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        match read("type").as_str() {
+            // `Device` batteries belong to peripherals, e.g. a wireless mouse
+            "Battery" if read("scope") != "Device" => {
+                if let Ok(capacity) = read("capacity").parse::<f64>() {
+                    levels.push(capacity / 100.0);
+                }
+                charging |= read("status") == "Charging";
+            }
+            "Mains" | "USB" => ac = Some(ac.unwrap_or(false) || read("online") == "1"),
+            _ => {}
+        }
+    }
+
+    let battery = (!levels.is_empty()).then(|| Battery {
+        level: levels.iter().sum::<f64>() / levels.len() as f64,
+        charging,
+    });
+    let source = match (ac, battery.is_some()) {
+        (Some(true), _) => PowerSource::Ac,
+        (_, true) => PowerSource::Battery,
+        _ => PowerSource::Unknown,
+    };
+    PowerStatus { source, battery }
+}
+
+fn watch_sleep(emit: &Emitter) -> zbus::Result<()> {
+    let connection = Connection::system()?;
+    for signal in manager(&connection)?.receive_signal("PrepareForSleep")? {
+        let suspending: bool = signal.body()?;
+        emit(if suspending {
+            PowerEvent::Suspend
+        } else {
+            PowerEvent::Resume
+        });
+    }
+    Ok(())
+}
+
+fn watch_lock(emit: &Emitter) -> zbus::Result<()> {
+    let connection = Connection::system()?;
+    let session: OwnedObjectPath =
+        manager(&connection)?.call("GetSessionByPID", &std::process::id())?;
+    let session = Proxy::new(
+        &connection,
+        LOGIND,
+        session,
+        "org.freedesktop.login1.Session",
+    )?;
+    for signal in session.receive_all_signals()? {
+        match signal.member().as_ref().map(|m| m.as_str()) {
+            Some("Lock") => emit(PowerEvent::Lock),
+            Some("Unlock") => emit(PowerEvent::Unlock),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Listens to logind, which reports sleep and the lock requests of the session.
+pub(crate) fn listen(emit: Emitter) {
+    let emit_ = emit.clone();
+    spawn(move || {
+        if let Err(e) = watch_sleep(&emit_) {
+            warn!("failed to listen to sleep events: {}", e);
+        }
+    });
+    spawn(move || {
+        if let Err(e) = watch_lock(&emit) {
+            warn!("failed to listen to lock events: {}", e);
+        }
+    });
+}
+
+pub(crate) struct Inhibitor {
+    /// logind releases the inhibitor when the file descriptor is closed.
+    _fd: OwnedFd,
+    screensaver: Option<(Connection, u32)>,
+}
+
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        if let Some((connection, cookie)) = &self.screensaver {
+            let _ = connection.call_method(
+                Some(SCREENSAVER),
+                "/org/freedesktop/ScreenSaver",
+                Some(SCREENSAVER),
+                "UnInhibit",
+                cookie,
+            );
+        }
+    }
+}
+
+pub(crate) fn inhibit(app: &str, reason: &str, kind: InhibitKind) -> Result<Inhibitor> {
+    let connection = Connection::system()?;
+    let fd = manager(&connection)?.call("Inhibit", &("sleep:idle", app, reason, "block"))?;
+
+    // logind doesn't blank the screen, the desktop environment does
+    let screensaver = if kind == InhibitKind::Display {
+        let connection = Connection::session()?;
+        let cookie = connection
+            .call_method(
+                Some(SCREENSAVER),
+                "/org/freedesktop/ScreenSaver",
+                Some(SCREENSAVER),
+                "Inhibit",
+                &(app, reason),
+            )?
+            .body()?;
+        Some((connection, cookie))
+    } else {
+        None
+    };
+
+    Ok(Inhibitor {
+        _fd: fd,
+        screensaver,
+    })
+}
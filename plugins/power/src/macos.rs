@@ -0,0 +1,110 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{
+    process::{Child, Command},
+    thread::{sleep, spawn},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{Battery, Emitter, InhibitKind, PowerEvent, PowerSource, PowerStatus, Result};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// `Instant` stops while the machine sleeps, a larger gap with the wall clock means it slept.
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(10);
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses `pmset -g batt`:
+///
+/// ```text
+/// Now drawing from 'AC Power'
+///  -InternalBattery-0 (id=4653155)	85%; charging; 1:05 remaining present: true
+/// ```
+pub(crate) fn status() -> PowerStatus {
+    let Some(output) = run("pmset", &["-g", "batt"]) else {
+        return PowerStatus::default();
+    };
+    let source = match output.lines().next() {
+        Some(line) if line.contains("'AC Power'") => PowerSource::Ac,
+        Some(line) if line.contains("'Battery Power'") => PowerSource::Battery,
+        _ => PowerSource::Unknown,
+    };
+    let battery = output
+        .lines()
+        .find(|line| line.contains("InternalBattery"))
+        .and_then(|line| {
+            let (_, details) = line.split_once('\t')?;
+            let mut fields = details.split(';').map(str::trim);
+            let level = fields.next()?.strip_suffix('%')?.parse::<f64>().ok()?;
+            let state = fields.next().unwrap_or_default();
+            Some(Battery {
+                level: level / 100.0,
+                charging: state == "charging" || state == "finishing charge",
+            })
+        });
+    PowerStatus { source, battery }
+}
+
+fn is_locked() -> bool {
+    run("ioreg", &["-n", "Root", "-d1"])
+        .is_some_and(|output| output.contains("\"CGSSessionScreenIsLocked\"=Yes"))
+}
+
+/// Polls the lock state and detects wake ups, there is no notification without an `NSApplication` observer.
+pub(crate) fn listen(emit: Emitter) {
+    spawn(move || {
+        let mut locked = is_locked();
+        let mut instant = Instant::now();
+        let mut time = SystemTime::now();
+        loop {
+            sleep(POLL_INTERVAL);
+
+            let elapsed = instant.elapsed();
+            let wall = time.elapsed().unwrap_or_default();
+            if wall > elapsed + SLEEP_THRESHOLD {
+                emit(PowerEvent::Resume);
+            }
+            instant = Instant::now();
+            time = SystemTime::now();
+
+            if is_locked() != locked {
+                locked = !locked;
+                emit(if locked {
+                    PowerEvent::Lock
+                } else {
+                    PowerEvent::Unlock
+                });
+            }
+        }
+    });
+}
+
+/// Holds a `caffeinate` process, which exits with the app thanks to `-w`.
+pub(crate) struct Inhibitor(Child);
+
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+pub(crate) fn inhibit(_app: &str, _reason: &str, kind: InhibitKind) -> Result<Inhibitor> {
+    let flags = match kind {
+        InhibitKind::Sleep => "-i",
+        InhibitKind::Display => "-id",
+    };
+    Command::new("caffeinate")
+        .args([flags, "-w", &std::process::id().to_string()])
+        .spawn()
+        .map(Inhibitor)
+        .map_err(Into::into)
+}
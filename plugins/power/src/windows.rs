@@ -0,0 +1,165 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use log::warn;
+use windows::{
+    core::PWSTR,
+    Win32::{
+        Foundation::{CloseHandle, HANDLE},
+        System::{
+            Power::{
+                GetSystemPowerStatus, PowerClearRequest, PowerCreateRequest,
+                PowerRegisterSuspendResumeNotification, PowerRequestDisplayRequired,
+                PowerRequestSystemRequired, PowerSetRequest, DEVICE_NOTIFY_CALLBACK,
+                DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, POWER_REQUEST_TYPE, SYSTEM_POWER_STATUS,
+            },
+            StationsAndDesktops::{CloseDesktop, OpenInputDesktop},
+            Threading::{POWER_REQUEST_CONTEXT_SIMPLE_STRING, REASON_CONTEXT, REASON_CONTEXT_0},
+        },
+    },
+};
+
+use std::{
+    ffi::c_void,
+    iter::once,
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+use crate::{Battery, Emitter, InhibitKind, PowerEvent, PowerSource, PowerStatus, Result};
+
+const PBT_APMSUSPEND: u32 = 0x4;
+const PBT_APMRESUMEAUTOMATIC: u32 = 0x12;
+const DESKTOP_SWITCHDESKTOP: u32 = 0x100;
+const POWER_REQUEST_CONTEXT_VERSION: u32 = 0;
+
+const AC_LINE_OFFLINE: u8 = 0;
+const AC_LINE_ONLINE: u8 = 1;
+const BATTERY_FLAG_CHARGING: u8 = 8;
+/// Also set when the status is unknown, which is `255`.
+const BATTERY_FLAG_NO_BATTERY: u8 = 128;
+
+pub(crate) fn status() -> PowerStatus {
+    let mut status = SYSTEM_POWER_STATUS::default();
+    if !unsafe { GetSystemPowerStatus(&mut status) }.as_bool() {
+        return PowerStatus::default();
+    }
+
+    let battery = (status.BatteryFlag & BATTERY_FLAG_NO_BATTERY == 0
+        && status.BatteryLifePercent <= 100)
+        .then(|| Battery {
+            level: status.BatteryLifePercent as f64 / 100.0,
+            charging: status.BatteryFlag & BATTERY_FLAG_CHARGING != 0,
+        });
+    let source = match status.ACLineStatus {
+        AC_LINE_ONLINE => PowerSource::Ac,
+        AC_LINE_OFFLINE => PowerSource::Battery,
+        _ => PowerSource::Unknown,
+    };
+    PowerStatus { source, battery }
+}
+
+unsafe extern "system" fn on_power_event(
+    context: *const c_void,
+    kind: u32,
+    _setting: *const c_void,
+) -> u32 {
+    let emit = &*(context as *const Emitter);
+    match kind {
+        PBT_APMSUSPEND => emit(PowerEvent::Suspend),
+        PBT_APMRESUMEAUTOMATIC => emit(PowerEvent::Resume),
+        _ => {}
+    }
+    0
+}
+
+/// The input desktop can't be opened while the secure desktop of the lock screen is shown.
+fn is_locked() -> bool {
+    match unsafe { OpenInputDesktop(0, false, DESKTOP_SWITCHDESKTOP) } {
+        Ok(desktop) => {
+            unsafe { CloseDesktop(desktop) };
+            false
+        }
+        Err(_) => true,
+    }
+}
+
+pub(crate) fn listen(emit: Emitter) {
+    // the registration lives as long as the app, so the parameters are leaked
+    let parameters = Box::leak(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+        Callback: Some(on_power_event),
+        Context: Box::into_raw(Box::new(emit.clone())) as *mut c_void,
+    }));
+    let mut registration = std::ptr::null_mut();
+    let result = unsafe {
+        PowerRegisterSuspendResumeNotification(
+            DEVICE_NOTIFY_CALLBACK.0,
+            HANDLE(parameters as *mut _ as isize),
+            &mut registration,
+        )
+    };
+    if result != 0 {
+        warn!("failed to listen to sleep events: error {}", result);
+    }
+
+    spawn(move || {
+        let mut locked = is_locked();
+        loop {
+            sleep(Duration::from_secs(1));
+            if is_locked() != locked {
+                locked = !locked;
+                emit(if locked {
+                    PowerEvent::Lock
+                } else {
+                    PowerEvent::Unlock
+                });
+            }
+        }
+    });
+}
+
+pub(crate) struct Inhibitor {
+    request: HANDLE,
+    kinds: Vec<POWER_REQUEST_TYPE>,
+}
+
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        unsafe {
+            for kind in &self.kinds {
+                PowerClearRequest(self.request, *kind);
+            }
+            CloseHandle(self.request);
+        }
+    }
+}
+
+pub(crate) fn inhibit(_app: &str, reason: &str, kind: InhibitKind) -> Result<Inhibitor> {
+    let mut reason = reason.encode_utf16().chain(once(0)).collect::<Vec<_>>();
+    let context = REASON_CONTEXT {
+        Version: POWER_REQUEST_CONTEXT_VERSION,
+        Flags: POWER_REQUEST_CONTEXT_SIMPLE_STRING,
+        Reason: REASON_CONTEXT_0 {
+            SimpleReasonString: PWSTR(reason.as_mut_ptr()),
+        },
+    };
+    let request = unsafe { PowerCreateRequest(&context) }?;
+
+    let mut inhibitor = Inhibitor {
+        request,
+        kinds: Vec::new(),
+    };
+    let kinds = match kind {
+        InhibitKind::Sleep => vec![PowerRequestSystemRequired],
+        InhibitKind::Display => vec![PowerRequestSystemRequired, PowerRequestDisplayRequired],
+    };
+    for kind in kinds {
+        if !unsafe { PowerSetRequest(request, kind) }.as_bool() {
+            // dropping the inhibitor clears the requests already set
+            return Err(windows::core::Error::from_win32().into());
+        }
+        inhibitor.kinds.push(kind);
+    }
+    Ok(inhibitor)
+}
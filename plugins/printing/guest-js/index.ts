@@ -0,0 +1,83 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface Printer {
+  name: string;
+  description: string | null;
+  isDefault: boolean;
+  /** `stopped` when paused, offline or in error, jobs are queued until it's back. */
+  state: "idle" | "printing" | "stopped";
+}
+
+export interface PrintOptions {
+  /** Defaults to the default printer of the system. */
+  printer?: string;
+  /** Defaults to 1. */
+  copies?: number;
+  /** The media name, e.g. `A4`, `Letter` or `Legal`. Defaults to the paper of the printer. */
+  paper?: string;
+  orientation?: "portrait" | "landscape";
+  /** The name of the job shown in the queue, defaults to the file name. */
+  title?: string;
+}
+
+export type JobState =
+  | "pending"
+  | "printing"
+  | "completed"
+  | "canceled"
+  | "failed";
+
+export interface Job {
+  id: number;
+  printer: string;
+  state: JobState;
+}
+
+/**
+ * The printers installed on the system.
+ */
+async function printers(): Promise<Printer[]> {
+  return await invoke("plugin:printing|printers");
+}
+
+/**
+ * Sends the file to the printer without showing a dialog.
+ *
+ * Linux and macOS print PDF, PostScript, image and text files.
+ * Windows hands the file to the app registered to print its type, which may ignore the paper and orientation.
+ */
+async function printFile(path: string, options?: PrintOptions): Promise<Job> {
+  return await invoke("plugin:printing|print_file", { path, options });
+}
+
+/**
+ * Prints the content of the current window with the print dialog of the system.
+ *
+ * The page is laid out with its print styles, e.g. `@page` and `@media print` rules.
+ */
+async function printWindow(): Promise<void> {
+  return await invoke("plugin:printing|print_window");
+}
+
+/**
+ * The jobs sent by the app that are not finished yet.
+ */
+async function jobs(): Promise<Job[]> {
+  return await invoke("plugin:printing|jobs");
+}
+
+async function cancelJob(id: number): Promise<void> {
+  return await invoke("plugin:printing|cancel_job", { id });
+}
+
+/**
+ * Calls the handler every time the state of a job sent by the app changes, until it's finished.
+ */
+async function onJobChange(handler: (job: Job) => void): Promise<UnlistenFn> {
+  return await listen<Job>("printing://job", ({ payload }) => handler(payload));
+}
+
+export { printers, printFile, printWindow, jobs, cancelJob, onJobChange };
@@ -0,0 +1,157 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Talks to CUPS with its command line tools, which are installed alongside the scheduler on Linux and macOS.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+    process::{Command, Output},
+};
+
+use crate::{Error, JobState, Orientation, PrintOptions, Printer, PrinterState, Result};
+
+/// The tools are localized, the messages are parsed in English.
+fn command(program: &str) -> Command {
+    let mut command = Command::new(program);
+    command.env("LC_ALL", "C");
+    command
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).trim().to_string()
+}
+
+fn lpstat(args: &[&str]) -> Result<String> {
+    let output = command("lpstat").args(args).output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let message = stderr(&output);
+        // `lpstat` fails when there is nothing to list
+        if message.contains("No destinations added") {
+            Ok(String::new())
+        } else {
+            Err(io::Error::other(message).into())
+        }
+    }
+}
+
+/// Jobs are named `<printer>-<id>`.
+fn job_id(name: &str) -> Option<u32> {
+    name.rsplit_once('-')?.1.parse().ok()
+}
+
+fn job_name(printer: &str, id: u32) -> String {
+    format!("{}-{}", printer, id)
+}
+
+pub(crate) fn printers() -> Result<Vec<Printer>> {
+    let default = lpstat(&["-d"])?;
+    let default = default
+        .trim()
+        .strip_prefix("system default destination: ")
+        .map(ToString::to_string);
+
+    let mut printers: Vec<Printer> = Vec::new();
+    for line in lpstat(&["-l", "-p"])?.lines() {
+        if let Some(line) = line.strip_prefix("printer ") {
+            let (name, status) = line.split_once(' ').unwrap_or((line, ""));
+            let state = if status.starts_with("disabled") {
+                PrinterState::Stopped
+            } else if status.starts_with("now printing") {
+                PrinterState::Printing
+            } else {
+                PrinterState::Idle
+            };
+            printers.push(Printer {
+                name: name.to_string(),
+                description: None,
+                is_default: default.as_deref() == Some(name),
+                state,
+            });
+        } else if let Some(description) = line.trim_start().strip_prefix("Description: ") {
+            if let Some(printer) = printers.last_mut() {
+                let description = description.trim();
+                if !description.is_empty() {
+                    printer.description.replace(description.to_string());
+                }
+            }
+        }
+    }
+    Ok(printers)
+}
+
+pub(crate) fn print(path: &Path, printer: &str, options: &PrintOptions) -> Result<u32> {
+    let mut lp = command("lp");
+    lp.args(["-d", printer]);
+    if let Some(copies) = options.copies {
+        lp.arg("-n").arg(copies.to_string());
+    }
+    if let Some(paper) = &options.paper {
+        lp.arg("-o").arg(format!("media={}", paper));
+    }
+    match options.orientation {
+        Some(Orientation::Portrait) => {
+            lp.args(["-o", "orientation-requested=3"]);
+        }
+        Some(Orientation::Landscape) => {
+            lp.args(["-o", "orientation-requested=4"]);
+        }
+        None => {}
+    }
+    if let Some(title) = &options.title {
+        lp.arg("-t").arg(title);
+    }
+
+    let output = lp.arg("--").arg(path).output()?;
+    if !output.status.success() {
+        return Err(Error::PrintFailed(path.to_path_buf(), stderr(&output)));
+    }
+    // request id is <printer>-<id> (1 file(s))
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .skip_while(|word| *word != "is")
+        .nth(1)
+        .and_then(job_id)
+        .ok_or_else(|| Error::PrintFailed(path.to_path_buf(), "unexpected response of lp".into()))
+}
+
+pub(crate) fn active_jobs(printer: &str) -> Result<HashMap<u32, JobState>> {
+    let status = lpstat(&["-p", printer])?;
+    let printing = status
+        .split_whitespace()
+        .skip_while(|word| *word != "printing")
+        .nth(1)
+        .and_then(|name| job_id(name.trim_end_matches('.')));
+    let stopped = status
+        .split_whitespace()
+        .nth(2)
+        .is_some_and(|word| word == "disabled");
+
+    let mut jobs = HashMap::new();
+    for line in lpstat(&["-o", printer])?.lines() {
+        if let Some(id) = line.split_whitespace().next().and_then(job_id) {
+            let state = if stopped {
+                JobState::Failed
+            } else if printing == Some(id) {
+                JobState::Printing
+            } else {
+                JobState::Pending
+            };
+            jobs.insert(id, state);
+        }
+    }
+    Ok(jobs)
+}
+
+pub(crate) fn cancel(printer: &str, id: u32) -> Result<()> {
+    let output = command("cancel").arg(job_name(printer, id)).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(stderr(&output)).into())
+    }
+}
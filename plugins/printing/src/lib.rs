@@ -0,0 +1,361 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State, Window,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod cups;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use cups as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error("no printer given and no default printer configured")]
+    NoDefaultPrinter,
+    #[error("printer not found: {0}")]
+    PrinterNotFound(String),
+    #[error("failed to print {0}: {1}")]
+    PrintFailed(PathBuf, String),
+    #[error("print job not found for the given id: {0}")]
+    JobNotFound(u32),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "printing";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Tauri(..) => "tauri",
+            Self::NoDefaultPrinter => "no_default_printer",
+            Self::PrinterNotFound(..) => "printer_not_found",
+            Self::PrintFailed(..) => "print_failed",
+            Self::JobNotFound(..) => "job_not_found",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PrinterState {
+    Idle,
+    Printing,
+    /// Paused, offline or in error, jobs are queued until it's back.
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Printer {
+    pub name: String,
+    pub description: Option<String>,
+    pub is_default: bool,
+    pub state: PrinterState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrintOptions {
+    /// Defaults to the default printer of the system.
+    pub printer: Option<String>,
+    /// Defaults to 1.
+    pub copies: Option<u32>,
+    /// The media name, e.g. `A4`, `Letter` or `Legal`. Defaults to the paper of the printer.
+    pub paper: Option<String>,
+    pub orientation: Option<Orientation>,
+    /// The name of the job shown in the queue, defaults to the file name.
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    Pending,
+    Printing,
+    Completed,
+    Canceled,
+    /// The printer reported an error, the job may resume once it's solved.
+    Failed,
+}
+
+impl JobState {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Canceled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: u32,
+    pub printer: String,
+    pub state: JobState,
+}
+
+pub struct Printing<R: Runtime> {
+    app: AppHandle<R>,
+    jobs: Mutex<HashMap<u32, Job>>,
+}
+
+impl<R: Runtime> Printing<R> {
+    /// The printers installed on the system.
+    pub fn printers(&self) -> Result<Vec<Printer>> {
+        platform::printers()
+    }
+
+    pub fn default_printer(&self) -> Result<Option<Printer>> {
+        Ok(self
+            .printers()?
+            .into_iter()
+            .find(|printer| printer.is_default))
+    }
+
+    /// Sends the file to the printer without showing a dialog.
+    ///
+    /// Linux and macOS print PDF, PostScript, image and text files.
+    /// Windows hands the file to the app registered to print its type,
+    /// which may ignore the paper and orientation.
+    ///
+    /// The state of the job is then emitted with the `printing://job` event until it's finished.
+    pub fn print_file(&self, path: &Path, options: &PrintOptions) -> Result<Job> {
+        let printer = match &options.printer {
+            Some(printer) => printer.clone(),
+            None => self
+                .default_printer()?
+                .map(|printer| printer.name)
+                .ok_or(Error::NoDefaultPrinter)?,
+        };
+        if !self.printers()?.iter().any(|p| p.name == printer) {
+            return Err(Error::PrinterNotFound(printer));
+        }
+
+        let id = platform::print(path, &printer, options)?;
+        let job = Job {
+            id,
+            printer,
+            state: JobState::Pending,
+        };
+        self.jobs.lock().unwrap().insert(id, job.clone());
+        Ok(job)
+    }
+
+    /// The jobs sent by the app that are not finished yet.
+    pub fn jobs(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn cancel_job(&self, id: u32) -> Result<()> {
+        let printer = self
+            .jobs
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|job| job.printer.clone())
+            .ok_or(Error::JobNotFound(id))?;
+        platform::cancel(&printer, id)?;
+        self.set_state(id, JobState::Canceled);
+        Ok(())
+    }
+
+    fn set_state(&self, id: u32, state: JobState) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = match jobs.get_mut(&id) {
+            Some(job) if job.state != state => job,
+            _ => return,
+        };
+        job.state = state;
+        let job = if state.is_finished() {
+            jobs.remove(&id).unwrap()
+        } else {
+            job.clone()
+        };
+        drop(jobs);
+        let _ = self.app.emit_all("printing://job", job);
+    }
+
+    fn refresh(&self) {
+        let mut printers = self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .map(|job| job.printer.clone())
+            .collect::<Vec<_>>();
+        printers.sort();
+        printers.dedup();
+
+        for printer in printers {
+            let active = match platform::active_jobs(&printer) {
+                Ok(active) => active,
+                Err(e) => {
+                    log::warn!("failed to list the jobs of `{}`: {}", printer, e);
+                    continue;
+                }
+            };
+            let ids = self
+                .jobs
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|job| job.printer == printer)
+                .map(|job| job.id)
+                .collect::<Vec<_>>();
+            for id in ids {
+                // jobs leave the queue once printed
+                let state = active.get(&id).copied().unwrap_or(JobState::Completed);
+                self.set_state(id, state);
+            }
+        }
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn printing(&self) -> State<'_, Printing<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn printing(&self) -> State<'_, Printing<R>> {
+        self.state::<Printing<R>>()
+    }
+}
+
+#[command]
+async fn printers<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Printer>> {
+    app.printing().printers()
+}
+
+#[command]
+async fn print_file<R: Runtime>(
+    app: AppHandle<R>,
+    path: PathBuf,
+    options: Option<PrintOptions>,
+) -> Result<Job> {
+    app.printing()
+        .print_file(&path, &options.unwrap_or_default())
+}
+
+#[command]
+async fn print_window<R: Runtime>(window: Window<R>) -> Result<()> {
+    window.print().map_err(Into::into)
+}
+
+#[command]
+async fn jobs<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Job>> {
+    Ok(app.printing().jobs())
+}
+
+#[command]
+async fn cancel_job<R: Runtime>(app: AppHandle<R>, id: u32) -> Result<()> {
+    app.printing().cancel_job(id)
+}
+
+pub struct Builder {
+    poll_interval: Duration,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How often the state of the jobs is checked, defaults to 1 second.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let poll_interval = self.poll_interval;
+
+        PluginBuilder::new("printing")
+            .invoke_handler(tauri::generate_handler![
+                printers,
+                print_file,
+                print_window,
+                jobs,
+                cancel_job
+            ])
+            .setup(move |app| {
+                app.manage(Printing {
+                    app: app.clone(),
+                    jobs: Default::default(),
+                });
+
+                let app = app.clone();
+                spawn(move || loop {
+                    sleep(poll_interval);
+                    app.printing().refresh();
+                });
+
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
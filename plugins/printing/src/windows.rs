@@ -0,0 +1,264 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use windows::{
+    core::{PCWSTR, PWSTR},
+    Win32::{
+        Foundation::{HANDLE, HWND},
+        Graphics::Printing::{
+            ClosePrinter, EnumJobsW, EnumPrintersW, GetDefaultPrinterW, OpenPrinterW, SetJobW,
+            JOB_CONTROL_DELETE, JOB_INFO_1W, JOB_STATUS_BLOCKED_DEVQ, JOB_STATUS_COMPLETE,
+            JOB_STATUS_DELETED, JOB_STATUS_DELETING, JOB_STATUS_ERROR, JOB_STATUS_OFFLINE,
+            JOB_STATUS_PAPEROUT, JOB_STATUS_PRINTED, JOB_STATUS_PRINTING,
+            JOB_STATUS_USER_INTERVENTION, PRINTER_ENUM_CONNECTIONS, PRINTER_ENUM_LOCAL,
+            PRINTER_INFO_2W, PRINTER_STATUS_ERROR, PRINTER_STATUS_OFFLINE, PRINTER_STATUS_PAUSED,
+            PRINTER_STATUS_PRINTING,
+        },
+        UI::{Shell::ShellExecuteW, WindowsAndMessaging::SW_HIDE},
+    },
+};
+
+use std::{
+    collections::HashMap,
+    io,
+    iter::once,
+    path::Path,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use crate::{Error, JobState, PrintOptions, Printer, PrinterState, Result};
+
+/// How long the app printing the file may take to spool it.
+const SPOOL_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(once(0)).collect()
+}
+
+unsafe fn from_wide(s: PWSTR) -> Option<String> {
+    if s.is_null() {
+        None
+    } else {
+        s.to_string().ok().filter(|s| !s.is_empty())
+    }
+}
+
+/// Calls an enumeration function of the spooler twice, to get the size of the buffer and then fill it.
+///
+/// The strings of the structs point into the buffer, `map` reads them before it's freed.
+unsafe fn enumerate<T, U>(
+    call: impl Fn(*mut u8, u32, &mut u32, &mut u32) -> bool,
+    map: impl Fn(&T) -> U,
+) -> Vec<U> {
+    let (mut needed, mut count) = (0, 0);
+    call(std::ptr::null_mut(), 0, &mut needed, &mut count);
+    if needed == 0 {
+        return Vec::new();
+    }
+    // u64 keeps the buffer aligned for the structs
+    let mut buffer = vec![0u64; (needed as usize).div_ceil(8)];
+    if !call(
+        buffer.as_mut_ptr() as *mut u8,
+        needed,
+        &mut needed,
+        &mut count,
+    ) {
+        return Vec::new();
+    }
+    std::slice::from_raw_parts(buffer.as_ptr() as *const T, count as usize)
+        .iter()
+        .map(map)
+        .collect()
+}
+
+fn default_printer() -> Option<String> {
+    let mut len = 0;
+    unsafe { GetDefaultPrinterW(PWSTR::null(), &mut len) };
+    let mut buffer = vec![0u16; len as usize];
+    unsafe { GetDefaultPrinterW(PWSTR(buffer.as_mut_ptr()), &mut len) }
+        .as_bool()
+        .then(|| String::from_utf16_lossy(&buffer[..len.saturating_sub(1) as usize]))
+}
+
+pub(crate) fn printers() -> Result<Vec<Printer>> {
+    let default = default_printer();
+    let printers = unsafe {
+        enumerate(
+            |buffer, size, needed, count| {
+                EnumPrintersW(
+                    PRINTER_ENUM_LOCAL | PRINTER_ENUM_CONNECTIONS,
+                    PCWSTR::null(),
+                    2,
+                    buffer,
+                    size,
+                    needed,
+                    count,
+                )
+                .as_bool()
+            },
+            |info: &PRINTER_INFO_2W| {
+                let name = from_wide(info.pPrinterName)?;
+                let state = if info.Status
+                    & (PRINTER_STATUS_PAUSED | PRINTER_STATUS_ERROR | PRINTER_STATUS_OFFLINE)
+                    != 0
+                {
+                    PrinterState::Stopped
+                } else if info.Status & PRINTER_STATUS_PRINTING != 0 || info.cJobs > 0 {
+                    PrinterState::Printing
+                } else {
+                    PrinterState::Idle
+                };
+                Some(Printer {
+                    is_default: default.as_deref() == Some(name.as_str()),
+                    description: from_wide(info.pComment),
+                    name,
+                    state,
+                })
+            },
+        )
+    };
+    Ok(printers.into_iter().flatten().collect())
+}
+
+struct PrinterHandle(HANDLE);
+
+impl PrinterHandle {
+    fn open(printer: &str) -> Result<Self> {
+        let name = wide(printer);
+        let mut handle = HANDLE::default();
+        if unsafe { OpenPrinterW(PCWSTR(name.as_ptr()), &mut handle, std::ptr::null()) }.as_bool() {
+            Ok(Self(handle))
+        } else {
+            Err(Error::PrinterNotFound(printer.into()))
+        }
+    }
+
+    /// The id, document name and status of the jobs in the queue.
+    fn jobs(&self) -> Vec<(u32, Option<String>, u32)> {
+        unsafe {
+            enumerate(
+                |buffer, size, needed, count| {
+                    EnumJobsW(self.0, 0, u32::MAX, 1, buffer, size, needed, count).as_bool()
+                },
+                |job: &JOB_INFO_1W| (job.JobId, from_wide(job.pDocument), job.Status),
+            )
+        }
+    }
+}
+
+impl Drop for PrinterHandle {
+    fn drop(&mut self) {
+        unsafe { ClosePrinter(self.0) };
+    }
+}
+
+fn state(status: u32) -> JobState {
+    if status & (JOB_STATUS_DELETING | JOB_STATUS_DELETED) != 0 {
+        JobState::Canceled
+    } else if status & (JOB_STATUS_PRINTED | JOB_STATUS_COMPLETE) != 0 {
+        JobState::Completed
+    } else if status
+        & (JOB_STATUS_ERROR
+            | JOB_STATUS_OFFLINE
+            | JOB_STATUS_PAPEROUT
+            | JOB_STATUS_BLOCKED_DEVQ
+            | JOB_STATUS_USER_INTERVENTION)
+        != 0
+    {
+        JobState::Failed
+    } else if status & JOB_STATUS_PRINTING != 0 {
+        JobState::Printing
+    } else {
+        JobState::Pending
+    }
+}
+
+fn print_once(path: &Path, printer: &str) -> Result<u32> {
+    let handle = PrinterHandle::open(printer)?;
+    let before = handle
+        .jobs()
+        .into_iter()
+        .map(|(id, _, _)| id)
+        .collect::<Vec<_>>();
+
+    let file = wide(&path.to_string_lossy());
+    let parameters = wide(&format!("\"{}\"", printer));
+    let operation = wide("printto");
+    let result = unsafe {
+        ShellExecuteW(
+            HWND::default(),
+            PCWSTR(operation.as_ptr()),
+            PCWSTR(file.as_ptr()),
+            PCWSTR(parameters.as_ptr()),
+            PCWSTR::null(),
+            SW_HIDE.0 as i32,
+        )
+    };
+    // values up to 32 are errors
+    if result.0 <= 32 {
+        return Err(Error::PrintFailed(
+            path.to_path_buf(),
+            format!(
+                "no app is registered to print this type of file (error {})",
+                result.0
+            ),
+        ));
+    }
+
+    // the job is spooled by the app, it's the new one named after the file
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let started = Instant::now();
+    while started.elapsed() < SPOOL_TIMEOUT {
+        let job = handle.jobs().into_iter().find(|(id, document, _)| {
+            !before.contains(id)
+                && match document {
+                    Some(document) => document.contains(&file_name),
+                    None => true,
+                }
+        });
+        if let Some((id, _, _)) = job {
+            return Ok(id);
+        }
+        sleep(Duration::from_millis(250));
+    }
+    Err(Error::PrintFailed(
+        path.to_path_buf(),
+        "the document was not spooled in time".into(),
+    ))
+}
+
+/// The copies and the paper are picked by the app printing the file, the copies are sent as separate jobs.
+pub(crate) fn print(path: &Path, printer: &str, options: &PrintOptions) -> Result<u32> {
+    if options.paper.is_some() || options.orientation.is_some() || options.title.is_some() {
+        log::warn!(
+            "the paper, orientation and title are chosen by the app printing the file on Windows"
+        );
+    }
+    let mut id = print_once(path, printer)?;
+    for _ in 1..options.copies.unwrap_or(1) {
+        id = print_once(path, printer)?;
+    }
+    Ok(id)
+}
+
+pub(crate) fn active_jobs(printer: &str) -> Result<HashMap<u32, JobState>> {
+    Ok(PrinterHandle::open(printer)?
+        .jobs()
+        .into_iter()
+        .map(|(id, _, status)| (id, state(status)))
+        .collect())
+}
+
+pub(crate) fn cancel(printer: &str, id: u32) -> Result<()> {
+    let handle = PrinterHandle::open(printer)?;
+    if unsafe { SetJobW(handle.0, id, 0, std::ptr::null_mut(), JOB_CONTROL_DELETE) }.as_bool() {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error().into())
+    }
+}
@@ -0,0 +1,148 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { UnlistenFn } from "@tauri-apps/api/event";
+import { appWindow } from "@tauri-apps/api/window";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface SessionOptions {
+  /** The program to run, defaults to the shell of the user. The other programs must be allowed by the app. */
+  shell?: string;
+  args?: string[];
+  /** Defaults to the working directory of the app. */
+  cwd?: string;
+  /** Added to the environment of the app, `TERM` defaults to `xterm-256color`. `LD_*` and `DYLD_*` are rejected. */
+  env?: Record<string, string>;
+  /** Defaults to 80. */
+  cols?: number;
+  /** Defaults to 24. */
+  rows?: number;
+}
+
+interface DataPayload {
+  id: number;
+  /** Base64, as Tauri v1 has no channels for the raw bytes. */
+  data: string;
+}
+
+interface ExitPayload {
+  id: number;
+  exitCode: number;
+}
+
+export interface SessionHandlers {
+  /** The raw output of the terminal, escape sequences included. */
+  onData?: (data: Uint8Array) => void;
+  /** Called once the shell exited, after all its output. */
+  onExit?: (exitCode: number) => void;
+}
+
+const encoder = new TextEncoder();
+
+function toBase64(bytes: Uint8Array): string {
+  let binary = "";
+  // in chunks, as `String.fromCharCode` takes its arguments on the stack
+  for (let i = 0; i < bytes.length; i += 0x8000) {
+    binary += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+  }
+  return btoa(binary);
+}
+
+function fromBase64(base64: string): Uint8Array {
+  const binary = atob(base64);
+  const bytes = new Uint8Array(binary.length);
+  for (let i = 0; i < binary.length; i++) {
+    bytes[i] = binary.charCodeAt(i);
+  }
+  return bytes;
+}
+
+/**
+ * A shell running in a pseudo terminal, e.g. to connect to xterm.js.
+ */
+class Session {
+  id: number;
+  private unlisten: UnlistenFn[];
+
+  constructor(id: number, unlisten: UnlistenFn[]) {
+    this.id = id;
+    this.unlisten = unlisten;
+  }
+
+  async write(data: string | Uint8Array): Promise<void> {
+    const bytes = typeof data === "string" ? encoder.encode(data) : data;
+    return await invoke("plugin:pty|write", {
+      id: this.id,
+      data: toBase64(bytes),
+    });
+  }
+
+  async resize(cols: number, rows: number): Promise<void> {
+    return await invoke("plugin:pty|resize", { id: this.id, cols, rows });
+  }
+
+  /**
+   * The exit is reported to `onExit` like any other.
+   */
+  async kill(): Promise<void> {
+    return await invoke("plugin:pty|kill", { id: this.id });
+  }
+
+  /**
+   * Stops the handlers, they are stopped after the exit otherwise.
+   */
+  close(): void {
+    this.unlisten.forEach((unlisten) => unlisten());
+    this.unlisten = [];
+  }
+}
+
+/**
+ * Spawns a shell in a new pseudo terminal.
+ *
+ * The handlers are attached before the shell starts so its first output, e.g.
+ * the prompt, isn't missed.
+ */
+async function spawn(
+  options: SessionOptions = {},
+  handlers: SessionHandlers = {}
+): Promise<Session> {
+  let id: number | null = null;
+  let session: Session | null = null;
+  // the events arriving before the id is known
+  const pending: Array<() => void> = [];
+  const dispatch = (eventId: number, handle: () => void): void => {
+    if (id === null) {
+      pending.push(() => {
+        if (eventId === id) handle();
+      });
+    } else if (eventId === id) {
+      handle();
+    }
+  };
+
+  const unlisten = await Promise.all([
+    appWindow.listen<DataPayload>("pty://data", (event) => {
+      dispatch(event.payload.id, () =>
+        handlers.onData?.(fromBase64(event.payload.data))
+      );
+    }),
+    appWindow.listen<ExitPayload>("pty://exit", (event) => {
+      dispatch(event.payload.id, () => {
+        handlers.onExit?.(event.payload.exitCode);
+        session?.close();
+      });
+    }),
+  ]);
+
+  try {
+    id = await invoke<number>("plugin:pty|spawn_session", { options });
+  } catch (e) {
+    unlisten.forEach((u) => u());
+    throw e;
+  }
+  session = new Session(id, unlisten);
+  pending.forEach((handle) => handle());
+  return session;
+}
+
+export { Session, spawn };
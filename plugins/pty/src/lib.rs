@@ -0,0 +1,424 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State, Window, WindowEvent,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::channel,
+        Arc, Mutex,
+    },
+    thread::spawn,
+};
+
+type Id = u32;
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Pty(#[from] anyhow::Error),
+    /// Also returned to the windows other than the one that spawned the session.
+    #[error("session not found for the given id: {0}")]
+    SessionNotFound(Id),
+    #[error("shell not allowed: {0}")]
+    ShellNotAllowed(String),
+    /// The variable makes the dynamic loader run other code in the shell, e.g. `LD_PRELOAD`.
+    #[error("environment variable not allowed: {0}")]
+    EnvNotAllowed(String),
+    /// The input sent from JavaScript isn't valid base64.
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "pty";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Pty(..) => "pty",
+            Self::SessionNotFound(..) => "session_not_found",
+            Self::ShellNotAllowed(..) => "shell_not_allowed",
+            Self::EnvNotAllowed(..) => "env_not_allowed",
+            Self::Base64(..) => "base64",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+fn default_cols() -> u16 {
+    80
+}
+
+fn default_rows() -> u16 {
+    24
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionOptions {
+    /// The program to run, defaults to the shell of the user, the others must be allowed with [`Builder::shells`].
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Defaults to the working directory of the app.
+    pub cwd: Option<PathBuf>,
+    /// Added to the environment of the app, `TERM` defaults to `xterm-256color`.
+    ///
+    /// The variables of the dynamic loader, `LD_*` and `DYLD_*`, are rejected.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default = "default_cols")]
+    pub cols: u16,
+    #[serde(default = "default_rows")]
+    pub rows: u16,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self {
+            shell: None,
+            args: Vec::new(),
+            cwd: None,
+            env: HashMap::new(),
+            cols: default_cols(),
+            rows: default_rows(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    /// The raw output of the terminal, escape sequences included.
+    Data(Vec<u8>),
+    /// The shell exited, all the output was sent before.
+    Exit { exit_code: u32 },
+}
+
+struct Session {
+    /// The label of the window that spawned it, the only one its commands are accepted from.
+    window: Option<String>,
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+}
+
+pub struct Pty<R: Runtime> {
+    app: AppHandle<R>,
+    /// The programs allowed besides the shell of the user.
+    shells: Vec<String>,
+    next_id: AtomicU32,
+    sessions: Mutex<HashMap<Id, Arc<Session>>>,
+}
+
+impl<R: Runtime> Pty<R> {
+    fn session(&self, id: Id) -> Result<Arc<Session>> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or(Error::SessionNotFound(id))
+    }
+
+    /// Fails unless the session was spawned by the given window, or from Rust.
+    fn check_window(&self, id: Id, window: &str) -> Result<()> {
+        match &self.session(id)?.window {
+            Some(label) if label != window => Err(Error::SessionNotFound(id)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Spawns a shell in a new pseudo terminal.
+    ///
+    /// `on_event` is called from a background thread with the output, then once the shell exited.
+    /// The session can be used from any window, the ones spawned from JavaScript only by their window.
+    pub fn spawn<F: FnMut(Id, SessionEvent) + Send + 'static>(
+        &self,
+        options: &SessionOptions,
+        on_event: F,
+    ) -> Result<Id> {
+        self.spawn_for(None, options, on_event)
+    }
+
+    fn spawn_for<F: FnMut(Id, SessionEvent) + Send + 'static>(
+        &self,
+        window: Option<String>,
+        options: &SessionOptions,
+        mut on_event: F,
+    ) -> Result<Id> {
+        let default_shell = CommandBuilder::new_default_prog().get_shell();
+        let mut command = match &options.shell {
+            Some(shell) if *shell != default_shell && !self.shells.contains(shell) => {
+                return Err(Error::ShellNotAllowed(shell.clone()));
+            }
+            Some(shell) => CommandBuilder::new(shell),
+            None => CommandBuilder::new(default_shell),
+        };
+        // they'd load any library in the allowed shells
+        if let Some(key) = options
+            .env
+            .keys()
+            .find(|key| key.starts_with("LD_") || key.starts_with("DYLD_"))
+        {
+            return Err(Error::EnvNotAllowed(key.clone()));
+        }
+        command.args(&options.args);
+        match &options.cwd {
+            Some(cwd) => command.cwd(cwd),
+            None => command.cwd(std::env::current_dir()?),
+        }
+        if !options.env.contains_key("TERM") {
+            command.env("TERM", "xterm-256color");
+        }
+        for (key, value) in &options.env {
+            command.env(key, value);
+        }
+
+        let pair = native_pty_system().openpty(PtySize {
+            rows: options.rows,
+            cols: options.cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+        let mut child = pair.slave.spawn_command(command)?;
+        // the reader only ends once every handle of the slave is closed
+        drop(pair.slave);
+
+        let mut reader = pair.master.try_clone_reader()?;
+        let session = Session {
+            window,
+            writer: Mutex::new(pair.master.take_writer()?),
+            killer: Mutex::new(child.clone_killer()),
+            master: Mutex::new(pair.master),
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.sessions.lock().unwrap().insert(id, Arc::new(session));
+
+        // ConPTY keeps the output open after the shell exited, until the master is closed
+        let (tx, rx) = channel();
+        let app = self.app.clone();
+        spawn(move || {
+            let exit_code = child.wait().map(|status| status.exit_code()).unwrap_or(1);
+            app.pty().sessions.lock().unwrap().remove(&id);
+            let _ = tx.send(exit_code);
+        });
+        spawn(move || {
+            let mut buffer = [0; 4096];
+            loop {
+                match reader.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => on_event(id, SessionEvent::Data(buffer[..n].to_vec())),
+                }
+            }
+            let exit_code = rx.recv().unwrap_or(1);
+            on_event(id, SessionEvent::Exit { exit_code });
+        });
+
+        Ok(id)
+    }
+
+    /// Writes the input of the user, e.g. the keys typed in the terminal.
+    pub fn write(&self, id: Id, data: &[u8]) -> Result<()> {
+        let session = self.session(id)?;
+        let mut writer = session.writer.lock().unwrap();
+        writer.write_all(data)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Resizes the terminal, the shell is notified to redraw.
+    pub fn resize(&self, id: Id, cols: u16, rows: u16) -> Result<()> {
+        self.session(id)?
+            .master
+            .lock()
+            .unwrap()
+            .resize(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(Into::into)
+    }
+
+    /// Kills the shell, the exit is reported like any other.
+    pub fn kill(&self, id: Id) -> Result<()> {
+        self.session(id)?
+            .killer
+            .lock()
+            .unwrap()
+            .kill()
+            .map_err(Into::into)
+    }
+
+    /// The ids of the running sessions.
+    pub fn sessions(&self) -> Vec<Id> {
+        self.sessions.lock().unwrap().keys().copied().collect()
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn pty(&self) -> State<'_, Pty<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn pty(&self) -> State<'_, Pty<R>> {
+        self.state::<Pty<R>>()
+    }
+}
+
+/// Tauri v1 has no channels, the output goes through JSON events, in base64 rather than an array of numbers.
+#[derive(Clone, Serialize)]
+struct DataPayload {
+    id: Id,
+    data: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExitPayload {
+    id: Id,
+    exit_code: u32,
+}
+
+#[command]
+async fn spawn_session<R: Runtime>(
+    window: Window<R>,
+    options: Option<SessionOptions>,
+) -> Result<Id> {
+    let window_ = window.clone();
+    let id = window.pty().spawn_for(
+        Some(window.label().into()),
+        &options.unwrap_or_default(),
+        move |id, event| {
+            let _ = match event {
+                SessionEvent::Data(data) => window_.emit(
+                    "pty://data",
+                    DataPayload {
+                        id,
+                        data: base64::encode(data),
+                    },
+                ),
+                SessionEvent::Exit { exit_code } => {
+                    window_.emit("pty://exit", ExitPayload { id, exit_code })
+                }
+            };
+        },
+    )?;
+
+    // nothing could read the terminal anymore
+    let app = window.app_handle();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            let _ = app.pty().kill(id);
+        }
+    });
+
+    Ok(id)
+}
+
+/// Writes the input, sent as base64 like the output.
+#[command]
+async fn write<R: Runtime>(window: Window<R>, id: Id, data: String) -> Result<()> {
+    let pty = window.pty();
+    pty.check_window(id, window.label())?;
+    pty.write(id, &base64::decode(data)?)
+}
+
+#[command]
+async fn resize<R: Runtime>(window: Window<R>, id: Id, cols: u16, rows: u16) -> Result<()> {
+    let pty = window.pty();
+    pty.check_window(id, window.label())?;
+    pty.resize(id, cols, rows)
+}
+
+#[command]
+async fn kill<R: Runtime>(window: Window<R>, id: Id) -> Result<()> {
+    let pty = window.pty();
+    pty.check_window(id, window.label())?;
+    pty.kill(id)
+}
+
+#[derive(Default)]
+pub struct Builder {
+    shells: Vec<String>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the webview to run the given programs, only the shell of the user can be run by default.
+    pub fn shells<I: IntoIterator<Item = S>, S: Into<String>>(mut self, shells: I) -> Self {
+        self.shells.extend(shells.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let shells = self.shells;
+
+        PluginBuilder::new("pty")
+            .invoke_handler(tauri::generate_handler![spawn_session, write, resize, kill])
+            .setup(move |app| {
+                app.manage(Pty {
+                    app: app.clone(),
+                    shells,
+                    next_id: Default::default(),
+                    sessions: Default::default(),
+                });
+                Ok(())
+            })
+            .on_event(|app, event| {
+                // the shells would outlive the app
+                if let tauri::RunEvent::Exit = event {
+                    for id in app.pty().sessions() {
+                        let _ = app.pty().kill(id);
+                    }
+                }
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
@@ -0,0 +1,81 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export type Schedule =
+  /** A five field cron expression, evaluated in local time. */
+  | { type: "cron"; value: string }
+  /** A fixed interval in seconds. */
+  | { type: "interval"; value: number };
+
+/** What to do with the runs missed while the app wasn't running. */
+export type CatchUp = "skip" | "runOnce";
+
+export interface JobStatus {
+  name: string;
+  schedule: Schedule;
+  catchUp: CatchUp;
+  /** Milliseconds since the Unix epoch. */
+  nextRun: number | null;
+  lastRun: number | null;
+  lastError: string | null;
+  running: boolean;
+}
+
+export interface JobStarted {
+  name: string;
+  scheduledAt: number;
+}
+
+export interface JobFinished {
+  name: string;
+  error: string | null;
+  durationMs: number;
+}
+
+/**
+ * Adds a job replacing any job with the same name.
+ * The job is persisted and listeners registered with `onJob` are called when it is due.
+ */
+export async function addJob(
+  name: string,
+  schedule: Schedule,
+  catchUp: CatchUp = "skip"
+): Promise<void> {
+  await invoke("plugin:scheduler|add_job", { name, schedule, catchUp });
+}
+
+export async function removeJob(name: string): Promise<void> {
+  await invoke("plugin:scheduler|remove_job", { name });
+}
+
+/** Runs a job immediately, without changing its schedule. */
+export async function runNow(name: string): Promise<void> {
+  await invoke("plugin:scheduler|run_now", { name });
+}
+
+export async function jobs(): Promise<JobStatus[]> {
+  return await invoke("plugin:scheduler|jobs");
+}
+
+/** Calls `handler` every time the job named `name` is due. */
+export async function onJob(
+  name: string,
+  handler: (event: JobStarted) => void
+): Promise<UnlistenFn> {
+  return await listen<JobStarted>("scheduler://job-started", (event) => {
+    if (event.payload.name === name) {
+      handler(event.payload);
+    }
+  });
+}
+
+/** Listens to the completion of the jobs run on the Rust side. */
+export async function onJobFinished(
+  handler: (event: JobFinished) => void
+): Promise<UnlistenFn> {
+  return await listen<JobFinished>("scheduler://job-finished", (event) =>
+    handler(event.payload)
+  );
+}
@@ -0,0 +1,171 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! A parser for the classic five field cron expressions, `minute hour day-of-month month day-of-week`.
+//!
+//! Fields accept `*`, values, ranges (`1-5`), lists (`1,15`) and steps (`*/10`, `0-30/5`).
+//! Day of week `0` and `7` are Sunday. The `@yearly`, `@monthly`, `@weekly`, `@daily` and `@hourly`
+//! shorthands are supported as well.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+
+use std::str::FromStr;
+
+use crate::Error;
+
+/// Upper bound of the search for the next occurrence, e.g. `0 0 30 2 *` never matches.
+const SEARCH_LIMIT_DAYS: i64 = 366 * 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether the day of month and day of week fields don't start with `*`,
+    /// when both are restricted the day matches if either does.
+    restricted_days: (bool, bool),
+}
+
+fn parse_field(field: &str, min: u32, max: u32, expr: &str) -> Result<(u64, bool), Error> {
+    let invalid = || Error::InvalidCron(expr.into());
+    let mut bits = 0u64;
+    let mut restricted = false;
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (item, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else {
+            restricted = true;
+            match range.split_once('-') {
+                Some((start, end)) => (
+                    start.parse().map_err(|_| invalid())?,
+                    end.parse().map_err(|_| invalid())?,
+                ),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    // `5/15` means every 15 starting at 5
+                    (value, if item.contains('/') { max } else { value })
+                }
+            }
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+
+        let mut value = start;
+        while value <= end {
+            bits |= 1 << value;
+            value += step;
+        }
+    }
+
+    Ok((bits, restricted))
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+impl FromStr for CronExpr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expr = match s.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            expr => expr,
+        };
+
+        let fields = expr.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(Error::InvalidCron(s.into()));
+        }
+
+        let (minutes, _) = parse_field(fields[0], 0, 59, s)?;
+        let (hours, _) = parse_field(fields[1], 0, 23, s)?;
+        let (days_of_month, dom_restricted) = parse_field(fields[2], 1, 31, s)?;
+        let (months, _) = parse_field(fields[3], 1, 12, s)?;
+        let (mut days_of_week, dow_restricted) = parse_field(fields[4], 0, 7, s)?;
+        if has(days_of_week, 7) {
+            days_of_week |= 1;
+        }
+
+        Ok(Self {
+            minutes,
+            hours,
+            days_of_month,
+            months,
+            days_of_week,
+            restricted_days: (dom_restricted, dow_restricted),
+        })
+    }
+}
+
+impl CronExpr {
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let dom = has(self.days_of_month, date.day());
+        let dow = has(self.days_of_week, date.weekday().num_days_from_sunday());
+        if self.restricted_days == (true, true) {
+            dom || dow
+        } else {
+            dom && dow
+        }
+    }
+
+    /// The first occurrence strictly after `after`, in local time.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(SEARCH_LIMIT_DAYS);
+        let mut time = start;
+
+        while time < limit {
+            let date = time.date();
+            if !has(self.months, date.month()) {
+                let (year, month) = if date.month() == 12 {
+                    (date.year() + 1, 1)
+                } else {
+                    (date.year(), date.month() + 1)
+                };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.day_matches(date) {
+                time = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !has(self.hours, time.hour()) {
+                time = date.and_hms_opt(time.hour(), 0, 0)? + Duration::hours(1);
+                continue;
+            }
+            if !has(self.minutes, time.minute()) {
+                time += Duration::minutes(1);
+                continue;
+            }
+            // the local time may not exist when the clocks go forward
+            if let Some(next) = local(time) {
+                return Some(next);
+            }
+            time += Duration::minutes(1);
+        }
+
+        None
+    }
+}
+
+fn local(time: NaiveDateTime) -> Option<DateTime<Local>> {
+    Local.from_local_datetime(&time).earliest()
+}
@@ -0,0 +1,481 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use chrono::{Local, TimeZone};
+use log::error;
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, read_to_string, rename, write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread::{sleep, spawn},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+mod cron;
+
+pub use cron::CronExpr;
+
+type Result<T> = std::result::Result<T, Error>;
+type JobHandler<R> = dyn Fn(&AppHandle<R>) -> std::result::Result<(), String> + Send + Sync;
+
+pub const STATE_FILENAME: &str = ".scheduler";
+
+const TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("invalid cron expression `{0}`")]
+    InvalidCron(String),
+    #[error("the interval must be at least one second")]
+    InvalidInterval,
+    #[error("job `{0}` not found")]
+    JobNotFound(String),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "scheduler";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Json(..) => "json",
+            Self::InvalidCron(..) => "invalid_cron",
+            Self::InvalidInterval => "invalid_interval",
+            Self::JobNotFound(..) => "job_not_found",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// When a job runs.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum Schedule {
+    /// A five field cron expression, evaluated in local time.
+    Cron(String),
+    /// A fixed interval in seconds.
+    Interval(u64),
+}
+
+impl Schedule {
+    fn validate(&self) -> Result<()> {
+        match self {
+            Self::Cron(expr) => expr.parse::<CronExpr>().map(|_| ()),
+            Self::Interval(0) => Err(Error::InvalidInterval),
+            Self::Interval(_) => Ok(()),
+        }
+    }
+
+    /// The first run strictly after `after`, in milliseconds since the Unix epoch.
+    fn next_after(&self, after: u64) -> Option<u64> {
+        match self {
+            Self::Cron(expr) => {
+                let after = Local.timestamp_millis_opt(after as i64).single()?;
+                let next = expr.parse::<CronExpr>().ok()?.next_after(after)?;
+                Some(next.timestamp_millis() as u64)
+            }
+            Self::Interval(seconds) => Some(after + seconds * 1000),
+        }
+    }
+}
+
+/// What to do with the runs missed while the app wasn't running.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CatchUp {
+    /// Wait for the next scheduled run.
+    #[default]
+    Skip,
+    /// Run once as soon as the job is registered, however many runs were missed.
+    RunOnce,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    pub name: String,
+    pub schedule: Schedule,
+    pub catch_up: CatchUp,
+    pub next_run: Option<u64>,
+    pub last_run: Option<u64>,
+    pub last_error: Option<String>,
+    pub running: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobStartedPayload {
+    name: String,
+    scheduled_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobFinishedPayload {
+    name: String,
+    error: Option<String>,
+    duration_ms: u64,
+}
+
+/// The persisted part of a job.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobState {
+    schedule: Schedule,
+    catch_up: CatchUp,
+    last_run: Option<u64>,
+    last_error: Option<String>,
+    /// Jobs run by the frontend are restored on startup, Rust jobs are registered again by the app.
+    frontend: bool,
+}
+
+struct Job<R: Runtime> {
+    state: JobState,
+    handler: Option<Arc<JobHandler<R>>>,
+    next_run: Option<u64>,
+    running: bool,
+}
+
+pub struct Scheduler<R: Runtime> {
+    app: AppHandle<R>,
+    path: Option<PathBuf>,
+    jobs: Arc<Mutex<HashMap<String, Job<R>>>>,
+    /// States of the Rust jobs the app didn't register again yet, kept for their catch-up.
+    stale: Mutex<HashMap<String, JobState>>,
+}
+
+impl<R: Runtime> Scheduler<R> {
+    /// Adds a job running `handler` on the given schedule, replacing any job with the same name.
+    ///
+    /// Errors returned by the handler are reported in the job status and the `scheduler://job-finished` event.
+    pub fn add_job<F>(
+        &self,
+        name: &str,
+        schedule: Schedule,
+        catch_up: CatchUp,
+        handler: F,
+    ) -> Result<()>
+    where
+        F: Fn(&AppHandle<R>) -> std::result::Result<(), String> + Send + Sync + 'static,
+    {
+        self.insert(name, schedule, catch_up, Some(Arc::new(handler)))
+    }
+
+    /// Adds a job run by the frontend, which is notified with the `scheduler://job-started` event.
+    ///
+    /// These jobs are restored when the app starts.
+    pub fn add_frontend_job(
+        &self,
+        name: &str,
+        schedule: Schedule,
+        catch_up: CatchUp,
+    ) -> Result<()> {
+        self.insert(name, schedule, catch_up, None)
+    }
+
+    pub fn remove_job(&self, name: &str) -> Result<()> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .remove(name)
+            .ok_or_else(|| Error::JobNotFound(name.into()))?;
+        self.save()
+    }
+
+    /// Runs a job immediately, without changing its schedule.
+    pub fn run_now(&self, name: &str) -> Result<()> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs
+            .get_mut(name)
+            .ok_or_else(|| Error::JobNotFound(name.into()))?;
+        start_job(&self.app, &self.jobs, name, job, now());
+        drop(jobs);
+        self.save()
+    }
+
+    pub fn jobs(&self) -> Vec<JobStatus> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, job)| JobStatus {
+                name: name.clone(),
+                schedule: job.state.schedule.clone(),
+                catch_up: job.state.catch_up,
+                next_run: job.next_run,
+                last_run: job.state.last_run,
+                last_error: job.state.last_error.clone(),
+                running: job.running,
+            })
+            .collect()
+    }
+
+    fn insert(
+        &self,
+        name: &str,
+        schedule: Schedule,
+        catch_up: CatchUp,
+        handler: Option<Arc<JobHandler<R>>>,
+    ) -> Result<()> {
+        schedule.validate()?;
+
+        let previous = self.stale.lock().unwrap().remove(name).or_else(|| {
+            self.jobs
+                .lock()
+                .unwrap()
+                .get(name)
+                .map(|job| job.state.clone())
+        });
+        let state = JobState {
+            frontend: handler.is_none(),
+            last_run: previous
+                .as_ref()
+                .filter(|p| p.schedule == schedule)
+                .and_then(|p| p.last_run),
+            last_error: None,
+            schedule,
+            catch_up,
+        };
+
+        self.jobs.lock().unwrap().insert(
+            name.into(),
+            Job {
+                next_run: first_run(&state, now()),
+                state,
+                handler,
+                running: false,
+            },
+        );
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut states = self.stale.lock().unwrap().clone();
+        states.extend(
+            self.jobs
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(name, job)| (name.clone(), job.state.clone())),
+        );
+
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("tmp");
+        write(&tmp, serde_json::to_vec(&states)?)?;
+        rename(tmp, path)?;
+        Ok(())
+    }
+
+    fn tick(&self) {
+        let now = now();
+        let mut started = false;
+        let mut jobs = self.jobs.lock().unwrap();
+        for (name, job) in jobs.iter_mut() {
+            if job.next_run.is_some_and(|next| next <= now) {
+                if !job.running {
+                    start_job(&self.app, &self.jobs, name, job, now);
+                    started = true;
+                }
+                job.next_run = job.state.schedule.next_after(now);
+            }
+        }
+        drop(jobs);
+
+        if started {
+            if let Err(e) = self.save() {
+                error!("failed to save the scheduler state: {}", e);
+            }
+        }
+    }
+}
+
+/// The next run of a newly registered job, applying its catch-up policy.
+fn first_run(state: &JobState, now: u64) -> Option<u64> {
+    match state
+        .last_run
+        .and_then(|last| state.schedule.next_after(last))
+    {
+        Some(missed) if missed <= now && state.catch_up == CatchUp::RunOnce => Some(now),
+        Some(next) if next > now => Some(next),
+        _ => state.schedule.next_after(now),
+    }
+}
+
+fn start_job<R: Runtime>(
+    app: &AppHandle<R>,
+    jobs: &Arc<Mutex<HashMap<String, Job<R>>>>,
+    name: &str,
+    job: &mut Job<R>,
+    scheduled_at: u64,
+) {
+    job.state.last_run.replace(scheduled_at);
+    let _ = app.emit_all(
+        "scheduler://job-started",
+        JobStartedPayload {
+            name: name.into(),
+            scheduled_at,
+        },
+    );
+
+    let Some(handler) = job.handler.clone() else {
+        return;
+    };
+    job.running = true;
+
+    let app = app.clone();
+    let jobs = jobs.clone();
+    let name = name.to_string();
+    spawn(move || {
+        let start = Instant::now();
+        let result = handler(&app);
+
+        if let Some(job) = jobs.lock().unwrap().get_mut(&name) {
+            job.running = false;
+            job.state.last_error = result.as_ref().err().cloned();
+        }
+        if let Err(e) = app.state::<Scheduler<R>>().save() {
+            error!("failed to save the scheduler state: {}", e);
+        }
+        let _ = app.emit_all(
+            "scheduler://job-finished",
+            JobFinishedPayload {
+                name,
+                error: result.err(),
+                duration_ms: start.elapsed().as_millis() as u64,
+            },
+        );
+    });
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn scheduler(&self) -> State<'_, Scheduler<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn scheduler(&self) -> State<'_, Scheduler<R>> {
+        self.state::<Scheduler<R>>()
+    }
+}
+
+#[command]
+async fn add_job<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    schedule: Schedule,
+    catch_up: Option<CatchUp>,
+) -> Result<()> {
+    app.scheduler()
+        .add_frontend_job(&name, schedule, catch_up.unwrap_or_default())
+}
+
+#[command]
+async fn remove_job<R: Runtime>(app: AppHandle<R>, name: String) -> Result<()> {
+    app.scheduler().remove_job(&name)
+}
+
+#[command]
+async fn run_now<R: Runtime>(app: AppHandle<R>, name: String) -> Result<()> {
+    app.scheduler().run_now(&name)
+}
+
+#[command]
+async fn jobs<R: Runtime>(app: AppHandle<R>) -> Result<Vec<JobStatus>> {
+    Ok(app.scheduler().jobs())
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    PluginBuilder::new("scheduler")
+        .invoke_handler(tauri::generate_handler![add_job, remove_job, run_now, jobs])
+        .setup(|app| {
+            let path = app
+                .path_resolver()
+                .app_data_dir()
+                .map(|dir| dir.join(STATE_FILENAME));
+            let mut states: HashMap<String, JobState> = match &path {
+                Some(path) if path.exists() => serde_json::from_str(&read_to_string(path)?)?,
+                _ => HashMap::new(),
+            };
+
+            let now = now();
+            let jobs = states
+                .iter()
+                .filter(|(_, state)| state.frontend)
+                .map(|(name, state)| {
+                    (
+                        name.clone(),
+                        Job {
+                            next_run: first_run(state, now),
+                            state: state.clone(),
+                            handler: None,
+                            running: false,
+                        },
+                    )
+                })
+                .collect::<HashMap<_, _>>();
+            states.retain(|_, state| !state.frontend);
+
+            app.manage(Scheduler {
+                app: app.clone(),
+                path,
+                jobs: Arc::new(Mutex::new(jobs)),
+                stale: Mutex::new(states),
+            });
+
+            let app = app.clone();
+            spawn(move || loop {
+                sleep(TICK);
+                app.scheduler().tick();
+            });
+
+            Ok(())
+        })
+        .build()
+}
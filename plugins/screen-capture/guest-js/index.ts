@@ -0,0 +1,168 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { UnlistenFn } from "@tauri-apps/api/event";
+import { appWindow } from "@tauri-apps/api/window";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface Display {
+  id: number;
+  /** The position and size of the display in logical pixels. */
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  scaleFactor: number;
+  isPrimary: boolean;
+}
+
+/** What to capture, coordinates are in logical pixels. */
+export type Target =
+  | { type: "display"; id?: number }
+  | { type: "window"; label: string }
+  | { type: "region"; x: number; y: number; width: number; height: number };
+
+export interface CaptureOptions {
+  /** Defaults to `png`. */
+  format?: "png" | "jpeg";
+  /** The quality of JPEG images from 1 to 100, defaults to 90. */
+  quality?: number;
+}
+
+export interface RecordingOptions {
+  /** Frames per second, defaults to 15. */
+  frameRate?: number;
+  /** Bits per second, defaults to 4 Mbps. */
+  bitrate?: number;
+}
+
+export interface RecordingProgress {
+  /** In milliseconds. */
+  duration: number;
+  frames: number;
+  /** The size of the encoded video so far, in bytes. */
+  size: number;
+}
+
+export interface RecordingResult {
+  path: string;
+  /** In milliseconds. */
+  duration: number;
+  frames: number;
+}
+
+/**
+ * Whether the app may capture the screen, only macOS asks the user.
+ */
+async function hasPermission(): Promise<boolean> {
+  return await invoke("plugin:screen-capture|has_permission");
+}
+
+/**
+ * Asks the user to allow the app to capture the screen.
+ *
+ * On macOS the user is sent to the system settings the first time, and the app must be restarted once allowed.
+ */
+async function requestPermission(): Promise<boolean> {
+  return await invoke("plugin:screen-capture|request_permission");
+}
+
+async function displays(): Promise<Display[]> {
+  return await invoke("plugin:screen-capture|displays");
+}
+
+/**
+ * Captures the target as an encoded image.
+ */
+async function capture(
+  target: Target,
+  options?: CaptureOptions
+): Promise<Uint8Array> {
+  return Uint8Array.from(
+    await invoke<number[]>("plugin:screen-capture|capture", {
+      target,
+      options,
+    })
+  );
+}
+
+/**
+ * Captures the target to an image file, the path must be allowed by the `fs` scope.
+ */
+async function captureToFile(
+  target: Target,
+  path: string,
+  options?: CaptureOptions
+): Promise<void> {
+  return await invoke("plugin:screen-capture|capture_to_file", {
+    target,
+    path,
+    options,
+  });
+}
+
+class Recording {
+  id: number;
+  private unlisten?: UnlistenFn;
+
+  constructor(id: number, unlisten?: UnlistenFn) {
+    this.id = id;
+    this.unlisten = unlisten;
+  }
+
+  /**
+   * Stops the recording and finishes writing the file.
+   */
+  async stop(): Promise<RecordingResult> {
+    try {
+      return await invoke("plugin:screen-capture|stop_recording", {
+        id: this.id,
+      });
+    } finally {
+      this.unlisten?.();
+    }
+  }
+}
+
+/**
+ * Records the target to an H.264 MP4 file, the path must be allowed by the `fs` scope.
+ *
+ * `onProgress` is called about every second until the recording is stopped.
+ */
+async function startRecording(
+  target: Target,
+  path: string,
+  options?: RecordingOptions,
+  onProgress?: (progress: RecordingProgress) => void
+): Promise<Recording> {
+  let id: number | undefined;
+  const unlisten = onProgress
+    ? await appWindow.listen<RecordingProgress & { id: number }>(
+        "screen-capture://progress",
+        ({ payload }) => {
+          if (payload.id === id) onProgress(payload);
+        }
+      )
+    : undefined;
+
+  try {
+    id = await invoke<number>("plugin:screen-capture|start_recording", {
+      target,
+      path,
+      options,
+    });
+  } catch (e) {
+    unlisten?.();
+    throw e;
+  }
+  return new Recording(id, unlisten);
+}
+
+export {
+  Recording,
+  hasPermission,
+  requestPermission,
+  displays,
+  capture,
+  captureToFile,
+  startRecording,
+};
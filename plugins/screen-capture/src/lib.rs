@@ -0,0 +1,483 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use screenshots::{
+    image::{DynamicImage, ImageOutputFormat, RgbaImage},
+    Screen,
+};
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State, Window,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Cursor, Seek, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+};
+
+#[cfg(target_os = "macos")]
+mod macos;
+mod recorder;
+
+pub use recorder::{Recording, RecordingOptions, RecordingProgress};
+
+type Id = u32;
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error(transparent)]
+    Image(#[from] screenshots::image::ImageError),
+    #[error(transparent)]
+    Mp4(#[from] mp4::Error),
+    #[error(transparent)]
+    Encoder(#[from] openh264::Error),
+    #[error("failed to capture the screen: {0}")]
+    Capture(String),
+    #[error("the app is not allowed to record the screen")]
+    PermissionDenied,
+    #[error("display not found for the given id: {0}")]
+    DisplayNotFound(u32),
+    #[error("window not found: {0}")]
+    WindowNotFound(String),
+    #[error("path not allowed on the configured scope: {0}")]
+    PathForbidden(PathBuf),
+    #[error("recording not found for the given id: {0}")]
+    RecordingNotFound(Id),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "screen-capture";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Tauri(..) => "tauri",
+            Self::Image(..) => "image",
+            Self::Mp4(..) => "mp4",
+            Self::Encoder(..) => "encoder",
+            Self::Capture(..) => "capture",
+            Self::PermissionDenied => "permission_denied",
+            Self::DisplayNotFound(..) => "display_not_found",
+            Self::WindowNotFound(..) => "window_not_found",
+            Self::PathForbidden(..) => "path_forbidden",
+            Self::RecordingNotFound(..) => "recording_not_found",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+fn capture_error(error: anyhow::Error) -> Error {
+    Error::Capture(error.to_string())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Display {
+    pub id: u32,
+    /// The position and size of the display in logical pixels.
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+impl From<&Screen> for Display {
+    fn from(screen: &Screen) -> Self {
+        let info = &screen.display_info;
+        Self {
+            id: info.id,
+            x: info.x,
+            y: info.y,
+            width: info.width,
+            height: info.height,
+            scale_factor: info.scale_factor,
+            is_primary: info.is_primary,
+        }
+    }
+}
+
+/// What to capture, coordinates are in logical pixels.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Target {
+    /// A whole display, the primary one when `id` is `None`.
+    Display { id: Option<u32> },
+    /// The area covered by a window of the app, including whatever is drawn over it.
+    Window { label: String },
+    /// An area of the desktop, which must fit in a single display.
+    Region {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    },
+}
+
+/// A rectangle of a screen, relative to its origin.
+struct Area {
+    screen: Screen,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl Area {
+    fn capture(&self) -> Result<RgbaImage> {
+        self.screen
+            .capture_area(self.x, self.y, self.width, self.height)
+            .map_err(capture_error)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureOptions {
+    #[serde(default)]
+    pub format: ImageFormat,
+    /// The quality of JPEG images from 1 to 100, defaults to 90.
+    pub quality: Option<u8>,
+}
+
+fn encode<W: Write + Seek>(
+    image: RgbaImage,
+    writer: &mut W,
+    options: &CaptureOptions,
+) -> Result<()> {
+    let image = DynamicImage::ImageRgba8(image);
+    match options.format {
+        ImageFormat::Png => image.write_to(writer, ImageOutputFormat::Png)?,
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()).write_to(
+            writer,
+            ImageOutputFormat::Jpeg(options.quality.unwrap_or(90).clamp(1, 100)),
+        )?,
+    }
+    Ok(())
+}
+
+struct ActiveRecording {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Result<Recording>>,
+}
+
+pub struct ScreenCapture<R: Runtime> {
+    app: AppHandle<R>,
+    next_id: AtomicU32,
+    recordings: Mutex<HashMap<Id, ActiveRecording>>,
+}
+
+impl<R: Runtime> ScreenCapture<R> {
+    /// Whether the app may capture the screen, only macOS asks the user.
+    pub fn has_permission(&self) -> bool {
+        #[cfg(target_os = "macos")]
+        return macos::has_permission();
+        #[cfg(not(target_os = "macos"))]
+        true
+    }
+
+    /// Asks the user to allow the app to capture the screen.
+    ///
+    /// On macOS the user is sent to the system settings the first time, and the app must be restarted once allowed.
+    pub fn request_permission(&self) -> bool {
+        #[cfg(target_os = "macos")]
+        return macos::request_permission();
+        #[cfg(not(target_os = "macos"))]
+        true
+    }
+
+    pub fn displays(&self) -> Result<Vec<Display>> {
+        Ok(Screen::all()
+            .map_err(capture_error)?
+            .iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    fn area(&self, target: &Target) -> Result<Area> {
+        if !self.has_permission() {
+            return Err(Error::PermissionDenied);
+        }
+
+        let region = |x: i32, y: i32, width: u32, height: u32| -> Result<Area> {
+            let screen = Screen::from_point(x, y).map_err(capture_error)?;
+            Ok(Area {
+                x: x - screen.display_info.x,
+                y: y - screen.display_info.y,
+                screen,
+                width,
+                height,
+            })
+        };
+
+        match target {
+            Target::Display { id } => {
+                let screens = Screen::all().map_err(capture_error)?;
+                let screen = match id {
+                    Some(id) => screens.iter().find(|s| s.display_info.id == *id).copied(),
+                    None => screens
+                        .iter()
+                        .find(|s| s.display_info.is_primary)
+                        .or_else(|| screens.first())
+                        .copied(),
+                }
+                .ok_or(Error::DisplayNotFound(id.unwrap_or_default()))?;
+                Ok(Area {
+                    x: 0,
+                    y: 0,
+                    width: screen.display_info.width,
+                    height: screen.display_info.height,
+                    screen,
+                })
+            }
+            Target::Window { label } => {
+                let window = self
+                    .app
+                    .get_window(label)
+                    .ok_or_else(|| Error::WindowNotFound(label.clone()))?;
+                let scale_factor = window.scale_factor()?;
+                let position = window.outer_position()?.to_logical::<i32>(scale_factor);
+                let size = window.outer_size()?.to_logical::<u32>(scale_factor);
+                region(position.x, position.y, size.width, size.height)
+            }
+            Target::Region {
+                x,
+                y,
+                width,
+                height,
+            } => region(*x, *y, *width, *height),
+        }
+    }
+
+    /// Captures the target as an encoded image.
+    pub fn capture(&self, target: &Target, options: &CaptureOptions) -> Result<Vec<u8>> {
+        let image = self.area(target)?.capture()?;
+        let mut buffer = Cursor::new(Vec::new());
+        encode(image, &mut buffer, options)?;
+        Ok(buffer.into_inner())
+    }
+
+    /// Captures the target to an image file.
+    pub fn capture_to_file(
+        &self,
+        target: &Target,
+        path: &Path,
+        options: &CaptureOptions,
+    ) -> Result<()> {
+        let image = self.area(target)?.capture()?;
+        let mut file = BufWriter::new(File::create(path)?);
+        encode(image, &mut file, options)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Records the target to an MP4 file until [`Self::stop_recording`] is called.
+    ///
+    /// `on_progress` is called from the recording thread about every second.
+    pub fn start_recording<F: FnMut(Id, RecordingProgress) + Send + 'static>(
+        &self,
+        target: &Target,
+        path: &Path,
+        options: &RecordingOptions,
+        mut on_progress: F,
+    ) -> Result<Id> {
+        let area = self.area(target)?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (stop, handle) =
+            recorder::start(area, path.to_path_buf(), options.clone(), move |progress| {
+                on_progress(id, progress)
+            })?;
+        self.recordings
+            .lock()
+            .unwrap()
+            .insert(id, ActiveRecording { stop, handle });
+        Ok(id)
+    }
+
+    /// Stops the recording and finishes writing the file.
+    pub fn stop_recording(&self, id: Id) -> Result<Recording> {
+        let recording = self
+            .recordings
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or(Error::RecordingNotFound(id))?;
+        recording.stop.store(true, Ordering::Relaxed);
+        recording
+            .handle
+            .join()
+            .map_err(|_| Error::Capture("the recording thread panicked".into()))?
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn screen_capture(&self) -> State<'_, ScreenCapture<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn screen_capture(&self) -> State<'_, ScreenCapture<R>> {
+        self.state::<ScreenCapture<R>>()
+    }
+}
+
+fn check_scope<R: Runtime>(window: &Window<R>, path: &Path) -> Result<()> {
+    if window.fs_scope().is_allowed(path) {
+        Ok(())
+    } else {
+        Err(Error::PathForbidden(path.to_path_buf()))
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ProgressPayload {
+    id: Id,
+    #[serde(flatten)]
+    progress: RecordingProgress,
+}
+
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(io::Error::other)?
+}
+
+#[command]
+async fn has_permission<R: Runtime>(app: AppHandle<R>) -> Result<bool> {
+    Ok(app.screen_capture().has_permission())
+}
+
+#[command]
+async fn request_permission<R: Runtime>(app: AppHandle<R>) -> Result<bool> {
+    Ok(app.screen_capture().request_permission())
+}
+
+#[command]
+async fn displays<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Display>> {
+    app.screen_capture().displays()
+}
+
+#[command]
+async fn capture<R: Runtime>(
+    app: AppHandle<R>,
+    target: Target,
+    options: Option<CaptureOptions>,
+) -> Result<Vec<u8>> {
+    blocking(move || {
+        app.screen_capture()
+            .capture(&target, &options.unwrap_or_default())
+    })
+    .await
+}
+
+#[command]
+async fn capture_to_file<R: Runtime>(
+    window: Window<R>,
+    target: Target,
+    path: PathBuf,
+    options: Option<CaptureOptions>,
+) -> Result<()> {
+    check_scope(&window, &path)?;
+    blocking(move || {
+        window
+            .screen_capture()
+            .capture_to_file(&target, &path, &options.unwrap_or_default())
+    })
+    .await
+}
+
+#[command]
+async fn start_recording<R: Runtime>(
+    window: Window<R>,
+    target: Target,
+    path: PathBuf,
+    options: Option<RecordingOptions>,
+) -> Result<Id> {
+    check_scope(&window, &path)?;
+    let window_ = window.clone();
+    window.screen_capture().start_recording(
+        &target,
+        &path,
+        &options.unwrap_or_default(),
+        move |id, progress| {
+            let _ = window_.emit(
+                "screen-capture://progress",
+                ProgressPayload { id, progress },
+            );
+        },
+    )
+}
+
+#[command]
+async fn stop_recording<R: Runtime>(app: AppHandle<R>, id: Id) -> Result<Recording> {
+    blocking(move || app.screen_capture().stop_recording(id)).await
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    PluginBuilder::new("screen-capture")
+        .invoke_handler(tauri::generate_handler![
+            has_permission,
+            request_permission,
+            displays,
+            capture,
+            capture_to_file,
+            start_recording,
+            stop_recording
+        ])
+        .setup(|app| {
+            app.manage(ScreenCapture {
+                app: app.clone(),
+                next_id: Default::default(),
+                recordings: Default::default(),
+            });
+            Ok(())
+        })
+        .build()
+}
@@ -0,0 +1,19 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The Screen Recording permission, available since macOS 10.15.
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGPreflightScreenCaptureAccess() -> bool;
+    fn CGRequestScreenCaptureAccess() -> bool;
+}
+
+pub(crate) fn has_permission() -> bool {
+    unsafe { CGPreflightScreenCaptureAccess() }
+}
+
+pub(crate) fn request_permission() -> bool {
+    unsafe { CGRequestScreenCaptureAccess() }
+}
@@ -0,0 +1,282 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Encodes the captured frames to H.264 and writes them to an MP4 file.
+
+use mp4::{AvcConfig, Bytes, MediaConfig, Mp4Config, Mp4Sample, Mp4Writer, TrackConfig, TrackType};
+use openh264::{
+    encoder::{EncodedBitStream, Encoder, EncoderConfig, FrameType},
+    formats::YUVBuffer,
+};
+use screenshots::image::{imageops, DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{sleep, spawn, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{Area, Result};
+
+/// The samples are timed in milliseconds.
+const TIMESCALE: u32 = 1000;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+const NAL_SPS: u8 = 7;
+const NAL_PPS: u8 = 8;
+
+fn default_frame_rate() -> u32 {
+    15
+}
+
+fn default_bitrate() -> u32 {
+    4_000_000
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingOptions {
+    /// Frames per second, defaults to 15. Capturing is slow on large displays, high rates may not be reached.
+    #[serde(default = "default_frame_rate")]
+    pub frame_rate: u32,
+    /// Bits per second, defaults to 4 Mbps.
+    #[serde(default = "default_bitrate")]
+    pub bitrate: u32,
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        Self {
+            frame_rate: default_frame_rate(),
+            bitrate: default_bitrate(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingProgress {
+    /// In milliseconds.
+    pub duration: u64,
+    pub frames: u64,
+    /// The size of the encoded video so far, in bytes.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recording {
+    pub path: PathBuf,
+    /// In milliseconds.
+    pub duration: u64,
+    pub frames: u64,
+}
+
+/// Removes the start code of the NAL units written by the encoder.
+fn strip_start_code(nal: &[u8]) -> &[u8] {
+    nal.strip_prefix(&[0, 0, 0, 1])
+        .or_else(|| nal.strip_prefix(&[0, 0, 1]))
+        .unwrap_or(nal)
+}
+
+/// Makes a frame fit the size of the video.
+///
+/// The odd row and column are cropped, areas that changed size while recording are scaled, e.g. a resized window.
+fn fit(image: RgbaImage, width: u32, height: u32) -> RgbaImage {
+    if image.width() == width && image.height() == height {
+        image
+    } else if image.width() & !1 == width && image.height() & !1 == height {
+        imageops::crop_imm(&image, 0, 0, width, height).to_image()
+    } else {
+        imageops::resize(&image, width, height, imageops::FilterType::Triangle)
+    }
+}
+
+/// A frame in the length prefixed format of MP4, without its parameter sets.
+struct Frame {
+    data: Vec<u8>,
+    is_sync: bool,
+}
+
+struct Video {
+    writer: Option<Mp4Writer<BufWriter<File>>>,
+    file: Option<File>,
+    width: u16,
+    height: u16,
+    size: u64,
+}
+
+impl Video {
+    /// Splits the parameter sets from the frame, the track is only added once they are known.
+    fn frame(&mut self, bitstream: &EncodedBitStream<'_>) -> Result<Frame> {
+        let (mut sps, mut pps) = (None, None);
+        let mut data = Vec::new();
+        for layer in (0..bitstream.num_layers()).filter_map(|i| bitstream.layer(i)) {
+            for nal in (0..layer.nal_count()).filter_map(|i| layer.nal_unit(i)) {
+                let nal = strip_start_code(nal);
+                match nal.first().map(|header| header & 0x1f) {
+                    Some(NAL_SPS) => sps = Some(nal.to_vec()),
+                    Some(NAL_PPS) => pps = Some(nal.to_vec()),
+                    Some(_) => {
+                        data.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+                        data.extend_from_slice(nal);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        if let (Some(file), Some(seq_param_set), Some(pic_param_set)) = (self.file.take(), sps, pps)
+        {
+            let mut writer = Mp4Writer::write_start(
+                BufWriter::new(file),
+                &Mp4Config {
+                    major_brand: "isom".parse().unwrap(),
+                    minor_version: 512,
+                    compatible_brands: ["isom", "iso2", "avc1", "mp41"]
+                        .iter()
+                        .map(|brand| brand.parse().unwrap())
+                        .collect(),
+                    timescale: TIMESCALE,
+                },
+            )?;
+            writer.add_track(&TrackConfig {
+                track_type: TrackType::Video,
+                timescale: TIMESCALE,
+                language: "und".into(),
+                media_conf: MediaConfig::AvcConfig(AvcConfig {
+                    width: self.width,
+                    height: self.height,
+                    seq_param_set,
+                    pic_param_set,
+                }),
+            })?;
+            self.writer.replace(writer);
+        }
+
+        Ok(Frame {
+            data,
+            is_sync: matches!(bitstream.frame_type(), FrameType::IDR | FrameType::I),
+        })
+    }
+
+    fn write(&mut self, frame: Frame, start_time: u64, duration: u64) -> Result<()> {
+        if let Some(writer) = &mut self.writer {
+            self.size += frame.data.len() as u64;
+            writer.write_sample(
+                1,
+                &Mp4Sample {
+                    start_time,
+                    duration: duration.max(1) as u32,
+                    rendering_offset: 0,
+                    is_sync: frame.is_sync,
+                    bytes: Bytes::from(frame.data),
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn record(
+    area: Area,
+    path: PathBuf,
+    options: RecordingOptions,
+    stop: Arc<AtomicBool>,
+    mut on_progress: impl FnMut(RecordingProgress),
+) -> Result<Recording> {
+    let first = area.capture()?;
+    // the chroma planes are subsampled, the frames must have an even size
+    let width = first.width() & !1;
+    let height = first.height() & !1;
+
+    let frame_rate = options.frame_rate.max(1);
+    let mut encoder = Encoder::with_config(
+        EncoderConfig::new(width, height)
+            .set_bitrate_bps(options.bitrate)
+            .max_frame_rate(frame_rate as f32),
+    )?;
+    let mut video = Video {
+        writer: None,
+        file: Some(File::create(&path)?),
+        width: width as u16,
+        height: height as u16,
+        size: 0,
+    };
+
+    let interval = Duration::from_secs(1) / frame_rate;
+    let started = Instant::now();
+    let mut last_progress = started;
+    let mut pending: Option<(Frame, u64)> = None;
+    let mut frames = 0;
+    let mut image = Some(first);
+
+    while !stop.load(Ordering::Relaxed) {
+        let frame_started = Instant::now();
+        let timestamp = started.elapsed().as_millis() as u64;
+        let captured = match image.take() {
+            Some(image) => image,
+            None => area.capture()?,
+        };
+        let rgb = fit(captured, width, height);
+        let rgb = DynamicImage::ImageRgba8(rgb).into_rgb8();
+        let yuv = YUVBuffer::with_rgb(width as usize, height as usize, rgb.as_raw());
+        let bitstream = encoder.encode(&yuv)?;
+
+        // skipped frames extend the duration of the previous one
+        if bitstream.frame_type() != FrameType::Skip {
+            let frame = video.frame(&bitstream)?;
+            if let Some((previous, start)) = pending.replace((frame, timestamp)) {
+                video.write(previous, start, timestamp - start)?;
+            }
+            frames += 1;
+        }
+
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            on_progress(RecordingProgress {
+                duration: timestamp,
+                frames,
+                size: video.size,
+            });
+        }
+
+        if let Some(remaining) = interval.checked_sub(frame_started.elapsed()) {
+            sleep(remaining);
+        }
+    }
+
+    let duration = started.elapsed().as_millis() as u64;
+    if let Some((frame, start)) = pending {
+        video.write(frame, start, duration - start)?;
+    }
+    if let Some(mut writer) = video.writer {
+        writer.write_end()?;
+    }
+
+    Ok(Recording {
+        path,
+        duration,
+        frames,
+    })
+}
+
+pub(crate) fn start(
+    area: Area,
+    path: PathBuf,
+    options: RecordingOptions,
+    on_progress: impl FnMut(RecordingProgress) + Send + 'static,
+) -> Result<(Arc<AtomicBool>, JoinHandle<Result<Recording>>)> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_ = stop.clone();
+    let handle = spawn(move || record(area, path, options, stop_, on_progress));
+    Ok((stop, handle))
+}
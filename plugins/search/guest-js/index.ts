@@ -0,0 +1,116 @@
+import { invoke } from "@tauri-apps/api/tauri";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+/** The fields of the documents the index uses, the documents are stored whole. */
+export interface Schema {
+  /** The fields searched by the queries. */
+  fields: string[];
+  /** The fields the results are counted and filtered by, e.g. a category or tags. */
+  facets?: string[];
+}
+
+/** A JSON object with a string or number `id`. */
+export type Document = Record<string, unknown> & { id: string | number };
+
+export interface SearchOptions {
+  /** Defaults to 20. */
+  limit?: number;
+  offset?: number;
+  /** Keeps the documents with one of the values for every facet. */
+  filters?: Record<string, string[]>;
+  /** The facets whose values are counted among the matching documents. */
+  facets?: string[];
+  /** Surrounds the matching terms of the highlights, defaults to `<mark>` and `</mark>`. */
+  highlight?: { before: string; after: string };
+}
+
+export interface Hit<T = Document> {
+  id: string;
+  /** Higher for better matches. */
+  score: number;
+  document: T;
+  /** The searched fields with the matching terms highlighted, as escaped HTML. */
+  highlights: Record<string, string>;
+}
+
+export interface FacetCount {
+  value: string;
+  count: number;
+}
+
+export interface SearchResults<T = Document> {
+  /** The number of matching documents, the hits are a page of them. */
+  total: number;
+  hits: Array<Hit<T>>;
+  /** The most frequent values first. */
+  facets: Record<string, FacetCount[]>;
+}
+
+export interface IndexInfo {
+  name: string;
+  schema: Required<Schema>;
+  documents: number;
+}
+
+/**
+ * A full-text index stored in the app data directory.
+ */
+export class Index {
+  name: string;
+
+  constructor(name: string) {
+    this.name = name;
+  }
+
+  /**
+   * Creates the index, its name can have letters, digits and underscores.
+   */
+  static async create(name: string, schema: Schema): Promise<Index> {
+    await invoke("plugin:search|create_index", { name, schema });
+    return new Index(name);
+  }
+
+  /** Adds the documents, replacing the ones with the same `id`. */
+  async add(documents: Document[]): Promise<void> {
+    return await invoke("plugin:search|add_documents", {
+      name: this.name,
+      documents,
+    });
+  }
+
+  async delete(ids: Array<string | number>): Promise<void> {
+    return await invoke("plugin:search|delete_documents", {
+      name: this.name,
+      ids: ids.map(String),
+    });
+  }
+
+  /** Deletes every document, keeping the schema. */
+  async clear(): Promise<void> {
+    return await invoke("plugin:search|clear", { name: this.name });
+  }
+
+  /**
+   * Ranks the documents matching the query, an empty query matches every document.
+   */
+  async search<T = Document>(
+    query: string,
+    options?: SearchOptions
+  ): Promise<SearchResults<T>> {
+    return await invoke("plugin:search|search", {
+      name: this.name,
+      query,
+      options,
+    });
+  }
+
+  /** Deletes the index and its documents. */
+  async drop(): Promise<void> {
+    return await invoke("plugin:search|delete_index", { name: this.name });
+  }
+}
+
+export async function indexes(): Promise<IndexInfo[]> {
+  return await invoke("plugin:search|indexes");
+}
@@ -0,0 +1,330 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! A tantivy index with the documents stored whole as JSON next to their searched fields and facets.
+
+use serde_json::Value as JsonValue;
+use tantivy::{
+    collector::{Count, FacetCollector, MultiCollector, TopDocs},
+    query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, TermSetQuery},
+    schema::{Facet, FacetOptions, Field, FieldType, Schema as IndexSchema, STORED, STRING, TEXT},
+    Document, IndexReader, IndexWriter, Term,
+};
+
+use std::{collections::HashMap, path::Path, sync::Mutex};
+
+use crate::{Error, FacetCount, Highlight, Hit, Result, Schema, SearchOptions, SearchResults};
+
+const ID: &str = "id";
+const SOURCE: &str = "_source";
+const MEMORY_BUDGET: usize = 50_000_000;
+const SNIPPET_LENGTH: usize = 150;
+
+pub(crate) fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub(crate) struct Index {
+    schema: Schema,
+    id: Field,
+    source: Field,
+    fields: Vec<(String, Field)>,
+    facets: HashMap<String, Field>,
+    index: tantivy::Index,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+}
+
+impl Index {
+    pub(crate) fn create(path: &Path, schema: Schema) -> Result<Self> {
+        let mut names = vec![ID, SOURCE];
+        for name in schema.fields.iter().chain(&schema.facets) {
+            if !is_identifier(name) || names.contains(&name.as_str()) {
+                return Err(Error::InvalidName(name.clone()));
+            }
+            names.push(name);
+        }
+
+        let mut builder = IndexSchema::builder();
+        builder.add_text_field(ID, STRING | STORED);
+        builder.add_text_field(SOURCE, STORED);
+        for field in &schema.fields {
+            builder.add_text_field(field, TEXT);
+        }
+        for facet in &schema.facets {
+            builder.add_facet_field(facet, FacetOptions::default());
+        }
+        Self::new(tantivy::Index::create_in_dir(path, builder.build())?)
+    }
+
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        Self::new(tantivy::Index::open_in_dir(path)?)
+    }
+
+    fn new(index: tantivy::Index) -> Result<Self> {
+        let index_schema = index.schema();
+        let mut schema = Schema {
+            fields: Vec::new(),
+            facets: Vec::new(),
+        };
+        let mut fields = Vec::new();
+        let mut facets = HashMap::new();
+        for (field, entry) in index_schema.fields() {
+            let name = entry.name();
+            match entry.field_type() {
+                FieldType::Str(options)
+                    if name != ID && options.get_indexing_options().is_some() =>
+                {
+                    schema.fields.push(name.into());
+                    fields.push((name.into(), field));
+                }
+                FieldType::Facet(_) => {
+                    schema.facets.push(name.into());
+                    facets.insert(name.into(), field);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            schema,
+            id: index_schema.get_field(ID)?,
+            source: index_schema.get_field(SOURCE)?,
+            fields,
+            facets,
+            reader: index.reader()?,
+            writer: Mutex::new(index.writer(MEMORY_BUDGET)?),
+            index,
+        })
+    }
+
+    pub(crate) fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub(crate) fn count(&self) -> u64 {
+        self.reader.searcher().num_docs()
+    }
+
+    fn facet(&self, name: &str) -> Result<Field> {
+        self.facets
+            .get(name)
+            .copied()
+            .ok_or_else(|| Error::UnknownFacet(name.into()))
+    }
+
+    /// Makes the changes visible to the searches once they are on disk.
+    fn commit(&self, writer: &mut IndexWriter) -> Result<()> {
+        writer.commit()?;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    pub(crate) fn add(&self, documents: &[JsonValue]) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        for document in documents {
+            let id = match document.get(ID) {
+                Some(JsonValue::String(id)) => id.clone(),
+                Some(JsonValue::Number(id)) => id.to_string(),
+                _ => return Err(Error::MissingId),
+            };
+
+            let mut doc = Document::new();
+            doc.add_text(self.id, &id);
+            doc.add_text(self.source, serde_json::to_string(document)?);
+            for (name, field) in &self.fields {
+                for text in values(document.get(name)) {
+                    doc.add_text(*field, text);
+                }
+            }
+            for (name, field) in &self.facets {
+                for value in values(document.get(name)) {
+                    doc.add_facet(*field, Facet::from_path([value]));
+                }
+            }
+
+            writer.delete_term(Term::from_field_text(self.id, &id));
+            writer.add_document(doc)?;
+        }
+        self.commit(&mut writer)
+    }
+
+    pub(crate) fn delete(&self, ids: &[String]) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        for id in ids {
+            writer.delete_term(Term::from_field_text(self.id, id));
+        }
+        self.commit(&mut writer)
+    }
+
+    pub(crate) fn clear(&self) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.delete_all_documents()?;
+        self.commit(&mut writer)
+    }
+
+    pub(crate) fn search(&self, query: &str, options: &SearchOptions) -> Result<SearchResults> {
+        let matching: Box<dyn Query> = if query.trim().is_empty() {
+            Box::new(AllQuery)
+        } else {
+            let fields = self.fields.iter().map(|(_, field)| *field).collect();
+            QueryParser::for_index(&self.index, fields).parse_query(query)?
+        };
+
+        let mut clauses = vec![(Occur::Must, matching.box_clone())];
+        for (name, values) in &options.filters {
+            let field = self.facet(name)?;
+            let terms = values
+                .iter()
+                .map(|value| Term::from_facet(field, &Facet::from_path([value])));
+            clauses.push((Occur::Must, Box::new(TermSetQuery::new(terms))));
+        }
+        let filtered = BooleanQuery::new(clauses);
+
+        let mut collectors = MultiCollector::new();
+        let total = collectors.add_collector(Count);
+        // a limit of 0 only counts
+        let top = (options.limit > 0).then(|| {
+            collectors.add_collector(
+                TopDocs::with_limit(options.limit as usize).and_offset(options.offset as usize),
+            )
+        });
+        let mut facets = Vec::new();
+        for name in &options.facets {
+            self.facet(name)?;
+            let mut collector = FacetCollector::for_field(name);
+            collector.add_facet(Facet::root());
+            facets.push((name, collectors.add_collector(collector)));
+        }
+
+        let searcher = self.reader.searcher();
+        let mut fruits = searcher.search(&filtered, &collectors)?;
+
+        let mut results = SearchResults {
+            total: total.extract(&mut fruits) as u64,
+            hits: Vec::new(),
+            facets: HashMap::new(),
+        };
+
+        for (name, collector) in facets {
+            let counts = collector.extract(&mut fruits);
+            let mut values = counts
+                .get(Facet::root())
+                .filter_map(|(facet, count)| {
+                    Some(FacetCount {
+                        value: facet.to_path().last()?.to_string(),
+                        count,
+                    })
+                })
+                .collect::<Vec<_>>();
+            values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+            results.facets.insert(name.clone(), values);
+        }
+
+        if let Some(top) = top {
+            let mut generators = Vec::new();
+            if !query.trim().is_empty() {
+                for (name, field) in &self.fields {
+                    let mut generator =
+                        tantivy::SnippetGenerator::create(&searcher, &*matching, *field)?;
+                    generator.set_max_num_chars(SNIPPET_LENGTH);
+                    generators.push((name, generator));
+                }
+            }
+
+            for (score, address) in top.extract(&mut fruits) {
+                let doc = searcher.doc(address)?;
+                let id = doc
+                    .get_first(self.id)
+                    .and_then(|id| id.as_text())
+                    .unwrap_or_default()
+                    .to_string();
+                let document: JsonValue = match doc.get_first(self.source).and_then(|s| s.as_text())
+                {
+                    Some(source) => serde_json::from_str(source)?,
+                    None => JsonValue::Null,
+                };
+
+                let mut highlights = HashMap::new();
+                for (name, generator) in &generators {
+                    let text = values(document.get(name.as_str()))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let snippet = generator.snippet(&text);
+                    if !snippet.highlighted().is_empty() {
+                        highlights.insert(
+                            name.to_string(),
+                            highlight(
+                                snippet.fragment(),
+                                snippet.highlighted(),
+                                &options.highlight,
+                            ),
+                        );
+                    }
+                }
+
+                results.hits.push(Hit {
+                    id,
+                    score,
+                    document,
+                    highlights,
+                });
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Waits for the merges of the segments.
+    pub(crate) fn close(self) {
+        if let Ok(writer) = self.writer.into_inner() {
+            let _ = writer.wait_merging_threads();
+        }
+    }
+}
+
+/// The strings, numbers and booleans of the value or of the array.
+fn values(value: Option<&JsonValue>) -> impl Iterator<Item = String> + '_ {
+    let values = match value {
+        Some(JsonValue::Array(values)) => values.as_slice(),
+        Some(value) => std::slice::from_ref(value),
+        None => &[],
+    };
+    values.iter().filter_map(|value| match value {
+        JsonValue::String(s) => Some(s.clone()),
+        JsonValue::Number(n) => Some(n.to_string()),
+        JsonValue::Bool(b) => Some(b.to_string()),
+        _ => None,
+    })
+}
+
+fn escape(text: &str, html: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+}
+
+/// The fragment escaped as HTML with the highlighted ranges surrounded by the markers.
+fn highlight(fragment: &str, ranges: &[std::ops::Range<usize>], markers: &Highlight) -> String {
+    let mut html = String::with_capacity(fragment.len());
+    let mut start = 0;
+    for range in ranges {
+        escape(&fragment[start..range.start], &mut html);
+        html.push_str(&markers.before);
+        escape(&fragment[range.clone()], &mut html);
+        html.push_str(&markers.after);
+        start = range.end;
+    }
+    escape(&fragment[start..], &mut html);
+    html
+}
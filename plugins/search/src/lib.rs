@@ -0,0 +1,453 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Full-text search indexes stored in the app data directory, with ranking, highlighting and facets.
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, read_dir, remove_dir_all},
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, RunEvent, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+
+mod index;
+
+use index::Index;
+
+type Result<T> = std::result::Result<T, Error>;
+
+const DIRECTORY: &str = "search";
+/// Written by tantivy in the directory of every index.
+const META: &str = "meta.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Tantivy(#[from] tantivy::TantivyError),
+    #[error(transparent)]
+    Query(#[from] tantivy::query::QueryParserError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("the app data directory could not be resolved")]
+    NoDirectory,
+    #[error("index not found: {0}")]
+    IndexNotFound(String),
+    #[error("index already exists: {0}")]
+    IndexExists(String),
+    #[error("invalid name, only letters, digits and underscores are allowed: {0}")]
+    InvalidName(String),
+    #[error("facet not in the schema of the index: {0}")]
+    UnknownFacet(String),
+    #[error("the documents need a string or number `id`")]
+    MissingId,
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "search";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Tantivy(..) => "tantivy",
+            Self::Query(..) => "query",
+            Self::Json(..) => "json",
+            Self::NoDirectory => "no_directory",
+            Self::IndexNotFound(..) => "index_not_found",
+            Self::IndexExists(..) => "index_exists",
+            Self::InvalidName(..) => "invalid_name",
+            Self::UnknownFacet(..) => "unknown_facet",
+            Self::MissingId => "missing_id",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+/// The fields of the documents the index uses, the documents are stored whole.
+///
+/// The names are those of the index, a field can't be a facet too and `id` is reserved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schema {
+    /// The fields searched by the queries, in the order of their highlights.
+    pub fields: Vec<String>,
+    /// The fields the results are counted and filtered by, e.g. a category or tags.
+    #[serde(default)]
+    pub facets: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Highlight {
+    pub before: String,
+    pub after: String,
+}
+
+impl Default for Highlight {
+    fn default() -> Self {
+        Self {
+            before: "<mark>".into(),
+            after: "</mark>".into(),
+        }
+    }
+}
+
+fn default_limit() -> u32 {
+    20
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchOptions {
+    /// Defaults to 20.
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+    #[serde(default)]
+    pub offset: u32,
+    /// Keeps the documents with one of the values for every facet.
+    #[serde(default)]
+    pub filters: HashMap<String, Vec<String>>,
+    /// The facets whose values are counted among the matching documents.
+    #[serde(default)]
+    pub facets: Vec<String>,
+    /// Surrounds the matching terms of the highlights, defaults to `<mark>` and `</mark>`.
+    #[serde(default)]
+    pub highlight: Highlight,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            limit: default_limit(),
+            offset: 0,
+            filters: HashMap::new(),
+            facets: Vec::new(),
+            highlight: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hit {
+    pub id: String,
+    /// Higher for better matches.
+    pub score: f32,
+    pub document: JsonValue,
+    /// The searched fields with the matching terms highlighted, empty without a query.
+    pub highlights: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FacetCount {
+    pub value: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+    /// The number of matching documents, the hits are a page of them.
+    pub total: u64,
+    pub hits: Vec<Hit>,
+    /// The most frequent values first.
+    pub facets: HashMap<String, Vec<FacetCount>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexInfo {
+    pub name: String,
+    pub schema: Schema,
+    pub documents: u64,
+}
+
+pub struct Search {
+    directory: Option<PathBuf>,
+    indexes: Mutex<HashMap<String, Arc<Index>>>,
+}
+
+impl Search {
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        if !index::is_identifier(name) {
+            return Err(Error::InvalidName(name.into()));
+        }
+        let directory = self.directory.as_ref().ok_or(Error::NoDirectory)?;
+        Ok(directory.join(name))
+    }
+
+    /// The open index, opened from its directory the first time.
+    fn index(&self, name: &str) -> Result<Arc<Index>> {
+        let mut indexes = self.indexes.lock().unwrap();
+        if let Some(index) = indexes.get(name) {
+            return Ok(index.clone());
+        }
+        let path = self.path(name)?;
+        if !path.join(META).exists() {
+            return Err(Error::IndexNotFound(name.into()));
+        }
+        let index = Arc::new(Index::open(&path)?);
+        indexes.insert(name.into(), index.clone());
+        Ok(index)
+    }
+
+    pub fn create_index(&self, name: &str, schema: Schema) -> Result<()> {
+        let mut indexes = self.indexes.lock().unwrap();
+        let path = self.path(name)?;
+        if path.join(META).exists() {
+            return Err(Error::IndexExists(name.into()));
+        }
+        create_dir_all(&path)?;
+        let index = match Index::create(&path, schema) {
+            Ok(index) => index,
+            Err(e) => {
+                // tantivy only creates an index in an empty directory
+                let _ = remove_dir_all(&path);
+                return Err(e);
+            }
+        };
+        indexes.insert(name.into(), Arc::new(index));
+        Ok(())
+    }
+
+    /// Deletes the index and its documents.
+    pub fn delete_index(&self, name: &str) -> Result<()> {
+        let mut indexes = self.indexes.lock().unwrap();
+        let path = self.path(name)?;
+        // the writer holds a lock file in the directory
+        if let Some(index) = indexes.remove(name) {
+            close(index);
+        }
+        if !path.join(META).exists() {
+            return Err(Error::IndexNotFound(name.into()));
+        }
+        remove_dir_all(path)?;
+        Ok(())
+    }
+
+    pub fn indexes(&self) -> Result<Vec<IndexInfo>> {
+        let directory = self.directory.as_ref().ok_or(Error::NoDirectory)?;
+        let mut names = match read_dir(directory) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.join(META).exists())
+                .filter_map(|path| Some(path.file_name()?.to_str()?.to_string()))
+                .filter(|name| index::is_identifier(name))
+                .collect::<Vec<_>>(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        names.sort();
+
+        let mut infos = Vec::with_capacity(names.len());
+        for name in names {
+            let index = self.index(&name)?;
+            infos.push(IndexInfo {
+                schema: index.schema().clone(),
+                documents: index.count(),
+                name,
+            });
+        }
+        Ok(infos)
+    }
+
+    /// Adds the documents to the index, replacing the ones with the same `id`.
+    ///
+    /// The documents are JSON objects, the fields and the facets of the schema can be strings, numbers or arrays of them.
+    pub fn add_documents(&self, name: &str, documents: &[JsonValue]) -> Result<()> {
+        self.index(name)?.add(documents)
+    }
+
+    pub fn delete_documents(&self, name: &str, ids: &[String]) -> Result<()> {
+        self.index(name)?.delete(ids)
+    }
+
+    /// Deletes every document of the index, keeping its schema.
+    pub fn clear(&self, name: &str) -> Result<()> {
+        self.index(name)?.clear()
+    }
+
+    /// Ranks the documents matching the query, written with the tantivy syntax:
+    /// `search plugin` matches either term, `+search +plugin` both, `"search plugin"` the phrase,
+    /// `-index` excludes the term and `title:search` matches the term in a field.
+    ///
+    /// An empty query matches every document.
+    pub fn search(
+        &self,
+        name: &str,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<SearchResults> {
+        self.index(name)?.search(query, options)
+    }
+
+    fn close(&self) {
+        for (_, index) in self.indexes.lock().unwrap().drain() {
+            close(index);
+        }
+    }
+}
+
+fn close(index: Arc<Index>) {
+    // a search still running drops the last reference instead
+    if let Ok(index) = Arc::try_unwrap(index) {
+        index.close();
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn search(&self) -> State<'_, Search>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn search(&self) -> State<'_, Search> {
+        self.state::<Search>()
+    }
+}
+
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(io::Error::other)?
+}
+
+#[command]
+async fn create_index<R: Runtime>(app: AppHandle<R>, name: String, schema: Schema) -> Result<()> {
+    blocking(move || app.search().create_index(&name, schema)).await
+}
+
+#[command]
+async fn delete_index<R: Runtime>(app: AppHandle<R>, name: String) -> Result<()> {
+    blocking(move || app.search().delete_index(&name)).await
+}
+
+#[command]
+async fn indexes<R: Runtime>(app: AppHandle<R>) -> Result<Vec<IndexInfo>> {
+    blocking(move || app.search().indexes()).await
+}
+
+#[command]
+async fn add_documents<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    documents: Vec<JsonValue>,
+) -> Result<()> {
+    blocking(move || app.search().add_documents(&name, &documents)).await
+}
+
+#[command]
+async fn delete_documents<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    ids: Vec<String>,
+) -> Result<()> {
+    blocking(move || app.search().delete_documents(&name, &ids)).await
+}
+
+#[command]
+async fn clear<R: Runtime>(app: AppHandle<R>, name: String) -> Result<()> {
+    blocking(move || app.search().clear(&name)).await
+}
+
+#[command]
+async fn search<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<SearchResults> {
+    blocking(move || {
+        app.search()
+            .search(&name, &query, &options.unwrap_or_default())
+    })
+    .await
+}
+
+#[derive(Default)]
+pub struct Builder {
+    directory: Option<PathBuf>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The directory of the indexes, defaults to `search` in the app data directory.
+    pub fn directory<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.directory.replace(directory.into());
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let directory = self.directory;
+
+        PluginBuilder::new("search")
+            .invoke_handler(tauri::generate_handler![
+                create_index,
+                delete_index,
+                indexes,
+                add_documents,
+                delete_documents,
+                clear,
+                search
+            ])
+            .setup(move |app| {
+                let directory = directory.or_else(|| {
+                    app.path_resolver()
+                        .app_data_dir()
+                        .map(|dir| dir.join(DIRECTORY))
+                });
+                app.manage(Search {
+                    directory,
+                    indexes: Default::default(),
+                });
+                Ok(())
+            })
+            .on_event(|app, event| {
+                if let RunEvent::Exit = event {
+                    app.search().close();
+                }
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
@@ -0,0 +1,154 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+import { appWindow } from "@tauri-apps/api/window";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export type PortType =
+  | {
+      type: "usb";
+      vendorId: number;
+      productId: number;
+      serialNumber: string | null;
+      manufacturer: string | null;
+      product: string | null;
+    }
+  | { type: "pci" }
+  | { type: "bluetooth" }
+  | { type: "unknown" };
+
+export type PortInfo = {
+  /** The path of the port, e.g. `/dev/ttyUSB0` or `COM3`. */
+  name: string;
+} & PortType;
+
+export interface PortOptions {
+  /** Defaults to 9600. */
+  baudRate?: number;
+  /** Between 5 and 8, defaults to 8. */
+  dataBits?: number;
+  parity?: "none" | "odd" | "even";
+  stopBits?: "one" | "two";
+  /** `software` uses XON/XOFF characters, `hardware` the RTS/CTS signals. */
+  flowControl?: "none" | "software" | "hardware";
+  /** How long a write may block, in milliseconds. Defaults to 1 second. */
+  timeout?: number;
+}
+
+interface DataPayload {
+  id: number;
+  data: number[];
+}
+
+interface ClosedPayload {
+  id: number;
+  error: string | null;
+}
+
+/**
+ * An open serial port.
+ */
+class SerialPort {
+  id: number;
+
+  constructor(id: number) {
+    this.id = id;
+  }
+
+  /**
+   * Calls the handler with the bytes received on the port.
+   */
+  async onData(handler: (data: Uint8Array) => void): Promise<UnlistenFn> {
+    return await appWindow.listen<DataPayload>(
+      "serialport://data",
+      ({ payload }) => {
+        if (payload.id === this.id) {
+          handler(new Uint8Array(payload.data));
+        }
+      }
+    );
+  }
+
+  /**
+   * Calls the handler once the port is closed, with the error that closed it
+   * if it wasn't closed by the app, e.g. when the device was unplugged.
+   */
+  async onClose(handler: (error: string | null) => void): Promise<UnlistenFn> {
+    return await appWindow.listen<ClosedPayload>(
+      "serialport://closed",
+      ({ payload }) => {
+        if (payload.id === this.id) {
+          handler(payload.error);
+        }
+      }
+    );
+  }
+
+  async write(data: Uint8Array | number[] | string): Promise<void> {
+    const bytes =
+      typeof data === "string" ? new TextEncoder().encode(data) : data;
+    return await invoke("plugin:serialport|write", {
+      id: this.id,
+      data: Array.from(bytes),
+    });
+  }
+
+  /**
+   * Changes the settings of the port.
+   */
+  async configure(options: PortOptions): Promise<void> {
+    return await invoke("plugin:serialport|configure", {
+      id: this.id,
+      options,
+    });
+  }
+
+  /**
+   * Sets the Data Terminal Ready and Request To Send signals.
+   */
+  async setSignals(signals: { dtr?: boolean; rts?: boolean }): Promise<void> {
+    return await invoke("plugin:serialport|set_signals", {
+      id: this.id,
+      ...signals,
+    });
+  }
+
+  async close(): Promise<void> {
+    return await invoke("plugin:serialport|close", { id: this.id });
+  }
+}
+
+/**
+ * The ports currently available on the system.
+ */
+async function availablePorts(): Promise<PortInfo[]> {
+  return await invoke("plugin:serialport|available_ports");
+}
+
+/**
+ * Opens the port at the given path.
+ *
+ * Register the `onData` handler right away, bytes received before are not buffered.
+ */
+async function open(path: string, options?: PortOptions): Promise<SerialPort> {
+  const id = await invoke<number>("plugin:serialport|open", { path, options });
+  return new SerialPort(id);
+}
+
+async function onPortAdded(
+  handler: (port: PortInfo) => void
+): Promise<UnlistenFn> {
+  return await listen<PortInfo>("serialport://added", ({ payload }) =>
+    handler(payload)
+  );
+}
+
+async function onPortRemoved(
+  handler: (port: PortInfo) => void
+): Promise<UnlistenFn> {
+  return await listen<PortInfo>("serialport://removed", ({ payload }) =>
+    handler(payload)
+  );
+}
+
+export { SerialPort, availablePorts, open, onPortAdded, onPortRemoved };
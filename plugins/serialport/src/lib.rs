@@ -0,0 +1,532 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use serialport::{SerialPort, SerialPortType};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State, Window,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+type Id = u32;
+type Result<T> = std::result::Result<T, Error>;
+
+/// How long a read waits for data before checking whether the port was closed.
+const READ_TIMEOUT: Duration = Duration::from_millis(50);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    SerialPort(#[from] serialport::Error),
+    #[error("port not found for the given id: {0}")]
+    PortNotFound(Id),
+    #[error("invalid number of data bits `{0}`, must be between 5 and 8")]
+    InvalidDataBits(u8),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "serialport";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::SerialPort(..) => "serial_port",
+            Self::PortNotFound(..) => "port_not_found",
+            Self::InvalidDataBits(..) => "invalid_data_bits",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PortType {
+    #[serde(rename_all = "camelCase")]
+    Usb {
+        vendor_id: u16,
+        product_id: u16,
+        serial_number: Option<String>,
+        manufacturer: Option<String>,
+        product: Option<String>,
+    },
+    Pci,
+    Bluetooth,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortInfo {
+    /// The path of the port, e.g. `/dev/ttyUSB0` or `COM3`.
+    pub name: String,
+    #[serde(flatten)]
+    pub port_type: PortType,
+}
+
+impl From<serialport::SerialPortInfo> for PortInfo {
+    fn from(info: serialport::SerialPortInfo) -> Self {
+        let port_type = match info.port_type {
+            SerialPortType::UsbPort(usb) => PortType::Usb {
+                vendor_id: usb.vid,
+                product_id: usb.pid,
+                serial_number: usb.serial_number,
+                manufacturer: usb.manufacturer,
+                product: usb.product,
+            },
+            SerialPortType::PciPort => PortType::Pci,
+            SerialPortType::BluetoothPort => PortType::Bluetooth,
+            SerialPortType::Unknown => PortType::Unknown,
+        };
+        Self {
+            name: info.port_name,
+            port_type,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Parity {
+    #[default]
+    None,
+    Odd,
+    Even,
+}
+
+impl From<Parity> for serialport::Parity {
+    fn from(parity: Parity) -> Self {
+        match parity {
+            Parity::None => Self::None,
+            Parity::Odd => Self::Odd,
+            Parity::Even => Self::Even,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StopBits {
+    #[default]
+    One,
+    Two,
+}
+
+impl From<StopBits> for serialport::StopBits {
+    fn from(stop_bits: StopBits) -> Self {
+        match stop_bits {
+            StopBits::One => Self::One,
+            StopBits::Two => Self::Two,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FlowControl {
+    #[default]
+    None,
+    /// XON/XOFF characters.
+    Software,
+    /// RTS/CTS signals.
+    Hardware,
+}
+
+impl From<FlowControl> for serialport::FlowControl {
+    fn from(flow_control: FlowControl) -> Self {
+        match flow_control {
+            FlowControl::None => Self::None,
+            FlowControl::Software => Self::Software,
+            FlowControl::Hardware => Self::Hardware,
+        }
+    }
+}
+
+fn default_baud_rate() -> u32 {
+    9600
+}
+
+fn default_data_bits() -> u8 {
+    8
+}
+
+fn default_timeout() -> u64 {
+    1000
+}
+
+fn data_bits(data_bits: u8) -> Result<serialport::DataBits> {
+    match data_bits {
+        5 => Ok(serialport::DataBits::Five),
+        6 => Ok(serialport::DataBits::Six),
+        7 => Ok(serialport::DataBits::Seven),
+        8 => Ok(serialport::DataBits::Eight),
+        _ => Err(Error::InvalidDataBits(data_bits)),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortOptions {
+    /// Defaults to 9600.
+    #[serde(default = "default_baud_rate")]
+    pub baud_rate: u32,
+    /// Defaults to 8.
+    #[serde(default = "default_data_bits")]
+    pub data_bits: u8,
+    #[serde(default)]
+    pub parity: Parity,
+    #[serde(default)]
+    pub stop_bits: StopBits,
+    #[serde(default)]
+    pub flow_control: FlowControl,
+    /// How long a write may block, in milliseconds. Defaults to 1 second.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for PortOptions {
+    fn default() -> Self {
+        Self {
+            baud_rate: default_baud_rate(),
+            data_bits: default_data_bits(),
+            parity: Default::default(),
+            stop_bits: Default::default(),
+            flow_control: Default::default(),
+            timeout: default_timeout(),
+        }
+    }
+}
+
+impl PortOptions {
+    fn apply(&self, port: &mut dyn SerialPort) -> Result<()> {
+        port.set_baud_rate(self.baud_rate)?;
+        port.set_data_bits(data_bits(self.data_bits)?)?;
+        port.set_parity(self.parity.into())?;
+        port.set_stop_bits(self.stop_bits.into())?;
+        port.set_flow_control(self.flow_control.into())?;
+        port.set_timeout(Duration::from_millis(self.timeout))?;
+        Ok(())
+    }
+}
+
+/// Emitted while a port is open.
+#[derive(Debug)]
+pub enum PortEvent {
+    /// Bytes were received.
+    Data(Vec<u8>),
+    /// The port was closed, with the error that closed it if it wasn't closed by the app,
+    /// e.g. when the device was unplugged.
+    Closed(Option<Error>),
+}
+
+struct Port {
+    writer: Box<dyn SerialPort>,
+    closed: Arc<AtomicBool>,
+}
+
+pub struct SerialPorts<R: Runtime> {
+    app: AppHandle<R>,
+    next_id: AtomicU32,
+    ports: Arc<Mutex<HashMap<Id, Port>>>,
+    known: Mutex<Vec<PortInfo>>,
+}
+
+impl<R: Runtime> SerialPorts<R> {
+    /// The ports currently available on the system.
+    pub fn available_ports(&self) -> Result<Vec<PortInfo>> {
+        Ok(serialport::available_ports()?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Opens the port at `path`, `handler` is called from a background thread with the id of the port
+    /// and the received bytes until the port is closed.
+    pub fn open<F: FnMut(Id, PortEvent) + Send + 'static>(
+        &self,
+        path: &str,
+        options: &PortOptions,
+        mut handler: F,
+    ) -> Result<Id> {
+        let mut writer = serialport::new(path, options.baud_rate).open()?;
+        options.apply(writer.as_mut())?;
+        let mut reader = writer.try_clone()?;
+        reader.set_timeout(READ_TIMEOUT)?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let closed = Arc::new(AtomicBool::new(false));
+        self.ports.lock().unwrap().insert(
+            id,
+            Port {
+                writer,
+                closed: closed.clone(),
+            },
+        );
+
+        let ports = self.ports.clone();
+        spawn(move || {
+            let mut buffer = [0; 4096];
+            let error = loop {
+                if closed.load(Ordering::Relaxed) {
+                    break None;
+                }
+                match reader.read(&mut buffer) {
+                    Ok(0) => {}
+                    Ok(n) => handler(id, PortEvent::Data(buffer[..n].to_vec())),
+                    Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => break Some(e.into()),
+                }
+            };
+            if error.is_some() {
+                ports.lock().unwrap().remove(&id);
+            }
+            handler(id, PortEvent::Closed(error));
+        });
+
+        Ok(id)
+    }
+
+    /// Changes the settings of an open port.
+    pub fn configure(&self, id: Id, options: &PortOptions) -> Result<()> {
+        let mut ports = self.ports.lock().unwrap();
+        let port = ports.get_mut(&id).ok_or(Error::PortNotFound(id))?;
+        options.apply(port.writer.as_mut())
+    }
+
+    /// Writes all the bytes to the port.
+    pub fn write(&self, id: Id, data: &[u8]) -> Result<()> {
+        let mut ports = self.ports.lock().unwrap();
+        let port = ports.get_mut(&id).ok_or(Error::PortNotFound(id))?;
+        port.writer.write_all(data)?;
+        port.writer.flush()?;
+        Ok(())
+    }
+
+    /// Sets the Data Terminal Ready and Request To Send signals,
+    /// e.g. toggling DTR resets most Arduino boards.
+    pub fn set_signals(&self, id: Id, dtr: Option<bool>, rts: Option<bool>) -> Result<()> {
+        let mut ports = self.ports.lock().unwrap();
+        let port = ports.get_mut(&id).ok_or(Error::PortNotFound(id))?;
+        if let Some(dtr) = dtr {
+            port.writer.write_data_terminal_ready(dtr)?;
+        }
+        if let Some(rts) = rts {
+            port.writer.write_request_to_send(rts)?;
+        }
+        Ok(())
+    }
+
+    /// Closes the port, the handler given to [`Self::open`] then receives [`PortEvent::Closed`].
+    pub fn close(&self, id: Id) -> Result<()> {
+        let port = self
+            .ports
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or(Error::PortNotFound(id))?;
+        port.closed.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn refresh(&self) {
+        let ports = match self.available_ports() {
+            Ok(ports) => ports,
+            Err(e) => {
+                log::warn!("failed to list the serial ports: {}", e);
+                return;
+            }
+        };
+
+        let mut known = self.known.lock().unwrap();
+        for port in ports.iter().filter(|port| !known.contains(port)) {
+            let _ = self.app.emit_all("serialport://added", port);
+        }
+        for port in known.iter().filter(|port| !ports.contains(port)) {
+            let _ = self.app.emit_all("serialport://removed", port);
+        }
+        *known = ports;
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn serial_ports(&self) -> State<'_, SerialPorts<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn serial_ports(&self) -> State<'_, SerialPorts<R>> {
+        self.state::<SerialPorts<R>>()
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct DataPayload {
+    id: Id,
+    data: Vec<u8>,
+}
+
+#[derive(Clone, Serialize)]
+struct ClosedPayload {
+    id: Id,
+    error: Option<String>,
+}
+
+#[command]
+async fn available_ports<R: Runtime>(app: AppHandle<R>) -> Result<Vec<PortInfo>> {
+    app.serial_ports().available_ports()
+}
+
+#[command]
+async fn open<R: Runtime>(
+    window: Window<R>,
+    path: String,
+    options: Option<PortOptions>,
+) -> Result<Id> {
+    let serial_ports = window.serial_ports();
+    let window_ = window.clone();
+    serial_ports.open(&path, &options.unwrap_or_default(), move |id, event| {
+        let _ = match event {
+            PortEvent::Data(data) => window_.emit("serialport://data", DataPayload { id, data }),
+            PortEvent::Closed(error) => window_.emit(
+                "serialport://closed",
+                ClosedPayload {
+                    id,
+                    error: error.map(|e| e.to_string()),
+                },
+            ),
+        };
+    })
+}
+
+#[command]
+async fn configure<R: Runtime>(app: AppHandle<R>, id: Id, options: PortOptions) -> Result<()> {
+    app.serial_ports().configure(id, &options)
+}
+
+#[command]
+async fn write<R: Runtime>(app: AppHandle<R>, id: Id, data: Vec<u8>) -> Result<()> {
+    app.serial_ports().write(id, &data)
+}
+
+#[command]
+async fn set_signals<R: Runtime>(
+    app: AppHandle<R>,
+    id: Id,
+    dtr: Option<bool>,
+    rts: Option<bool>,
+) -> Result<()> {
+    app.serial_ports().set_signals(id, dtr, rts)
+}
+
+#[command]
+async fn close<R: Runtime>(app: AppHandle<R>, id: Id) -> Result<()> {
+    app.serial_ports().close(id)
+}
+
+pub struct Builder {
+    poll_interval: Option<Duration>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            poll_interval: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How often the ports are listed to emit the `serialport://added` and `serialport://removed` events,
+    /// defaults to 1 second.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval.replace(interval);
+        self
+    }
+
+    /// Disables the `serialport://added` and `serialport://removed` events.
+    pub fn disable_hotplug(mut self) -> Self {
+        self.poll_interval.take();
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let poll_interval = self.poll_interval;
+
+        PluginBuilder::new("serialport")
+            .invoke_handler(tauri::generate_handler![
+                available_ports,
+                open,
+                configure,
+                write,
+                set_signals,
+                close
+            ])
+            .setup(move |app| {
+                let serial_ports = SerialPorts {
+                    app: app.clone(),
+                    next_id: Default::default(),
+                    ports: Default::default(),
+                    known: Default::default(),
+                };
+                *serial_ports.known.lock().unwrap() =
+                    serial_ports.available_ports().unwrap_or_default();
+                app.manage(serial_ports);
+
+                if let Some(poll_interval) = poll_interval {
+                    let app = app.clone();
+                    spawn(move || loop {
+                        sleep(poll_interval);
+                        app.serial_ports().refresh();
+                    });
+                }
+
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
@@ -0,0 +1,34 @@
+import { invoke } from "@tauri-apps/api/tauri";
+
+import type { ErrorPayload } from "./types/ErrorPayload";
+import type { ShareOutcome } from "./types/ShareOutcome";
+import type { SharedContent } from "./types/SharedContent";
+
+export type { ErrorPayload, ShareOutcome, SharedContent };
+
+export interface ShareOptions {
+  /** Shown by the share sheet on Windows, defaults to the name of the app. */
+  title?: string;
+  text?: string;
+  url?: string;
+  /** Absolute paths. */
+  files?: string[];
+}
+
+/**
+ * Shows the share sheet next to the current window, resolves once the user shared or dismissed it.
+ */
+async function share(options: ShareOptions): Promise<ShareOutcome> {
+  return await invoke("plugin:share|share", { options });
+}
+
+/**
+ * The content shared with the app when it was started as a share target, only returned once.
+ *
+ * Requires `Builder::share_target`.
+ */
+async function sharedContent(): Promise<SharedContent | null> {
+  return await invoke("plugin:share|shared_content");
+}
+
+export { share, sharedContent };
@@ -0,0 +1,220 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The share sheet of the system: the Windows Share flyout and the picker of `NSSharingService` on macOS.
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State, Window,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{path::PathBuf, sync::mpsc::channel, sync::Mutex};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+type Result<T> = std::result::Result<T, Error>;
+type CompletionHandler = Box<dyn FnOnce(ShareOutcome) + Send>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error("the share sheet is not available on this platform")]
+    UnsupportedPlatform,
+    #[error("nothing to share")]
+    Empty,
+    #[error("the file doesn't exist: {0}")]
+    FileNotFound(PathBuf),
+    #[error("failed to share: {0}")]
+    Share(String),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "share";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Tauri(..) => "tauri",
+            Self::UnsupportedPlatform => "unsupported_platform",
+            Self::Empty => "empty",
+            Self::FileNotFound(..) => "file_not_found",
+            Self::Share(..) => "share",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ShareOptions {
+    /// Shown by the share sheet on Windows, defaults to the name of the app.
+    pub title: Option<String>,
+    pub text: Option<String>,
+    pub url: Option<String>,
+    /// Absolute paths.
+    pub files: Vec<PathBuf>,
+}
+
+impl ShareOptions {
+    fn validate(&self) -> Result<()> {
+        if self.text.is_none() && self.url.is_none() && self.files.is_empty() {
+            return Err(Error::Empty);
+        }
+        if let Some(file) = self.files.iter().find(|file| !file.is_file()) {
+            return Err(Error::FileNotFound(file.clone()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct ShareOutcome {
+    /// `false` when the user dismissed the share sheet or the target failed.
+    pub completed: bool,
+    /// The name of the chosen service on macOS, the app user model id of the target app on Windows.
+    pub target: Option<String>,
+}
+
+/// What another app shared with this one, when it was started as a share target.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct SharedContent {
+    pub text: Option<String>,
+    pub url: Option<String>,
+    pub files: Vec<PathBuf>,
+}
+
+pub trait WindowExt {
+    /// Shows the share sheet next to the window, the handler is called once the user shared or dismissed it.
+    fn share<F: FnOnce(ShareOutcome) + Send + 'static>(
+        &self,
+        options: &ShareOptions,
+        on_complete: F,
+    ) -> Result<()>;
+}
+
+impl<R: Runtime> WindowExt for Window<R> {
+    fn share<F: FnOnce(ShareOutcome) + Send + 'static>(
+        &self,
+        options: &ShareOptions,
+        on_complete: F,
+    ) -> Result<()> {
+        options.validate()?;
+        let mut options = options.clone();
+        if options.title.is_none() {
+            options
+                .title
+                .replace(self.app_handle().package_info().name.clone());
+        }
+        platform::share(self, options, Box::new(on_complete))
+    }
+}
+
+pub struct Share {
+    received: Mutex<Option<SharedContent>>,
+}
+
+impl Share {
+    /// The content shared with the app at launch, only returned once.
+    pub fn take_shared_content(&self) -> Option<SharedContent> {
+        self.received.lock().unwrap().take()
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn share(&self) -> State<'_, Share>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn share(&self) -> State<'_, Share> {
+        self.state::<Share>()
+    }
+}
+
+#[command]
+async fn share<R: Runtime>(window: Window<R>, options: ShareOptions) -> Result<ShareOutcome> {
+    let (tx, rx) = channel();
+    WindowExt::share(&window, &options, move |outcome| {
+        let _ = tx.send(outcome);
+    })?;
+    // the handler is dropped without being called when the share sheet failed to show
+    Ok(
+        tauri::async_runtime::spawn_blocking(move || rx.recv().unwrap_or_default())
+            .await
+            .unwrap_or_default(),
+    )
+}
+
+#[command]
+fn shared_content<R: Runtime>(app: AppHandle<R>) -> Option<SharedContent> {
+    app.share().take_shared_content()
+}
+
+#[derive(Default)]
+pub struct Builder {
+    share_target: bool,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the content shared with the app when it was started as a share target.
+    ///
+    /// On Windows the app must be packaged and declare the `windows.shareTarget` extension in its manifest,
+    /// macOS requires a share extension bundled with the app and isn't supported.
+    pub fn share_target(mut self) -> Self {
+        self.share_target = true;
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let share_target = self.share_target;
+
+        PluginBuilder::new("share")
+            .invoke_handler(tauri::generate_handler![share, shared_content])
+            .setup(move |app| {
+                let received = if share_target {
+                    platform::shared_content()
+                } else {
+                    None
+                };
+                app.manage(Share {
+                    received: Mutex::new(received),
+                });
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
@@ -0,0 +1,21 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The desktops have no share sheet, nor a portal for it.
+
+use tauri::{Runtime, Window};
+
+use crate::{CompletionHandler, Error, Result, ShareOptions, SharedContent};
+
+pub(crate) fn share<R: Runtime>(
+    _window: &Window<R>,
+    _options: ShareOptions,
+    _on_complete: CompletionHandler,
+) -> Result<()> {
+    Err(Error::UnsupportedPlatform)
+}
+
+pub(crate) fn shared_content() -> Option<SharedContent> {
+    None
+}
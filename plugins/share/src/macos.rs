@@ -0,0 +1,192 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use objc::{
+    class,
+    declare::ClassDecl,
+    msg_send,
+    runtime::{Class, Object, Sel, BOOL, NO},
+    sel, sel_impl, Encode, Encoding,
+};
+use tauri::{Runtime, Window};
+
+use std::{ffi::c_void, os::raw::c_char, sync::mpsc::channel};
+
+use crate::{CompletionHandler, Error, Result, ShareOptions, ShareOutcome, SharedContent};
+
+type Id = *mut Object;
+
+const DELEGATE_CLASS: &str = "TauriPluginShareDelegate";
+const NS_UTF8_STRING_ENCODING: usize = 4;
+const NS_MIN_Y_EDGE: usize = 1;
+
+#[repr(C)]
+struct NSRect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+unsafe impl Encode for NSRect {
+    fn encode() -> Encoding {
+        unsafe { Encoding::from_str("{CGRect={CGPoint=dd}{CGSize=dd}}") }
+    }
+}
+
+unsafe fn ns_string(s: &str) -> Id {
+    let string: Id = msg_send![class!(NSString), alloc];
+    let string: Id = msg_send![string,
+        initWithBytes: s.as_ptr() as *const c_void
+        length: s.len()
+        encoding: NS_UTF8_STRING_ENCODING];
+    msg_send![string, autorelease]
+}
+
+unsafe fn string(string: Id) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+    let utf8: *const c_char = msg_send![string, UTF8String];
+    (!utf8.is_null()).then(|| {
+        std::ffi::CStr::from_ptr(utf8)
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+/// Calls the handler once and releases the delegate, the picker doesn't retain it.
+unsafe fn finish(this: &mut Object, outcome: ShareOutcome) {
+    let handler = *this.get_ivar::<usize>("handler") as *mut CompletionHandler;
+    if handler.is_null() {
+        return;
+    }
+    this.set_ivar::<usize>("handler", 0);
+    (Box::from_raw(handler))(outcome);
+
+    let picker = *this.get_ivar::<usize>("picker") as Id;
+    let _: () = msg_send![picker, autorelease];
+    let _: () = msg_send![this, autorelease];
+}
+
+extern "C" fn did_choose_service(this: &mut Object, _cmd: Sel, _picker: Id, service: Id) {
+    unsafe {
+        if service.is_null() {
+            // dismissed
+            finish(this, ShareOutcome::default());
+        } else {
+            let _: () = msg_send![service, setDelegate: this as *mut Object];
+        }
+    }
+}
+
+extern "C" fn did_share_items(this: &mut Object, _cmd: Sel, service: Id, _items: Id) {
+    unsafe {
+        let title: Id = msg_send![service, title];
+        finish(
+            this,
+            ShareOutcome {
+                completed: true,
+                target: string(title),
+            },
+        );
+    }
+}
+
+extern "C" fn did_fail_to_share_items(
+    this: &mut Object,
+    _cmd: Sel,
+    _service: Id,
+    _items: Id,
+    _error: Id,
+) {
+    unsafe { finish(this, ShareOutcome::default()) }
+}
+
+fn delegate_class() -> &'static Class {
+    if let Some(class) = Class::get(DELEGATE_CLASS) {
+        return class;
+    }
+
+    let mut decl = ClassDecl::new(DELEGATE_CLASS, class!(NSObject)).unwrap();
+    decl.add_ivar::<usize>("handler");
+    decl.add_ivar::<usize>("picker");
+    unsafe {
+        decl.add_method(
+            sel!(sharingServicePicker:didChooseSharingService:),
+            did_choose_service as extern "C" fn(&mut Object, Sel, Id, Id),
+        );
+        decl.add_method(
+            sel!(sharingService:didShareItems:),
+            did_share_items as extern "C" fn(&mut Object, Sel, Id, Id),
+        );
+        decl.add_method(
+            sel!(sharingService:didFailToShareItems:error:),
+            did_fail_to_share_items as extern "C" fn(&mut Object, Sel, Id, Id, Id),
+        );
+    }
+    decl.register()
+}
+
+unsafe fn items(options: &ShareOptions) -> Id {
+    let items: Id = msg_send![class!(NSMutableArray), array];
+    if let Some(text) = &options.text {
+        let _: () = msg_send![items, addObject: ns_string(text)];
+    }
+    if let Some(url) = &options.url {
+        let url: Id = msg_send![class!(NSURL), URLWithString: ns_string(url)];
+        if !url.is_null() {
+            let _: () = msg_send![items, addObject: url];
+        }
+    }
+    for file in &options.files {
+        let url: Id = msg_send![class!(NSURL), fileURLWithPath: ns_string(&file.to_string_lossy())];
+        let _: () = msg_send![items, addObject: url];
+    }
+    items
+}
+
+unsafe fn show(ns_window: Id, options: &ShareOptions, on_complete: CompletionHandler) {
+    let picker: Id = msg_send![class!(NSSharingServicePicker), alloc];
+    let picker: Id = msg_send![picker, initWithItems: items(options)];
+
+    let delegate: Id = msg_send![delegate_class(), new];
+    (*delegate).set_ivar::<usize>("handler", Box::into_raw(Box::new(on_complete)) as usize);
+    (*delegate).set_ivar::<usize>("picker", picker as usize);
+    let _: () = msg_send![picker, setDelegate: delegate];
+
+    let view: Id = msg_send![ns_window, contentView];
+    let bounds: NSRect = msg_send![view, bounds];
+    let _: () = msg_send![picker,
+        showRelativeToRect: bounds
+        ofView: view
+        preferredEdge: NS_MIN_Y_EDGE];
+}
+
+pub(crate) fn share<R: Runtime>(
+    window: &Window<R>,
+    options: ShareOptions,
+    on_complete: CompletionHandler,
+) -> Result<()> {
+    let ns_window = window.ns_window()? as usize;
+    // AppKit must be called from the main thread
+    let is_main_thread: BOOL = unsafe { msg_send![class!(NSThread), isMainThread] };
+    if is_main_thread != NO {
+        unsafe { show(ns_window as Id, &options, on_complete) };
+        return Ok(());
+    }
+
+    let (tx, rx) = channel();
+    window.run_on_main_thread(move || {
+        unsafe { show(ns_window as Id, &options, on_complete) };
+        let _ = tx.send(());
+    })?;
+    rx.recv()
+        .map_err(|_| Error::Share("the event loop is closed".into()))
+}
+
+/// The apps receive the shared content through a share extension, a separate bundle.
+pub(crate) fn shared_content() -> Option<SharedContent> {
+    None
+}
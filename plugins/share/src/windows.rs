@@ -0,0 +1,243 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use tauri::{Runtime, Window};
+use windows::{
+    core::{implement, IInspectable, Interface, Result as WinResult, HSTRING},
+    ApplicationModel::{
+        Activation::{ActivationKind, ShareTargetActivatedEventArgs},
+        AppInstance,
+        DataTransfer::{
+            DataPackage, DataPackageView, DataRequestedEventArgs, DataTransferManager,
+            ShareCompletedEventArgs, StandardDataFormats,
+        },
+    },
+    Foundation::{
+        Collections::{IIterable, IIterable_Impl, IIterator, IIterator_Impl},
+        TypedEventHandler, Uri,
+    },
+    Storage::{IStorageItem, StorageFile},
+    Win32::{
+        Foundation::HWND,
+        System::Threading::GetCurrentThreadId,
+        UI::{Shell::IDataTransferManagerInterop, WindowsAndMessaging::GetWindowThreadProcessId},
+    },
+};
+
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::channel,
+        Arc, Mutex,
+    },
+};
+
+use crate::{CompletionHandler, Error, Result, ShareOptions, ShareOutcome, SharedContent};
+
+fn share_error(error: windows::core::Error) -> Error {
+    Error::Share(error.message().to_string_lossy())
+}
+
+/// The packages take the files as an `IIterable`, which can't be created from a `Vec` in these bindings.
+#[implement(IIterable<IStorageItem>)]
+struct StorageItems(Vec<IStorageItem>);
+
+impl IIterable_Impl<IStorageItem> for StorageItems {
+    fn First(&self) -> WinResult<IIterator<IStorageItem>> {
+        Ok(StorageItemsIterator {
+            items: self.0.clone(),
+            index: AtomicUsize::new(0),
+        }
+        .into())
+    }
+}
+
+#[implement(IIterator<IStorageItem>)]
+struct StorageItemsIterator {
+    items: Vec<IStorageItem>,
+    index: AtomicUsize,
+}
+
+impl IIterator_Impl<IStorageItem> for StorageItemsIterator {
+    fn Current(&self) -> WinResult<IStorageItem> {
+        self.items
+            .get(self.index.load(Ordering::Relaxed))
+            .cloned()
+            .ok_or_else(|| windows::core::Error::from(windows::Win32::Foundation::E_BOUNDS))
+    }
+
+    fn HasCurrent(&self) -> WinResult<bool> {
+        Ok(self.index.load(Ordering::Relaxed) < self.items.len())
+    }
+
+    fn MoveNext(&self) -> WinResult<bool> {
+        let index = self.index.fetch_add(1, Ordering::Relaxed) + 1;
+        Ok(index < self.items.len())
+    }
+
+    fn GetMany(&self, items: &mut [Option<IStorageItem>]) -> WinResult<u32> {
+        let start = self.index.load(Ordering::Relaxed).min(self.items.len());
+        let count = items.len().min(self.items.len() - start);
+        for (slot, item) in items.iter_mut().zip(&self.items[start..start + count]) {
+            *slot = Some(item.clone());
+        }
+        self.index.store(start + count, Ordering::Relaxed);
+        Ok(count as u32)
+    }
+}
+
+fn fill(package: &DataPackage, options: &ShareOptions) -> WinResult<()> {
+    if let Some(title) = &options.title {
+        package
+            .Properties()?
+            .SetTitle(&HSTRING::from(title.as_str()))?;
+    }
+    if let Some(text) = &options.text {
+        package.SetText(&HSTRING::from(text.as_str()))?;
+    }
+    if let Some(url) = &options.url {
+        package.SetWebLink(&Uri::CreateUri(&HSTRING::from(url.as_str()))?)?;
+    }
+    if !options.files.is_empty() {
+        let mut files = Vec::new();
+        for path in &options.files {
+            let file =
+                StorageFile::GetFileFromPathAsync(&HSTRING::from(path.as_os_str()))?.get()?;
+            files.push(file.cast::<IStorageItem>()?);
+        }
+        let items: IIterable<IStorageItem> = StorageItems(files).into();
+        package.SetStorageItemsReadOnly(&items)?;
+    }
+    Ok(())
+}
+
+/// Reports the outcome once, the package may be destroyed after the share completed.
+fn watch(package: &DataPackage, on_complete: CompletionHandler) -> WinResult<()> {
+    let on_complete = Arc::new(Mutex::new(Some(on_complete)));
+    let finish = move |outcome: ShareOutcome| {
+        if let Some(on_complete) = on_complete.lock().unwrap().take() {
+            on_complete(outcome);
+        }
+    };
+
+    let finish_ = finish.clone();
+    package.ShareCompleted(&TypedEventHandler::new(
+        move |_, args: &Option<ShareCompletedEventArgs>| {
+            let target = args
+                .as_ref()
+                .and_then(|args| args.ShareTarget().ok())
+                .and_then(|target| target.AppUserModelId().ok())
+                .map(|id| id.to_string_lossy());
+            finish_(ShareOutcome {
+                completed: true,
+                target,
+            });
+            Ok(())
+        },
+    ))?;
+    let finish_ = finish.clone();
+    // Windows 11 only
+    let _ = package.ShareCanceled(&TypedEventHandler::new(
+        move |_, _: &Option<IInspectable>| {
+            finish_(ShareOutcome::default());
+            Ok(())
+        },
+    ));
+    package.Destroyed(&TypedEventHandler::new(
+        move |_, _: &Option<IInspectable>| {
+            finish(ShareOutcome::default());
+            Ok(())
+        },
+    ))?;
+    Ok(())
+}
+
+pub(crate) fn share<R: Runtime>(
+    window: &Window<R>,
+    options: ShareOptions,
+    on_complete: CompletionHandler,
+) -> Result<()> {
+    let hwnd = HWND(window.hwnd()?.0);
+    let show = move || unsafe {
+        let interop = windows::core::factory::<DataTransferManager, IDataTransferManagerInterop>()?;
+        let manager: DataTransferManager = interop.GetForWindow(hwnd)?;
+        let token = Arc::new(Mutex::new(None));
+        let token_ = token.clone();
+        let mut pending = Some((options, on_complete));
+        let registration = manager.DataRequested(&TypedEventHandler::new(
+            move |manager: &Option<DataTransferManager>, args: &Option<DataRequestedEventArgs>| {
+                // the handler stays registered on the window, it only serves this request
+                if let (Some(manager), Some(token)) = (manager, token_.lock().unwrap().take()) {
+                    let _ = manager.RemoveDataRequested(token);
+                }
+                if let (Some(args), Some((options, on_complete))) = (args, pending.take()) {
+                    let package = args.Request()?.Data()?;
+                    fill(&package, &options)?;
+                    watch(&package, on_complete)?;
+                }
+                Ok(())
+            },
+        ))?;
+        token.lock().unwrap().replace(registration);
+        interop.ShowShareUIForWindow(hwnd)
+    };
+
+    // the share UI belongs to the thread of the window
+    if unsafe { GetWindowThreadProcessId(hwnd, std::ptr::null_mut()) == GetCurrentThreadId() } {
+        return show().map_err(share_error);
+    }
+    let (tx, rx) = channel();
+    window.run_on_main_thread(move || {
+        let _ = tx.send(show());
+    })?;
+    rx.recv()
+        .map_err(|_| Error::Share("the event loop is closed".into()))?
+        .map_err(share_error)
+}
+
+fn read(view: &DataPackageView) -> WinResult<SharedContent> {
+    let mut content = SharedContent::default();
+    if view.Contains(&StandardDataFormats::Text()?)? {
+        content
+            .text
+            .replace(view.GetTextAsync()?.get()?.to_string_lossy());
+    }
+    if view.Contains(&StandardDataFormats::WebLink()?)? {
+        content
+            .url
+            .replace(view.GetWebLinkAsync()?.get()?.RawUri()?.to_string_lossy());
+    }
+    if view.Contains(&StandardDataFormats::StorageItems()?)? {
+        let items = view.GetStorageItemsAsync()?.get()?;
+        for i in 0..items.Size()? {
+            content
+                .files
+                .push(PathBuf::from(items.GetAt(i)?.Path()?.to_os_string()));
+        }
+    }
+    Ok(content)
+}
+
+/// Only the packaged apps declaring the `windows.shareTarget` extension are activated as share targets.
+pub(crate) fn shared_content() -> Option<SharedContent> {
+    let args = AppInstance::GetActivatedEventArgs().ok()?;
+    if args.Kind().ok()? != ActivationKind::ShareTarget {
+        return None;
+    }
+    let operation = args
+        .cast::<ShareTargetActivatedEventArgs>()
+        .and_then(|args| args.ShareOperation())
+        .ok()?;
+    let content = operation.Data().and_then(|view| read(&view));
+    // closes the share UI of the source app
+    let _ = operation.ReportCompleted();
+    match content {
+        Ok(content) => Some(content),
+        Err(e) => {
+            log::warn!("failed to read the shared content: {}", e.message());
+            None
+        }
+    }
+}
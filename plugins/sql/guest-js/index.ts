@@ -1,19 +1,268 @@
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/tauri";
 
+import type { ErrorPayload } from "./types/ErrorPayload";
+import type { Notification } from "./types/Notification";
+import type { Progress } from "./types/Progress";
+import type { RowsPayload } from "./types/RowsPayload";
+
+export type { ErrorPayload, Notification, Progress };
+
 export interface QueryResult {
   /** The number of rows affected by the query. */
   rowsAffected: number;
   /**
    * The last inserted `id`.
    *
-   * This value is always `0` when using the Postgres driver. If the
+   * This value is always `0` when using the Postgres or MSSQL driver. If the
    * last inserted id is required on Postgres, the `select` function
    * must be used, with a `RETURNING` clause
-   * (`INSERT INTO todos (title) VALUES ($1) RETURNING id`).
+   * (`INSERT INTO todos (title) VALUES ($1) RETURNING id`), or an
+   * `OUTPUT` clause on MSSQL (`INSERT INTO todos (title) OUTPUT INSERTED.id VALUES (@p1)`).
    */
   lastInsertId: number;
 }
 
+export interface QueryOptions {
+  /** Rejects with a `timeout` error once the query ran that long, in milliseconds. */
+  timeout?: number;
+  /** The id to abort the query with `Database.cancel`. */
+  id?: string;
+  /** Aborts the query once it's aborted. */
+  signal?: AbortSignal;
+}
+
+/** What a statement of a query does, from its first keyword. */
+export type StatementKind =
+  | "select"
+  | "insert"
+  | "update"
+  | "delete"
+  | "ddl"
+  | "attach"
+  | "pragma"
+  | "transaction"
+  | "other";
+
+export interface PoolOptions {
+  /** The most connections opened at once, e.g. `1` for SQLite to serialize the writes. */
+  maxConnections?: number;
+  /** The connections kept open even when they're idle. */
+  minConnections?: number;
+  /** How long a query waits for a connection before it fails, in milliseconds. */
+  acquireTimeout?: number;
+  /** How long a connection is idle before it's closed, in milliseconds. */
+  idleTimeout?: number;
+  /**
+   * The prepared statements each connection keeps, dropping the least recently used ones above it.
+   * `100` by default, `0` to prepare the queries every time. Ignored by MSSQL.
+   */
+  statementCacheCapacity?: number;
+  /** Only lets the queries run `select` statements, and opens the connections read-only. */
+  readOnly?: boolean;
+  /** The only kinds of statements the queries can run. */
+  allowedStatements?: StatementKind[];
+  /** The kinds of statements the queries can't run, e.g. `ddl` and `attach`. */
+  deniedStatements?: StatementKind[];
+  /**
+   * How the SQLite database journals its writes, e.g. `WAL` so the reads don't wait for them.
+   * The mode is kept by the database file, `DELETE` for a new one.
+   */
+  journalMode?: "DELETE" | "TRUNCATE" | "PERSIST" | "MEMORY" | "WAL" | "OFF";
+  /** How long a query waits for the SQLite database to be unlocked before it fails, in milliseconds, 5 seconds by default. */
+  busyTimeout?: number;
+  /** Whether SQLite enforces the foreign key constraints, `true` by default. */
+  foreignKeys?: boolean;
+  /** How often SQLite syncs the writes to the disk, `FULL` by default. `NORMAL` is safe and faster with `WAL`. */
+  synchronous?: "OFF" | "NORMAL" | "FULL" | "EXTRA";
+}
+
+/**
+ * **Transaction**
+ *
+ * A transaction begun with `Database.begin`, its queries are applied at once on `commit`.
+ *
+ * It can only be used from the window that began it, and it's rolled back if that window closes or reloads before it
+ * ends.
+ */
+export class Transaction {
+  path: string;
+  id: number;
+  constructor(path: string, id: number) {
+    this.path = path;
+    this.id = id;
+  }
+
+  /**
+   * **execute**
+   *
+   * Passes a SQL expression to the database for execution in the transaction.
+   */
+  async execute(
+    query: string,
+    bindValues?: unknown[],
+    options?: QueryOptions
+  ): Promise<QueryResult> {
+    return await execute(this.path, query, bindValues, options, this.id);
+  }
+
+  /**
+   * **select**
+   *
+   * Passes in a SELECT query to the database for execution in the transaction.
+   */
+  async select<T>(
+    query: string,
+    bindValues?: unknown[],
+    options?: QueryOptions
+  ): Promise<T> {
+    return await select<T>(this.path, query, bindValues, options, this.id);
+  }
+
+  /**
+   * **commit**
+   *
+   * Applies the queries of the transaction and ends it.
+   */
+  async commit(): Promise<void> {
+    await invoke("plugin:sql|commit", { transaction: this.id });
+  }
+
+  /**
+   * **rollback**
+   *
+   * Discards the queries of the transaction and ends it.
+   */
+  async rollback(): Promise<void> {
+    await invoke("plugin:sql|rollback", { transaction: this.id });
+  }
+}
+
+/**
+ * **Cursor**
+ *
+ * A query opened with `Database.selectStream`, whose rows are sent in batches as they're fetched.
+ *
+ * It's closed once its rows were all fetched, or if the window closes or reloads.
+ */
+export class Cursor<T> {
+  id: number;
+  constructor(id: number) {
+    this.id = id;
+  }
+
+  /**
+   * **onRows**
+   *
+   * Calls the handler with each batch of rows fetched.
+   */
+  async onRows(handler: (rows: T[]) => void): Promise<UnlistenFn> {
+    return await listen<RowsPayload>(
+      "sql://rows",
+      ({ payload }) => {
+        if (payload.cursor === this.id) {
+          handler(payload.rows as T[]);
+        }
+      }
+    );
+  }
+
+  /**
+   * **fetchNext**
+   *
+   * Sends the next batch of rows to the `onRows` handlers, resolves to `false` once there are no rows left.
+   */
+  async fetchNext(): Promise<boolean> {
+    return await invoke<boolean>("plugin:sql|fetch_next", { cursor: this.id });
+  }
+
+  /**
+   * **close**
+   *
+   * Closes the cursor before its rows were all fetched.
+   */
+  async close(): Promise<void> {
+    await invoke("plugin:sql|close_cursor", { cursor: this.id });
+  }
+}
+
+let nextQueryId = 0;
+
+/**
+ * Runs the query with its options, cancelling it once the signal is aborted.
+ */
+async function run<T>(
+  cmd: string,
+  args: Record<string, unknown>,
+  options: QueryOptions | undefined,
+  transaction: number | undefined
+): Promise<T> {
+  const { timeout, signal } = options ?? {};
+  let id = options?.id;
+  if (signal && id === undefined) {
+    id = `${Date.now()}-${nextQueryId++}`;
+  }
+  const abort = (): void => {
+    void cancel(id as string);
+  };
+  signal?.addEventListener("abort", abort);
+  try {
+    return await invoke<T>(cmd, {
+      ...args,
+      options: { transaction, timeout, id },
+    });
+  } finally {
+    signal?.removeEventListener("abort", abort);
+  }
+}
+
+async function cancel(queryId: string): Promise<boolean> {
+  return await invoke<boolean>("plugin:sql|cancel", { queryId });
+}
+
+async function execute(
+  db: string,
+  query: string,
+  bindValues?: unknown[],
+  options?: QueryOptions,
+  transaction?: number
+): Promise<QueryResult> {
+  const [rowsAffected, lastInsertId] = await run<[number, number]>(
+    "plugin:sql|execute",
+    {
+      db,
+      query,
+      values: bindValues ?? [],
+    },
+    options,
+    transaction
+  );
+
+  return {
+    lastInsertId,
+    rowsAffected,
+  };
+}
+
+async function select<T>(
+  db: string,
+  query: string,
+  bindValues?: unknown[],
+  options?: QueryOptions,
+  transaction?: number
+): Promise<T> {
+  return await run<T>(
+    "plugin:sql|select",
+    {
+      db,
+      query,
+      values: bindValues ?? [],
+    },
+    options,
+    transaction
+  );
+}
+
 /**
  * **Database**
  *
@@ -36,14 +285,19 @@ export default class Database {
    *
    * The path is relative to `tauri::api::path::BaseDirectory::App` and must start with `sqlite:`.
    *
+   * The pool options override the ones of the app for this database, except `readOnly`, `allowedStatements` and
+   * `deniedStatements` which can only add to its restrictions. Loading a database again with options allowing more
+   * statements than before rejects.
+   *
    * @example
    * ```ts
-   * const db = await Database.load("sqlite:test.db");
+   * const db = await Database.load("sqlite:test.db", { maxConnections: 1 });
    * ```
    */
-  static async load(path: string): Promise<Database> {
+  static async load(path: string, options?: PoolOptions): Promise<Database> {
     const _path = await invoke<string>("plugin:sql|load", {
       db: path,
+      options,
     });
 
     return new Database(_path);
@@ -82,20 +336,12 @@ export default class Database {
    * );
    * ```
    */
-  async execute(query: string, bindValues?: unknown[]): Promise<QueryResult> {
-    const [rowsAffected, lastInsertId] = await invoke<[number, number]>(
-      "plugin:sql|execute",
-      {
-        db: this.path,
-        query,
-        values: bindValues ?? [],
-      }
-    );
-
-    return {
-      lastInsertId,
-      rowsAffected,
-    };
+  async execute(
+    query: string,
+    bindValues?: unknown[],
+    options?: QueryOptions
+  ): Promise<QueryResult> {
+    return await execute(this.path, query, bindValues, options);
   }
 
   /**
@@ -103,6 +349,9 @@ export default class Database {
    *
    * Passes in a SELECT query to the database for execution.
    *
+   * The dates and times are ISO-8601 strings, in UTC for the timestamps with a time zone. The `NUMERIC` and
+   * `DECIMAL` values are strings of their exact digits, and the binary values arrays of bytes.
+   *
    * @example
    * ```ts
    * const result = await db.select(
@@ -110,14 +359,187 @@ export default class Database {
    * );
    * ```
    */
-  async select<T>(query: string, bindValues?: unknown[]): Promise<T> {
-    const result = await invoke<T>("plugin:sql|select", {
+  async select<T>(
+    query: string,
+    bindValues?: unknown[],
+    options?: QueryOptions
+  ): Promise<T> {
+    return await select<T>(this.path, query, bindValues, options);
+  }
+
+  /**
+   * **backup**
+   *
+   * Copies the SQLite database to the path while it's in use, e.g. to export the data of the user. The path must be
+   * on the fs scope.
+   *
+   * @example
+   * ```ts
+   * await db.backup("/home/me/export.db", ({ copied, total }) => setProgress(copied / total));
+   * ```
+   */
+  async backup(
+    path: string,
+    onProgress?: (progress: Progress) => void
+  ): Promise<void> {
+    await this.copy("backup", path, onProgress);
+  }
+
+  /**
+   * **restore**
+   *
+   * Replaces the SQLite database with the one at the path, without closing it. The path must be on the fs scope.
+   *
+   * Rejects on a read-only database.
+   */
+  async restore(
+    path: string,
+    onProgress?: (progress: Progress) => void
+  ): Promise<void> {
+    await this.copy("restore", path, onProgress);
+  }
+
+  private async copy(
+    operation: "backup" | "restore",
+    path: string,
+    onProgress?: (progress: Progress) => void
+  ): Promise<void> {
+    const unlisten =
+      onProgress &&
+      (await listen<Progress>("sql://progress", ({ payload }) => {
+        if (payload.db === this.path && payload.operation === operation) {
+          onProgress(payload);
+        }
+      }));
+    try {
+      await invoke(`plugin:sql|${operation}`, { db: this.path, path });
+    } finally {
+      unlisten?.();
+    }
+  }
+
+  /**
+   * **listen**
+   *
+   * Calls the handler with the notifications sent to the channel of the Postgres database with `NOTIFY`, until the
+   * returned function is called.
+   *
+   * @example
+   * ```ts
+   * const unlisten = await db.listen("todos", ({ payload }) => refresh(payload));
+   * ```
+   */
+  async listen(
+    channel: string,
+    handler: (notification: Notification) => void
+  ): Promise<UnlistenFn> {
+    const unlisten = await listen<Notification>(
+      "sql://notification",
+      ({ payload }) => {
+        if (payload.db === this.path && payload.channel === channel) {
+          handler(payload);
+        }
+      }
+    );
+    try {
+      await invoke("plugin:sql|listen", { db: this.path, channel });
+    } catch (e) {
+      unlisten();
+      throw e;
+    }
+
+    return () => {
+      unlisten();
+      void invoke("plugin:sql|unlisten", { db: this.path, channel });
+    };
+  }
+
+  /**
+   * **cancel**
+   *
+   * Aborts the query run with the id, which rejects with a `cancelled` error. Resolves to whether it was still
+   * running.
+   *
+   * The database may still finish running the query, and the transaction of a cancelled query should be rolled back.
+   *
+   * @example
+   * ```ts
+   * const rows = db.select("SELECT * from logs", [], { id: "logs", timeout: 5000 });
+   * await db.cancel("logs");
+   * ```
+   */
+  async cancel(queryId: string): Promise<boolean> {
+    return await cancel(queryId);
+  }
+
+  /**
+   * **selectStream**
+   *
+   * Passes in a SELECT query to the database, whose rows are fetched in batches of `batchSize`, 100 by default,
+   * instead of all at once.
+   *
+   * @example
+   * ```ts
+   * const cursor = await db.selectStream<Todo>("SELECT * from todos", [], 500);
+   * const unlisten = await cursor.onRows((todos) => render(todos));
+   * while (await cursor.fetchNext()) {}
+   * unlisten();
+   * ```
+   */
+  async selectStream<T>(
+    query: string,
+    bindValues?: unknown[],
+    batchSize?: number
+  ): Promise<Cursor<T>> {
+    const id = await invoke<number>("plugin:sql|select_stream", {
       db: this.path,
       query,
       values: bindValues ?? [],
+      batchSize,
     });
+    return new Cursor<T>(id);
+  }
+
+  /**
+   * **begin**
+   *
+   * Begins a transaction, for several queries to be applied at once or not at all.
+   *
+   * @example
+   * ```ts
+   * const tx = await db.begin();
+   * try {
+   *   await tx.execute("UPDATE accounts SET balance = balance - $1 WHERE id = $2", [amount, from]);
+   *   await tx.execute("UPDATE accounts SET balance = balance + $1 WHERE id = $2", [amount, to]);
+   *   await tx.commit();
+   * } catch (e) {
+   *   await tx.rollback();
+   *   throw e;
+   * }
+   * ```
+   */
+  async begin(): Promise<Transaction> {
+    const id = await invoke<number>("plugin:sql|begin", { db: this.path });
+    return new Transaction(this.path, id);
+  }
 
-    return result;
+  /**
+   * **rollbackMigrations**
+   *
+   * Reverts the migrations of the database above the version with their down migrations.
+   *
+   * Rejects unless the app enabled it with `TauriSql::js_rollback`, and on a read-only database.
+   *
+   * @example
+   * ```ts
+   * await db.rollbackMigrations(2);
+   * ```
+   */
+  async rollbackMigrations(version: number): Promise<void> {
+    await invoke("plugin:sql|rollback_migrations", {
+      db: this.path,
+      version,
+    });
   }
 
   /**
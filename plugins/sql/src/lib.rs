@@ -5,24 +5,58 @@
 #[cfg(any(
     all(feature = "sqlite", feature = "mysql"),
     all(feature = "sqlite", feature = "postgres"),
-    all(feature = "mysql", feature = "postgres")
+    all(feature = "sqlite", feature = "mssql"),
+    all(feature = "mysql", feature = "postgres"),
+    all(feature = "mysql", feature = "mssql"),
+    all(feature = "postgres", feature = "mssql")
 ))]
 compile_error!("Only one database driver can be enabled. Use `default-features = false` and set the feature flag for the driver of your choice.");
 
-#[cfg(not(any(feature = "sqlite", feature = "mysql", feature = "postgres")))]
+#[cfg(not(any(
+    feature = "sqlite",
+    feature = "mysql",
+    feature = "postgres",
+    feature = "mssql"
+)))]
 compile_error!(
     "Database driver not defined. Please set the feature flag for the driver of your choice."
 );
 
 #[cfg(any(
-    all(feature = "sqlite", not(any(feature = "mysql", feature = "postgres"))),
-    all(feature = "mysql", not(any(feature = "sqlite", feature = "postgres"))),
-    all(feature = "postgres", not(any(feature = "sqlite", feature = "mysql"))),
+    all(
+        feature = "sqlite",
+        not(any(feature = "mysql", feature = "postgres", feature = "mssql"))
+    ),
+    all(
+        feature = "mysql",
+        not(any(feature = "sqlite", feature = "postgres", feature = "mssql"))
+    ),
+    all(
+        feature = "postgres",
+        not(any(feature = "sqlite", feature = "mysql", feature = "mssql"))
+    ),
+    all(
+        feature = "mssql",
+        not(any(feature = "sqlite", feature = "mysql", feature = "postgres"))
+    ),
 ))]
 mod plugin;
 #[cfg(any(
-    all(feature = "sqlite", not(any(feature = "mysql", feature = "postgres"))),
-    all(feature = "mysql", not(any(feature = "sqlite", feature = "postgres"))),
-    all(feature = "postgres", not(any(feature = "sqlite", feature = "mysql"))),
+    all(
+        feature = "sqlite",
+        not(any(feature = "mysql", feature = "postgres", feature = "mssql"))
+    ),
+    all(
+        feature = "mysql",
+        not(any(feature = "sqlite", feature = "postgres", feature = "mssql"))
+    ),
+    all(
+        feature = "postgres",
+        not(any(feature = "sqlite", feature = "mysql", feature = "mssql"))
+    ),
+    all(
+        feature = "mssql",
+        not(any(feature = "sqlite", feature = "mysql", feature = "postgres"))
+    ),
 ))]
 pub use plugin::*;
@@ -2,27 +2,60 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
-use futures::future::BoxFuture;
-use serde::{ser::Serializer, Deserialize, Serialize};
+use futures::future::{AbortHandle, Abortable, BoxFuture};
+use futures::TryStreamExt;
+#[cfg(feature = "sqlite")]
+use serde::de;
+use serde::{ser::Serializer, Deserialize, Deserializer, Serialize};
 use serde_json::Value as JsonValue;
+#[cfg(not(feature = "mssql"))]
+use sqlx::migrate::{MigrateDatabase, Migrator};
+#[cfg(any(feature = "mysql", feature = "postgres"))]
+use sqlx::Executor;
 use sqlx::{
+    database::HasArguments,
     error::BoxDynError,
-    migrate::{
-        MigrateDatabase, Migration as SqlxMigration, MigrationSource, MigrationType, Migrator,
-    },
-    Column, Pool, Row, TypeInfo,
+    migrate::{Migration as SqlxMigration, MigrationSource, MigrationType},
+    pool::PoolOptions,
+    query::Query,
+    Connection, Database, Pool, Transaction,
 };
 use tauri::{
+    async_runtime::JoinHandle,
     command,
     plugin::{Plugin, Result as PluginResult},
-    AppHandle, Invoke, Manager, RunEvent, Runtime, State,
+    AppHandle, Invoke, Manager, PageLoadPayload, RunEvent, Runtime, State, Window, WindowEvent,
 };
-use tokio::sync::Mutex;
+use tauri_plugin_common::{
+    config, error,
+    shutdown::{self, Stage},
+    DiagnosticsExt, PluginConfig, PluginError, Report, ShutdownExt,
+};
+use tokio::sync::{mpsc, Mutex};
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+#[cfg(feature = "sqlite")]
+use std::{fs::create_dir_all, str::FromStr};
 
-use std::collections::HashMap;
+#[cfg(feature = "sqlite")]
+pub use sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
 
 #[cfg(feature = "sqlite")]
-use std::{fs::create_dir_all, path::PathBuf};
+mod backup;
+mod decode;
+mod statement;
+
+use decode::decode_row;
+pub use statement::StatementKind;
 
 #[cfg(feature = "sqlite")]
 type Db = sqlx::sqlite::Sqlite;
@@ -30,11 +63,13 @@ type Db = sqlx::sqlite::Sqlite;
 type Db = sqlx::mysql::MySql;
 #[cfg(feature = "postgres")]
 type Db = sqlx::postgres::Postgres;
+#[cfg(feature = "mssql")]
+type Db = sqlx::mssql::Mssql;
 
 #[cfg(feature = "sqlite")]
-type LastInsertId = i64;
+pub type LastInsertId = i64;
 #[cfg(not(feature = "sqlite"))]
-type LastInsertId = u64;
+pub type LastInsertId = u64;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -44,6 +79,45 @@ pub enum Error {
     Migration(#[from] sqlx::migrate::MigrateError),
     #[error("database {0} not loaded")]
     DatabaseNotLoaded(String),
+    /// The transaction was committed, rolled back, its window closed, or another window began it.
+    #[error("transaction {0} not found")]
+    TransactionNotFound(u64),
+    /// The cursor was closed, read to the end, or its window closed.
+    #[error("cursor {0} not found")]
+    CursorNotFound(u64),
+    #[error("migration {0} has no down migration")]
+    Irreversible(i64),
+    #[error("rolling back the migrations from the webview is disabled")]
+    RollbackDenied,
+    #[cfg(feature = "mssql")]
+    #[error("sqlx can't run the migrations of MSSQL databases")]
+    MigrationsUnsupported,
+    #[cfg(not(feature = "postgres"))]
+    #[error("only the Postgres databases send notifications")]
+    NotificationsUnsupported,
+    #[cfg(feature = "sqlite")]
+    #[error("path not allowed on the configured scope: {0}")]
+    PathForbidden(PathBuf),
+    #[cfg(feature = "sqlite")]
+    #[error("failed to copy the database: {0}")]
+    Backup(String),
+    #[cfg(not(feature = "sqlite"))]
+    #[error("only the SQLite databases are backed up")]
+    BackupUnsupported,
+    #[error("the query timed out after {0:?}")]
+    Timeout(Duration),
+    /// The query was aborted with the `cancel` command.
+    #[error("the query was cancelled")]
+    Cancelled,
+    /// The read-only mode or the statement lists of the database don't allow the statement.
+    #[error("{0} statements are not allowed on this database")]
+    StatementDenied(StatementKind),
+    /// The frontend can't restore or roll back the migrations of a read-only database.
+    #[error("database {0} is read-only")]
+    ReadOnly(String),
+    /// The frontend loaded the database again with options allowing more statements than before.
+    #[error("the options would relax the statement policy of the loaded database {0}")]
+    PolicyRelaxed(String),
 }
 
 impl Serialize for Error {
@@ -51,7 +125,42 @@ impl Serialize for Error {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "sql";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Sql(..) => "sql",
+            Self::Migration(..) => "migration",
+            Self::DatabaseNotLoaded(..) => "database_not_loaded",
+            Self::TransactionNotFound(..) => "transaction_not_found",
+            Self::CursorNotFound(..) => "cursor_not_found",
+            Self::Irreversible(..) => "irreversible",
+            Self::RollbackDenied => "rollback_denied",
+            #[cfg(feature = "mssql")]
+            Self::MigrationsUnsupported => "migrations_unsupported",
+            #[cfg(not(feature = "postgres"))]
+            Self::NotificationsUnsupported => "notifications_unsupported",
+            #[cfg(feature = "sqlite")]
+            Self::PathForbidden(..) => "path_forbidden",
+            #[cfg(feature = "sqlite")]
+            Self::Backup(..) => "backup",
+            #[cfg(not(feature = "sqlite"))]
+            Self::BackupUnsupported => "backup_unsupported",
+            Self::Timeout(..) => "timeout",
+            Self::Cancelled => "cancelled",
+            Self::StatementDenied(..) => "statement_denied",
+            Self::ReadOnly(..) => "read_only",
+            Self::PolicyRelaxed(..) => "policy_relaxed",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        matches!(self, Self::Timeout(..))
     }
 }
 
@@ -67,6 +176,16 @@ fn app_path<R: Runtime>(app: &AppHandle<R>) -> PathBuf {
         .expect("No App path was found!")
 }
 
+#[cfg(feature = "sqlite")]
+/// The file of the SQLite database in the App's "app_path"
+fn db_path<R: Runtime>(app: &AppHandle<R>, db: &str) -> PathBuf {
+    app_path(app).join(
+        db.split_once(':')
+            .expect("Couldn't parse the connection string for DB!")
+            .1,
+    )
+}
+
 #[cfg(feature = "sqlite")]
 /// Maps the user supplied DB connection string to a connection string
 /// with a fully qualified file path to the App's designed "app_path"
@@ -89,17 +208,770 @@ fn path_mapper(mut app_path: PathBuf, connection_string: &str) -> String {
 #[derive(Default)]
 struct DbInstances(Mutex<HashMap<String, Pool<Db>>>);
 
-struct Migrations(Mutex<HashMap<String, MigrationList>>);
+/// A database of the plugin, to run queries from Rust on the pool shared with the frontend.
+pub struct DbPool<R: Runtime> {
+    app: AppHandle<R>,
+    db: String,
+}
+
+impl<R: Runtime> DbPool<R> {
+    /// Loads the database as `Database.load` does from the frontend, applying its migrations.
+    pub async fn load(&self, options: Option<PoolConfig>) -> Result<()> {
+        let config = self.app.state::<PoolConfigs>().get(&self.db, options);
+        self.load_config(config).await
+    }
+
+    async fn load_config(&self, config: PoolConfig) -> Result<()> {
+        #[cfg(feature = "sqlite")]
+        let fqdb = path_mapper(app_path(&self.app), &self.db);
+        #[cfg(not(feature = "sqlite"))]
+        let fqdb = self.db.clone();
+
+        #[cfg(feature = "sqlite")]
+        create_dir_all(app_path(&self.app)).expect("Problem creating App directory!");
+
+        let migrations = self.app.state::<Migrations>();
+        let migrations = migrations.lists.lock().await.get(&self.db).cloned();
+        let pool = connect(&fqdb, &config, migrations).await?;
+
+        self.app
+            .state::<DbInstances>()
+            .0
+            .lock()
+            .await
+            .insert(self.db.clone(), pool);
+        self.app.state::<Queries>().loaded(&self.db, &config).await;
+        Ok(())
+    }
+
+    /// The pool of the database, e.g. to run queries with sqlx directly.
+    pub async fn pool(&self) -> Result<Pool<Db>> {
+        let pool = self
+            .app
+            .state::<DbInstances>()
+            .0
+            .lock()
+            .await
+            .get(&self.db)
+            .cloned();
+        pool.ok_or_else(|| Error::DatabaseNotLoaded(self.db.clone()))
+    }
+
+    /// Runs the query with the values bound to its parameters, as `Database.execute`.
+    pub async fn execute(
+        &self,
+        query: &str,
+        values: Vec<JsonValue>,
+    ) -> Result<(u64, LastInsertId)> {
+        let pool = self.pool().await?;
+        self.app.state::<Queries>().record(&self.db, query).await;
+        let result = bind(query, values).execute(&pool).await?;
+        Ok(executed(result))
+    }
+
+    /// The rows of the query with the values bound to its parameters, decoded as for `Database.select`.
+    pub async fn select(
+        &self,
+        query: &str,
+        values: Vec<JsonValue>,
+    ) -> Result<Vec<HashMap<String, JsonValue>>> {
+        let pool = self.pool().await?;
+        self.app.state::<Queries>().record(&self.db, query).await;
+        let rows = bind(query, values).fetch_all(&pool).await?;
+        Ok(rows.iter().map(decode_row).collect())
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    /// The database of the path or url, failing with [`Error::DatabaseNotLoaded`] until it's preloaded or loaded.
+    fn db(&self, db: &str) -> DbPool<R>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn db(&self, db: &str) -> DbPool<R> {
+        DbPool {
+            app: self.app_handle(),
+            db: db.into(),
+        }
+    }
+}
+
+/// How the connections of a database are pooled and opened, with the sqlx defaults for the options left unset.
+///
+/// From the frontend, the timeouts are in milliseconds and the SQLite modes are their names, e.g. `"WAL"`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PoolConfig {
+    max_connections: Option<u32>,
+    min_connections: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_millis")]
+    acquire_timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_millis")]
+    idle_timeout: Option<Duration>,
+    statement_cache_capacity: Option<usize>,
+    read_only: Option<bool>,
+    allowed_statements: Option<Vec<StatementKind>>,
+    denied_statements: Option<Vec<StatementKind>>,
+    #[cfg(feature = "sqlite")]
+    #[serde(default, deserialize_with = "deserialize_from_str")]
+    journal_mode: Option<SqliteJournalMode>,
+    #[cfg(feature = "sqlite")]
+    #[serde(default, deserialize_with = "deserialize_millis")]
+    busy_timeout: Option<Duration>,
+    #[cfg(feature = "sqlite")]
+    foreign_keys: Option<bool>,
+    #[cfg(feature = "sqlite")]
+    #[serde(default, deserialize_with = "deserialize_from_str")]
+    synchronous: Option<SqliteSynchronous>,
+}
+
+impl PoolConfig {
+    /// The most connections opened at once, e.g. `1` for SQLite to serialize the writes.
+    #[must_use]
+    pub fn max_connections(mut self, max_connections: u32) -> Self {
+        self.max_connections.replace(max_connections);
+        self
+    }
+
+    /// The connections kept open even when they're idle.
+    #[must_use]
+    pub fn min_connections(mut self, min_connections: u32) -> Self {
+        self.min_connections.replace(min_connections);
+        self
+    }
+
+    /// How long a query waits for a connection before it fails.
+    #[must_use]
+    pub fn acquire_timeout(mut self, acquire_timeout: Duration) -> Self {
+        self.acquire_timeout.replace(acquire_timeout);
+        self
+    }
+
+    /// How long a connection is idle before it's closed, above the minimum connections.
+    #[must_use]
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout.replace(idle_timeout);
+        self
+    }
+
+    /// The prepared statements each connection keeps, dropping the least recently used ones above it. `100` by
+    /// default, `0` to prepare the queries every time.
+    ///
+    /// Ignored by MSSQL, whose connections don't cache the statements.
+    #[must_use]
+    pub fn statement_cache_capacity(mut self, statement_cache_capacity: usize) -> Self {
+        self.statement_cache_capacity
+            .replace(statement_cache_capacity);
+        self
+    }
+
+    /// Only lets the frontend run [`StatementKind::Select`] statements, and opens the connections read-only.
+    ///
+    /// The frontend can't restore a backup over the database or roll back its migrations either.
+    ///
+    /// The Rust queries of [`ManagerExt::db`] share the connections, so they can't write either, except on MSSQL
+    /// where only the frontend is checked. The migrations of a read-only database fail.
+    #[must_use]
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only.replace(read_only);
+        self
+    }
+
+    /// The only kinds of statements the frontend can run, checked before the queries reach the driver.
+    #[must_use]
+    pub fn allowed_statements(mut self, kinds: impl IntoIterator<Item = StatementKind>) -> Self {
+        self.allowed_statements.replace(kinds.into_iter().collect());
+        self
+    }
+
+    /// The kinds of statements the frontend can't run, e.g. [`StatementKind::Ddl`] and [`StatementKind::Attach`].
+    #[must_use]
+    pub fn denied_statements(mut self, kinds: impl IntoIterator<Item = StatementKind>) -> Self {
+        self.denied_statements.replace(kinds.into_iter().collect());
+        self
+    }
+
+    /// How the SQLite database journals its writes, e.g. [`SqliteJournalMode::Wal`] so the reads don't wait for them.
+    /// The mode is kept by the database file, `DELETE` for a new one.
+    #[cfg(feature = "sqlite")]
+    #[must_use]
+    pub fn journal_mode(mut self, journal_mode: SqliteJournalMode) -> Self {
+        self.journal_mode.replace(journal_mode);
+        self
+    }
+
+    /// How long a query waits for the SQLite database to be unlocked before it fails, 5 seconds by default.
+    #[cfg(feature = "sqlite")]
+    #[must_use]
+    pub fn busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout.replace(busy_timeout);
+        self
+    }
+
+    /// Whether SQLite enforces the foreign key constraints, `true` by default.
+    #[cfg(feature = "sqlite")]
+    #[must_use]
+    pub fn foreign_keys(mut self, foreign_keys: bool) -> Self {
+        self.foreign_keys.replace(foreign_keys);
+        self
+    }
+
+    /// How often SQLite syncs the writes to the disk, [`SqliteSynchronous::Full`] by default. `Normal` is safe and
+    /// faster with the WAL journal mode.
+    #[cfg(feature = "sqlite")]
+    #[must_use]
+    pub fn synchronous(mut self, synchronous: SqliteSynchronous) -> Self {
+        self.synchronous.replace(synchronous);
+        self
+    }
+
+    /// The options of the frontend set here over the ones of `config`, only tightening its read-only mode and
+    /// statement lists: the allowed statements are intersected and the denied ones merged.
+    fn tightening(self, config: &Self) -> Self {
+        let read_only = Some(self.read_only == Some(true) || config.read_only == Some(true));
+        let allowed_statements = match (&self.allowed_statements, &config.allowed_statements) {
+            (Some(allowed), Some(app_allowed)) => Some(
+                allowed
+                    .iter()
+                    .filter(|kind| app_allowed.contains(kind))
+                    .copied()
+                    .collect(),
+            ),
+            (allowed, app_allowed) => allowed.clone().or_else(|| app_allowed.clone()),
+        };
+        let mut denied_statements = config.denied_statements.clone().unwrap_or_default();
+        for kind in self.denied_statements.iter().flatten() {
+            if !denied_statements.contains(kind) {
+                denied_statements.push(*kind);
+            }
+        }
+        Self {
+            read_only,
+            allowed_statements,
+            denied_statements: Some(denied_statements),
+            ..self.or(config)
+        }
+    }
+
+    /// The options set here, then the ones set in `defaults`.
+    fn or(self, defaults: &Self) -> Self {
+        Self {
+            max_connections: self.max_connections.or(defaults.max_connections),
+            min_connections: self.min_connections.or(defaults.min_connections),
+            acquire_timeout: self.acquire_timeout.or(defaults.acquire_timeout),
+            idle_timeout: self.idle_timeout.or(defaults.idle_timeout),
+            statement_cache_capacity: self
+                .statement_cache_capacity
+                .or(defaults.statement_cache_capacity),
+            read_only: self.read_only.or(defaults.read_only),
+            allowed_statements: self
+                .allowed_statements
+                .or_else(|| defaults.allowed_statements.clone()),
+            denied_statements: self
+                .denied_statements
+                .or_else(|| defaults.denied_statements.clone()),
+            #[cfg(feature = "sqlite")]
+            journal_mode: self.journal_mode.or(defaults.journal_mode),
+            #[cfg(feature = "sqlite")]
+            busy_timeout: self.busy_timeout.or(defaults.busy_timeout),
+            #[cfg(feature = "sqlite")]
+            foreign_keys: self.foreign_keys.or(defaults.foreign_keys),
+            #[cfg(feature = "sqlite")]
+            synchronous: self.synchronous.or(defaults.synchronous),
+        }
+    }
+
+    fn options(&self) -> PoolOptions<Db> {
+        let mut options = PoolOptions::new();
+        if let Some(max_connections) = self.max_connections {
+            options = options.max_connections(max_connections);
+        }
+        if let Some(min_connections) = self.min_connections {
+            options = options.min_connections(min_connections);
+        }
+        if let Some(acquire_timeout) = self.acquire_timeout {
+            options = options.acquire_timeout(acquire_timeout);
+        }
+        if let Some(idle_timeout) = self.idle_timeout {
+            options = options.idle_timeout(idle_timeout);
+        }
+        #[cfg(any(feature = "mysql", feature = "postgres"))]
+        if self.read_only == Some(true) {
+            options = options.after_connect(|connection: &mut <Db as Database>::Connection, _| {
+                Box::pin(async move {
+                    #[cfg(feature = "mysql")]
+                    connection
+                        .execute("SET SESSION TRANSACTION READ ONLY")
+                        .await?;
+                    #[cfg(feature = "postgres")]
+                    connection
+                        .execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY")
+                        .await?;
+                    Ok(())
+                })
+            });
+        }
+        options
+    }
+
+    /// The options of each connection to the database.
+    fn connect_options(
+        &self,
+        fqdb: &str,
+    ) -> Result<<<Db as Database>::Connection as Connection>::Options> {
+        let options = fqdb.parse::<<<Db as Database>::Connection as Connection>::Options>()?;
+        #[cfg(not(feature = "mssql"))]
+        let options = match self.statement_cache_capacity {
+            Some(capacity) => options.statement_cache_capacity(capacity),
+            None => options,
+        };
+        #[cfg(feature = "sqlite")]
+        let options = {
+            let mut options = options.read_only(self.read_only == Some(true));
+            if let Some(journal_mode) = self.journal_mode {
+                options = options.journal_mode(journal_mode);
+            }
+            if let Some(busy_timeout) = self.busy_timeout {
+                options = options.busy_timeout(busy_timeout);
+            }
+            if let Some(foreign_keys) = self.foreign_keys {
+                options = options.foreign_keys(foreign_keys);
+            }
+            if let Some(synchronous) = self.synchronous {
+                options = options.synchronous(synchronous);
+            }
+            options
+        };
+        Ok(options)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn deserialize_from_str<'de, D: Deserializer<'de>, T: FromStr>(
+    deserializer: D,
+) -> std::result::Result<Option<T>, D::Error>
+where
+    T::Err: std::fmt::Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| s.parse().map_err(de::Error::custom))
+        .transpose()
+}
+
+fn deserialize_millis<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<Duration>, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+}
+
+/// The pool configs of [`TauriSql::pool_config`] and [`TauriSql::database_pool_config`].
+#[derive(Default)]
+struct PoolConfigs {
+    defaults: PoolConfig,
+    databases: HashMap<String, PoolConfig>,
+}
+
+impl PoolConfigs {
+    /// The config of the database, overridden by the options passed to `load` from Rust.
+    fn get(&self, db: &str, options: Option<PoolConfig>) -> PoolConfig {
+        let config = match self.databases.get(db) {
+            Some(config) => config.clone().or(&self.defaults),
+            None => self.defaults.clone(),
+        };
+        match options {
+            Some(options) => options.or(&config),
+            None => config,
+        }
+    }
+
+    /// The config of the database with the options passed to `load` from the frontend, which can't relax its
+    /// statement policy.
+    fn get_for_frontend(&self, db: &str, options: Option<PoolConfig>) -> PoolConfig {
+        let config = self.get(db, None);
+        match options {
+            Some(options) => options.tightening(&config),
+            None => config,
+        }
+    }
+}
+
+/// Counts the hits of the statement caches of the connections of a database, which sqlx doesn't report.
+///
+/// It follows a single cache of the same capacity for all the connections, so the counts are an estimate when the
+/// pool has more than one.
+struct StatementCache {
+    capacity: usize,
+    uses: u64,
+    /// When each cached query was last run, in uses.
+    last_used: HashMap<String, u64>,
+    hits: u64,
+    misses: u64,
+}
+
+impl StatementCache {
+    fn new(config: &PoolConfig) -> Self {
+        #[cfg(not(feature = "mssql"))]
+        let capacity = config.statement_cache_capacity.unwrap_or(100);
+        #[cfg(feature = "mssql")]
+        let capacity = {
+            let _ = config;
+            0
+        };
+        Self {
+            capacity,
+            uses: 0,
+            last_used: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn record(&mut self, query: &str) {
+        if self.capacity == 0 {
+            self.misses += 1;
+            return;
+        }
+        self.uses += 1;
+        if self.last_used.insert(query.into(), self.uses).is_some() {
+            self.hits += 1;
+            return;
+        }
+        self.misses += 1;
+        if self.last_used.len() > self.capacity {
+            let lru = self
+                .last_used
+                .iter()
+                .min_by_key(|(_, uses)| **uses)
+                .map(|(query, _)| query.clone());
+            if let Some(lru) = lru {
+                self.last_used.remove(&lru);
+            }
+        }
+    }
+}
+
+type Rows = Vec<HashMap<String, JsonValue>>;
 
+/// A query whose rows are read in batches from the frontend, holding a connection of the pool until it's closed.
+struct Cursor {
+    db: String,
+    /// The window that opened it, the cursor is closed once it closes or reloads.
+    window: String,
+    /// The batches read ahead by the task running the query, which stops once it's dropped.
+    batches: Arc<Mutex<mpsc::Receiver<Result<Rows>>>>,
+}
+
+#[derive(Default)]
+struct Cursors {
+    next_id: AtomicU64,
+    open: Mutex<HashMap<u64, Cursor>>,
+}
+
+impl Cursors {
+    /// Closes the cursors matching the predicate.
+    async fn close_where(&self, f: impl Fn(&Cursor) -> bool) {
+        self.open.lock().await.retain(|_, cursor| !f(cursor));
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+struct RowsPayload {
+    #[cfg_attr(test, ts(type = "number"))]
+    cursor: u64,
+    #[cfg_attr(test, ts(type = "Array<Record<string, unknown>>"))]
+    rows: Rows,
+}
+
+/// The tasks forwarding the notifications of a channel of a database to a window, by window, database and channel.
+#[derive(Default)]
+struct Listeners(Mutex<HashMap<(String, String, String), JoinHandle<()>>>);
+
+impl Listeners {
+    /// Stops the listeners of the window, database and channel matching the predicate.
+    async fn close_where(&self, f: impl Fn(&str, &str, &str) -> bool) {
+        self.0.lock().await.retain(|(window, db, channel), task| {
+            let close = f(window, db, channel);
+            if close {
+                task.abort();
+            }
+            !close
+        });
+    }
+}
+
+#[cfg(feature = "postgres")]
+#[derive(Clone, Serialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export, export_to = "guest-js/types/", rename = "Notification")
+)]
+struct NotificationPayload {
+    /// The path of the database.
+    db: String,
+    channel: String,
+    payload: String,
+}
+
+#[derive(Default)]
+struct Queries {
+    /// The running queries given an id, to be cancelled with it.
+    running: Mutex<HashMap<String, AbortHandle>>,
+    /// The statement caches of the loaded databases, reset when they're loaded again.
+    statements: Mutex<HashMap<String, StatementCache>>,
+    /// The statements the frontend can run on the loaded databases.
+    policies: Mutex<HashMap<String, StatementPolicy>>,
+}
+
+impl Queries {
+    /// Resets the statement cache and the statement policy of the database once it's loaded.
+    async fn loaded(&self, db: &str, config: &PoolConfig) {
+        self.statements
+            .lock()
+            .await
+            .insert(db.into(), StatementCache::new(config));
+        self.policies
+            .lock()
+            .await
+            .insert(db.into(), StatementPolicy::new(config));
+    }
+
+    async fn record(&self, db: &str, query: &str) {
+        if let Some(cache) = self.statements.lock().await.get_mut(db) {
+            cache.record(query);
+        }
+    }
+
+    /// Records the query of the frontend once its statements are allowed on the database.
+    async fn check(&self, db: &str, query: &str) -> Result<()> {
+        if let Some(policy) = self.policies.lock().await.get(db) {
+            policy.check(query)?;
+        }
+        self.record(db, query).await;
+        Ok(())
+    }
+
+    /// Fails with [`Error::PolicyRelaxed`] when the policy allows more than the one of the loaded database.
+    async fn check_not_relaxed(&self, db: &str, policy: &StatementPolicy) -> Result<()> {
+        match self.policies.lock().await.get(db) {
+            Some(current) if !policy.within(current) => Err(Error::PolicyRelaxed(db.into())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails with [`Error::ReadOnly`] for the commands of the frontend writing a read-only database by other means
+    /// than its queries.
+    async fn check_writable(&self, db: &str) -> Result<()> {
+        match self.policies.lock().await.get(db) {
+            Some(policy) if policy.read_only => Err(Error::ReadOnly(db.into())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The statements the frontend can run on a database, from its [`PoolConfig`].
+struct StatementPolicy {
+    read_only: bool,
+    allowed: Option<Vec<StatementKind>>,
+    denied: Vec<StatementKind>,
+}
+
+impl StatementPolicy {
+    fn new(config: &PoolConfig) -> Self {
+        Self {
+            read_only: config.read_only == Some(true),
+            allowed: config.allowed_statements.clone(),
+            denied: config.denied_statements.clone().unwrap_or_default(),
+        }
+    }
+
+    fn check(&self, query: &str) -> Result<()> {
+        if !self.read_only && self.allowed.is_none() && self.denied.is_empty() {
+            return Ok(());
+        }
+        for kind in statement::kinds(query) {
+            if !self.allows(kind) {
+                return Err(Error::StatementDenied(kind));
+            }
+        }
+        Ok(())
+    }
+
+    fn allows(&self, kind: StatementKind) -> bool {
+        (!self.read_only || kind == StatementKind::Select)
+            && self.allowed.iter().all(|allowed| allowed.contains(&kind))
+            && !self.denied.contains(&kind)
+    }
+
+    /// Whether the policy allows nothing more than `other`.
+    fn within(&self, other: &Self) -> bool {
+        (self.read_only || !other.read_only)
+            && StatementKind::ALL
+                .into_iter()
+                .all(|kind| !self.allows(kind) || other.allows(kind))
+    }
+}
+
+/// The options of a query.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryOptions {
+    /// The transaction to run the query in.
+    transaction: Option<u64>,
+    /// In milliseconds.
+    #[serde(default, deserialize_with = "deserialize_millis")]
+    timeout: Option<Duration>,
+    /// The id to cancel the query with.
+    id: Option<String>,
+}
+
+impl QueryOptions {
+    /// Runs the query until it completes, times out or is cancelled.
+    ///
+    /// The future of a query stopped early is dropped, but the database may still finish running it.
+    async fn run<T>(
+        self,
+        queries: &Queries,
+        query: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let (handle, registration) = AbortHandle::new_pair();
+        if let Some(id) = &self.id {
+            queries.running.lock().await.insert(id.clone(), handle);
+        }
+        let query = Abortable::new(query, registration);
+        let result = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, query)
+                .await
+                .unwrap_or(Ok(Err(Error::Timeout(timeout)))),
+            None => query.await,
+        };
+        if let Some(id) = &self.id {
+            queries.running.lock().await.remove(id);
+        }
+        result.unwrap_or(Err(Error::Cancelled))
+    }
+}
+
+/// The migrations of the databases, managed by the plugin as `app.state::<Migrations>()`.
+pub struct Migrations {
+    lists: Mutex<HashMap<String, MigrationList>>,
+    js_rollback: bool,
+}
+
+impl Migrations {
+    /// Reverts the migrations of the database applied above the version with their `Down` migrations, newest first.
+    ///
+    /// Fails with [`Error::Irreversible`] before reverting anything when one of them has no `Down` migration.
+    pub async fn rollback_to(&self, pool: &Pool<Db>, db: &str, version: i64) -> Result<()> {
+        let list = self.lists.lock().await.get(db).cloned().unwrap_or_default();
+        let irreversible = list.0.iter().find(|up| {
+            matches!(up.kind, MigrationKind::Up)
+                && up.version > version
+                && !list.0.iter().any(|down| {
+                    matches!(down.kind, MigrationKind::Down) && down.version == up.version
+                })
+        });
+        if let Some(migration) = irreversible {
+            return Err(Error::Irreversible(migration.version));
+        }
+
+        #[cfg(not(feature = "mssql"))]
+        {
+            let migrator = Migrator::new(list).await?;
+            migrator.undo(pool, version).await?;
+            Ok(())
+        }
+        #[cfg(feature = "mssql")]
+        {
+            let _ = pool;
+            Err(Error::MigrationsUnsupported)
+        }
+    }
+}
+
+/// A transaction begun from the frontend, holding a connection of the pool until it ends.
+struct OpenTransaction {
+    db: String,
+    /// The window that began it, the transaction is rolled back once it closes or reloads.
+    window: String,
+    /// Locked by the query running in it, taken once it ends.
+    transaction: Mutex<Option<Transaction<'static, Db>>>,
+}
+
+#[derive(Default)]
+struct Transactions {
+    next_id: AtomicU64,
+    open: Mutex<HashMap<u64, Arc<OpenTransaction>>>,
+}
+
+impl Transactions {
+    /// The transaction begun by the window, on the database if given.
+    ///
+    /// The map is unlocked once it returns, so a slow query only holds its own transaction.
+    async fn get(&self, id: u64, window: &str, db: Option<&str>) -> Result<Arc<OpenTransaction>> {
+        self.open
+            .lock()
+            .await
+            .get(&id)
+            .filter(|open| open.window == window && db.iter().all(|db| open.db == *db))
+            .cloned()
+            .ok_or(Error::TransactionNotFound(id))
+    }
+
+    /// Removes the transaction begun by the window, to commit or roll it back.
+    async fn end(&self, id: u64, window: &str) -> Result<Transaction<'static, Db>> {
+        let open = {
+            let mut open = self.open.lock().await;
+            match open.get(&id) {
+                Some(transaction) if transaction.window == window => open.remove(&id),
+                _ => None,
+            }
+        };
+        let open = open.ok_or(Error::TransactionNotFound(id))?;
+        // waits for the query running in it
+        let transaction = open.transaction.lock().await.take();
+        transaction.ok_or(Error::TransactionNotFound(id))
+    }
+
+    /// Rolls back the transactions matching the predicate.
+    async fn rollback_where(&self, f: impl Fn(&OpenTransaction) -> bool) {
+        let ended = {
+            let mut open = self.open.lock().await;
+            let ids = open
+                .iter()
+                .filter(|(_, transaction)| f(transaction))
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+            ids.into_iter()
+                .filter_map(|id| open.remove(&id))
+                .collect::<Vec<_>>()
+        };
+        for open in ended {
+            // dropping it rolls it back too, only later
+            if let Some(transaction) = open.transaction.lock().await.take() {
+                let _ = transaction.rollback().await;
+            }
+        }
+    }
+}
+
+/// `plugins > sql` in tauri.conf.json.
 #[derive(Default, Deserialize)]
-struct PluginConfig {
+#[serde(deny_unknown_fields)]
+struct Config {
+    /// The databases loaded at startup, with their migrations.
     #[serde(default)]
     preload: Vec<String>,
 }
 
-#[derive(Debug)]
+impl PluginConfig for Config {}
+
+#[derive(Debug, Clone, Copy)]
 pub enum MigrationKind {
+    /// Applied when the database is loaded.
     Up,
+    /// Reverts the `Up` migration of the same version in [`Migrations::rollback_to`].
     Down,
 }
 
@@ -113,7 +985,7 @@ impl From<MigrationKind> for MigrationType {
 }
 
 /// A migration definition.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Migration {
     pub version: i64,
     pub description: &'static str,
@@ -121,7 +993,7 @@ pub struct Migration {
     pub kind: MigrationKind,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 struct MigrationList(Vec<Migration>);
 
 impl MigrationSource<'static> for MigrationList {
@@ -129,15 +1001,16 @@ impl MigrationSource<'static> for MigrationList {
         Box::pin(async move {
             let mut migrations = Vec::new();
             for migration in self.0 {
-                if matches!(migration.kind, MigrationKind::Up) {
-                    migrations.push(SqlxMigration::new(
-                        migration.version,
-                        migration.description.into(),
-                        migration.kind.into(),
-                        migration.sql.into(),
-                    ));
-                }
+                // the runner only applies the up migrations, and reverts with the down ones
+                migrations.push(SqlxMigration::new(
+                    migration.version,
+                    migration.description.into(),
+                    migration.kind.into(),
+                    migration.sql.into(),
+                ));
             }
+            // reverted from the last one
+            migrations.sort_by_key(|migration| migration.version);
             Ok(migrations)
         })
     }
@@ -145,38 +1018,69 @@ impl MigrationSource<'static> for MigrationList {
 
 #[command]
 async fn load<R: Runtime>(
-    #[allow(unused_variables)] app: AppHandle<R>,
-    db_instances: State<'_, DbInstances>,
-    migrations: State<'_, Migrations>,
+    app: AppHandle<R>,
     db: String,
+    options: Option<PoolConfig>,
 ) -> Result<String> {
-    #[cfg(feature = "sqlite")]
-    let fqdb = path_mapper(app_path(&app), &db);
-    #[cfg(not(feature = "sqlite"))]
-    let fqdb = db.clone();
-
-    #[cfg(feature = "sqlite")]
-    create_dir_all(app_path(&app)).expect("Problem creating App directory!");
+    let config = app.state::<PoolConfigs>().get_for_frontend(&db, options);
+    app.state::<Queries>()
+        .check_not_relaxed(&db, &StatementPolicy::new(&config))
+        .await?;
+    app.db(&db).load_config(config).await?;
+    Ok(db)
+}
 
-    if !Db::database_exists(&fqdb).await.unwrap_or(false) {
-        Db::create_database(&fqdb).await?;
+/// Connects to the database, created if it doesn't exist yet, and applies its migrations.
+async fn connect(
+    fqdb: &str,
+    config: &PoolConfig,
+    migrations: Option<MigrationList>,
+) -> Result<Pool<Db>> {
+    // sqlx can neither create nor migrate the MSSQL databases
+    #[cfg(feature = "mssql")]
+    if migrations.is_some() {
+        return Err(Error::MigrationsUnsupported);
+    }
+    #[cfg(not(feature = "mssql"))]
+    if !Db::database_exists(fqdb).await.unwrap_or(false) {
+        Db::create_database(fqdb).await?;
     }
-    let pool = Pool::connect(&fqdb).await?;
 
-    if let Some(migrations) = migrations.0.lock().await.remove(&db) {
+    let pool = config
+        .options()
+        .connect_with(config.connect_options(fqdb)?)
+        .await?;
+    #[cfg(not(feature = "mssql"))]
+    if let Some(migrations) = migrations {
         let migrator = Migrator::new(migrations).await?;
         migrator.run(&pool).await?;
     }
-
-    db_instances.0.lock().await.insert(db.clone(), pool);
-    Ok(db)
+    Ok(pool)
 }
 
 /// Allows the database connection(s) to be closed; if no database
 /// name is passed in then _all_ database connection pools will be
 /// shut down.
+///
+/// Their open transactions are rolled back.
 #[command]
-async fn close(db_instances: State<'_, DbInstances>, db: Option<String>) -> Result<bool> {
+async fn close(
+    db_instances: State<'_, DbInstances>,
+    transactions: State<'_, Transactions>,
+    cursors: State<'_, Cursors>,
+    listeners: State<'_, Listeners>,
+    db: Option<String>,
+) -> Result<bool> {
+    // closing a pool waits for the connections held by the transactions and the cursors
+    transactions
+        .rollback_where(|transaction| db.iter().all(|db| &transaction.db == db))
+        .await;
+    cursors
+        .close_where(|cursor| db.iter().all(|db| &cursor.db == db))
+        .await;
+    listeners
+        .close_where(|_, listened, _| db.iter().all(|db| listened == db))
+        .await;
     let mut instances = db_instances.0.lock().await;
 
     let pools = if let Some(db) = db {
@@ -195,114 +1099,476 @@ async fn close(db_instances: State<'_, DbInstances>, db: Option<String>) -> Resu
     Ok(true)
 }
 
-/// Execute a command against the database
+/// Begins a transaction on the database, used by passing its id to [`execute`] and [`select`] until it's committed or
+/// rolled back.
 #[command]
-async fn execute(
+async fn begin<R: Runtime>(
+    window: Window<R>,
     db_instances: State<'_, DbInstances>,
+    transactions: State<'_, Transactions>,
     db: String,
-    query: String,
-    values: Vec<JsonValue>,
-) -> Result<(u64, LastInsertId)> {
-    let mut instances = db_instances.0.lock().await;
+) -> Result<u64> {
+    let transaction = {
+        let instances = db_instances.0.lock().await;
+        let pool = instances
+            .get(&db)
+            .ok_or_else(|| Error::DatabaseNotLoaded(db.clone()))?;
+        pool.begin().await?
+    };
+    let id = transactions.next_id.fetch_add(1, Ordering::Relaxed);
+    transactions.open.lock().await.insert(
+        id,
+        Arc::new(OpenTransaction {
+            db,
+            window: window.label().into(),
+            transaction: Mutex::new(Some(transaction)),
+        }),
+    );
+    Ok(id)
+}
+
+/// Commits the transaction, only from the window that began it.
+#[command]
+async fn commit<R: Runtime>(
+    window: Window<R>,
+    transactions: State<'_, Transactions>,
+    transaction: u64,
+) -> Result<()> {
+    let transaction = transactions.end(transaction, window.label()).await?;
+    transaction.commit().await?;
+    Ok(())
+}
 
-    let db = instances.get_mut(&db).ok_or(Error::DatabaseNotLoaded(db))?;
-    let mut query = sqlx::query(&query);
+/// Rolls back the transaction, only from the window that began it.
+#[command]
+async fn rollback<R: Runtime>(
+    window: Window<R>,
+    transactions: State<'_, Transactions>,
+    transaction: u64,
+) -> Result<()> {
+    let transaction = transactions.end(transaction, window.label()).await?;
+    transaction.rollback().await?;
+    Ok(())
+}
+
+fn bind(query: &str, values: Vec<JsonValue>) -> Query<'_, Db, <Db as HasArguments<'_>>::Arguments> {
+    let mut query = sqlx::query(query);
     for value in values {
+        // sqlx can't bind JSON to MSSQL
+        #[cfg(feature = "mssql")]
+        {
+            query = match value {
+                JsonValue::Null => query.bind(None::<String>),
+                JsonValue::Bool(b) => query.bind(b),
+                JsonValue::Number(n) => match n.as_i64() {
+                    Some(n) => query.bind(n),
+                    None => query.bind(n.as_f64()),
+                },
+                JsonValue::String(s) => query.bind(s),
+                value => query.bind(value.to_string()),
+            };
+        }
+        #[cfg(not(feature = "mssql"))]
         if value.is_string() {
             query = query.bind(value.as_str().unwrap().to_owned())
         } else {
             query = query.bind(value);
         }
     }
-    let result = query.execute(&*db).await?;
+    query
+}
+
+/// Execute a command against the database, in the transaction of the options if there's one
+#[command]
+async fn execute<R: Runtime>(
+    window: Window<R>,
+    db_instances: State<'_, DbInstances>,
+    queries: State<'_, Queries>,
+    db: String,
+    query: String,
+    values: Vec<JsonValue>,
+    options: Option<QueryOptions>,
+) -> Result<(u64, LastInsertId)> {
+    queries.check(&db, &query).await?;
+    let query = bind(&query, values);
+    let options = options.unwrap_or_default();
+    let transaction = options.transaction;
+    let result = options
+        .run(&queries, async {
+            let result = match transaction {
+                Some(id) => {
+                    let transactions = window.state::<Transactions>();
+                    let open = transactions.get(id, window.label(), Some(&db)).await?;
+                    let mut transaction = open.transaction.lock().await;
+                    let transaction = transaction.as_mut().ok_or(Error::TransactionNotFound(id))?;
+                    query.execute(transaction).await?
+                }
+                None => {
+                    let pool = db_instances.0.lock().await.get(&db).cloned();
+                    let pool = pool.ok_or(Error::DatabaseNotLoaded(db))?;
+                    query.execute(&pool).await?
+                }
+            };
+            Ok::<_, Error>(result)
+        })
+        .await?;
+    Ok(executed(result))
+}
+
+/// The rows affected by the query, and the id of the row it inserted last.
+fn executed(result: <Db as Database>::QueryResult) -> (u64, LastInsertId) {
     #[cfg(feature = "sqlite")]
-    let r = Ok((result.rows_affected(), result.last_insert_rowid()));
+    let r = (result.rows_affected(), result.last_insert_rowid());
     #[cfg(feature = "mysql")]
-    let r = Ok((result.rows_affected(), result.last_insert_id()));
-    #[cfg(feature = "postgres")]
-    let r = Ok((result.rows_affected(), 0));
+    let r = (result.rows_affected(), result.last_insert_id());
+    #[cfg(any(feature = "postgres", feature = "mssql"))]
+    let r = (result.rows_affected(), 0);
     r
 }
 
 #[command]
-async fn select(
+async fn select<R: Runtime>(
+    window: Window<R>,
     db_instances: State<'_, DbInstances>,
+    queries: State<'_, Queries>,
     db: String,
     query: String,
     values: Vec<JsonValue>,
+    options: Option<QueryOptions>,
 ) -> Result<Vec<HashMap<String, JsonValue>>> {
-    let mut instances = db_instances.0.lock().await;
-    let db = instances.get_mut(&db).ok_or(Error::DatabaseNotLoaded(db))?;
-    let mut query = sqlx::query(&query);
-    for value in values {
-        if value.is_string() {
-            query = query.bind(value.as_str().unwrap().to_owned())
-        } else {
-            query = query.bind(value);
+    queries.check(&db, &query).await?;
+    let query = bind(&query, values);
+    let options = options.unwrap_or_default();
+    let transaction = options.transaction;
+    let rows = options
+        .run(&queries, async {
+            let rows = match transaction {
+                Some(id) => {
+                    let transactions = window.state::<Transactions>();
+                    let open = transactions.get(id, window.label(), Some(&db)).await?;
+                    let mut transaction = open.transaction.lock().await;
+                    let transaction = transaction.as_mut().ok_or(Error::TransactionNotFound(id))?;
+                    query.fetch_all(transaction).await?
+                }
+                None => {
+                    let pool = db_instances.0.lock().await.get(&db).cloned();
+                    let pool = pool.ok_or(Error::DatabaseNotLoaded(db))?;
+                    query.fetch_all(&pool).await?
+                }
+            };
+            Ok::<_, Error>(rows)
+        })
+        .await?;
+    Ok(rows.iter().map(decode_row).collect())
+}
+
+/// Forwards the notifications of the channel of the Postgres database to the window as `sql://notification` events,
+/// until [`unlisten`] or the window closes or reloads.
+#[command]
+async fn listen<R: Runtime>(
+    window: Window<R>,
+    db_instances: State<'_, DbInstances>,
+    listeners: State<'_, Listeners>,
+    db: String,
+    channel: String,
+) -> Result<()> {
+    #[cfg(feature = "postgres")]
+    {
+        let key = (window.label().to_string(), db.clone(), channel.clone());
+        let mut listeners = listeners.0.lock().await;
+        if listeners.contains_key(&key) {
+            return Ok(());
         }
-    }
-    let rows = query.fetch_all(&*db).await?;
-    let mut values = Vec::new();
-    for row in rows {
-        let mut value = HashMap::default();
-        for (i, column) in row.columns().iter().enumerate() {
-            let info = column.type_info();
-            let v = if info.is_null() {
-                JsonValue::Null
-            } else {
-                match info.name() {
-                    "VARCHAR" | "STRING" | "TEXT" | "DATETIME" => {
-                        if let Ok(s) = row.try_get(i) {
-                            JsonValue::String(s)
-                        } else {
-                            JsonValue::Null
-                        }
-                    }
-                    "BOOL" | "BOOLEAN" => {
-                        if let Ok(b) = row.try_get(i) {
-                            JsonValue::Bool(b)
-                        } else {
-                            let x: String = row.get(i);
-                            JsonValue::Bool(x.to_lowercase() == "true")
-                        }
+
+        let pool = db_instances.0.lock().await.get(&db).cloned();
+        let pool = pool.ok_or_else(|| Error::DatabaseNotLoaded(db.clone()))?;
+        let mut listener = sqlx::postgres::PgListener::connect_with(&pool).await?;
+        listener.listen(&channel).await?;
+        // on its own connection, so the notifications arrive while the pool runs the queries
+        let task = tauri::async_runtime::spawn(async move {
+            loop {
+                match listener.recv().await {
+                    Ok(notification) => {
+                        let _ = window.emit(
+                            "sql://notification",
+                            NotificationPayload {
+                                db: db.clone(),
+                                channel: notification.channel().into(),
+                                payload: notification.payload().into(),
+                            },
+                        );
                     }
-                    "INT" | "NUMBER" | "INTEGER" | "BIGINT" | "INT8" => {
-                        if let Ok(n) = row.try_get::<i64, usize>(i) {
-                            JsonValue::Number(n.into())
-                        } else {
-                            JsonValue::Null
-                        }
+                    Err(e) => {
+                        log::warn!(
+                            "stopped listening to the channel `{}` of {}: {}",
+                            channel,
+                            db,
+                            e
+                        );
+                        break;
                     }
-                    "REAL" => {
-                        if let Ok(n) = row.try_get::<f64, usize>(i) {
-                            JsonValue::from(n)
-                        } else {
-                            JsonValue::Null
-                        }
+                }
+            }
+        });
+        listeners.insert(key, task);
+        Ok(())
+    }
+    #[cfg(not(feature = "postgres"))]
+    {
+        let _ = (window, db_instances, listeners, db, channel);
+        Err(Error::NotificationsUnsupported)
+    }
+}
+
+/// Stops forwarding the notifications of the channel to the window. Resolves to whether it was listened to.
+#[command]
+async fn unlisten<R: Runtime>(
+    window: Window<R>,
+    listeners: State<'_, Listeners>,
+    db: String,
+    channel: String,
+) -> Result<bool> {
+    let key = (window.label().to_string(), db, channel);
+    let task = listeners.0.lock().await.remove(&key);
+    Ok(task.map(|task| task.abort()).is_some())
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Clone, Serialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export, export_to = "guest-js/types/", rename = "Progress")
+)]
+struct ProgressPayload {
+    /// The path of the database.
+    db: String,
+    /// `backup` or `restore`.
+    #[cfg_attr(test, ts(type = r#""backup" | "restore""#))]
+    operation: &'static str,
+    /// The pages copied.
+    copied: u32,
+    total: u32,
+}
+
+/// Copies the loaded SQLite database to the path while it's in use, emitting the progress to the window as
+/// `sql://progress` events.
+#[command]
+async fn backup<R: Runtime>(
+    window: Window<R>,
+    db_instances: State<'_, DbInstances>,
+    db: String,
+    path: PathBuf,
+) -> Result<()> {
+    #[cfg(feature = "sqlite")]
+    {
+        copy_database(window, db_instances, db, "backup", path).await
+    }
+    #[cfg(not(feature = "sqlite"))]
+    {
+        let _ = (window, db_instances, db, path);
+        Err(Error::BackupUnsupported)
+    }
+}
+
+/// Replaces the loaded SQLite database with the one at the path, without closing its pool, emitting the progress to
+/// the window as `sql://progress` events.
+#[command]
+async fn restore<R: Runtime>(
+    window: Window<R>,
+    db_instances: State<'_, DbInstances>,
+    db: String,
+    path: PathBuf,
+) -> Result<()> {
+    #[cfg(feature = "sqlite")]
+    {
+        window.state::<Queries>().check_writable(&db).await?;
+        copy_database(window, db_instances, db, "restore", path).await
+    }
+    #[cfg(not(feature = "sqlite"))]
+    {
+        let _ = (window, db_instances, db, path);
+        Err(Error::BackupUnsupported)
+    }
+}
+
+/// Copies the loaded database to the path given by the webview for a backup, or the other way around for a restore,
+/// the path must be on the fs scope.
+#[cfg(feature = "sqlite")]
+async fn copy_database<R: Runtime>(
+    window: Window<R>,
+    db_instances: State<'_, DbInstances>,
+    db: String,
+    operation: &'static str,
+    path: PathBuf,
+) -> Result<()> {
+    // `db_path` panics on the connection strings not naming a file, the loaded ones all do
+    if !db_instances.0.lock().await.contains_key(&db) {
+        return Err(Error::DatabaseNotLoaded(db));
+    }
+    if !window.fs_scope().is_allowed(&path) {
+        return Err(Error::PathForbidden(path));
+    }
+    let db_file = db_path(&window.app_handle(), &db);
+    let (from, to) = if operation == "backup" {
+        (db_file, path)
+    } else {
+        (path, db_file)
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        backup::copy(&from, &to, |copied, total| {
+            let _ = window.emit(
+                "sql://progress",
+                ProgressPayload {
+                    db: db.clone(),
+                    operation,
+                    copied,
+                    total,
+                },
+            );
+        })
+    })
+    .await
+    .map_err(|e| Error::Backup(e.to_string()))?
+    .map_err(Error::Backup)
+}
+
+/// Aborts the query run with the id, failing it with [`Error::Cancelled`].
+///
+/// Resolves to whether the query was still running.
+#[command]
+async fn cancel(queries: State<'_, Queries>, query_id: String) -> Result<bool> {
+    let handle = queries.running.lock().await.remove(&query_id);
+    Ok(handle.map(|handle| handle.abort()).is_some())
+}
+
+/// Runs the query for its rows to be read in batches of `batch_size`, 100 by default, with [`fetch_next`].
+#[command]
+async fn select_stream<R: Runtime>(
+    window: Window<R>,
+    db_instances: State<'_, DbInstances>,
+    cursors: State<'_, Cursors>,
+    db: String,
+    query: String,
+    values: Vec<JsonValue>,
+    batch_size: Option<usize>,
+) -> Result<u64> {
+    let pool = db_instances.0.lock().await.get(&db).cloned();
+    let pool = pool.ok_or_else(|| Error::DatabaseNotLoaded(db.clone()))?;
+    window.state::<Queries>().check(&db, &query).await?;
+    let batch_size = batch_size.unwrap_or(100).max(1);
+
+    // one batch is read ahead, the next ones wait until it's fetched
+    let (tx, rx) = mpsc::channel(1);
+    tauri::async_runtime::spawn(async move {
+        let mut rows = bind(&query, values).fetch(&pool);
+        let mut batch = Vec::with_capacity(batch_size);
+        loop {
+            match rows.try_next().await {
+                Ok(Some(row)) => {
+                    batch.push(decode_row(&row));
+                    if batch.len() < batch_size {
+                        continue;
                     }
-                    // "JSON" => JsonValue::Object(row.get(i)),
-                    "BLOB" => {
-                        if let Ok(n) = row.try_get::<Vec<u8>, usize>(i) {
-                            JsonValue::Array(
-                                n.into_iter().map(|n| JsonValue::Number(n.into())).collect(),
-                            )
-                        } else {
-                            JsonValue::Null
-                        }
+                }
+                Ok(None) => {
+                    if !batch.is_empty() {
+                        let _ = tx.send(Ok(batch)).await;
                     }
-                    _ => JsonValue::Null,
+                    break;
                 }
-            };
-            value.insert(column.name().to_string(), v);
+                Err(e) => {
+                    let _ = tx.send(Err(e.into())).await;
+                    break;
+                }
+            }
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+            if tx.send(Ok(full)).await.is_err() {
+                // the cursor was closed
+                break;
+            }
         }
-        values.push(value);
+    });
+
+    let id = cursors.next_id.fetch_add(1, Ordering::Relaxed);
+    cursors.open.lock().await.insert(
+        id,
+        Cursor {
+            db,
+            window: window.label().into(),
+            batches: Arc::new(Mutex::new(rx)),
+        },
+    );
+    Ok(id)
+}
+
+/// Emits the next batch of rows of the cursor to the window as a `sql://rows` event.
+///
+/// Resolves to `false` without emitting once the rows were all read, which closes the cursor.
+#[command]
+async fn fetch_next<R: Runtime>(
+    window: Window<R>,
+    cursors: State<'_, Cursors>,
+    cursor: u64,
+) -> Result<bool> {
+    let batches = cursors
+        .open
+        .lock()
+        .await
+        .get(&cursor)
+        .map(|open| open.batches.clone())
+        .ok_or(Error::CursorNotFound(cursor))?;
+    // without locking the other cursors while the batch is read
+    let batch = batches.lock().await.recv().await;
+    match batch {
+        Some(Ok(rows)) => {
+            let _ = window.emit("sql://rows", RowsPayload { cursor, rows });
+            Ok(true)
+        }
+        batch => {
+            cursors.open.lock().await.remove(&cursor);
+            batch.transpose().map(|_| false)
+        }
+    }
+}
+
+/// Closes the cursor before its rows were all read, releasing its connection.
+#[command]
+async fn close_cursor(cursors: State<'_, Cursors>, cursor: u64) -> Result<()> {
+    cursors
+        .open
+        .lock()
+        .await
+        .remove(&cursor)
+        .map(|_| ())
+        .ok_or(Error::CursorNotFound(cursor))
+}
+
+/// Reverts the migrations of the database above the version, once the app enabled it with
+/// [`TauriSql::js_rollback`].
+#[command]
+async fn rollback_migrations(
+    db_instances: State<'_, DbInstances>,
+    migrations: State<'_, Migrations>,
+    queries: State<'_, Queries>,
+    db: String,
+    version: i64,
+) -> Result<()> {
+    if !migrations.js_rollback {
+        return Err(Error::RollbackDenied);
     }
-    Ok(values)
+    let pool = db_instances.0.lock().await.get(&db).cloned();
+    let pool = pool.ok_or_else(|| Error::DatabaseNotLoaded(db.clone()))?;
+    queries.check_writable(&db).await?;
+    migrations.rollback_to(&pool, &db, version).await
 }
 
 /// Tauri SQL plugin.
 pub struct TauriSql<R: Runtime> {
     migrations: Option<HashMap<String, MigrationList>>,
+    js_rollback: bool,
+    pool_configs: Option<PoolConfigs>,
     invoke_handler: Box<dyn Fn(Invoke<R>) + Send + Sync>,
 }
 
@@ -310,13 +1576,34 @@ impl<R: Runtime> Default for TauriSql<R> {
     fn default() -> Self {
         Self {
             migrations: Some(Default::default()),
-            invoke_handler: Box::new(tauri::generate_handler![load, execute, select, close]),
+            js_rollback: false,
+            pool_configs: Some(Default::default()),
+            invoke_handler: Box::new(tauri::generate_handler![
+                load,
+                execute,
+                select,
+                close,
+                begin,
+                commit,
+                rollback,
+                rollback_migrations,
+                select_stream,
+                fetch_next,
+                close_cursor,
+                cancel,
+                listen,
+                unlisten,
+                backup,
+                restore
+            ]),
         }
     }
 }
 
 impl<R: Runtime> TauriSql<R> {
     /// Add migrations to a database.
+    ///
+    /// sqlx doesn't migrate the MSSQL databases, loading them fails with their migrations.
     #[must_use]
     pub fn add_migrations(mut self, db_url: &str, migrations: Vec<Migration>) -> Self {
         self.migrations
@@ -325,6 +1612,34 @@ impl<R: Runtime> TauriSql<R> {
             .insert(db_url.to_string(), MigrationList(migrations));
         self
     }
+
+    /// The pool config of the databases, overridden by [`TauriSql::database_pool_config`] and the options passed to
+    /// `load` from the frontend. The frontend can only tighten the read-only mode and the statement lists.
+    #[must_use]
+    pub fn pool_config(mut self, config: PoolConfig) -> Self {
+        self.pool_configs.as_mut().unwrap().defaults = config;
+        self
+    }
+
+    /// The pool config of a database, over the one of [`TauriSql::pool_config`].
+    #[must_use]
+    pub fn database_pool_config(mut self, db_url: &str, config: PoolConfig) -> Self {
+        self.pool_configs
+            .as_mut()
+            .unwrap()
+            .databases
+            .insert(db_url.to_string(), config);
+        self
+    }
+
+    /// Lets the webview revert the migrations with the `rollback_migrations` command, e.g. during development. It can
+    /// drop the data of the app, so it's disabled by default and [`Migrations::rollback_to`] is only available from
+    /// Rust.
+    #[must_use]
+    pub fn js_rollback(mut self, js_rollback: bool) -> Self {
+        self.js_rollback = js_rollback;
+        self
+    }
 }
 
 impl<R: Runtime> Plugin<R> for TauriSql<R> {
@@ -334,16 +1649,14 @@ impl<R: Runtime> Plugin<R> for TauriSql<R> {
 
     fn initialize(&mut self, app: &AppHandle<R>, config: serde_json::Value) -> PluginResult<()> {
         tauri::async_runtime::block_on(async move {
-            let config: PluginConfig = if config.is_null() {
-                Default::default()
-            } else {
-                serde_json::from_value(config)?
-            };
+            let config: Config = config::parse("sql", config)?;
 
             #[cfg(feature = "sqlite")]
             create_dir_all(app_path(app)).expect("problems creating App directory!");
 
+            let pool_configs = self.pool_configs.take().unwrap();
             let instances = DbInstances::default();
+            let queries = Queries::default();
             let mut lock = instances.0.lock().await;
             for db in config.preload {
                 #[cfg(feature = "sqlite")]
@@ -351,37 +1664,194 @@ impl<R: Runtime> Plugin<R> for TauriSql<R> {
                 #[cfg(not(feature = "sqlite"))]
                 let fqdb = db.clone();
 
-                if !Db::database_exists(&fqdb).await.unwrap_or(false) {
-                    Db::create_database(&fqdb).await?;
-                }
-                let pool = Pool::connect(&fqdb).await?;
-
-                if let Some(migrations) = self.migrations.as_mut().unwrap().remove(&db) {
-                    let migrator = Migrator::new(migrations).await?;
-                    migrator.run(&pool).await?;
-                }
+                let migrations = self.migrations.as_ref().unwrap().get(&db).cloned();
+                let config = pool_configs.get(&db, None);
+                let pool = connect(&fqdb, &config, migrations).await?;
+                queries.loaded(&db, &config).await;
                 lock.insert(db, pool);
             }
             drop(lock);
             app.manage(instances);
-            app.manage(Migrations(Mutex::new(self.migrations.take().unwrap())));
+            app.manage(Migrations {
+                lists: Mutex::new(self.migrations.take().unwrap()),
+                js_rollback: self.js_rollback,
+            });
+            app.manage(Transactions::default());
+            app.manage(Cursors::default());
+            app.manage(queries);
+            app.manage(Listeners::default());
+            app.manage(pool_configs);
+
+            let app_ = app.clone();
+            app.diagnostics().register("sql", move || {
+                let instances = app_.state::<DbInstances>();
+                let queries = app_.state::<Queries>();
+                // the pools are only read when they aren't busy
+                let report = Report::default();
+                let report = match instances.0.try_lock() {
+                    Ok(instances) => instances.iter().fold(
+                        report.metric("databases", instances.len() as f64),
+                        |report, (db, pool)| {
+                            report
+                                .metric(format!("{}.connections", db), pool.size())
+                                .metric(format!("{}.idle", db), pool.num_idle() as f64)
+                        },
+                    ),
+                    Err(_) => report,
+                };
+                let report = match queries.statements.try_lock() {
+                    Ok(statements) => statements.iter().fold(report, |report, (db, cache)| {
+                        report
+                            .metric(format!("{}.statement_hits", db), cache.hits as f64)
+                            .metric(format!("{}.statement_misses", db), cache.misses as f64)
+                    }),
+                    Err(_) => report,
+                };
+                report
+            });
+
+            let app_ = app.clone();
+            app.shutdown().register("sql", Stage::Release, move || {
+                tauri::async_runtime::block_on(async {
+                    app_.state::<Transactions>().rollback_where(|_| true).await;
+                    app_.state::<Cursors>().close_where(|_| true).await;
+                    app_.state::<Listeners>().close_where(|_, _, _| true).await;
+                    let instances = app_.state::<DbInstances>();
+                    let instances = instances.0.lock().await;
+                    for value in instances.values() {
+                        value.close().await;
+                    }
+                });
+            });
             Ok(())
         })
     }
 
+    fn created(&mut self, window: Window<R>) {
+        let label = window.label().to_string();
+        let app = window.app_handle();
+        window.on_window_event(move |event| {
+            if let WindowEvent::Destroyed = event {
+                release_window(&app, label.clone());
+            }
+        });
+    }
+
+    fn on_page_load(&mut self, window: Window<R>, _payload: PageLoadPayload) {
+        // the reloaded page lost the ids of its transactions and cursors, and its event listeners
+        release_window(&window.app_handle(), window.label().into());
+    }
+
     fn extend_api(&mut self, message: Invoke<R>) {
         (self.invoke_handler)(message)
     }
 
     fn on_event(&mut self, app: &AppHandle<R>, event: &RunEvent) {
-        if let RunEvent::Exit = event {
-            tauri::async_runtime::block_on(async move {
-                let instances = &*app.state::<DbInstances>();
-                let instances = instances.0.lock().await;
-                for value in instances.values() {
-                    value.close().await;
-                }
-            });
+        shutdown::on_event(app, "sql", event);
+    }
+}
+
+/// Rolls back the transactions begun by the window, closes its cursors and stops its listeners.
+fn release_window<R: Runtime>(app: &AppHandle<R>, label: String) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Some(transactions) = app.try_state::<Transactions>() {
+            transactions
+                .rollback_where(|transaction| transaction.window == label)
+                .await;
+        }
+        if let Some(cursors) = app.try_state::<Cursors>() {
+            cursors.close_where(|cursor| cursor.window == label).await;
         }
+        if let Some(listeners) = app.try_state::<Listeners>() {
+            listeners.close_where(|window, _, _| window == label).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frontend_options(options: serde_json::Value) -> Option<PoolConfig> {
+        Some(serde_json::from_value(options).unwrap())
+    }
+
+    #[test]
+    fn frontend_options_only_tighten_the_policy() {
+        let mut configs = PoolConfigs::default();
+        configs.databases.insert(
+            "sqlite:app.db".into(),
+            PoolConfig::default()
+                .read_only(true)
+                .allowed_statements([StatementKind::Select, StatementKind::Insert])
+                .denied_statements([StatementKind::Ddl, StatementKind::Attach]),
+        );
+
+        let relaxed = configs.get_for_frontend(
+            "sqlite:app.db",
+            frontend_options(serde_json::json!({
+                "readOnly": false,
+                "allowedStatements": ["insert", "delete"],
+                "deniedStatements": ["pragma"],
+                "maxConnections": 1,
+            })),
+        );
+        assert_eq!(relaxed.read_only, Some(true));
+        assert_eq!(
+            relaxed.allowed_statements,
+            Some(vec![StatementKind::Insert])
+        );
+        assert_eq!(
+            relaxed.denied_statements,
+            Some(vec![
+                StatementKind::Ddl,
+                StatementKind::Attach,
+                StatementKind::Pragma
+            ])
+        );
+        assert_eq!(relaxed.max_connections, Some(1));
+
+        let cleared = configs.get_for_frontend(
+            "sqlite:app.db",
+            frontend_options(serde_json::json!({ "deniedStatements": [] })),
+        );
+        assert_eq!(cleared.read_only, Some(true));
+        assert_eq!(
+            cleared.denied_statements,
+            Some(vec![StatementKind::Ddl, StatementKind::Attach])
+        );
+    }
+
+    #[test]
+    fn relaxing_a_loaded_policy_is_rejected() {
+        let queries = Queries::default();
+        let strict = PoolConfig::default()
+            .read_only(true)
+            .denied_statements([StatementKind::Attach]);
+        let relaxed = PoolConfig::default().denied_statements([StatementKind::Attach]);
+        let stricter = PoolConfig::default()
+            .read_only(true)
+            .allowed_statements([StatementKind::Select])
+            .denied_statements([StatementKind::Attach, StatementKind::Ddl]);
+
+        tauri::async_runtime::block_on(async {
+            queries.loaded("sqlite:app.db", &strict).await;
+            assert!(matches!(
+                queries
+                    .check_not_relaxed("sqlite:app.db", &StatementPolicy::new(&relaxed))
+                    .await,
+                Err(Error::PolicyRelaxed(..))
+            ));
+            assert!(queries
+                .check_not_relaxed("sqlite:app.db", &StatementPolicy::new(&stricter))
+                .await
+                .is_ok());
+            // the databases not loaded yet take any policy
+            assert!(queries
+                .check_not_relaxed("sqlite:other.db", &StatementPolicy::new(&relaxed))
+                .await
+                .is_ok());
+        });
     }
 }
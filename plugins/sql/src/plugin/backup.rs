@@ -0,0 +1,91 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Copies the SQLite databases with the online backup API, while their pools keep running the queries.
+
+use libsqlite3_sys as ffi;
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_int,
+    path::Path,
+    ptr, thread,
+    time::Duration,
+};
+
+/// The pages copied at once, the database is only locked while they're copied.
+const PAGES_PER_STEP: c_int = 128;
+
+/// A connection of its own for the copy, closed once dropped.
+struct Connection(*mut ffi::sqlite3);
+
+impl Connection {
+    fn open(path: &Path, flags: c_int) -> Result<Self, String> {
+        let path = CString::new(path.to_string_lossy().as_bytes()).map_err(|e| e.to_string())?;
+        let mut db = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_open_v2(path.as_ptr(), &mut db, flags, ptr::null()) };
+        // closed even when it failed to open
+        let connection = Self(db);
+        if rc != ffi::SQLITE_OK {
+            return Err(connection.error());
+        }
+        unsafe { ffi::sqlite3_busy_timeout(db, 5000) };
+        Ok(connection)
+    }
+
+    fn error(&self) -> String {
+        if self.0.is_null() {
+            return "out of memory".into();
+        }
+        unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(self.0)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3_close(self.0) };
+    }
+}
+
+/// Copies the database at `from` over the one at `to`, created if needed, calling `progress` with the pages copied
+/// and their total after each step.
+///
+/// Blocks until the copy is complete, it restarts when another connection writes to `from` meanwhile.
+pub(crate) fn copy(
+    from: &Path,
+    to: &Path,
+    mut progress: impl FnMut(u32, u32),
+) -> Result<(), String> {
+    let source = Connection::open(from, ffi::SQLITE_OPEN_READONLY)?;
+    let destination = Connection::open(to, ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE)?;
+    let main = c"main".as_ptr();
+    let backup = unsafe { ffi::sqlite3_backup_init(destination.0, main, source.0, main) };
+    if backup.is_null() {
+        return Err(destination.error());
+    }
+
+    let rc = loop {
+        let rc = unsafe { ffi::sqlite3_backup_step(backup, PAGES_PER_STEP) };
+        let total = unsafe { ffi::sqlite3_backup_pagecount(backup) };
+        let remaining = unsafe { ffi::sqlite3_backup_remaining(backup) };
+        match rc {
+            ffi::SQLITE_OK | ffi::SQLITE_DONE => {
+                progress((total - remaining) as u32, total as u32);
+                if rc == ffi::SQLITE_DONE {
+                    break rc;
+                }
+            }
+            // another connection is writing
+            ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED => thread::sleep(Duration::from_millis(50)),
+            rc => break rc,
+        }
+    };
+    let finish = unsafe { ffi::sqlite3_backup_finish(backup) };
+    if rc == ffi::SQLITE_DONE && finish == ffi::SQLITE_OK {
+        Ok(())
+    } else {
+        Err(destination.error())
+    }
+}
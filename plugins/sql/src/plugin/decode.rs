@@ -0,0 +1,412 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Decodes the rows of the queries to JSON.
+//!
+//! The dates and times are ISO-8601 strings, the `NUMERIC` and `DECIMAL` values strings of their exact digits and
+//! the binary values arrays of bytes, whatever the driver.
+
+use super::Db;
+use serde_json::Value as JsonValue;
+#[cfg(not(feature = "mssql"))]
+use sqlx::types::chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+#[cfg(feature = "sqlite")]
+use sqlx::ValueRef;
+use sqlx::{Column, Database, Decode, Row, Type, TypeInfo};
+use std::collections::HashMap;
+
+pub(crate) fn decode_row(row: &<Db as Database>::Row) -> HashMap<String, JsonValue> {
+    let mut value = HashMap::default();
+    for (i, column) in row.columns().iter().enumerate() {
+        let info = column.type_info();
+        let v = if info.is_null() {
+            untyped(row, i)
+        } else {
+            decode_value(row, i, info)
+        };
+        value.insert(column.name().to_string(), v);
+    }
+    value
+}
+
+/// SQLite types the columns by their declaration, which sqlx doesn't know for `NUMERIC` and `DECIMAL`, so the values
+/// of the untyped columns are read as `NUMERIC`.
+#[cfg(feature = "sqlite")]
+fn untyped(row: &<Db as Database>::Row, i: usize) -> JsonValue {
+    match row.try_get_raw(i) {
+        Ok(value) if value.is_null() => JsonValue::Null,
+        Ok(value) if value.type_info().name() == "BLOB" => decode_value(row, i, &value.type_info()),
+        Ok(_) => decimal(row, i).map_or(JsonValue::Null, JsonValue::String),
+        Err(_) => JsonValue::Null,
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn untyped(_row: &<Db as Database>::Row, _i: usize) -> JsonValue {
+    JsonValue::Null
+}
+
+fn get<'r, T: Decode<'r, Db> + Type<Db>>(row: &'r <Db as Database>::Row, i: usize) -> Option<T> {
+    row.try_get(i).ok()
+}
+
+#[cfg(not(feature = "mssql"))]
+fn decode_value(
+    row: &<Db as Database>::Row,
+    i: usize,
+    info: &<Db as Database>::TypeInfo,
+) -> JsonValue {
+    let value = match info.name() {
+        "VARCHAR" | "STRING" | "TEXT" | "CHAR" | "BPCHAR" | "NAME" | "ENUM" | "SET" => {
+            get::<String>(row, i).map(JsonValue::String)
+        }
+        "BOOL" | "BOOLEAN" => get::<bool>(row, i)
+            .or_else(|| get::<String>(row, i).map(|x| x.to_lowercase() == "true"))
+            .map(JsonValue::Bool),
+        "INT" | "NUMBER" | "INTEGER" | "BIGINT" | "INT8" | "INT4" | "INT2" | "TINYINT"
+        | "SMALLINT" | "MEDIUMINT" | "YEAR" => get::<i64>(row, i)
+            .or_else(|| get::<i32>(row, i).map(Into::into))
+            .or_else(|| get::<i16>(row, i).map(Into::into))
+            .map(JsonValue::from),
+        #[cfg(feature = "mysql")]
+        "TINYINT UNSIGNED" | "SMALLINT UNSIGNED" | "INT UNSIGNED" | "MEDIUMINT UNSIGNED"
+        | "BIGINT UNSIGNED" => get::<u64>(row, i).map(JsonValue::from),
+        "REAL" | "FLOAT" | "DOUBLE" | "FLOAT4" | "FLOAT8" => get::<f64>(row, i)
+            .or_else(|| get::<f32>(row, i).map(Into::into))
+            .map(JsonValue::from),
+        "NUMERIC" | "DECIMAL" => decimal(row, i).map(JsonValue::String),
+        // with a time zone, to UTC
+        "TIMESTAMPTZ" | "TIMESTAMP" => get::<DateTime<Utc>>(row, i)
+            .map(utc_date_time)
+            .or_else(|| get::<NaiveDateTime>(row, i).map(naive_date_time))
+            .or_else(|| get::<String>(row, i))
+            .map(JsonValue::String),
+        "DATETIME" => zoned_date_time(row, i)
+            .or_else(|| get::<NaiveDateTime>(row, i).map(naive_date_time))
+            .or_else(|| get::<String>(row, i))
+            .map(JsonValue::String),
+        "DATE" => get::<NaiveDate>(row, i)
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .or_else(|| get::<String>(row, i))
+            .map(JsonValue::String),
+        "TIME" => get::<NaiveTime>(row, i)
+            .map(|t| t.format("%H:%M:%S%.f").to_string())
+            .or_else(|| get::<String>(row, i))
+            .map(JsonValue::String),
+        "JSON" | "JSONB" => get::<JsonValue>(row, i),
+        "BLOB" | "BYTEA" | "BINARY" | "VARBINARY" | "TINYBLOB" | "MEDIUMBLOB" | "LONGBLOB" => {
+            get::<Vec<u8>>(row, i).map(|bytes| bytes.into_iter().map(JsonValue::from).collect())
+        }
+        _ => get::<String>(row, i).map(JsonValue::String),
+    };
+    value.unwrap_or(JsonValue::Null)
+}
+
+#[cfg(not(feature = "mssql"))]
+fn utc_date_time(t: DateTime<Utc>) -> String {
+    t.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string()
+}
+
+#[cfg(not(feature = "mssql"))]
+fn naive_date_time(t: NaiveDateTime) -> String {
+    t.format("%Y-%m-%dT%H:%M:%S%.f").to_string()
+}
+
+/// SQLite names the `TIMESTAMP` columns `DATETIME` too and keeps them as text, those with a time zone are converted
+/// to UTC.
+#[cfg(feature = "sqlite")]
+fn zoned_date_time(row: &<Db as Database>::Row, i: usize) -> Option<String> {
+    get::<&str>(row, i)
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| utc_date_time(t.with_timezone(&Utc)))
+}
+
+/// The `DATETIME` of MySQL has no time zone.
+#[cfg(not(any(feature = "sqlite", feature = "mssql")))]
+fn zoned_date_time(_row: &<Db as Database>::Row, _i: usize) -> Option<String> {
+    None
+}
+
+/// SQLite stores the `NUMERIC` values as integers, reals or text, they're all read as strings.
+#[cfg(feature = "sqlite")]
+fn decimal(row: &<Db as Database>::Row, i: usize) -> Option<String> {
+    get::<i64>(row, i)
+        .map(|n| n.to_string())
+        .or_else(|| get::<f64>(row, i).map(|n| n.to_string()))
+        .or_else(|| get::<String>(row, i))
+}
+
+#[cfg(any(feature = "mysql", feature = "postgres"))]
+fn decimal(row: &<Db as Database>::Row, i: usize) -> Option<String> {
+    get::<Decimal>(row, i).map(|decimal| decimal.0)
+}
+
+/// The exact digits of a `NUMERIC` or `DECIMAL` value, as sqlx decodes them only to the big decimal crates.
+#[cfg(any(feature = "mysql", feature = "postgres"))]
+struct Decimal(String);
+
+#[cfg(feature = "mysql")]
+mod mysql {
+    use super::Decimal;
+    use sqlx::{
+        error::BoxDynError,
+        mysql::{MySql, MySqlTypeInfo, MySqlValueRef},
+        Decode, Type, TypeInfo,
+    };
+
+    impl Type<MySql> for Decimal {
+        fn type_info() -> MySqlTypeInfo {
+            <str as Type<MySql>>::type_info()
+        }
+
+        fn compatible(ty: &MySqlTypeInfo) -> bool {
+            ty.name() == "DECIMAL"
+        }
+    }
+
+    /// MySQL sends the decimals as their digits.
+    impl<'r> Decode<'r, MySql> for Decimal {
+        fn decode(value: MySqlValueRef<'r>) -> Result<Self, BoxDynError> {
+            <&str as Decode<MySql>>::decode(value).map(|digits| Self(digits.to_owned()))
+        }
+    }
+}
+
+#[cfg(feature = "postgres")]
+mod postgres {
+    use super::Decimal;
+    use sqlx::{
+        error::BoxDynError,
+        postgres::{PgTypeInfo, PgValueFormat, PgValueRef, Postgres},
+        Decode, Type,
+    };
+    use std::fmt::Write;
+
+    impl Type<Postgres> for Decimal {
+        fn type_info() -> PgTypeInfo {
+            PgTypeInfo::with_name("NUMERIC")
+        }
+    }
+
+    impl<'r> Decode<'r, Postgres> for Decimal {
+        fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+            match value.format() {
+                PgValueFormat::Binary => numeric(value.as_bytes()?).map(Self),
+                PgValueFormat::Text => Ok(Self(value.as_str()?.to_owned())),
+            }
+        }
+    }
+
+    /// Formats the binary `NUMERIC`: its number of base 10000 digits, the weight of the first one, its sign and its
+    /// number of decimal digits, followed by the digits.
+    fn numeric(bytes: &[u8]) -> Result<String, BoxDynError> {
+        let read = |i: usize| -> Result<u16, BoxDynError> {
+            bytes
+                .get(i * 2..i * 2 + 2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .ok_or_else(|| "truncated NUMERIC".into())
+        };
+        let ndigits = read(0)? as usize;
+        let weight = read(1)? as i16 as i64;
+        let sign = read(2)?;
+        let dscale = read(3)? as usize;
+        match sign {
+            0xC000 => return Ok("NaN".into()),
+            0xD000 => return Ok("Infinity".into()),
+            0xF000 => return Ok("-Infinity".into()),
+            _ => {}
+        }
+        let digits = (0..ndigits)
+            .map(|i| read(4 + i))
+            .collect::<Result<Vec<_>, _>>()?;
+        let digit = |i: i64| {
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| digits.get(i).copied())
+                .unwrap_or(0)
+        };
+
+        let mut s = String::new();
+        if sign == 0x4000 {
+            s.push('-');
+        }
+        if weight < 0 {
+            s.push('0');
+        }
+        for i in 0..=weight {
+            if i == 0 {
+                write!(s, "{}", digit(i))?;
+            } else {
+                write!(s, "{:04}", digit(i))?;
+            }
+        }
+        if dscale > 0 {
+            let mut fraction = String::with_capacity(dscale + 4);
+            let mut i = weight + 1;
+            while fraction.len() < dscale {
+                write!(fraction, "{:04}", digit(i))?;
+                i += 1;
+            }
+            fraction.truncate(dscale);
+            s.push('.');
+            s.push_str(&fraction);
+        }
+        Ok(s)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::numeric;
+
+        /// The binary `NUMERIC` of the base 10000 digits.
+        fn binary(weight: i16, sign: u16, dscale: u16, digits: &[u16]) -> Vec<u8> {
+            [digits.len() as u16, weight as u16, sign, dscale]
+                .iter()
+                .chain(digits)
+                .flat_map(|n| n.to_be_bytes())
+                .collect()
+        }
+
+        #[test]
+        fn numeric_to_string() {
+            assert_eq!(numeric(&binary(0, 0, 0, &[])).unwrap(), "0");
+            assert_eq!(numeric(&binary(0, 0, 2, &[])).unwrap(), "0.00");
+            assert_eq!(
+                numeric(&binary(1, 0, 3, &[1, 2345, 6780])).unwrap(),
+                "12345.678"
+            );
+            assert_eq!(numeric(&binary(0, 0x4000, 1, &[1, 5000])).unwrap(), "-1.5");
+            // the trailing zero digits aren't sent
+            assert_eq!(numeric(&binary(2, 0, 0, &[1])).unwrap(), "100000000");
+        }
+
+        #[test]
+        fn numeric_with_negative_weight() {
+            assert_eq!(numeric(&binary(-1, 0, 4, &[12])).unwrap(), "0.0012");
+            assert_eq!(
+                numeric(&binary(-2, 0x4000, 8, &[12])).unwrap(),
+                "-0.00000012"
+            );
+        }
+
+        #[test]
+        fn special_numerics() {
+            assert_eq!(numeric(&binary(0, 0xC000, 0, &[])).unwrap(), "NaN");
+            assert_eq!(numeric(&binary(0, 0xD000, 0, &[])).unwrap(), "Infinity");
+            assert_eq!(numeric(&binary(0, 0xF000, 0, &[])).unwrap(), "-Infinity");
+            assert!(numeric(&binary(0, 0, 0, &[1])[..9]).is_err());
+        }
+    }
+}
+
+/// sqlx doesn't name all the MSSQL types it reads, so they're told apart by the Rust types they decode to.
+#[cfg(feature = "mssql")]
+fn decode_value(
+    row: &<Db as Database>::Row,
+    i: usize,
+    info: &<Db as Database>::TypeInfo,
+) -> JsonValue {
+    let value = if <String as Type<Db>>::compatible(info) {
+        get::<String>(row, i).map(JsonValue::from)
+    } else if <bool as Type<Db>>::compatible(info) {
+        get::<bool>(row, i).map(JsonValue::from)
+    } else if <i64 as Type<Db>>::compatible(info) {
+        get::<i64>(row, i).map(JsonValue::from)
+    } else if <i32 as Type<Db>>::compatible(info) {
+        get::<i32>(row, i).map(JsonValue::from)
+    } else if <i16 as Type<Db>>::compatible(info) {
+        get::<i16>(row, i).map(JsonValue::from)
+    } else if <u8 as Type<Db>>::compatible(info) {
+        get::<u8>(row, i).map(JsonValue::from)
+    } else if <f64 as Type<Db>>::compatible(info) {
+        get::<f64>(row, i).map(JsonValue::from)
+    } else if <f32 as Type<Db>>::compatible(info) {
+        get::<f32>(row, i).map(JsonValue::from)
+    } else {
+        None
+    };
+    value.unwrap_or(JsonValue::Null)
+}
+
+#[cfg(all(test, any(feature = "sqlite", feature = "mysql")))]
+mod tests {
+    use super::*;
+
+    /// Decodes the row of the table `t`, in a database of its own.
+    #[cfg(feature = "sqlite")]
+    fn sqlite_row(create: &str, insert: &str) -> HashMap<String, JsonValue> {
+        tauri::async_runtime::block_on(async {
+            // each connection has its own in-memory database
+            let pool = sqlx::pool::PoolOptions::<Db>::new()
+                .max_connections(1)
+                .connect("sqlite::memory:")
+                .await
+                .unwrap();
+            sqlx::query(create).execute(&pool).await.unwrap();
+            sqlx::query(insert).execute(&pool).await.unwrap();
+            let row = sqlx::query("SELECT * FROM t")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            decode_row(&row)
+        })
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_timestamp_to_iso_8601() {
+        let row = sqlite_row(
+            "CREATE TABLE t (local TIMESTAMP, utc TIMESTAMP, at DATETIME, day DATE)",
+            "INSERT INTO t VALUES \
+             ('2024-02-29 13:45:30.5', '2024-02-29T13:45:30Z', '2024-02-29 13:45:30', '2024-02-29')",
+        );
+        assert_eq!(row["local"], "2024-02-29T13:45:30.500");
+        assert_eq!(row["utc"], "2024-02-29T13:45:30Z");
+        assert_eq!(row["at"], "2024-02-29T13:45:30");
+        assert_eq!(row["day"], "2024-02-29");
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_decimal_to_string() {
+        let row = sqlite_row(
+            "CREATE TABLE t (price DECIMAL(10, 2), amount NUMERIC, code NUMERIC, missing NUMERIC)",
+            "INSERT INTO t VALUES ('12.50', 42, 'A1', NULL)",
+        );
+        assert_eq!(row["price"], "12.5");
+        assert_eq!(row["amount"], "42");
+        assert_eq!(row["code"], "A1");
+        assert_eq!(row["missing"], JsonValue::Null);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_blob_to_bytes() {
+        let row = sqlite_row(
+            "CREATE TABLE t (data BLOB, untyped NUMERIC)",
+            "INSERT INTO t VALUES (x'00ff10', x'01')",
+        );
+        assert_eq!(row["data"], serde_json::json!([0, 255, 16]));
+        assert_eq!(row["untyped"], serde_json::json!([1]));
+    }
+
+    #[cfg(feature = "mysql")]
+    #[test]
+    #[ignore = "needs a MySQL server at MYSQL_DATABASE_URL"]
+    fn mysql_decimal_to_string() {
+        let url = std::env::var("MYSQL_DATABASE_URL").unwrap();
+        let row = tauri::async_runtime::block_on(async {
+            let pool = sqlx::Pool::<Db>::connect(&url).await.unwrap();
+            sqlx::query(
+                "SELECT CAST(-12345.678 AS DECIMAL(10, 3)) AS d, CAST(0 AS DECIMAL(4, 2)) AS z",
+            )
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        });
+        let row = decode_row(&row);
+        assert_eq!(row["d"], "-12345.678");
+        assert_eq!(row["z"], "0.00");
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Classifies the statements of the queries by their first keyword, to check them before they reach the driver.
+//!
+//! The strings, quoted identifiers and comments are skipped, so their keywords and semicolons don't count.
+
+use serde::Deserialize;
+use std::fmt;
+
+/// What a statement of a query does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum StatementKind {
+    /// `SELECT` and `VALUES`, and the `WITH` queries only selecting.
+    Select,
+    /// `INSERT` and `REPLACE`.
+    Insert,
+    Update,
+    Delete,
+    /// `CREATE`, `ALTER`, `DROP`, `TRUNCATE` and `RENAME`.
+    Ddl,
+    /// `ATTACH` and `DETACH`, opening other SQLite databases on the connection.
+    Attach,
+    Pragma,
+    /// `BEGIN`, `COMMIT`, `ROLLBACK` and the savepoints.
+    Transaction,
+    /// Any other statement, e.g. `EXPLAIN`, `SET`, `DO` or `EXEC`.
+    Other,
+}
+
+impl StatementKind {
+    pub(crate) const ALL: [Self; 9] = [
+        Self::Select,
+        Self::Insert,
+        Self::Update,
+        Self::Delete,
+        Self::Ddl,
+        Self::Attach,
+        Self::Pragma,
+        Self::Transaction,
+        Self::Other,
+    ];
+}
+
+impl fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Select => "select",
+            Self::Insert => "insert",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Ddl => "ddl",
+            Self::Attach => "attach",
+            Self::Pragma => "pragma",
+            Self::Transaction => "transaction",
+            Self::Other => "other",
+        })
+    }
+}
+
+/// The kinds of the statements of the query, in order.
+pub(crate) fn kinds(query: &str) -> Vec<StatementKind> {
+    statements(query).iter().map(|words| kind(words)).collect()
+}
+
+fn kind(words: &[String]) -> StatementKind {
+    match words[0].as_str() {
+        "select" | "values" => StatementKind::Select,
+        "insert" | "replace" => StatementKind::Insert,
+        "update" => StatementKind::Update,
+        "delete" => StatementKind::Delete,
+        "create" | "alter" | "drop" | "truncate" | "rename" => StatementKind::Ddl,
+        "attach" | "detach" => StatementKind::Attach,
+        "pragma" => StatementKind::Pragma,
+        "begin" | "commit" | "rollback" | "savepoint" | "release" | "start" | "end" => {
+            StatementKind::Transaction
+        }
+        // the common table expressions of Postgres can modify rows too, so any of them counts
+        "with" => words[1..]
+            .iter()
+            .find_map(|word| match word.as_str() {
+                "insert" | "replace" => Some(StatementKind::Insert),
+                "update" => Some(StatementKind::Update),
+                "delete" => Some(StatementKind::Delete),
+                _ => None,
+            })
+            .unwrap_or_else(|| {
+                if words.iter().any(|word| word == "select") {
+                    StatementKind::Select
+                } else {
+                    StatementKind::Other
+                }
+            }),
+        _ => StatementKind::Other,
+    }
+}
+
+/// The lowercased words of each statement of the query, without the empty statements.
+fn statements(query: &str) -> Vec<Vec<String>> {
+    let chars = query.chars().collect::<Vec<_>>();
+    let mut statements = Vec::new();
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ';' => {
+                if !words.is_empty() {
+                    statements.push(std::mem::take(&mut words));
+                }
+                i += 1;
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                i = find(&chars, i + 2, &['\n']).map_or(chars.len(), |end| end + 1);
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i = find(&chars, i + 2, &['*', '/']).map_or(chars.len(), |end| end + 2);
+            }
+            '\'' | '"' | '`' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                // the doubled quotes are escaped, and read as two strings
+                i = find(&chars, i + 1, &[close]).map_or(chars.len(), |end| end + 1);
+            }
+            '$' => {
+                // the dollar-quoted strings of Postgres, `$tag$...$tag$`
+                let tag_end = (i + 1..chars.len())
+                    .find(|&j| !(chars[j].is_alphanumeric() || chars[j] == '_'))
+                    .filter(|&j| chars[j] == '$' && !chars[i + 1].is_ascii_digit());
+                i = match tag_end {
+                    Some(tag_end) => {
+                        let tag = &chars[i..=tag_end];
+                        find(&chars, tag_end + 1, tag).map_or(chars.len(), |end| end + tag.len())
+                    }
+                    None => i + 1,
+                };
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let end = (i..chars.len())
+                    .find(|&j| !(chars[j].is_alphanumeric() || chars[j] == '_' || chars[j] == '$'))
+                    .unwrap_or(chars.len());
+                words.push(chars[i..end].iter().collect::<String>().to_lowercase());
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+    if !words.is_empty() {
+        statements.push(words);
+    }
+    statements
+}
+
+/// The start of the next occurrence of `pattern` from `from`.
+fn find(chars: &[char], from: usize, pattern: &[char]) -> Option<usize> {
+    (from..chars.len()).find(|&i| chars[i..].starts_with(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kinds, StatementKind::*};
+
+    #[test]
+    fn skips_comments() {
+        assert_eq!(kinds("-- delete\nselect 1"), [Select]);
+        assert_eq!(kinds("/* drop table t; */ select 1"), [Select]);
+        assert_eq!(kinds("select 1 -- ; delete from t"), [Select]);
+    }
+
+    #[test]
+    fn skips_quotes() {
+        assert_eq!(kinds("select 'a; delete from t'"), [Select]);
+        assert_eq!(kinds("select 'it''s; drop'"), [Select]);
+        assert_eq!(
+            kinds(r#"select "delete"; update t set a = 1"#),
+            [Select, Update]
+        );
+        assert_eq!(kinds("select `a;b`, [c;d] from t"), [Select]);
+    }
+
+    #[test]
+    fn skips_dollar_quotes() {
+        assert_eq!(kinds("select $$; delete from t$$"), [Select]);
+        assert_eq!(kinds("select $tag$ $$; drop $tag$"), [Select]);
+        assert_eq!(
+            kinds("select * from t where a = $1; delete from t"),
+            [Select, Delete]
+        );
+    }
+
+    #[test]
+    fn classifies_with_queries() {
+        assert_eq!(kinds("with a as (select 1) select * from a"), [Select]);
+        assert_eq!(
+            kinds("with a as (delete from t returning *) select * from a"),
+            [Delete]
+        );
+        assert_eq!(
+            kinds("with a as (select 1) insert into t select * from a"),
+            [Insert]
+        );
+    }
+
+    #[test]
+    fn splits_statements() {
+        assert_eq!(
+            kinds("begin; insert into t values (1);; commit;"),
+            [Transaction, Insert, Transaction]
+        );
+        assert_eq!(
+            kinds("PRAGMA foreign_keys = ON; ATTACH 'b.db' AS b"),
+            [Pragma, Attach]
+        );
+        assert!(kinds("  ; -- nothing\n").is_empty());
+    }
+}
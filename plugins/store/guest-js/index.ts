@@ -2,14 +2,67 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
-import { invoke } from "@tauri-apps/api/tauri";
+import { convertFileSrc, invoke } from "@tauri-apps/api/tauri";
 import { UnlistenFn } from "@tauri-apps/api/event";
 import { appWindow } from "@tauri-apps/api/window";
 
-interface ChangePayload<T> {
-  path: string;
-  key: string;
-  value: T | null;
+import type { BatchChange } from "./types/BatchChange";
+import type { Corrupted } from "./types/Corrupted";
+import type { ErrorPayload } from "./types/ErrorPayload";
+import type { KeyChange } from "./types/KeyChange";
+import type { Page } from "./types/Page";
+
+export type { Corrupted, ErrorPayload, KeyChange, Page };
+
+export type Operation =
+  | { op: "set"; key: string; value: unknown; ttl?: number }
+  | { op: "delete"; key: string };
+
+function toBase64(bytes: Uint8Array): string {
+  let binary = "";
+  // in chunks, as `String.fromCharCode` takes its arguments on the stack
+  for (let i = 0; i < bytes.length; i += 0x8000) {
+    binary += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+  }
+  return btoa(binary);
+}
+
+function fromBase64(base64: string): Uint8Array {
+  const binary = atob(base64);
+  const bytes = new Uint8Array(binary.length);
+  for (let i = 0; i < binary.length; i++) {
+    bytes[i] = binary.charCodeAt(i);
+  }
+  return bytes;
+}
+
+/**
+ * Calls the handler with each change of the store, emitted one by one or at once by a batch.
+ */
+async function listenChanges(
+  path: string,
+  handler: (change: KeyChange) => void
+): Promise<UnlistenFn> {
+  const unlistenChange = await appWindow.listen<KeyChange>(
+    "store://change",
+    (event) => {
+      if (event.payload.path === path) {
+        handler(event.payload);
+      }
+    }
+  );
+  const unlistenBatch = await appWindow.listen<BatchChange>(
+    "store://batch",
+    (event) => {
+      if (event.payload.path === path) {
+        event.payload.changes.forEach(handler);
+      }
+    }
+  );
+  return () => {
+    unlistenChange();
+    unlistenBatch();
+  };
 }
 
 /**
@@ -26,13 +79,37 @@ export class Store {
    *
    * @param key
    * @param value
+   * @param options `ttl` expires the key after the milliseconds, e.g. for a cached response.
    * @returns
    */
-  async set(key: string, value: unknown): Promise<void> {
+  async set(
+    key: string,
+    value: unknown,
+    options?: { ttl?: number }
+  ): Promise<void> {
     await invoke("plugin:store|set", {
       path: this.path,
       key,
       value,
+      ttl: options?.ttl,
+    });
+  }
+
+  /**
+   * Applies the operations at once, so the other windows never see them half applied, with a single change event.
+   *
+   * @param operations `set` operations take an optional `ttl` in milliseconds.
+   * @param options `save` saves the store once the operations are applied.
+   * @returns
+   */
+  async batch(
+    operations: Operation[],
+    options?: { save?: boolean }
+  ): Promise<void> {
+    await invoke("plugin:store|batch", {
+      path: this.path,
+      operations,
+      save: options?.save,
     });
   }
 
@@ -75,6 +152,76 @@ export class Store {
     });
   }
 
+  /**
+   * Sets the binary value of the key, saved as it is. The binary values have their own keys.
+   *
+   * The value is sent as base64, there's no raw channel for the writes: `binaryUrl` only serves the reads.
+   *
+   * @param key
+   * @param value
+   * @returns
+   */
+  async setBinary(key: string, value: Uint8Array): Promise<void> {
+    await invoke("plugin:store|set_binary", {
+      path: this.path,
+      key,
+      value: toBase64(value),
+    });
+  }
+
+  /**
+   * Returns the binary value of the key or `null` when it isn't set.
+   *
+   * Prefer fetching `binaryUrl` for the large values when the app registered the protocol.
+   * @param key
+   * @returns
+   */
+  async getBinary(key: string): Promise<Uint8Array | null> {
+    const value = await invoke<string | null>("plugin:store|get_binary", {
+      path: this.path,
+      key,
+    });
+    return value === null ? null : fromBase64(value);
+  }
+
+  /**
+   * Removes the binary value of the key.
+   *
+   * @param key
+   * @returns
+   */
+  async deleteBinary(key: string): Promise<boolean> {
+    return await invoke("plugin:store|delete_binary", {
+      path: this.path,
+      key,
+    });
+  }
+
+  /**
+   * Returns the keys of the binary values.
+   *
+   * @returns
+   */
+  async binaryKeys(): Promise<string[]> {
+    return await invoke("plugin:store|binary_keys", {
+      path: this.path,
+    });
+  }
+
+  /**
+   * The URL serving the binary value of the key without encoding it, e.g. for `fetch` or an `img` source.
+   *
+   * The app must register `binary_protocol` as the `store` URI scheme.
+   * @param key
+   * @returns
+   */
+  binaryUrl(key: string): string {
+    return convertFileSrc(
+      `${encodeURIComponent(this.path)}/${encodeURIComponent(key)}`,
+      "store"
+    );
+  }
+
   /**
    * Clears the store, removing all key-value pairs.
    *
@@ -132,6 +279,39 @@ export class Store {
     });
   }
 
+  /**
+   * Returns the keys from the offset up to the limit, by their order, e.g. to list a big store page by page.
+   *
+   * @param offset
+   * @param limit
+   * @returns
+   */
+  async keysPage(offset: number, limit: number): Promise<Page<string>> {
+    return await invoke("plugin:store|keys_page", {
+      path: this.path,
+      offset,
+      limit,
+    });
+  }
+
+  /**
+   * Returns the entries from the offset up to the limit, by the order of their keys.
+   *
+   * @param offset
+   * @param limit
+   * @returns
+   */
+  async entriesPage<T>(
+    offset: number,
+    limit: number
+  ): Promise<Page<[key: string, value: T]>> {
+    return await invoke("plugin:store|entries_page", {
+      path: this.path,
+      offset,
+      limit,
+    });
+  }
+
   /**
    * Returns the number of key-value pairs in the store.
    *
@@ -160,8 +340,8 @@ export class Store {
   /**
    * Saves the store to disk at the stores `path`.
    *
-   * As the store is only persistet to disk before the apps exit, changes might be lost in a crash.
-   * This method let's you persist the store to disk whenever you deem necessary.
+   * Unless the store has a save strategy set in Rust, it's only persisted to disk before the app exits and
+   * changes might be lost in a crash. This method let's you persist the store to disk whenever you deem necessary.
    * @returns
    */
   async save(): Promise<void> {
@@ -171,40 +351,32 @@ export class Store {
   }
 
   /**
-   * Listen to changes on a store key.
+   * Listen to changes on a store key, made by any window or from Rust.
    * @param key
-   * @param cb
+   * @param cb Called with the new value and the previous one, `null` when the key isn't set.
    * @returns A promise resolving to a function to unlisten to the event.
    */
   async onKeyChange<T>(
     key: string,
-    cb: (value: T | null) => void
+    cb: (value: T | null, oldValue: T | null) => void
   ): Promise<UnlistenFn> {
-    return await appWindow.listen<ChangePayload<T>>(
-      "store://change",
-      (event) => {
-        if (event.payload.path === this.path && event.payload.key === key) {
-          cb(event.payload.value);
-        }
+    return await listenChanges(this.path, (change) => {
+      if (change.key === key) {
+        cb(change.value as T | null, change.oldValue as T | null);
       }
-    );
+    });
   }
 
   /**
-   * Listen to changes on the store.
+   * Listen to changes on the store, made by any window or from Rust.
    * @param cb
    * @returns A promise resolving to a function to unlisten to the event.
    */
   async onChange(
-    cb: (key: string, value: unknown) => void
+    cb: (key: string, value: unknown, oldValue: unknown) => void
   ): Promise<UnlistenFn> {
-    return await appWindow.listen<ChangePayload<unknown>>(
-      "store://change",
-      (event) => {
-        if (event.payload.path === this.path) {
-          cb(event.payload.key, event.payload.value);
-        }
-      }
-    );
+    return await listenChanges(this.path, (change) => {
+      cb(change.key, change.value, change.oldValue);
+    });
   }
 }
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KeyChange } from "./KeyChange";
+
+export interface BatchChange { path: string, changes: Array<KeyChange>, }
\ No newline at end of file
@@ -0,0 +1,316 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Persists the entries of the stores, in a file by default.
+
+use crate::{
+    compression::{self, Compression},
+    encryption, Error,
+};
+use serde_json::Value as JsonValue;
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fs::{create_dir_all, read, remove_file, File},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteBackend;
+
+pub(crate) type SerializeFn =
+    fn(&HashMap<String, JsonValue>) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+pub(crate) type DeserializeFn =
+    fn(&[u8]) -> Result<HashMap<String, JsonValue>, Box<dyn std::error::Error>>;
+
+/// Loads and saves the entries of the stores, selected with [`StoreBuilder::backend`](crate::StoreBuilder::backend).
+pub trait StoreBackend: Send + Sync + 'static {
+    /// Reads the entries saved at the path of the store in the app data directory, `None` when nothing was saved
+    /// there so the store keeps its defaults.
+    fn load(&self, path: &Path) -> Result<Option<HashMap<String, JsonValue>>, Error>;
+
+    /// Saves the entries of the store at its path.
+    ///
+    /// `changed` has the keys set or removed since the entries were loaded or last saved, for the backends writing
+    /// only the changes. It's `None` when all of them must be written, replacing what was saved.
+    fn save(
+        &self,
+        path: &Path,
+        entries: &HashMap<String, JsonValue>,
+        changed: Option<&HashSet<String>>,
+    ) -> Result<(), Error>;
+
+    /// Reads the last good entries of the store once [`StoreBackend::load`] found them corrupted, e.g. from a
+    /// backup. `None` by default.
+    fn load_backup(&self, path: &Path) -> Result<Option<HashMap<String, JsonValue>>, Error> {
+        let _ = path;
+        Ok(None)
+    }
+
+    /// Reads the binary values saved at the path of the store, none by default.
+    fn load_binary(&self, path: &Path) -> Result<HashMap<String, Vec<u8>>, Error> {
+        let _ = path;
+        Ok(Default::default())
+    }
+
+    /// Saves the binary values of the store, `changed` as for [`StoreBackend::save`].
+    ///
+    /// The backends without binary values fail with [`Error::BinaryUnsupported`] by default.
+    fn save_binary(
+        &self,
+        path: &Path,
+        values: &HashMap<String, Vec<u8>>,
+        changed: Option<&HashSet<String>>,
+    ) -> Result<(), Error> {
+        let _ = (path, changed);
+        if values.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::BinaryUnsupported)
+        }
+    }
+}
+
+/// Starts the files of the binary values, followed by each key and value prefixed by their length.
+const BINARY_MAGIC: &[u8] = b"tauri-store:binary:1\n";
+
+/// The file of the binary values, next to the store file.
+fn binary_path(path: &Path) -> PathBuf {
+    let mut binary_path = OsString::from(path);
+    binary_path.push(".bin");
+    binary_path.into()
+}
+
+fn encode_binary(values: &HashMap<String, Vec<u8>>) -> Vec<u8> {
+    let len = values
+        .iter()
+        .map(|(key, value)| 12 + key.len() + value.len())
+        .sum::<usize>();
+    let mut bytes = Vec::with_capacity(BINARY_MAGIC.len() + len);
+    bytes.extend_from_slice(BINARY_MAGIC);
+    for (key, value) in values {
+        bytes.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(key.as_bytes());
+        bytes.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(value);
+    }
+    bytes
+}
+
+fn decode_binary(bytes: &[u8]) -> Result<HashMap<String, Vec<u8>>, Error> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], Error> {
+        if bytes.len() < len {
+            return Err(Error::Deserialize(
+                "the binary values of the store are truncated".into(),
+            ));
+        }
+        let (taken, rest) = bytes.split_at(len);
+        *bytes = rest;
+        Ok(taken)
+    }
+
+    let mut bytes = bytes.strip_prefix(BINARY_MAGIC).ok_or_else(|| {
+        Error::Deserialize("the file isn't made of the binary values of a store".into())
+    })?;
+    let mut values = HashMap::new();
+    while !bytes.is_empty() {
+        let len = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap()) as usize;
+        let key = String::from_utf8(take(&mut bytes, len)?.to_vec())
+            .map_err(|e| Error::Deserialize(e.into()))?;
+        let len = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap()) as usize;
+        values.insert(key, take(&mut bytes, len)?.to_vec());
+    }
+    Ok(values)
+}
+
+/// Starts the files with a checksum, followed by the CRC-32 of the rest of the file.
+const CHECKSUM_MAGIC: &[u8] = b"tauri-store:crc32:";
+
+/// The last good store file, next to the store file.
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup_path = OsString::from(path);
+    backup_path.push(".bak");
+    backup_path.into()
+}
+
+fn write(path: &Path, bytes: &[u8]) -> Result<(), Error> {
+    create_dir_all(path.parent().expect("invalid store path"))?;
+    let mut f = File::create(path)?;
+    f.write_all(bytes)?;
+    Ok(())
+}
+
+/// Saves the whole store in a file, with the serialization, the compression, the checksum and the encryption of
+/// the [`StoreBuilder`](crate::StoreBuilder).
+pub(crate) struct FileBackend {
+    pub(crate) serialize: SerializeFn,
+    pub(crate) deserialize: DeserializeFn,
+    pub(crate) compression: Compression,
+    pub(crate) checksum: bool,
+    pub(crate) backup: bool,
+    #[cfg(feature = "encryption")]
+    pub(crate) encryption_key: Option<[u8; 32]>,
+}
+
+impl FileBackend {
+    fn encode(&self, bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let mut bytes = compression::compress(self.compression, bytes)?;
+        if self.checksum {
+            let checksum = crc32fast::hash(&bytes).to_le_bytes();
+            bytes = [CHECKSUM_MAGIC, &checksum, &bytes].concat();
+        }
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.encryption_key {
+            bytes = encryption::encrypt(key, &bytes)?;
+        }
+        Ok(bytes)
+    }
+
+    /// Decodes the files with or without a checksum, whether the store has one.
+    fn decode(&self, bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let mut bytes = encryption::decrypt(self.encryption_key(), bytes)?;
+        if let Some(framed) = bytes.strip_prefix(CHECKSUM_MAGIC) {
+            if framed.len() < 4 {
+                return Err(Error::Corrupted);
+            }
+            let (checksum, rest) = framed.split_at(4);
+            if crc32fast::hash(rest).to_le_bytes() != checksum {
+                return Err(Error::Corrupted);
+            }
+            bytes = rest.to_vec();
+        }
+        compression::decompress(bytes)
+    }
+
+    fn read_entries(&self, path: &Path) -> Result<HashMap<String, JsonValue>, Error> {
+        let bytes = self.decode(read(path)?)?;
+        (self.deserialize)(&bytes).map_err(Error::Deserialize)
+    }
+
+    fn encryption_key(&self) -> Option<&[u8; 32]> {
+        #[cfg(feature = "encryption")]
+        {
+            self.encryption_key.as_ref()
+        }
+        #[cfg(not(feature = "encryption"))]
+        {
+            None
+        }
+    }
+}
+
+impl StoreBackend for FileBackend {
+    fn load(&self, path: &Path) -> Result<Option<HashMap<String, JsonValue>>, Error> {
+        self.read_entries(path).map(Some)
+    }
+
+    fn load_backup(&self, path: &Path) -> Result<Option<HashMap<String, JsonValue>>, Error> {
+        if !self.backup {
+            return Ok(None);
+        }
+        match self.read_entries(&backup_path(path)) {
+            Err(Error::Io(e)) if e.kind() == ErrorKind::NotFound => Ok(None),
+            entries => entries.map(Some),
+        }
+    }
+
+    fn save(
+        &self,
+        path: &Path,
+        entries: &HashMap<String, JsonValue>,
+        _changed: Option<&HashSet<String>>,
+    ) -> Result<(), Error> {
+        let bytes = self.encode((self.serialize)(entries).map_err(Error::Serialize)?)?;
+        write(path, &bytes)?;
+        // once the store file is complete, so a save interrupted on either file leaves the other one good
+        if self.backup {
+            write(&backup_path(path), &bytes)?;
+        }
+
+        Ok(())
+    }
+
+    fn load_binary(&self, path: &Path) -> Result<HashMap<String, Vec<u8>>, Error> {
+        match read(binary_path(path)) {
+            Ok(bytes) => decode_binary(&self.decode(bytes)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Default::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save_binary(
+        &self,
+        path: &Path,
+        values: &HashMap<String, Vec<u8>>,
+        _changed: Option<&HashSet<String>>,
+    ) -> Result<(), Error> {
+        let path = binary_path(path);
+        if values.is_empty() {
+            return match remove_file(path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+        write(&path, &self.encode(encode_binary(values))?)
+    }
+}
+
+/// Keeps the saved entries in memory until the app exits, e.g. for caches or tests.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    stores: Mutex<HashMap<PathBuf, HashMap<String, JsonValue>>>,
+    binary: Mutex<HashMap<PathBuf, HashMap<String, Vec<u8>>>>,
+}
+
+impl StoreBackend for MemoryBackend {
+    fn load(&self, path: &Path) -> Result<Option<HashMap<String, JsonValue>>, Error> {
+        Ok(self
+            .stores
+            .lock()
+            .expect("mutex poisoned")
+            .get(path)
+            .cloned())
+    }
+
+    fn save(
+        &self,
+        path: &Path,
+        entries: &HashMap<String, JsonValue>,
+        _changed: Option<&HashSet<String>>,
+    ) -> Result<(), Error> {
+        self.stores
+            .lock()
+            .expect("mutex poisoned")
+            .insert(path.to_path_buf(), entries.clone());
+        Ok(())
+    }
+
+    fn load_binary(&self, path: &Path) -> Result<HashMap<String, Vec<u8>>, Error> {
+        Ok(self
+            .binary
+            .lock()
+            .expect("mutex poisoned")
+            .get(path)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn save_binary(
+        &self,
+        path: &Path,
+        values: &HashMap<String, Vec<u8>>,
+        _changed: Option<&HashSet<String>>,
+    ) -> Result<(), Error> {
+        self.binary
+            .lock()
+            .expect("mutex poisoned")
+            .insert(path.to_path_buf(), values.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use super::StoreBackend;
+use crate::Error;
+use futures::executor::block_on;
+use serde_json::Value as JsonValue;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteConnection},
+    Connection, Row,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Saves the store in a SQLite database at its path, one row per key, with the `sqlite` feature.
+///
+/// Only the keys changed since the last save are written, which keeps saving the stores with many entries fast.
+#[derive(Debug, Default)]
+pub struct SqliteBackend {
+    connections: Mutex<HashMap<PathBuf, SqliteConnection>>,
+}
+
+impl SqliteBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_connection<T>(
+        &self,
+        path: &Path,
+        f: impl FnOnce(&mut SqliteConnection) -> Result<T, sqlx::Error>,
+    ) -> Result<T, Error> {
+        let mut connections = self.connections.lock().expect("mutex poisoned");
+        let connection = match connections.entry(path.to_path_buf()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                create_dir_all(path.parent().expect("invalid store path"))?;
+                let options = SqliteConnectOptions::new()
+                    .filename(path)
+                    .create_if_missing(true);
+                let mut connection = block_on(SqliteConnection::connect_with(&options))?;
+                block_on(
+                    sqlx::query(
+                        "CREATE TABLE IF NOT EXISTS entries (key TEXT PRIMARY KEY NOT NULL, value TEXT NOT NULL);
+                        CREATE TABLE IF NOT EXISTS binary (key TEXT PRIMARY KEY NOT NULL, value BLOB NOT NULL)",
+                    )
+                    .execute(&mut connection),
+                )?;
+                entry.insert(connection)
+            }
+        };
+        Ok(f(connection)?)
+    }
+}
+
+impl StoreBackend for SqliteBackend {
+    fn load(&self, path: &Path) -> Result<Option<HashMap<String, JsonValue>>, Error> {
+        let rows = self.with_connection(path, |connection| {
+            block_on(sqlx::query("SELECT key, value FROM entries").fetch_all(connection))
+        })?;
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let mut entries = HashMap::with_capacity(rows.len());
+        for row in rows {
+            let value: String = row.try_get("value")?;
+            entries.insert(row.try_get("key")?, serde_json::from_str(&value)?);
+        }
+        Ok(Some(entries))
+    }
+
+    fn save(
+        &self,
+        path: &Path,
+        entries: &HashMap<String, JsonValue>,
+        changed: Option<&HashSet<String>>,
+    ) -> Result<(), Error> {
+        let mut writes = Vec::new();
+        let mut deletes = Vec::new();
+        match changed {
+            Some(changed) => {
+                for key in changed {
+                    match entries.get(key) {
+                        Some(value) => writes.push((key, serde_json::to_string(value)?)),
+                        None => deletes.push(key),
+                    }
+                }
+            }
+            None => {
+                for (key, value) in entries {
+                    writes.push((key, serde_json::to_string(value)?));
+                }
+            }
+        }
+
+        self.with_connection(path, |connection| {
+            block_on(async {
+                let mut transaction = connection.begin().await?;
+                if changed.is_none() {
+                    sqlx::query("DELETE FROM entries")
+                        .execute(&mut transaction)
+                        .await?;
+                }
+                for key in deletes {
+                    sqlx::query("DELETE FROM entries WHERE key = ?")
+                        .bind(key)
+                        .execute(&mut transaction)
+                        .await?;
+                }
+                for (key, value) in writes {
+                    sqlx::query("INSERT OR REPLACE INTO entries (key, value) VALUES (?, ?)")
+                        .bind(key)
+                        .bind(value)
+                        .execute(&mut transaction)
+                        .await?;
+                }
+                transaction.commit().await
+            })
+        })
+    }
+
+    fn load_binary(&self, path: &Path) -> Result<HashMap<String, Vec<u8>>, Error> {
+        let rows = self.with_connection(path, |connection| {
+            block_on(sqlx::query("SELECT key, value FROM binary").fetch_all(connection))
+        })?;
+
+        let mut values = HashMap::with_capacity(rows.len());
+        for row in rows {
+            values.insert(row.try_get("key")?, row.try_get("value")?);
+        }
+        Ok(values)
+    }
+
+    fn save_binary(
+        &self,
+        path: &Path,
+        values: &HashMap<String, Vec<u8>>,
+        changed: Option<&HashSet<String>>,
+    ) -> Result<(), Error> {
+        self.with_connection(path, |connection| {
+            block_on(async {
+                let mut transaction = connection.begin().await?;
+                let keys = match changed {
+                    Some(changed) => changed.iter().collect::<Vec<_>>(),
+                    None => {
+                        sqlx::query("DELETE FROM binary")
+                            .execute(&mut transaction)
+                            .await?;
+                        values.keys().collect()
+                    }
+                };
+                for key in keys {
+                    match values.get(key) {
+                        Some(value) => {
+                            sqlx::query("INSERT OR REPLACE INTO binary (key, value) VALUES (?, ?)")
+                                .bind(key)
+                                .bind(value)
+                                .execute(&mut transaction)
+                                .await?;
+                        }
+                        None => {
+                            sqlx::query("DELETE FROM binary WHERE key = ?")
+                                .bind(key)
+                                .execute(&mut transaction)
+                                .await?;
+                        }
+                    }
+                }
+                transaction.commit().await
+            })
+        })
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Compresses the store files with gzip or zstd, with the `gzip` and `zstd` features.
+
+use crate::Error;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use std::io::Read;
+#[cfg(feature = "gzip")]
+use std::io::Write;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// How the store files are compressed. They're decompressed by their magic bytes whatever the compression is,
+/// so the files saved before it changed still open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    #[default]
+    None,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Smaller and faster than gzip.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+pub(crate) fn compress(compression: Compression, bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    match compression {
+        Compression::None => Ok(bytes),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&bytes)?;
+            Ok(encoder.finish()?)
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(zstd::encode_all(bytes.as_slice(), 0)?),
+    }
+}
+
+/// Decompresses the gzip and zstd files, the other ones are returned as they are.
+pub(crate) fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if bytes.starts_with(GZIP_MAGIC) {
+        #[cfg(feature = "gzip")]
+        {
+            let mut decompressed = Vec::new();
+            flate2::read::GzDecoder::new(bytes.as_slice())
+                .read_to_end(&mut decompressed)
+                .map_err(Error::Decompress)?;
+            return Ok(decompressed);
+        }
+        #[cfg(not(feature = "gzip"))]
+        return Err(Error::Compressed("gzip"));
+    }
+    if bytes.starts_with(ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        {
+            let mut decompressed = Vec::new();
+            zstd::Decoder::new(bytes.as_slice())
+                .and_then(|mut decoder| decoder.read_to_end(&mut decompressed))
+                .map_err(Error::Decompress)?;
+            return Ok(decompressed);
+        }
+        #[cfg(not(feature = "zstd"))]
+        return Err(Error::Compressed("zstd"));
+    }
+    Ok(bytes)
+}
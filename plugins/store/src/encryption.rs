@@ -0,0 +1,50 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Encrypts the store files with XChaCha20-Poly1305 when the app sets a key, with the `encryption` feature.
+
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    Key, XChaCha20Poly1305, XNonce,
+};
+
+use crate::Error;
+
+/// Starts the encrypted store files, followed by the nonce and the encrypted store.
+const MAGIC: &[u8] = b"tauri-store:xchacha20poly1305:";
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 24;
+
+#[cfg(feature = "encryption")]
+pub(crate) fn encrypt(key: &[u8; 32], bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let encrypted = cipher
+        .encrypt(XNonce::from_slice(&nonce), bytes)
+        .map_err(|_| Error::Encrypt)?;
+    Ok([MAGIC, &nonce, &encrypted].concat())
+}
+
+/// Decrypts the store file, the plain files are returned as they are so they're encrypted on the next save.
+pub(crate) fn decrypt(key: Option<&[u8; 32]>, bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if !bytes.starts_with(MAGIC) {
+        return Ok(bytes);
+    }
+    #[cfg(feature = "encryption")]
+    if let Some(key) = key {
+        let encrypted = &bytes[MAGIC.len()..];
+        if encrypted.len() < NONCE_LEN {
+            return Err(Error::Decrypt);
+        }
+        let (nonce, encrypted) = encrypted.split_at(NONCE_LEN);
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+        return cipher
+            .decrypt(XNonce::from_slice(nonce), encrypted)
+            .map_err(|_| Error::Decrypt);
+    }
+    #[cfg(not(feature = "encryption"))]
+    let _ = key;
+    Err(Error::Encrypted)
+}
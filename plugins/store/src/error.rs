@@ -4,6 +4,7 @@
 
 use serde::{Serialize, Serializer};
 use std::path::PathBuf;
+use tauri_plugin_common::{error, PluginError};
 
 /// The error types.
 #[derive(thiserror::Error, Debug)]
@@ -16,12 +17,49 @@ pub enum Error {
     /// JSON error.
     #[error(transparent)]
     Json(#[from] serde_json::Error),
+    /// The value of the key doesn't match the type it's read as or can't be written as JSON.
+    #[error("the value of the key `{key}` doesn't match its type: {source}")]
+    Value {
+        key: String,
+        source: serde_json::Error,
+    },
     /// IO error.
     #[error(transparent)]
     Io(#[from] std::io::Error),
     /// Store not found
     #[error("Store \"{0}\" not found")]
     NotFound(PathBuf),
+    /// The store file is encrypted and the store has no key.
+    #[error(
+        "the store file is encrypted, its key must be set with `StoreBuilder::encryption_key`"
+    )]
+    Encrypted,
+    /// The store was saved by a newer version of the app.
+    #[error("the store has the version {0}, this version of the app migrates up to {1}")]
+    UnsupportedVersion(u32, u32),
+    /// SQLite error of the [`SqliteBackend`](crate::SqliteBackend).
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    Sqlite(#[from] sqlx::Error),
+    /// The [`StoreBackend`](crate::StoreBackend) of the store doesn't save binary values.
+    #[error("the backend of the store doesn't save binary values")]
+    BinaryUnsupported,
+    /// The binary value sent from JavaScript isn't valid base64.
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+    /// The store file is compressed without the feature decompressing it.
+    #[error("the store file is compressed with {0}, its `{0}` feature must be enabled")]
+    Compressed(&'static str),
+    #[error("failed to decompress the store file: {0}")]
+    Decompress(std::io::Error),
+    /// The checksum of the store file doesn't match.
+    #[error("the store file is corrupted, its checksum doesn't match")]
+    Corrupted,
+    #[error("failed to encrypt the store file")]
+    Encrypt,
+    /// The key is wrong or the file was modified.
+    #[error("failed to decrypt the store file, the key is wrong or the file was modified")]
+    Decrypt,
 }
 
 impl Serialize for Error {
@@ -29,6 +67,46 @@ impl Serialize for Error {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "store";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Serialize(..) => "serialize",
+            Self::Deserialize(..) => "deserialize",
+            Self::Json(..) => "json",
+            Self::Value { .. } => "value",
+            Self::Io(..) => "io",
+            Self::NotFound(..) => "not_found",
+            Self::UnsupportedVersion(..) => "unsupported_version",
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(..) => "sqlite",
+            Self::BinaryUnsupported => "binary_unsupported",
+            Self::Base64(..) => "base64",
+            Self::Compressed(..) => "compressed",
+            Self::Decompress(..) => "decompress",
+            Self::Corrupted => "corrupted",
+            Self::Encrypted => "encrypted",
+            Self::Encrypt => "encrypt",
+            Self::Decrypt => "decrypt",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
     }
 }
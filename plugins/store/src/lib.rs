@@ -2,36 +2,279 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
+#[cfg(feature = "sqlite")]
+pub use backend::SqliteBackend;
+pub use backend::{MemoryBackend, StoreBackend};
+pub use compression::Compression;
 pub use error::Error;
 use log::warn;
-use serde::Serialize;
+use percent_encoding::percent_decode_str;
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value as JsonValue;
-use std::{collections::HashMap, path::PathBuf, sync::Mutex};
-pub use store::{Store, StoreBuilder};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::spawn,
+    time::{Duration, Instant},
+};
+pub use store::{Operation, Page, SaveStrategy, Store, StoreBuilder};
 use tauri::{
+    http::{Request as HttpRequest, Response as HttpResponse, ResponseBuilder},
     plugin::{self, TauriPlugin},
-    AppHandle, Manager, RunEvent, Runtime, State, Window,
+    utils::config::{AppUrl, WindowUrl},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{
+    shutdown::{self, Stage},
+    DiagnosticsExt, Report, ShutdownExt,
 };
 
+mod backend;
+mod compression;
+mod encryption;
 mod error;
 mod store;
 
-#[derive(Serialize, Clone)]
-struct ChangePayload {
+/// A change of a key, `Null` for a key that wasn't set or was removed.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct KeyChange {
+    pub path: PathBuf,
+    pub key: String,
+    #[cfg_attr(test, ts(type = "unknown"))]
+    pub value: JsonValue,
+    #[cfg_attr(test, ts(type = "unknown"))]
+    pub old_value: JsonValue,
+}
+
+/// The payload of the `store://corrupted` event, emitted when a store file fails to load as it's corrupted.
+#[derive(Debug, Serialize, Clone)]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct Corrupted {
+    pub path: PathBuf,
+    pub error: String,
+    /// Whether the store was restored from its backup, it has its defaults otherwise.
+    pub recovered: bool,
+}
+
+/// The changes of a batch, emitted at once.
+#[derive(Debug, Serialize, Clone)]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+struct BatchChange<'a> {
+    path: &'a Path,
+    // not a slice, which ts-rs exports without its element type
+    changes: &'a Vec<KeyChange>,
+}
+
+/// Identifies a callback registered by [`StoreCollection::on_key_change`] or [`StoreCollection::on_change`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchId(u64);
+
+struct Watcher {
+    id: WatchId,
     path: PathBuf,
-    key: String,
-    value: JsonValue,
+    /// `None` for all the keys of the store.
+    key: Option<String>,
+    callback: Box<dyn Fn(&KeyChange) + Send + Sync>,
 }
 
+/// The stores of the plugin.
 #[derive(Default)]
-struct StoreCollection {
+pub struct StoreCollection {
     stores: Mutex<HashMap<PathBuf, Store>>,
     frozen: bool,
+    watchers: Mutex<Vec<Arc<Watcher>>>,
+    next_watch_id: AtomicU64,
+    /// Sends the debounced stores to the thread saving them.
+    saver: Mutex<Option<Sender<(PathBuf, Duration)>>>,
+}
+
+impl StoreCollection {
+    /// Calls the callback every time the key of the store changes.
+    pub fn on_key_change<P, K, F>(&self, path: P, key: K, callback: F) -> WatchId
+    where
+        P: Into<PathBuf>,
+        K: Into<String>,
+        F: Fn(&KeyChange) + Send + Sync + 'static,
+    {
+        self.watch(path.into(), Some(key.into()), Box::new(callback))
+    }
+
+    /// Calls the callback every time a key of the store changes.
+    pub fn on_change<P, F>(&self, path: P, callback: F) -> WatchId
+    where
+        P: Into<PathBuf>,
+        F: Fn(&KeyChange) + Send + Sync + 'static,
+    {
+        self.watch(path.into(), None, Box::new(callback))
+    }
+
+    /// Removes the callback, returns whether it was registered.
+    pub fn unwatch(&self, id: WatchId) -> bool {
+        let mut watchers = self.watchers.lock().expect("mutex poisoned");
+        let len = watchers.len();
+        watchers.retain(|watcher| watcher.id != id);
+        watchers.len() != len
+    }
+
+    fn watch(
+        &self,
+        path: PathBuf,
+        key: Option<String>,
+        callback: Box<dyn Fn(&KeyChange) + Send + Sync>,
+    ) -> WatchId {
+        let id = WatchId(self.next_watch_id.fetch_add(1, Ordering::Relaxed));
+        self.watchers
+            .lock()
+            .expect("mutex poisoned")
+            .push(Arc::new(Watcher {
+                id,
+                path,
+                key,
+                callback,
+            }));
+        id
+    }
+
+    /// Reads the value of the key as `T`, its default when the key isn't set, lets the callback update it and sets
+    /// it back, with its change event.
+    pub fn with_store_typed<R, P, T, U, F>(
+        &self,
+        app: &AppHandle<R>,
+        path: P,
+        key: &str,
+        f: F,
+    ) -> Result<U, Error>
+    where
+        R: Runtime,
+        P: Into<PathBuf>,
+        T: Serialize + DeserializeOwned + Default,
+        F: FnOnce(&mut T) -> U,
+    {
+        let path = path.into();
+        let (output, changes) = with_store(app, self, path.clone(), |store| {
+            let mut value = store.get_de::<T>(key)?.unwrap_or_default();
+            let output = f(&mut value);
+            let old_value = store.set_ser(key.into(), &value)?;
+            Ok((output, diff(&path, store, [(key.to_string(), old_value)])))
+        })?;
+        self.notify(app, changes);
+        Ok(output)
+    }
+
+    /// Applies the operations to the store at once, then saves it when `save` is set. The changes are kept when
+    /// saving fails.
+    ///
+    /// The windows get a single `store://batch` event with the changes, and the callbacks are called once all of
+    /// them are applied.
+    pub fn batch<R, P, I>(
+        &self,
+        app: &AppHandle<R>,
+        path: P,
+        operations: I,
+        save: bool,
+    ) -> Result<(), Error>
+    where
+        R: Runtime,
+        P: Into<PathBuf>,
+        I: IntoIterator<Item = Operation>,
+    {
+        let path = path.into();
+        let (changes, saved) = with_store(app, self, path.clone(), |store| {
+            let previous = store.batch(operations);
+            let changes = diff(&path, store, previous);
+            let saved = if save { store.save(app) } else { Ok(()) };
+            Ok((changes, saved))
+        })?;
+        if !changes.is_empty() {
+            let _ = app.emit_all(
+                "store://batch",
+                BatchChange {
+                    path: &path,
+                    changes: &changes,
+                },
+            );
+            self.call_watchers(&changes);
+            if !save {
+                self.auto_save(app, &path);
+            }
+        }
+        saved
+    }
+
+    /// Emits the changes to all the windows and calls the callbacks watching them, once the store is unlocked
+    /// so they can use it.
+    fn notify<R: Runtime>(&self, app: &AppHandle<R>, changes: Vec<KeyChange>) {
+        for change in &changes {
+            let _ = app.emit_all("store://change", change);
+        }
+        self.call_watchers(&changes);
+        if let Some(change) = changes.first() {
+            self.auto_save(app, &change.path);
+        }
+    }
+
+    /// Saves the changed store with its [`SaveStrategy`].
+    fn auto_save<R: Runtime>(&self, app: &AppHandle<R>, path: &Path) {
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        let Some(store) = stores.get_mut(path) else {
+            return;
+        };
+        match store.save_strategy {
+            SaveStrategy::OnChange => log_save(app, store),
+            SaveStrategy::Debounced(debounce) => {
+                let saver = self.saver.lock().expect("mutex poisoned");
+                // the saver only stops when it panicked
+                let sent = match saver.as_ref() {
+                    Some(saver) => saver.send((path.into(), debounce)).is_ok(),
+                    None => false,
+                };
+                if !sent {
+                    log_save(app, store);
+                }
+            }
+            SaveStrategy::OnExit => (),
+        }
+    }
+
+    fn call_watchers(&self, changes: &[KeyChange]) {
+        if changes.is_empty() {
+            return;
+        }
+        let watchers = self.watchers.lock().expect("mutex poisoned").clone();
+        for change in changes {
+            for watcher in &watchers {
+                let key_matches = match &watcher.key {
+                    Some(key) => *key == change.key,
+                    None => true,
+                };
+                if watcher.path == change.path && key_matches {
+                    (watcher.callback)(change);
+                }
+            }
+        }
+    }
+}
+
+pub trait StoreExt<R: Runtime> {
+    fn stores(&self) -> State<'_, StoreCollection>;
+}
+
+impl<R: Runtime, T: Manager<R>> StoreExt<R> for T {
+    fn stores(&self) -> State<'_, StoreCollection> {
+        self.state::<StoreCollection>()
+    }
 }
 
 fn with_store<R: Runtime, T, F: FnOnce(&mut Store) -> Result<T, Error>>(
     app: &AppHandle<R>,
-    collection: State<'_, StoreCollection>,
+    collection: &StoreCollection,
     path: PathBuf,
     f: F,
 ) -> Result<T, Error> {
@@ -52,25 +295,115 @@ fn with_store<R: Runtime, T, F: FnOnce(&mut Store) -> Result<T, Error>>(
         stores.insert(path.clone(), store);
     }
 
-    f(stores
+    let store = stores
         .get_mut(&path)
-        .expect("failed to retrieve store. This is a bug!"))
+        .expect("failed to retrieve store. This is a bug!");
+    if store.lazy && !store.loaded {
+        if let Err(err) = store.load(app) {
+            warn!(
+                "Failed to load store {:?} from disk: {}. Falling back to default values.",
+                path, err
+            );
+        }
+    }
+    f(store)
+}
+
+fn log_save<R: Runtime>(app: &AppHandle<R>, store: &mut Store) {
+    if let Err(err) = store.save(app) {
+        warn!("failed to save store {:?} with error {:?}", store.path, err);
+    }
+}
+
+/// Saves the debounced stores once their changes stop coming in.
+fn spawn_saver<R: Runtime>(app: AppHandle<R>, changes: Receiver<(PathBuf, Duration)>) {
+    spawn(move || {
+        let mut pending = HashMap::<PathBuf, Instant>::new();
+        loop {
+            let received = match pending.values().min() {
+                Some(deadline) => {
+                    changes.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => changes.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok((path, debounce)) => {
+                    pending.insert(path, Instant::now() + debounce);
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            let now = Instant::now();
+            let due = pending
+                .iter()
+                .filter(|(_, deadline)| **deadline <= now)
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>();
+            if due.is_empty() {
+                continue;
+            }
+            let collection = app.stores();
+            let mut stores = collection.stores.lock().expect("mutex poisoned");
+            for path in due {
+                pending.remove(&path);
+                if let Some(store) = stores.get_mut(&path) {
+                    log_save(&app, store);
+                }
+            }
+        }
+    });
+}
+
+/// The changes of the keys from the previous values to the current ones of the store.
+fn diff(
+    path: &Path,
+    store: &Store,
+    previous: impl IntoIterator<Item = (String, Option<JsonValue>)>,
+) -> Vec<KeyChange> {
+    previous
+        .into_iter()
+        .filter_map(|(key, old_value)| {
+            let value = store.get(&key).cloned();
+            (value != old_value).then(|| KeyChange {
+                path: path.to_path_buf(),
+                key,
+                value: value.unwrap_or(JsonValue::Null),
+                old_value: old_value.unwrap_or(JsonValue::Null),
+            })
+        })
+        .collect()
 }
 
 #[tauri::command]
 async fn set<R: Runtime>(
     app: AppHandle<R>,
-    window: Window<R>,
     stores: State<'_, StoreCollection>,
     path: PathBuf,
     key: String,
     value: JsonValue,
+    ttl: Option<u64>,
 ) -> Result<(), Error> {
-    with_store(&app, stores, path.clone(), |store| {
-        store.cache.insert(key.clone(), value.clone());
-        let _ = window.emit("store://change", ChangePayload { path, key, value });
-        Ok(())
-    })
+    let changes = with_store(&app, &stores, path.clone(), |store| {
+        let old_value = match ttl {
+            Some(ttl) => store.set_with_ttl(key.clone(), value, Duration::from_millis(ttl)),
+            None => store.set(key.clone(), value),
+        };
+        Ok(diff(&path, store, [(key, old_value)]))
+    })?;
+    stores.notify(&app, changes);
+    Ok(())
+}
+
+#[tauri::command]
+async fn batch<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection>,
+    path: PathBuf,
+    operations: Vec<Operation>,
+    save: Option<bool>,
+) -> Result<(), Error> {
+    stores.batch(&app, path, operations, save.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -80,9 +413,7 @@ async fn get<R: Runtime>(
     path: PathBuf,
     key: String,
 ) -> Result<Option<JsonValue>, Error> {
-    with_store(&app, stores, path, |store| {
-        Ok(store.cache.get(&key).cloned())
-    })
+    with_store(&app, &stores, path, |store| Ok(store.get(&key).cloned()))
 }
 
 #[tauri::command]
@@ -92,63 +423,158 @@ async fn has<R: Runtime>(
     path: PathBuf,
     key: String,
 ) -> Result<bool, Error> {
-    with_store(&app, stores, path, |store| {
-        Ok(store.cache.contains_key(&key))
-    })
+    with_store(&app, &stores, path, |store| Ok(store.has(&key)))
 }
 
 #[tauri::command]
 async fn delete<R: Runtime>(
     app: AppHandle<R>,
-    window: Window<R>,
     stores: State<'_, StoreCollection>,
     path: PathBuf,
     key: String,
 ) -> Result<bool, Error> {
-    with_store(&app, stores, path.clone(), |store| {
-        let flag = store.cache.remove(&key).is_some();
-        if flag {
-            let _ = window.emit(
-                "store://change",
-                ChangePayload {
-                    path,
-                    key,
-                    value: JsonValue::Null,
-                },
-            );
-        }
-        Ok(flag)
-    })
+    let changes = with_store(&app, &stores, path.clone(), |store| {
+        let old_value = store.delete(&key);
+        Ok(diff(&path, store, [(key, old_value)]))
+    })?;
+    let flag = !changes.is_empty();
+    stores.notify(&app, changes);
+    Ok(flag)
 }
 
+/// Sets the binary value, sent as base64 as the invoke arguments are JSON.
+///
+/// There's no raw channel for the writes, [`binary_protocol`] only serves the reads: the custom protocols of Tauri 1
+/// don't get the request bodies on every platform.
 #[tauri::command]
-async fn clear<R: Runtime>(
+async fn set_binary<R: Runtime>(
     app: AppHandle<R>,
-    window: Window<R>,
     stores: State<'_, StoreCollection>,
     path: PathBuf,
+    key: String,
+    value: String,
 ) -> Result<(), Error> {
-    with_store(&app, stores, path.clone(), |store| {
-        let keys = store.cache.keys().cloned().collect::<Vec<String>>();
-        store.cache.clear();
-        for key in keys {
-            let _ = window.emit(
-                "store://change",
-                ChangePayload {
-                    path: path.clone(),
-                    key,
-                    value: JsonValue::Null,
-                },
-            );
-        }
+    let value = base64::decode(value)?;
+    with_store(&app, &stores, path.clone(), |store| {
+        store.set_binary(key, value);
         Ok(())
+    })?;
+    stores.auto_save(&app, &path);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_binary<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection>,
+    path: PathBuf,
+    key: String,
+) -> Result<Option<String>, Error> {
+    with_store(&app, &stores, path, |store| {
+        Ok(store.get_binary(&key).map(base64::encode))
     })
 }
 
+#[tauri::command]
+async fn delete_binary<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection>,
+    path: PathBuf,
+    key: String,
+) -> Result<bool, Error> {
+    let deleted = with_store(&app, &stores, path.clone(), |store| {
+        Ok(store.delete_binary(&key).is_some())
+    })?;
+    if deleted {
+        stores.auto_save(&app, &path);
+    }
+    Ok(deleted)
+}
+
+#[tauri::command]
+async fn binary_keys<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection>,
+    path: PathBuf,
+) -> Result<Vec<String>, Error> {
+    with_store(&app, &stores, path, |store| {
+        Ok(store.binary_keys().cloned().collect())
+    })
+}
+
+/// Serves the binary values as they are to the reads, for the app to register as the `store` URI scheme with
+/// `tauri::Builder::register_uri_scheme_protocol`. The JavaScript `Store.binaryUrl` builds their URLs.
+pub fn binary_protocol<R: Runtime>(
+    app: &AppHandle<R>,
+    request: &HttpRequest,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let url = request.uri();
+    let encoded = url
+        .split_once("localhost/")
+        .map_or("", |(_, encoded)| encoded);
+    let encoded = encoded.split(['?', '#']).next().unwrap_or_default();
+    // the path and the key are encoded on their own, then together by `convertFileSrc`
+    let decoded = percent_decode_str(encoded).decode_utf8()?;
+    let (path, key) = decoded
+        .split_once('/')
+        .ok_or("the URL has no store path and key")?;
+    let path = percent_decode_str(path).decode_utf8()?;
+    let key = percent_decode_str(key).decode_utf8()?;
+
+    let collection = app.stores();
+    let value = with_store(app, &collection, PathBuf::from(&*path), |store| {
+        Ok(store.get_binary(&key).map(<[u8]>::to_vec))
+    })?;
+    let mut response = ResponseBuilder::new().header("Vary", "Origin");
+    // the scheme has its own origin on Windows, so `fetch` needs the pages of the app to be allowed
+    if let Some(origin) = request
+        .headers()
+        .get("Origin")
+        .and_then(|origin| origin.to_str().ok())
+    {
+        if app_origins(app).iter().any(|allowed| allowed == origin) {
+            response = response.header("Access-Control-Allow-Origin", origin);
+        }
+    }
+    match value {
+        Some(value) => response.mimetype("application/octet-stream").body(value),
+        None => response.status(404).body(Vec::new()),
+    }
+}
+
+/// The origins of the pages of the app, the only ones [`binary_protocol`] serves the values to across origins.
+fn app_origins<R: Runtime>(app: &AppHandle<R>) -> Vec<String> {
+    let mut origins = vec!["tauri://localhost".into(), "https://tauri.localhost".into()];
+    // the dev server only serves the pages of the debug builds
+    if let (true, AppUrl::Url(WindowUrl::External(url))) =
+        (cfg!(debug_assertions), &app.config().build.dev_path)
+    {
+        origins.push(url.origin().ascii_serialization());
+    }
+    origins
+}
+
+#[tauri::command]
+async fn clear<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection>,
+    path: PathBuf,
+) -> Result<(), Error> {
+    let changes = with_store(&app, &stores, path.clone(), |store| {
+        let previous = store
+            .entries()
+            .map(|(key, value)| (key.clone(), Some(value.clone())))
+            .collect::<Vec<_>>();
+        store.clear();
+        Ok(diff(&path, store, previous))
+    })?;
+    stores.notify(&app, changes);
+    Ok(())
+}
+
 #[tauri::command]
 async fn reset<R: Runtime>(
     app: AppHandle<R>,
-    window: Window<R>,
     collection: State<'_, StoreCollection>,
     path: PathBuf,
 ) -> Result<(), Error> {
@@ -160,26 +586,27 @@ async fn reset<R: Runtime>(
         .map(|store| store.defaults.is_some());
 
     if Some(true) == has_defaults {
-        with_store(&app, collection, path.clone(), |store| {
-            if let Some(defaults) = &store.defaults {
-                for (key, value) in &store.cache {
-                    if defaults.get(key) != Some(value) {
-                        let _ = window.emit(
-                            "store://change",
-                            ChangePayload {
-                                path: path.clone(),
-                                key: key.clone(),
-                                value: defaults.get(key).cloned().unwrap_or(JsonValue::Null),
-                            },
-                        );
-                    }
-                }
-                store.cache = defaults.clone();
-            }
-            Ok(())
-        })
+        let changes = with_store(&app, &collection, path.clone(), |store| {
+            let previous = store
+                .entries()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect::<HashMap<_, _>>();
+            store.reset();
+            let keys = previous.keys().chain(store.keys()).cloned();
+            let keys = keys.collect::<HashSet<_>>();
+            Ok(diff(
+                &path,
+                store,
+                keys.into_iter().map(|key| {
+                    let old_value = previous.get(&key).cloned();
+                    (key, old_value)
+                }),
+            ))
+        })?;
+        collection.notify(&app, changes);
+        Ok(())
     } else {
-        clear(app, window, collection, path).await
+        clear(app, collection, path).await
     }
 }
 
@@ -189,8 +616,8 @@ async fn keys<R: Runtime>(
     stores: State<'_, StoreCollection>,
     path: PathBuf,
 ) -> Result<Vec<String>, Error> {
-    with_store(&app, stores, path, |store| {
-        Ok(store.cache.keys().cloned().collect())
+    with_store(&app, &stores, path, |store| {
+        Ok(store.keys().cloned().collect())
     })
 }
 
@@ -200,8 +627,8 @@ async fn values<R: Runtime>(
     stores: State<'_, StoreCollection>,
     path: PathBuf,
 ) -> Result<Vec<JsonValue>, Error> {
-    with_store(&app, stores, path, |store| {
-        Ok(store.cache.values().cloned().collect())
+    with_store(&app, &stores, path, |store| {
+        Ok(store.values().cloned().collect())
     })
 }
 
@@ -211,8 +638,49 @@ async fn entries<R: Runtime>(
     stores: State<'_, StoreCollection>,
     path: PathBuf,
 ) -> Result<Vec<(String, JsonValue)>, Error> {
-    with_store(&app, stores, path, |store| {
-        Ok(store.cache.clone().into_iter().collect())
+    with_store(&app, &stores, path, |store| {
+        Ok(store
+            .entries()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    })
+}
+
+#[tauri::command]
+async fn keys_page<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection>,
+    path: PathBuf,
+    offset: usize,
+    limit: usize,
+) -> Result<Page<String>, Error> {
+    with_store(&app, &stores, path, |store| {
+        let page = store.keys_page(offset, limit);
+        Ok(Page {
+            items: page.items.into_iter().cloned().collect(),
+            total: page.total,
+        })
+    })
+}
+
+#[tauri::command]
+async fn entries_page<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection>,
+    path: PathBuf,
+    offset: usize,
+    limit: usize,
+) -> Result<Page<(String, JsonValue)>, Error> {
+    with_store(&app, &stores, path, |store| {
+        let page = store.entries_page(offset, limit);
+        Ok(Page {
+            items: page
+                .items
+                .into_iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            total: page.total,
+        })
     })
 }
 
@@ -222,7 +690,7 @@ async fn length<R: Runtime>(
     stores: State<'_, StoreCollection>,
     path: PathBuf,
 ) -> Result<usize, Error> {
-    with_store(&app, stores, path, |store| Ok(store.cache.len()))
+    with_store(&app, &stores, path, |store| Ok(store.len()))
 }
 
 #[tauri::command]
@@ -231,7 +699,7 @@ async fn load<R: Runtime>(
     stores: State<'_, StoreCollection>,
     path: PathBuf,
 ) -> Result<(), Error> {
-    with_store(&app, stores, path, |store| store.load(&app))
+    with_store(&app, &stores, path, |store| store.load(&app))
 }
 
 #[tauri::command]
@@ -240,7 +708,7 @@ async fn save<R: Runtime>(
     stores: State<'_, StoreCollection>,
     path: PathBuf,
 ) -> Result<(), Error> {
-    with_store(&app, stores, path, |store| store.save(&app))
+    with_store(&app, &stores, path, |store| store.save(&app))
 }
 
 #[derive(Default)]
@@ -334,10 +802,28 @@ impl PluginBuilder {
     pub fn build<R: Runtime>(mut self) -> TauriPlugin<R> {
         plugin::Builder::new("store")
             .invoke_handler(tauri::generate_handler![
-                set, get, has, delete, clear, reset, keys, values, length, entries, load, save
+                set,
+                batch,
+                get,
+                has,
+                delete,
+                set_binary,
+                get_binary,
+                delete_binary,
+                binary_keys,
+                clear,
+                reset,
+                keys,
+                values,
+                length,
+                entries,
+                keys_page,
+                entries_page,
+                load,
+                save
             ])
             .setup(move |app_handle| {
-                for (path, store) in self.stores.iter_mut() {
+                for (path, store) in self.stores.iter_mut().filter(|(_, store)| !store.lazy) {
                     // ignore loading errors, just use the default
                     if let Err(err) = store.load(app_handle) {
                         warn!(
@@ -347,24 +833,51 @@ impl PluginBuilder {
                     }
                 }
 
+                let (saver, changes) = channel();
                 app_handle.manage(StoreCollection {
                     stores: Mutex::new(self.stores),
                     frozen: self.frozen,
+                    saver: Mutex::new(Some(saver)),
+                    ..Default::default()
                 });
+                spawn_saver(app_handle.clone(), changes);
 
-                Ok(())
-            })
-            .on_event(|app_handle, event| {
-                if let RunEvent::Exit = event {
-                    let collection = app_handle.state::<StoreCollection>();
+                let app = app_handle.clone();
+                app_handle.diagnostics().register("store", move || {
+                    let collection = app.state::<StoreCollection>();
+                    let stores = collection.stores.lock().expect("mutex poisoned");
+                    Report::default()
+                        .metric("stores", stores.len() as f64)
+                        .metric(
+                            "keys",
+                            stores.values().map(|store| store.len()).sum::<usize>() as f64,
+                        )
+                });
 
-                    for store in collection.stores.lock().expect("mutex poisoned").values() {
-                        if let Err(err) = store.save(app_handle) {
-                            eprintln!("failed to save store {:?} with error {:?}", store.path, err);
+                let app = app_handle.clone();
+                app_handle
+                    .shutdown()
+                    .register("store", Stage::Save, move || {
+                        let collection = app.state::<StoreCollection>();
+
+                        for store in collection
+                            .stores
+                            .lock()
+                            .expect("mutex poisoned")
+                            .values_mut()
+                        {
+                            if let Err(err) = store.save(&app) {
+                                eprintln!(
+                                    "failed to save store {:?} with error {:?}",
+                                    store.path, err
+                                );
+                            }
                         }
-                    }
-                }
+                    });
+
+                Ok(())
             })
+            .on_event(|app_handle, event| shutdown::on_event(app_handle, "store", event))
             .build()
     }
 }
@@ -2,18 +2,29 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
-use crate::Error;
+use crate::{
+    backend::{DeserializeFn, FileBackend, SerializeFn},
+    Compression, Corrupted, Error, StoreBackend,
+};
+use log::warn;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
 use serde_json::Value as JsonValue;
 use std::{
-    collections::HashMap,
-    fs::{create_dir_all, read, File},
-    io::Write,
-    path::PathBuf,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tauri::{AppHandle, Runtime};
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_common::StateDirsExt;
+
+/// The key the expiry of the keys set with a TTL is saved under, in milliseconds since the Unix epoch.
+const EXPIRY_KEY: &str = "__tauri_store_expires_at__";
+/// The key the version of the store is saved under, the stores saved without it are the version 1.
+const VERSION_KEY: &str = "__tauri_store_version__";
 
-type SerializeFn = fn(&HashMap<String, JsonValue>) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
-type DeserializeFn = fn(&[u8]) -> Result<HashMap<String, JsonValue>, Box<dyn std::error::Error>>;
+type MigrateFn =
+    Arc<dyn Fn(HashMap<String, JsonValue>) -> HashMap<String, JsonValue> + Send + Sync>;
 
 fn default_serialize(
     cache: &HashMap<String, JsonValue>,
@@ -27,6 +38,18 @@ fn default_deserialize(
     serde_json::from_slice(bytes).map_err(Into::into)
 }
 
+/// When the changes of a store are saved, besides [`Store::save`] and the exit of the app.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SaveStrategy {
+    /// On every change.
+    OnChange,
+    /// Once the changes stop coming in for the duration.
+    Debounced(Duration),
+    /// Only when the app exits, a crash loses the changes.
+    #[default]
+    OnExit,
+}
+
 /// Builds a [`Store`]
 pub struct StoreBuilder {
     path: PathBuf,
@@ -34,6 +57,16 @@ pub struct StoreBuilder {
     cache: HashMap<String, JsonValue>,
     serialize: SerializeFn,
     deserialize: DeserializeFn,
+    migrations: BTreeMap<u32, MigrateFn>,
+    backend: Option<Arc<dyn StoreBackend>>,
+    save_strategy: SaveStrategy,
+    compression: Compression,
+    ephemeral: bool,
+    lazy: bool,
+    checksum: bool,
+    backup: bool,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<[u8; 32]>,
 }
 
 impl StoreBuilder {
@@ -56,6 +89,16 @@ impl StoreBuilder {
             cache: Default::default(),
             serialize: default_serialize,
             deserialize: default_deserialize,
+            migrations: Default::default(),
+            backend: None,
+            save_strategy: Default::default(),
+            compression: Default::default(),
+            ephemeral: false,
+            lazy: false,
+            checksum: false,
+            backup: false,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
         }
     }
 
@@ -136,6 +179,142 @@ impl StoreBuilder {
         self
     }
 
+    /// Registers the migration of the store from the version to the next one, run when an older store is loaded.
+    ///
+    /// The version of the store is the one after its last migration, the stores saved without a version are the
+    /// version 1.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// // the version 2 nests the theme in the appearance settings
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .migrate(1, |mut cache| {
+    ///     if let Some(theme) = cache.remove("theme") {
+    ///       cache.insert("appearance".into(), serde_json::json!({ "theme": theme }));
+    ///     }
+    ///     cache
+    ///   });
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn migrate<F>(mut self, from: u32, migration: F) -> Self
+    where
+        F: Fn(HashMap<String, JsonValue>) -> HashMap<String, JsonValue> + Send + Sync + 'static,
+    {
+        self.migrations.insert(from, Arc::new(migration));
+        self
+    }
+
+    /// Persists the store with the backend instead of a file, e.g. a [`SqliteBackend`](crate::SqliteBackend) for the
+    /// stores with many entries.
+    ///
+    /// The custom serialization and the encryption only apply to the file.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{MemoryBackend, StoreBuilder};
+    ///
+    /// let builder = StoreBuilder::new("cache".parse()?)
+    ///   .backend(MemoryBackend::default());
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn backend<B: StoreBackend>(mut self, backend: B) -> Self {
+        self.backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// Sets when the changes made through the plugin are saved, [`SaveStrategy::OnExit`] by default.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::time::Duration;
+    /// use tauri_plugin_store::{SaveStrategy, StoreBuilder};
+    ///
+    /// let builder = StoreBuilder::new("store.bin".parse()?)
+    ///   .save_strategy(SaveStrategy::Debounced(Duration::from_millis(500)));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save_strategy(mut self, save_strategy: SaveStrategy) -> Self {
+        self.save_strategy = save_strategy;
+        self
+    }
+
+    /// Keeps the store in memory only, it's never loaded nor saved, e.g. for the state of the session shared by
+    /// the windows.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("session".parse()?).ephemeral();
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn ephemeral(mut self) -> Self {
+        self.ephemeral = true;
+        self
+    }
+
+    /// Loads the store on its first use instead of the app startup, e.g. for the big stores read later.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("history.json".parse()?).lazy();
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lazy(mut self) -> Self {
+        self.lazy = true;
+        self
+    }
+
+    /// Saves the store file with a checksum, so a corrupted file is detected on load. The files saved without it
+    /// are still loaded.
+    pub fn checksum(mut self) -> Self {
+        self.checksum = true;
+        self
+    }
+
+    /// Keeps a backup of the store file, restored when the store file is corrupted.
+    pub fn backup(mut self) -> Self {
+        self.backup = true;
+        self
+    }
+
+    /// Compresses the store file, with the `gzip` or `zstd` feature. The uncompressed files are still loaded, and
+    /// compressed on the next save.
+    ///
+    /// The compressed files are detected by their magic bytes, a custom serialization must not start with them.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Encrypts the store file with the key, e.g. one kept in the keychain or the stronghold of the app.
+    ///
+    /// A plain store file is still loaded, and encrypted on the next save.
+    #[cfg(feature = "encryption")]
+    pub fn encryption_key(mut self, key: [u8; 32]) -> Self {
+        self.encryption_key = Some(key);
+        self
+    }
+
     /// Builds the [`Store`].
     ///
     /// # Examples
@@ -152,50 +331,476 @@ impl StoreBuilder {
             path: self.path,
             defaults: self.defaults,
             cache: self.cache,
-            serialize: self.serialize,
-            deserialize: self.deserialize,
+            expires_at: Default::default(),
+            changed: None,
+            binary: Default::default(),
+            binary_changed: None,
+            unwritable: None,
+            save_strategy: self.save_strategy,
+            ephemeral: self.ephemeral,
+            lazy: self.lazy,
+            loaded: false,
+            backend: self.backend.unwrap_or_else(|| {
+                Arc::new(FileBackend {
+                    serialize: self.serialize,
+                    deserialize: self.deserialize,
+                    compression: self.compression,
+                    checksum: self.checksum,
+                    backup: self.backup,
+                    #[cfg(feature = "encryption")]
+                    encryption_key: self.encryption_key,
+                })
+            }),
+            version: self
+                .migrations
+                .keys()
+                .next_back()
+                .map_or(1, |from| from + 1),
+            migrations: self.migrations,
         }
     }
 }
 
+/// An operation of [`Store::batch`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum Operation {
+    /// Sets the value of the key, until the TTL elapses when there's one. It's in milliseconds from JavaScript.
+    Set {
+        key: String,
+        value: JsonValue,
+        #[serde(default, deserialize_with = "deserialize_ttl")]
+        ttl: Option<Duration>,
+    },
+    Delete {
+        key: String,
+    },
+}
+
+fn deserialize_ttl<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64)
+}
+
+/// A page of the keys or the entries of a store, by the order of the keys.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The number of keys of the store.
+    pub total: usize,
+}
+
+/// Why the saved store isn't overwritten, as it couldn't be loaded.
+#[derive(Debug, Clone, Copy)]
+enum Unwritable {
+    Encrypted,
+    Undecryptable,
+    NewerVersion(u32),
+}
+
 #[derive(Clone)]
 pub struct Store {
     pub(crate) path: PathBuf,
     pub(crate) defaults: Option<HashMap<String, JsonValue>>,
     pub(crate) cache: HashMap<String, JsonValue>,
-    serialize: SerializeFn,
-    deserialize: DeserializeFn,
+    /// The keys set with a TTL, they're dropped lazily once expired.
+    expires_at: HashMap<String, u64>,
+    /// The keys set or removed since the store was loaded or saved, `None` when all of them must be saved.
+    changed: Option<HashSet<String>>,
+    /// The binary values, apart from the JSON ones.
+    binary: HashMap<String, Vec<u8>>,
+    binary_changed: Option<HashSet<String>>,
+    unwritable: Option<Unwritable>,
+    pub(crate) save_strategy: SaveStrategy,
+    ephemeral: bool,
+    pub(crate) lazy: bool,
+    /// Whether the store was loaded, a lazy store isn't saved before.
+    pub(crate) loaded: bool,
+    backend: Arc<dyn StoreBackend>,
+    version: u32,
+    migrations: BTreeMap<u32, MigrateFn>,
 }
 
 impl Store {
+    /// Returns the value of the key, `None` when it isn't set or expired.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        if self.is_expired(key, now_millis()) {
+            return None;
+        }
+        self.cache.get(key)
+    }
+
+    /// Returns the value of the key deserialized as `T`, `None` when it isn't set or expired.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let mut store = StoreBuilder::new("settings.json".parse()?).build();
+    /// store.set_ser("volume".into(), &0.8)?;
+    /// let volume: Option<f64> = store.get_de("volume")?;
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_de<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+        self.get(key)
+            .map(|value| T::deserialize(value))
+            .transpose()
+            .map_err(|source| Error::Value {
+                key: key.into(),
+                source,
+            })
+    }
+
+    /// Sets the key to the value serialized as JSON, returns the previous one.
+    pub fn set_ser<T: Serialize + ?Sized>(
+        &mut self,
+        key: String,
+        value: &T,
+    ) -> Result<Option<JsonValue>, Error> {
+        match serde_json::to_value(value) {
+            Ok(value) => Ok(self.set(key, value)),
+            Err(source) => Err(Error::Value { key, source }),
+        }
+    }
+
+    pub fn has(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Sets the value of the key, returns the previous one.
+    pub fn set(&mut self, key: String, value: JsonValue) -> Option<JsonValue> {
+        let previous = self.delete(&key);
+        self.touch(&key);
+        self.cache.insert(key, value);
+        previous
+    }
+
+    /// Sets the value of the key until the TTL elapses, e.g. for a cached response. Returns the previous value.
+    ///
+    /// The expired keys are skipped by the reads and dropped when the store is saved.
+    pub fn set_with_ttl(
+        &mut self,
+        key: String,
+        value: JsonValue,
+        ttl: Duration,
+    ) -> Option<JsonValue> {
+        let expires_at = now_millis().saturating_add(ttl.as_millis() as u64);
+        let previous = self.set(key.clone(), value);
+        self.expires_at.insert(key, expires_at);
+        previous
+    }
+
+    /// Removes the key, returns its value.
+    pub fn delete(&mut self, key: &str) -> Option<JsonValue> {
+        let expired = self.is_expired(key, now_millis());
+        self.touch(key);
+        self.expires_at.remove(key);
+        self.cache.remove(key).filter(|_| !expired)
+    }
+
+    /// Returns the binary value of the key.
+    pub fn get_binary(&self, key: &str) -> Option<&[u8]> {
+        self.binary.get(key).map(Vec::as_slice)
+    }
+
+    /// Sets the binary value of the key, returns the previous one. The binary values have their own keys.
+    pub fn set_binary(&mut self, key: String, value: Vec<u8>) -> Option<Vec<u8>> {
+        if let Some(changed) = &mut self.binary_changed {
+            changed.insert(key.clone());
+        }
+        self.binary.insert(key, value)
+    }
+
+    /// Removes the binary value of the key, returns it.
+    pub fn delete_binary(&mut self, key: &str) -> Option<Vec<u8>> {
+        if let Some(changed) = &mut self.binary_changed {
+            changed.insert(key.into());
+        }
+        self.binary.remove(key)
+    }
+
+    pub fn binary_keys(&self) -> impl Iterator<Item = &String> {
+        self.binary.keys()
+    }
+
+    /// Removes all the keys, with the binary values.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.expires_at.clear();
+        self.binary.clear();
+        self.changed = None;
+        self.binary_changed = None;
+    }
+
+    /// Sets the keys back to the defaults, or removes them without defaults. The binary values are removed.
+    pub fn reset(&mut self) {
+        self.cache = self.defaults.clone().unwrap_or_default();
+        self.expires_at.clear();
+        self.binary.clear();
+        self.changed = None;
+        self.binary_changed = None;
+    }
+
+    /// Applies the operations in order, returns the values of the keys they changed from before the batch.
+    pub fn batch<I: IntoIterator<Item = Operation>>(
+        &mut self,
+        operations: I,
+    ) -> HashMap<String, Option<JsonValue>> {
+        let mut previous = HashMap::new();
+        for operation in operations {
+            let (key, old_value) = match operation {
+                Operation::Set {
+                    key,
+                    value,
+                    ttl: Some(ttl),
+                } => (key.clone(), self.set_with_ttl(key, value, ttl)),
+                Operation::Set {
+                    key,
+                    value,
+                    ttl: None,
+                } => (key.clone(), self.set(key, value)),
+                Operation::Delete { key } => {
+                    let old_value = self.delete(&key);
+                    (key, old_value)
+                }
+            };
+            previous.entry(key).or_insert(old_value);
+        }
+        previous
+    }
+
+    /// The keys and their values, without the expired ones.
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &JsonValue)> {
+        let now = now_millis();
+        self.cache
+            .iter()
+            .filter(move |(key, _)| !self.is_expired(key, now))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &JsonValue> {
+        self.entries().map(|(_, value)| value)
+    }
+
+    /// The keys from the offset, up to the limit, by their order.
+    pub fn keys_page(&self, offset: usize, limit: usize) -> Page<&String> {
+        let page = self.entries_page(offset, limit);
+        Page {
+            items: page.items.into_iter().map(|(key, _)| key).collect(),
+            total: page.total,
+        }
+    }
+
+    /// The entries from the offset, up to the limit, by the order of their keys.
+    pub fn entries_page(&self, offset: usize, limit: usize) -> Page<(&String, &JsonValue)> {
+        let mut entries = self.entries().collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|&(key, _)| key);
+        Page {
+            total: entries.len(),
+            items: entries.into_iter().skip(offset).take(limit).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the store is kept in memory only, see [`StoreBuilder::ephemeral`].
+    pub fn is_ephemeral(&self) -> bool {
+        self.ephemeral
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(changed) = &mut self.changed {
+            changed.insert(key.into());
+        }
+    }
+
+    fn is_expired(&self, key: &str, now: u64) -> bool {
+        self.expires_at
+            .get(key)
+            .is_some_and(|expires_at| now >= *expires_at)
+    }
+
     /// Update the store from the on-disk state
     pub fn load<R: Runtime>(&mut self, app: &AppHandle<R>) -> Result<(), Error> {
+        // a lazy store failing to load isn't loaded again on every use
+        self.loaded = true;
+        if self.ephemeral {
+            return Ok(());
+        }
         let app_dir = app
-            .path_resolver()
-            .app_data_dir()
+            .state_dirs()
+            .data_dir()
             .expect("failed to resolve app dir");
         let store_path = app_dir.join(&self.path);
 
-        let bytes = read(&store_path)?;
-
-        self.cache = (self.deserialize)(&bytes).map_err(Error::Deserialize)?;
+        let loaded = match self.backend.load(&store_path) {
+            Err(e @ (Error::Corrupted | Error::Deserialize(_) | Error::Decompress(_))) => {
+                self.recover(app, &store_path, e)
+            }
+            loaded => loaded,
+        };
+        let loaded = loaded.and_then(|cache| {
+            let binary = self.backend.load_binary(&store_path)?;
+            Ok((cache, binary))
+        });
+        let mut cache = match loaded {
+            Ok((cache, binary)) => {
+                self.binary = binary;
+                self.binary_changed = Some(Default::default());
+                match cache {
+                    Some(cache) => cache,
+                    None => {
+                        self.unwritable = None;
+                        return Ok(());
+                    }
+                }
+            }
+            Err(e) => {
+                match e {
+                    Error::Encrypted => self.unwritable = Some(Unwritable::Encrypted),
+                    Error::Decrypt => self.unwritable = Some(Unwritable::Undecryptable),
+                    _ => {}
+                }
+                return Err(e);
+            }
+        };
+        let version = match cache.remove(VERSION_KEY) {
+            Some(version) => serde_json::from_value(version)?,
+            None => 1,
+        };
+        if version > self.version {
+            self.unwritable = Some(Unwritable::NewerVersion(version));
+            return Err(Error::UnsupportedVersion(version, self.version));
+        }
+        self.unwritable = None;
+        let expires_at = cache
+            .remove(EXPIRY_KEY)
+            .and_then(|expires_at| serde_json::from_value(expires_at).ok())
+            .unwrap_or_default();
+        let mut migrations = self.migrations.range(version..).peekable();
+        self.changed = match migrations.peek() {
+            Some(_) => None,
+            None => Some(Default::default()),
+        };
+        for (_, migration) in migrations {
+            cache = migration(cache);
+        }
+        self.cache = cache;
+        self.expires_at = expires_at;
 
         Ok(())
     }
 
+    /// Reads the backup of the corrupted store, and emits the `store://corrupted` event.
+    fn recover<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        store_path: &Path,
+        error: Error,
+    ) -> Result<Option<HashMap<String, JsonValue>>, Error> {
+        let backup = self.backend.load_backup(store_path);
+        let recovered = matches!(backup, Ok(Some(_)));
+        let _ = app.emit_all(
+            "store://corrupted",
+            Corrupted {
+                path: self.path.clone(),
+                error: error.to_string(),
+                recovered,
+            },
+        );
+        match backup {
+            Ok(Some(cache)) => {
+                warn!(
+                    "The store {:?} is corrupted: {}. It was restored from its backup.",
+                    self.path, error
+                );
+                Ok(Some(cache))
+            }
+            _ => Err(error),
+        }
+    }
+
     /// Saves the store to disk
-    pub fn save<R: Runtime>(&self, app: &AppHandle<R>) -> Result<(), Error> {
+    pub fn save<R: Runtime>(&mut self, app: &AppHandle<R>) -> Result<(), Error> {
+        if self.ephemeral || (self.lazy && !self.loaded) {
+            return Ok(());
+        }
         let app_dir = app
-            .path_resolver()
-            .app_data_dir()
+            .state_dirs()
+            .data_dir()
             .expect("failed to resolve app dir");
         let store_path = app_dir.join(&self.path);
 
-        create_dir_all(store_path.parent().expect("invalid store path"))?;
+        match self.unwritable {
+            Some(Unwritable::Encrypted) => return Err(Error::Encrypted),
+            Some(Unwritable::Undecryptable) => return Err(Error::Decrypt),
+            Some(Unwritable::NewerVersion(version)) => {
+                return Err(Error::UnsupportedVersion(version, self.version))
+            }
+            None => {}
+        }
+
+        let now = now_millis();
+        let mut cache = self
+            .entries()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<HashMap<_, _>>();
+        let expires_at = self
+            .expires_at
+            .iter()
+            .filter(|(key, expires_at)| now < **expires_at && cache.contains_key(*key))
+            .collect::<HashMap<_, _>>();
+        if !expires_at.is_empty() {
+            cache.insert(EXPIRY_KEY.into(), serde_json::to_value(expires_at)?);
+        }
+        cache.insert(VERSION_KEY.into(), self.version.into());
 
-        let bytes = (self.serialize)(&self.cache).map_err(Error::Serialize)?;
-        let mut f = File::create(&store_path)?;
-        f.write_all(&bytes)?;
+        let changed = self.changed.as_ref().map(|changed| {
+            let expired = self
+                .expires_at
+                .iter()
+                .filter(|(_, expires_at)| now >= **expires_at)
+                .map(|(key, _)| key);
+            changed
+                .iter()
+                .chain(expired)
+                .cloned()
+                .chain([EXPIRY_KEY.into(), VERSION_KEY.into()])
+                .collect::<HashSet<_>>()
+        });
+        self.backend.save(&store_path, &cache, changed.as_ref())?;
+        self.changed = Some(Default::default());
+
+        // all of them are saved when the changes aren't tracked
+        if !self
+            .binary_changed
+            .as_ref()
+            .is_some_and(|changed| changed.is_empty())
+        {
+            self.backend
+                .save_binary(&store_path, &self.binary, self.binary_changed.as_ref())?;
+            self.binary_changed = Some(Default::default());
+        }
 
         Ok(())
     }
@@ -1,4 +1,15 @@
 import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+import { appWindow } from "@tauri-apps/api/window";
+
+import type { BusyPayload } from "./types/BusyPayload";
+import type { ChangePasswordProgress } from "./types/ChangePasswordProgress";
+import type { ChangePasswordStage } from "./types/ChangePasswordStage";
+import type { ErrorPayload } from "./types/ErrorPayload";
+import type { LockFailedPayload } from "./types/LockFailedPayload";
+import type { LockStatePayload } from "./types/LockStatePayload";
+
+export type { ChangePasswordProgress, ChangePasswordStage, ErrorPayload };
 
 type BytesDto = string | number[];
 export type ClientPath =
@@ -82,6 +93,35 @@ export interface Duration {
   nanos: number;
 }
 
+export type HashType = "Sha256" | "Sha384" | "Sha512";
+
+/** The hash functions of the TOTP codes, SHA-1 for the secrets of most authenticator apps (`otpauth://` URIs). */
+export type TOTPHashType = "Sha1" | HashType;
+
+/** The keys generated in the vaults. Secp256k1 isn't supported by the Stronghold runtime of the plugin. */
+export type KeyType = "Ed25519" | "X25519";
+
+export type AeadCipher = "Aes256Gcm" | "XChaCha20Poly1305";
+
+export type MnemonicLanguage = "English" | "Japanese";
+
+export interface TOTPOptions {
+  /** The hash function used for the HMAC. Defaults to `Sha1`, as in RFC 6238. */
+  hashType?: TOTPHashType;
+  /** Time step in seconds. Defaults to `30`. */
+  period?: number;
+  /** Number of digits of the code, at most 9. Defaults to `6`. */
+  digits?: number;
+  /** Unix timestamp in seconds the code is generated for. Defaults to now. */
+  timestamp?: number;
+}
+
+/** The options of the TOTP codes of the vault secrets, which only have the SHA-2 HMACs. */
+export interface VaultTOTPOptions extends Omit<TOTPOptions, "hashType"> {
+  /** The hash function used for the HMAC. Defaults to `Sha256`. */
+  hashType?: HashType;
+}
+
 export class Location {
   type: string;
   payload: Record<string, unknown>;
@@ -129,8 +169,16 @@ class ProcedureExecutor {
     }).then((n) => Uint8Array.from(n));
   }
 
+  /**
+   * Derives the key of the chain from the seed or the key at `sourceLocation`, and stores it at `outputLocation`.
+   * @param chain The indices of the path, or the path itself like `m/44'/4218'/0'/0'`. All the segments are hardened.
+   * @param source
+   * @param sourceLocation
+   * @param outputLocation
+   * @returns The chain code.
+   */
   async deriveSLIP10(
-    chain: number[],
+    chain: number[] | string,
     source: "Seed" | "Key",
     sourceLocation: Location,
     outputLocation: Location
@@ -169,9 +217,17 @@ class ProcedureExecutor {
     }).then((n) => Uint8Array.from(n));
   }
 
+  /**
+   * Generates a mnemonic and stores its seed at `outputLocation`.
+   * @param outputLocation
+   * @param passphrase
+   * @param language Defaults to `English`.
+   * @returns The mnemonic, to be written down by the user.
+   */
   async generateBIP39(
     outputLocation: Location,
-    passphrase?: string
+    passphrase?: string,
+    language?: MnemonicLanguage
   ): Promise<Uint8Array> {
     return await invoke<number[]>("plugin:stronghold|execute_procedure", {
       ...this.procedureArgs,
@@ -180,6 +236,7 @@ class ProcedureExecutor {
         payload: {
           output: outputLocation,
           passphrase,
+          language,
         },
       },
     }).then((n) => Uint8Array.from(n));
@@ -198,6 +255,73 @@ class ProcedureExecutor {
     }).then((n) => Uint8Array.from(n));
   }
 
+  /**
+   * Generates a private key at `outputLocation`, which never leaves the vault.
+   * @param type
+   * @param outputLocation
+   * @returns
+   */
+  async generateKey(type: KeyType, outputLocation: Location): Promise<void> {
+    await invoke("plugin:stronghold|execute_procedure", {
+      ...this.procedureArgs,
+      procedure: {
+        type: "GenerateKey",
+        payload: {
+          type,
+          output: outputLocation,
+        },
+      },
+    });
+  }
+
+  /**
+   * The public key of the private key stored at `privateKeyLocation`.
+   * @param type
+   * @param privateKeyLocation
+   * @returns
+   */
+  async getPublicKey(
+    type: KeyType,
+    privateKeyLocation: Location
+  ): Promise<Uint8Array> {
+    return await invoke<number[]>("plugin:stronghold|execute_procedure", {
+      ...this.procedureArgs,
+      procedure: {
+        type: "PublicKey",
+        payload: {
+          type,
+          privateKey: privateKeyLocation,
+        },
+      },
+    }).then((n) => Uint8Array.from(n));
+  }
+
+  /**
+   * Stores the X25519 shared key of the private key at `privateKeyLocation` and the public key of the peer at
+   * `sharedKeyLocation`, e.g. to encrypt with it.
+   * @param privateKeyLocation
+   * @param publicKey The 32 bytes of the public key of the peer.
+   * @param sharedKeyLocation
+   * @returns
+   */
+  async deriveSharedKey(
+    privateKeyLocation: Location,
+    publicKey: number[],
+    sharedKeyLocation: Location
+  ): Promise<void> {
+    await invoke("plugin:stronghold|execute_procedure", {
+      ...this.procedureArgs,
+      procedure: {
+        type: "X25519DiffieHellman",
+        payload: {
+          publicKey,
+          privateKey: privateKeyLocation,
+          sharedKey: sharedKeyLocation,
+        },
+      },
+    });
+  }
+
   async signEd25519(
     privateKeyLocation: Location,
     msg: string
@@ -213,6 +337,127 @@ class ProcedureExecutor {
       },
     }).then((n) => Uint8Array.from(n));
   }
+
+  /**
+   * Generates a TOTP code (RFC 6238) from the secret stored at `keyLocation`.
+   *
+   * The vault only computes the SHA-2 HMACs, so `hashType` defaults to `Sha256`. The SHA-1 secrets of most
+   * authenticator apps are kept in the store instead, see `Store.generateTOTP`.
+   * @param keyLocation
+   * @param options
+   * @returns The code, zero-padded to the requested number of digits.
+   */
+  async generateTOTP(
+    keyLocation: Location,
+    options?: VaultTOTPOptions
+  ): Promise<string> {
+    return await invoke<number[]>("plugin:stronghold|execute_procedure", {
+      ...this.procedureArgs,
+      procedure: {
+        type: "TOTP",
+        payload: {
+          key: keyLocation,
+          ...options,
+        },
+      },
+    }).then((n) => String.fromCharCode(...n));
+  }
+
+  /**
+   * Encrypts `plaintext` with the key stored at `keyLocation`.
+   * @param cipher
+   * @param keyLocation
+   * @param plaintext
+   * @param nonce 12 bytes for `Aes256Gcm`, 24 bytes for `XChaCha20Poly1305`.
+   * @param associatedData
+   * @returns The authentication tag followed by the ciphertext.
+   */
+  async encrypt(
+    cipher: AeadCipher,
+    keyLocation: Location,
+    plaintext: number[],
+    nonce: number[],
+    associatedData?: number[]
+  ): Promise<Uint8Array> {
+    return await invoke<number[]>("plugin:stronghold|execute_procedure", {
+      ...this.procedureArgs,
+      procedure: {
+        type: "AeadEncrypt",
+        payload: {
+          cipher,
+          key: keyLocation,
+          plaintext,
+          nonce,
+          associatedData,
+        },
+      },
+    }).then((n) => Uint8Array.from(n));
+  }
+
+  /**
+   * Decrypts `ciphertext` with the key stored at `keyLocation`.
+   * @param cipher
+   * @param keyLocation
+   * @param ciphertext
+   * @param tag The authentication tag returned by `encrypt`.
+   * @param nonce The nonce used for encryption.
+   * @param associatedData
+   * @returns The plaintext.
+   */
+  async decrypt(
+    cipher: AeadCipher,
+    keyLocation: Location,
+    ciphertext: number[],
+    tag: number[],
+    nonce: number[],
+    associatedData?: number[]
+  ): Promise<Uint8Array> {
+    return await invoke<number[]>("plugin:stronghold|execute_procedure", {
+      ...this.procedureArgs,
+      procedure: {
+        type: "AeadDecrypt",
+        payload: {
+          cipher,
+          key: keyLocation,
+          ciphertext,
+          tag,
+          nonce,
+          associatedData,
+        },
+      },
+    }).then((n) => Uint8Array.from(n));
+  }
+
+  /**
+   * Derives a key from the secret at `inputLocation` with HKDF and stores it at `outputLocation`.
+   * @param hashType
+   * @param inputLocation
+   * @param outputLocation
+   * @param salt
+   * @param label
+   * @returns
+   */
+  async deriveHKDF(
+    hashType: HashType,
+    inputLocation: Location,
+    outputLocation: Location,
+    salt: number[],
+    label: number[]
+  ): Promise<void> {
+    await invoke("plugin:stronghold|execute_procedure", {
+      ...this.procedureArgs,
+      procedure: {
+        type: "HKDF",
+        payload: {
+          hashType,
+          salt,
+          label,
+          ikm: inputLocation,
+          okm: outputLocation,
+        },
+      },
+    });
+  }
 }
 
 export class Client {
@@ -250,6 +495,13 @@ export class Store {
     }).then((v) => Uint8Array.from(v));
   }
 
+  /**
+   * Inserts a record in the store, replacing the previous one.
+   *
+   * @param lifetime How long the record is kept, e.g. for session tokens. The expired records are removed
+   * when the store is read and when the snapshot is saved.
+   * @returns
+   */
   async insert(
     key: StoreKey,
     value: number[],
@@ -274,6 +526,24 @@ export class Store {
       }
     ).then((v) => (v != null ? Uint8Array.from(v) : null));
   }
+
+  /**
+   * Generates a TOTP code (RFC 6238) from the secret saved under `key`, e.g. the decoded `secret` of an
+   * `otpauth://` URI.
+   *
+   * Unlike `generateTOTP` on the vault secrets, the SHA-1 default is supported.
+   * @param key
+   * @param options
+   * @returns The code, zero-padded to the requested number of digits.
+   */
+  async generateTOTP(key: StoreKey, options?: TOTPOptions): Promise<string> {
+    return await invoke<string>("plugin:stronghold|generate_totp", {
+      snapshotPath: this.path,
+      client: this.client,
+      key: toBytesDto(key),
+      options: options ?? {},
+    });
+  }
 }
 
 export class Vault extends ProcedureExecutor {
@@ -320,6 +590,14 @@ export class Stronghold {
     void this.reload(password);
   }
 
+  /**
+   * The paths of the unlocked snapshots this window can access, for apps opening one snapshot per profile.
+   * @returns
+   */
+  static async listOpen(): Promise<string[]> {
+    return await invoke("plugin:stronghold|list_snapshots");
+  }
+
   private async reload(password: string): Promise<void> {
     return await invoke("plugin:stronghold|initialize", {
       snapshotPath: this.path,
@@ -327,6 +605,139 @@ export class Stronghold {
     });
   }
 
+  /**
+   * Unlocks the snapshot again after it has been locked.
+   * @param password
+   * @returns
+   */
+  async unlock(password: string): Promise<void> {
+    return await this.reload(password);
+  }
+
+  /**
+   * Unlocks the snapshot with the key stored in the OS keychain.
+   *
+   * Requires the `keychain` feature and a previous call to `enableKeychainUnlock`.
+   * Fails with `user_presence_denied` when the user presence verifier of the plugin refuses the unlock.
+   * @returns
+   */
+  async unlockWithKeychain(): Promise<void> {
+    return await invoke("plugin:stronghold|unlock_with_keychain", {
+      snapshotPath: this.path,
+    });
+  }
+
+  /**
+   * Stores the snapshot key in the OS keychain so the snapshot can be unlocked with `unlockWithKeychain`.
+   *
+   * The snapshot must be unlocked.
+   * @param password The snapshot password, used to verify the user before storing the key.
+   * @returns
+   */
+  async enableKeychainUnlock(password: string): Promise<void> {
+    return await invoke("plugin:stronghold|enable_keychain_unlock", {
+      snapshotPath: this.path,
+      password,
+    });
+  }
+
+  /**
+   * Removes the snapshot key from the OS keychain.
+   * @returns
+   */
+  async disableKeychainUnlock(): Promise<void> {
+    return await invoke("plugin:stronghold|disable_keychain_unlock", {
+      snapshotPath: this.path,
+    });
+  }
+
+  /**
+   * Saves the snapshot and removes its key from memory.
+   *
+   * The snapshot must be unlocked with its password again before it can be used.
+   * @returns
+   */
+  async lockNow(): Promise<void> {
+    return await invoke("plugin:stronghold|lock_now", {
+      snapshotPath: this.path,
+    });
+  }
+
+  /**
+   * Listen to the snapshot being locked, either explicitly or after the configured idle timeout.
+   * @param cb
+   * @returns A promise resolving to a function to unlisten to the event.
+   */
+  async onLock(cb: () => void): Promise<UnlistenFn> {
+    return await listen<LockStatePayload>("stronghold://locked", (event) => {
+      if (event.payload.snapshotPath === this.path) {
+        cb();
+      }
+    });
+  }
+
+  /**
+   * Listen to the idle timeout failing to lock the snapshot, which stays unlocked until it's accessed again.
+   * @param cb Called with the error saving the snapshot.
+   * @returns A promise resolving to a function to unlisten to the event.
+   */
+  async onLockFailed(cb: (error: string) => void): Promise<UnlistenFn> {
+    return await listen<LockFailedPayload>(
+      "stronghold://lock-failed",
+      (event) => {
+        if (event.payload.snapshotPath === this.path) {
+          cb(event.payload.error);
+        }
+      }
+    );
+  }
+
+  /**
+   * Listen to the snapshot being unlocked.
+   * @param cb
+   * @returns A promise resolving to a function to unlisten to the event.
+   */
+  async onUnlock(cb: () => void): Promise<UnlistenFn> {
+    return await listen<LockStatePayload>("stronghold://unlocked", (event) => {
+      if (event.payload.snapshotPath === this.path) {
+        cb();
+      }
+    });
+  }
+
+  /**
+   * Listen to the commands waiting on this snapshot, which runs them one at a time in the order they were invoked.
+   * @param cb Called with the number of commands running or waiting, `0` once they all ran.
+   * @returns A promise resolving to a function to unlisten to the event.
+   */
+  async onBusy(cb: (depth: number) => void): Promise<UnlistenFn> {
+    return await listen<BusyPayload>("stronghold://busy", (event) => {
+      if (event.payload.snapshotPath === this.path) {
+        cb(event.payload.depth);
+      }
+    });
+  }
+
+  /**
+   * Returns the number of commands running or waiting on this snapshot.
+   * @returns
+   */
+  async queueDepth(): Promise<number> {
+    return await invoke("plugin:stronghold|queue_depth", {
+      snapshotPath: this.path,
+    });
+  }
+
+  /**
+   * Saves and locks this snapshot, leaving the other ones open.
+   * @returns Whether the snapshot was unlocked.
+   */
+  async close(): Promise<boolean> {
+    return await invoke("plugin:stronghold|close_snapshot", {
+      snapshotPath: this.path,
+    });
+  }
+
   async unload(): Promise<void> {
     return await invoke("plugin:stronghold|destroy", {
       snapshotPath: this.path,
@@ -352,4 +763,55 @@ export class Stronghold {
       snapshotPath: this.path,
     });
   }
+
+  /**
+   * Re-encrypts the snapshot with a new password.
+   *
+   * The snapshot is written to a temporary file and swapped in place, so the old snapshot stays intact if anything fails.
+   * @param oldPassword The password the snapshot is currently unlocked with.
+   * @param newPassword The password used to re-encrypt the snapshot.
+   * @param progressHandler Called for every step of the re-encryption.
+   * @returns
+   */
+  /**
+   * Writes a copy of the snapshot at a path allowed on the fs scope, e.g. as a backup before rotating the password.
+   * @param path
+   * @param password Encrypts the copy instead of the password the snapshot is unlocked with.
+   * @returns
+   */
+  async export(path: string, password?: string): Promise<void> {
+    return await invoke("plugin:stronghold|export_snapshot", {
+      snapshotPath: this.path,
+      destination: path,
+      password,
+    });
+  }
+
+  async changePassword(
+    oldPassword: string,
+    newPassword: string,
+    progressHandler?: (progress: ChangePasswordProgress) => void
+  ): Promise<void> {
+    const unlisten =
+      progressHandler != null
+        ? await appWindow.listen<ChangePasswordProgress>(
+            "stronghold://change-password-progress",
+            ({ payload }) => {
+              if (payload.snapshotPath === this.path) {
+                progressHandler(payload);
+              }
+            }
+          )
+        : null;
+
+    try {
+      await invoke("plugin:stronghold|change_password", {
+        snapshotPath: this.path,
+        oldPassword,
+        newPassword,
+      });
+    } finally {
+      unlisten?.();
+    }
+  }
 }
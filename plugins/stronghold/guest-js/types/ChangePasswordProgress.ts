@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChangePasswordStage } from "./ChangePasswordStage";
+
+export interface ChangePasswordProgress { snapshotPath: string, stage: ChangePasswordStage, progress: number, total: number, }
\ No newline at end of file
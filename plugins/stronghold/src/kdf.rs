@@ -0,0 +1,438 @@
+//! Hashing of the snapshot passwords with Argon2id (RFC 9106), for [`Builder::with_argon2`](crate::Builder::with_argon2).
+//!
+//! The lanes are filled one after the other on the calling thread and split the memory between them, so the
+//! parallelism neither adds to the memory nor makes the unlock faster. It only matters to derive the same keys as
+//! another Argon2 implementation.
+
+use std::{
+    fs::{self, create_dir_all},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use blake2::{
+    digest::{Update, VariableOutput},
+    Blake2bVar,
+};
+use zeroize::Zeroize;
+
+use crate::stronghold::{Error, Result};
+
+const BLOCK_WORDS: usize = 128;
+const SYNC_POINTS: u32 = 4;
+const VERSION: u32 = 0x13;
+/// Argon2id.
+const TYPE: u32 = 2;
+const SALT_LEN: usize = 32;
+
+type Block = [u64; BLOCK_WORDS];
+
+/// The cost of the Argon2id hash, trading the unlock latency for the resistance to brute force.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Params {
+    memory: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl Default for Argon2Params {
+    /// 64 MiB, 3 iterations and 4 lanes, the second recommended option of RFC 9106.
+    fn default() -> Self {
+        Self {
+            memory: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+impl Argon2Params {
+    /// A lighter profile for the mobile devices, 19 MiB, 2 iterations and a single lane.
+    pub fn mobile() -> Self {
+        Self {
+            memory: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+
+    /// The memory filled by the hash in KiB, raised to 8 KiB per lane.
+    #[must_use]
+    pub fn memory(mut self, kib: u32) -> Self {
+        self.memory = kib;
+        self
+    }
+
+    /// The passes over the memory, at least 1.
+    #[must_use]
+    pub fn iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// The lanes of the memory, at least 1.
+    #[must_use]
+    pub fn parallelism(mut self, parallelism: u32) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+}
+
+/// Reads the salt of the passwords at the path, generated on the first use.
+pub fn salt(path: &Path) -> Result<Vec<u8>> {
+    match fs::read(path) {
+        Ok(salt) => checked(salt),
+        Err(e) if e.kind() == ErrorKind::NotFound => create_salt(path),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn checked(salt: Vec<u8>) -> Result<Vec<u8>> {
+    if salt.len() < 8 {
+        Err(Error::InvalidSalt)
+    } else {
+        Ok(salt)
+    }
+}
+
+/// Writes a new salt at the path, or reads the one written first by a concurrent call.
+fn create_salt(path: &Path) -> Result<Vec<u8>> {
+    let mut salt = vec![0; SALT_LEN];
+    let mut suffix = [0; 8];
+    crypto::utils::rand::fill(&mut salt).map_err(|e| io::Error::other(e.to_string()))?;
+    crypto::utils::rand::fill(&mut suffix).map_err(|e| io::Error::other(e.to_string()))?;
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    // linked in place once written, unlike a rename it never replaces the salt another call linked first
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(format!(".{}.tmp", hex::encode(suffix)));
+    let tmp_path = PathBuf::from(tmp_path);
+    fs::write(&tmp_path, &salt)?;
+    let linked = fs::hard_link(&tmp_path, path);
+    let _ = fs::remove_file(&tmp_path);
+    match linked {
+        Ok(()) => Ok(salt),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => checked(fs::read(path)?),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Derives the 32 bytes key of a snapshot from the password and the salt.
+pub fn argon2id(password: &[u8], salt: &[u8], params: Argon2Params) -> Vec<u8> {
+    hash(password, salt, &[], &[], params)
+}
+
+/// Argon2id with the optional secret and associated data of RFC 9106.
+fn hash(password: &[u8], salt: &[u8], secret: &[u8], data: &[u8], params: Argon2Params) -> Vec<u8> {
+    let lanes = params.parallelism.max(1);
+    let passes = params.iterations.max(1);
+    let memory = params.memory.max(2 * SYNC_POINTS * lanes);
+    let segment = memory / (SYNC_POINTS * lanes);
+    let lane_len = segment * SYNC_POINTS;
+    let tag_len: u32 = 32;
+
+    let mut h0 = [0; 72];
+    blake2b(
+        &mut h0[..64],
+        &[
+            &lanes.to_le_bytes(),
+            &tag_len.to_le_bytes(),
+            &memory.to_le_bytes(),
+            &passes.to_le_bytes(),
+            &VERSION.to_le_bytes(),
+            &TYPE.to_le_bytes(),
+            &(password.len() as u32).to_le_bytes(),
+            password,
+            &(salt.len() as u32).to_le_bytes(),
+            salt,
+            &(secret.len() as u32).to_le_bytes(),
+            secret,
+            &(data.len() as u32).to_le_bytes(),
+            data,
+        ],
+    );
+
+    let mut blocks = vec![[0u64; BLOCK_WORDS]; (lane_len * lanes) as usize];
+    let mut bytes = [0; 1024];
+    for lane in 0..lanes {
+        for column in 0..2u32 {
+            h0[64..68].copy_from_slice(&column.to_le_bytes());
+            h0[68..].copy_from_slice(&lane.to_le_bytes());
+            variable_hash(&mut bytes, &[&h0]);
+            blocks[(lane * lane_len + column) as usize] = from_bytes(&bytes);
+        }
+    }
+
+    for pass in 0..passes {
+        for slice in 0..SYNC_POINTS {
+            for lane in 0..lanes {
+                let geometry = Geometry {
+                    pass,
+                    slice,
+                    lane,
+                    lanes,
+                    passes,
+                    blocks: lane_len * lanes,
+                    segment,
+                    lane_len,
+                };
+                fill_segment(&mut blocks, &geometry);
+            }
+        }
+    }
+
+    let mut last = blocks[(lane_len - 1) as usize];
+    for lane in 1..lanes {
+        xor(
+            &mut last,
+            &blocks[(lane * lane_len + lane_len - 1) as usize],
+        );
+    }
+    let mut tag = vec![0; tag_len as usize];
+    variable_hash(&mut tag, &[&to_bytes(&last)]);
+
+    blocks.zeroize();
+    h0.zeroize();
+    bytes.zeroize();
+    last.zeroize();
+    tag
+}
+
+struct Geometry {
+    pass: u32,
+    slice: u32,
+    lane: u32,
+    lanes: u32,
+    passes: u32,
+    /// The blocks of memory, rounded down to a multiple of the lanes and the slices.
+    blocks: u32,
+    segment: u32,
+    lane_len: u32,
+}
+
+fn fill_segment(blocks: &mut [Block], g: &Geometry) {
+    // the first half of the first pass is independent of the password, against side channels
+    let data_independent = g.pass == 0 && g.slice < SYNC_POINTS / 2;
+    let mut input = [0u64; BLOCK_WORDS];
+    let mut addresses = [0u64; BLOCK_WORDS];
+    if data_independent {
+        input[..6].copy_from_slice(&[
+            g.pass as u64,
+            g.lane as u64,
+            g.slice as u64,
+            g.blocks as u64,
+            g.passes as u64,
+            TYPE as u64,
+        ]);
+    }
+
+    let first = if g.pass == 0 && g.slice == 0 { 2 } else { 0 };
+    if data_independent && first != 0 {
+        next_addresses(&mut input, &mut addresses);
+    }
+    for index in first..g.segment {
+        let column = g.slice * g.segment + index;
+        let current = (g.lane * g.lane_len + column) as usize;
+        let previous = if column == 0 {
+            current + g.lane_len as usize - 1
+        } else {
+            current - 1
+        };
+
+        let pseudo_random = if data_independent {
+            if index % BLOCK_WORDS as u32 == 0 {
+                next_addresses(&mut input, &mut addresses);
+            }
+            addresses[(index % BLOCK_WORDS as u32) as usize]
+        } else {
+            blocks[previous][0]
+        };
+
+        let ref_lane = if g.pass == 0 && g.slice == 0 {
+            g.lane
+        } else {
+            ((pseudo_random >> 32) % g.lanes as u64) as u32
+        };
+        let ref_column = reference_column(g, index, ref_lane == g.lane, pseudo_random as u32);
+        let reference = (ref_lane * g.lane_len + ref_column) as usize;
+
+        let mut block = blocks[previous];
+        xor(&mut block, &blocks[reference]);
+        let mut result = block;
+        permute(&mut block);
+        xor(&mut result, &block);
+        if g.pass > 0 {
+            xor(&mut result, &blocks[current]);
+        }
+        blocks[current] = result;
+    }
+}
+
+/// The column of the block referenced by the block at the index of the segment, from the blocks computed already.
+fn reference_column(g: &Geometry, index: u32, same_lane: bool, pseudo_random: u32) -> u32 {
+    let area = if g.pass == 0 {
+        if g.slice == 0 || same_lane {
+            g.slice * g.segment + index - 1
+        } else {
+            g.slice * g.segment - (index == 0) as u32
+        }
+    } else if same_lane {
+        g.lane_len - g.segment + index - 1
+    } else {
+        g.lane_len - g.segment - (index == 0) as u32
+    } as u64;
+    let x = (pseudo_random as u64 * pseudo_random as u64) >> 32;
+    let relative = area - 1 - ((area * x) >> 32);
+    let start = if g.pass == 0 || g.slice == SYNC_POINTS - 1 {
+        0
+    } else {
+        (g.slice + 1) * g.segment
+    } as u64;
+    ((start + relative) % g.lane_len as u64) as u32
+}
+
+/// The next block of pseudo-random addresses of the data-independent segments.
+fn next_addresses(input: &mut Block, addresses: &mut Block) {
+    input[6] += 1;
+    *addresses = compress(&[0; BLOCK_WORDS], input);
+    *addresses = compress(&[0; BLOCK_WORDS], addresses);
+}
+
+fn compress(x: &Block, y: &Block) -> Block {
+    let mut block = *x;
+    xor(&mut block, y);
+    let mut result = block;
+    permute(&mut block);
+    xor(&mut result, &block);
+    result
+}
+
+/// Applies the permutation of Argon2 to the rows, then to the columns, of the block seen as 8x8 16 bytes registers.
+fn permute(block: &mut Block) {
+    for row in 0..8 {
+        let mut v = [0; 16];
+        v.copy_from_slice(&block[row * 16..row * 16 + 16]);
+        round(&mut v);
+        block[row * 16..row * 16 + 16].copy_from_slice(&v);
+    }
+    for column in 0..8 {
+        let mut v = [0; 16];
+        for register in 0..8 {
+            v[2 * register] = block[register * 16 + 2 * column];
+            v[2 * register + 1] = block[register * 16 + 2 * column + 1];
+        }
+        round(&mut v);
+        for register in 0..8 {
+            block[register * 16 + 2 * column] = v[2 * register];
+            block[register * 16 + 2 * column + 1] = v[2 * register + 1];
+        }
+    }
+}
+
+fn round(v: &mut [u64; 16]) {
+    for (a, b, c, d) in [
+        (0, 4, 8, 12),
+        (1, 5, 9, 13),
+        (2, 6, 10, 14),
+        (3, 7, 11, 15),
+        (0, 5, 10, 15),
+        (1, 6, 11, 12),
+        (2, 7, 8, 13),
+        (3, 4, 9, 14),
+    ] {
+        mix(v, a, b, c, d);
+    }
+}
+
+/// The BLAKE2b mixing function, with the multiplications that make Argon2 costly to compute in hardware.
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize) {
+    fn add(x: u64, y: u64) -> u64 {
+        x.wrapping_add(y)
+            .wrapping_add(2u64.wrapping_mul((x as u32 as u64) * (y as u32 as u64)))
+    }
+    v[a] = add(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = add(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = add(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = add(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+fn xor(block: &mut Block, other: &Block) {
+    for (word, other) in block.iter_mut().zip(other) {
+        *word ^= other;
+    }
+}
+
+fn blake2b(out: &mut [u8], inputs: &[&[u8]]) {
+    let mut hasher = Blake2bVar::new(out.len()).expect("invalid BLAKE2b output size");
+    for input in inputs {
+        hasher.update(input);
+    }
+    hasher
+        .finalize_variable(out)
+        .expect("invalid BLAKE2b output size");
+}
+
+/// The variable-length hash function H' of Argon2.
+fn variable_hash(out: &mut [u8], inputs: &[&[u8]]) {
+    let len = (out.len() as u32).to_le_bytes();
+    let mut with_len = vec![&len[..]];
+    with_len.extend_from_slice(inputs);
+    if out.len() <= 64 {
+        blake2b(out, &with_len);
+        return;
+    }
+
+    let mut v = [0; 64];
+    blake2b(&mut v, &with_len);
+    let mut written = 0;
+    while out.len() - written > 64 {
+        out[written..written + 32].copy_from_slice(&v[..32]);
+        written += 32;
+        let previous = v;
+        blake2b(&mut v[..(out.len() - written).min(64)], &[&previous]);
+    }
+    let rest = out.len() - written;
+    out[written..].copy_from_slice(&v[..rest]);
+    v.zeroize();
+}
+
+fn from_bytes(bytes: &[u8; 1024]) -> Block {
+    let mut block = [0; BLOCK_WORDS];
+    for (word, bytes) in block.iter_mut().zip(bytes.chunks_exact(8)) {
+        *word = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+    block
+}
+
+fn to_bytes(block: &Block) -> [u8; 1024] {
+    let mut bytes = [0; 1024];
+    for (bytes, word) in bytes.chunks_exact_mut(8).zip(block) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc_9106_test_vector() {
+        // section 5.3
+        let params = Argon2Params::default()
+            .memory(32)
+            .iterations(3)
+            .parallelism(4);
+        let tag = hash(&[1; 32], &[2; 16], &[3; 8], &[4; 12], params);
+        assert_eq!(
+            hex::encode(tag),
+            "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659"
+        );
+    }
+}
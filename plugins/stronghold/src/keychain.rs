@@ -0,0 +1,119 @@
+//! Storage of snapshot keys in the OS keychain.
+//!
+//! Keys are stored in the macOS Keychain, the Windows Credential Manager or the Secret Service on Linux,
+//! one entry per snapshot path. Whether releasing an entry requires user presence (Touch ID, Windows Hello)
+//! depends on the keychain policy of the platform, [`Builder::user_presence`](crate::Builder::user_presence)
+//! verifies it in the app before `unlock_with_keychain` reads the entry.
+//!
+//! Requires the `keychain` feature, without it every function returns [`Error::KeychainUnavailable`].
+
+use std::path::Path;
+
+use crate::stronghold::{Error, Result};
+
+#[cfg(feature = "keychain")]
+fn entry(service: &str, snapshot_path: &Path) -> Result<keyring::Entry> {
+    keyring::Entry::new(service, &snapshot_path.to_string_lossy()).map_err(Into::into)
+}
+
+#[cfg(feature = "keychain")]
+fn set(entry: &keyring::Entry, key: &[u8]) -> Result<()> {
+    use zeroize::Zeroize;
+
+    let mut encoded = hex::encode(key);
+    let result = entry.set_password(&encoded);
+    encoded.zeroize();
+    result.map_err(Into::into)
+}
+
+#[cfg(feature = "keychain")]
+fn replace(entry: &keyring::Entry, key: &[u8]) -> Result<bool> {
+    use zeroize::Zeroize;
+
+    match entry.get_password() {
+        Ok(mut encoded) => encoded.zeroize(),
+        Err(keyring::Error::NoEntry) => return Ok(false),
+        Err(e) => return Err(e.into()),
+    }
+    set(entry, key).map(|()| true)
+}
+
+/// Stores the (hashed) snapshot key in the OS keychain, replacing any previous entry.
+#[cfg(feature = "keychain")]
+pub fn store_key(service: &str, snapshot_path: &Path, key: &[u8]) -> Result<()> {
+    set(&entry(service, snapshot_path)?, key)
+}
+
+/// Replaces the snapshot key in the OS keychain if it holds one, e.g. after a password change.
+///
+/// Returns whether an entry was replaced.
+#[cfg(feature = "keychain")]
+pub fn replace_key(service: &str, snapshot_path: &Path, key: &[u8]) -> Result<bool> {
+    replace(&entry(service, snapshot_path)?, key)
+}
+
+/// Reads the snapshot key from the OS keychain.
+#[cfg(feature = "keychain")]
+pub fn load_key(service: &str, snapshot_path: &Path) -> Result<Vec<u8>> {
+    use zeroize::Zeroize;
+
+    let mut encoded = entry(service, snapshot_path)?.get_password()?;
+    let key = hex::decode(&encoded).map_err(|_| Error::InvalidKeychainEntry);
+    encoded.zeroize();
+    key
+}
+
+/// Removes the snapshot key from the OS keychain.
+#[cfg(feature = "keychain")]
+pub fn delete_key(service: &str, snapshot_path: &Path) -> Result<()> {
+    match entry(service, snapshot_path)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn store_key(_service: &str, _snapshot_path: &Path, _key: &[u8]) -> Result<()> {
+    Err(Error::KeychainUnavailable)
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn replace_key(_service: &str, _snapshot_path: &Path, _key: &[u8]) -> Result<bool> {
+    Ok(false)
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn load_key(_service: &str, _snapshot_path: &Path) -> Result<Vec<u8>> {
+    Err(Error::KeychainUnavailable)
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn delete_key(_service: &str, _snapshot_path: &Path) -> Result<()> {
+    Err(Error::KeychainUnavailable)
+}
+
+#[cfg(all(test, feature = "keychain"))]
+mod tests {
+    use keyring::{mock::MockCredential, Entry};
+
+    use super::*;
+
+    fn mock_entry() -> Entry {
+        Entry::new_with_credential(Box::<MockCredential>::default())
+    }
+
+    #[test]
+    fn replace_keeps_missing_entries_missing() {
+        let entry = mock_entry();
+        assert!(!replace(&entry, b"new").unwrap());
+        assert!(matches!(entry.get_password(), Err(keyring::Error::NoEntry)));
+    }
+
+    #[test]
+    fn replace_stores_the_new_key() {
+        let entry = mock_entry();
+        set(&entry, b"old").unwrap();
+        assert!(replace(&entry, b"new").unwrap());
+        assert_eq!(entry.get_password().unwrap(), hex::encode(b"new"));
+    }
+}
@@ -1,36 +1,136 @@
 use std::{
     collections::HashMap,
     fmt,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
-    time::Duration,
+    thread::{sleep, spawn},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use hmac::{digest::KeyInit, Mac};
 use iota_stronghold::{
     procedures::{
-        BIP39Generate, BIP39Recover, Chain, Ed25519Sign, KeyType as StrongholdKeyType,
-        MnemonicLanguage, PublicKey, Slip10Derive, Slip10DeriveInput, Slip10Generate,
-        StrongholdProcedure,
+        AeadCipher, AeadDecrypt, AeadEncrypt, BIP39Generate, BIP39Recover, Chain, Ed25519Sign,
+        GenerateKey, Hkdf, Hmac, KeyType as StrongholdKeyType, MnemonicLanguage, PublicKey,
+        Sha2Hash, Slip10Derive, Slip10DeriveInput, Slip10Generate, StrongholdProcedure,
+        X25519DiffieHellman,
     },
     Client, Location,
 };
-use serde::{de::Visitor, Deserialize, Deserializer};
+use log::error;
+use serde::{de::Visitor, Deserialize, Deserializer, Serialize};
 use stronghold::{Error, Result, Stronghold};
 use tauri::{
     plugin::{Builder as PluginBuilder, TauriPlugin},
-    Manager, Runtime, State,
+    AppHandle, Manager, Runtime, State, Window,
 };
 use zeroize::Zeroize;
 
+pub mod kdf;
+pub mod keychain;
+mod queue;
 pub mod stronghold;
 
-type PasswordHashFn = dyn Fn(&str) -> Vec<u8> + Send + Sync;
+pub use kdf::Argon2Params;
+use queue::{Queues, Turn};
+
+type PasswordHashFn = dyn Fn(&str) -> Result<Vec<u8>> + Send + Sync;
+type AccessPolicyFn = dyn Fn(&str, &Path) -> bool + Send + Sync;
+type UserPresenceFn = dyn Fn(&Path) -> bool + Send + Sync;
 
 #[derive(Default)]
-struct StrongholdCollection(Arc<Mutex<HashMap<PathBuf, Stronghold>>>);
+struct StrongholdCollection {
+    strongholds: Arc<Mutex<HashMap<PathBuf, Stronghold>>>,
+    access_policy: Option<Box<AccessPolicyFn>>,
+    user_presence: Option<Box<UserPresenceFn>>,
+    queues: Queues,
+}
+
+impl StrongholdCollection {
+    /// Checks the access policy configured on the [`Builder`] for the given window.
+    fn check_access<R: Runtime>(&self, window: &Window<R>, snapshot_path: &Path) -> Result<()> {
+        match &self.access_policy {
+            Some(policy) if !policy(window.label(), snapshot_path) => {
+                Err(Error::AccessDenied(window.label().into()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the access to the snapshot and waits for the commands invoked on it before, emitting the
+    /// `stronghold://busy` event while some are waiting.
+    async fn enqueue<R: Runtime>(&self, window: &Window<R>, snapshot_path: &Path) -> Result<Turn> {
+        self.check_access(window, snapshot_path)?;
+        let app = window.app_handle();
+        let payload_path = snapshot_path.to_path_buf();
+        Ok(self
+            .queues
+            .enqueue(snapshot_path, move |depth| {
+                let _ = app.emit_all(
+                    "stronghold://busy",
+                    BusyPayload {
+                        snapshot_path: payload_path.clone(),
+                        depth,
+                    },
+                );
+            })
+            .await)
+    }
+
+    /// Verifies the user's presence with the verifier configured on the [`Builder`], if any.
+    fn check_user_presence(&self, snapshot_path: &Path) -> Result<()> {
+        match &self.user_presence {
+            Some(verify) if !verify(snapshot_path) => Err(Error::UserPresenceDenied),
+            _ => Ok(()),
+        }
+    }
+}
 
 struct PasswordHashFunction(Box<PasswordHashFn>);
 
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+struct LockStatePayload {
+    snapshot_path: PathBuf,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+struct LockFailedPayload {
+    snapshot_path: PathBuf,
+    error: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+struct BusyPayload {
+    snapshot_path: PathBuf,
+    /// The commands running or waiting on the snapshot.
+    depth: usize,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+enum ChangePasswordStage {
+    Verify,
+    Reencrypt,
+    Done,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
+struct ChangePasswordProgress {
+    snapshot_path: PathBuf,
+    stage: ChangePasswordStage,
+    progress: u32,
+    total: u32,
+}
+
 #[derive(Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
 #[serde(untagged)]
 enum BytesDto {
@@ -89,6 +189,67 @@ impl From<Slip10DeriveInputDto> for Slip10DeriveInput {
     }
 }
 
+/// A SLIP-10 derivation path, from its indices or a string like `m/44'/4218'/0'`, hardened as ed25519 requires.
+struct ChainDto(Vec<u32>);
+
+impl<'de> Deserialize<'de> for ChainDto {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Chain {
+            Indices(Vec<u32>),
+            Path(String),
+        }
+
+        match Chain::deserialize(deserializer)? {
+            Chain::Indices(indices) => Ok(Self(indices)),
+            Chain::Path(path) => {
+                let path = path.strip_prefix('m').unwrap_or(&path);
+                path.split('/')
+                    .filter(|segment| !segment.is_empty())
+                    .map(|segment| {
+                        let index = segment
+                            .strip_suffix(|c| c == '\'' || c == 'h' || c == 'H')
+                            .ok_or_else(|| {
+                                serde::de::Error::custom(format!(
+                                    "segment `{}` isn't hardened, as ed25519 requires",
+                                    segment
+                                ))
+                            })?;
+                        index
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|index| *index < 1 << 31)
+                            .ok_or_else(|| {
+                                serde::de::Error::custom(format!("invalid segment `{}`", segment))
+                            })
+                    })
+                    .collect::<std::result::Result<_, _>>()
+                    .map(Self)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+enum MnemonicLanguageDto {
+    #[default]
+    English,
+    Japanese,
+}
+
+impl From<MnemonicLanguageDto> for MnemonicLanguage {
+    fn from(dto: MnemonicLanguageDto) -> MnemonicLanguage {
+        match dto {
+            MnemonicLanguageDto::English => MnemonicLanguage::English,
+            MnemonicLanguageDto::Japanese => MnemonicLanguage::Japanese,
+        }
+    }
+}
+
 pub enum KeyType {
     Ed25519,
     X25519,
@@ -133,6 +294,116 @@ impl<'de> Deserialize<'de> for KeyType {
     }
 }
 
+#[derive(Default, Deserialize)]
+enum HashTypeDto {
+    #[default]
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl From<HashTypeDto> for Sha2Hash {
+    fn from(dto: HashTypeDto) -> Sha2Hash {
+        match dto {
+            HashTypeDto::Sha256 => Sha2Hash::Sha256,
+            HashTypeDto::Sha384 => Sha2Hash::Sha384,
+            HashTypeDto::Sha512 => Sha2Hash::Sha512,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+enum CipherDto {
+    Aes256Gcm,
+    XChaCha20Poly1305,
+}
+
+impl From<CipherDto> for AeadCipher {
+    fn from(dto: CipherDto) -> AeadCipher {
+        match dto {
+            CipherDto::Aes256Gcm => AeadCipher::Aes256Gcm,
+            CipherDto::XChaCha20Poly1305 => AeadCipher::XChaCha20Poly1305,
+        }
+    }
+}
+
+const DEFAULT_TOTP_PERIOD: u64 = 30;
+const DEFAULT_TOTP_DIGITS: u32 = 6;
+
+/// The hash functions of the TOTP codes, SHA-1 for the secrets of most authenticator apps.
+#[derive(Clone, Copy, Default, Deserialize)]
+enum TotpHashDto {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+/// The options of the TOTP codes, the vault procedures only take the SHA-2 [`HashTypeDto`] and default to SHA-256.
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "H: Deserialize<'de> + Default"))]
+struct TotpOptionsDto<H = TotpHashDto> {
+    #[serde(rename = "hashType", default)]
+    hash_type: H,
+    /// Time step in seconds, defaults to 30.
+    period: Option<u64>,
+    /// Number of digits of the code (at most 9), defaults to 6.
+    digits: Option<u32>,
+    /// Unix timestamp in seconds the code is generated for, defaults to now.
+    timestamp: Option<u64>,
+}
+
+impl<H> TotpOptionsDto<H> {
+    /// The HOTP counter of the time step of the timestamp.
+    fn counter(&self) -> u64 {
+        let timestamp = self.timestamp.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        });
+        timestamp / self.period.unwrap_or(DEFAULT_TOTP_PERIOD).max(1)
+    }
+
+    fn digits(&self) -> u32 {
+        self.digits.unwrap_or(DEFAULT_TOTP_DIGITS).clamp(1, 9)
+    }
+}
+
+/// Computes the HMAC of the TOTP counter with the secret, outside of the vaults which only have the SHA-2 ones.
+fn totp_hmac(hash_type: TotpHashDto, secret: &[u8], counter: u64) -> Vec<u8> {
+    fn mac<M: Mac + KeyInit>(secret: &[u8], msg: &[u8]) -> Vec<u8> {
+        let mut mac = <M as Mac>::new_from_slice(secret).expect("HMAC keys have any length");
+        mac.update(msg);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    let msg = counter.to_be_bytes();
+    match hash_type {
+        TotpHashDto::Sha1 => mac::<hmac::Hmac<sha1::Sha1>>(secret, &msg),
+        TotpHashDto::Sha256 => mac::<hmac::Hmac<sha2::Sha256>>(secret, &msg),
+        TotpHashDto::Sha384 => mac::<hmac::Hmac<sha2::Sha384>>(secret, &msg),
+        TotpHashDto::Sha512 => mac::<hmac::Hmac<sha2::Sha512>>(secret, &msg),
+    }
+}
+
+/// Truncates a HMAC into a HOTP code as described in RFC 4226 section 5.3.
+fn hotp_code(mac: &[u8], digits: u32) -> String {
+    let offset = (mac[mac.len() - 1] & 0xf) as usize;
+    let binary = u32::from_be_bytes([
+        mac[offset] & 0x7f,
+        mac[offset + 1],
+        mac[offset + 2],
+        mac[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(digits),
+        width = digits as usize
+    )
+}
+
 #[derive(Deserialize)]
 #[serde(tag = "type", content = "payload")]
 #[allow(clippy::upper_case_acronyms)]
@@ -143,7 +414,7 @@ enum ProcedureDto {
         size_bytes: Option<usize>,
     },
     SLIP10Derive {
-        chain: Vec<u32>,
+        chain: ChainDto,
         input: Slip10DeriveInputDto,
         output: LocationDto,
     },
@@ -155,6 +426,9 @@ enum ProcedureDto {
     BIP39Generate {
         passphrase: Option<String>,
         output: LocationDto,
+        /// The words of the mnemonic, English by default.
+        #[serde(default)]
+        language: MnemonicLanguageDto,
     },
     PublicKey {
         #[serde(rename = "type")]
@@ -167,11 +441,59 @@ enum ProcedureDto {
         private_key: LocationDto,
         msg: String,
     },
+    /// Generates a private key at `output`, which never leaves the vault.
+    GenerateKey {
+        #[serde(rename = "type")]
+        ty: KeyType,
+        output: LocationDto,
+    },
+    /// Stores the X25519 shared key of the private key and the public key of the peer at `shared_key`.
+    X25519DiffieHellman {
+        #[serde(rename = "publicKey")]
+        public_key: [u8; 32],
+        #[serde(rename = "privateKey")]
+        private_key: LocationDto,
+        #[serde(rename = "sharedKey")]
+        shared_key: LocationDto,
+    },
+    /// Generates a TOTP code (RFC 6238) from the secret at `key`, with a SHA-2 HMAC (SHA-256 by default).
+    TOTP {
+        key: LocationDto,
+        #[serde(flatten)]
+        options: TotpOptionsDto<HashTypeDto>,
+    },
+    AeadEncrypt {
+        cipher: CipherDto,
+        key: LocationDto,
+        plaintext: Vec<u8>,
+        #[serde(rename = "associatedData", default)]
+        associated_data: Vec<u8>,
+        nonce: Vec<u8>,
+    },
+    AeadDecrypt {
+        cipher: CipherDto,
+        key: LocationDto,
+        ciphertext: Vec<u8>,
+        #[serde(rename = "associatedData", default)]
+        associated_data: Vec<u8>,
+        tag: Vec<u8>,
+        nonce: Vec<u8>,
+    },
+    HKDF {
+        #[serde(rename = "hashType")]
+        hash_type: HashTypeDto,
+        salt: Vec<u8>,
+        label: Vec<u8>,
+        ikm: LocationDto,
+        okm: LocationDto,
+    },
 }
 
-impl From<ProcedureDto> for StrongholdProcedure {
-    fn from(dto: ProcedureDto) -> StrongholdProcedure {
-        match dto {
+impl TryFrom<ProcedureDto> for StrongholdProcedure {
+    type Error = Error;
+
+    fn try_from(dto: ProcedureDto) -> Result<StrongholdProcedure> {
+        let procedure = match dto {
             ProcedureDto::SLIP10Generate { output, size_bytes } => {
                 StrongholdProcedure::Slip10Generate(Slip10Generate {
                     output: output.into(),
@@ -183,7 +505,7 @@ impl From<ProcedureDto> for StrongholdProcedure {
                 input,
                 output,
             } => StrongholdProcedure::Slip10Derive(Slip10Derive {
-                chain: Chain::from_u32_hardened(chain),
+                chain: Chain::from_u32_hardened(chain.0),
                 input: input.into(),
                 output: output.into(),
             }),
@@ -196,13 +518,15 @@ impl From<ProcedureDto> for StrongholdProcedure {
                 passphrase,
                 output: output.into(),
             }),
-            ProcedureDto::BIP39Generate { passphrase, output } => {
-                StrongholdProcedure::BIP39Generate(BIP39Generate {
-                    passphrase,
-                    output: output.into(),
-                    language: MnemonicLanguage::English,
-                })
-            }
+            ProcedureDto::BIP39Generate {
+                passphrase,
+                output,
+                language,
+            } => StrongholdProcedure::BIP39Generate(BIP39Generate {
+                passphrase,
+                output: output.into(),
+                language: language.into(),
+            }),
             ProcedureDto::PublicKey { ty, private_key } => {
                 StrongholdProcedure::PublicKey(PublicKey {
                     ty: ty.into(),
@@ -215,36 +539,254 @@ impl From<ProcedureDto> for StrongholdProcedure {
                     msg: msg.as_bytes().to_vec(),
                 })
             }
+            ProcedureDto::GenerateKey { ty, output } => {
+                StrongholdProcedure::GenerateKey(GenerateKey {
+                    ty: ty.into(),
+                    output: output.into(),
+                })
+            }
+            ProcedureDto::X25519DiffieHellman {
+                public_key,
+                private_key,
+                shared_key,
+            } => StrongholdProcedure::X25519DiffieHellman(X25519DiffieHellman {
+                public_key,
+                private_key: private_key.into(),
+                shared_key: shared_key.into(),
+            }),
+            // the code is truncated from the HMAC in `execute_procedure`
+            ProcedureDto::TOTP { key, options } => StrongholdProcedure::Hmac(Hmac {
+                msg: options.counter().to_be_bytes().to_vec(),
+                hash_type: options.hash_type.into(),
+                key: key.into(),
+            }),
+            ProcedureDto::AeadEncrypt {
+                cipher,
+                key,
+                plaintext,
+                associated_data,
+                nonce,
+            } => StrongholdProcedure::AeadEncrypt(AeadEncrypt {
+                cipher: cipher.into(),
+                associated_data,
+                plaintext,
+                nonce,
+                key: key.into(),
+            }),
+            ProcedureDto::AeadDecrypt {
+                cipher,
+                key,
+                ciphertext,
+                associated_data,
+                tag,
+                nonce,
+            } => StrongholdProcedure::AeadDecrypt(AeadDecrypt {
+                cipher: cipher.into(),
+                associated_data,
+                ciphertext,
+                tag,
+                nonce,
+                key: key.into(),
+            }),
+            ProcedureDto::HKDF {
+                hash_type,
+                salt,
+                label,
+                ikm,
+                okm,
+            } => StrongholdProcedure::Hkdf(Hkdf {
+                hash_type: hash_type.into(),
+                salt,
+                label,
+                ikm: ikm.into(),
+                okm: okm.into(),
+            }),
+        };
+        Ok(procedure)
+    }
+}
+
+/// Saves the snapshot and removes it from memory, emitting the `stronghold://locked` event.
+///
+/// Returns whether the snapshot was open.
+fn lock<R: Runtime>(
+    app: &AppHandle<R>,
+    collection: &mut HashMap<PathBuf, Stronghold>,
+    snapshot_path: &Path,
+) -> Result<bool> {
+    if let Some(stronghold) = collection.remove(snapshot_path) {
+        if let Err(e) = stronghold.save() {
+            collection.insert(snapshot_path.to_path_buf(), stronghold);
+            return Err(e);
         }
+        stronghold.clear()?;
+        let _ = app.emit_all(
+            "stronghold://locked",
+            LockStatePayload {
+                snapshot_path: snapshot_path.to_path_buf(),
+            },
+        );
+        return Ok(true);
     }
+    Ok(false)
 }
 
 #[tauri::command]
-async fn initialize(
+async fn initialize<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     hash_function: State<'_, PasswordHashFunction>,
     snapshot_path: PathBuf,
     mut password: String,
 ) -> Result<()> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
     let hash = (hash_function.0)(&password);
     password.zeroize();
-    let stronghold = Stronghold::new(snapshot_path.clone(), hash)?;
+    unlock(&app, &collection, snapshot_path, hash?)
+}
+
+/// Loads the snapshot with the given (hashed) password, emitting the `stronghold://unlocked` event.
+fn unlock<R: Runtime>(
+    app: &AppHandle<R>,
+    collection: &StrongholdCollection,
+    snapshot_path: PathBuf,
+    password: Vec<u8>,
+) -> Result<()> {
+    let stronghold = Stronghold::new(snapshot_path.clone(), password)?;
 
     collection
-        .0
+        .strongholds
         .lock()
         .unwrap()
-        .insert(snapshot_path, stronghold);
+        .insert(snapshot_path.clone(), stronghold);
+
+    let _ = app.emit_all("stronghold://unlocked", LockStatePayload { snapshot_path });
 
     Ok(())
 }
 
 #[tauri::command]
-async fn destroy(
+async fn lock_now<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     snapshot_path: PathBuf,
 ) -> Result<()> {
-    let mut collection = collection.0.lock().unwrap();
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    let mut collection = collection.strongholds.lock().unwrap();
+    lock(&app, &mut collection, &snapshot_path).map(|_| ())
+}
+
+/// The paths of the unlocked snapshots the window can access, sorted.
+#[tauri::command]
+async fn list_snapshots<R: Runtime>(
+    window: Window<R>,
+    collection: State<'_, StrongholdCollection>,
+) -> Result<Vec<PathBuf>> {
+    let mut paths = collection
+        .strongholds
+        .lock()
+        .unwrap()
+        .keys()
+        .filter(|path| collection.check_access(&window, path).is_ok())
+        .cloned()
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Locks the snapshot as [`lock_now`] and returns whether it was unlocked, leaving the other snapshots open.
+#[tauri::command]
+async fn close_snapshot<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    collection: State<'_, StrongholdCollection>,
+    snapshot_path: PathBuf,
+) -> Result<bool> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    let mut collection = collection.strongholds.lock().unwrap();
+    lock(&app, &mut collection, &snapshot_path)
+}
+
+/// Returns the number of commands running or waiting on the snapshot, this one excluded.
+#[tauri::command]
+async fn queue_depth<R: Runtime>(
+    window: Window<R>,
+    collection: State<'_, StrongholdCollection>,
+    snapshot_path: PathBuf,
+) -> Result<usize> {
+    collection.check_access(&window, &snapshot_path)?;
+    Ok(collection.queues.depth(&snapshot_path))
+}
+
+fn keychain_service<R: Runtime>(app: &AppHandle<R>) -> String {
+    format!("{}.stronghold", app.config().tauri.bundle.identifier)
+}
+
+#[tauri::command]
+async fn enable_keychain_unlock<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    collection: State<'_, StrongholdCollection>,
+    hash_function: State<'_, PasswordHashFunction>,
+    snapshot_path: PathBuf,
+    mut password: String,
+) -> Result<()> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    let hash = (hash_function.0)(&password);
+    password.zeroize();
+    let mut hash = hash?;
+
+    let valid = {
+        let mut collection = collection.strongholds.lock().unwrap();
+        let stronghold = collection
+            .get_mut(&snapshot_path)
+            .ok_or(Error::StrongholdNotInitialized)?;
+        stronghold.touch();
+        stronghold.verify_password(&hash)?
+    };
+    let result = if valid {
+        keychain::store_key(&keychain_service(&app), &snapshot_path, &hash)
+    } else {
+        Err(Error::InvalidPassword)
+    };
+    hash.zeroize();
+    result
+}
+
+#[tauri::command]
+async fn disable_keychain_unlock<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    collection: State<'_, StrongholdCollection>,
+    snapshot_path: PathBuf,
+) -> Result<()> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    keychain::delete_key(&keychain_service(&app), &snapshot_path)
+}
+
+#[tauri::command]
+async fn unlock_with_keychain<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    collection: State<'_, StrongholdCollection>,
+    snapshot_path: PathBuf,
+) -> Result<()> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    collection.check_user_presence(&snapshot_path)?;
+    let key = keychain::load_key(&keychain_service(&app), &snapshot_path)?;
+    unlock(&app, &collection, snapshot_path, key)
+}
+
+#[tauri::command]
+async fn destroy<R: Runtime>(
+    window: Window<R>,
+    collection: State<'_, StrongholdCollection>,
+    snapshot_path: PathBuf,
+) -> Result<()> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    let mut collection = collection.strongholds.lock().unwrap();
     if let Some(stronghold) = collection.remove(&snapshot_path) {
         if let Err(e) = stronghold.save() {
             collection.insert(snapshot_path, stronghold);
@@ -255,49 +797,174 @@ async fn destroy(
 }
 
 #[tauri::command]
-async fn save(collection: State<'_, StrongholdCollection>, snapshot_path: PathBuf) -> Result<()> {
-    let collection = collection.0.lock().unwrap();
-    if let Some(stronghold) = collection.get(&snapshot_path) {
+async fn save<R: Runtime>(
+    window: Window<R>,
+    collection: State<'_, StrongholdCollection>,
+    snapshot_path: PathBuf,
+) -> Result<()> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    let mut collection = collection.strongholds.lock().unwrap();
+    if let Some(stronghold) = collection.get_mut(&snapshot_path) {
+        stronghold.touch();
         stronghold.save()?;
     }
     Ok(())
 }
 
 #[tauri::command]
-async fn create_client(
+async fn change_password<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    collection: State<'_, StrongholdCollection>,
+    hash_function: State<'_, PasswordHashFunction>,
+    snapshot_path: PathBuf,
+    mut old_password: String,
+    mut new_password: String,
+) -> Result<()> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    let old_hash = (hash_function.0)(&old_password);
+    old_password.zeroize();
+    let new_hash = (hash_function.0)(&new_password);
+    new_password.zeroize();
+    let (old_hash, mut new_hash) = (old_hash?, new_hash?);
+
+    let emit_progress = |stage, progress| {
+        let _ = window.emit(
+            "stronghold://change-password-progress",
+            ChangePasswordProgress {
+                snapshot_path: snapshot_path.clone(),
+                stage,
+                progress,
+                total: 2,
+            },
+        );
+    };
+
+    let result = change_snapshot_password(
+        &collection,
+        &snapshot_path,
+        old_hash,
+        &new_hash,
+        emit_progress,
+    );
+    if result.is_ok() {
+        // the keychain entry holds the old key, only an up to date one may stay
+        let service = keychain_service(&app);
+        if let Err(e) = keychain::replace_key(&service, &snapshot_path, &new_hash) {
+            error!("failed to update the keychain entry of {snapshot_path:?}, removing it: {e}");
+            let _ = keychain::delete_key(&service, &snapshot_path);
+        }
+    }
+    new_hash.zeroize();
+    result
+}
+
+fn change_snapshot_password(
+    collection: &StrongholdCollection,
+    snapshot_path: &Path,
+    old_hash: Vec<u8>,
+    new_hash: &[u8],
+    emit_progress: impl Fn(ChangePasswordStage, u32),
+) -> Result<()> {
+    let mut collection = collection.strongholds.lock().unwrap();
+    let stronghold = collection
+        .get_mut(snapshot_path)
+        .ok_or(Error::StrongholdNotInitialized)?;
+    stronghold.touch();
+
+    emit_progress(ChangePasswordStage::Verify, 0);
+    if !stronghold.verify_password(&old_hash)? {
+        return Err(Error::InvalidPassword);
+    }
+
+    emit_progress(ChangePasswordStage::Reencrypt, 1);
+    stronghold.change_password(new_hash.to_vec())?;
+
+    emit_progress(ChangePasswordStage::Done, 2);
+    Ok(())
+}
+
+/// Writes a copy of the snapshot at a path of the fs scope, encrypted with `password` or the current password.
+#[tauri::command]
+async fn export_snapshot<R: Runtime>(
+    window: Window<R>,
+    collection: State<'_, StrongholdCollection>,
+    hash_function: State<'_, PasswordHashFunction>,
+    snapshot_path: PathBuf,
+    destination: PathBuf,
+    password: Option<String>,
+) -> Result<()> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    if !window.fs_scope().is_allowed(&destination) {
+        return Err(Error::PathForbidden(destination));
+    }
+    let hash = password.map(|mut password| {
+        let hash = (hash_function.0)(&password);
+        password.zeroize();
+        hash
+    });
+    let hash = hash.transpose()?;
+
+    let mut collection = collection.strongholds.lock().unwrap();
+    let stronghold = collection
+        .get_mut(&snapshot_path)
+        .ok_or(Error::StrongholdNotInitialized)?;
+    stronghold.touch();
+    stronghold.export(&destination, hash)
+}
+
+#[tauri::command]
+async fn create_client<R: Runtime>(
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     snapshot_path: PathBuf,
     client: BytesDto,
 ) -> Result<()> {
-    let stronghold = get_stronghold(collection, snapshot_path)?;
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    let mut collection = collection.strongholds.lock().unwrap();
+    let stronghold = collection
+        .get_mut(&snapshot_path)
+        .ok_or(Error::StrongholdNotInitialized)?;
+    stronghold.touch();
     stronghold.create_client(client)?;
     Ok(())
 }
 
 #[tauri::command]
-async fn load_client(
+async fn load_client<R: Runtime>(
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     snapshot_path: PathBuf,
     client: BytesDto,
 ) -> Result<()> {
-    let stronghold = get_stronghold(collection, snapshot_path)?;
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    let mut collection = collection.strongholds.lock().unwrap();
+    let stronghold = collection
+        .get_mut(&snapshot_path)
+        .ok_or(Error::StrongholdNotInitialized)?;
+    stronghold.touch();
     stronghold.load_client(client)?;
     Ok(())
 }
 
 #[tauri::command]
-async fn get_store_record(
+async fn get_store_record<R: Runtime>(
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     snapshot_path: PathBuf,
     client: BytesDto,
     key: String,
 ) -> Result<Option<Vec<u8>>> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
     let client = get_client(collection, snapshot_path, client)?;
-    client.store().get(key.as_ref()).map_err(Into::into)
+    let store = client.store();
+    stronghold::purge_expired(&store)?;
+    store.get(key.as_ref()).map_err(Into::into)
 }
 
 #[tauri::command]
-async fn save_store_record(
+async fn save_store_record<R: Runtime>(
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     snapshot_path: PathBuf,
     client: BytesDto,
@@ -305,6 +972,7 @@ async fn save_store_record(
     value: Vec<u8>,
     lifetime: Option<Duration>,
 ) -> Result<Option<Vec<u8>>> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
     let client = get_client(collection, snapshot_path, client)?;
     client
         .store()
@@ -313,18 +981,21 @@ async fn save_store_record(
 }
 
 #[tauri::command]
-async fn remove_store_record(
+async fn remove_store_record<R: Runtime>(
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     snapshot_path: PathBuf,
     client: BytesDto,
     key: String,
 ) -> Result<Option<Vec<u8>>> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
     let client = get_client(collection, snapshot_path, client)?;
     client.store().delete(key.as_ref()).map_err(Into::into)
 }
 
 #[tauri::command]
-async fn save_secret(
+async fn save_secret<R: Runtime>(
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     snapshot_path: PathBuf,
     client: BytesDto,
@@ -332,6 +1003,7 @@ async fn save_secret(
     record_path: BytesDto,
     secret: Vec<u8>,
 ) -> Result<()> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
     let client = get_client(collection, snapshot_path, client)?;
     client
         .vault(&vault)
@@ -340,13 +1012,15 @@ async fn save_secret(
 }
 
 #[tauri::command]
-async fn remove_secret(
+async fn remove_secret<R: Runtime>(
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     snapshot_path: PathBuf,
     client: BytesDto,
     vault: BytesDto,
     record_path: BytesDto,
 ) -> Result<()> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
     let client = get_client(collection, snapshot_path, client)?;
     client
         .vault(vault)
@@ -356,29 +1030,53 @@ async fn remove_secret(
 }
 
 #[tauri::command]
-async fn execute_procedure(
+async fn execute_procedure<R: Runtime>(
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     snapshot_path: PathBuf,
     client: BytesDto,
     procedure: ProcedureDto,
 ) -> Result<Vec<u8>> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
     let client = get_client(collection, snapshot_path, client)?;
-    client
-        .execute_procedure(StrongholdProcedure::from(procedure))
-        .map(Into::into)
-        .map_err(Into::into)
+    let totp_digits = match &procedure {
+        ProcedureDto::TOTP { options, .. } => Some(options.digits()),
+        _ => None,
+    };
+    let output: Vec<u8> = client
+        .execute_procedure(StrongholdProcedure::try_from(procedure)?)
+        .map(Into::into)?;
+    match totp_digits {
+        Some(digits) => Ok(hotp_code(&output, digits).into_bytes()),
+        None => Ok(output),
+    }
 }
 
-fn get_stronghold(
+/// Generates a TOTP code (RFC 6238) from the secret saved in the store of the client under `key`.
+///
+/// The vault procedures only have the SHA-2 HMACs, so the SHA-1 secrets of most authenticator apps are kept in the
+/// store, encrypted in the snapshot as well.
+#[tauri::command]
+async fn generate_totp<R: Runtime>(
+    window: Window<R>,
     collection: State<'_, StrongholdCollection>,
     snapshot_path: PathBuf,
-) -> Result<iota_stronghold::Stronghold> {
-    let collection = collection.0.lock().unwrap();
-    if let Some(stronghold) = collection.get(&snapshot_path) {
-        Ok(stronghold.inner().clone())
-    } else {
-        Err(Error::StrongholdNotInitialized)
-    }
+    client: BytesDto,
+    key: String,
+    options: TotpOptionsDto,
+) -> Result<String> {
+    let _turn = collection.enqueue(&window, &snapshot_path).await?;
+    let client = get_client(collection, snapshot_path, client)?;
+    let store = client.store();
+    stronghold::purge_expired(&store)?;
+    let mut secret = store
+        .get(key.as_ref())?
+        .ok_or_else(|| Error::RecordNotFound(key.clone()))?;
+    let mut mac = totp_hmac(options.hash_type, &secret, options.counter());
+    secret.zeroize();
+    let code = hotp_code(&mac, options.digits());
+    mac.zeroize();
+    Ok(code)
 }
 
 fn get_client(
@@ -386,8 +1084,9 @@ fn get_client(
     snapshot_path: PathBuf,
     client: BytesDto,
 ) -> Result<Client> {
-    let collection = collection.0.lock().unwrap();
-    if let Some(stronghold) = collection.get(&snapshot_path) {
+    let mut collection = collection.strongholds.lock().unwrap();
+    if let Some(stronghold) = collection.get_mut(&snapshot_path) {
+        stronghold.touch();
         stronghold.get_client(client).map_err(Into::into)
     } else {
         Err(Error::StrongholdNotInitialized)
@@ -396,28 +1095,174 @@ fn get_client(
 
 pub struct Builder {
     password_hash_function: Box<PasswordHashFn>,
+    idle_timeout: Option<Duration>,
+    access_policy: Option<Box<AccessPolicyFn>>,
+    user_presence: Option<Box<UserPresenceFn>>,
 }
 
 impl Builder {
     pub fn new<F: Fn(&str) -> Vec<u8> + Send + Sync + 'static>(password_hash_function: F) -> Self {
+        Self::with_hash(move |password| Ok(password_hash_function(password)))
+    }
+
+    /// Hashes the passwords with Argon2id, salted with the bytes of the file at `salt_path`, generated on the first
+    /// unlock.
+    ///
+    /// The salt is needed to unlock the snapshots again, so it must be kept with them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tauri_plugin_stronghold::{Argon2Params, Builder};
+    ///
+    /// let params = if cfg!(any(target_os = "android", target_os = "ios")) {
+    ///   Argon2Params::mobile()
+    /// } else {
+    ///   Argon2Params::default().memory(128 * 1024)
+    /// };
+    /// let builder = Builder::with_argon2("salt.bin", params);
+    /// ```
+    pub fn with_argon2<P: Into<PathBuf>>(salt_path: P, params: Argon2Params) -> Self {
+        let salt_path = salt_path.into();
+        Self::with_hash(move |password| {
+            let salt = kdf::salt(&salt_path)?;
+            Ok(kdf::argon2id(password.as_bytes(), &salt, params))
+        })
+    }
+
+    fn with_hash<F: Fn(&str) -> Result<Vec<u8>> + Send + Sync + 'static>(
+        password_hash_function: F,
+    ) -> Self {
         Self {
             password_hash_function: Box::new(password_hash_function),
+            idle_timeout: None,
+            access_policy: None,
+            user_presence: None,
         }
     }
 
+    /// Locks snapshots that haven't been accessed for the given duration.
+    ///
+    /// Locking saves the snapshot, drops its key from memory and emits the `stronghold://locked` event.
+    /// The snapshot must be initialized with its password again before it can be used.
+    ///
+    /// When the snapshot can't be saved, it stays unlocked and the `stronghold://lock-failed` event is emitted once,
+    /// locking it is only retried after it's accessed again.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout.replace(timeout);
+        self
+    }
+
+    /// Restricts which windows may access which snapshots.
+    ///
+    /// The policy is called with the window label and the snapshot path on every command,
+    /// commands for which it returns `false` fail without touching the snapshot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tauri_plugin_stronghold::Builder;
+    ///
+    /// let builder = Builder::new(|password| password.as_bytes().to_vec())
+    ///   .access_policy(|label, snapshot_path| label == "main" || snapshot_path.starts_with(label));
+    /// ```
+    pub fn access_policy<F: Fn(&str, &Path) -> bool + Send + Sync + 'static>(
+        mut self,
+        policy: F,
+    ) -> Self {
+        self.access_policy.replace(Box::new(policy));
+        self
+    }
+
+    /// Verifies the user's presence before releasing a snapshot key from the OS keychain, e.g. with Touch ID or
+    /// Windows Hello.
+    ///
+    /// The verifier is called with the snapshot path on every `unlock_with_keychain`, the unlock fails when it
+    /// returns `false`. Without it, only the keychain policy of the platform guards the key.
+    pub fn user_presence<F: Fn(&Path) -> bool + Send + Sync + 'static>(
+        mut self,
+        verify: F,
+    ) -> Self {
+        self.user_presence.replace(Box::new(verify));
+        self
+    }
+
     pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
         let password_hash_function = self.password_hash_function;
+        let idle_timeout = self.idle_timeout;
+        let access_policy = self.access_policy;
+        let user_presence = self.user_presence;
 
         PluginBuilder::new("stronghold")
             .setup(move |app| {
-                app.manage(StrongholdCollection::default());
+                let collection = StrongholdCollection {
+                    strongholds: Default::default(),
+                    access_policy,
+                    user_presence,
+                    queues: Default::default(),
+                };
+
+                if let Some(timeout) = idle_timeout {
+                    let strongholds = collection.strongholds.clone();
+                    let queues = collection.queues.clone();
+                    let app = app.clone();
+                    spawn(move || {
+                        // the snapshots that failed to lock and their last access, retried once accessed again
+                        let mut failed = HashMap::new();
+                        loop {
+                            sleep(timeout.min(Duration::from_secs(1)));
+                            let mut strongholds = strongholds.lock().unwrap();
+                            failed.retain(|path, last_access| {
+                                strongholds.get(path).is_some_and(|stronghold| {
+                                    stronghold.last_access() == *last_access
+                                })
+                            });
+                            let expired = strongholds
+                                .iter()
+                                // the snapshots with commands running or waiting aren't idle
+                                .filter(|(path, stronghold)| {
+                                    stronghold.idle_time() >= timeout
+                                        && queues.depth(path) == 0
+                                        && !failed.contains_key(*path)
+                                })
+                                .map(|(path, _)| path.clone())
+                                .collect::<Vec<_>>();
+                            for path in expired {
+                                if let Err(e) = lock(&app, &mut strongholds, &path) {
+                                    error!("failed to lock stronghold {:?}: {}", path, e);
+                                    let _ = app.emit_all(
+                                        "stronghold://lock-failed",
+                                        LockFailedPayload {
+                                            snapshot_path: path.clone(),
+                                            error: e.to_string(),
+                                        },
+                                    );
+                                    if let Some(stronghold) = strongholds.get(&path) {
+                                        failed.insert(path, stronghold.last_access());
+                                    }
+                                }
+                            }
+                        }
+                    });
+                }
+
+                app.manage(collection);
                 app.manage(PasswordHashFunction(password_hash_function));
                 Ok(())
             })
             .invoke_handler(tauri::generate_handler![
                 initialize,
+                lock_now,
+                list_snapshots,
+                close_snapshot,
+                queue_depth,
+                enable_keychain_unlock,
+                disable_keychain_unlock,
+                unlock_with_keychain,
                 destroy,
                 save,
+                change_password,
+                export_snapshot,
                 create_client,
                 load_client,
                 get_store_record,
@@ -426,7 +1271,41 @@ impl Builder {
                 save_secret,
                 remove_secret,
                 execute_procedure,
+                generate_totp,
             ])
             .build()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn totp_procedure_defaults_to_sha256() {
+        let client = Client::default();
+        client
+            .vault("vault")
+            .write_secret(
+                Location::generic("vault", "totp"),
+                b"12345678901234567890123456789012".to_vec(),
+            )
+            .unwrap();
+
+        // RFC 6238 test vector, without a `hashType`
+        let procedure: ProcedureDto = serde_json::from_value(serde_json::json!({
+            "type": "TOTP",
+            "payload": {
+                "key": { "type": "Generic", "payload": { "vault": "vault", "record": "totp" } },
+                "digits": 8,
+                "timestamp": 59,
+            },
+        }))
+        .unwrap();
+        let mac: Vec<u8> = client
+            .execute_procedure(StrongholdProcedure::try_from(procedure).unwrap())
+            .map(Into::into)
+            .unwrap();
+        assert_eq!(hotp_code(&mac, 8), "46119246");
+    }
+}
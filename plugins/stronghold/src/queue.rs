@@ -0,0 +1,111 @@
+//! Runs the commands on a snapshot one at a time, in the order they were invoked.
+//!
+//! Each command waits for its turn on a fair async lock of the snapshot, so concurrent invokes can't interleave
+//! their reads and writes of the snapshot and the waiting ones don't block the threads of the async runtime.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::OwnedMutexGuard;
+
+type NotifyFn = dyn Fn(usize) + Send + Sync;
+
+#[derive(Default)]
+struct Tickets {
+    depth: usize,
+    /// Whether a command had to wait since the queue was last empty.
+    contended: bool,
+}
+
+#[derive(Default)]
+struct Queue {
+    tickets: Mutex<Tickets>,
+    // tokio's mutex serves the waiters in the order they locked it
+    turn: Arc<tokio::sync::Mutex<()>>,
+}
+
+/// The queues of the snapshots with commands running or waiting.
+#[derive(Clone, Default)]
+pub(crate) struct Queues(Arc<Mutex<HashMap<PathBuf, Arc<Queue>>>>);
+
+impl Queues {
+    /// Waits for the commands invoked before on the snapshot, the next one runs once the returned turn is dropped.
+    ///
+    /// `notify` is called with the depth of the queue whenever it changes while commands are waiting, and with `0`
+    /// once they all ran.
+    pub(crate) async fn enqueue<F: Fn(usize) + Send + Sync + 'static>(
+        &self,
+        snapshot_path: &Path,
+        notify: F,
+    ) -> Turn {
+        // the ticket is taken with the queues locked, so the queue can't be removed before
+        let queue = {
+            let mut queues = self.0.lock().unwrap();
+            let queue = queues
+                .entry(snapshot_path.to_path_buf())
+                .or_default()
+                .clone();
+            let mut tickets = queue.tickets.lock().unwrap();
+            tickets.depth += 1;
+            if tickets.depth > 1 {
+                tickets.contended = true;
+                notify(tickets.depth);
+            }
+            drop(tickets);
+            queue
+        };
+
+        // the turn gives the ticket back on drop, even if the command is cancelled while waiting
+        let mut turn = Turn {
+            queues: self.clone(),
+            snapshot_path: snapshot_path.to_path_buf(),
+            queue,
+            notify: Box::new(notify),
+            guard: None,
+        };
+        turn.guard = Some(turn.queue.turn.clone().lock_owned().await);
+        turn
+    }
+
+    /// The number of commands running or waiting on the snapshot.
+    pub(crate) fn depth(&self, snapshot_path: &Path) -> usize {
+        self.0
+            .lock()
+            .unwrap()
+            .get(snapshot_path)
+            .map_or(0, |queue| queue.tickets.lock().unwrap().depth)
+    }
+}
+
+/// The turn of a command on its snapshot, passed to the next one on drop.
+pub(crate) struct Turn {
+    queues: Queues,
+    snapshot_path: PathBuf,
+    queue: Arc<Queue>,
+    notify: Box<NotifyFn>,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        let mut queues = self.queues.0.lock().unwrap();
+        let mut tickets = self.queue.tickets.lock().unwrap();
+        tickets.depth -= 1;
+        let depth = tickets.depth;
+        let contended = tickets.contended;
+        if depth == 0 {
+            tickets.contended = false;
+            queues.remove(&self.snapshot_path);
+        }
+        drop(tickets);
+        drop(queues);
+
+        self.guard.take();
+        if contended {
+            (self.notify)(depth);
+        }
+    }
+}
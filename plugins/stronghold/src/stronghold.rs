@@ -1,7 +1,16 @@
-use std::{convert::TryFrom, ops::Deref, path::Path};
+use std::{
+    collections::HashSet,
+    convert::TryFrom,
+    ffi::OsString,
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
-use iota_stronghold::{KeyProvider, SnapshotPath};
+use iota_stronghold::{Client, KeyProvider, SnapshotPath, Store};
 use serde::{Serialize, Serializer};
+use tauri_plugin_common::{error, PluginError};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -9,6 +18,29 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     #[error("stronghold not initialized")]
     StrongholdNotInitialized,
+    #[error("invalid password")]
+    InvalidPassword,
+    /// The salt file of [`Builder::with_argon2`](crate::Builder::with_argon2) is shorter than 8 bytes.
+    #[error("invalid password salt")]
+    InvalidSalt,
+    #[error("window `{0}` is not allowed to access this snapshot")]
+    AccessDenied(String),
+    #[error("path not allowed on the configured scope: {0}")]
+    PathForbidden(PathBuf),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("the `keychain` feature is not enabled")]
+    KeychainUnavailable,
+    #[error("invalid key stored in the OS keychain")]
+    InvalidKeychainEntry,
+    #[error("record `{0}` not found in the store")]
+    RecordNotFound(String),
+    /// The verifier of [`Builder::user_presence`](crate::Builder::user_presence) refused the unlock.
+    #[error("user presence verification failed")]
+    UserPresenceDenied,
+    #[cfg(feature = "keychain")]
+    #[error(transparent)]
+    Keychain(#[from] keyring::Error),
     #[error(transparent)]
     Stronghold(#[from] iota_stronghold::ClientError),
     #[error(transparent)]
@@ -22,7 +54,45 @@ impl Serialize for Error {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.to_string().as_str())
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "stronghold";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::StrongholdNotInitialized => "stronghold_not_initialized",
+            Self::InvalidPassword => "invalid_password",
+            Self::InvalidSalt => "invalid_salt",
+            Self::AccessDenied(..) => "access_denied",
+            Self::PathForbidden(..) => "path_forbidden",
+            Self::Io(..) => "io",
+            Self::KeychainUnavailable => "keychain_unavailable",
+            Self::InvalidKeychainEntry => "invalid_keychain_entry",
+            Self::RecordNotFound(..) => "record_not_found",
+            Self::UserPresenceDenied => "user_presence_denied",
+            #[cfg(feature = "keychain")]
+            Self::Keychain(..) => "keychain",
+            Self::Stronghold(..) => "stronghold",
+            Self::Memory(..) => "memory",
+            Self::Procedure(..) => "procedure",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
     }
 }
 
@@ -30,6 +100,9 @@ pub struct Stronghold {
     inner: iota_stronghold::Stronghold,
     path: SnapshotPath,
     keyprovider: KeyProvider,
+    last_access: Instant,
+    /// The clients created or loaded, whose stores are purged of their expired records on save.
+    clients: HashSet<Vec<u8>>,
 }
 
 impl Stronghold {
@@ -44,19 +117,112 @@ impl Stronghold {
             inner: stronghold,
             path,
             keyprovider,
+            last_access: Instant::now(),
+            clients: Default::default(),
         })
     }
 
     pub fn save(&self) -> Result<()> {
+        self.purge_expired()?;
         self.inner.commit(&self.path, &self.keyprovider)?;
         Ok(())
     }
 
+    /// Checks whether the given (hashed) password matches the one the snapshot was unlocked with.
+    pub fn verify_password(&self, password: &[u8]) -> Result<bool> {
+        let buffer = self.keyprovider.try_unlock()?;
+        let key = buffer.borrow();
+        Ok(key.deref() == password)
+    }
+
+    /// Re-encrypts the snapshot with the given (hashed) password.
+    ///
+    /// The snapshot is written to a temporary file next to the original one which is then moved in place,
+    /// so a failure leaves both the file on disk and the in-memory key untouched.
+    pub fn change_password(&mut self, password: Vec<u8>) -> Result<()> {
+        let keyprovider = KeyProvider::try_from(password)?;
+        self.write(self.path.as_path(), &keyprovider)?;
+        self.keyprovider = keyprovider;
+        Ok(())
+    }
+
+    /// Writes a copy of the snapshot at the path, encrypted with the given (hashed) password or the current one.
+    ///
+    /// The copy is swapped in place like the snapshot in [`Stronghold::change_password`].
+    pub fn export(&self, path: &Path, password: Option<Vec<u8>>) -> Result<()> {
+        match password {
+            Some(password) => self.write(path, &KeyProvider::try_from(password)?),
+            None => self.write(path, &self.keyprovider),
+        }
+    }
+
+    fn write(&self, path: &Path, keyprovider: &KeyProvider) -> Result<()> {
+        self.purge_expired()?;
+        let mut tmp_path = OsString::from(path);
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        if let Err(e) = self
+            .inner
+            .commit(&SnapshotPath::from_path(&tmp_path), keyprovider)
+        {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn create_client<P: AsRef<[u8]>>(&mut self, client_path: P) -> Result<Client> {
+        let client = self.inner.create_client(&client_path)?;
+        self.clients.insert(client_path.as_ref().to_vec());
+        Ok(client)
+    }
+
+    pub fn load_client<P: AsRef<[u8]>>(&mut self, client_path: P) -> Result<Client> {
+        let client = self.inner.load_client(&client_path)?;
+        self.clients.insert(client_path.as_ref().to_vec());
+        Ok(client)
+    }
+
+    /// Removes the expired records from the stores of the clients, so they aren't written to the snapshot.
+    fn purge_expired(&self) -> Result<()> {
+        for client_path in &self.clients {
+            purge_expired(&self.inner.get_client(client_path)?.store())?;
+        }
+        Ok(())
+    }
+
+    /// Records an access to the snapshot, resetting its idle time.
+    pub fn touch(&mut self) {
+        self.last_access = Instant::now();
+    }
+
+    /// Returns the time elapsed since the snapshot was last accessed.
+    pub fn idle_time(&self) -> Duration {
+        self.last_access.elapsed()
+    }
+
+    /// Returns when the snapshot was last accessed.
+    pub fn last_access(&self) -> Instant {
+        self.last_access
+    }
+
     pub fn inner(&self) -> &iota_stronghold::Stronghold {
         &self.inner
     }
 }
 
+/// Removes the records whose lifetime has elapsed from the store, which only hides them otherwise.
+pub fn purge_expired(store: &Store) -> Result<()> {
+    for key in store.keys()? {
+        if !store.contains_key(&key)? {
+            store.delete(&key)?;
+        }
+    }
+    Ok(())
+}
+
 impl Deref for Stronghold {
     type Target = iota_stronghold::Stronghold;
     fn deref(&self) -> &Self::Target {
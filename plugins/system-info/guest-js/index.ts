@@ -0,0 +1,123 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { appWindow } from "@tauri-apps/api/window";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface Process {
+  pid: number;
+  parent: number | null;
+  name: string;
+  exe: string | null;
+  cmd: string[];
+  /** e.g. `Runnable` or `Sleeping`. */
+  status: string;
+  /** In percent of a single CPU, it goes over 100 when the process uses several CPUs. */
+  cpuUsage: number;
+  /** The resident memory in bytes. */
+  memory: number;
+  /** In seconds since the Unix epoch. */
+  startTime: number;
+}
+
+/** In bytes. */
+export interface Memory {
+  total: number;
+  used: number;
+  available: number;
+  swapTotal: number;
+  swapUsed: number;
+}
+
+export interface Disk {
+  name: string;
+  mountPoint: string;
+  fileSystem: string;
+  type: "hdd" | "ssd" | "unknown";
+  /** In bytes. */
+  total: number;
+  available: number;
+  removable: boolean;
+}
+
+export interface SystemMetrics {
+  /** In percent, averaged over all CPUs. */
+  cpuUsage: number;
+  /** In percent, for every logical CPU. */
+  cpus: number[];
+  memory: Memory;
+  disks: Disk[];
+  /** In seconds. */
+  uptime: number;
+}
+
+/** The usage of the app and the processes it started, e.g. the webview and sidecars. */
+export interface AppMetrics {
+  /** In percent of a single CPU, summed over the processes. */
+  cpuUsage: number;
+  /** The resident memory in bytes, summed over the processes. */
+  memory: number;
+  /** The app process first. */
+  processes: Process[];
+}
+
+export interface Metrics {
+  system: SystemMetrics;
+  app: AppMetrics;
+}
+
+/**
+ * The CPU usages are averaged since the previous call, or since the app started.
+ */
+async function systemMetrics(): Promise<SystemMetrics> {
+  return await invoke("plugin:system-info|system_metrics");
+}
+
+async function appMetrics(): Promise<AppMetrics> {
+  return await invoke("plugin:system-info|app_metrics");
+}
+
+/**
+ * All the processes the user can see.
+ */
+async function processes(): Promise<Process[]> {
+  return await invoke("plugin:system-info|processes");
+}
+
+/**
+ * Kills a process started by the app.
+ *
+ * The process is asked to terminate, unless `force` is set or the platform can't, e.g. on Windows.
+ */
+async function kill(pid: number, force = false): Promise<void> {
+  return await invoke("plugin:system-info|kill", { pid, force });
+}
+
+/**
+ * Calls the handler with the metrics every `interval` milliseconds, until the returned function is called.
+ */
+async function subscribe(
+  handler: (metrics: Metrics) => void,
+  interval = 1000
+): Promise<() => Promise<void>> {
+  let id: number | undefined;
+  const unlisten = await appWindow.listen<Metrics & { id: number }>(
+    "system-info://metrics",
+    ({ payload }) => {
+      if (payload.id === id) handler(payload);
+    }
+  );
+
+  try {
+    id = await invoke<number>("plugin:system-info|subscribe", { interval });
+  } catch (e) {
+    unlisten();
+    throw e;
+  }
+
+  return async () => {
+    unlisten();
+    await invoke("plugin:system-info|unsubscribe", { id });
+  };
+}
+
+export { systemMetrics, appMetrics, processes, kill, subscribe };
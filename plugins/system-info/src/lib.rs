@@ -0,0 +1,423 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use serde::{ser::Serializer, Serialize};
+use sysinfo::{
+    CpuRefreshKind, DiskKind, Disks, Pid, ProcessRefreshKind, Signal, System, UpdateKind,
+    MINIMUM_CPU_UPDATE_INTERVAL,
+};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State, Window, WindowEvent,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::{sleep, spawn},
+    time::Duration,
+};
+
+type Id = u32;
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("process not found: {0}")]
+    ProcessNotFound(u32),
+    #[error("process {0} was not started by the app")]
+    NotChildProcess(u32),
+    #[error("failed to kill the process {0}")]
+    KillFailed(u32),
+    #[error("subscription not found for the given id: {0}")]
+    SubscriptionNotFound(Id),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "system-info";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::ProcessNotFound(..) => "process_not_found",
+            Self::NotChildProcess(..) => "not_child_process",
+            Self::KillFailed(..) => "kill_failed",
+            Self::SubscriptionNotFound(..) => "subscription_not_found",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Process {
+    pub pid: u32,
+    pub parent: Option<u32>,
+    pub name: String,
+    pub exe: Option<PathBuf>,
+    pub cmd: Vec<String>,
+    /// e.g. `Runnable` or `Sleeping`.
+    pub status: String,
+    /// In percent of a single CPU, it goes over 100 when the process uses several CPUs.
+    pub cpu_usage: f32,
+    /// The resident memory in bytes.
+    pub memory: u64,
+    /// In seconds since the Unix epoch.
+    pub start_time: u64,
+}
+
+impl From<&sysinfo::Process> for Process {
+    fn from(process: &sysinfo::Process) -> Self {
+        Self {
+            pid: process.pid().as_u32(),
+            parent: process.parent().map(Pid::as_u32),
+            name: process.name().to_string(),
+            exe: process.exe().map(Into::into),
+            cmd: process.cmd().to_vec(),
+            status: process.status().to_string(),
+            cpu_usage: process.cpu_usage(),
+            memory: process.memory(),
+            start_time: process.start_time(),
+        }
+    }
+}
+
+/// In bytes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Memory {
+    pub total: u64,
+    pub used: u64,
+    pub available: u64,
+    pub swap_total: u64,
+    pub swap_used: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiskType {
+    Hdd,
+    Ssd,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Disk {
+    pub name: String,
+    pub mount_point: PathBuf,
+    pub file_system: String,
+    #[serde(rename = "type")]
+    pub disk_type: DiskType,
+    /// In bytes.
+    pub total: u64,
+    pub available: u64,
+    pub removable: bool,
+}
+
+impl From<&sysinfo::Disk> for Disk {
+    fn from(disk: &sysinfo::Disk) -> Self {
+        Self {
+            name: disk.name().to_string_lossy().into_owned(),
+            mount_point: disk.mount_point().into(),
+            file_system: disk.file_system().to_string_lossy().into_owned(),
+            disk_type: match disk.kind() {
+                DiskKind::HDD => DiskType::Hdd,
+                DiskKind::SSD => DiskType::Ssd,
+                DiskKind::Unknown(_) => DiskType::Unknown,
+            },
+            total: disk.total_space(),
+            available: disk.available_space(),
+            removable: disk.is_removable(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemMetrics {
+    /// In percent, averaged over all CPUs.
+    pub cpu_usage: f32,
+    /// In percent, for every logical CPU.
+    pub cpus: Vec<f32>,
+    pub memory: Memory,
+    pub disks: Vec<Disk>,
+    /// In seconds.
+    pub uptime: u64,
+}
+
+/// The usage of the app and the processes it started, e.g. the webview and sidecars.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppMetrics {
+    /// In percent of a single CPU, summed over the processes.
+    pub cpu_usage: f32,
+    /// The resident memory in bytes, summed over the processes.
+    pub memory: u64,
+    /// The app process first.
+    pub processes: Vec<Process>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metrics {
+    pub system: SystemMetrics,
+    pub app: AppMetrics,
+}
+
+pub struct SystemInfo<R: Runtime> {
+    app: AppHandle<R>,
+    system: Mutex<System>,
+    disks: Mutex<Disks>,
+    next_id: AtomicU32,
+    subscriptions: Mutex<HashMap<Id, Arc<AtomicBool>>>,
+}
+
+/// What [`Process`] reads, the command lines don't change once started.
+fn process_refresh_kind() -> ProcessRefreshKind {
+    ProcessRefreshKind::new()
+        .with_cpu()
+        .with_memory()
+        .with_exe(UpdateKind::OnlyIfNotSet)
+        .with_cmd(UpdateKind::OnlyIfNotSet)
+}
+
+/// Whether `pid` is `ancestor` or one of its descendants.
+fn is_descendant(system: &System, pid: Pid, ancestor: Pid) -> bool {
+    let mut current = Some(pid);
+    // the parents can form a cycle while the pids are reused
+    for _ in 0..system.processes().len() {
+        match current {
+            Some(pid) if pid == ancestor => return true,
+            Some(pid) => current = system.process(pid).and_then(|p| p.parent()),
+            None => return false,
+        }
+    }
+    false
+}
+
+impl<R: Runtime> SystemInfo<R> {
+    /// The CPU usages are averaged since the previous call, or since the plugin was initialized.
+    pub fn system_metrics(&self) -> SystemMetrics {
+        let mut system = self.system.lock().unwrap();
+        system.refresh_cpu_usage();
+        system.refresh_memory();
+        let mut disks = self.disks.lock().unwrap();
+        // also lists the drives plugged in since the previous call
+        disks.refresh_list();
+
+        SystemMetrics {
+            cpu_usage: system.global_cpu_info().cpu_usage(),
+            cpus: system.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+            memory: Memory {
+                total: system.total_memory(),
+                used: system.used_memory(),
+                available: system.available_memory(),
+                swap_total: system.total_swap(),
+                swap_used: system.used_swap(),
+            },
+            disks: disks.list().iter().map(Into::into).collect(),
+            uptime: System::uptime(),
+        }
+    }
+
+    /// All the processes the user can see.
+    pub fn processes(&self) -> Vec<Process> {
+        let mut system = self.system.lock().unwrap();
+        system.refresh_processes_specifics(process_refresh_kind());
+        system.processes().values().map(Into::into).collect()
+    }
+
+    pub fn app_metrics(&self) -> AppMetrics {
+        let mut system = self.system.lock().unwrap();
+        system.refresh_processes_specifics(process_refresh_kind());
+
+        let app = Pid::from_u32(std::process::id());
+        let mut processes = system
+            .processes()
+            .values()
+            .filter(|process| is_descendant(&system, process.pid(), app))
+            .map(Process::from)
+            .collect::<Vec<_>>();
+        processes.sort_by_key(|process| (process.pid != app.as_u32(), process.pid));
+
+        AppMetrics {
+            cpu_usage: processes.iter().map(|process| process.cpu_usage).sum(),
+            memory: processes.iter().map(|process| process.memory).sum(),
+            processes,
+        }
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            system: self.system_metrics(),
+            app: self.app_metrics(),
+        }
+    }
+
+    /// Kills a process started by the app.
+    ///
+    /// The process is asked to terminate, unless `force` is set or the platform can't, e.g. on Windows.
+    pub fn kill(&self, pid: u32, force: bool) -> Result<()> {
+        let mut system = self.system.lock().unwrap();
+        system.refresh_processes_specifics(ProcessRefreshKind::new());
+
+        let app = Pid::from_u32(std::process::id());
+        let pid_ = Pid::from_u32(pid);
+        let process = system.process(pid_).ok_or(Error::ProcessNotFound(pid))?;
+        if pid_ == app || !is_descendant(&system, pid_, app) {
+            return Err(Error::NotChildProcess(pid));
+        }
+
+        let killed = if force {
+            process.kill()
+        } else {
+            process
+                .kill_with(Signal::Term)
+                .unwrap_or_else(|| process.kill())
+        };
+        if killed {
+            Ok(())
+        } else {
+            Err(Error::KillFailed(pid))
+        }
+    }
+
+    /// Calls `on_metrics` from a background thread every `interval` until [`Self::unsubscribe`] is called.
+    pub fn subscribe<F: FnMut(Id, Metrics) + Send + 'static>(
+        &self,
+        interval: Duration,
+        mut on_metrics: F,
+    ) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let stop = Arc::new(AtomicBool::new(false));
+        self.subscriptions.lock().unwrap().insert(id, stop.clone());
+
+        // the CPU usage is meaningless over shorter periods
+        let interval = interval.max(MINIMUM_CPU_UPDATE_INTERVAL);
+        let app = self.app.clone();
+        spawn(move || loop {
+            sleep(interval);
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            on_metrics(id, app.system_info().metrics());
+        });
+        id
+    }
+
+    pub fn unsubscribe(&self, id: Id) -> Result<()> {
+        let stop = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or(Error::SubscriptionNotFound(id))?;
+        stop.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn system_info(&self) -> State<'_, SystemInfo<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn system_info(&self) -> State<'_, SystemInfo<R>> {
+        self.state::<SystemInfo<R>>()
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct MetricsPayload {
+    id: Id,
+    #[serde(flatten)]
+    metrics: Metrics,
+}
+
+#[command]
+async fn system_metrics<R: Runtime>(app: AppHandle<R>) -> Result<SystemMetrics> {
+    Ok(app.system_info().system_metrics())
+}
+
+#[command]
+async fn app_metrics<R: Runtime>(app: AppHandle<R>) -> Result<AppMetrics> {
+    Ok(app.system_info().app_metrics())
+}
+
+#[command]
+async fn processes<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Process>> {
+    Ok(app.system_info().processes())
+}
+
+#[command]
+async fn kill<R: Runtime>(app: AppHandle<R>, pid: u32, force: Option<bool>) -> Result<()> {
+    app.system_info().kill(pid, force.unwrap_or_default())
+}
+
+#[command]
+async fn subscribe<R: Runtime>(window: Window<R>, interval: Option<u64>) -> Result<Id> {
+    let window_ = window.clone();
+    let id = window.system_info().subscribe(
+        Duration::from_millis(interval.unwrap_or(1000)),
+        move |id, metrics| {
+            let _ = window_.emit("system-info://metrics", MetricsPayload { id, metrics });
+        },
+    );
+
+    // the page can't unsubscribe once its window is closed
+    let app = window.app_handle();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            let _ = app.system_info().unsubscribe(id);
+        }
+    });
+    Ok(id)
+}
+
+#[command]
+async fn unsubscribe<R: Runtime>(app: AppHandle<R>, id: Id) -> Result<()> {
+    app.system_info().unsubscribe(id)
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    PluginBuilder::new("system-info")
+        .invoke_handler(tauri::generate_handler![
+            system_metrics,
+            app_metrics,
+            processes,
+            kill,
+            subscribe,
+            unsubscribe
+        ])
+        .setup(|app| {
+            // the first CPU usages are measured from here
+            let mut system = System::new();
+            system.refresh_cpu_specifics(CpuRefreshKind::new().with_cpu_usage());
+            system.refresh_processes_specifics(ProcessRefreshKind::new().with_cpu());
+
+            app.manage(SystemInfo {
+                app: app.clone(),
+                system: Mutex::new(system),
+                disks: Mutex::new(Disks::new()),
+                next_id: Default::default(),
+                subscriptions: Default::default(),
+            });
+            Ok(())
+        })
+        .build()
+}
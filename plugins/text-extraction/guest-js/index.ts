@@ -0,0 +1,118 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { appWindow } from "@tauri-apps/api/window";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+/** From the top left corner, in pixels for the images and in points for the PDFs. */
+export interface BoundingBox {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+export interface Word {
+  text: string;
+  bbox: BoundingBox;
+  /** From 0 to 100, `null` for the text layer of the PDFs. */
+  confidence: number | null;
+}
+
+export interface Line {
+  text: string;
+  bbox: BoundingBox;
+  words: Word[];
+}
+
+export interface Block {
+  text: string;
+  bbox: BoundingBox;
+  lines: Line[];
+}
+
+export interface Page {
+  /** Starts at 1. */
+  number: number;
+  width: number;
+  height: number;
+  text: string;
+  /** Whether the text was recognized from the pixels of the page. */
+  ocr: boolean;
+  blocks: Block[];
+}
+
+export interface Document {
+  /** The pages separated by form feeds. */
+  text: string;
+  pages: Page[];
+}
+
+export interface ExtractOptions {
+  /** The tesseract languages of the text, e.g. `eng` or `deu`. */
+  languages?: string[];
+  /**
+   * When the PDF pages are recognized, defaults to `auto` which recognizes
+   * the pages without text, e.g. the scanned ones.
+   */
+  pdfOcr?: "never" | "auto" | "always";
+  /** The resolution the PDF pages are rendered at for the OCR, defaults to 300. */
+  dpi?: number;
+}
+
+interface ProgressPayload {
+  id: number;
+  page: number;
+  pages: number;
+}
+
+type ProgressHandler = (page: number, pages: number) => void;
+const handlers: Map<number, ProgressHandler> = new Map();
+let listening = false;
+
+async function listenToProgressEventIfNeeded(): Promise<void> {
+  if (listening) {
+    return await Promise.resolve();
+  }
+  return await appWindow
+    .listen<ProgressPayload>("text-extraction://progress", ({ payload }) => {
+      const handler = handlers.get(payload.id);
+      if (handler != null) {
+        handler(payload.page, payload.pages);
+      }
+    })
+    .then(() => {
+      listening = true;
+    });
+}
+
+/**
+ * Reads the text of a PDF, recognizing the pages without text, or recognizes
+ * the text of an image.
+ */
+async function extract(
+  path: string,
+  options?: ExtractOptions,
+  progressHandler?: ProgressHandler
+): Promise<Document> {
+  const ids = new Uint32Array(1);
+  window.crypto.getRandomValues(ids);
+  const id = ids[0];
+
+  if (progressHandler != null) {
+    handlers.set(id, progressHandler);
+  }
+
+  await listenToProgressEventIfNeeded();
+
+  try {
+    return await invoke("plugin:text-extraction|extract", {
+      id,
+      path,
+      options,
+    });
+  } finally {
+    handlers.delete(id);
+  }
+}
+
+export { extract };
@@ -0,0 +1,486 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The text of images and PDFs with its layout, recognized with tesseract and read with poppler.
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    Manager, Runtime, State, Window,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+mod poppler;
+mod tesseract;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// The resolution the pages without text are rendered at for the OCR, tesseract works best from 300 DPI.
+const DEFAULT_DPI: u32 = 300;
+const POINTS_PER_INCH: f64 = 72.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("{0} was not found, install it or configure its path")]
+    ToolNotFound(&'static str),
+    #[error("{0} failed: {1}")]
+    Tool(&'static str, String),
+    #[error("path not allowed on the configured scope: {0}")]
+    PathForbidden(PathBuf),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "text-extraction";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::ToolNotFound(..) => "tool_not_found",
+            Self::Tool(..) => "tool",
+            Self::PathForbidden(..) => "path_forbidden",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+/// From the top left corner of the page, in pixels for the images and in points for the PDFs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoundingBox {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl BoundingBox {
+    fn scale(&mut self, factor: f64) {
+        self.x *= factor;
+        self.y *= factor;
+        self.width *= factor;
+        self.height *= factor;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Word {
+    pub text: String,
+    pub bbox: BoundingBox,
+    /// From 0 to 100, `None` for the text layer of the PDFs.
+    pub confidence: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Line {
+    /// The words separated by spaces.
+    pub text: String,
+    pub bbox: BoundingBox,
+    pub words: Vec<Word>,
+}
+
+/// A paragraph or a column of text.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Block {
+    /// The lines separated by line breaks.
+    pub text: String,
+    pub bbox: BoundingBox,
+    pub lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page {
+    /// Starts at 1.
+    pub number: u32,
+    pub width: f64,
+    pub height: f64,
+    /// The blocks separated by empty lines.
+    pub text: String,
+    /// Whether the text was recognized from the pixels of the page.
+    pub ocr: bool,
+    pub blocks: Vec<Block>,
+}
+
+impl Page {
+    /// Drops the empty lines and blocks and joins the texts.
+    fn finish(&mut self) {
+        for block in &mut self.blocks {
+            block.lines.retain(|line| !line.words.is_empty());
+            for line in &mut block.lines {
+                line.text = line
+                    .words
+                    .iter()
+                    .map(|word| word.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+            }
+            block.text = block
+                .lines
+                .iter()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        self.blocks.retain(|block| !block.lines.is_empty());
+        self.text = self
+            .blocks
+            .iter()
+            .map(|block| block.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    }
+
+    fn scale(&mut self, factor: f64) {
+        self.width *= factor;
+        self.height *= factor;
+        for block in &mut self.blocks {
+            block.bbox.scale(factor);
+            for line in &mut block.lines {
+                line.bbox.scale(factor);
+                for word in &mut line.words {
+                    word.bbox.scale(factor);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    /// The pages separated by form feeds.
+    pub text: String,
+    pub pages: Vec<Page>,
+}
+
+impl From<Vec<Page>> for Document {
+    fn from(pages: Vec<Page>) -> Self {
+        Self {
+            text: pages
+                .iter()
+                .map(|page| page.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\u{c}"),
+            pages,
+        }
+    }
+}
+
+/// When the pages of the PDFs are recognized with OCR.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PdfOcr {
+    /// Only reads the text layer.
+    Never,
+    /// Recognizes the pages without text, e.g. the scanned ones.
+    #[default]
+    Auto,
+    /// Recognizes every page, e.g. when the text layer is wrong.
+    Always,
+}
+
+fn default_dpi() -> u32 {
+    DEFAULT_DPI
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractOptions {
+    /// The tesseract languages of the text, e.g. `eng` or `deu`, defaults to English.
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub pdf_ocr: PdfOcr,
+    /// The resolution the PDF pages are rendered at for the OCR, defaults to 300.
+    #[serde(default = "default_dpi")]
+    pub dpi: u32,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            languages: Vec::new(),
+            pdf_ocr: Default::default(),
+            dpi: default_dpi(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    /// The number of the page that was just processed.
+    pub page: u32,
+    pub pages: u32,
+}
+
+pub(crate) struct Tools {
+    tesseract: PathBuf,
+    poppler: Option<PathBuf>,
+}
+
+impl Tools {
+    pub(crate) fn command(&self, name: &str) -> Command {
+        match name {
+            "tesseract" => Command::new(&self.tesseract),
+            _ => Command::new(
+                self.poppler
+                    .as_ref()
+                    .map_or_else(|| PathBuf::from(name), |dir| dir.join(name)),
+            ),
+        }
+    }
+}
+
+/// Runs the tool to completion and returns its output.
+pub(crate) fn run(name: &'static str, mut command: Command) -> Result<String> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // CREATE_NO_WINDOW, the console would flash otherwise
+        command.creation_flags(0x08000000);
+    }
+    let output = command.output().map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            Error::ToolNotFound(name)
+        } else {
+            Error::Io(e)
+        }
+    })?;
+    if !output.status.success() {
+        let log = String::from_utf8_lossy(&output.stderr);
+        let message = log
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("unknown error");
+        return Err(Error::Tool(name, message.trim().into()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub struct TextExtraction {
+    tools: Tools,
+    next_id: AtomicU32,
+}
+
+impl TextExtraction {
+    /// Recognizes the text of an image, e.g. a PNG, JPEG or TIFF file.
+    pub fn recognize(&self, image: &Path, options: &ExtractOptions) -> Result<Document> {
+        let mut page = tesseract::recognize(&self.tools, image, &options.languages, 1)?;
+        page.ocr = true;
+        Ok(vec![page].into())
+    }
+
+    /// Reads the text of every page of a PDF, `on_progress` is called after each page.
+    pub fn extract_pdf<F: FnMut(Progress)>(
+        &self,
+        pdf: &Path,
+        options: &ExtractOptions,
+        mut on_progress: F,
+    ) -> Result<Document> {
+        let pages = poppler::page_count(&self.tools, pdf)?;
+        // the rendered pages are removed once recognized
+        let temp = std::env::temp_dir().join(format!(
+            "tauri-plugin-text-extraction-{}-{}",
+            std::process::id(),
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = (1..=pages)
+            .map(|number| {
+                let page = self.extract_pdf_page(pdf, number, options, &temp)?;
+                on_progress(Progress {
+                    page: number,
+                    pages,
+                });
+                Ok(page)
+            })
+            .collect::<Result<Vec<_>>>();
+        let _ = fs::remove_dir_all(&temp);
+        result.map(Into::into)
+    }
+
+    fn extract_pdf_page(
+        &self,
+        pdf: &Path,
+        number: u32,
+        options: &ExtractOptions,
+        temp: &Path,
+    ) -> Result<Page> {
+        let page = poppler::extract_page(&self.tools, pdf, number)?;
+        let ocr = match options.pdf_ocr {
+            PdfOcr::Never => false,
+            PdfOcr::Auto => page.blocks.is_empty(),
+            PdfOcr::Always => true,
+        };
+        if !ocr {
+            return Ok(page);
+        }
+
+        fs::create_dir_all(temp)?;
+        let image = poppler::render_page(
+            &self.tools,
+            pdf,
+            number,
+            options.dpi,
+            &temp.join(format!("page-{}", number)),
+        )?;
+        let mut recognized = tesseract::recognize(&self.tools, &image, &options.languages, number)?;
+        let _ = fs::remove_file(image);
+        // the pixels of the rendered page to the points of the text layer
+        recognized.scale(POINTS_PER_INCH / options.dpi as f64);
+        recognized.ocr = true;
+        Ok(recognized)
+    }
+
+    /// Reads the PDFs and recognizes the other files as images.
+    pub fn extract<F: FnMut(Progress)>(
+        &self,
+        path: &Path,
+        options: &ExtractOptions,
+        mut on_progress: F,
+    ) -> Result<Document> {
+        let is_pdf = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+        if is_pdf {
+            self.extract_pdf(path, options, on_progress)
+        } else {
+            let document = self.recognize(path, options)?;
+            on_progress(Progress { page: 1, pages: 1 });
+            Ok(document)
+        }
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn text_extraction(&self) -> State<'_, TextExtraction>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn text_extraction(&self) -> State<'_, TextExtraction> {
+        self.state::<TextExtraction>()
+    }
+}
+
+fn check_scope<R: Runtime>(window: &Window<R>, path: &Path) -> Result<()> {
+    if window.fs_scope().is_allowed(path) {
+        Ok(())
+    } else {
+        Err(Error::PathForbidden(path.to_path_buf()))
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ProgressPayload {
+    id: u32,
+    #[serde(flatten)]
+    progress: Progress,
+}
+
+/// `id` identifies the progress events of the extraction, it is chosen by the caller.
+#[command]
+async fn extract<R: Runtime>(
+    window: Window<R>,
+    id: u32,
+    path: PathBuf,
+    options: Option<ExtractOptions>,
+) -> Result<Document> {
+    check_scope(&window, &path)?;
+    // the tools take seconds per page
+    tauri::async_runtime::spawn_blocking(move || {
+        window
+            .text_extraction()
+            .extract(&path, &options.unwrap_or_default(), |progress| {
+                let _ = window.emit(
+                    "text-extraction://progress",
+                    ProgressPayload { id, progress },
+                );
+            })
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+#[derive(Default)]
+pub struct Builder {
+    tesseract: Option<PathBuf>,
+    poppler: Option<PathBuf>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The path of the tesseract executable, defaults to looking it up in the `PATH`.
+    pub fn tesseract<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.tesseract.replace(path.into());
+        self
+    }
+
+    /// The directory of the poppler executables, `pdfinfo`, `pdftotext` and `pdftoppm`,
+    /// defaults to looking them up in the `PATH`.
+    pub fn poppler<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.poppler.replace(dir.into());
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let tools = Tools {
+            tesseract: self.tesseract.unwrap_or_else(|| "tesseract".into()),
+            poppler: self.poppler,
+        };
+
+        PluginBuilder::new("text-extraction")
+            .invoke_handler(tauri::generate_handler![extract])
+            .setup(move |app| {
+                app.manage(TextExtraction {
+                    tools,
+                    next_id: Default::default(),
+                });
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
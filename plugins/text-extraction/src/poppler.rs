@@ -0,0 +1,145 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Reads the text layer of the PDFs and renders their pages with the poppler command line tools.
+
+use std::path::{Path, PathBuf};
+
+use crate::{run, Block, BoundingBox, Error, Line, Page, Result, Tools, Word};
+
+pub(crate) fn page_count(tools: &Tools, pdf: &Path) -> Result<u32> {
+    let mut command = tools.command("pdfinfo");
+    command.arg(pdf);
+    let info = run("pdfinfo", command)?;
+    info.lines()
+        .find_map(|line| line.strip_prefix("Pages:"))
+        .and_then(|pages| pages.trim().parse().ok())
+        .ok_or_else(|| Error::Tool("pdfinfo", "the page count is missing".into()))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn attribute(tag: &str, name: &str) -> f64 {
+    let pattern = format!("{}=\"", name);
+    tag.find(&pattern)
+        .map(|start| &tag[start + pattern.len()..])
+        .and_then(|value| value.split('"').next())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default()
+}
+
+fn bbox(tag: &str) -> BoundingBox {
+    let [x_min, y_min, x_max, y_max] =
+        ["xMin", "yMin", "xMax", "yMax"].map(|name| attribute(tag, name));
+    BoundingBox {
+        x: x_min,
+        y: y_min,
+        width: x_max - x_min,
+        height: y_max - y_min,
+    }
+}
+
+/// `pdftotext -bbox-layout` writes every element on its own line:
+///
+/// ```text
+/// <page width="612.000000" height="792.000000">
+///   <flow>
+///     <block xMin="72.000000" yMin="71.731000" xMax="176.740000" yMax="83.685000">
+///       <line xMin="72.000000" yMin="71.731000" xMax="176.740000" yMax="83.685000">
+///         <word xMin="72.000000" yMin="71.731000" xMax="101.880000" yMax="83.685000">Hello</word>
+/// ```
+fn parse(html: &str, number: u32) -> Page {
+    let mut page = Page {
+        number,
+        width: 0.0,
+        height: 0.0,
+        text: String::new(),
+        ocr: false,
+        blocks: Vec::new(),
+    };
+    for line in html.lines().map(str::trim) {
+        if line.starts_with("<page ") {
+            page.width = attribute(line, "width");
+            page.height = attribute(line, "height");
+        } else if line.starts_with("<block ") {
+            page.blocks.push(Block {
+                text: String::new(),
+                bbox: bbox(line),
+                lines: Vec::new(),
+            });
+        } else if line.starts_with("<line ") {
+            if let Some(block) = page.blocks.last_mut() {
+                block.lines.push(Line {
+                    text: String::new(),
+                    bbox: bbox(line),
+                    words: Vec::new(),
+                });
+            }
+        } else if let Some((tag, text)) = line
+            .strip_prefix("<word ")
+            .and_then(|word| word.strip_suffix("</word>"))
+            .and_then(|word| word.split_once('>'))
+        {
+            if let Some(line) = page
+                .blocks
+                .last_mut()
+                .and_then(|block| block.lines.last_mut())
+            {
+                line.words.push(Word {
+                    text: unescape(text),
+                    bbox: bbox(tag),
+                    confidence: None,
+                });
+            }
+        }
+    }
+    page.finish();
+    page
+}
+
+/// The bounding boxes are in points.
+pub(crate) fn extract_page(tools: &Tools, pdf: &Path, number: u32) -> Result<Page> {
+    let number_ = number.to_string();
+    let mut command = tools.command("pdftotext");
+    command
+        .args([
+            "-f",
+            &number_,
+            "-l",
+            &number_,
+            "-bbox-layout",
+            "-enc",
+            "UTF-8",
+        ])
+        .arg(pdf)
+        .arg("-");
+    let html = run("pdftotext", command)?;
+    Ok(parse(&html, number))
+}
+
+/// Renders the page to `{prefix}.png`.
+pub(crate) fn render_page(
+    tools: &Tools,
+    pdf: &Path,
+    number: u32,
+    dpi: u32,
+    prefix: &Path,
+) -> Result<PathBuf> {
+    let number_ = number.to_string();
+    let mut command = tools.command("pdftoppm");
+    command
+        .args(["-f", &number_, "-l", &number_, "-r", &dpi.to_string()])
+        .args(["-png", "-singlefile"])
+        .arg(pdf)
+        .arg(prefix);
+    run("pdftoppm", command)?;
+    Ok(prefix.with_extension("png"))
+}
@@ -0,0 +1,102 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Recognizes the text of images with the tesseract command line.
+
+use std::path::Path;
+
+use crate::{run, Block, BoundingBox, Line, Page, Result, Tools, Word};
+
+/// The levels of the rows of the TSV output.
+const LEVEL_PAGE: u8 = 1;
+const LEVEL_BLOCK: u8 = 2;
+const LEVEL_LINE: u8 = 4;
+const LEVEL_WORD: u8 = 5;
+
+/// The columns are `level page_num block_num par_num line_num word_num left top width height conf text`,
+/// the rows of a level are followed by the rows of the levels they contain.
+fn parse(tsv: &str, number: u32) -> Page {
+    let mut page = Page {
+        number,
+        width: 0.0,
+        height: 0.0,
+        text: String::new(),
+        ocr: false,
+        blocks: Vec::new(),
+    };
+    for row in tsv.lines().skip(1) {
+        let columns = row.splitn(12, '\t').collect::<Vec<_>>();
+        if columns.len() < 11 {
+            continue;
+        }
+        let Ok(level) = columns[0].parse::<u8>() else {
+            continue;
+        };
+        let [left, top, width, height] =
+            [6, 7, 8, 9].map(|i| columns[i].parse::<f64>().unwrap_or_default());
+        let bbox = BoundingBox {
+            x: left,
+            y: top,
+            width,
+            height,
+        };
+        match level {
+            LEVEL_PAGE => {
+                page.width = width;
+                page.height = height;
+            }
+            LEVEL_BLOCK => page.blocks.push(Block {
+                text: String::new(),
+                bbox,
+                lines: Vec::new(),
+            }),
+            LEVEL_LINE => {
+                if let Some(block) = page.blocks.last_mut() {
+                    block.lines.push(Line {
+                        text: String::new(),
+                        bbox,
+                        words: Vec::new(),
+                    });
+                }
+            }
+            LEVEL_WORD => {
+                let text = columns.get(11).map_or("", |text| text.trim());
+                if text.is_empty() {
+                    continue;
+                }
+                if let Some(line) = page
+                    .blocks
+                    .last_mut()
+                    .and_then(|block| block.lines.last_mut())
+                {
+                    line.words.push(Word {
+                        text: text.into(),
+                        bbox,
+                        confidence: columns[10].parse().ok().filter(|conf| *conf >= 0.0),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    page.finish();
+    page
+}
+
+/// The bounding boxes are in pixels.
+pub(crate) fn recognize(
+    tools: &Tools,
+    image: &Path,
+    languages: &[String],
+    number: u32,
+) -> Result<Page> {
+    let mut command = tools.command("tesseract");
+    command.arg(image).arg("stdout");
+    if !languages.is_empty() {
+        command.args(["-l", &languages.join("+")]);
+    }
+    command.arg("tsv");
+    let tsv = run("tesseract", command)?;
+    Ok(parse(&tsv, number))
+}
@@ -0,0 +1,30 @@
+import { invoke, convertFileSrc } from "@tauri-apps/api/tauri";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+/**
+ * The URL of the icon the file manager shows for the file, at most `size` pixels per side.
+ */
+async function icon(path: string, size: number): Promise<string> {
+  return convertFileSrc(await invoke("plugin:thumbnail|icon", { path, size }));
+}
+
+/**
+ * The URL of a preview of an image, a video or a document, at most `size` pixels per side.
+ *
+ * Rejects when the OS can't generate one for the file, fall back to `icon`.
+ */
+async function thumbnail(path: string, size: number): Promise<string> {
+  return convertFileSrc(
+    await invoke("plugin:thumbnail|thumbnail", { path, size })
+  );
+}
+
+/**
+ * Deletes the cached images.
+ */
+async function clearCache(): Promise<void> {
+  return await invoke("plugin:thumbnail|clear_cache");
+}
+
+export { icon, thumbnail, clearCache };
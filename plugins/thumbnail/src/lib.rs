@@ -0,0 +1,271 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! File icons and thumbnails from the OS, cached as PNG files the webview loads through the asset protocol.
+
+use serde::{ser::Serializer, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, executor::Priority, ExecutorExt, PluginError};
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::UNIX_EPOCH,
+};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+type Result<T> = std::result::Result<T, Error>;
+
+const DIRECTORY: &str = "thumbnails";
+const MAX_SIZE: u32 = 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("the size must be between 1 and 1024, got {0}")]
+    InvalidSize(u32),
+    #[error("the file doesn't exist: {0}")]
+    FileNotFound(PathBuf),
+    #[error("no thumbnail can be generated for `{0}`")]
+    Unsupported(String),
+    #[error("failed to generate the image: {0}")]
+    Generate(String),
+    #[error("no cache directory, set one with `Builder::cache_dir`")]
+    NoCacheDir,
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "thumbnail";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Tauri(..) => "tauri",
+            Self::Io(..) => "io",
+            Self::InvalidSize(..) => "invalid_size",
+            Self::FileNotFound(..) => "file_not_found",
+            Self::Unsupported(..) => "unsupported",
+            Self::Generate(..) => "generate",
+            Self::NoCacheDir => "no_cache_dir",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+enum Kind {
+    Icon,
+    Thumbnail,
+}
+
+pub struct Thumbnails<R: Runtime> {
+    app: AppHandle<R>,
+    cache_dir: Option<PathBuf>,
+}
+
+impl<R: Runtime> Thumbnails<R> {
+    /// The icon the file manager shows for the file, as a PNG of at most `size` pixels per side.
+    pub fn icon<P: AsRef<Path>>(&self, path: P, size: u32) -> Result<PathBuf> {
+        self.cached(Kind::Icon, path.as_ref(), size)
+    }
+
+    /// A preview of the content of an image, a video or a document, as a PNG of at most `size` pixels per side.
+    ///
+    /// Errors with [`Error::Unsupported`] when the OS has no thumbnailer for the file, fall back to [`Self::icon`].
+    pub fn thumbnail<P: AsRef<Path>>(&self, path: P, size: u32) -> Result<PathBuf> {
+        self.cached(Kind::Thumbnail, path.as_ref(), size)
+    }
+
+    pub fn clear_cache(&self) -> Result<()> {
+        let cache_dir = self.cache_dir.as_ref().ok_or(Error::NoCacheDir)?;
+        match fs::remove_dir_all(cache_dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The images are keyed by the path, the size and the modification time of the file, so edited files get new ones.
+    fn cached(&self, kind: Kind, path: &Path, size: u32) -> Result<PathBuf> {
+        if size == 0 || size > MAX_SIZE {
+            return Err(Error::InvalidSize(size));
+        }
+        let cache_dir = self.cache_dir.as_ref().ok_or(Error::NoCacheDir)?;
+        let path = path
+            .canonicalize()
+            .map_err(|_| Error::FileNotFound(path.into()))?;
+        let metadata = fs::metadata(&path)?;
+
+        let mut hasher = DefaultHasher::new();
+        kind.hash(&mut hasher);
+        path.hash(&mut hasher);
+        size.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .hash(&mut hasher);
+        let image = cache_dir.join(format!("{:016x}.png", hasher.finish()));
+        if image.is_file() {
+            return Ok(image);
+        }
+
+        // written next to the image and renamed, concurrent requests for the same image don't see partial files.
+        // some thumbnailers pick the format from the extension
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        fs::create_dir_all(cache_dir)?;
+        let partial = cache_dir.join(format!(
+            "{}-{}.partial.png",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let generated = match kind {
+            Kind::Icon => platform::icon(&self.app, &path, size, &partial),
+            Kind::Thumbnail => platform::thumbnail(&path, size, &partial),
+        };
+        match generated.and_then(|()| fs::rename(&partial, &image).map_err(Into::into)) {
+            Ok(()) => Ok(image),
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                Err(e)
+            }
+        }
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn thumbnails(&self) -> State<'_, Thumbnails<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn thumbnails(&self) -> State<'_, Thumbnails<R>> {
+        self.state::<Thumbnails<R>>()
+    }
+}
+
+/// Runs the work on the executor shared by the plugins.
+async fn blocking<R: Runtime, T: Send + 'static>(
+    app: &AppHandle<R>,
+    priority: Priority,
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    app.executor()
+        .spawn(priority, move |_| f())
+        .join()
+        .await
+        .map_err(|e| Error::Generate(e.to_string()))?
+}
+
+#[command]
+async fn icon<R: Runtime>(app: AppHandle<R>, path: PathBuf, size: u32) -> Result<PathBuf> {
+    let app_ = app.clone();
+    blocking(&app, Priority::High, move || {
+        app_.thumbnails().icon(path, size)
+    })
+    .await
+}
+
+#[command]
+async fn thumbnail<R: Runtime>(app: AppHandle<R>, path: PathBuf, size: u32) -> Result<PathBuf> {
+    let app_ = app.clone();
+    blocking(&app, Priority::Normal, move || {
+        app_.thumbnails().thumbnail(path, size)
+    })
+    .await
+}
+
+#[command]
+async fn clear_cache<R: Runtime>(app: AppHandle<R>) -> Result<()> {
+    let app_ = app.clone();
+    blocking(&app, Priority::Low, move || app_.thumbnails().clear_cache()).await
+}
+
+#[derive(Default)]
+pub struct Builder {
+    cache_dir: Option<PathBuf>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where the images are cached, defaults to the `thumbnails` directory in the app cache directory.
+    pub fn cache_dir<P: Into<PathBuf>>(mut self, cache_dir: P) -> Self {
+        self.cache_dir.replace(cache_dir.into());
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let cache_dir = self.cache_dir;
+
+        PluginBuilder::new("thumbnail")
+            .invoke_handler(tauri::generate_handler![icon, thumbnail, clear_cache])
+            .setup(move |app| {
+                let cache_dir = cache_dir.or_else(|| {
+                    app.path_resolver()
+                        .app_cache_dir()
+                        .map(|dir| dir.join(DIRECTORY))
+                });
+                // the webview loads the images with `convertFileSrc`
+                if let Some(cache_dir) = &cache_dir {
+                    app.asset_protocol_scope()
+                        .allow_directory(cache_dir, false)?;
+                }
+                app.manage(Thumbnails {
+                    app: app.clone(),
+                    cache_dir,
+                });
+                Ok(())
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::default().build()
+}
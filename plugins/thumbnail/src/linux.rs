@@ -0,0 +1,187 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The icons come from the icon theme, the thumbnails from gdk-pixbuf for images and from the
+//! thumbnailers of the freedesktop specification for the other files.
+
+use gtk::{
+    gdk_pixbuf::Pixbuf,
+    gio::{self, prelude::*},
+    glib,
+    prelude::*,
+    IconLookupFlags, IconTheme,
+};
+use tauri::{AppHandle, Runtime};
+
+use std::{
+    ffi::{OsStr, OsString},
+    path::Path,
+    process::Command,
+    sync::mpsc::channel,
+};
+
+use crate::{Error, Result};
+
+const THUMBNAILER_GROUP: &str = "Thumbnailer Entry";
+
+fn generate_error(error: glib::Error) -> Error {
+    Error::Generate(error.to_string())
+}
+
+fn query(path: &Path) -> Result<gio::FileInfo> {
+    gio::File::for_path(path)
+        .query_info(
+            "standard::icon,standard::content-type",
+            gio::FileQueryInfoFlags::NONE,
+            gio::Cancellable::NONE,
+        )
+        .map_err(generate_error)
+}
+
+fn themed_icon(icon: &gio::Icon, size: u32, dest: &Path) -> Result<()> {
+    let theme = IconTheme::default().ok_or_else(|| Error::Generate("no icon theme".into()))?;
+    let info = theme
+        .lookup_by_gicon(icon, size as i32, IconLookupFlags::FORCE_SIZE)
+        .ok_or_else(|| Error::Generate("the icon isn't in the theme".into()))?;
+    info.load_icon()
+        .and_then(|pixbuf| pixbuf.savev(dest, "png", &[]))
+        .map_err(generate_error)
+}
+
+pub(crate) fn icon<R: Runtime>(
+    app: &AppHandle<R>,
+    path: &Path,
+    size: u32,
+    dest: &Path,
+) -> Result<()> {
+    let icon = query(path)?
+        .icon()
+        .ok_or_else(|| Error::Generate("the file has no icon".into()))?;
+    // the icon theme belongs to the main thread, only the serialized icon can be sent to it
+    let icon = icon
+        .serialize()
+        .ok_or_else(|| Error::Generate("the icon can't be serialized".into()))?;
+    let dest = dest.to_path_buf();
+
+    let (tx, rx) = channel();
+    app.run_on_main_thread(move || {
+        let result = gio::Icon::deserialize(&icon)
+            .ok_or_else(|| Error::Generate("the icon can't be deserialized".into()))
+            .and_then(|icon| themed_icon(&icon, size, &dest));
+        let _ = tx.send(result);
+    })?;
+    rx.recv()
+        .map_err(|_| Error::Generate("the event loop is closed".into()))?
+}
+
+struct Thumbnailer {
+    exec: String,
+    mime_types: Vec<String>,
+}
+
+/// The `.thumbnailer` files of the user and system data directories, whose program is installed.
+fn thumbnailers() -> Vec<Thumbnailer> {
+    let mut thumbnailers = Vec::new();
+    let dirs = std::iter::once(glib::user_data_dir()).chain(glib::system_data_dirs());
+    for dir in dirs {
+        let entries = match std::fs::read_dir(dir.join("thumbnailers")) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new("thumbnailer")) {
+                continue;
+            }
+            let file = glib::KeyFile::new();
+            if file
+                .load_from_file(&path, glib::KeyFileFlags::NONE)
+                .is_err()
+            {
+                continue;
+            }
+            if let Ok(program) = file.string(THUMBNAILER_GROUP, "TryExec") {
+                if glib::find_program_in_path(program.as_str()).is_none() {
+                    continue;
+                }
+            }
+            if let (Ok(exec), Ok(mime_types)) = (
+                file.string(THUMBNAILER_GROUP, "Exec"),
+                file.string_list(THUMBNAILER_GROUP, "MimeType"),
+            ) {
+                thumbnailers.push(Thumbnailer {
+                    exec: exec.into(),
+                    mime_types: mime_types.into_iter().map(Into::into).collect(),
+                });
+            }
+        }
+    }
+    thumbnailers
+}
+
+/// Expands the `%i`, `%u`, `%o` and `%s` fields of the command line.
+fn expand(argument: &str, path: &Path, uri: &str, size: u32, dest: &Path) -> OsString {
+    let mut expanded = OsString::new();
+    let mut chars = argument.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c.to_string());
+            continue;
+        }
+        match chars.next() {
+            Some('i') => expanded.push(path),
+            Some('u') => expanded.push(uri),
+            Some('o') => expanded.push(dest),
+            Some('s') => expanded.push(size.to_string()),
+            Some('%') => expanded.push("%"),
+            _ => (),
+        }
+    }
+    expanded
+}
+
+fn run(thumbnailer: &Thumbnailer, path: &Path, size: u32, dest: &Path) -> Result<()> {
+    let uri = glib::filename_to_uri(path, None).map_err(generate_error)?;
+    let arguments = glib::shell_parse_argv(&thumbnailer.exec).map_err(generate_error)?;
+    let mut arguments = arguments
+        .iter()
+        .map(|argument| expand(&argument.to_string_lossy(), path, &uri, size, dest));
+    let program = arguments
+        .next()
+        .ok_or_else(|| Error::Generate("the thumbnailer has no command".into()))?;
+
+    let output = Command::new(program).args(arguments).output()?;
+    if !output.status.success() || !dest.is_file() {
+        return Err(Error::Generate(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn thumbnail(path: &Path, size: u32, dest: &Path) -> Result<()> {
+    let content_type = query(path)?
+        .content_type()
+        .map(|content_type| content_type.to_string())
+        .unwrap_or_default();
+
+    if content_type.starts_with("image/") {
+        let scaled = Pixbuf::from_file_at_scale(path, size as i32, size as i32, true)
+            .and_then(|pixbuf| pixbuf.savev(dest, "png", &[]));
+        if scaled.is_ok() {
+            return Ok(());
+        }
+    }
+
+    let thumbnailer = thumbnailers().into_iter().find(|thumbnailer| {
+        thumbnailer
+            .mime_types
+            .iter()
+            .any(|mime_type| gio::content_type_is_a(&content_type, mime_type))
+    });
+    match thumbnailer {
+        Some(thumbnailer) => run(&thumbnailer, path, size, dest),
+        None => Err(Error::Unsupported(content_type)),
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The icons come from `NSWorkspace`, the thumbnails from Quick Look and its generators.
+
+use objc::{
+    class, msg_send,
+    runtime::{Object, BOOL, NO},
+    sel, sel_impl,
+};
+use tauri::{AppHandle, Runtime};
+
+use std::{ffi::c_void, path::Path};
+
+use crate::{Error, Result};
+
+type Id = *mut Object;
+type CGImageRef = *mut c_void;
+
+const NS_UTF8_STRING_ENCODING: usize = 4;
+const NS_BITMAP_IMAGE_FILE_TYPE_PNG: usize = 4;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CGSize {
+    width: f64,
+    height: f64,
+}
+
+#[repr(C)]
+struct NSRect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+#[link(name = "QuickLook", kind = "framework")]
+extern "C" {
+    fn QLThumbnailImageCreate(
+        allocator: *const c_void,
+        url: Id,
+        max_size: CGSize,
+        options: Id,
+    ) -> CGImageRef;
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGImageRelease(image: CGImageRef);
+}
+
+unsafe fn ns_string(s: &str) -> Id {
+    let string: Id = msg_send![class!(NSString), alloc];
+    let string: Id = msg_send![string,
+        initWithBytes: s.as_ptr() as *const c_void
+        length: s.len()
+        encoding: NS_UTF8_STRING_ENCODING];
+    msg_send![string, autorelease]
+}
+
+/// Encodes the image as PNG, `NSBitmapImageRep` only releases its image once it's released itself.
+unsafe fn write_png(image: CGImageRef, dest: &Path) -> Result<()> {
+    let rep: Id = msg_send![class!(NSBitmapImageRep), alloc];
+    let rep: Id = msg_send![rep, initWithCGImage: image];
+    if rep.is_null() {
+        return Err(Error::Generate("invalid image".into()));
+    }
+    let properties: Id = msg_send![class!(NSDictionary), dictionary];
+    let data: Id = msg_send![rep,
+        representationUsingType: NS_BITMAP_IMAGE_FILE_TYPE_PNG
+        properties: properties];
+    let written: BOOL = if data.is_null() {
+        NO
+    } else {
+        msg_send![data,
+            writeToFile: ns_string(&dest.to_string_lossy())
+            atomically: NO]
+    };
+    let _: () = msg_send![rep, release];
+    if written == NO {
+        return Err(Error::Generate("failed to encode the image".into()));
+    }
+    Ok(())
+}
+
+/// Runs in an autorelease pool, the commands run on threads without one.
+fn autoreleased<T>(f: impl FnOnce() -> T) -> T {
+    unsafe {
+        let pool: Id = msg_send![class!(NSAutoreleasePool), new];
+        let result = f();
+        let _: () = msg_send![pool, drain];
+        result
+    }
+}
+
+pub(crate) fn icon<R: Runtime>(
+    _app: &AppHandle<R>,
+    path: &Path,
+    size: u32,
+    dest: &Path,
+) -> Result<()> {
+    autoreleased(|| unsafe {
+        let workspace: Id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let icon: Id = msg_send![workspace, iconForFile: ns_string(&path.to_string_lossy())];
+        if icon.is_null() {
+            return Err(Error::Generate("the file has no icon".into()));
+        }
+        // the representation closest to the size, scaled to it
+        let mut rect = NSRect {
+            x: 0.0,
+            y: 0.0,
+            width: size as f64,
+            height: size as f64,
+        };
+        let null: Id = std::ptr::null_mut();
+        let image: CGImageRef = msg_send![icon,
+            CGImageForProposedRect: &mut rect as *mut NSRect
+            context: null
+            hints: null];
+        if image.is_null() {
+            return Err(Error::Generate("failed to draw the icon".into()));
+        }
+        write_png(image, dest)
+    })
+}
+
+pub(crate) fn thumbnail(path: &Path, size: u32, dest: &Path) -> Result<()> {
+    autoreleased(|| unsafe {
+        let url: Id = msg_send![class!(NSURL), fileURLWithPath: ns_string(&path.to_string_lossy())];
+        let max_size = CGSize {
+            width: size as f64,
+            height: size as f64,
+        };
+        let image = QLThumbnailImageCreate(std::ptr::null(), url, max_size, std::ptr::null_mut());
+        if image.is_null() {
+            return Err(Error::Unsupported(path.display().to_string()));
+        }
+        let written = write_png(image, dest);
+        CGImageRelease(image);
+        written
+    })
+}
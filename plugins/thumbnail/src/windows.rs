@@ -0,0 +1,162 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Both come from the image factory of the shell, which uses the thumbnail handlers of the installed apps.
+
+use tauri::{AppHandle, Runtime};
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::{HWND, SIZE},
+        Graphics::Gdi::{
+            DeleteObject, GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO,
+            BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, HBITMAP,
+        },
+        System::Com::{CoInitializeEx, COINIT_APARTMENTTHREADED},
+        UI::Shell::{
+            IShellItemImageFactory, SHCreateItemFromParsingName, SIIGBF, SIIGBF_ICONONLY,
+            SIIGBF_THUMBNAILONLY,
+        },
+    },
+};
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    os::windows::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Result};
+
+fn generate_error(error: windows::core::Error) -> Error {
+    Error::Generate(error.message().to_string_lossy())
+}
+
+/// The shell doesn't parse the verbatim paths returned by `canonicalize`.
+fn shell_path(path: &Path) -> PathBuf {
+    let path_str = path.to_string_lossy();
+    if let Some(unc) = path_str.strip_prefix(r"\\?\UNC\") {
+        PathBuf::from(format!(r"\\{}", unc))
+    } else if let Some(path) = path_str.strip_prefix(r"\\?\") {
+        PathBuf::from(path)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// The pixels of the bitmap as top-down RGBA, with the alpha of the shell's premultiplied BGRA undone.
+unsafe fn pixels(bitmap: HBITMAP) -> Result<(u32, u32, Vec<u8>)> {
+    let mut info = BITMAP::default();
+    if GetObjectW(
+        bitmap,
+        std::mem::size_of::<BITMAP>() as i32,
+        &mut info as *mut BITMAP as *mut _,
+    ) == 0
+    {
+        return Err(Error::Generate("invalid bitmap".into()));
+    }
+    let (width, height) = (info.bmWidth.unsigned_abs(), info.bmHeight.unsigned_abs());
+
+    let mut header = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            // negative for top-down rows
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB as u32,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut data = vec![0u8; width as usize * height as usize * 4];
+    let dc = GetDC(HWND(0));
+    let lines = GetDIBits(
+        dc,
+        bitmap,
+        0,
+        height,
+        data.as_mut_ptr() as *mut _,
+        &mut header,
+        DIB_RGB_COLORS,
+    );
+    ReleaseDC(HWND(0), dc);
+    if lines == 0 {
+        return Err(Error::Generate("failed to read the bitmap".into()));
+    }
+
+    // the thumbnails of photos usually have no alpha channel at all
+    let opaque = data.chunks_exact(4).all(|pixel| pixel[3] == 0);
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+        if opaque {
+            pixel[3] = 255;
+        } else if pixel[3] != 0 && pixel[3] != 255 {
+            let alpha = pixel[3] as u32;
+            for channel in &mut pixel[..3] {
+                *channel = (*channel as u32 * 255 / alpha).min(255) as u8;
+            }
+        }
+    }
+    Ok((width, height, data))
+}
+
+fn write_png(dest: &Path, width: u32, height: u32, data: &[u8]) -> Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(dest)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(data))
+        .map_err(|e| Error::Generate(e.to_string()))
+}
+
+fn image(path: &Path, size: u32, flags: SIIGBF, dest: &Path) -> Result<()> {
+    let wide: Vec<u16> = shell_path(path)
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+    unsafe {
+        // the commands run on a pool of threads, initializing once per thread is enough
+        let _ = CoInitializeEx(std::ptr::null(), COINIT_APARTMENTTHREADED);
+        let factory: IShellItemImageFactory =
+            SHCreateItemFromParsingName(PCWSTR(wide.as_ptr()), None).map_err(generate_error)?;
+        let bitmap = factory
+            .GetImage(
+                SIZE {
+                    cx: size as i32,
+                    cy: size as i32,
+                },
+                flags,
+            )
+            .map_err(|e| {
+                // no handler for the type of the file
+                if flags == SIIGBF_THUMBNAILONLY {
+                    Error::Unsupported(path.display().to_string())
+                } else {
+                    generate_error(e)
+                }
+            })?;
+        let pixels = pixels(bitmap);
+        DeleteObject(bitmap);
+        let (width, height, data) = pixels?;
+        write_png(dest, width, height, &data)
+    }
+}
+
+pub(crate) fn icon<R: Runtime>(
+    _app: &AppHandle<R>,
+    path: &Path,
+    size: u32,
+    dest: &Path,
+) -> Result<()> {
+    image(path, size, SIIGBF_ICONONLY, dest)
+}
+
+pub(crate) fn thumbnail(path: &Path, size: u32, dest: &Path) -> Result<()> {
+    image(path, size, SIIGBF_THUMBNAILONLY, dest)
+}
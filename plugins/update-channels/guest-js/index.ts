@@ -0,0 +1,83 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface Update {
+  channel: string;
+  version: string;
+  currentVersion: string;
+  notes?: string;
+  pubDate?: string;
+  /** Whether a delta patch replaces the download of the whole bundle. */
+  delta: boolean;
+}
+
+export type Stage = "downloading" | "verifying" | "installing";
+
+export interface Progress {
+  stage: Stage;
+  /** Whether the delta patch is used, a failed patch starts over with the whole bundle. */
+  delta: boolean;
+  downloaded: number;
+  /** Unknown when the server doesn't send the length. */
+  total?: number;
+}
+
+export interface Installed {
+  version: string;
+  /** Whether an installer runs and the app exits, on Windows. */
+  exiting: boolean;
+}
+
+export async function channels(): Promise<string[]> {
+  return await invoke("plugin:update-channels|channels");
+}
+
+/** The channel picked with `setChannel`, or the first one. */
+export async function channel(): Promise<string> {
+  return await invoke("plugin:update-channels|channel");
+}
+
+/** Switches to the channel, remembered across the launches. */
+export async function setChannel(channel: string): Promise<void> {
+  return await invoke("plugin:update-channels|set_channel", { channel });
+}
+
+/** Looks for a newer release on the channel, offered to this installation by its rollout. */
+export async function check(): Promise<Update | null> {
+  return await invoke("plugin:update-channels|check");
+}
+
+/**
+ * Downloads, verifies and installs the update found by the last check.
+ * The new version runs after `restart`.
+ */
+export async function install(
+  onProgress?: (progress: Progress) => void
+): Promise<Update> {
+  const unlisten =
+    onProgress === undefined
+      ? undefined
+      : await listen<Progress>("update-channels://progress", (event) =>
+          onProgress(event.payload)
+        );
+  try {
+    return await invoke("plugin:update-channels|install");
+  } finally {
+    unlisten?.();
+  }
+}
+
+export async function restart(): Promise<void> {
+  return await invoke("plugin:update-channels|restart");
+}
+
+/** Called in every window once an update is installed, e.g. to offer a restart. */
+export async function onInstalled(
+  handler: (installed: Installed) => void
+): Promise<UnlistenFn> {
+  return await listen<Installed>("update-channels://installed", (event) =>
+    handler(event.payload)
+  );
+}
@@ -0,0 +1,182 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Replaces the installed app with the same bundles as the Tauri updater:
+//! an `.AppImage.tar.gz` on Linux, an `.app.tar.gz` on macOS and an `.msi.zip` on Windows.
+
+use std::{
+    fs::{remove_file, rename, write},
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Result};
+
+/// What happens once the new version is in place.
+pub(crate) enum Outcome {
+    /// The new version runs after a restart.
+    Restart,
+    /// An installer runs, the app has to exit.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Exit,
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+/// The file the delta patches apply to.
+pub(crate) fn patch_target() -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    {
+        // the executable is in the mounted image
+        std::env::var_os("APPIMAGE").map(PathBuf::from)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        std::env::current_exe().ok()
+    }
+}
+
+/// Writes the file next to the target and moves it in place, the running app keeps the old one open.
+#[cfg(unix)]
+pub(crate) fn replace(target: &Path, data: &[u8]) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let update = sibling(target, "update");
+    write(&update, data)?;
+    std::fs::set_permissions(&update, std::fs::Permissions::from_mode(0o755))?;
+    if let Err(e) = rename(&update, target) {
+        let _ = remove_file(&update);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// A running executable can't be overwritten but can be renamed, the old one is removed at the next launch.
+#[cfg(windows)]
+pub(crate) fn replace(target: &Path, data: &[u8]) -> Result<()> {
+    let old = sibling(target, "old");
+    let _ = remove_file(&old);
+    rename(target, &old)?;
+    if let Err(e) = write(target, data) {
+        let _ = rename(&old, target);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Removes what the last update left behind.
+pub(crate) fn clean_up() {
+    #[cfg(windows)]
+    if let Some(target) = patch_target() {
+        let _ = remove_file(sibling(&target, "old"));
+    }
+}
+
+#[cfg(not(windows))]
+fn entries(archive: &[u8]) -> tar::Archive<flate2::read::GzDecoder<&[u8]>> {
+    tar::Archive::new(flate2::read::GzDecoder::new(archive))
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn install(archive: &[u8]) -> Result<Outcome> {
+    use std::io::Read;
+
+    let target =
+        patch_target().ok_or_else(|| Error::Install("only the AppImage can be updated".into()))?;
+    for entry in entries(archive).entries()? {
+        let mut entry = entry?;
+        if entry.path()?.extension().is_some_and(|e| e == "AppImage") {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            replace(&target, &data)?;
+            return Ok(Outcome::Restart);
+        }
+    }
+    Err(Error::Install("the archive has no AppImage".into()))
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn install(archive: &[u8]) -> Result<Outcome> {
+    use std::fs::{create_dir_all, read_dir, remove_dir_all};
+
+    // Name.app/Contents/MacOS/name
+    let bundle = std::env::current_exe()?
+        .ancestors()
+        .nth(3)
+        .filter(|bundle| bundle.extension().is_some_and(|e| e == "app"))
+        .map(Path::to_path_buf)
+        .ok_or_else(|| Error::Install("the app doesn't run from a bundle".into()))?;
+
+    let update = sibling(&bundle, "update");
+    let _ = remove_dir_all(&update);
+    create_dir_all(&update)?;
+    let result = (|| {
+        entries(archive).unpack(&update)?;
+        let extracted = read_dir(&update)?
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|path| path.extension().is_some_and(|e| e == "app"))
+            .ok_or_else(|| Error::Install("the archive has no app bundle".into()))?;
+
+        let old = sibling(&bundle, "old");
+        let _ = remove_dir_all(&old);
+        rename(&bundle, &old)?;
+        if let Err(e) = rename(&extracted, &bundle) {
+            let _ = rename(&old, &bundle);
+            return Err(e.into());
+        }
+        let _ = remove_dir_all(&old);
+        Ok(Outcome::Restart)
+    })();
+    let _ = remove_dir_all(&update);
+    result
+}
+
+#[cfg(windows)]
+pub(crate) fn install(archive: &[u8]) -> Result<Outcome> {
+    use std::{fs::File, io, os::windows::process::CommandExt, process::Command};
+
+    let mut archive = zip::ZipArchive::new(io::Cursor::new(archive))
+        .map_err(|e| Error::Install(e.to_string()))?;
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| Error::Install(e.to_string()))?;
+        let Some(name) = entry
+            .enclosed_name()
+            .and_then(|name| name.file_name().map(PathBuf::from))
+        else {
+            continue;
+        };
+        let extension = name
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase);
+        if !matches!(extension.as_deref(), Some("msi") | Some("exe")) {
+            continue;
+        }
+
+        let installer = std::env::temp_dir().join(name);
+        io::copy(&mut entry, &mut File::create(&installer)?)?;
+        let mut command = if extension.as_deref() == Some("msi") {
+            let mut command = Command::new("msiexec.exe");
+            command
+                .arg("/i")
+                .arg(&installer)
+                .args(["/passive", "/promptrestart"]);
+            command
+        } else {
+            let mut command = Command::new(&installer);
+            command.arg("/P");
+            command
+        };
+        command.creation_flags(0x08000000).spawn()?;
+        return Ok(Outcome::Exit);
+    }
+    Err(Error::Install("the archive has no installer".into()))
+}
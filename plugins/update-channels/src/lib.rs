@@ -0,0 +1,589 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Updates from release channels switchable at runtime, with staged rollouts and binary delta patches,
+//! signed with the key of the Tauri updater.
+
+use minisign_verify::{PublicKey, Signature};
+use semver::Version;
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    api::dialog::blocking::ask,
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, State,
+};
+use tauri_plugin_common::{error, PluginError};
+
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, read, read_to_string, write},
+    io,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+mod install;
+
+use install::Outcome;
+
+type Result<T> = std::result::Result<T, Error>;
+
+const SETTINGS_FILE: &str = "update-channels.json";
+const PROGRESS_EVENT: &str = "update-channels://progress";
+const INSTALLED_EVENT: &str = "update-channels://installed";
+/// The progress is emitted about every 256 KiB.
+const PROGRESS_STEP: u64 = 256 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error(transparent)]
+    Version(#[from] semver::Error),
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+    #[error("invalid signature: {0}")]
+    Signature(#[from] minisign_verify::Error),
+    #[error("the updater `pubkey` is missing from the configuration")]
+    NoPublicKey,
+    #[error("unknown channel: {0}")]
+    UnknownChannel(String),
+    #[error("the release has no bundle for {0}")]
+    UnsupportedPlatform(String),
+    #[error("no update was found by the last check")]
+    NoUpdate,
+    #[error("could not install the update: {0}")]
+    Install(String),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "update-channels";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Json(..) => "json",
+            Self::Request(..) => "request",
+            Self::Version(..) => "version",
+            Self::Base64(..) => "base64",
+            Self::Signature(..) => "signature",
+            Self::NoPublicKey => "no_public_key",
+            Self::UnknownChannel(..) => "unknown_channel",
+            Self::UnsupportedPlatform(..) => "unsupported_platform",
+            Self::NoUpdate => "no_update",
+            Self::Install(..) => "install",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            Self::Request(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
+    }
+}
+
+fn full_rollout() -> f64 {
+    100.0
+}
+
+/// The JSON served for a channel, the static format of the Tauri updater with the `rollout` and the `patches`.
+#[derive(Debug, Clone, Deserialize)]
+struct Release {
+    version: String,
+    notes: Option<String>,
+    pub_date: Option<String>,
+    /// The percentage of the installations the release is offered to.
+    #[serde(default = "full_rollout")]
+    rollout: f64,
+    platforms: HashMap<String, Platform>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Platform {
+    url: String,
+    signature: String,
+    #[serde(default)]
+    patches: Vec<Patch>,
+}
+
+/// A `bsdiff` patch from a version to the release.
+#[derive(Debug, Clone, Deserialize)]
+struct Patch {
+    from: String,
+    url: String,
+    /// The signature of the patched file, not of the patch.
+    signature: String,
+}
+
+/// What the app remembers between the launches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Settings {
+    channel: Option<String>,
+    /// Where the installation falls in the staged rollouts, from 0 to 100.
+    bucket: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Update {
+    pub channel: String,
+    pub version: String,
+    pub current_version: String,
+    pub notes: Option<String>,
+    pub pub_date: Option<String>,
+    /// Whether a delta patch replaces the download of the whole bundle.
+    pub delta: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Stage {
+    Downloading,
+    Verifying,
+    Installing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub stage: Stage,
+    /// Whether the delta patch is used, a failed patch starts over with the whole bundle.
+    pub delta: bool,
+    pub downloaded: u64,
+    /// Unknown when the server doesn't send the length.
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InstalledPayload {
+    version: String,
+    /// Whether the app restarts by itself, when an installer runs.
+    exiting: bool,
+}
+
+/// `linux`, `darwin` or `windows`, like the `{{target}}` of the Tauri updater.
+fn target() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    }
+}
+
+fn decode(base64: &str) -> Result<String> {
+    Ok(String::from_utf8_lossy(&base64::decode(base64)?).into_owned())
+}
+
+pub struct UpdateChannels<R: Runtime> {
+    app: AppHandle<R>,
+    channels: Vec<(String, String)>,
+    settings_path: Option<PathBuf>,
+    settings: Mutex<Settings>,
+    client: reqwest::Client,
+    /// Found by the last check, for this channel.
+    available: Mutex<Option<(Update, Platform)>>,
+    restart_prompt: bool,
+}
+
+impl<R: Runtime> UpdateChannels<R> {
+    pub fn channels(&self) -> Vec<&str> {
+        self.channels
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// The channel picked with `set_channel`, or the first one.
+    pub fn channel(&self) -> &str {
+        let settings = self.settings.lock().unwrap();
+        self.channels
+            .iter()
+            .find(|(name, _)| Some(name) == settings.channel.as_ref())
+            .or_else(|| self.channels.first())
+            .map(|(name, _)| name.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Switches to the channel, remembered across the launches.
+    ///
+    /// Only newer versions are installed, leaving a beta waits for a stable release at least as recent.
+    pub fn set_channel(&self, channel: &str) -> Result<()> {
+        if !self.channels.iter().any(|(name, _)| name == channel) {
+            return Err(Error::UnknownChannel(channel.into()));
+        }
+        let mut settings = self.settings.lock().unwrap();
+        settings.channel.replace(channel.into());
+        self.available.lock().unwrap().take();
+        if let Some(path) = &self.settings_path {
+            if let Some(directory) = path.parent() {
+                create_dir_all(directory)?;
+            }
+            write(path, serde_json::to_vec(&*settings)?)?;
+        }
+        Ok(())
+    }
+
+    fn current_version(&self) -> &Version {
+        &self.app.package_info().version
+    }
+
+    fn url(&self, channel: &str) -> Result<String> {
+        let (_, url) = self
+            .channels
+            .iter()
+            .find(|(name, _)| name == channel)
+            .ok_or_else(|| Error::UnknownChannel(channel.into()))?;
+        Ok(url
+            .replace("{{channel}}", channel)
+            .replace("{{target}}", target())
+            .replace("{{arch}}", std::env::consts::ARCH)
+            .replace("{{current_version}}", &self.current_version().to_string()))
+    }
+
+    /// Looks for a newer release on the channel, offered to this installation by its rollout.
+    pub async fn check(&self) -> Result<Option<Update>> {
+        let channel = self.channel().to_string();
+        let release: Release = self
+            .client
+            .get(self.url(&channel)?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let version = Version::parse(release.version.trim_start_matches('v'))?;
+        let bucket = self.settings.lock().unwrap().bucket;
+        if &version <= self.current_version() || bucket >= release.rollout {
+            self.available.lock().unwrap().take();
+            return Ok(None);
+        }
+
+        let key = format!("{}-{}", target(), std::env::consts::ARCH);
+        let platform = release
+            .platforms
+            .get(&key)
+            .cloned()
+            .ok_or(Error::UnsupportedPlatform(key))?;
+        let current_version = self.current_version().to_string();
+        let update = Update {
+            channel,
+            version: version.to_string(),
+            delta: install::patch_target().is_some()
+                && platform
+                    .patches
+                    .iter()
+                    .any(|patch| patch.from.trim_start_matches('v') == current_version),
+            current_version,
+            notes: release.notes,
+            pub_date: release.pub_date,
+        };
+        self.available
+            .lock()
+            .unwrap()
+            .replace((update.clone(), platform));
+        Ok(Some(update))
+    }
+
+    fn verify(&self, data: &[u8], signature: &str) -> Result<()> {
+        let pubkey = &self.app.config().tauri.updater.pubkey;
+        if pubkey.is_empty() {
+            return Err(Error::NoPublicKey);
+        }
+        let pubkey = PublicKey::decode(&decode(pubkey)?)?;
+        let signature = Signature::decode(&decode(signature)?)?;
+        pubkey.verify(data, &signature, true)?;
+        Ok(())
+    }
+
+    async fn download<F: Fn(Progress)>(
+        &self,
+        url: &str,
+        delta: bool,
+        on_progress: &F,
+    ) -> Result<Vec<u8>> {
+        let mut response = self.client.get(url).send().await?.error_for_status()?;
+        let total = response.content_length();
+        let mut data = Vec::with_capacity(total.unwrap_or_default() as usize);
+        let mut reported = 0;
+        while let Some(chunk) = response.chunk().await? {
+            data.extend_from_slice(&chunk);
+            let downloaded = data.len() as u64;
+            if downloaded - reported >= PROGRESS_STEP || Some(downloaded) == total {
+                reported = downloaded;
+                on_progress(Progress {
+                    stage: Stage::Downloading,
+                    delta,
+                    downloaded,
+                    total,
+                });
+            }
+        }
+        Ok(data)
+    }
+
+    async fn install_patch<F: Fn(Progress)>(
+        &self,
+        patch: &Patch,
+        on_progress: &F,
+    ) -> Result<Outcome> {
+        let target =
+            install::patch_target().ok_or_else(|| Error::Install("nothing to patch".into()))?;
+        let data = self.download(&patch.url, true, on_progress).await?;
+        let size = data.len() as u64;
+        let progress = |stage| {
+            on_progress(Progress {
+                stage,
+                delta: true,
+                downloaded: size,
+                total: Some(size),
+            })
+        };
+
+        progress(Stage::Verifying);
+        let old = read(&target)?;
+        let mut new = Vec::with_capacity(old.len());
+        bsdiff::patch(&old, &mut data.as_slice(), &mut new)?;
+        self.verify(&new, &patch.signature)?;
+
+        progress(Stage::Installing);
+        install::replace(&target, &new)?;
+        Ok(Outcome::Restart)
+    }
+
+    async fn install_bundle<F: Fn(Progress)>(
+        &self,
+        platform: &Platform,
+        on_progress: &F,
+    ) -> Result<Outcome> {
+        let data = self.download(&platform.url, false, on_progress).await?;
+        let size = data.len() as u64;
+        let progress = |stage| {
+            on_progress(Progress {
+                stage,
+                delta: false,
+                downloaded: size,
+                total: Some(size),
+            })
+        };
+
+        progress(Stage::Verifying);
+        self.verify(&data, &platform.signature)?;
+
+        progress(Stage::Installing);
+        install::install(&data)
+    }
+
+    /// Downloads, verifies and installs the update found by the last check.
+    ///
+    /// The delta patch is tried first when there is one, then the whole bundle.
+    /// The new version runs after a restart, except on Windows where the installer closes the app.
+    pub async fn install<F: Fn(Progress)>(&self, on_progress: F) -> Result<Update> {
+        let (update, platform) = self
+            .available
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or(Error::NoUpdate)?;
+
+        let patch = platform
+            .patches
+            .iter()
+            .find(|patch| patch.from.trim_start_matches('v') == update.current_version);
+        let mut outcome = None;
+        if let (true, Some(patch)) = (update.delta, patch) {
+            match self.install_patch(patch, &on_progress).await {
+                Ok(o) => outcome = Some(o),
+                Err(e) => log::warn!(
+                    "the delta patch failed, downloading the whole bundle: {}",
+                    e
+                ),
+            }
+        }
+        let outcome = match outcome {
+            Some(outcome) => outcome,
+            None => self.install_bundle(&platform, &on_progress).await?,
+        };
+        self.available.lock().unwrap().take();
+
+        let exiting = matches!(outcome, Outcome::Exit);
+        let _ = self.app.emit_all(
+            INSTALLED_EVENT,
+            InstalledPayload {
+                version: update.version.clone(),
+                exiting,
+            },
+        );
+        if exiting {
+            self.app.exit(0);
+        } else if self.restart_prompt {
+            let app = self.app.clone();
+            let version = update.version.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let name = app.package_info().name.clone();
+                if ask(
+                    None::<&tauri::Window<R>>,
+                    "Update installed",
+                    format!("{} {} is installed. Restart now?", name, version),
+                ) {
+                    app.restart();
+                }
+            });
+        }
+        Ok(update)
+    }
+}
+
+pub trait ManagerExt<R: Runtime> {
+    fn update_channels(&self) -> State<'_, UpdateChannels<R>>;
+}
+
+impl<R: Runtime, T: Manager<R>> ManagerExt<R> for T {
+    fn update_channels(&self) -> State<'_, UpdateChannels<R>> {
+        self.state::<UpdateChannels<R>>()
+    }
+}
+
+#[command]
+fn channels<R: Runtime>(app: AppHandle<R>) -> Vec<String> {
+    app.update_channels()
+        .channels()
+        .into_iter()
+        .map(Into::into)
+        .collect()
+}
+
+#[command]
+fn channel<R: Runtime>(app: AppHandle<R>) -> String {
+    app.update_channels().channel().into()
+}
+
+#[command]
+fn set_channel<R: Runtime>(app: AppHandle<R>, channel: String) -> Result<()> {
+    app.update_channels().set_channel(&channel)
+}
+
+#[command]
+async fn check<R: Runtime>(app: AppHandle<R>) -> Result<Option<Update>> {
+    app.update_channels().check().await
+}
+
+#[command]
+async fn install<R: Runtime>(app: AppHandle<R>) -> Result<Update> {
+    let app_ = app.clone();
+    app.update_channels()
+        .install(move |progress| {
+            let _ = app_.emit_all(PROGRESS_EVENT, progress);
+        })
+        .await
+}
+
+#[command]
+fn restart<R: Runtime>(app: AppHandle<R>) {
+    app.restart();
+}
+
+#[derive(Default)]
+pub struct Builder {
+    channels: Vec<(String, String)>,
+    restart_prompt: bool,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a channel, the first one is used until another is picked.
+    ///
+    /// The URL can contain `{{channel}}`, `{{target}}`, `{{arch}}` and `{{current_version}}`.
+    pub fn channel<N: Into<String>, U: Into<String>>(mut self, name: N, url: U) -> Self {
+        self.channels.push((name.into(), url.into()));
+        self
+    }
+
+    /// Asks the user to restart once an update is installed.
+    pub fn restart_prompt(mut self, restart_prompt: bool) -> Self {
+        self.restart_prompt = restart_prompt;
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let Self {
+            channels: channel_urls,
+            restart_prompt,
+        } = self;
+
+        PluginBuilder::new("update-channels")
+            .invoke_handler(tauri::generate_handler![
+                channels,
+                channel,
+                set_channel,
+                check,
+                install,
+                restart
+            ])
+            .setup(move |app| {
+                install::clean_up();
+
+                let settings_path = app
+                    .path_resolver()
+                    .app_data_dir()
+                    .map(|dir| dir.join(SETTINGS_FILE));
+                let settings = match settings_path.as_ref().map(read_to_string) {
+                    Some(Ok(settings)) => serde_json::from_str(&settings)?,
+                    Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {
+                        let settings = Settings {
+                            channel: None,
+                            bucket: rand::random::<f64>() * 100.0,
+                        };
+                        // the bucket must stay the same for the rollouts to grow
+                        if let Some(path) = &settings_path {
+                            if let Some(directory) = path.parent() {
+                                create_dir_all(directory)?;
+                            }
+                            write(path, serde_json::to_vec(&settings)?)?;
+                        }
+                        settings
+                    }
+                };
+
+                app.manage(UpdateChannels {
+                    app: app.clone(),
+                    channels: channel_urls,
+                    settings_path,
+                    settings: Mutex::new(settings),
+                    client: reqwest::Client::new(),
+                    available: Default::default(),
+                    restart_prompt,
+                });
+                Ok(())
+            })
+            .build()
+    }
+}
@@ -1,11 +1,10 @@
 import { invoke } from "@tauri-apps/api/tauri";
 import { appWindow } from "@tauri-apps/api/window";
 
-interface ProgressPayload {
-  id: number;
-  progress: number;
-  total: number;
-}
+import type { ErrorPayload } from "./types/ErrorPayload";
+import type { ProgressPayload } from "./types/ProgressPayload";
+
+export type { ErrorPayload };
 
 type ProgressHandler = (progress: number, total: number) => void;
 const handlers: Map<number, ProgressHandler> = new Map();
@@ -3,8 +3,16 @@
 // SPDX-License-Identifier: MIT
 
 use futures::TryStreamExt;
-use serde::{ser::Serializer, Serialize};
-use tauri::{command, plugin::Plugin, Invoke, Runtime, Window};
+use serde::{ser::Serializer, Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri::{command, plugin::Plugin, AppHandle, Invoke, Runtime, Window};
+use tauri_plugin_common::{
+    config, error,
+    executor::{Priority, TaskError},
+    network::{ScopeConfig, ScopeError},
+    ExecutorExt, NetworkScopeExt, PluginConfig, PluginError,
+};
+use tauri_plugin_consent::Permission;
 use tokio::fs::File;
 use tokio_util::codec::{BytesCodec, FramedRead};
 
@@ -14,12 +22,22 @@ use std::{collections::HashMap, sync::Mutex};
 
 type Result<T> = std::result::Result<T, Error>;
 
+const UPLOAD: Permission = Permission::new("upload:upload", "Upload", "upload files");
+/// The redirects reqwest follows by default.
+const MAX_REDIRECTS: usize = 10;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
     Request(#[from] reqwest::Error),
+    #[error("the user denied the permission to upload files")]
+    PermissionDenied,
+    #[error(transparent)]
+    Scope(#[from] ScopeError),
+    #[error(transparent)]
+    Task(#[from] TaskError),
 }
 
 impl Serialize for Error {
@@ -27,14 +45,46 @@ impl Serialize for Error {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "upload";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Io(..) => "io",
+            Self::Request(..) => "request",
+            Self::PermissionDenied => "permission_denied",
+            Self::Scope(..) => "scope",
+            Self::Task(..) => "task",
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        match self {
+            Self::Io(e) => error::io_retryable(e),
+            Self::Request(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::Io(e) => error::io_detail(e),
+            _ => None,
+        }
     }
 }
 
 #[derive(Clone, Serialize)]
+#[cfg_attr(test, derive(ts_rs::TS), ts(export, export_to = "guest-js/types/"))]
 struct ProgressPayload {
     id: u32,
+    #[cfg_attr(test, ts(type = "number"))]
     progress: u64,
+    #[cfg_attr(test, ts(type = "number"))]
     total: u64,
 }
 
@@ -46,11 +96,30 @@ async fn upload<R: Runtime>(
     file_path: &str,
     headers: HashMap<String, String>,
 ) -> Result<serde_json::Value> {
+    let scope = window.network_scope("upload");
+    scope.check(url)?;
+    if !tauri_plugin_consent::check_async(window.clone(), UPLOAD).await {
+        return Err(Error::PermissionDenied);
+    }
+
     // Read the file
     let file = File::open(file_path).await?;
 
     // Create the request and attach the file to the body
-    let client = reqwest::Client::new();
+    // the redirects must stay in the scope too
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if scope.is_allowed(attempt.url()) {
+                attempt.follow()
+            } else {
+                let url = attempt.url().to_string();
+                attempt.error(ScopeError::NotAllowed(url))
+            }
+        }))
+        .build()?;
+    let executor = window.executor().inner().clone();
     let mut request = client.post(url).body(file_to_body(id, window, file));
 
     // Loop trought the headers keys and values
@@ -59,9 +128,14 @@ async fn upload<R: Runtime>(
         request = request.header(&key, value);
     }
 
-    let response = request.send().await?;
-
-    response.json().await.map_err(Into::into)
+    // the transfers share the threads of the executor with the other plugins' heavy work
+    executor
+        .spawn_async(Priority::Normal, move |_| async move {
+            let response = request.send().await?;
+            response.json().await.map_err(Into::into)
+        })
+        .join()
+        .await?
 }
 
 fn file_to_body<R: Runtime>(id: u32, window: Window<R>, file: File) -> reqwest::Body {
@@ -82,6 +156,17 @@ fn file_to_body<R: Runtime>(id: u32, window: Window<R>, file: File) -> reqwest::
     ))
 }
 
+/// The `plugins > upload` section of `tauri.conf.json`.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Config {
+    /// The servers the files may be uploaded to.
+    #[serde(default)]
+    scope: ScopeConfig,
+}
+
+impl PluginConfig for Config {}
+
 /// Tauri plugin.
 pub struct Upload<R: Runtime> {
     invoke_handler: Box<dyn Fn(Invoke<R>) + Send + Sync>,
@@ -100,6 +185,12 @@ impl<R: Runtime> Plugin<R> for Upload<R> {
         "upload"
     }
 
+    fn initialize(&mut self, app: &AppHandle<R>, config: JsonValue) -> tauri::plugin::Result<()> {
+        let config: Config = config::parse("upload", config)?;
+        app.network_scope("upload").set_config(config.scope);
+        Ok(())
+    }
+
     fn extend_api(&mut self, message: Invoke<R>) {
         (self.invoke_handler)(message)
     }
@@ -1,5 +1,7 @@
 import { invoke, transformCallback } from "@tauri-apps/api/tauri";
 
+export type { ErrorPayload } from "./types/ErrorPayload";
+
 export interface MessageKind<T, D> {
   type: T;
   data: D;
@@ -4,7 +4,13 @@ use serde_json::Value as JsonValue;
 use tauri::{
     api::ipc::{format_callback, CallbackFn},
     plugin::Plugin,
-    AppHandle, Invoke, Manager, Runtime, State, Window,
+    AppHandle, Invoke, Manager, RunEvent, Runtime, State, Window,
+};
+use tauri_plugin_common::{
+    config, error,
+    network::{ScopeConfig, ScopeError},
+    shutdown::{self, Stage},
+    DiagnosticsExt, NetworkScopeExt, PluginConfig, PluginError, Report, ShutdownExt,
 };
 use tokio::{net::TcpStream, sync::Mutex};
 use tokio_tungstenite::{
@@ -29,6 +35,8 @@ enum Error {
     Websocket(#[from] tokio_tungstenite::tungstenite::Error),
     #[error("connection not found for the given id: {0}")]
     ConnectionNotFound(Id),
+    #[error(transparent)]
+    Scope(#[from] ScopeError),
 }
 
 impl Serialize for Error {
@@ -36,10 +44,33 @@ impl Serialize for Error {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.to_string().as_str())
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "websocket";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Websocket(..) => "websocket",
+            Self::ConnectionNotFound(..) => "connection_not_found",
+            Self::Scope(..) => "scope",
+        }
     }
 }
 
+/// The `plugins > websocket` section of `tauri.conf.json`.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Config {
+    /// The servers the webview may connect to.
+    #[serde(default)]
+    scope: ScopeConfig,
+}
+
+impl PluginConfig for Config {}
+
 #[derive(Default)]
 struct ConnectionManager(Mutex<HashMap<Id, WebSocketWriter>>);
 
@@ -86,6 +117,7 @@ fn connect<R: Runtime>(
     callback_function: CallbackFn,
     config: Option<ConnectionConfig>,
 ) -> Result<Id> {
+    window.network_scope("websocket").check(&url)?;
     let id = rand::random();
     let (ws_stream, _) =
         tauri::async_runtime::block_on(connect_async_with_config(url, config.map(Into::into)))?;
@@ -178,12 +210,42 @@ impl<R: Runtime> Plugin<R> for TauriWebsocket<R> {
         "websocket"
     }
 
-    fn initialize(&mut self, app: &AppHandle<R>, _config: JsonValue) -> tauri::plugin::Result<()> {
+    fn initialize(&mut self, app: &AppHandle<R>, config: JsonValue) -> tauri::plugin::Result<()> {
+        let config: Config = config::parse("websocket", config)?;
+        app.network_scope("websocket").set_config(config.scope);
         app.manage(ConnectionManager::default());
+
+        let app_ = app.clone();
+        app.shutdown()
+            .register("websocket", Stage::Disconnect, move || {
+                tauri::async_runtime::block_on(async {
+                    let manager = app_.state::<ConnectionManager>();
+                    let mut connections = manager.0.lock().await;
+                    // starts the close handshakes, the servers close the connections
+                    for (_, mut write) in connections.drain() {
+                        let _ = write.send(Message::Close(None)).await;
+                    }
+                });
+            });
+
+        let app_ = app.clone();
+        app.diagnostics().register("websocket", move || {
+            let manager = app_.state::<ConnectionManager>();
+            // the connections are only counted when they aren't busy
+            let connections = manager.0.try_lock().map(|connections| connections.len());
+            match connections {
+                Ok(connections) => Report::default().metric("connections", connections as f64),
+                Err(_) => Report::default(),
+            }
+        });
         Ok(())
     }
 
     fn extend_api(&mut self, invoke: Invoke<R>) {
         (self.invoke_handler)(invoke)
     }
+
+    fn on_event(&mut self, app: &AppHandle<R>, event: &RunEvent) {
+        shutdown::on_event(app, "websocket", event);
+    }
 }
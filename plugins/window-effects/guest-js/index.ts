@@ -0,0 +1,89 @@
+import { invoke } from "@tauri-apps/api/tauri";
+
+export type { ErrorPayload } from "./types/ErrorPayload";
+
+export interface Color {
+  r: number;
+  g: number;
+  b: number;
+  a: number;
+}
+
+/** The semantic materials of the macOS vibrancy. */
+export type Material =
+  | "titlebar"
+  | "selection"
+  | "menu"
+  | "popover"
+  | "sidebar"
+  | "headerView"
+  | "sheet"
+  | "windowBackground"
+  | "hudWindow"
+  | "fullScreenUi"
+  | "tooltip"
+  | "contentBackground"
+  | "underWindowBackground"
+  | "underPageBackground";
+
+export type Effect =
+  /** Windows 7, 10 and 11, the color tints the blur. */
+  | { type: "blur"; color?: Color }
+  /** Windows 10 version 1809 and later, the color is ignored on Windows 11 22H2 and later. */
+  | { type: "acrylic"; color?: Color }
+  /** Windows 11. */
+  | { type: "mica" }
+  /** macOS. */
+  | {
+      type: "vibrancy";
+      /** Defaults to `windowBackground`. */
+      material?: Material;
+      /** Defaults to `followsWindowActiveState`. */
+      state?: "followsWindowActiveState" | "active" | "inactive";
+      /** The corner radius of the effect, in points. */
+      radius?: number;
+    };
+
+export type Corners = "default" | "square" | "round" | "roundSmall";
+
+/** What the operating system supports, detected from its version. */
+export interface Capabilities {
+  blur: boolean;
+  acrylic: boolean;
+  mica: boolean;
+  vibrancy: boolean;
+  /** The shadow of windows without decorations. */
+  shadow: boolean;
+  corners: boolean;
+}
+
+async function capabilities(): Promise<Capabilities> {
+  return await invoke("plugin:window-effects|capabilities");
+}
+
+/**
+ * Applies the effect to the background of the window, replacing the previous one, or clears it with `null`.
+ *
+ * The effect shows through the transparent parts of the page, the window must be created with `transparent: true`.
+ *
+ * @param label The window to apply the effect to, defaults to the current one.
+ */
+async function setEffect(effect: Effect | null, label?: string): Promise<void> {
+  return await invoke("plugin:window-effects|set_effect", { effect, label });
+}
+
+/**
+ * Enables or disables the shadow of a window without decorations, which also rounds its corners on Windows 11.
+ */
+async function setShadow(enable: boolean, label?: string): Promise<void> {
+  return await invoke("plugin:window-effects|set_shadow", { enable, label });
+}
+
+/**
+ * Sets the shape of the corners on Windows 11 and of the windows without decorations on macOS.
+ */
+async function setCorners(corners: Corners, label?: string): Promise<void> {
+  return await invoke("plugin:window-effects|set_corners", { corners, label });
+}
+
+export { capabilities, setEffect, setShadow, setCorners };
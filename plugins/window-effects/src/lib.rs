@@ -0,0 +1,272 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Native translucent backgrounds, shadows and rounded corners for the windows.
+//!
+//! The effects show through the transparent parts of the page, the window must be created with `transparent: true`.
+
+use serde::{ser::Serializer, Deserialize, Serialize};
+use tauri::{
+    command,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    AppHandle, Manager, Runtime, Window,
+};
+use tauri_plugin_common::{error, PluginError};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error("{0} is not supported on this version of the operating system")]
+    Unsupported(&'static str),
+    #[error("failed to apply the effect: {0}")]
+    Effect(String),
+    #[error("window not found: {0}")]
+    WindowNotFound(String),
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        error::serialize(self, serializer)
+    }
+}
+
+impl PluginError for Error {
+    const PLUGIN: &'static str = "window-effects";
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Tauri(..) => "tauri",
+            Self::Unsupported(..) => "unsupported",
+            Self::Effect(..) => "effect",
+            Self::WindowNotFound(..) => "window_not_found",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+/// The semantic materials of the macOS vibrancy, older versions fall back to a material appropriate for the appearance.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Material {
+    Titlebar,
+    Selection,
+    Menu,
+    Popover,
+    Sidebar,
+    HeaderView,
+    Sheet,
+    #[default]
+    WindowBackground,
+    HudWindow,
+    FullScreenUi,
+    Tooltip,
+    ContentBackground,
+    UnderWindowBackground,
+    UnderPageBackground,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VibrancyState {
+    /// Vibrant while the window is focused.
+    #[default]
+    FollowsWindowActiveState,
+    Active,
+    Inactive,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Effect {
+    /// Windows 7, 10 and 11, the color tints the blur.
+    Blur { color: Option<Color> },
+    /// Windows 10 version 1809 and later, the color is ignored on Windows 11 22H2 and later.
+    ///
+    /// Resizing and moving the window lag on Windows 10 version 1903 and later, and on Windows 11 before 22H2.
+    Acrylic { color: Option<Color> },
+    /// Windows 11.
+    Mica,
+    /// macOS.
+    Vibrancy {
+        #[serde(default)]
+        material: Material,
+        #[serde(default)]
+        state: VibrancyState,
+        /// The corner radius of the effect, in points.
+        radius: Option<f64>,
+    },
+}
+
+impl Effect {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Blur { .. } => "blur",
+            Self::Acrylic { .. } => "acrylic",
+            Self::Mica => "mica",
+            Self::Vibrancy { .. } => "vibrancy",
+        }
+    }
+}
+
+/// The shape of the corners of the window.
+///
+/// On macOS, the corners are rounded with a radius of 10 and 5 points for windows without decorations,
+/// the other windows always have rounded corners.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Corners {
+    /// The corners chosen by the system.
+    #[default]
+    Default,
+    Square,
+    Round,
+    RoundSmall,
+}
+
+/// What the operating system supports, detected from its version.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub blur: bool,
+    pub acrylic: bool,
+    pub mica: bool,
+    pub vibrancy: bool,
+    /// The shadow of windows without decorations.
+    pub shadow: bool,
+    pub corners: bool,
+}
+
+impl Capabilities {
+    pub fn detect() -> Self {
+        platform::capabilities()
+    }
+}
+
+/// A [`Window`] extension that applies the native effects.
+pub trait WindowExt {
+    /// Applies the effect to the background of the window, replacing the previous one, or clears it with `None`.
+    fn set_effect(&self, effect: Option<&Effect>) -> Result<()>;
+
+    /// Enables or disables the shadow of a window without decorations, which also rounds its corners on Windows 11.
+    fn set_shadow(&self, enable: bool) -> Result<()>;
+
+    fn set_corners(&self, corners: Corners) -> Result<()>;
+}
+
+impl<R: Runtime> WindowExt for Window<R> {
+    fn set_effect(&self, effect: Option<&Effect>) -> Result<()> {
+        let capabilities = Capabilities::detect();
+        if let Some(effect) = effect {
+            let supported = match effect {
+                Effect::Blur { .. } => capabilities.blur,
+                Effect::Acrylic { .. } => capabilities.acrylic,
+                Effect::Mica => capabilities.mica,
+                Effect::Vibrancy { .. } => capabilities.vibrancy,
+            };
+            if !supported {
+                return Err(Error::Unsupported(effect.name()));
+            }
+        }
+        platform::set_effect(self, effect)
+    }
+
+    fn set_shadow(&self, enable: bool) -> Result<()> {
+        if !Capabilities::detect().shadow {
+            return Err(Error::Unsupported("the window shadow"));
+        }
+        platform::set_shadow(self, enable)
+    }
+
+    fn set_corners(&self, corners: Corners) -> Result<()> {
+        if !Capabilities::detect().corners {
+            return Err(Error::Unsupported("the window corners"));
+        }
+        platform::set_corners(self, corners)
+    }
+}
+
+/// The window with the given label, or the window calling the command.
+fn target<R: Runtime>(
+    app: &AppHandle<R>,
+    window: Window<R>,
+    label: Option<String>,
+) -> Result<Window<R>> {
+    match label {
+        Some(label) => app.get_window(&label).ok_or(Error::WindowNotFound(label)),
+        None => Ok(window),
+    }
+}
+
+#[command]
+async fn capabilities() -> Result<Capabilities> {
+    Ok(Capabilities::detect())
+}
+
+#[command]
+async fn set_effect<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    label: Option<String>,
+    effect: Option<Effect>,
+) -> Result<()> {
+    target(&app, window, label)?.set_effect(effect.as_ref())
+}
+
+#[command]
+async fn set_shadow<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    label: Option<String>,
+    enable: bool,
+) -> Result<()> {
+    target(&app, window, label)?.set_shadow(enable)
+}
+
+#[command]
+async fn set_corners<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    label: Option<String>,
+    corners: Corners,
+) -> Result<()> {
+    target(&app, window, label)?.set_corners(corners)
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    PluginBuilder::new("window-effects")
+        .invoke_handler(tauri::generate_handler![
+            capabilities,
+            set_effect,
+            set_shadow,
+            set_corners
+        ])
+        .build()
+}
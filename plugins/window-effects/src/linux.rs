@@ -0,0 +1,29 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The blur, shadows and corners are drawn by the compositor, the apps can't change them.
+
+use tauri::{Runtime, Window};
+
+use crate::{Capabilities, Corners, Effect, Error, Result};
+
+pub(crate) fn capabilities() -> Capabilities {
+    Capabilities::default()
+}
+
+pub(crate) fn set_effect<R: Runtime>(_window: &Window<R>, effect: Option<&Effect>) -> Result<()> {
+    // clearing is a no-op
+    match effect {
+        Some(effect) => Err(Error::Unsupported(effect.name())),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn set_shadow<R: Runtime>(_window: &Window<R>, _enable: bool) -> Result<()> {
+    Err(Error::Unsupported("the window shadow"))
+}
+
+pub(crate) fn set_corners<R: Runtime>(_window: &Window<R>, _corners: Corners) -> Result<()> {
+    Err(Error::Unsupported("the window corners"))
+}
@@ -0,0 +1,133 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use objc::{
+    class, msg_send,
+    runtime::{Object, BOOL, NO, YES},
+    sel, sel_impl,
+};
+use tauri::{Runtime, Window};
+use window_vibrancy::{NSVisualEffectMaterial, NSVisualEffectState};
+
+use std::sync::mpsc::channel;
+
+use crate::{Capabilities, Corners, Effect, Error, Material, Result, VibrancyState};
+
+type Id = *mut Object;
+
+/// The radius of the rounded corners of the system windows.
+const ROUND_RADIUS: f64 = 10.0;
+const ROUND_SMALL_RADIUS: f64 = 5.0;
+
+/// NSVisualEffectView is available since macOS 10.10, older versions can't run the webview.
+pub(crate) fn capabilities() -> Capabilities {
+    Capabilities {
+        vibrancy: true,
+        shadow: true,
+        corners: true,
+        ..Default::default()
+    }
+}
+
+/// AppKit must be called from the main thread, the commands run on the async runtime.
+fn on_main_thread<R: Runtime, F: FnOnce(&Window<R>) -> Result<()> + Send + 'static>(
+    window: &Window<R>,
+    f: F,
+) -> Result<()> {
+    let is_main_thread: BOOL = unsafe { msg_send![class!(NSThread), isMainThread] };
+    if is_main_thread != NO {
+        return f(window);
+    }
+
+    let (tx, rx) = channel();
+    let window_ = window.clone();
+    window.run_on_main_thread(move || {
+        let _ = tx.send(f(&window_));
+    })?;
+    rx.recv()
+        .map_err(|_| Error::Effect("the event loop is closed".into()))?
+}
+
+fn material(material: Material) -> NSVisualEffectMaterial {
+    match material {
+        Material::Titlebar => NSVisualEffectMaterial::Titlebar,
+        Material::Selection => NSVisualEffectMaterial::Selection,
+        Material::Menu => NSVisualEffectMaterial::Menu,
+        Material::Popover => NSVisualEffectMaterial::Popover,
+        Material::Sidebar => NSVisualEffectMaterial::Sidebar,
+        Material::HeaderView => NSVisualEffectMaterial::HeaderView,
+        Material::Sheet => NSVisualEffectMaterial::Sheet,
+        Material::WindowBackground => NSVisualEffectMaterial::WindowBackground,
+        Material::HudWindow => NSVisualEffectMaterial::HudWindow,
+        Material::FullScreenUi => NSVisualEffectMaterial::FullScreenUI,
+        Material::Tooltip => NSVisualEffectMaterial::Tooltip,
+        Material::ContentBackground => NSVisualEffectMaterial::ContentBackground,
+        Material::UnderWindowBackground => NSVisualEffectMaterial::UnderWindowBackground,
+        Material::UnderPageBackground => NSVisualEffectMaterial::UnderPageBackground,
+    }
+}
+
+fn state(state: VibrancyState) -> NSVisualEffectState {
+    match state {
+        VibrancyState::FollowsWindowActiveState => NSVisualEffectState::FollowsWindowActiveState,
+        VibrancyState::Active => NSVisualEffectState::Active,
+        VibrancyState::Inactive => NSVisualEffectState::Inactive,
+    }
+}
+
+/// `window-vibrancy` adds an effect view below the webview and can't remove it.
+unsafe fn remove_vibrancy(ns_window: Id) {
+    let content_view: Id = msg_send![ns_window, contentView];
+    let subviews: Id = msg_send![content_view, subviews];
+    let count: usize = msg_send![subviews, count];
+    for i in (0..count).rev() {
+        let view: Id = msg_send![subviews, objectAtIndex: i];
+        let is_effect: BOOL = msg_send![view, isKindOfClass: class!(NSVisualEffectView)];
+        if is_effect != NO {
+            let _: () = msg_send![view, removeFromSuperview];
+        }
+    }
+}
+
+pub(crate) fn set_effect<R: Runtime>(window: &Window<R>, effect: Option<&Effect>) -> Result<()> {
+    let effect = effect.cloned();
+    on_main_thread(window, move |window| {
+        unsafe { remove_vibrancy(window.ns_window()? as Id) };
+        match effect {
+            Some(Effect::Vibrancy {
+                material: m,
+                state: s,
+                radius,
+            }) => window_vibrancy::apply_vibrancy(window, material(m), Some(state(s)), radius)
+                .map_err(|e| Error::Effect(e.to_string())),
+            Some(effect) => Err(Error::Unsupported(effect.name())),
+            None => Ok(()),
+        }
+    })
+}
+
+pub(crate) fn set_shadow<R: Runtime>(window: &Window<R>, enable: bool) -> Result<()> {
+    on_main_thread(window, move |window| {
+        window_shadows::set_shadow(window, enable).map_err(|e| Error::Effect(e.to_string()))
+    })
+}
+
+pub(crate) fn set_corners<R: Runtime>(window: &Window<R>, corners: Corners) -> Result<()> {
+    let radius = match corners {
+        Corners::Default | Corners::Square => 0.0,
+        Corners::Round => ROUND_RADIUS,
+        Corners::RoundSmall => ROUND_SMALL_RADIUS,
+    };
+    on_main_thread(window, move |window| unsafe {
+        let ns_window = window.ns_window()? as Id;
+        let content_view: Id = msg_send![ns_window, contentView];
+        let _: () = msg_send![content_view, setWantsLayer: YES];
+        let layer: Id = msg_send![content_view, layer];
+        let _: () = msg_send![layer, setCornerRadius: radius];
+        let _: () = msg_send![layer, setMasksToBounds: if radius > 0.0 { YES } else { NO }];
+        // the shadow follows the shape of the content
+        let _: () = msg_send![ns_window, invalidateShadow];
+        Ok(())
+    })
+}
@@ -0,0 +1,121 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use tauri::{Runtime, Window};
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::ERROR_SUCCESS,
+        Graphics::Dwm::{
+            DwmSetWindowAttribute, DWMWA_WINDOW_CORNER_PREFERENCE, DWMWCP_DEFAULT,
+            DWMWCP_DONOTROUND, DWMWCP_ROUND, DWMWCP_ROUNDSMALL, DWM_WINDOW_CORNER_PREFERENCE,
+        },
+        System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ},
+    },
+};
+
+use std::{ffi::c_void, iter::once, mem::size_of};
+
+use crate::{Capabilities, Color, Corners, Effect, Error, Result};
+
+/// Windows 8 is build 9200.
+const LAST_WINDOWS_7_BUILD: u32 = 7601;
+/// Windows 10 version 1809, the first with the accent policies.
+const ACCENT_BUILD: u32 = 17763;
+const WINDOWS_11_BUILD: u32 = 22000;
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(once(0)).collect()
+}
+
+/// `RtlGetVersion` would need the ntdll bindings, the registry has the same number.
+fn build() -> u32 {
+    let subkey = wide(r"SOFTWARE\Microsoft\Windows NT\CurrentVersion");
+    let value = wide("CurrentBuildNumber");
+    let mut buffer = [0u16; 16];
+    let mut size = (buffer.len() * size_of::<u16>()) as u32;
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(subkey.as_ptr()),
+            PCWSTR(value.as_ptr()),
+            RRF_RT_REG_SZ,
+            std::ptr::null_mut(),
+            buffer.as_mut_ptr() as *mut c_void,
+            &mut size,
+        )
+    };
+    if result != ERROR_SUCCESS {
+        return 0;
+    }
+    let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+    String::from_utf16_lossy(&buffer[..len])
+        .parse()
+        .unwrap_or_default()
+}
+
+pub(crate) fn capabilities() -> Capabilities {
+    let build = build();
+    Capabilities {
+        blur: build <= LAST_WINDOWS_7_BUILD || build >= ACCENT_BUILD,
+        acrylic: build >= ACCENT_BUILD,
+        mica: build >= WINDOWS_11_BUILD,
+        vibrancy: false,
+        shadow: true,
+        corners: build >= WINDOWS_11_BUILD,
+    }
+}
+
+fn color(color: Option<Color>) -> Option<window_vibrancy::Color> {
+    color.map(|Color { r, g, b, a }| (r, g, b, a))
+}
+
+fn effect_error(error: window_vibrancy::Error) -> Error {
+    Error::Effect(error.to_string())
+}
+
+pub(crate) fn set_effect<R: Runtime>(window: &Window<R>, effect: Option<&Effect>) -> Result<()> {
+    // the effects are exclusive, the clear functions fail when they are not supported
+    let capabilities = capabilities();
+    if capabilities.blur {
+        window_vibrancy::clear_blur(window).map_err(effect_error)?;
+    }
+    if capabilities.acrylic {
+        window_vibrancy::clear_acrylic(window).map_err(effect_error)?;
+    }
+    if capabilities.mica {
+        window_vibrancy::clear_mica(window).map_err(effect_error)?;
+    }
+
+    match effect {
+        Some(Effect::Blur { color: c }) => window_vibrancy::apply_blur(window, color(*c)),
+        Some(Effect::Acrylic { color: c }) => window_vibrancy::apply_acrylic(window, color(*c)),
+        Some(Effect::Mica) => window_vibrancy::apply_mica(window),
+        Some(effect @ Effect::Vibrancy { .. }) => return Err(Error::Unsupported(effect.name())),
+        None => Ok(()),
+    }
+    .map_err(effect_error)
+}
+
+pub(crate) fn set_shadow<R: Runtime>(window: &Window<R>, enable: bool) -> Result<()> {
+    window_shadows::set_shadow(window, enable).map_err(|e| Error::Effect(e.to_string()))
+}
+
+pub(crate) fn set_corners<R: Runtime>(window: &Window<R>, corners: Corners) -> Result<()> {
+    let preference: DWM_WINDOW_CORNER_PREFERENCE = match corners {
+        Corners::Default => DWMWCP_DEFAULT,
+        Corners::Square => DWMWCP_DONOTROUND,
+        Corners::Round => DWMWCP_ROUND,
+        Corners::RoundSmall => DWMWCP_ROUNDSMALL,
+    };
+    unsafe {
+        DwmSetWindowAttribute(
+            window.hwnd()?,
+            DWMWA_WINDOW_CORNER_PREFERENCE,
+            &preference as *const DWM_WINDOW_CORNER_PREFERENCE as *const c_void,
+            size_of::<DWM_WINDOW_CORNER_PREFERENCE>() as u32,
+        )
+    }
+    .map_err(|e| Error::Effect(e.to_string()))
+}
@@ -5,7 +5,11 @@
 use serde::{Deserialize, Serialize};
 use tauri::{
     plugin::{Builder as PluginBuilder, TauriPlugin},
-    LogicalSize, Manager, PhysicalPosition, RunEvent, Runtime, Window, WindowEvent,
+    LogicalSize, Manager, PhysicalPosition, Runtime, Window, WindowEvent,
+};
+use tauri_plugin_common::{
+    shutdown::{self, Stage},
+    ShutdownExt, StateDirsExt,
 };
 
 use std::{
@@ -68,7 +72,7 @@ pub trait AppHandleExt {
 
 impl<R: Runtime> AppHandleExt for tauri::AppHandle<R> {
     fn save_window_state(&self) -> Result<()> {
-        if let Some(app_dir) = self.path_resolver().app_config_dir() {
+        if let Some(app_dir) = self.state_dirs().config_dir() {
             let state_path = app_dir.join(STATE_FILENAME);
             let cache = self.state::<WindowStateCache>();
             let state = cache.0.lock().unwrap();
@@ -195,7 +199,7 @@ impl Builder {
         PluginBuilder::new("window-state")
             .setup(|app| {
                 let cache: Arc<Mutex<HashMap<String, WindowMetadata>>> = if let Some(app_dir) =
-                    app.path_resolver().app_config_dir()
+                    app.state_dirs().config_dir()
                 {
                     let state_path = app_dir.join(STATE_FILENAME);
                     if state_path.exists() {
@@ -212,6 +216,12 @@ impl Builder {
                     Default::default()
                 };
                 app.manage(WindowStateCache(cache));
+
+                let app_ = app.clone();
+                app.shutdown()
+                    .register("window-state", Stage::Save, move || {
+                        let _ = app_.save_window_state();
+                    });
                 Ok(())
             })
             .on_webview_ready(move |window| {
@@ -280,11 +290,7 @@ impl Builder {
                     _ => {}
                 });
             })
-            .on_event(|app, event| {
-                if let RunEvent::Exit = event {
-                    let _ = app.save_window_state();
-                }
-            })
+            .on_event(|app, event| shutdown::on_event(app, "window-state", event))
             .build()
     }
 }